@@ -11,6 +11,8 @@ const syncBudgetValue = $("#sync-budget-value");
 const syncParamsInput = $("#sync-params");
 const syncTargetsInput = $("#sync-targets");
 const syncOutput = $("#sync-output");
+const syncProgress = $("#sync-progress");
+const syncProgressLabel = $("#sync-progress-label");
 const hybridQueryInput = $("#hybrid-query");
 const hybridEntitiesInput = $("#hybrid-entities");
 const hybridAlphaInput = $("#hybrid-alpha");
@@ -32,7 +34,7 @@ async function fetchJSON(url, options) {
 
 async function loadStatus() {
     try {
-        const data = await fetchJSON("/api/status");
+        const data = await fetchJSON("/api/v1/status");
         statusOutput.textContent = JSON.stringify(data, null, 2);
     } catch (error) {
         statusOutput.textContent = `加载失败: ${error.message}`;
@@ -58,7 +60,7 @@ function renderFetcher(fetcher) {
 async function loadFetchers() {
     fetcherList.innerHTML = "加载中…";
     try {
-        const fetchers = await fetchJSON("/api/fetchers");
+        const fetchers = await fetchJSON("/api/v1/fetchers");
         if (!fetchers.length) {
             fetcherList.textContent = "尚未注册 fetcher。";
             return;
@@ -100,7 +102,7 @@ async function loadTables() {
     try {
         const prefix = $("#table-prefix").value.trim();
         const query = prefix ? `?prefix=${encodeURIComponent(prefix)}` : "";
-        const tables = await fetchJSON(`/api/tables${query}`);
+        const tables = await fetchJSON(`/api/v1/tables${query}`);
         if (!tables.length) {
             tablesOutput.textContent = "未查询到表。";
             return;
@@ -120,7 +122,7 @@ async function runReadiness() {
         if (!Array.isArray(payload)) {
             throw new Error("输入必须是 JSON 数组");
         }
-        const data = await fetchJSON("/api/readiness", {
+        const data = await fetchJSON("/api/v1/readiness", {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(payload),
@@ -216,10 +218,12 @@ async function runSync() {
                       count: Math.max(1, Math.floor(budgetValue) || 100),
                   };
 
+        // Someone is watching this sync, so it jumps ahead of scheduled refreshes.
         const payload = {
             fetcher,
             params,
             budget,
+            priority: "interactive",
         };
 
         const triggeringQuery = (syncQueryInput?.value || "").trim();
@@ -230,12 +234,19 @@ async function runSync() {
             payload.target_entities = targets;
         }
 
-        const result = await fetchJSON("/api/sync", {
+        const accepted = await fetchJSON("/api/v1/sync", {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(payload),
         });
 
+        await followSyncProgress(accepted.job_id);
+        const result = await waitForSyncJob(accepted.status_url, (job) => {
+            if (syncOutput) {
+                syncOutput.textContent = JSON.stringify(job, null, 2);
+            }
+        });
+
         if (syncOutput) {
             syncOutput.textContent = JSON.stringify(result, null, 2);
         }
@@ -251,12 +262,80 @@ async function runSync() {
     }
 }
 
+const TERMINAL_JOB_STATES = new Set(["succeeded", "failed", "stopped"]);
+const SYNC_PHASE_LABELS = {
+    fetch: "拉取数据",
+    embedding: "生成向量",
+    lake_write: "写入 Delta",
+    engine_ingest: "写入图引擎",
+};
+
+function renderSyncProgress(event) {
+    if (!syncProgress || !syncProgressLabel) {
+        return;
+    }
+    syncProgress.hidden = false;
+    if (event.type === "state") {
+        syncProgressLabel.textContent = `任务 ${event.job_id}: ${event.state}`;
+        if (TERMINAL_JOB_STATES.has(event.state)) {
+            syncProgress.max = 1;
+            syncProgress.value = 1;
+        }
+        return;
+    }
+    const label = SYNC_PHASE_LABELS[event.phase] || event.phase;
+    if (event.total) {
+        syncProgress.max = event.total;
+        syncProgress.value = event.completed;
+        syncProgressLabel.textContent = `${label} ${event.completed}/${event.total}`;
+    } else {
+        // Unknown totals (fetch, embedding) show an indeterminate bar.
+        syncProgress.removeAttribute("value");
+        syncProgressLabel.textContent = `${label} ${event.completed || ""}`.trim();
+    }
+}
+
+// Resolves once the job finishes or the socket drops; polling covers the rest.
+function followSyncProgress(jobId) {
+    return new Promise((resolve) => {
+        const scheme = window.location.protocol === "https:" ? "wss" : "ws";
+        let socket;
+        try {
+            socket = new WebSocket(`${scheme}://${window.location.host}/api/v1/sync/ws?job_id=${jobId}`);
+        } catch (error) {
+            console.warn("无法建立同步进度连接:", error);
+            resolve();
+            return;
+        }
+        socket.onmessage = (message) => {
+            try {
+                renderSyncProgress(JSON.parse(message.data));
+            } catch (error) {
+                console.warn("无法解析同步进度:", error);
+            }
+        };
+        socket.onclose = () => resolve();
+        socket.onerror = () => resolve();
+    });
+}
+
+async function waitForSyncJob(statusUrl, onUpdate) {
+    for (;;) {
+        const job = await fetchJSON(statusUrl);
+        onUpdate(job);
+        if (TERMINAL_JOB_STATES.has(job.state)) {
+            return job;
+        }
+        await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+}
+
 async function loadHybridDefaults() {
     if (!hybridEntitiesInput) {
         return;
     }
     try {
-        const types = await fetchJSON("/api/search/hybrid/types");
+        const types = await fetchJSON("/api/v1/search/hybrid/types");
         if (types.length && !hybridEntitiesInput.value) {
             hybridEntitiesInput.value = types.join(", ");
         }
@@ -296,7 +375,7 @@ async function runHybridSearch() {
 
     hybridOutput.textContent = "查询中…";
     try {
-        const response = await fetchJSON(`/api/search/hybrid_all?${params.toString()}`);
+        const response = await fetchJSON(`/api/v1/search/hybrid_all?${params.toString()}`);
         hybridOutput.textContent = JSON.stringify(response, null, 2);
     } catch (error) {
         hybridOutput.textContent = `查询失败: ${error.message}`;
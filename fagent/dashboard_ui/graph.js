@@ -9,6 +9,7 @@ const graphDepthInput = $("#graph-depth");
 const graphNodeLimitInput = $("#graph-node-limit");
 const graphEdgeLimitInput = $("#graph-edge-limit");
 const graphEdgeTypesInput = $("#graph-edge-types");
+const graphEdgeTypeOptions = $("#graph-edge-type-options");
 const loadButton = $("#graph-load");
 const typeFilterSelect = $("#graph-type-filter");
 const pathFromInput = $("#graph-path-from");
@@ -265,7 +266,7 @@ async function runShortestPath() {
         if (edgeLabel) {
             params.set("edge_label", edgeLabel);
         }
-        const result = await fetchJSON(`/api/graph/shortest_path?${params.toString()}`);
+        const result = await fetchJSON(`/api/v1/graph/shortest_path?${params.toString()}`);
         if (!result || !result.found) {
             pathResultBox.textContent = "未找到满足条件的路径。";
             return;
@@ -280,7 +281,7 @@ async function runShortestPath() {
 async function loadTypeStyles() {
     if (typeStylesLoaded) return;
     try {
-        const data = await fetchJSON("/api/graph/types");
+        const data = await fetchJSON("/api/v1/graph/types");
         if (Array.isArray(data)) {
             data.forEach((entry) => registerTypeStyle(entry));
         }
@@ -304,6 +305,27 @@ async function loadTypeStyles() {
     }
 }
 
+async function loadEdgeTypeOptions() {
+    if (!graphEdgeTypeOptions) return;
+    try {
+        const data = await fetchJSON("/api/v1/graph/edge_types");
+        if (!Array.isArray(data)) return;
+        graphEdgeTypeOptions.innerHTML = "";
+        data.forEach((entry) => {
+            const option = document.createElement("option");
+            option.value = entry.label;
+            const from = (entry.from_types || []).join("/");
+            const to = (entry.to_types || []).join("/");
+            if (from || to) {
+                option.label = `${entry.label} (${from || "?"} → ${to || "?"})`;
+            }
+            graphEdgeTypeOptions.appendChild(option);
+        });
+    } catch (error) {
+        console.warn("Failed to load edge types:", error);
+    }
+}
+
 function populateTypeFilterOptions() {
     if (!typeFilterSelect || !typeStyles.size) return;
     const current = typeFilterSelect.value;
@@ -501,7 +523,7 @@ async function performSearch(term) {
         if (typeFilter) {
             params.set("entity_type", typeFilter);
         }
-        const data = await fetchJSON(`/api/graph/search?${params.toString()}`);
+        const data = await fetchJSON(`/api/v1/graph/search?${params.toString()}`);
         const candidates = Array.isArray(data.candidates) ? data.candidates : [];
         renderSuggestions(candidates);
         if (!selectedNodeId && candidates.length) {
@@ -548,7 +570,7 @@ async function loadSubgraph({ startId, showError = true } = {}) {
         params.set("edge_limit", Math.min(Math.max(edgeLimit, 10), 1000));
         if (edgeTypes) params.set("edge_types", edgeTypes);
 
-        const graphJson = await fetchJSON(`/api/graph/subgraph?${params.toString()}`);
+        const graphJson = await fetchJSON(`/api/v1/graph/subgraph?${params.toString()}`);
         renderGraph(graphJson);
         selectedNodeId = nodeId;
         const matched =
@@ -625,7 +647,7 @@ async function bootstrap() {
     await loadTypeStyles();
     populateTypeFilterOptions();
     updateLegend();
-    await loadInitialSuggestions();
+    await Promise.all([loadEdgeTypeOptions(), loadInitialSuggestions()]);
 }
 
 document.addEventListener("DOMContentLoaded", bootstrap);
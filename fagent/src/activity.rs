@@ -0,0 +1,136 @@
+//! Daily activity of a project, for sparklines.
+//!
+//! Counts are read from the activity table the storage refreshes after every
+//! sync job, so a series reflects the data as of the last sync.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use fstorage::models::ActivityDay;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{readme::resolve_project_url, ApiError, ApiResult, AppState, ErrorResponse};
+
+pub(crate) const DEFAULT_WINDOW_DAYS: u32 = 90;
+const MAX_WINDOW_DAYS: u32 = 730;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct ActivityQuery {
+    /// Days to cover up to today, as `<n>d` or `<n>w`; `90d` by default.
+    #[serde(default)]
+    window: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ActivityPoint {
+    /// UTC day, `YYYY-MM-DD`.
+    date: String,
+    commits: u64,
+    issues: u64,
+    pull_requests: u64,
+}
+
+impl From<ActivityDay> for ActivityPoint {
+    fn from(day: ActivityDay) -> Self {
+        Self {
+            date: day.date,
+            commits: day.commits,
+            issues: day.issues,
+            pull_requests: day.pull_requests,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ActivityResponse {
+    project_id: String,
+    project_url: String,
+    window_days: u32,
+    /// One point per day, oldest first, ending today.
+    series: Vec<ActivityPoint>,
+    /// Sums over the window.
+    totals: ActivityTotals,
+}
+
+#[derive(Default, Serialize, ToSchema)]
+pub(crate) struct ActivityTotals {
+    commits: u64,
+    issues: u64,
+    pull_requests: u64,
+}
+
+impl ActivityTotals {
+    pub(crate) fn of(days: &[ActivityDay]) -> Self {
+        let mut totals = Self::default();
+        for day in days {
+            totals.commits += day.commits;
+            totals.issues += day.issues;
+            totals.pull_requests += day.pull_requests;
+        }
+        totals
+    }
+}
+
+/// Days in a `90d` / `12w` window.
+pub(crate) fn parse_window(raw: &str) -> ApiResult<u32> {
+    let raw = raw.trim();
+    let invalid = || {
+        ApiError::BadRequest(format!(
+            "invalid window '{raw}', expected e.g. 30d or 12w of at most {MAX_WINDOW_DAYS} days"
+        ))
+    };
+    let (count, days_per_unit) = if let Some(count) = raw.strip_suffix(['d', 'D']) {
+        (count, 1)
+    } else if let Some(count) = raw.strip_suffix(['w', 'W']) {
+        (count, 7)
+    } else {
+        return Err(invalid());
+    };
+    count
+        .parse::<u32>()
+        .ok()
+        .and_then(|count| count.checked_mul(days_per_unit))
+        .filter(|days| (1..=MAX_WINDOW_DAYS).contains(days))
+        .ok_or_else(invalid)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/activity",
+    tag = "graph",
+    params(("id" = String, Path, description = "Project node id"), ActivityQuery),
+    responses(
+        (status = 200, description = "Daily commits, issues and pull requests", body = ActivityResponse),
+        (status = 304, description = "Unchanged since the If-None-Match ETag"),
+        (status = 400, description = "Malformed window", body = ErrorResponse),
+        (status = 404, description = "No such project", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_project_activity(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ActivityQuery>,
+) -> ApiResult<Json<ActivityResponse>> {
+    let window_days = match query.window.as_deref() {
+        Some(window) => parse_window(window)?,
+        None => DEFAULT_WINDOW_DAYS,
+    };
+    let project_url = resolve_project_url(&state, &id).await?;
+    let days = state
+        .storage
+        .project_activity(&project_url, window_days)
+        .await
+        .map_err(ApiError::from_storage)?;
+
+    let totals = ActivityTotals::of(&days);
+    Ok(Json(ActivityResponse {
+        project_id: id,
+        project_url,
+        window_days,
+        series: days.into_iter().map(ActivityPoint::from).collect(),
+        totals,
+    }))
+}
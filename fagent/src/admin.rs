@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{MatchedPath, Query, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use fstorage::{
+    models::{ApiUsageAggregate, CostAggregate, UsageBucket},
+    FStorage,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+const DEFAULT_USAGE_WINDOW_SECS: i64 = 30 * 86_400;
+const ANONYMOUS_KEY: &str = "anonymous";
+/// Counted calls are written once the oldest unwritten one is this old, or
+/// once this many endpoint/key pairs are waiting.
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const USAGE_FLUSH_ENTRIES: usize = 256;
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct UsageQuery {
+    #[serde(default)]
+    bucket: Option<String>,
+    #[serde(default)]
+    since: Option<i64>,
+    #[serde(default)]
+    until: Option<i64>,
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    api_key: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct UsageResponse {
+    #[schema(value_type = String, example = "day")]
+    bucket: UsageBucket,
+    since: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<i64>,
+    total: i64,
+    #[schema(value_type = Vec<Object>)]
+    rows: Vec<ApiUsageAggregate>,
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct CostsQuery {
+    #[serde(default)]
+    bucket: Option<String>,
+    #[serde(default)]
+    since: Option<i64>,
+    #[serde(default)]
+    until: Option<i64>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CostsResponse {
+    #[schema(value_type = String, example = "day")]
+    bucket: UsageBucket,
+    since: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<i64>,
+    total_tokens: i64,
+    total_cost_usd: f64,
+    #[schema(value_type = Vec<Object>)]
+    rows: Vec<CostAggregate>,
+}
+
+/// Extracts the caller's API key from `x-api-key` or a bearer `Authorization` header.
+pub(crate) fn request_api_key(headers: &HeaderMap) -> Option<String> {
+    if let Some(value) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
+        }
+    }
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Raw keys never reach the catalog; usage rows carry a short prefix plus a hash.
+fn usage_key_label(raw: Option<&str>) -> String {
+    let Some(raw) = raw else {
+        return ANONYMOUS_KEY.to_string();
+    };
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in raw.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    let prefix: String = raw.chars().take(4).collect();
+    format!("{prefix}…{hash:08x}")
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// API calls counted in memory and not yet written to the catalog, so that
+/// requests never wait on SQLite.
+#[derive(Default)]
+pub(crate) struct UsageCounter {
+    pending: Mutex<PendingUsage>,
+    /// Held while counts are written, so a flush returns only once the
+    /// counts taken by an earlier one are in the catalog too.
+    writing: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct PendingUsage {
+    /// Calls per day, endpoint and key.
+    counts: HashMap<(i64, String, String), i64>,
+    oldest: Option<Instant>,
+}
+
+impl UsageCounter {
+    /// Counts one call; returns whether the pending counts are due to be
+    /// written.
+    fn count(&self, endpoint: String, key: String) -> bool {
+        let now = unix_now();
+        let day = now - now.rem_euclid(86_400);
+        let mut pending = self.pending.lock().unwrap();
+        *pending.counts.entry((day, endpoint, key)).or_default() += 1;
+        let oldest = *pending.oldest.get_or_insert_with(Instant::now);
+        pending.counts.len() >= USAGE_FLUSH_ENTRIES || oldest.elapsed() >= USAGE_FLUSH_INTERVAL
+    }
+
+    /// Writes the pending counts to the catalog on the blocking pool.
+    pub(crate) async fn flush(&self, storage: &Arc<FStorage>) {
+        let _writing = self.writing.lock().await;
+        let counts: Vec<(i64, String, String, i64)> = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.oldest = None;
+            pending
+                .counts
+                .drain()
+                .map(|((day, endpoint, key), count)| (day, endpoint, key, count))
+                .collect()
+        };
+        if counts.is_empty() {
+            return;
+        }
+        let storage = Arc::clone(storage);
+        match tokio::task::spawn_blocking(move || storage.catalog.add_api_usage(&counts)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("failed to record API usage: {}", err),
+            Err(err) => warn!("failed to record API usage: {}", err),
+        }
+    }
+}
+
+/// Middleware counting API calls per endpoint, key and day.
+pub(crate) async fn track_usage(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let endpoint = format!("{} {}", request.method(), path);
+    let key = usage_key_label(request_api_key(request.headers()).as_deref());
+
+    let response = next.run(request).await;
+
+    if state.usage.count(endpoint, key) {
+        tokio::spawn(async move { state.usage.flush(&state.storage).await });
+    }
+    response
+}
+
+/// Resolves the shared `bucket`/`since`/`until` parameters of the admin reports.
+fn resolve_window(
+    bucket: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> ApiResult<(UsageBucket, i64)> {
+    let bucket = match bucket {
+        Some(raw) => raw.parse::<UsageBucket>().map_err(ApiError::from_storage)?,
+        None => UsageBucket::Day,
+    };
+    let since = since.unwrap_or_else(|| unix_now() - DEFAULT_USAGE_WINDOW_SECS);
+    if let Some(until) = until {
+        if until <= since {
+            return Err(ApiError::BadRequest(
+                "until must be greater than since".to_string(),
+            ));
+        }
+    }
+    Ok((bucket, since))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/usage",
+    tag = "admin",
+    params(UsageQuery),
+    responses(
+        (status = 200, body = UsageResponse),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> ApiResult<Json<UsageResponse>> {
+    let (bucket, since) = resolve_window(query.bucket.as_deref(), query.since, query.until)?;
+
+    state.usage.flush(&state.storage).await;
+    let rows = state
+        .storage
+        .catalog
+        .aggregate_api_usage(
+            bucket,
+            Some(since),
+            query.until,
+            query.endpoint.as_deref(),
+            query.api_key.as_deref(),
+        )
+        .map_err(ApiError::from_storage)?;
+    let total = rows.iter().map(|row| row.count).sum();
+
+    Ok(Json(UsageResponse {
+        bucket,
+        since,
+        until: query.until,
+        total,
+        rows,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/costs",
+    tag = "admin",
+    params(CostsQuery),
+    responses(
+        (status = 200, body = CostsResponse),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_costs(
+    State(state): State<AppState>,
+    Query(query): Query<CostsQuery>,
+) -> ApiResult<Json<CostsResponse>> {
+    let (bucket, since) = resolve_window(query.bucket.as_deref(), query.since, query.until)?;
+
+    let rows = state
+        .storage
+        .catalog
+        .aggregate_costs(bucket, Some(since), query.until, query.scope.as_deref())
+        .map_err(ApiError::from_storage)?;
+    let total_tokens = rows.iter().map(|row| row.tokens).sum();
+    let total_cost_usd = rows.iter().map(|row| row.cost_usd).sum();
+
+    Ok(Json(CostsResponse {
+        bucket,
+        since,
+        until: query.until,
+        total_tokens,
+        total_cost_usd,
+        rows,
+    }))
+}
@@ -0,0 +1,48 @@
+//! `fagent export` and `fagent import`: moving a whole store to another
+//! machine as one archive file.
+
+use anyhow::Context;
+use fstorage::{archive, models::ArchiveManifest, FStorage};
+
+use crate::{ExportArgs, ImportArgs};
+
+pub(crate) async fn run_export(args: ExportArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let manifest = storage.export_archive(&args.out).await?;
+    println!(
+        "exported {} to {}",
+        args.store.base_path()?.display(),
+        args.out.display()
+    );
+    print!("{}", render(&manifest));
+    Ok(())
+}
+
+pub(crate) async fn run_import(args: ImportArgs) -> anyhow::Result<()> {
+    let base_path = args.store.base_path()?.to_path_buf();
+    let (archive_path, target) = (args.archive.clone(), base_path.clone());
+    let manifest =
+        tokio::task::spawn_blocking(move || archive::import(&archive_path, &target)).await??;
+    // Opening the store checks that the restored catalog and engine load.
+    FStorage::new(args.store.storage_config(&base_path, None))
+        .await
+        .context("restored store does not open")?;
+    println!(
+        "imported {} into {}",
+        args.archive.display(),
+        base_path.display()
+    );
+    print!("{}", render(&manifest));
+    Ok(())
+}
+
+fn render(manifest: &ArchiveManifest) -> String {
+    let mut out = format!(
+        "  format {}, fstorage {}, graph version {}\n",
+        manifest.format_version, manifest.fstorage_version, manifest.graph_version
+    );
+    for (table, version) in &manifest.table_versions {
+        out += &format!("  {table} @ v{version}\n");
+    }
+    out
+}
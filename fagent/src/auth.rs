@@ -0,0 +1,148 @@
+//! Role-based access control for the dashboard API.
+//!
+//! API keys are mapped to one of three roles. Viewers may read the graph,
+//! search and job status and keep saved searches and bookmarks; operators may
+//! also start syncs and readiness checks (which fetch on demand), have hybrid
+//! searches queue syncs of stale projects, import
+//! precomputed embeddings, take and mount snapshots, delete data and make any
+//! other change a new route brings; admins
+//! additionally see the usage and cost reports and handle personal-data export
+//! and erasure requests. With no keys configured, every request is allowed.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{admin::request_api_key, ApiError, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!(
+                "unknown role '{other}', expected viewer, operator or admin"
+            )),
+        }
+    }
+}
+
+/// API keys and the role each one grants.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AccessPolicy {
+    keys: HashMap<String, Role>,
+}
+
+impl AccessPolicy {
+    pub(crate) fn grant(&mut self, key: String, role: Role) {
+        self.keys.insert(key, role);
+    }
+
+    fn is_open(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn role_of(&self, key: &str) -> Option<Role> {
+        self.keys.get(key).copied()
+    }
+
+    /// Whether the API key in `headers` grants at least `role`, for what a
+    /// route allows only some of its callers to do. Without keys, anyone may.
+    pub(crate) fn permits(&self, headers: &HeaderMap, role: Role) -> bool {
+        self.is_open()
+            || request_api_key(headers)
+                .and_then(|key| self.role_of(&key))
+                .is_some_and(|granted| granted >= role)
+    }
+}
+
+/// Requests other than reads that a viewer may still make: changes to its own
+/// dashboard state rather than to stored data, and reads whose parameters are
+/// sent as a body. Mounted snapshots only serve read routes.
+const VIEWER_WRITES: &[(&str, &str)] = &[
+    ("POST", "/api/saved_searches"),
+    ("DELETE", "/api/saved_searches/:name"),
+    ("POST", "/api/bookmarks"),
+    ("DELETE", "/api/bookmarks/:node_id"),
+    ("POST", "/api/graph/nodes"),
+    ("POST", "/api/query"),
+    ("POST", "/api/mounts/:name/*rest"),
+];
+
+/// Role needed to call `method` on the route template `path`. Anything but a
+/// read needs an operator unless it is one of [`VIEWER_WRITES`].
+pub(crate) fn required_role(method: &Method, path: &str) -> Role {
+    if path.starts_with("/api/admin/") || path.starts_with("/api/privacy/") {
+        return Role::Admin;
+    }
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+        || VIEWER_WRITES.contains(&(method.as_str(), path))
+    {
+        return Role::Viewer;
+    }
+    Role::Operator
+}
+
+/// Middleware rejecting requests whose API key lacks the role the route needs.
+pub(crate) async fn authorize(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.access.is_open() {
+        return next.run(request).await;
+    }
+
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let required = required_role(request.method(), &path);
+    let Some(key) = request_api_key(request.headers()) else {
+        return ApiError::Unauthorized("an API key is required".to_string()).into_response();
+    };
+    let Some(role) = state.access.role_of(&key) else {
+        return ApiError::Unauthorized("unknown API key".to_string()).into_response();
+    };
+    if role < required {
+        return ApiError::Forbidden(format!(
+            "{} {} requires the {required} role, this key has {role}",
+            request.method(),
+            path
+        ))
+        .into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,167 @@
+//! Nodes pinned to a working set that outlives the dashboard session.
+//!
+//! Bookmarks keep only the node id and a note in the catalog; every read
+//! resolves the ids against the current graph, so a bookmark shows the node's
+//! latest display name and type. Deleting a node removes its bookmark.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use fstorage::models::Bookmark;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{map_node_summary, ApiError, ApiResult, AppState, ErrorResponse, GraphNodeSummary};
+
+const MAX_NOTE_CHARS: usize = 4_096;
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct BookmarkRequest {
+    node_id: String,
+    /// Replaces the note of an existing bookmark.
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct BookmarkDto {
+    node_id: String,
+    /// The bookmarked node as it is now; absent when it is no longer in the
+    /// graph.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<GraphNodeSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// Resolves the bookmarked nodes in one lookup.
+async fn hydrate(state: &AppState, bookmarks: Vec<Bookmark>) -> ApiResult<Vec<BookmarkDto>> {
+    let ids: Vec<String> = bookmarks
+        .iter()
+        .map(|bookmark| bookmark.node_id.clone())
+        .collect();
+    let mut nodes = state
+        .storage
+        .lake
+        .get_nodes_by_ids(&ids)
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(bookmarks
+        .into_iter()
+        .map(|bookmark| BookmarkDto {
+            node: nodes.remove(&bookmark.node_id).and_then(map_node_summary),
+            node_id: bookmark.node_id,
+            note: bookmark.note,
+            created_at: bookmark.created_at,
+            updated_at: bookmark.updated_at,
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks",
+    tag = "graph",
+    request_body = BookmarkRequest,
+    responses(
+        (status = 200, description = "The node was bookmarked or its note replaced", body = BookmarkDto),
+        (status = 400, description = "The note is too long", body = ErrorResponse),
+        (status = 404, description = "No such node", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn put_bookmark(
+    State(state): State<AppState>,
+    Json(body): Json<BookmarkRequest>,
+) -> ApiResult<Json<BookmarkDto>> {
+    let note = body
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note
+        .as_ref()
+        .is_some_and(|note| note.chars().count() > MAX_NOTE_CHARS)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "notes are limited to {MAX_NOTE_CHARS} characters"
+        )));
+    }
+    let node = state
+        .storage
+        .lake
+        .get_node_by_id(&body.node_id, None)
+        .await
+        .map_err(ApiError::from_storage)?;
+    if node.is_none() {
+        return Err(ApiError::NotFound(format!(
+            "node '{}' not found",
+            body.node_id
+        )));
+    }
+
+    let catalog = &state.storage.catalog;
+    catalog
+        .put_bookmark(&Bookmark {
+            node_id: body.node_id.clone(),
+            note,
+            created_at: 0,
+            updated_at: 0,
+        })
+        .map_err(ApiError::from_storage)?;
+    info!(node_id = %body.node_id, "bookmarked node");
+    let stored = catalog
+        .get_bookmark(&body.node_id)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::Internal("bookmark was not stored".to_string()))?;
+    let mut hydrated = hydrate(&state, vec![stored]).await?;
+    Ok(Json(hydrated.remove(0)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bookmarks",
+    tag = "graph",
+    responses((status = 200, description = "Bookmarks, oldest first", body = [BookmarkDto]))
+)]
+pub(crate) async fn list_bookmarks(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<BookmarkDto>>> {
+    let bookmarks = state
+        .storage
+        .catalog
+        .list_bookmarks()
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(hydrate(&state, bookmarks).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/bookmarks/{node_id}",
+    tag = "graph",
+    params(("node_id" = String, Path)),
+    responses(
+        (status = 204, description = "The bookmark was removed; the node is kept"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn delete_bookmark(
+    State(state): State<AppState>,
+    Path(node_id): Path<String>,
+) -> ApiResult<StatusCode> {
+    let deleted = state
+        .storage
+        .catalog
+        .delete_bookmark(&node_id)
+        .map_err(ApiError::from_storage)?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!(
+            "no bookmark on node '{node_id}'"
+        )));
+    }
+    info!(node_id = %node_id, "removed bookmark");
+    Ok(StatusCode::NO_CONTENT)
+}
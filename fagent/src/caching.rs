@@ -0,0 +1,111 @@
+//! Conditional requests for the read API.
+//!
+//! `GET` responses from the read routes carry a weak ETag derived from
+//! [`fstorage::FStorage::data_version`], so a client repeating a request with
+//! `If-None-Match` gets `304 Not Modified` until a write lands in the engine or
+//! the lake. The tag only names the storage state; the URL, query string
+//! included, is what tells two payloads apart.
+//!
+//! The graph routes additionally report [`fstorage::FStorage::graph_version`] in
+//! `Graph-Version`. Pollers can echo it back in `If-Graph-Version` and get a
+//! `304` without the handler running until the next sync lands.
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{HeaderName, CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use tracing::warn;
+
+use crate::{ApiError, AppState};
+
+const GRAPH_VERSION: HeaderName = HeaderName::from_static("graph-version");
+const IF_GRAPH_VERSION: HeaderName = HeaderName::from_static("if-graph-version");
+
+/// Middleware answering `304` when the client already holds the current data.
+/// Requests other than `GET` and `HEAD` pass through untouched.
+pub(crate) async fn data_etag(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+
+    // Read before the handler runs: a write racing the request then yields a
+    // stale tag, which only costs the client one extra download.
+    let version = match state.storage.data_version().await {
+        Ok(version) => version,
+        Err(err) => {
+            warn!("failed to read the data version: {}", err);
+            return next.run(request).await;
+        }
+    };
+    let etag = format!("W/\"{version}\"");
+    let etag = HeaderValue::from_str(&etag).expect("ETag is ASCII");
+
+    if matches_etag(request.headers(), &etag) {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        response.headers_mut().insert(ETAG, etag);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::OK {
+        let headers = response.headers_mut();
+        headers.insert(ETAG, etag);
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    }
+    response
+}
+
+/// Middleware for the graph routes: tags responses with the graph version and
+/// answers `304` to `GET`s whose `If-Graph-Version` is still current.
+pub(crate) async fn graph_version(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let version = match state.storage.graph_version() {
+        Ok(version) => version,
+        Err(err) => return ApiError::from_storage(err).into_response(),
+    };
+    let value = HeaderValue::from(version);
+
+    let cacheable = matches!(*request.method(), Method::GET | Method::HEAD);
+    let known = request
+        .headers()
+        .get(IF_GRAPH_VERSION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    if cacheable && known == Some(version) {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        response.headers_mut().insert(GRAPH_VERSION, value);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(GRAPH_VERSION, value);
+    response
+}
+
+/// Weak comparison of `If-None-Match` against `etag`, as RFC 9110 requires for
+/// that header.
+fn matches_etag(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let Ok(current) = etag.to_str().map(opaque) else {
+        return false;
+    };
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == current)
+}
@@ -0,0 +1,326 @@
+//! Two tracked projects side by side, for choosing between them.
+//!
+//! The comparison puts together what the graph knows about each: the
+//! libraries and file languages of its head version, its contributors, its
+//! activity over a window, and how close the two READMEs' chunk embeddings
+//! are. Libraries, languages and contributors are split into what both
+//! projects have and what only one of them has.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use fstorage::{fetch::Fetchable, models::ActivityDay, schemas::generated_schemas::ContributesTo};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    activity::{parse_window, ActivityTotals, DEFAULT_WINDOW_DAYS},
+    map_node_summary,
+    readme::resolve_project_url,
+    related::{head_files, linked, project_libraries, readme_neighbors},
+    ApiError, ApiResult, AppState, ErrorResponse, GraphNodeSummary,
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct CompareQuery {
+    /// Node id of the first project.
+    a: String,
+    /// Node id of the second project.
+    b: String,
+    /// Days of activity to compare, as `<n>d` or `<n>w`; `90d` by default.
+    #[serde(default)]
+    window: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ComparedProject {
+    project: GraphNodeSummary,
+    project_url: String,
+    /// Primary language, as the project reports it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    /// Files of the head version per language.
+    file_languages: BTreeMap<String, usize>,
+    libraries: usize,
+    contributors: usize,
+    activity: ActivityTrend,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ActivityTrend {
+    /// Sums over the window.
+    totals: ActivityTotals,
+    /// Commits, issues and pull requests in the older half of the window.
+    earlier: u64,
+    /// Commits, issues and pull requests in the newer half.
+    recent: u64,
+    trend: Trend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Trend {
+    /// More activity in the newer half of the window than in the older.
+    Rising,
+    /// Less activity in the newer half.
+    Falling,
+    Steady,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Overlap {
+    /// In both projects, sorted.
+    shared: Vec<String>,
+    only_a: Vec<String>,
+    only_b: Vec<String>,
+    /// Shared items over the items of either project, 0 to 1.
+    jaccard: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ContributorOverlap {
+    /// Developers contributing to both projects.
+    shared: Vec<GraphNodeSummary>,
+    only_a: usize,
+    only_b: usize,
+    /// Shared contributors over the contributors of either project, 0 to 1.
+    jaccard: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ProjectComparison {
+    a: ComparedProject,
+    b: ComparedProject,
+    window_days: u32,
+    /// Library names.
+    dependencies: Overlap,
+    /// Languages of the head versions' files.
+    languages: Overlap,
+    contributors: ContributorOverlap,
+    /// Best similarity between a chunk of one README and the nearest chunks
+    /// of the other, 0 to 1: 0 when neither comes up among the other's
+    /// nearest chunks, absent when either project has no README embedded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    readme_similarity: Option<f32>,
+}
+
+/// What is read of one side before the two are compared.
+struct Side {
+    id: String,
+    summary: GraphNodeSummary,
+    url: String,
+    language: Option<String>,
+    file_languages: BTreeMap<String, usize>,
+    libraries: BTreeSet<String>,
+    contributors: BTreeSet<String>,
+    days: Vec<ActivityDay>,
+}
+
+fn string_property(node: &HashMap<String, JsonValue>, key: &str) -> Option<String> {
+    node.get("properties")
+        .and_then(|properties| properties.get(key))
+        .and_then(JsonValue::as_str)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+async fn load_side(state: &AppState, id: &str, window_days: u32) -> ApiResult<Side> {
+    let url = resolve_project_url(state, id).await?;
+    let lake = &state.storage.lake;
+    // Stored ids are lowercase whatever case the project was asked by.
+    let id = Uuid::parse_str(id).map_or(id.to_string(), |uuid| uuid.to_string());
+    let node = lake
+        .get_nodes_by_ids(std::slice::from_ref(&id))
+        .await
+        .map_err(ApiError::from_storage)?
+        .remove(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("project '{id}' not found")))?;
+    let language = string_property(&node, "language");
+    let summary = map_node_summary(node)
+        .ok_or_else(|| ApiError::NotFound(format!("project '{id}' not found")))?;
+
+    let files: Vec<String> = head_files(lake, &id).await?.into_iter().collect();
+    let mut file_languages = BTreeMap::new();
+    for file in lake
+        .get_nodes_by_ids(&files)
+        .await
+        .map_err(ApiError::from_storage)?
+        .values()
+    {
+        if let Some(language) = string_property(file, "language") {
+            *file_languages.entry(language).or_default() += 1;
+        }
+    }
+
+    let libraries = project_libraries(lake, &id).await?;
+    let contributors = linked(lake, &id, ContributesTo::ENTITY_TYPE, false)
+        .await?
+        .into_iter()
+        .collect();
+    let days = state
+        .storage
+        .project_activity(&url, window_days)
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Side {
+        id,
+        summary,
+        url,
+        language,
+        file_languages,
+        libraries,
+        contributors,
+        days,
+    })
+}
+
+fn jaccard(shared: usize, a: usize, b: usize) -> f32 {
+    let union = a + b - shared;
+    if union == 0 {
+        0.0
+    } else {
+        shared as f32 / union as f32
+    }
+}
+
+fn overlap(a: &BTreeSet<String>, b: &BTreeSet<String>) -> Overlap {
+    let shared: Vec<String> = a.intersection(b).cloned().collect();
+    Overlap {
+        jaccard: jaccard(shared.len(), a.len(), b.len()),
+        shared,
+        only_a: a.difference(b).cloned().collect(),
+        only_b: b.difference(a).cloned().collect(),
+    }
+}
+
+/// Activity in the older and the newer half of `days`, oldest first. The
+/// middle day of an odd window counts for neither.
+fn activity_trend(days: &[ActivityDay]) -> ActivityTrend {
+    let events = |days: &[ActivityDay]| {
+        days.iter()
+            .map(|day| day.commits + day.issues + day.pull_requests)
+            .sum::<u64>()
+    };
+    let half = days.len() / 2;
+    let earlier = events(&days[..half]);
+    let recent = events(&days[days.len() - half..]);
+    ActivityTrend {
+        totals: ActivityTotals::of(days),
+        earlier,
+        recent,
+        trend: match recent.cmp(&earlier) {
+            std::cmp::Ordering::Greater => Trend::Rising,
+            std::cmp::Ordering::Less => Trend::Falling,
+            std::cmp::Ordering::Equal => Trend::Steady,
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/compare",
+    tag = "graph",
+    params(CompareQuery),
+    responses(
+        (status = 200, description = "The two projects side by side", body = ProjectComparison),
+        (status = 304, description = "Unchanged since the If-None-Match ETag"),
+        (status = 400, description = "The same project twice, or a malformed window", body = ErrorResponse),
+        (status = 404, description = "No such project", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn compare_projects(
+    State(state): State<AppState>,
+    Query(query): Query<CompareQuery>,
+) -> ApiResult<Json<ProjectComparison>> {
+    let window_days = match query.window.as_deref() {
+        Some(window) => parse_window(window)?,
+        None => DEFAULT_WINDOW_DAYS,
+    };
+    let a = load_side(&state, &query.a, window_days).await?;
+    let b = load_side(&state, &query.b, window_days).await?;
+    if a.id == b.id {
+        return Err(ApiError::BadRequest(
+            "a and b must be two different projects".to_string(),
+        ));
+    }
+    let lake = &state.storage.lake;
+
+    let library_ids: Vec<String> = a.libraries.union(&b.libraries).cloned().collect();
+    let library_nodes = lake
+        .get_nodes_by_ids(&library_ids)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let library_names = |libraries: &BTreeSet<String>| -> BTreeSet<String> {
+        libraries
+            .iter()
+            .map(|id| {
+                library_nodes
+                    .get(id)
+                    .and_then(|node| string_property(node, "name"))
+                    .unwrap_or_else(|| id.clone())
+            })
+            .collect()
+    };
+    let dependencies = overlap(&library_names(&a.libraries), &library_names(&b.libraries));
+
+    let languages = |side: &Side| -> BTreeSet<String> {
+        side.file_languages
+            .keys()
+            .cloned()
+            .chain(side.language.clone())
+            .collect()
+    };
+    let languages = overlap(&languages(&a), &languages(&b));
+
+    let shared: Vec<String> = a
+        .contributors
+        .intersection(&b.contributors)
+        .cloned()
+        .collect();
+    let mut developers = lake
+        .get_nodes_by_ids(&shared)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let contributors = ContributorOverlap {
+        only_a: a.contributors.len() - shared.len(),
+        only_b: b.contributors.len() - shared.len(),
+        jaccard: jaccard(shared.len(), a.contributors.len(), b.contributors.len()),
+        shared: shared
+            .iter()
+            .filter_map(|id| developers.remove(id).and_then(map_node_summary))
+            .collect(),
+    };
+
+    let a_neighbors = readme_neighbors(lake, &a.id).await?;
+    let b_neighbors = readme_neighbors(lake, &b.id).await?;
+    let readme_similarity = (!a_neighbors.is_empty() && !b_neighbors.is_empty()).then(|| {
+        let a_to_b = a_neighbors.get(&b.id).copied().unwrap_or(0.0);
+        let b_to_a = b_neighbors.get(&a.id).copied().unwrap_or(0.0);
+        a_to_b.max(b_to_a)
+    });
+
+    let compared = |side: Side| ComparedProject {
+        libraries: side.libraries.len(),
+        contributors: side.contributors.len(),
+        activity: activity_trend(&side.days),
+        project: side.summary,
+        project_url: side.url,
+        language: side.language,
+        file_languages: side.file_languages,
+    };
+    Ok(Json(ProjectComparison {
+        a: compared(a),
+        b: compared(b),
+        window_days,
+        dependencies,
+        languages,
+        contributors,
+        readme_similarity,
+    }))
+}
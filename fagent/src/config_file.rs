@@ -0,0 +1,306 @@
+//! `fagent dashboard --config fagent.toml`: dashboard settings kept in a file
+//! instead of a long command line.
+//!
+//! The file covers the settings a deployment usually pins down: where the
+//! store lives, the bind address, extra workspaces, the fetchers to register
+//! and their tokens, embedding providers, API keys and sync schedules. A flag
+//! or environment variable that is given replaces the file's value, lists
+//! included, so one setting can be overridden without editing the file.
+//! Relative paths in the file are resolved against the file's directory.
+//! Subcommands that open the store without serving it, such as `query` or
+//! `status`, take the same `--config` and read only `base_path` from it.
+//!
+//! ```toml
+//! base_path = "data"
+//! bind = "0.0.0.0:3000"
+//!
+//! [workspaces]
+//! research = "/srv/fagent/research"
+//!
+//! [fetchers.gitfetcher]
+//! token = "ghp_..."
+//! repo_allow = ["rust-lang/*"]
+//!
+//! [embedding]
+//! models = ["fastembed:BAAI/bge-small-en-v1.5"]
+//! limits = { openai = "3000/1000000" }
+//!
+//! [auth]
+//! api_keys = { "secret-admin-key" = "admin" }
+//!
+//! [[schedules]]
+//! name = "nightly"
+//! cron = "0 3 * * *"
+//! request = { fetcher = "gitfetcher", params = { repo = "rust-lang/rust" } }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use fstorage::{
+    embedding::{EmbeddingModelConfig, OnnxDevice},
+    rerank::RerankerConfig,
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::Role, parse_embedding_limit, schedules::ScheduleRequest, DashboardArgs, OnnxPoolingMode,
+    StoreArgs,
+};
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DashboardFile {
+    base_path: Option<PathBuf>,
+    bind: Option<String>,
+    sync_workers: Option<usize>,
+    workspaces: BTreeMap<String, PathBuf>,
+    fetchers: FetchersSection,
+    embedding: EmbeddingSection,
+    auth: AuthSection,
+    schedules: Vec<ScheduleRequest>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FetchersSection {
+    gitfetcher: Option<GitFetcherSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GitFetcherSection {
+    enabled: bool,
+    token: Option<String>,
+    repo_allow: Vec<String>,
+    repo_deny: Vec<String>,
+    repo_licenses: Vec<String>,
+    max_repo_size_kb: Option<u64>,
+    workspace_dir: Option<PathBuf>,
+    code_index_dir: Option<PathBuf>,
+    coverage_dir: Option<PathBuf>,
+    benchmark_dir: Option<PathBuf>,
+}
+
+impl Default for GitFetcherSection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            token: None,
+            repo_allow: Vec::new(),
+            repo_deny: Vec::new(),
+            repo_licenses: Vec::new(),
+            max_repo_size_kb: None,
+            workspace_dir: None,
+            code_index_dir: None,
+            coverage_dir: None,
+            benchmark_dir: None,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct EmbeddingSection {
+    onnx_model: Option<PathBuf>,
+    onnx_tokenizer: Option<PathBuf>,
+    onnx_pooling: Option<String>,
+    onnx_device: Option<String>,
+    onnx_normalize: Option<bool>,
+    reranker: Option<String>,
+    reranker_api_key: Option<String>,
+    reranker_model: Option<String>,
+    /// Extra models, as for `--embedding-model`.
+    models: Vec<String>,
+    /// `provider[:model]` to `RPM/TPM`, as for `--embedding-limit`.
+    limits: BTreeMap<String, String>,
+    /// `provider[:model]` to a per-1k-token USD price.
+    token_prices: BTreeMap<String, f64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthSection {
+    /// API key to the role it grants.
+    api_keys: BTreeMap<String, String>,
+    cors_origins: Vec<String>,
+}
+
+fn read(path: &Path) -> anyhow::Result<DashboardFile> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("invalid config file {}", path.display()))
+}
+
+/// Whether `matches` shows the setting `id` was neither passed as a flag nor
+/// set through the environment.
+fn unset(matches: &ArgMatches, id: &str) -> bool {
+    !matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Fills where the store lives from the `--config` file, if any, for the
+/// subcommands that open the store without serving it.
+pub(crate) fn apply_store(store: &mut StoreArgs, matches: &ArgMatches) -> anyhow::Result<()> {
+    let Some(path) = store.config.clone() else {
+        return Ok(());
+    };
+    let file = read(&path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    if let Some(base_path) = file.base_path.filter(|_| unset(matches, "base_path")) {
+        store.base_path = Some(dir.join(base_path));
+    }
+    Ok(())
+}
+
+/// Fills the settings of `args` that `matches` shows were neither passed as a
+/// flag nor set through the environment from the `--config` file, if any.
+pub(crate) fn apply(args: &mut DashboardArgs, matches: &ArgMatches) -> anyhow::Result<()> {
+    apply_store(&mut args.store, matches)?;
+    let Some(path) = args.store.config.clone() else {
+        return Ok(());
+    };
+    let file = read(&path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let resolve = |path: PathBuf| dir.join(path);
+    let unset = |id: &str| unset(matches, id);
+
+    if let Some(bind) = file.bind.filter(|_| unset("bind")) {
+        args.bind = bind;
+    }
+    if let Some(workers) = file.sync_workers.filter(|_| unset("sync_workers")) {
+        args.sync_workers = workers;
+    }
+    if unset("workspaces") {
+        args.workspaces = file
+            .workspaces
+            .into_iter()
+            .map(|(name, path)| (name, resolve(path)))
+            .collect();
+    }
+
+    if let Some(git) = file.fetchers.gitfetcher {
+        if unset("disable_gitfetcher") {
+            args.disable_gitfetcher = !git.enabled;
+        }
+        if let Some(token) = git.token.filter(|_| unset("github_token")) {
+            args.github_token = Some(token);
+        }
+        if unset("repo_allow") {
+            args.repo_allow = git.repo_allow;
+        }
+        if unset("repo_deny") {
+            args.repo_deny = git.repo_deny;
+        }
+        if unset("repo_licenses") {
+            args.repo_licenses = git.repo_licenses;
+        }
+        if let Some(size) = git.max_repo_size_kb.filter(|_| unset("max_repo_size_kb")) {
+            args.max_repo_size_kb = Some(size);
+        }
+        for (id, slot, value) in [
+            ("workspace_dir", &mut args.workspace_dir, git.workspace_dir),
+            (
+                "code_index_dir",
+                &mut args.code_index_dir,
+                git.code_index_dir,
+            ),
+            ("coverage_dir", &mut args.coverage_dir, git.coverage_dir),
+            ("benchmark_dir", &mut args.benchmark_dir, git.benchmark_dir),
+        ] {
+            if let Some(dir) = value.filter(|_| unset(id)) {
+                *slot = Some(resolve(dir));
+            }
+        }
+    }
+
+    let embedding = file.embedding;
+    if let Some(model) = embedding.onnx_model.filter(|_| unset("onnx_model")) {
+        args.onnx_model = Some(resolve(model));
+    }
+    if let Some(tokenizer) = embedding.onnx_tokenizer.filter(|_| unset("onnx_tokenizer")) {
+        args.onnx_tokenizer = Some(resolve(tokenizer));
+    }
+    if let Some(pooling) = embedding.onnx_pooling.filter(|_| unset("onnx_pooling")) {
+        args.onnx_pooling = OnnxPoolingMode::from_str(&pooling, true)
+            .map_err(|err| anyhow::anyhow!("embedding.onnx_pooling: {err}"))?;
+    }
+    if let Some(device) = embedding.onnx_device.filter(|_| unset("onnx_device")) {
+        args.onnx_device = device
+            .parse::<OnnxDevice>()
+            .map_err(|err| anyhow::anyhow!("embedding.onnx_device: {err}"))?;
+    }
+    if let Some(normalize) = embedding
+        .onnx_normalize
+        .filter(|_| unset("onnx_no_normalize"))
+    {
+        args.onnx_no_normalize = !normalize;
+    }
+    if let Some(reranker) = embedding.reranker.filter(|_| unset("reranker")) {
+        args.reranker = Some(
+            reranker
+                .parse::<RerankerConfig>()
+                .map_err(|err| anyhow::anyhow!("embedding.reranker: {err}"))?,
+        );
+    }
+    if let Some(key) = embedding
+        .reranker_api_key
+        .filter(|_| unset("reranker_api_key"))
+    {
+        args.reranker_api_key = Some(key);
+    }
+    if let Some(model) = embedding.reranker_model.filter(|_| unset("reranker_model")) {
+        args.reranker_model = Some(model);
+    }
+    if unset("embedding_models") {
+        args.embedding_models = embedding
+            .models
+            .iter()
+            .map(|model| {
+                model
+                    .parse::<EmbeddingModelConfig>()
+                    .map_err(|err| anyhow::anyhow!("embedding.models: {err}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    if unset("embedding_limits") {
+        args.embedding_limits = embedding
+            .limits
+            .iter()
+            .map(|(provider, limits)| {
+                parse_embedding_limit(&format!("{provider}={limits}"))
+                    .map_err(|err| anyhow::anyhow!("embedding.limits: {err}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    if unset("token_prices") {
+        args.token_prices = embedding.token_prices.into_iter().collect();
+    }
+
+    if unset("api_keys") {
+        args.api_keys = file
+            .auth
+            .api_keys
+            .into_iter()
+            .map(|(key, role)| {
+                let role = role
+                    .parse::<Role>()
+                    .map_err(|err| anyhow::anyhow!("auth.api_keys: {err}"))?;
+                Ok((key, role))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    if unset("cors_origins") {
+        args.cors_origins = file.auth.cors_origins;
+    }
+
+    args.schedules = file.schedules;
+    Ok(())
+}
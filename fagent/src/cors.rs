@@ -0,0 +1,78 @@
+//! Cross-origin access for browser frontends served from other origins.
+//!
+//! The bundled dashboard is same-origin and needs none of this. Preflight
+//! requests are answered before authentication, since browsers send them
+//! without the API key.
+
+use axum::http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, LINK, RETRY_AFTER},
+    HeaderName, HeaderValue, Method,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Origins allowed to call the API from a browser.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CorsOrigins {
+    /// Cross-origin requests are not allowed.
+    #[default]
+    None,
+    /// Any origin, for `*`.
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+impl CorsOrigins {
+    /// Parses origins such as `https://app.example.com`; `*` allows any origin.
+    pub fn parse<S: AsRef<str>>(origins: &[S]) -> Result<Self, String> {
+        let origins: Vec<&str> = origins
+            .iter()
+            .map(|origin| origin.as_ref().trim())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        if origins.is_empty() {
+            return Ok(Self::None);
+        }
+        if origins.contains(&"*") {
+            return Ok(Self::Any);
+        }
+        origins
+            .into_iter()
+            .map(|origin| {
+                if !(origin.starts_with("http://") || origin.starts_with("https://"))
+                    || origin.ends_with('/')
+                {
+                    return Err(format!(
+                        "invalid CORS origin '{origin}', expected scheme://host[:port]"
+                    ));
+                }
+                HeaderValue::from_str(origin).map_err(|_| format!("invalid CORS origin '{origin}'"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::List)
+    }
+
+    pub(crate) fn layer(&self) -> Option<CorsLayer> {
+        let allow_origin = match self {
+            Self::None => return None,
+            Self::Any => AllowOrigin::any(),
+            Self::List(origins) => AllowOrigin::list(origins.iter().cloned()),
+        };
+        Some(
+            CorsLayer::new()
+                .allow_origin(allow_origin)
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+                .allow_headers([
+                    CONTENT_TYPE,
+                    AUTHORIZATION,
+                    HeaderName::from_static("x-api-key"),
+                ])
+                .expose_headers([
+                    RETRY_AFTER,
+                    LINK,
+                    HeaderName::from_static("deprecation"),
+                    HeaderName::from_static("sunset"),
+                    HeaderName::from_static("graph-version"),
+                ]),
+        )
+    }
+}
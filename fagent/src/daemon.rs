@@ -0,0 +1,166 @@
+//! `fagent daemon`: the long-running deployment mode.
+//!
+//! Runs what `fagent dashboard` runs, with the same flags and config file: the
+//! HTTP API, `--sync-workers` sync workers and a cron scheduler per store, and
+//! the GitHub webhook receiver. Workers and schedulers run as supervised
+//! tasks. One that panics or returns is logged and started again after a
+//! pause that doubles, up to `--max-restart-delay-secs`, for as long as it
+//! keeps failing within minutes of each start. A sync job that panics is
+//! marked failed by its worker, which carries on with the next one. Of the
+//! processes opened on one store, only the one holding the catalog's scheduler
+//! lease fires its schedules.
+//!
+//! `--webhook-bind` opens a second listener serving only the webhook route, so
+//! GitHub deliveries can arrive on a public address while the API stays on a
+//! private one. The process stops on Ctrl-C or SIGTERM once both listeners
+//! have drained.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use axum::{middleware, Router};
+use tokio::task::{JoinHandle, JoinSet};
+use tower::Layer;
+use tracing::{info, warn};
+
+use crate::{
+    build_router, dashboard_state, schedules, serve, telemetry, versioning, webhook_routes,
+    AppState, DaemonArgs, DashboardBind,
+};
+
+pub(crate) const DEFAULT_MAX_RESTART_DELAY_SECS: u64 = 60;
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+/// A task that failed after running this long is restarted after the
+/// shortest pause again.
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+pub(crate) async fn run(args: DaemonArgs) -> anyhow::Result<()> {
+    let dashboard = &args.dashboard;
+    let bind = DashboardBind::parse(&dashboard.bind)?;
+    let webhook_bind = args
+        .webhook_bind
+        .as_deref()
+        .map(DashboardBind::parse)
+        .transpose()?;
+    let state = dashboard_state(dashboard, |storage, _| AppState::unstarted(storage)).await?;
+    match (&webhook_bind, &state.github_webhook_secret) {
+        (Some(_), None) => bail!("--webhook-bind needs --github-webhook-secret"),
+        (None, None) => info!("No GitHub webhook secret configured; webhooks are not received"),
+        _ => {}
+    }
+
+    let max_delay = Duration::from_secs(args.max_restart_delay_secs).max(MIN_RESTART_DELAY);
+    let stores = std::iter::once((None, &state)).chain(
+        state
+            .workspaces
+            .iter()
+            .map(|(name, workspace)| (Some(name.as_str()), workspace)),
+    );
+    let mut supervisors = Vec::new();
+    for (workspace, store) in stores {
+        let name = |task: &str| match workspace {
+            Some(workspace) => format!("{workspace}/{task}"),
+            None => task.to_string(),
+        };
+        for worker in 0..dashboard.sync_workers.max(1) {
+            let jobs = store.jobs.clone();
+            supervisors.push(supervise(
+                name(&format!("sync-worker-{worker}")),
+                max_delay,
+                move || jobs.clone().run_worker(),
+            ));
+        }
+        let scheduler = store.clone();
+        supervisors.push(supervise(name("scheduler"), max_delay, move || {
+            schedules::run_scheduler(scheduler.clone())
+        }));
+    }
+    info!(tasks = supervisors.len(), "daemon started");
+
+    let api = serve(bind, build_router(state.clone()), terminate_signal());
+    let outcome = match webhook_bind {
+        Some(webhook_bind) => {
+            let webhooks = serve(webhook_bind, webhook_router(&state), terminate_signal());
+            tokio::try_join!(api, webhooks).map(|_| ())
+        }
+        None => api.await,
+    };
+    for supervisor in supervisors {
+        supervisor.abort();
+    }
+    state.flush_usage().await;
+    outcome
+}
+
+/// Runs the future `start` makes as a task of its own, and makes another
+/// whenever the last one panicked or returned. Aborting the returned handle
+/// aborts the running task with it.
+fn supervise<F, Fut>(name: String, max_delay: Duration, start: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut delay = MIN_RESTART_DELAY;
+        loop {
+            let started = Instant::now();
+            let mut task = JoinSet::new();
+            task.spawn(start());
+            let Some(outcome) = task.join_next().await else {
+                return;
+            };
+            if started.elapsed() >= STABLE_AFTER {
+                delay = MIN_RESTART_DELAY;
+            }
+            match outcome {
+                Ok(()) => {
+                    warn!(task = %name, "supervised task returned; restarting in {:?}", delay)
+                }
+                Err(err) if err.is_panic() => {
+                    warn!(task = %name, "supervised task panicked; restarting in {:?}", delay)
+                }
+                Err(_) => return,
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(max_delay);
+        }
+    })
+}
+
+/// Router of the `--webhook-bind` listener: the webhook route, also under the
+/// `/api/v1` prefix, and nothing else.
+fn webhook_router(state: &AppState) -> Router {
+    let routes = webhook_routes(state);
+    Router::new()
+        .fallback_service(
+            middleware::from_fn_with_state(state.legacy_api, versioning::route_version)
+                .layer(routes),
+        )
+        .layer(middleware::from_fn(telemetry::log_request))
+}
+
+/// Completes on Ctrl-C, or on SIGTERM from a service manager.
+async fn terminate_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(err) => {
+                warn!("failed to listen for SIGTERM: {}", err);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+    info!("Shutdown signal received");
+}
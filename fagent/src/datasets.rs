@@ -0,0 +1,44 @@
+//! `fagent export-project` and `fagent import-project`: moving one project
+//! between stores as a directory of Parquet files and a `manifest.json`.
+
+use fstorage::models::DatasetManifest;
+
+use crate::{ExportProjectArgs, ImportProjectArgs};
+
+pub(crate) async fn run_export(args: ExportProjectArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let manifest = storage.export_project(&args.project, &args.out).await?;
+    println!(
+        "exported {} to {}",
+        manifest.project_url,
+        args.out.display()
+    );
+    print!("{}", render_files(&manifest));
+    Ok(())
+}
+
+pub(crate) async fn run_import(args: ImportProjectArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let manifest = storage.import_dataset(&args.dir).await?;
+    println!(
+        "imported {} from {}",
+        manifest.project_url,
+        args.dir.display()
+    );
+    print!("{}", render_files(&manifest));
+    Ok(())
+}
+
+fn render_files(manifest: &DatasetManifest) -> String {
+    let width = manifest
+        .files
+        .iter()
+        .map(|file| file.path.len())
+        .max()
+        .unwrap_or(0);
+    manifest
+        .files
+        .iter()
+        .map(|file| format!("  {:width$}  {} rows\n", file.path, file.rows))
+        .collect()
+}
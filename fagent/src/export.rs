@@ -0,0 +1,382 @@
+//! Subgraph download in formats desktop graph tools open directly: GraphML
+//! (yEd, Cytoscape), DOT (Graphviz) and GEXF (Gephi).
+//!
+//! Node and edge properties become attributes, with non-string values written
+//! as their JSON text.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use axum::{
+    extract::{Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    load_subgraph, ApiResult, AppState, ErrorResponse, GraphEdgeDto, GraphNodeDto,
+    GraphSubgraphQuery, GraphSubgraphResponse,
+};
+
+#[derive(Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportFormat {
+    Graphml,
+    Dot,
+    Gexf,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Graphml => "application/graphml+xml",
+            ExportFormat::Dot => "text/vnd.graphviz",
+            ExportFormat::Gexf => "application/gexf+xml",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Graphml => "graphml",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Gexf => "gexf",
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct GraphExportQuery {
+    start_id: String,
+    #[serde(default)]
+    depth: Option<usize>,
+    #[serde(default)]
+    node_limit: Option<usize>,
+    #[serde(default)]
+    edge_limit: Option<usize>,
+    #[serde(default)]
+    edge_types: Option<String>,
+    format: ExportFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/export",
+    tag = "graph",
+    params(GraphExportQuery),
+    responses(
+        (status = 200, description = "The subgraph as GraphML, DOT or GEXF", body = String),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn graph_export(
+    State(state): State<AppState>,
+    Query(query): Query<GraphExportQuery>,
+) -> ApiResult<Response> {
+    let subgraph = load_subgraph(
+        &state,
+        &GraphSubgraphQuery {
+            start_id: query.start_id,
+            depth: query.depth,
+            node_limit: query.node_limit,
+            edge_limit: query.edge_limit,
+            edge_types: query.edge_types,
+            stream: None,
+        },
+    )
+    .await?;
+
+    let mut graph = ExportGraph::from(subgraph);
+    graph.nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    let body = match query.format {
+        ExportFormat::Graphml => graph.to_graphml(),
+        ExportFormat::Dot => graph.to_dot(),
+        ExportFormat::Gexf => graph.to_gexf(),
+    };
+    let disposition = format!(
+        "attachment; filename=\"subgraph.{}\"",
+        query.format.extension()
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, query.format.content_type().to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+struct ExportGraph {
+    nodes: Vec<GraphNodeDto>,
+    edges: Vec<GraphEdgeDto>,
+}
+
+impl From<GraphSubgraphResponse> for ExportGraph {
+    fn from(subgraph: GraphSubgraphResponse) -> Self {
+        Self {
+            nodes: subgraph.nodes,
+            edges: subgraph.edges,
+        }
+    }
+}
+
+/// Properties of `properties` as text, skipping nulls.
+fn attributes(properties: &JsonValue) -> Vec<(&str, String)> {
+    let Some(object) = properties.as_object() else {
+        return Vec::new();
+    };
+    object
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let text = match value {
+                JsonValue::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            (key.as_str(), text)
+        })
+        .collect()
+}
+
+fn attribute_keys<'a>(properties: impl Iterator<Item = &'a JsonValue>) -> Vec<String> {
+    let keys: BTreeSet<String> = properties
+        .flat_map(|properties| attributes(properties).into_iter())
+        .map(|(key, _)| key.to_string())
+        .collect();
+    keys.into_iter().collect()
+}
+
+fn xml_escape(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for ch in raw.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0.
+            ch if ch.is_control() && !matches!(ch, '\t' | '\n' | '\r') => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn dot_quote(raw: &str) -> String {
+    let mut quoted = String::with_capacity(raw.len() + 2);
+    quoted.push('"');
+    for ch in raw.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl ExportGraph {
+    fn node_label(node: &GraphNodeDto) -> &str {
+        node.display_name.as_deref().unwrap_or(&node.id)
+    }
+
+    // `write!` into a `String` cannot fail, hence the ignored results below.
+    fn to_graphml(&self) -> String {
+        let node_keys = attribute_keys(self.nodes.iter().map(|node| &node.properties));
+        let edge_keys = attribute_keys(self.edges.iter().map(|edge| &edge.properties));
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+             \x20 <key id=\"n_label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"n_entity_type\" for=\"node\" attr.name=\"entity_type\" attr.type=\"string\"/>\n\
+             \x20 <key id=\"e_label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        );
+        for (prefix, domain, keys) in [("n", "node", &node_keys), ("e", "edge", &edge_keys)] {
+            for (idx, key) in keys.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "  <key id=\"{prefix}{idx}\" for=\"{domain}\" attr.name=\"{}\" attr.type=\"string\"/>",
+                    xml_escape(key)
+                );
+            }
+        }
+        out.push_str("  <graph id=\"subgraph\" edgedefault=\"directed\">\n");
+        let key_ids = |keys: &[String]| -> HashMap<String, usize> {
+            keys.iter()
+                .enumerate()
+                .map(|(idx, key)| (key.clone(), idx))
+                .collect()
+        };
+        let (node_ids, edge_ids) = (key_ids(&node_keys), key_ids(&edge_keys));
+        for node in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+            let _ = writeln!(
+                out,
+                "      <data key=\"n_label\">{}</data>",
+                xml_escape(Self::node_label(node))
+            );
+            let _ = writeln!(
+                out,
+                "      <data key=\"n_entity_type\">{}</data>",
+                xml_escape(&node.entity_type)
+            );
+            for (key, value) in attributes(&node.properties) {
+                let _ = writeln!(
+                    out,
+                    "      <data key=\"n{}\">{}</data>",
+                    node_ids[key],
+                    xml_escape(&value)
+                );
+            }
+            out.push_str("    </node>\n");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+                xml_escape(&edge.id),
+                xml_escape(&edge.from),
+                xml_escape(&edge.to)
+            );
+            let _ = writeln!(
+                out,
+                "      <data key=\"e_label\">{}</data>",
+                xml_escape(&edge.label)
+            );
+            for (key, value) in attributes(&edge.properties) {
+                let _ = writeln!(
+                    out,
+                    "      <data key=\"e{}\">{}</data>",
+                    edge_ids[key],
+                    xml_escape(&value)
+                );
+            }
+            out.push_str("    </edge>\n");
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph subgraph {\n");
+        for node in &self.nodes {
+            let _ = write!(
+                out,
+                "  {} [label={}, entity_type={}",
+                dot_quote(&node.id),
+                dot_quote(Self::node_label(node)),
+                dot_quote(&node.entity_type)
+            );
+            for (key, value) in attributes(&node.properties) {
+                let _ = write!(out, ", {}={}", dot_quote(key), dot_quote(&value));
+            }
+            out.push_str("];\n");
+        }
+        for edge in &self.edges {
+            let _ = write!(
+                out,
+                "  {} -> {} [label={}, id={}",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to),
+                dot_quote(&edge.label),
+                dot_quote(&edge.id)
+            );
+            for (key, value) in attributes(&edge.properties) {
+                let _ = write!(out, ", {}={}", dot_quote(key), dot_quote(&value));
+            }
+            out.push_str("];\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_gexf(&self) -> String {
+        let node_keys = attribute_keys(self.nodes.iter().map(|node| &node.properties));
+        let edge_keys = attribute_keys(self.edges.iter().map(|edge| &edge.properties));
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n\
+             \x20 <graph defaultedgetype=\"directed\" mode=\"static\">\n",
+        );
+        // Attribute 0 of each class is the entity type / edge label; the
+        // properties follow.
+        for (class, first, keys) in [
+            ("node", "entity_type", &node_keys),
+            ("edge", "label", &edge_keys),
+        ] {
+            let _ = writeln!(out, "    <attributes class=\"{class}\">");
+            let _ = writeln!(
+                out,
+                "      <attribute id=\"0\" title=\"{first}\" type=\"string\"/>"
+            );
+            for (idx, key) in keys.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "      <attribute id=\"{}\" title=\"{}\" type=\"string\"/>",
+                    idx + 1,
+                    xml_escape(key)
+                );
+            }
+            out.push_str("    </attributes>\n");
+        }
+        let attribute_ids = |keys: &[String]| -> HashMap<String, usize> {
+            keys.iter()
+                .enumerate()
+                .map(|(idx, key)| (key.clone(), idx + 1))
+                .collect()
+        };
+        let (node_ids, edge_ids) = (attribute_ids(&node_keys), attribute_ids(&edge_keys));
+
+        out.push_str("    <nodes>\n");
+        for node in &self.nodes {
+            let _ = writeln!(
+                out,
+                "      <node id=\"{}\" label=\"{}\">\n        <attvalues>\n          <attvalue for=\"0\" value=\"{}\"/>",
+                xml_escape(&node.id),
+                xml_escape(Self::node_label(node)),
+                xml_escape(&node.entity_type)
+            );
+            for (key, value) in attributes(&node.properties) {
+                let _ = writeln!(
+                    out,
+                    "          <attvalue for=\"{}\" value=\"{}\"/>",
+                    node_ids[key],
+                    xml_escape(&value)
+                );
+            }
+            out.push_str("        </attvalues>\n      </node>\n");
+        }
+        out.push_str("    </nodes>\n    <edges>\n");
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "      <edge id=\"{}\" source=\"{}\" target=\"{}\" label=\"{}\">\n        <attvalues>\n          <attvalue for=\"0\" value=\"{}\"/>",
+                xml_escape(&edge.id),
+                xml_escape(&edge.from),
+                xml_escape(&edge.to),
+                xml_escape(&edge.label),
+                xml_escape(&edge.label)
+            );
+            for (key, value) in attributes(&edge.properties) {
+                let _ = writeln!(
+                    out,
+                    "          <attvalue for=\"{}\" value=\"{}\"/>",
+                    edge_ids[key],
+                    xml_escape(&value)
+                );
+            }
+            out.push_str("        </attvalues>\n      </edge>\n");
+        }
+        out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+        out
+    }
+}
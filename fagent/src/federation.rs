@@ -0,0 +1,434 @@
+//! Searches and subgraphs answered by this store together with other fagent
+//! instances.
+//!
+//! Each `--federate name=url` names a remote dashboard. The federation routes
+//! run a request against the local store and forward the same query string to
+//! every remote's versioned API, then merge what comes back. Node ids are
+//! derived from entity keys, so an entity stored in several places carries
+//! the same id everywhere; merged results list every origin that returned
+//! it, with the local store named [`LOCAL_ORIGIN`]. An origin that fails or
+//! times out, the local store included, is reported in `errors` instead of
+//! failing the request; only a malformed request fails it.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Query, RawQuery, State},
+    Json,
+};
+use fstorage::models::MultiEntitySearchHit;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio::task::JoinSet;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    load_subgraph, run_hybrid_search, telemetry, ApiError, ApiResult, AppState, ErrorResponse,
+    GraphEdgeDto, GraphNodeDto, GraphSubgraphQuery, HybridMultiQuery, HybridOptions,
+};
+
+/// Origin tag of results from the store serving the request.
+pub(crate) const LOCAL_ORIGIN: &str = "local";
+
+/// How long a remote may take to answer before it is reported as failed.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A remote fagent dashboard queried by the federation routes.
+#[derive(Clone)]
+pub(crate) struct FederatedRemote {
+    pub(crate) name: String,
+    /// Root of the remote dashboard, e.g. `https://fagent.team-b.internal`.
+    pub(crate) base_url: String,
+    pub(crate) api_key: Option<String>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Federation {
+    remotes: Vec<FederatedRemote>,
+    client: reqwest::Client,
+}
+
+impl Default for Federation {
+    fn default() -> Self {
+        Self {
+            remotes: Vec::new(),
+            client: reqwest::Client::builder()
+                .timeout(REMOTE_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Federation {
+    pub(crate) fn add(&mut self, remote: FederatedRemote) -> Result<(), String> {
+        let name = remote.name.trim();
+        if name.is_empty() || name == LOCAL_ORIGIN {
+            return Err(format!("'{name}' cannot name a federated remote"));
+        }
+        if self.remotes.iter().any(|existing| existing.name == name) {
+            return Err(format!("federated remote '{name}' is already registered"));
+        }
+        if !(remote.base_url.starts_with("http://") || remote.base_url.starts_with("https://")) {
+            return Err(format!(
+                "federated remote '{name}' needs an http(s) URL, got '{}'",
+                remote.base_url
+            ));
+        }
+        self.remotes.push(FederatedRemote {
+            name: name.to_string(),
+            base_url: remote.base_url.trim_end_matches('/').to_string(),
+            api_key: remote.api_key,
+        });
+        Ok(())
+    }
+
+    /// GETs `path` with `query` from every remote, in registration order.
+    /// `Ok(None)` is a remote answering 404.
+    async fn fan_out<T: DeserializeOwned + Send + 'static>(
+        &self,
+        path: &str,
+        query: &str,
+    ) -> Vec<(String, Result<Option<T>, String>)> {
+        let request_id = telemetry::current_request_id();
+        let mut requests = JoinSet::new();
+        for (index, remote) in self.remotes.iter().enumerate() {
+            let mut request = self
+                .client
+                .get(format!("{}/api/v1{path}?{query}", remote.base_url));
+            if let Some(key) = &remote.api_key {
+                request = request.header("x-api-key", key);
+            }
+            // Lets a remote's logs be matched with the request fanned out here.
+            if let Some(id) = &request_id {
+                request = request.header(telemetry::REQUEST_ID_HEADER.as_str(), id.as_ref());
+            }
+            requests.spawn(async move { (index, fetch::<T>(request).await) });
+        }
+        let mut results: Vec<(usize, Result<Option<T>, String>)> = Vec::new();
+        while let Some(joined) = requests.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(err) => warn!("federated request task failed: {}", err),
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results
+            .into_iter()
+            .map(|(index, result)| (self.remotes[index].name.clone(), result))
+            .collect()
+    }
+}
+
+async fn fetch<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<Option<T>, String> {
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<JsonValue>(&body)
+            .ok()
+            .and_then(|body| body.get("error")?.as_str().map(str::to_string))
+            .unwrap_or(body);
+        return Err(format!("{status}: {message}"));
+    }
+    response
+        .json::<T>()
+        .await
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+/// An origin that could not answer.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct OriginError {
+    origin: String,
+    error: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FederatedSearchHit {
+    /// Score scaled by the best score of any origin, so hits of every origin
+    /// rank on one scale and only the best hit overall scores 1.
+    federated_score: f32,
+    /// Origins that returned this hit, the one whose hit is shown first.
+    origins: Vec<String>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    hit: MultiEntitySearchHit,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FederatedSearchResponse {
+    hits: Vec<FederatedSearchHit>,
+    errors: Vec<OriginError>,
+}
+
+#[derive(Deserialize)]
+struct RemoteSearchResponse {
+    hits: Vec<MultiEntitySearchHit>,
+}
+
+/// Identity of a hit across stores: its node or vector id when it has one.
+fn hit_key(hit: &MultiEntitySearchHit) -> Option<String> {
+    let id = hit
+        .node
+        .as_ref()
+        .and_then(|node| node.get("id"))
+        .or_else(|| hit.vector.as_ref().and_then(|vector| vector.get("id")))?
+        .as_str()?;
+    Some(format!("{}:{id}", hit.entity_type))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/federation/search/hybrid_all",
+    tag = "search",
+    params(HybridMultiQuery),
+    responses(
+        (status = 200, description = "Hits of this store and every federated remote, best first", body = FederatedSearchResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn federated_hybrid_search(
+    State(state): State<AppState>,
+    Query(query): Query<HybridMultiQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<FederatedSearchResponse>> {
+    let entity_types = query
+        .entity_types
+        .as_deref()
+        .map(|raw| {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let limit = query.limit.unwrap_or(20).clamp(1, 200);
+    let raw_query = raw_query.unwrap_or_default();
+    let (local, remote) = tokio::join!(
+        run_hybrid_search(
+            &state,
+            query.q.as_deref().unwrap_or_default(),
+            entity_types,
+            query.alpha,
+            query.limit,
+            HybridOptions {
+                rerank: query.rerank,
+                embedding_model: query.embedding_model.as_deref(),
+                graph_expand: query.graph_expand,
+            },
+        ),
+        state
+            .federation
+            .fan_out::<RemoteSearchResponse>("/search/hybrid_all", &raw_query)
+    );
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    match local {
+        Ok(response) => results.push((LOCAL_ORIGIN.to_string(), response.hits)),
+        Err(err @ ApiError::BadRequest(_)) => return Err(err),
+        Err(err) => errors.push(OriginError {
+            origin: LOCAL_ORIGIN.to_string(),
+            error: err.to_string(),
+        }),
+    }
+    for (origin, result) in remote {
+        match result {
+            Ok(response) => results.push((origin, response.map(|r| r.hits).unwrap_or_default())),
+            Err(error) => errors.push(OriginError { origin, error }),
+        }
+    }
+
+    let best = results
+        .iter()
+        .flat_map(|(_, hits)| hits)
+        .map(|hit| hit.score)
+        .fold(0.0_f32, f32::max);
+    let mut merged: Vec<FederatedSearchHit> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for (origin, hits) in results {
+        for hit in hits {
+            let federated_score = if best > 0.0 { hit.score / best } else { 0.0 };
+            let key = hit_key(&hit);
+            match key.as_ref().and_then(|key| by_key.get(key)).copied() {
+                Some(index) => {
+                    let existing = &mut merged[index];
+                    existing.origins.push(origin.clone());
+                    if federated_score > existing.federated_score {
+                        existing.federated_score = federated_score;
+                        existing.hit = hit;
+                        existing.origins.rotate_right(1);
+                    }
+                }
+                None => {
+                    if let Some(key) = key {
+                        by_key.insert(key, merged.len());
+                    }
+                    merged.push(FederatedSearchHit {
+                        federated_score,
+                        origins: vec![origin.clone()],
+                        hit,
+                    });
+                }
+            }
+        }
+    }
+    merged.sort_by(|a, b| b.federated_score.total_cmp(&a.federated_score));
+    merged.truncate(limit);
+    Ok(Json(FederatedSearchResponse {
+        hits: merged,
+        errors,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FederatedNode {
+    origins: BTreeSet<String>,
+    #[serde(flatten)]
+    node: GraphNodeDto,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FederatedEdge {
+    origins: BTreeSet<String>,
+    #[serde(flatten)]
+    edge: GraphEdgeDto,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FederatedSubgraphResponse {
+    center: FederatedNode,
+    nodes: Vec<FederatedNode>,
+    edges: Vec<FederatedEdge>,
+    errors: Vec<OriginError>,
+}
+
+#[derive(Deserialize)]
+/// The part of a subgraph response merged across origins.
+struct OriginSubgraph {
+    center: GraphNodeDto,
+    nodes: Vec<GraphNodeDto>,
+    edges: Vec<GraphEdgeDto>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/federation/graph/subgraph",
+    tag = "graph",
+    params(GraphSubgraphQuery),
+    responses(
+        (status = 200, description = "Union of the neighbourhoods every origin holds around the start node", body = FederatedSubgraphResponse),
+        (status = 404, description = "No origin holds the start node", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn federated_subgraph(
+    State(state): State<AppState>,
+    Query(query): Query<GraphSubgraphQuery>,
+    RawQuery(raw_query): RawQuery,
+) -> ApiResult<Json<FederatedSubgraphResponse>> {
+    let raw_query = raw_query.unwrap_or_default();
+    let (local, remote) = tokio::join!(
+        load_subgraph(&state, &query),
+        state
+            .federation
+            .fan_out::<OriginSubgraph>("/graph/subgraph", &raw_query)
+    );
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    match local {
+        Ok(subgraph) => results.push((
+            LOCAL_ORIGIN.to_string(),
+            OriginSubgraph {
+                center: subgraph.center,
+                nodes: subgraph.nodes,
+                edges: subgraph.edges,
+            },
+        )),
+        // The start node may only be held by a remote.
+        Err(ApiError::NotFound(_)) => {}
+        Err(err @ ApiError::BadRequest(_)) => return Err(err),
+        Err(err) => errors.push(OriginError {
+            origin: LOCAL_ORIGIN.to_string(),
+            error: err.to_string(),
+        }),
+    }
+    for (origin, result) in remote {
+        match result {
+            Ok(Some(subgraph)) => results.push((origin, subgraph)),
+            Ok(None) => {}
+            Err(error) => errors.push(OriginError { origin, error }),
+        }
+    }
+
+    let mut center: Option<FederatedNode> = None;
+    let mut nodes: Vec<FederatedNode> = Vec::new();
+    let mut node_index: HashMap<String, usize> = HashMap::new();
+    let mut edges: Vec<FederatedEdge> = Vec::new();
+    let mut edge_index: HashMap<String, usize> = HashMap::new();
+    for (origin, subgraph) in results {
+        match &mut center {
+            Some(center) => {
+                center.origins.insert(origin.clone());
+            }
+            None => {
+                center = Some(FederatedNode {
+                    origins: BTreeSet::from([origin.clone()]),
+                    node: subgraph.center,
+                })
+            }
+        }
+        for node in subgraph.nodes {
+            match node_index.get(&node.id) {
+                Some(&index) => {
+                    nodes[index].origins.insert(origin.clone());
+                }
+                None => {
+                    node_index.insert(node.id.clone(), nodes.len());
+                    nodes.push(FederatedNode {
+                        origins: BTreeSet::from([origin.clone()]),
+                        node,
+                    });
+                }
+            }
+        }
+        for edge in subgraph.edges {
+            match edge_index.get(&edge.id) {
+                Some(&index) => {
+                    edges[index].origins.insert(origin.clone());
+                }
+                None => {
+                    edge_index.insert(edge.id.clone(), edges.len());
+                    edges.push(FederatedEdge {
+                        origins: BTreeSet::from([origin.clone()]),
+                        edge,
+                    });
+                }
+            }
+        }
+    }
+
+    let center = center.ok_or_else(|| {
+        ApiError::NotFound(format!(
+            "start node '{}' not found in any federated store",
+            query.start_id
+        ))
+    })?;
+    Ok(Json(FederatedSubgraphResponse {
+        center,
+        nodes,
+        edges,
+        errors,
+    }))
+}
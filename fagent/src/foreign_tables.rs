@@ -0,0 +1,116 @@
+//! Admin endpoints attaching external Delta tables as read-only foreign tables.
+//!
+//! An attached table is previewed and queried as `foreign/<name>` like any lake
+//! table, and `/api/query` statements can join it as `foreign_<name>`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use fstorage::{foreign, models::ForeignTable};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct AttachForeignTableRequest {
+    /// Lowercase letters, digits and `_`, starting with a letter.
+    name: String,
+    /// Delta table location: an absolute path or a URI.
+    uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ForeignTableDto {
+    name: String,
+    uri: String,
+    /// Lake path for previews and the `table` of `/api/query`.
+    path: String,
+    /// Name SQL run through `/api/query` can refer to it by.
+    sql_name: String,
+    attached_at: i64,
+}
+
+impl From<ForeignTable> for ForeignTableDto {
+    fn from(table: ForeignTable) -> Self {
+        Self {
+            path: foreign::table_path(&table.name),
+            sql_name: foreign::sql_name(&table.name),
+            name: table.name,
+            uri: table.uri,
+            attached_at: table.attached_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/foreign_tables",
+    tag = "storage",
+    responses((status = 200, body = [ForeignTableDto]))
+)]
+pub(crate) async fn list_foreign_tables(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<ForeignTableDto>>> {
+    let tables = state
+        .storage
+        .catalog
+        .list_foreign_tables()
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(
+        tables.into_iter().map(ForeignTableDto::from).collect(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/foreign_tables",
+    tag = "storage",
+    request_body = AttachForeignTableRequest,
+    responses(
+        (status = 200, description = "The table was attached, replacing any of the same name", body = ForeignTableDto),
+        (status = 400, description = "Invalid name, or no Delta table at the URI", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn attach_foreign_table(
+    State(state): State<AppState>,
+    Json(body): Json<AttachForeignTableRequest>,
+) -> ApiResult<Json<ForeignTableDto>> {
+    let table = state
+        .storage
+        .attach_foreign_table(&body.name, &body.uri)
+        .await
+        .map_err(ApiError::from_storage)?;
+    info!(foreign_table = %table.name, uri = %table.uri, "attached foreign table");
+    Ok(Json(table.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/foreign_tables/{name}",
+    tag = "storage",
+    params(("name" = String, Path)),
+    responses(
+        (status = 204, description = "The table was detached; its data is untouched"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn detach_foreign_table(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    let detached = state
+        .storage
+        .detach_foreign_table(&name)
+        .map_err(ApiError::from_storage)?;
+    if !detached {
+        return Err(ApiError::NotFound(format!(
+            "foreign table '{name}' not found"
+        )));
+    }
+    info!(foreign_table = %name, "detached foreign table");
+    Ok(StatusCode::NO_CONTENT)
+}
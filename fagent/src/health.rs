@@ -0,0 +1,48 @@
+//! Liveness and readiness probes for orchestrators.
+//!
+//! Both run real checks against the store rather than reporting that the process
+//! is up: `/healthz` needs the catalog and engine to answer reads, `/readyz`
+//! additionally needs the lake to accept writes. They are served without API
+//! keys so probes do not need credentials.
+
+use axum::{extract::State, http::StatusCode, Json};
+use fstorage::models::HealthReport;
+
+use crate::AppState;
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Catalog and engine answer reads", body = Object),
+        (status = 503, description = "A component check failed", body = Object)
+    )
+)]
+pub(crate) async fn healthz(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    respond(state.storage.health(false).await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Catalog and engine answer reads, the lake accepts writes", body = Object),
+        (status = 503, description = "A component check failed", body = Object)
+    )
+)]
+pub(crate) async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    respond(state.storage.health(true).await)
+}
+
+fn respond(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
@@ -0,0 +1,121 @@
+//! Review of the `same_as` links identity resolution draws between developers
+//! and the git identities their commits are authored under.
+//!
+//! `POST /api/identities/resolve` runs the resolution pass and returns every
+//! link it leaves in the graph. Suggestions are listed by
+//! `GET /api/identities/matches` and settled with
+//! `PUT /api/identities/matches/{developer_id}/{identity_id}`; verdicts stick
+//! across later runs.
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use fstorage::models::{IdentityMatch, IdentityMatchStatus, IdentityResolutionReport};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+#[derive(Deserialize, IntoParams)]
+pub(crate) struct MatchesQuery {
+    /// Only matches in this state: suggested, confirmed or rejected.
+    status: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct IdentityDecisionRequest {
+    /// `confirmed` or `rejected`.
+    status: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/identities/resolve",
+    tag = "graph",
+    responses(
+        (status = 200, description = "The same_as links in the graph after the run", body = Object)
+    )
+)]
+pub(crate) async fn resolve_identities(
+    State(state): State<AppState>,
+) -> ApiResult<Json<IdentityResolutionReport>> {
+    let report = state
+        .storage
+        .resolve_identities()
+        .await
+        .map_err(ApiError::from_storage)?;
+    info!(
+        matches = report.matches.len(),
+        stale_removed = report.stale_removed,
+        "identity resolution finished"
+    );
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/identities/matches",
+    tag = "graph",
+    params(MatchesQuery),
+    responses(
+        (status = 200, description = "Identity matches, best first, then rejected pairs", body = [Object]),
+        (status = 400, description = "Unknown status", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn list_identity_matches(
+    State(state): State<AppState>,
+    Query(query): Query<MatchesQuery>,
+) -> ApiResult<Json<Vec<IdentityMatch>>> {
+    let status = query
+        .status
+        .as_deref()
+        .map(str::parse::<IdentityMatchStatus>)
+        .transpose()
+        .map_err(ApiError::from_storage)?;
+    let mut matches = state
+        .storage
+        .identity_matches()
+        .await
+        .map_err(ApiError::from_storage)?;
+    if let Some(status) = status {
+        matches.retain(|link| link.status == status);
+    }
+    Ok(Json(matches))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/identities/matches/{developer_id}/{identity_id}",
+    tag = "graph",
+    params(
+        ("developer_id" = String, Path, description = "Id of the Developer node"),
+        ("identity_id" = String, Path, description = "Id of the GitIdentity node")
+    ),
+    request_body = IdentityDecisionRequest,
+    responses(
+        (status = 200, description = "The match as it stands after the decision", body = Object),
+        (status = 400, description = "Invalid id or status", body = ErrorResponse),
+        (status = 404, description = "No such developer or git identity", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn decide_identity_match(
+    State(state): State<AppState>,
+    Path((developer_id, identity_id)): Path<(String, String)>,
+    Json(body): Json<IdentityDecisionRequest>,
+) -> ApiResult<Json<IdentityMatch>> {
+    let status: IdentityMatchStatus = body.status.parse().map_err(ApiError::from_storage)?;
+    let link = state
+        .storage
+        .decide_identity_match(&developer_id, &identity_id, status)
+        .await
+        .map_err(ApiError::from_storage)?;
+    info!(
+        developer = %link.developer_id,
+        identity = %link.identity_id,
+        status = link.status.as_str(),
+        "identity match decided"
+    );
+    Ok(Json(link))
+}
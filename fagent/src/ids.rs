@@ -0,0 +1,102 @@
+//! Stable ids, for tracing a node or edge back to the keys it was derived from.
+//!
+//! `GET /api/debug/id` and `fagent id node|edge` compute an id the way sync
+//! does, with the string that was hashed. `POST /api/reports/id_collisions`
+//! and `fagent id audit` look for ids that distinct primary-key sets share; the
+//! command exits with an error when it finds any.
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use axum::{extract::Query, Json};
+use fstorage::{
+    ids,
+    models::{IdAuditReport, StableId},
+};
+
+use crate::{ApiError, ApiResult, ErrorResponse, IdArgs, IdCommand};
+
+#[utoipa::path(
+    get,
+    path = "/api/debug/id",
+    tag = "storage",
+    params(
+        ("entity_type" = Option<String>, Query, description = "Node type; every other parameter is one of its primary keys"),
+        ("edge" = Option<String>, Query, description = "Edge label, with the endpoint node ids as `from` and `to`")
+    ),
+    responses(
+        (status = 200, description = "The id and the string hashed into it", body = Object),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_stable_id(
+    Query(mut params): Query<BTreeMap<String, String>>,
+) -> ApiResult<Json<StableId>> {
+    if let Some(label) = params.remove("edge") {
+        let (Some(from), Some(to)) = (params.remove("from"), params.remove("to")) else {
+            return Err(ApiError::BadRequest(
+                "an edge id needs `from` and `to`".to_string(),
+            ));
+        };
+        if let Some(extra) = params.keys().next() {
+            return Err(ApiError::BadRequest(format!(
+                "unexpected parameter `{extra}` for an edge id"
+            )));
+        }
+        return Ok(Json(ids::edge_id(&label, &from, &to)));
+    }
+    let entity_type = params.remove("entity_type").ok_or_else(|| {
+        ApiError::BadRequest(
+            "pass `entity_type` with its primary keys, or `edge`, `from` and `to`".to_string(),
+        )
+    })?;
+    ids::node_id(&entity_type, &params)
+        .map(Json)
+        .map_err(ApiError::from_storage)
+}
+
+pub(crate) async fn run(args: IdArgs) -> anyhow::Result<()> {
+    match args.command {
+        IdCommand::Node { entity_type, keys } => {
+            let id = ids::node_id(&entity_type, &keys.into_iter().collect())?;
+            println!("{}\n  {}", id.id, id.name);
+        }
+        IdCommand::Edge { label, from, to } => {
+            let id = ids::edge_id(&label, &from, &to);
+            println!("{}\n  {}", id.id, id.name);
+        }
+        IdCommand::Audit { store, json } => {
+            let storage = store.open().await?;
+            let report = storage.audit_ids().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", render(&report));
+            }
+            if !report.collisions.is_empty() {
+                bail!("{} id collision(s) found", report.collisions.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render(report: &IdAuditReport) -> String {
+    let mut out = format!(
+        "{} index rows in {} tables, {} collisions\n",
+        report.rows,
+        report.tables,
+        report.collisions.len()
+    );
+    for collision in &report.collisions {
+        out += &format!("\n{} {}\n", collision.entity_type, collision.id);
+        for keys in &collision.key_sets {
+            let keys: Vec<String> = keys
+                .iter()
+                .map(|(key, value)| format!("{key}={}", value.as_deref().unwrap_or("<null>")))
+                .collect();
+            out += &format!("  {}\n", keys.join(" "));
+        }
+    }
+    out
+}
@@ -0,0 +1,798 @@
+//! Background execution of sync requests.
+//!
+//! `POST /api/sync` only records a job in the catalog and queues it; a small pool
+//! of workers drains the queue and writes the outcome back to the job row, so
+//! callers poll `/api/sync/jobs/{id}` instead of holding the request open.
+//! Queued jobs are started by priority, so an interactive sync never waits behind
+//! a backlog of scheduled refreshes (running jobs are not preempted). Failed or
+//! budget-stopped jobs can be resumed, and the synchronizer then continues from
+//! the checkpoint the previous attempt left behind. A sync that panics fails
+//! its job like any other error.
+//!
+//! Requests are stored with secrets in their parameters redacted; a job that is
+//! resumed, or requeued after a restart, runs with the redacted credential
+//! members removed, so its fetcher falls back to the credentials it is
+//! configured with.
+//!
+//! Phase progress and state changes are written to the job row and broadcast to
+//! `/api/sync/ws` subscribers.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use fstorage::{
+    catalog::Catalog,
+    errors::StorageError,
+    models::{PhaseStatus, SyncHistoryEntry, SyncJob, SyncJobState, SyncProgressEvent},
+    progress::SyncProgress,
+    redaction::strip_redacted_members,
+    FStorage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio::{
+    sync::{broadcast, Notify},
+    task::JoinSet,
+};
+use tracing::{info, instrument, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse, SyncBudgetPayload, SyncRequest};
+
+pub(crate) const DEFAULT_SYNC_WORKERS: usize = 2;
+const DEFAULT_JOB_LIST_LIMIT: usize = 50;
+const MAX_JOB_LIST_LIMIT: usize = 500;
+const RECOVERY_SCAN_LIMIT: usize = 10_000;
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Messages pushed to `/api/sync/ws` subscribers.
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum SyncJobEvent {
+    Progress {
+        job_id: i64,
+        #[serde(flatten)]
+        event: SyncProgressEvent,
+    },
+    State {
+        job_id: i64,
+        state: SyncJobState,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl SyncJobEvent {
+    fn job_id(&self) -> i64 {
+        match self {
+            SyncJobEvent::Progress { job_id, .. } | SyncJobEvent::State { job_id, .. } => *job_id,
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self, SyncJobEvent::State { state, .. } if state.is_terminal())
+    }
+}
+
+/// Least time between two progress writes of a running phase to its job row.
+const PROGRESS_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Persists the latest event on the job row and forwards every event to subscribers.
+struct JobProgress {
+    job_id: i64,
+    catalog: Arc<Catalog>,
+    events: broadcast::Sender<SyncJobEvent>,
+    last_write: Mutex<Option<Instant>>,
+}
+
+impl JobProgress {
+    fn due(&self, event: &SyncProgressEvent) -> bool {
+        let mut last_write = self.last_write.lock().unwrap();
+        let due = event.status != PhaseStatus::Running
+            || last_write.is_none_or(|at| at.elapsed() >= PROGRESS_WRITE_INTERVAL);
+        if due {
+            *last_write = Some(Instant::now());
+        }
+        due
+    }
+}
+
+impl SyncProgress for JobProgress {
+    fn report(&self, event: SyncProgressEvent) {
+        if self.due(&event) {
+            match serde_json::to_string(&event) {
+                Ok(progress) => {
+                    if let Err(err) = self
+                        .catalog
+                        .update_sync_job_progress(self.job_id, &progress)
+                    {
+                        warn!(
+                            "failed to update progress of sync job {}: {}",
+                            self.job_id, err
+                        );
+                    }
+                }
+                Err(err) => warn!("failed to encode sync progress: {}", err),
+            }
+        }
+        // Nobody listening is fine.
+        let _ = self.events.send(SyncJobEvent::Progress {
+            job_id: self.job_id,
+            event,
+        });
+    }
+}
+
+struct QueuedJob {
+    job_id: i64,
+    request: SyncRequest,
+}
+
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.request
+            .priority
+            .cmp(&other.request.priority)
+            // Job ids grow monotonically: the older job wins within a priority.
+            .then_with(|| other.job_id.cmp(&self.job_id))
+    }
+}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.job_id == other.job_id
+    }
+}
+
+impl Eq for QueuedJob {}
+
+pub(crate) struct SyncJobQueue {
+    storage: Arc<FStorage>,
+    pending: Mutex<BinaryHeap<QueuedJob>>,
+    notify: Notify,
+    events: broadcast::Sender<SyncJobEvent>,
+}
+
+impl SyncJobQueue {
+    /// Creates the queue, recovers jobs persisted by a previous process and spawns `workers`.
+    pub(crate) fn start(storage: Arc<FStorage>, workers: usize) -> Arc<Self> {
+        let queue = Self::recovered(storage);
+        for _ in 0..workers.max(1) {
+            let worker = Arc::clone(&queue);
+            tokio::spawn(async move { worker.run_worker().await });
+        }
+        queue
+    }
+
+    /// Creates the queue and recovers jobs persisted by a previous process, leaving
+    /// it to the caller to run workers with [`Self::run_worker`].
+    pub(crate) fn recovered(storage: Arc<FStorage>) -> Arc<Self> {
+        let queue = Self::idle(storage);
+        queue.recover();
+        queue
+    }
+
+    /// A queue without workers that leaves persisted jobs alone, for stores that
+    /// must not be written to.
+    pub(crate) fn idle(storage: Arc<FStorage>) -> Arc<Self> {
+        Arc::new(Self {
+            storage,
+            pending: Mutex::new(BinaryHeap::new()),
+            notify: Notify::new(),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        })
+    }
+
+    /// Persists `request` as a queued job and hands it to the workers.
+    pub(crate) fn submit(&self, request: SyncRequest) -> ApiResult<i64> {
+        let request_json = self.stored_request(&request)?;
+        let job_id = self
+            .storage
+            .catalog
+            .create_sync_job(&request.fetcher, &request_json)
+            .map_err(ApiError::from_storage)?;
+        self.enqueue(QueuedJob { job_id, request });
+        Ok(job_id)
+    }
+
+    /// Requeues a finished job under its original id.
+    fn resume(&self, job_id: i64, request: SyncRequest) -> ApiResult<()> {
+        let request_json = self.stored_request(&request)?;
+        self.storage
+            .catalog
+            .requeue_sync_job(job_id, &request_json)
+            .map_err(ApiError::from_storage)?;
+        self.enqueue(QueuedJob { job_id, request });
+        Ok(())
+    }
+
+    /// `request` as written to the job row, with secrets redacted.
+    fn stored_request(&self, request: &SyncRequest) -> ApiResult<String> {
+        let mut value =
+            serde_json::to_value(request).map_err(|err| ApiError::Internal(err.to_string()))?;
+        self.storage
+            .synchronizer
+            .redactor()
+            .redact_json(&mut value, &mut HashMap::new());
+        Ok(value.to_string())
+    }
+
+    fn enqueue(&self, job: QueuedJob) {
+        self.publish_state(job.job_id, SyncJobState::Queued, None);
+        self.pending.lock().unwrap().push(job);
+        self.notify.notify_one();
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<SyncJobEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish_state(&self, job_id: i64, state: SyncJobState, error: Option<String>) {
+        let _ = self.events.send(SyncJobEvent::State {
+            job_id,
+            state,
+            error,
+        });
+    }
+
+    /// Jobs and history entries still `running` died with the previous process;
+    /// `queued` jobs are picked up again.
+    fn recover(&self) {
+        let catalog = &self.storage.catalog;
+        match catalog.fail_interrupted_sync_jobs() {
+            Ok(0) => {}
+            Ok(count) => warn!("marked {} interrupted sync job(s) as failed", count),
+            Err(err) => warn!("failed to fail interrupted sync jobs: {}", err),
+        }
+        match catalog.fail_interrupted_sync_history() {
+            Ok(0) => {}
+            Ok(count) => warn!(
+                "marked {} interrupted sync(s) in the history as failed",
+                count
+            ),
+            Err(err) => warn!("failed to fail interrupted sync history: {}", err),
+        }
+
+        let queued = match catalog.list_sync_jobs(Some(SyncJobState::Queued), RECOVERY_SCAN_LIMIT) {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                warn!("failed to load queued sync jobs: {}", err);
+                return;
+            }
+        };
+        for job in queued {
+            match stored_request_to_rerun(&job.request) {
+                Ok(request) => {
+                    info!("requeueing sync job {}", job.job_id);
+                    self.enqueue(QueuedJob {
+                        job_id: job.job_id,
+                        request,
+                    });
+                }
+                Err(err) => {
+                    let message = format!("stored request is unreadable: {err}");
+                    if let Err(err) = catalog.finish_sync_job(
+                        job.job_id,
+                        SyncJobState::Failed,
+                        None,
+                        None,
+                        Some(&message),
+                    ) {
+                        warn!("failed to update sync job {}: {}", job.job_id, err);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn next_job(&self) -> QueuedJob {
+        loop {
+            let next = self.pending.lock().unwrap().pop();
+            if let Some(job) = next {
+                return job;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Runs queued jobs one at a time until the task is dropped.
+    pub(crate) async fn run_worker(self: Arc<Self>) {
+        loop {
+            let job = self.next_job().await;
+            let job_id = job.job_id;
+            // The job runs as a task of its own so that a panic fails the job
+            // rather than leaving it `running`, which would keep the schedule
+            // that submitted it from firing again. Dropping the set aborts it.
+            let mut run = JoinSet::new();
+            let queue = Arc::clone(&self);
+            run.spawn(async move { queue.run_job(job).await });
+            if let Some(Err(err)) = run.join_next().await {
+                if err.is_panic() {
+                    self.fail_panicked(job_id);
+                }
+            }
+        }
+    }
+
+    /// Queues the first of the syncs chained after `job_id`, which succeeded,
+    /// handing it the rest to queue in turn. They were admitted along with it.
+    fn queue_then(&self, job_id: i64, mut then: Vec<SyncRequest>) {
+        if then.is_empty() {
+            return;
+        }
+        let mut next = then.remove(0);
+        next.then.extend(then);
+        match self.submit(next) {
+            Ok(next_id) => info!("queued sync job {} after job {}", next_id, job_id),
+            Err(err) => warn!(
+                "failed to queue the sync chained after job {}: {}",
+                job_id, err
+            ),
+        }
+    }
+
+    /// Records that the run of `job_id` panicked.
+    fn fail_panicked(&self, job_id: i64) {
+        let error = "sync worker panicked while running the job".to_string();
+        warn!("sync job {} failed: {}", job_id, error);
+        if let Err(err) = self.storage.catalog.finish_sync_job(
+            job_id,
+            SyncJobState::Failed,
+            None,
+            None,
+            Some(&error),
+        ) {
+            warn!("failed to record outcome of sync job {}: {}", job_id, err);
+        }
+        self.publish_state(job_id, SyncJobState::Failed, Some(error));
+    }
+
+    #[instrument(name = "sync.job", skip_all, fields(job_id = job.job_id, fetcher = %job.request.fetcher))]
+    async fn run_job(&self, job: QueuedJob) {
+        let catalog = &self.storage.catalog;
+        let job_id = job.job_id;
+        if let Err(err) = catalog.mark_sync_job_running(job_id) {
+            warn!("failed to mark sync job {} as running: {}", job_id, err);
+        }
+        self.publish_state(job_id, SyncJobState::Running, None);
+
+        let progress = Arc::new(JobProgress {
+            job_id,
+            catalog: Arc::clone(catalog),
+            events: self.events.clone(),
+            last_write: Mutex::new(None),
+        });
+        let request = job.request;
+        let outcome = self
+            .storage
+            .synchronizer
+            .sync_with_progress(
+                &request.fetcher,
+                request.params.clone(),
+                request.sync_context(),
+                request.sync_budget(),
+                progress,
+            )
+            .await;
+
+        let (state, error, finished) = match outcome {
+            Ok(report) => {
+                if let Err(err) = self.storage.materialize_activity().await {
+                    warn!(
+                        "failed to refresh project activity after sync job {}: {}",
+                        job_id, err
+                    );
+                }
+                let result = serde_json::to_string(&report).ok();
+                let finished = catalog.finish_sync_job(
+                    job_id,
+                    SyncJobState::Succeeded,
+                    Some(report.task_id),
+                    result.as_deref(),
+                    None,
+                );
+                self.queue_then(job_id, request.then);
+                (SyncJobState::Succeeded, None, finished)
+            }
+            Err(err) => {
+                let state = match err {
+                    StorageError::BudgetExhausted(_) => SyncJobState::Stopped,
+                    _ => SyncJobState::Failed,
+                };
+                warn!("sync job {} {}: {}", job_id, state.as_str(), err);
+                let error = err.to_string();
+                let finished = catalog.finish_sync_job(job_id, state, None, None, Some(&error));
+                (state, Some(error), finished)
+            }
+        };
+        if let Err(err) = finished {
+            warn!("failed to record outcome of sync job {}: {}", job_id, err);
+        }
+        self.publish_state(job_id, state, error);
+    }
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SyncJobsQuery {
+    /// Only jobs in this state, e.g. `running` or `failed`.
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SyncHistoryQuery {
+    /// Only syncs of this fetcher.
+    #[serde(default)]
+    fetcher: Option<String>,
+    /// Only entries older than this id, to page through the history.
+    #[serde(default)]
+    before: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Default, Deserialize, ToSchema)]
+pub(crate) struct ResumeSyncJobRequest {
+    /// Replaces the budget of the original request, e.g. to grant more tokens.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    budget: Option<SyncBudgetPayload>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SyncJobDto {
+    job_id: i64,
+    fetcher: String,
+    #[schema(value_type = String, example = "succeeded")]
+    state: SyncJobState,
+    request: JsonValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_at: Option<i64>,
+}
+
+/// One sync run, whether started by a job, a schedule or a webhook.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SyncHistoryDto {
+    id: i64,
+    fetcher: String,
+    /// Digest of the sync parameters; repeated syncs of the same scope share it.
+    params_hash: String,
+    task_id: i64,
+    #[schema(value_type = String, example = "succeeded")]
+    state: SyncJobState,
+    started_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_at: Option<i64>,
+    /// Rows written to the lake per entity type.
+    rows: BTreeMap<String, u64>,
+    total_rows: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<SyncHistoryEntry> for SyncHistoryDto {
+    fn from(entry: SyncHistoryEntry) -> Self {
+        Self {
+            id: entry.id,
+            fetcher: entry.fetcher,
+            params_hash: entry.params_hash,
+            task_id: entry.task_id,
+            state: entry.state,
+            started_at: entry.started_at,
+            finished_at: entry.finished_at,
+            total_rows: entry.rows.values().sum(),
+            rows: entry.rows,
+            error: entry.error,
+        }
+    }
+}
+
+fn parse_json_column(raw: Option<&str>) -> Option<JsonValue> {
+    raw.and_then(|value| serde_json::from_str(value).ok())
+}
+
+impl From<SyncJob> for SyncJobDto {
+    fn from(job: SyncJob) -> Self {
+        Self {
+            job_id: job.job_id,
+            fetcher: job.fetcher,
+            state: job.state,
+            request: parse_json_column(Some(&job.request)).unwrap_or(JsonValue::Null),
+            progress: parse_json_column(job.progress.as_deref()),
+            task_id: job.task_id,
+            result: parse_json_column(job.result.as_deref()),
+            error: job.error,
+            created_at: job.created_at,
+            started_at: job.started_at,
+            finished_at: job.finished_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/jobs/{job_id}",
+    tag = "sync",
+    params(("job_id" = i64, Path)),
+    responses(
+        (status = 200, body = SyncJobDto),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_sync_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<i64>,
+) -> ApiResult<Json<SyncJobDto>> {
+    let job = state
+        .storage
+        .catalog
+        .get_sync_job(job_id)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("sync job {job_id} not found")))?;
+    Ok(Json(job.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/jobs",
+    tag = "sync",
+    params(SyncJobsQuery),
+    responses((status = 200, body = [SyncJobDto]))
+)]
+pub(crate) async fn list_sync_jobs(
+    State(state): State<AppState>,
+    Query(query): Query<SyncJobsQuery>,
+) -> ApiResult<Json<Vec<SyncJobDto>>> {
+    let job_state = query
+        .state
+        .as_deref()
+        .map(str::parse::<SyncJobState>)
+        .transpose()
+        .map_err(ApiError::from_storage)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_JOB_LIST_LIMIT)
+        .clamp(1, MAX_JOB_LIST_LIMIT);
+
+    let jobs = state
+        .storage
+        .catalog
+        .list_sync_jobs(job_state, limit)
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(jobs.into_iter().map(SyncJobDto::from).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sync/jobs/{job_id}/resume",
+    tag = "sync",
+    params(("job_id" = i64, Path)),
+    request_body(content = Option<ResumeSyncJobRequest>),
+    responses(
+        (status = 202, body = SyncJobDto),
+        (status = 400, description = "The job is not failed or stopped", body = ErrorResponse),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn resume_sync_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<i64>,
+    body: Option<Json<ResumeSyncJobRequest>>,
+) -> ApiResult<(StatusCode, Json<SyncJobDto>)> {
+    let catalog = &state.storage.catalog;
+    let job = catalog
+        .get_sync_job(job_id)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("sync job {job_id} not found")))?;
+    if !matches!(job.state, SyncJobState::Failed | SyncJobState::Stopped) {
+        return Err(ApiError::BadRequest(format!(
+            "sync job {job_id} is {} and cannot be resumed",
+            job.state.as_str()
+        )));
+    }
+
+    let mut request = stored_request_to_rerun(&job.request)
+        .map_err(|err| ApiError::Internal(format!("stored request is unreadable: {err}")))?;
+    let overrides = body.map(|Json(body)| body).unwrap_or_default();
+    if let Some(budget) = overrides.budget {
+        request.budget = Some(budget);
+    }
+    state.jobs.resume(job_id, request)?;
+
+    let job = catalog
+        .get_sync_job(job_id)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("sync job {job_id} not found")))?;
+    Ok((StatusCode::ACCEPTED, Json(job.into())))
+}
+
+/// Reads a request written by [`SyncJobQueue::stored_request`] for another run,
+/// dropping the credential members its redaction replaced.
+fn stored_request_to_rerun(stored: &str) -> serde_json::Result<SyncRequest> {
+    let mut value: JsonValue = serde_json::from_str(stored)?;
+    strip_redacted_members(&mut value);
+    serde_json::from_value(value)
+}
+
+#[derive(Clone, Deserialize)]
+pub(crate) struct SyncWsQuery {
+    /// Follow a single job; the socket closes once it finishes.
+    #[serde(default)]
+    job_id: Option<i64>,
+}
+
+pub(crate) async fn sync_progress_ws(
+    State(state): State<AppState>,
+    Query(query): Query<SyncWsQuery>,
+    ws: WebSocketUpgrade,
+) -> ApiResult<Response> {
+    // Subscribe before reading the current state so no transition falls in between.
+    let events = state.jobs.subscribe();
+    let current = match query.job_id {
+        Some(job_id) => Some(
+            state
+                .storage
+                .catalog
+                .get_sync_job(job_id)
+                .map_err(ApiError::from_storage)?
+                .ok_or_else(|| ApiError::NotFound(format!("sync job {job_id} not found")))?,
+        ),
+        None => None,
+    };
+    Ok(ws.on_upgrade(move |socket| stream_sync_events(socket, events, current)))
+}
+
+async fn send_event(socket: &mut WebSocket, event: &SyncJobEvent) -> bool {
+    match serde_json::to_string(event) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(err) => {
+            warn!("failed to encode sync event: {}", err);
+            true
+        }
+    }
+}
+
+async fn stream_sync_events(
+    mut socket: WebSocket,
+    mut events: broadcast::Receiver<SyncJobEvent>,
+    followed: Option<SyncJob>,
+) {
+    let job_id = followed.as_ref().map(|job| job.job_id);
+    if let Some(job) = followed {
+        let snapshot = SyncJobEvent::State {
+            job_id: job.job_id,
+            state: job.state,
+            error: job.error,
+        };
+        let finished = snapshot.is_terminal();
+        if !send_event(&mut socket, &snapshot).await {
+            return;
+        }
+        if let Some(event) = job
+            .progress
+            .as_deref()
+            .and_then(|raw| serde_json::from_str::<SyncProgressEvent>(raw).ok())
+        {
+            let progress = SyncJobEvent::Progress {
+                job_id: job.job_id,
+                event,
+            };
+            if !send_event(&mut socket, &progress).await {
+                return;
+            }
+        }
+        if finished {
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) => {
+                    if job_id.is_some_and(|id| id != event.job_id()) {
+                        continue;
+                    }
+                    if !send_event(&mut socket, &event).await {
+                        return;
+                    }
+                    if job_id.is_some() && event.is_terminal() {
+                        let _ = socket.send(Message::Close(None)).await;
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("sync progress subscriber lagged by {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/history",
+    tag = "sync",
+    params(SyncHistoryQuery),
+    responses((status = 200, description = "Sync runs, most recent first", body = [SyncHistoryDto]))
+)]
+pub(crate) async fn list_sync_history(
+    State(state): State<AppState>,
+    Query(query): Query<SyncHistoryQuery>,
+) -> ApiResult<Json<Vec<SyncHistoryDto>>> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_JOB_LIST_LIMIT)
+        .clamp(1, MAX_JOB_LIST_LIMIT);
+    let entries = state
+        .storage
+        .catalog
+        .list_sync_history(query.fetcher.as_deref(), query.before, limit)
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(
+        entries.into_iter().map(SyncHistoryDto::from).collect(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/history/{id}",
+    tag = "sync",
+    params(("id" = i64, Path)),
+    responses(
+        (status = 200, body = SyncHistoryDto),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_sync_history(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Json<SyncHistoryDto>> {
+    let entry = state
+        .storage
+        .catalog
+        .get_sync_history(id)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("sync history entry {id} not found")))?;
+    Ok(Json(entry.into()))
+}
@@ -0,0 +1,306 @@
+//! Server-side layout of subgraphs, for neighbourhoods of thousands of nodes
+//! that would stall the dashboard if it laid them out in the browser.
+//!
+//! Nodes are laid out in id order from fixed starting positions, so the same
+//! subgraph always gets the same coordinates and the endpoint can sit behind
+//! the response cache. The start node ends up at the origin.
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    load_subgraph, ApiError, ApiResult, AppState, ErrorResponse, GraphEdgeDto, GraphNodeDto,
+    GraphSubgraphQuery, GraphSubgraphResponse,
+};
+
+const DEFAULT_ITERATIONS: usize = 100;
+const MAX_ITERATIONS: usize = 500;
+/// Length edges settle at in force layouts, and the gap between neighbours
+/// within a layer of hierarchical ones.
+const NODE_SPACING: f64 = 60.0;
+const LAYER_SPACING: f64 = 120.0;
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LayoutAlgorithm {
+    /// Fruchterman–Reingold: linked nodes attract, nearby nodes repel.
+    #[default]
+    Force,
+    /// One row per hop from the start node, top to bottom.
+    Hierarchical,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct GraphLayoutQuery {
+    start_id: String,
+    #[serde(default)]
+    depth: Option<usize>,
+    #[serde(default)]
+    node_limit: Option<usize>,
+    #[serde(default)]
+    edge_limit: Option<usize>,
+    #[serde(default)]
+    edge_types: Option<String>,
+    #[serde(default)]
+    algorithm: LayoutAlgorithm,
+    /// Rounds of the force layout; defaults to 100, at most 500.
+    #[serde(default)]
+    iterations: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PositionedNode {
+    #[serde(flatten)]
+    node: GraphNodeDto,
+    x: f64,
+    y: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct GraphLayoutResponse {
+    algorithm: LayoutAlgorithm,
+    /// Id of the start node, placed at `(0, 0)`.
+    center: String,
+    nodes: Vec<PositionedNode>,
+    edges: Vec<GraphEdgeDto>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/layout",
+    tag = "graph",
+    params(GraphLayoutQuery),
+    responses(
+        (status = 200, description = "The subgraph with x/y coordinates for every node", body = GraphLayoutResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn graph_layout(
+    State(state): State<AppState>,
+    Query(query): Query<GraphLayoutQuery>,
+) -> ApiResult<Json<GraphLayoutResponse>> {
+    let iterations = query
+        .iterations
+        .unwrap_or(DEFAULT_ITERATIONS)
+        .min(MAX_ITERATIONS);
+    let algorithm = query.algorithm;
+    let subgraph = load_subgraph(
+        &state,
+        &GraphSubgraphQuery {
+            start_id: query.start_id,
+            depth: query.depth,
+            node_limit: query.node_limit,
+            edge_limit: query.edge_limit,
+            edge_types: query.edge_types,
+            stream: None,
+        },
+    )
+    .await?;
+
+    tokio::task::spawn_blocking(move || lay_out(subgraph, algorithm, iterations))
+        .await
+        .map(Json)
+        .map_err(|err| ApiError::Internal(err.to_string()))
+}
+
+fn lay_out(
+    subgraph: GraphSubgraphResponse,
+    algorithm: LayoutAlgorithm,
+    iterations: usize,
+) -> GraphLayoutResponse {
+    let GraphSubgraphResponse {
+        center,
+        mut nodes,
+        edges,
+    } = subgraph;
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let (root, links) = {
+        let index: HashMap<&str, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect();
+        let mut links: Vec<(usize, usize)> = edges
+            .iter()
+            .filter_map(|edge| {
+                Some((
+                    *index.get(edge.from.as_str())?,
+                    *index.get(edge.to.as_str())?,
+                ))
+            })
+            .filter(|(from, to)| from != to)
+            .map(|(from, to)| (from.min(to), from.max(to)))
+            .collect();
+        links.sort_unstable();
+        links.dedup();
+        (index.get(center.id.as_str()).copied().unwrap_or(0), links)
+    };
+
+    let positions = match algorithm {
+        LayoutAlgorithm::Force => force_layout(nodes.len(), &links, iterations),
+        LayoutAlgorithm::Hierarchical => hierarchical_layout(nodes.len(), &links, root),
+    };
+    let (origin_x, origin_y) = positions.get(root).copied().unwrap_or_default();
+
+    GraphLayoutResponse {
+        algorithm,
+        center: center.id,
+        nodes: nodes
+            .into_iter()
+            .zip(positions)
+            .map(|(node, (x, y))| PositionedNode {
+                node,
+                x: x - origin_x,
+                y: y - origin_y,
+            })
+            .collect(),
+        edges,
+    }
+}
+
+/// Fruchterman–Reingold with the grid approximation: repulsion only counts
+/// nodes within two edge lengths, which keeps each round close to linear.
+fn force_layout(count: usize, links: &[(usize, usize)], iterations: usize) -> Vec<(f64, f64)> {
+    let k = NODE_SPACING;
+    let cell = 2.0 * k;
+    // A sunflower spiral spreads the nodes evenly without any coinciding.
+    let mut positions: Vec<(f64, f64)> = (0..count)
+        .map(|i| {
+            let radius = k * (i as f64).sqrt();
+            let angle = i as f64 * GOLDEN_ANGLE;
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+    let mut temperature = k * (count as f64).sqrt() / 2.0;
+    let cooling = temperature / iterations.max(1) as f64;
+    let cell_of = |(x, y): (f64, f64)| ((x / cell).floor() as i64, (y / cell).floor() as i64);
+
+    for _ in 0..iterations {
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, &position) in positions.iter().enumerate() {
+            grid.entry(cell_of(position)).or_default().push(i);
+        }
+
+        let mut shifts = vec![(0.0, 0.0); count];
+        for (i, shift) in shifts.iter_mut().enumerate() {
+            let (x, y) = positions[i];
+            let (cell_x, cell_y) = cell_of((x, y));
+            for grid_x in cell_x - 1..=cell_x + 1 {
+                for grid_y in cell_y - 1..=cell_y + 1 {
+                    let Some(others) = grid.get(&(grid_x, grid_y)) else {
+                        continue;
+                    };
+                    for &j in others.iter().filter(|&&j| j != i) {
+                        let (dx, dy) = (x - positions[j].0, y - positions[j].1);
+                        let distance_sq = (dx * dx + dy * dy).max(0.01);
+                        if distance_sq > cell * cell {
+                            continue;
+                        }
+                        // k²/d along the unit vector from j to i.
+                        let force = k * k / distance_sq;
+                        shift.0 += dx * force;
+                        shift.1 += dy * force;
+                    }
+                }
+            }
+        }
+        for &(a, b) in links {
+            let (dx, dy) = (
+                positions[a].0 - positions[b].0,
+                positions[a].1 - positions[b].1,
+            );
+            // d²/k along the unit vector between the ends.
+            let force = (dx * dx + dy * dy).sqrt() / k;
+            shifts[a].0 -= dx * force;
+            shifts[a].1 -= dy * force;
+            shifts[b].0 += dx * force;
+            shifts[b].1 += dy * force;
+        }
+
+        for (position, (shift_x, shift_y)) in positions.iter_mut().zip(shifts) {
+            let length = (shift_x * shift_x + shift_y * shift_y).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature);
+                position.0 += shift_x / length * step;
+                position.1 += shift_y / length * step;
+            }
+        }
+        temperature -= cooling;
+    }
+    positions
+}
+
+/// Rows by hop distance from `root`. Each row is ordered by where its
+/// neighbours in the row above sit, which keeps most edges short and
+/// uncrossed. Nodes the kept edges don't reach go in a final row.
+fn hierarchical_layout(count: usize, links: &[(usize, usize)], root: usize) -> Vec<(f64, f64)> {
+    let mut neighbours = vec![Vec::new(); count];
+    for &(a, b) in links {
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+    }
+
+    let mut depths = vec![usize::MAX; count];
+    let mut queue = VecDeque::new();
+    if root < count {
+        depths[root] = 0;
+        queue.push_back(root);
+    }
+    while let Some(node) = queue.pop_front() {
+        for &next in &neighbours[node] {
+            if depths[next] == usize::MAX {
+                depths[next] = depths[node] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    let unreached = depths
+        .iter()
+        .filter(|&&depth| depth != usize::MAX)
+        .max()
+        .map_or(0, |deepest| deepest + 1);
+    for depth in depths.iter_mut().filter(|depth| **depth == usize::MAX) {
+        *depth = unreached;
+    }
+
+    let mut rows = vec![Vec::new(); unreached + 1];
+    for (node, &depth) in depths.iter().enumerate() {
+        rows[depth].push(node);
+    }
+
+    let mut positions = vec![(0.0, 0.0); count];
+    for (depth, row) in rows.iter().enumerate() {
+        let key = |node: usize| {
+            let above: Vec<f64> = neighbours[node]
+                .iter()
+                .filter(|&&other| depths[other] + 1 == depth)
+                .map(|&other| positions[other].0)
+                .collect();
+            if above.is_empty() {
+                f64::MAX
+            } else {
+                above.iter().sum::<f64>() / above.len() as f64
+            }
+        };
+        let mut keyed: Vec<(f64, usize)> = row.iter().map(|&node| (key(node), node)).collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let offset = (keyed.len() as f64 - 1.0) / 2.0;
+        for (slot, &(_, node)) in keyed.iter().enumerate() {
+            positions[node] = (
+                (slot as f64 - offset) * NODE_SPACING,
+                depth as f64 * LAYER_SPACING,
+            );
+        }
+    }
+    positions
+}
@@ -1,29 +1,95 @@
+mod activity;
+mod admin;
+mod archives;
+mod auth;
+mod bookmarks;
+mod caching;
+mod compare;
+mod config_file;
+mod cors;
+mod daemon;
+mod datasets;
+mod export;
+mod federation;
+mod foreign_tables;
+mod health;
+mod identities;
+mod ids;
+mod jobs;
+mod layout;
+mod lsp;
+mod maintenance;
+mod ndjson;
+mod onboard;
+mod openapi;
+mod pagination;
+mod privacy;
+mod query;
+mod ratelimit;
+mod readiness;
+mod readme;
+mod related;
+mod repl;
+mod reports;
+mod response_cache;
+mod saved_searches;
+mod schedules;
+mod search_cli;
+mod snapshots;
+mod sql_cli;
+mod status;
+mod structure;
+mod telemetry;
+#[cfg(feature = "ui")]
+mod ui;
+mod ui_dir;
+#[cfg(unix)]
+mod unix_socket;
+mod verify;
+mod versioning;
+mod webhooks;
+mod workspaces;
+
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
     net::SocketAddr,
-    path::PathBuf,
+    ops::Bound,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::Context;
 use axum::{
-    body::Body,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{any, delete, get, post, put},
     Json, Router,
 };
-use clap::{Args, Parser, Subcommand};
-use fstorage::sync::DataSynchronizer;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use fstorage::{
     config::StorageConfig,
+    embedding::{EmbeddingModelConfig, OnnxConfig, OnnxDevice, OnnxPooling},
+    engine_map::{EngineConfig, EngineSyncMode},
     errors::StorageError,
-    fetch::{EntityCategory, FetcherCapability},
+    fetch::{EntityCategory, Fetchable, FetcherCapability},
+    import::EmbeddingImport,
+    lake::{IndexPosition, NeighborDirection, NeighborEdgeOrientation},
+    lake_store::LakeStoreConfig,
     models::{
-        EntityIdentifier, MultiEntitySearchHit, ReadinessReport, SyncBudget, SyncContext,
-        TableSummary,
+        BudgetLimits, EdgeTypeSummary, EmbeddingImportReport, EntityIdentifier, GraphDiff,
+        GraphStatistics, MultiEntitySearchHit, NodeDeletion, SyncBudget, SyncContext, SyncJobState,
+        SyncRun, TableSummary, TextSearchHit, VectorSearchHit,
     },
+    redaction::RedactionRule,
+    rerank::RerankerConfig,
+    schemas::generated_schemas::NodeEmbedding,
+    sql_engine::SqlEngine,
+    table_format::{IcebergConfig, TableFormat},
+    throttle::ProviderLimits,
     FStorage,
 };
 use helix_db::helix_engine::storage_core::graph_visualization::GraphVisualization;
@@ -31,27 +97,75 @@ use helix_db::helix_engine::types::GraphError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
 use tokio::signal;
-use tracing::{error, info};
-use tracing_subscriber::{fmt, EnvFilter};
+use tower::{Layer, ServiceBuilder};
+use tower_http::compression::CompressionLayer;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::ndjson::StreamFormat;
+use crate::pagination::GraphCursor;
+
+pub use crate::auth::Role;
+pub use crate::cors::CorsOrigins;
+pub use crate::lsp::serve_lsp;
+pub use crate::ratelimit::RateLimit;
 
 /// Runs the command line interface for the fagent dashboard.
 pub async fn run_cli() -> anyhow::Result<()> {
-    init_tracing();
-
-    let cli = Cli::parse();
-    match cli.command {
-        Some(Command::Dashboard(args)) => run_dashboard(args).await?,
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    match (&mut cli.command, matches.subcommand()) {
+        (Some(Command::Dashboard(args)), Some(("dashboard", dashboard))) => {
+            config_file::apply(args, dashboard)?;
+        }
+        (Some(Command::Daemon(args)), Some(("daemon", daemon))) => {
+            config_file::apply(&mut args.dashboard, daemon)?;
+        }
+        (Some(command), Some((_, sub_matches))) => {
+            if let Some((store, store_matches)) = command.store(sub_matches) {
+                config_file::apply_store(store, store_matches)?;
+            }
+        }
+        _ => {}
+    }
+    let telemetry = telemetry::Telemetry::init(cli.otlp_endpoint.as_deref())?;
+
+    let outcome = match cli.command {
+        Some(Command::Dashboard(args)) => run_dashboard(args).await,
+        Some(Command::Daemon(args)) => daemon::run(args).await,
+        Some(Command::DiffSnapshots(args)) => snapshots::run_diff(args).await,
+        Some(Command::ExportProject(args)) => datasets::run_export(args).await,
+        Some(Command::ImportProject(args)) => datasets::run_import(args).await,
+        Some(Command::Lsp(args)) => lsp::run(args).await,
+        Some(Command::VerifyLake(args)) => verify::run(args).await,
+        Some(Command::RepairTable(args)) => verify::run_repair(args).await,
+        Some(Command::Validate(args)) => verify::run_validate(args).await,
+        Some(Command::Query(args)) => sql_cli::run(args).await,
+        Some(Command::Search(args)) => search_cli::run(args).await,
+        Some(Command::Export(args)) => archives::run_export(args).await,
+        Some(Command::Import(args)) => archives::run_import(args).await,
+        Some(Command::Repl(args)) => repl::run(args).await,
+        Some(Command::Status(args)) => status::run(args).await,
+        Some(Command::Id(args)) => ids::run(args).await,
+        Some(Command::Maintenance(args)) => maintenance::run(args).await,
+        Some(Command::EmbedStructure(args)) => structure::run(args).await,
         None => {
             println!("No subcommand provided. Use --help to see available commands.");
+            Ok(())
         }
-    }
+    };
 
-    Ok(())
+    telemetry.shutdown();
+    outcome
 }
 
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
+    /// OTLP/gRPC collector to export traces to, e.g. http://localhost:4317
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -60,50 +174,1011 @@ struct Cli {
 enum Command {
     /// Starts the fagent dashboard HTTP service
     Dashboard(DashboardArgs),
+    /// Runs the HTTP API, sync workers, cron scheduler and webhook receiver
+    /// as one long-running process that restarts whichever part fails
+    Daemon(DaemonArgs),
+    /// Compares two named snapshots table by table
+    DiffSnapshots(DiffSnapshotsArgs),
+    /// Writes one project's nodes, edges and vectors to a Parquet dataset
+    ExportProject(ExportProjectArgs),
+    /// Loads a dataset written by export-project into this store
+    ImportProject(ImportProjectArgs),
+    /// Serves the stored code graph to editors as a language server on stdin/stdout
+    Lsp(LspArgs),
+    /// Checks every lake data file against its recorded checksum and decodes it
+    VerifyLake(VerifyLakeArgs),
+    /// Quarantines malformed Delta log entries of a table and rebuilds its checkpoint
+    RepairTable(RepairTableArgs),
+    /// Cross-checks catalog offsets, node indexes, edges and vectors against the
+    /// lake tables and the graph engine
+    Validate(ValidateArgs),
+    /// Runs a read-only SQL statement against the lake tables and prints the rows
+    Query(QueryArgs),
+    /// Runs a hybrid text and vector search and prints the hits
+    Search(SearchArgs),
+    /// Packs the catalog, lake and graph engine into one archive file
+    Export(ExportArgs),
+    /// Restores an archive written by export into an empty directory
+    Import(ImportArgs),
+    /// Opens an interactive shell for SQL, search and graph lookups
+    Repl(ReplArgs),
+    /// Prints store statistics, ingestion offsets and disk usage per lake table
+    Status(StatusArgs),
+    /// Computes stable node and edge ids, or audits the store for colliding ones
+    Id(IdArgs),
+    /// Compacts and vacuums the lake tables; with --dry-run only reports what it would reclaim
+    Maintenance(MaintenanceArgs),
+    /// Trains node2vec embeddings over the graph for structural similarity search
+    EmbedStructure(EmbedStructureArgs),
+}
+
+impl Command {
+    /// The store settings of a subcommand that opens a store without serving
+    /// it, with the matches they were parsed from.
+    fn store<'a>(
+        &'a mut self,
+        matches: &'a ArgMatches,
+    ) -> Option<(&'a mut StoreArgs, &'a ArgMatches)> {
+        let store = match self {
+            Command::Dashboard(_) | Command::Daemon(_) => return None,
+            Command::Id(IdArgs {
+                command: IdCommand::Audit { store, .. },
+            }) => return Some((store, matches.subcommand()?.1)),
+            Command::Id(_) => return None,
+            Command::DiffSnapshots(args) => &mut args.store,
+            Command::ExportProject(args) => &mut args.store,
+            Command::ImportProject(args) => &mut args.store,
+            Command::Lsp(args) => &mut args.store,
+            Command::VerifyLake(args) => &mut args.store,
+            Command::RepairTable(args) => &mut args.store,
+            Command::Validate(args) => &mut args.store,
+            Command::Query(args) => &mut args.store,
+            Command::Search(args) => &mut args.store,
+            Command::Export(args) => &mut args.store,
+            Command::Import(args) => &mut args.store,
+            Command::Repl(args) => &mut args.store,
+            Command::Status(args) => &mut args.store,
+            Command::Maintenance(args) => &mut args.store,
+            Command::EmbedStructure(args) => &mut args.store,
+        };
+        Some((store, matches))
+    }
+}
+
+#[derive(Args)]
+struct LspArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+}
+
+#[derive(Args)]
+struct VerifyLakeArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct RepairTableArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Lake path of the table, e.g. silver/entities/project
+    table: String,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Rewind offsets past their table, reload nodes the engine lost, and
+    /// delete dangling edges and orphan vectors
+    #[arg(long, default_value_t = false)]
+    repair: bool,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// DataFusion SQL; with --table, `{{table}}` names that table
+    sql: String,
+    /// Lake path of the table to query, e.g. silver/entities/project. Without
+    /// it every table is registered under its path with `/` replaced by `_`,
+    /// e.g. silver_entities_project
+    #[arg(long)]
+    table: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value = "table")]
+    format: QueryOutput,
+    /// Most rows to print
+    #[arg(long, default_value_t = 1000)]
+    limit: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum QueryOutput {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Text to search for
+    query: String,
+    /// Entity type to search; repeat for several. Every ingested node and
+    /// vector type when omitted
+    #[arg(long = "entity-type")]
+    entity_types: Vec<String>,
+    /// Weight of the BM25 score against vector similarity, from 0 to 1
+    #[arg(long, default_value_t = 0.5)]
+    alpha: f32,
+    /// Most hits to print
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    /// Also print the graph context of the hits: callers, callees,
+    /// containing files and sibling functions
+    #[arg(long, default_value_t = false)]
+    graph_expand: bool,
+    /// Print the hits as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Archive file to write; must not exist yet
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// Settings of the restored store; its --base-path must be missing or empty
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Archive written by `fagent export`
+    archive: PathBuf,
+}
+
+#[derive(Args)]
+struct ReplArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Most rows to print per SQL statement
+    #[arg(long, default_value_t = 100)]
+    limit: usize,
 }
 
 #[derive(Args)]
+struct StatusArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct MaintenanceArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Report what would be compacted and reclaimed without changing anything
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Keep files that versions from the last this many hours refer to
+    #[arg(long, default_value_t = 168)]
+    retention_hours: u64,
+    /// Compact data files smaller than this many MiB into files of about this size
+    #[arg(long, default_value_t = 128)]
+    target_file_size_mb: u64,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct EmbedStructureArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Node type to store embeddings for; repeat for several (project, class and function by default)
+    #[arg(long = "entity-type")]
+    entity_types: Vec<String>,
+    /// Length of each embedding
+    #[arg(long, default_value_t = 64)]
+    dimensions: usize,
+    /// Random walks started from every node per epoch
+    #[arg(long, default_value_t = 10)]
+    walks_per_node: usize,
+    /// Nodes visited by each walk
+    #[arg(long, default_value_t = 40)]
+    walk_length: usize,
+    /// Walk positions on either side that count as a node's context
+    #[arg(long, default_value_t = 5)]
+    window: usize,
+    /// Random non-neighbours each context node is contrasted with
+    #[arg(long, default_value_t = 5)]
+    negative_samples: usize,
+    /// Return parameter p; higher values make walks less likely to step back
+    #[arg(short = 'p', long, default_value_t = 1.0)]
+    return_param: f64,
+    /// In-out parameter q; below 1 walks explore outward, above 1 they stay local
+    #[arg(short = 'q', long, default_value_t = 1.0)]
+    in_out_param: f64,
+    /// Rounds of walks over the whole graph
+    #[arg(long, default_value_t = 1)]
+    epochs: usize,
+    /// Seed of the walks and initial weights, for reproducible runs
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct IdArgs {
+    #[command(subcommand)]
+    command: IdCommand,
+}
+
+#[derive(Subcommand)]
+enum IdCommand {
+    /// Prints the id sync gives a node, e.g. `fagent id node project --key url=https://...`
+    Node {
+        /// Node type, e.g. project
+        entity_type: String,
+        /// A primary key as key=value; repeat for each key of the type
+        #[arg(long = "key", value_parser = parse_id_key)]
+        keys: Vec<(String, String)>,
+    },
+    /// Prints the id sync gives an edge
+    Edge {
+        /// Edge label
+        label: String,
+        /// Id of the source node
+        from: String,
+        /// Id of the target node
+        to: String,
+    },
+    /// Lists node ids that distinct primary-key sets share
+    Audit {
+        #[command(flatten)]
+        store: StoreArgs,
+        /// Print the report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+struct ExportProjectArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// URL of the project to export
+    #[arg(long)]
+    project: String,
+    /// Directory to write the dataset to; must be missing or empty
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(Args)]
+struct ImportProjectArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Directory holding the dataset's manifest.json
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+struct DiffSnapshotsArgs {
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Snapshot to compare from
+    a: String,
+    /// Snapshot to compare to
+    b: String,
+    /// Changed nodes to show per entity table
+    #[arg(long, default_value_t = 5)]
+    sample: usize,
+    /// Print the report as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Args)]
+// The GitFetcher options are only read when it is compiled in.
+#[cfg_attr(not(feature = "fetchers"), allow(dead_code))]
 struct DashboardArgs {
-    /// Base directory for fstorage lake/catalog/engine data
-    #[arg(long, env = "FSTORAGE_BASE_PATH")]
-    base_path: PathBuf,
-    /// Socket address to bind the dashboard service
+    #[command(flatten)]
+    store: StoreArgs,
+    /// Socket address to bind the dashboard service, or `unix:<path>` for a
+    /// Unix domain socket
     #[arg(long, default_value = "127.0.0.1:3000")]
     bind: String,
+    /// Another store to serve under `/api/{name}/...`, as `name=path` (repeatable)
+    #[arg(long = "workspace", value_parser = parse_workspace)]
+    workspaces: Vec<(String, PathBuf)>,
+    /// Another fagent dashboard queried by the `/api/federation/...` routes,
+    /// as `name=url` (repeatable)
+    #[arg(long = "federate", value_parser = parse_federated_remote)]
+    federated_remotes: Vec<(String, String)>,
+    /// API key sent to a federated remote, as `name=key` (repeatable)
+    #[arg(long = "federate-api-key", value_parser = parse_federated_api_key)]
+    federated_api_keys: Vec<(String, String)>,
     /// Optional GitHub token for GitFetcher
     #[arg(long, env = "GITHUB_TOKEN")]
     github_token: Option<String>,
     /// Disable registering GitFetcher
     #[arg(long, default_value_t = false)]
     disable_gitfetcher: bool,
+    /// Per-1k-token USD price override, as `provider[:model]=price` (repeatable)
+    #[arg(long = "token-price", value_parser = parse_token_price)]
+    token_prices: Vec<(String, f64)>,
+    /// Embedding provider quota, as `provider[:model]=RPM/TPM`; leave a side empty
+    /// to not limit it, e.g. `openai=3000/1000000` or `openai=/150000` (repeatable)
+    #[arg(long = "embedding-limit", value_parser = parse_embedding_limit)]
+    embedding_limits: Vec<(String, ProviderLimits)>,
+    /// Number of sync jobs executed concurrently
+    #[arg(long, default_value_t = jobs::DEFAULT_SYNC_WORKERS)]
+    sync_workers: usize,
+    /// Only sync repositories matching this `owner/repo` glob (repeatable)
+    #[arg(long = "repo-allow")]
+    repo_allow: Vec<String>,
+    /// Never sync repositories matching this `owner/repo` glob (repeatable)
+    #[arg(long = "repo-deny")]
+    repo_deny: Vec<String>,
+    /// Refuse repositories larger than this many KiB
+    #[arg(long)]
+    max_repo_size_kb: Option<u64>,
+    /// Only sync repositories under this SPDX license id (repeatable)
+    #[arg(long = "repo-license")]
+    repo_licenses: Vec<String>,
+    /// Directory code checkouts are created in (defaults to the system temp dir)
+    #[arg(long, env = "FAGENT_WORKSPACE_DIR")]
+    workspace_dir: Option<PathBuf>,
+    /// Abort a checkout once clone data and working tree exceed this many MiB
+    #[arg(long)]
+    workspace_max_mb: Option<u64>,
+    /// Abort cloning a repository after this many seconds
+    #[arg(long)]
+    clone_timeout_secs: Option<u64>,
+    /// Abort language detection and AST parsing after this many seconds
+    #[arg(long)]
+    parse_timeout_secs: Option<u64>,
+    /// Directory SCIP/LSIF indexes named by a snapshot's `code_index` are read from
+    #[arg(long, env = "FAGENT_CODE_INDEX_DIR")]
+    code_index_dir: Option<PathBuf>,
+    /// Directory lcov/Cobertura reports named by a snapshot's `coverage` are read from
+    #[arg(long, env = "FAGENT_COVERAGE_DIR")]
+    coverage_dir: Option<PathBuf>,
+    /// Directory benchmark results named by a snapshot's `benchmarks` are read from
+    #[arg(long, env = "FAGENT_BENCHMARK_DIR")]
+    benchmark_dir: Option<PathBuf>,
+    /// Command prefix to run `git clone` under, e.g. "systemd-run --user --scope -p MemoryMax=1G"
+    #[arg(long)]
+    clone_sandbox: Option<String>,
+    /// Extra secret pattern to redact from ingested text, as `name=regex` (repeatable)
+    #[arg(long = "redact", value_parser = parse_redaction_rule)]
+    redaction_rules: Vec<RedactionRule>,
+    /// Store ingested text without secret redaction
+    #[arg(long, default_value_t = false)]
+    disable_redaction: bool,
+    /// API key and the role it grants, as `key=viewer|operator|admin` (repeatable).
+    /// Without any, the API is open to every caller
+    #[arg(long = "api-key", env = "FAGENT_API_KEYS", value_delimiter = ',', value_parser = parse_api_key)]
+    api_keys: Vec<(String, Role)>,
+    /// Origins browser frontends may call the API from, e.g. `https://app.example.com`
+    /// (comma separated; `*` allows any origin)
+    #[arg(long, env = "FAGENT_CORS_ORIGINS", value_delimiter = ',')]
+    cors_origins: Vec<String>,
+    /// Date (YYYY-MM-DD, UTC) from which unversioned `/api/...` paths answer 410 Gone
+    /// instead of redirecting clients to `/api/v1` via deprecation headers
+    #[arg(long)]
+    legacy_api_sunset: Option<NaiveDate>,
+    /// How developer names and emails are stored: keep, hash or omit
+    #[arg(long, value_enum, default_value_t = DeveloperPrivacyMode::Keep)]
+    developer_privacy: DeveloperPrivacyMode,
+    /// Salt mixed into hashed developer names and emails; required with
+    /// `--developer-privacy hash`
+    #[arg(long, env = "FAGENT_PRIVACY_SALT", default_value = "")]
+    privacy_salt: String,
+    /// Syncs and searches each API key (or IP, without a key) may make per minute
+    #[arg(long)]
+    rate_limit_per_minute: Option<u32>,
+    /// Requests a caller may make back to back before --rate-limit-per-minute applies
+    #[arg(long, requires = "rate_limit_per_minute")]
+    rate_limit_burst: Option<u32>,
+    /// Overview and statistics responses kept in memory between syncs (0 disables)
+    #[arg(long, default_value_t = response_cache::DEFAULT_CACHE_ENTRIES)]
+    response_cache_entries: usize,
+    /// Secret GitHub signs webhook deliveries with; enables `/api/webhooks/github`
+    #[arg(long, env = "FAGENT_GITHUB_WEBHOOK_SECRET")]
+    github_webhook_secret: Option<String>,
+    /// Directory whose files replace the built-in dashboard UI's, for UI work
+    /// and branding without a rebuild
+    #[arg(long, env = "FAGENT_UI_DIR")]
+    ui_dir: Option<PathBuf>,
+    /// Sentence-transformer ONNX model to embed with instead of OpenAI/FastEmbed
+    #[arg(long, env = "FAGENT_ONNX_MODEL")]
+    onnx_model: Option<PathBuf>,
+    /// Tokenizer for --onnx-model (defaults to tokenizer.json next to the model)
+    #[arg(long, requires = "onnx_model")]
+    onnx_tokenizer: Option<PathBuf>,
+    /// How --onnx-model token embeddings are pooled
+    #[arg(long, value_enum, default_value_t = OnnxPoolingMode::Mean)]
+    onnx_pooling: OnnxPoolingMode,
+    /// Device --onnx-model runs on: cpu, cuda[:id] or directml[:id]
+    #[arg(long, default_value = "cpu", value_parser = str::parse::<OnnxDevice>)]
+    onnx_device: OnnxDevice,
+    /// Keep --onnx-model vectors unnormalized
+    #[arg(long, default_value_t = false)]
+    onnx_no_normalize: bool,
+    /// Cross-encoder for `rerank=true` searches: fastembed[:model] or a rerank endpoint URL
+    #[arg(long, env = "FAGENT_RERANKER", value_parser = str::parse::<RerankerConfig>)]
+    reranker: Option<RerankerConfig>,
+    /// Bearer token sent to a --reranker endpoint
+    #[arg(long, env = "FAGENT_RERANKER_API_KEY", requires = "reranker")]
+    reranker_api_key: Option<String>,
+    /// Model name sent to a --reranker endpoint
+    #[arg(long, requires = "reranker")]
+    reranker_model: Option<String>,
+    /// Extra embedding model hybrid searches may select, as openai:<model> or fastembed:<model>
+    #[arg(long = "embedding-model", value_parser = str::parse::<EmbeddingModelConfig>)]
+    embedding_models: Vec<EmbeddingModelConfig>,
+    /// Sync schedules declared in the --config file
+    #[arg(skip)]
+    schedules: Vec<schedules::ScheduleRequest>,
+}
+
+/// Where a store lives and how its tables and graph engine are kept, for
+/// every subcommand that opens one.
+#[derive(Args)]
+struct StoreArgs {
+    /// TOML file with dashboard settings; flags and environment variables
+    /// that are given override it
+    #[arg(long, env = "FAGENT_CONFIG")]
+    config: Option<PathBuf>,
+    /// Base directory for fstorage lake/catalog/engine data
+    #[arg(long, env = "FSTORAGE_BASE_PATH", required_unless_present = "config")]
+    base_path: Option<PathBuf>,
+    /// Iceberg REST catalog URI to keep the lake tables in instead of Delta
+    /// (needs a build with the `iceberg` feature)
+    #[arg(long, env = "FAGENT_ICEBERG_CATALOG")]
+    iceberg_catalog: Option<String>,
+    /// Warehouse to request from the --iceberg-catalog
+    #[arg(long, requires = "iceberg_catalog")]
+    iceberg_warehouse: Option<String>,
+    /// Namespace of the lake tables in the --iceberg-catalog; workspaces use `<namespace>_<workspace>`
+    #[arg(long, default_value = "fagent", requires = "iceberg_catalog")]
+    iceberg_namespace: String,
+    /// Object store URI to keep the Delta lake tables in instead of the lake directory, e.g.
+    /// s3://<bucket>/<prefix>, gs://... or az://<container>/... (needs a build with the `s3`,
+    /// `gcs` or `azure` feature); workspaces use `<uri>_<workspace>`
+    #[arg(long, env = "FAGENT_LAKE_URI", conflicts_with = "iceberg_catalog")]
+    lake_uri: Option<String>,
+    /// Setting of the --lake-uri store as `key=value`, e.g. `aws_region=eu-west-1`; credentials
+    /// left out are read from the provider's usual environment variables (repeatable)
+    #[arg(long = "lake-store-option", value_parser = parse_lake_store_option, requires = "lake_uri")]
+    lake_store_options: Vec<(String, String)>,
+    /// Engine for `/api/query` SQL: datafusion, or duckdb (needs a build with the `duckdb` feature)
+    #[arg(long, env = "FAGENT_SQL_ENGINE", default_value = "datafusion", value_parser = str::parse::<SqlEngine>)]
+    sql_engine: SqlEngine,
+    /// Graph engine map size in bytes to open with
+    #[arg(long, env = "FAGENT_ENGINE_MAP_SIZE")]
+    engine_map_size: Option<u64>,
+    /// Largest size in bytes the graph engine map may grow to when writes fill it
+    #[arg(long, env = "FAGENT_ENGINE_MAP_CEILING")]
+    engine_map_ceiling: Option<u64>,
+    /// Flushing of graph engine commits: full, no_meta_sync, no_sync or map_async
+    #[arg(long, env = "FAGENT_ENGINE_SYNC_MODE", default_value = "full", value_parser = str::parse::<EngineSyncMode>)]
+    engine_sync_mode: EngineSyncMode,
+    /// Cap on graph engine read transactions open at once, further reads waiting for a slot;
+    /// it can only lower the engine's fixed reader table, not raise it
+    #[arg(long, env = "FAGENT_ENGINE_MAX_READERS")]
+    engine_max_readers: Option<u32>,
+    /// Graph reads run on blocking threads at once; further reads queue
+    #[arg(long, env = "FAGENT_ENGINE_READ_WORKERS")]
+    engine_read_workers: Option<usize>,
+    /// Refuse to open a store written with an older on-disk layout instead of
+    /// backing it up as a snapshot and upgrading it
+    #[arg(long, env = "FAGENT_NO_MIGRATE", default_value_t = false)]
+    no_migrate: bool,
+}
+
+#[derive(Args)]
+struct DaemonArgs {
+    #[command(flatten)]
+    dashboard: DashboardArgs,
+    /// Socket address, or `unix:<path>`, that only receives GitHub webhooks,
+    /// so --bind can stay private; needs --github-webhook-secret
+    #[arg(long)]
+    webhook_bind: Option<String>,
+    /// Longest pause, in seconds, before restarting a sync worker or
+    /// scheduler that keeps failing
+    #[arg(long, default_value_t = daemon::DEFAULT_MAX_RESTART_DELAY_SECS)]
+    max_restart_delay_secs: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DeveloperPrivacyMode {
+    Keep,
+    Hash,
+    Omit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnnxPoolingMode {
+    Mean,
+    Cls,
+    None,
+}
+
+impl DashboardArgs {
+    #[cfg(feature = "fetchers")]
+    fn workspace_limits(&self) -> gitfetcher::WorkspaceLimits {
+        let mut limits = gitfetcher::WorkspaceLimits {
+            root_dir: self.workspace_dir.clone(),
+            ..Default::default()
+        };
+        if let Some(mb) = self.workspace_max_mb {
+            limits.max_bytes = Some(mb.saturating_mul(1024 * 1024));
+        }
+        if let Some(secs) = self.clone_timeout_secs {
+            limits.clone_timeout_secs = Some(secs);
+        }
+        if let Some(secs) = self.parse_timeout_secs {
+            limits.parse_timeout_secs = Some(secs);
+        }
+        if let Some(command) = &self.clone_sandbox {
+            limits.sandbox_command = command.split_whitespace().map(str::to_string).collect();
+        }
+        limits
+    }
+
+    /// Unsalted digests of names and emails are reversed by hashing a list of
+    /// candidates, so hashing needs a salt.
+    fn check_developer_privacy(&self) -> anyhow::Result<()> {
+        if self.developer_privacy == DeveloperPrivacyMode::Hash
+            && self.privacy_salt.trim().is_empty()
+        {
+            anyhow::bail!("--developer-privacy hash needs a non-empty --privacy-salt");
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "fetchers")]
+    fn developer_privacy(&self) -> gitfetcher::DeveloperPrivacy {
+        match self.developer_privacy {
+            DeveloperPrivacyMode::Keep => gitfetcher::DeveloperPrivacy::Keep,
+            DeveloperPrivacyMode::Hash => gitfetcher::DeveloperPrivacy::Hash {
+                salt: self.privacy_salt.clone(),
+            },
+            DeveloperPrivacyMode::Omit => gitfetcher::DeveloperPrivacy::Omit,
+        }
+    }
+
+    fn onnx_config(&self) -> Option<OnnxConfig> {
+        let mut config = OnnxConfig::new(self.onnx_model.clone()?);
+        config.tokenizer_path = self.onnx_tokenizer.clone();
+        config.pooling = match self.onnx_pooling {
+            OnnxPoolingMode::Mean => OnnxPooling::Mean,
+            OnnxPoolingMode::Cls => OnnxPooling::Cls,
+            OnnxPoolingMode::None => OnnxPooling::None,
+        };
+        config.normalize = !self.onnx_no_normalize;
+        config.device = self.onnx_device;
+        Some(config)
+    }
+
+    fn reranker_config(&self) -> Option<RerankerConfig> {
+        let mut config = self.reranker.clone()?;
+        if let RerankerConfig::Http { api_key, model, .. } = &mut config {
+            *api_key = self.reranker_api_key.clone();
+            *model = self.reranker_model.clone();
+        }
+        Some(config)
+    }
+}
+
+impl StoreArgs {
+    fn base_path(&self) -> anyhow::Result<&Path> {
+        self.base_path
+            .as_deref()
+            .context("--base-path is required unless the --config file sets base_path")
+    }
+
+    /// Storage settings for the store at `base_path`, or for `workspace`.
+    fn storage_config(&self, base_path: &Path, workspace: Option<&str>) -> StorageConfig {
+        let mut config = StorageConfig::new(base_path);
+        config.table_format = self.table_format(workspace);
+        config.lake_store = self.lake_store(workspace);
+        config.sql_engine = self.sql_engine;
+        config.auto_migrate = !self.no_migrate;
+        config.engine = EngineConfig {
+            map_size: self.engine_map_size,
+            map_ceiling: self.engine_map_ceiling,
+            sync_mode: self.engine_sync_mode,
+            max_readers: self.engine_max_readers,
+            read_workers: self.engine_read_workers,
+        };
+        config
+    }
+
+    /// Opens the primary store, without fetchers, for subcommands that only
+    /// read or maintain it.
+    async fn open(&self) -> anyhow::Result<FStorage> {
+        FStorage::new(self.storage_config(self.base_path()?, None))
+            .await
+            .context("failed to open fstorage")
+    }
+
+    /// Iceberg tables for the primary store, or for `workspace` in a namespace of its own.
+    fn table_format(&self, workspace: Option<&str>) -> TableFormat {
+        let Some(uri) = &self.iceberg_catalog else {
+            return TableFormat::Delta;
+        };
+        let mut config = IcebergConfig::new(uri.clone());
+        config.warehouse = self.iceberg_warehouse.clone();
+        config.namespace = match workspace {
+            Some(workspace) => format!("{}_{workspace}", self.iceberg_namespace),
+            None => self.iceberg_namespace.clone(),
+        };
+        TableFormat::Iceberg(config)
+    }
+
+    /// The --lake-uri store for the primary store, or a sibling prefix for `workspace`.
+    fn lake_store(&self, workspace: Option<&str>) -> Option<LakeStoreConfig> {
+        let uri = self.lake_uri.as_deref()?.trim_end_matches('/');
+        let mut config = LakeStoreConfig::new(match workspace {
+            Some(workspace) => format!("{uri}_{workspace}"),
+            None => uri.to_string(),
+        });
+        config
+            .options
+            .extend(self.lake_store_options.iter().cloned());
+        Some(config)
+    }
+}
+
+fn parse_api_key(raw: &str) -> Result<(String, Role), String> {
+    let (key, role) = raw
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected key=role, got '{raw}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("API key must not be empty".to_string());
+    }
+    Ok((key.to_string(), role.parse()?))
+}
+
+fn parse_workspace(raw: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=path, got '{raw}'"))?;
+    let name = name.trim();
+    workspaces::validate_name(name)?;
+    if path.trim().is_empty() {
+        return Err(format!("workspace '{name}' needs a base path"));
+    }
+    Ok((name.to_string(), PathBuf::from(path.trim())))
+}
+
+/// Splits at the first `=` only; the value is hashed as given.
+fn parse_id_key(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got '{raw}'"))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+fn parse_federated_remote(raw: &str) -> Result<(String, String), String> {
+    let (name, url) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=url, got '{raw}'"))?;
+    Ok((name.trim().to_string(), url.trim().to_string()))
+}
+
+fn parse_federated_api_key(raw: &str) -> Result<(String, String), String> {
+    let (name, key) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=key, got '{raw}'"))?;
+    if key.trim().is_empty() {
+        return Err(format!("API key for '{}' must not be empty", name.trim()));
+    }
+    Ok((name.trim().to_string(), key.trim().to_string()))
+}
+
+fn parse_redaction_rule(raw: &str) -> Result<RedactionRule, String> {
+    let (name, pattern) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=regex, got '{raw}'"))?;
+    Ok(RedactionRule {
+        name: name.trim().to_string(),
+        pattern: pattern.to_string(),
+    })
+}
+
+fn parse_token_price(raw: &str) -> Result<(String, f64), String> {
+    let (key, price) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected provider[:model]=price, got '{raw}'"))?;
+    let price: f64 = price
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid price '{price}': {err}"))?;
+    if price < 0.0 {
+        return Err(format!("price must not be negative, got {price}"));
+    }
+    Ok((key.trim().to_string(), price))
+}
+
+fn parse_lake_store_option(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got '{raw}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("lake store option needs a key".to_string());
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn parse_embedding_limit(raw: &str) -> Result<(String, ProviderLimits), String> {
+    let (key, quota) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected provider[:model]=RPM/TPM, got '{raw}'"))?;
+    let (rpm, tpm) = quota
+        .split_once('/')
+        .ok_or_else(|| format!("expected RPM/TPM, got '{quota}'"))?;
+    let per_minute = |value: &str| -> Result<Option<u32>, String> {
+        match value.trim() {
+            "" => Ok(None),
+            value => match value.parse::<u32>() {
+                Ok(0) | Err(_) => Err(format!("invalid per-minute limit '{value}'")),
+                Ok(limit) => Ok(Some(limit)),
+            },
+        }
+    };
+    let limits = ProviderLimits {
+        requests_per_minute: per_minute(rpm)?,
+        tokens_per_minute: per_minute(tpm)?,
+    };
+    Ok((key.trim().to_string(), limits))
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub storage: Arc<FStorage>,
+    pub(crate) jobs: Arc<jobs::SyncJobQueue>,
+    pub(crate) access: Arc<auth::AccessPolicy>,
+    pub(crate) snapshots: Arc<snapshots::SnapshotMounts>,
+    pub(crate) limiter: Arc<ratelimit::RateLimiter>,
+    pub(crate) cors: CorsOrigins,
+    pub(crate) legacy_api: versioning::LegacyApiPolicy,
+    pub(crate) response_cache: Arc<response_cache::ResponseCache>,
+    pub(crate) github_webhook_secret: Option<Arc<str>>,
+    pub(crate) workspaces: Arc<BTreeMap<String, AppState>>,
+    pub(crate) federation: Arc<federation::Federation>,
+    pub(crate) ui_dir: Option<Arc<Path>>,
+    pub(crate) usage: Arc<admin::UsageCounter>,
 }
 
 impl AppState {
+    /// Must be called from within a Tokio runtime; the sync workers are spawned here.
     pub fn new(storage: Arc<FStorage>) -> Self {
-        Self { storage }
+        Self::with_sync_workers(storage, jobs::DEFAULT_SYNC_WORKERS)
+    }
+
+    pub fn with_sync_workers(storage: Arc<FStorage>, workers: usize) -> Self {
+        let jobs = jobs::SyncJobQueue::start(Arc::clone(&storage), workers);
+        let state = Self::with_jobs(storage, jobs);
+        schedules::start_scheduler(state.clone());
+        state
+    }
+
+    /// State whose sync workers and scheduler the caller runs itself, as the
+    /// daemon does under supervision. Jobs of a previous process are recovered.
+    fn unstarted(storage: Arc<FStorage>) -> Self {
+        let jobs = jobs::SyncJobQueue::recovered(Arc::clone(&storage));
+        Self::with_jobs(storage, jobs)
+    }
+
+    /// State for a store that only serves reads, such as a mounted snapshot.
+    fn read_only(storage: Arc<FStorage>) -> Self {
+        let jobs = jobs::SyncJobQueue::idle(Arc::clone(&storage));
+        Self::with_jobs(storage, jobs)
+    }
+
+    fn with_jobs(storage: Arc<FStorage>, jobs: Arc<jobs::SyncJobQueue>) -> Self {
+        Self {
+            storage,
+            jobs,
+            access: Arc::new(auth::AccessPolicy::default()),
+            snapshots: Arc::new(snapshots::SnapshotMounts::default()),
+            limiter: Arc::new(ratelimit::RateLimiter::default()),
+            cors: CorsOrigins::None,
+            legacy_api: versioning::LegacyApiPolicy::default(),
+            response_cache: Arc::new(response_cache::ResponseCache::new(
+                response_cache::DEFAULT_CACHE_ENTRIES,
+            )),
+            github_webhook_secret: None,
+            workspaces: Arc::default(),
+            federation: Arc::default(),
+            ui_dir: None,
+            usage: Arc::default(),
+        }
+    }
+
+    /// Writes the API calls counted since the last write, of this store and
+    /// of its workspaces.
+    async fn flush_usage(&self) {
+        self.usage.flush(&self.storage).await;
+        for workspace in self.workspaces.values() {
+            workspace.usage.flush(&workspace.storage).await;
+        }
+    }
+
+    /// Accepts `key` with the given role. Once any key is registered, requests
+    /// without a known key are rejected.
+    pub fn with_api_key(mut self, key: impl Into<String>, role: Role) -> Self {
+        Arc::make_mut(&mut self.access).grant(key.into(), role);
+        self
+    }
+
+    /// Limits how often each caller may start syncs and run searches.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limiter = Arc::new(ratelimit::RateLimiter::new(limit));
+        self
+    }
+
+    /// Lets browser frontends on `origins` call the API.
+    pub fn with_cors_origins(mut self, origins: CorsOrigins) -> Self {
+        self.cors = origins;
+        self
+    }
+
+    /// Answers the deprecated unversioned `/api/...` paths with `410 Gone` from
+    /// `sunset` on, announcing the date until then.
+    pub fn with_legacy_api_sunset(mut self, sunset: DateTime<Utc>) -> Self {
+        self.legacy_api.sunset = Some(sunset);
+        self
+    }
+
+    /// Keeps up to `max_entries` overview and statistics responses between
+    /// syncs; 0 turns the cache off.
+    pub fn with_response_cache(mut self, max_entries: usize) -> Self {
+        self.response_cache = Arc::new(response_cache::ResponseCache::new(max_entries));
+        self
+    }
+
+    /// Accepts GitHub webhook deliveries signed with `secret` at
+    /// `/api/webhooks/github`; without one the route answers 404.
+    pub fn with_github_webhook_secret(mut self, secret: impl Into<String>) -> Self {
+        self.github_webhook_secret = Some(Arc::from(secret.into()));
+        self
+    }
+
+    /// Serves the dashboard's static files from `dir`, falling back to the
+    /// built-in UI for files it does not hold.
+    pub fn with_ui_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.ui_dir = Some(Arc::from(dir.as_ref()));
+        self
+    }
+
+    /// Serves `workspace`'s API under `/api/{name}/...`, using this state's API
+    /// keys and rate limits. Fails when `name` is not a valid path segment or
+    /// would shadow one of the API's own routes.
+    pub fn with_workspace(
+        mut self,
+        name: impl Into<String>,
+        workspace: AppState,
+    ) -> Result<Self, String> {
+        let name = name.into();
+        workspaces::validate_name(&name)?;
+        if self.workspaces.contains_key(&name) {
+            return Err(format!("workspace '{name}' is already registered"));
+        }
+        Arc::make_mut(&mut self.workspaces).insert(name, workspace);
+        Ok(self)
+    }
+
+    /// Queries the fagent dashboard at `base_url` from the `/api/federation/...`
+    /// routes, tagging its results with `name`. `api_key` is sent to it as
+    /// `X-API-Key`.
+    pub fn with_federated_remote(
+        mut self,
+        name: impl Into<String>,
+        base_url: impl Into<String>,
+        api_key: Option<String>,
+    ) -> Result<Self, String> {
+        Arc::make_mut(&mut self.federation).add(federation::FederatedRemote {
+            name: name.into(),
+            base_url: base_url.into(),
+            api_key,
+        })?;
+        Ok(self)
     }
 }
 
+/// Stable, machine-readable kind of an error response, for clients to branch
+/// on instead of parsing messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum ErrorCode {
+    InvalidArgument,
+    Unauthorized,
+    Forbidden,
+    /// A fetcher's policy refused the sync.
+    PolicyDenied,
+    /// The node, edge, table or other stored entity asked for does not exist.
+    EntityNotFound,
+    /// No fetcher of that name is registered.
+    FetcherUnknown,
+    /// A sync ran out of its request or time budget.
+    BudgetExhausted,
+    RateLimited,
+    Timeout,
+    Gone,
+    /// The embedding provider failed to embed the text.
+    EmbeddingFailed,
+    SyncFailed,
+    Internal,
+}
+
 #[derive(Debug, thiserror::Error)]
 enum ApiError {
     #[error("{0}")]
     BadRequest(String),
     #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
     NotFound(String),
     #[error("{0}")]
+    TooManyRequests(String),
+    #[error("{0}")]
+    Timeout(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("{0}")]
     Internal(String),
+    /// An error with a more specific code than its status implies.
+    #[error("{message}")]
+    Coded { code: ErrorCode, message: String },
 }
 
 impl ApiError {
+    fn coded(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError::Coded {
+            code,
+            message: message.into(),
+        }
+    }
+
     fn from_storage(err: StorageError) -> Self {
+        let message = err.to_string();
         match err {
             StorageError::InvalidArg(msg) => ApiError::BadRequest(msg),
             StorageError::NotFound(msg) => ApiError::NotFound(msg),
+            StorageError::UnknownFetcher(_) => ApiError::coded(ErrorCode::FetcherUnknown, message),
+            StorageError::BudgetExhausted(_) => {
+                ApiError::coded(ErrorCode::BudgetExhausted, message)
+            }
+            StorageError::PolicyDenied(_) => ApiError::coded(ErrorCode::PolicyDenied, message),
+            StorageError::Embedding(_) => ApiError::coded(ErrorCode::EmbeddingFailed, message),
+            StorageError::SyncError(_) => ApiError::coded(ErrorCode::SyncFailed, message),
             StorageError::Graph(graph_err) => match graph_err {
                 GraphError::New(msg) => ApiError::NotFound(msg),
                 GraphError::NodeNotFound
@@ -116,34 +1191,71 @@ impl ApiError {
                 }
                 other => ApiError::Internal(other.to_string()),
             },
-            other => ApiError::Internal(other.to_string()),
+            _ => ApiError::Internal(message),
         }
     }
 
-    fn status_code(&self) -> StatusCode {
+    fn code(&self) -> ErrorCode {
         match self {
-            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
-            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::BadRequest(_) => ErrorCode::InvalidArgument,
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::NotFound(_) => ErrorCode::EntityNotFound,
+            ApiError::TooManyRequests(_) => ErrorCode::RateLimited,
+            ApiError::Timeout(_) => ErrorCode::Timeout,
+            ApiError::Gone(_) => ErrorCode::Gone,
+            ApiError::Internal(_) => ErrorCode::Internal,
+            ApiError::Coded { code, .. } => *code,
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self.code() {
+            ErrorCode::InvalidArgument | ErrorCode::FetcherUnknown => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden | ErrorCode::PolicyDenied => StatusCode::FORBIDDEN,
+            ErrorCode::EntityNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::BudgetExhausted | ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ErrorCode::Gone => StatusCode::GONE,
+            ErrorCode::EmbeddingFailed | ErrorCode::SyncFailed | ErrorCode::Internal => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
         }
     }
 }
 
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+struct ErrorResponse {
+    code: ErrorCode,
+    error: String,
+    /// The request's `x-request-id`, to find its log lines.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let status = self.status_code();
-        let body = Json(json!({ "error": self.to_string() }));
+        let body = Json(ErrorResponse {
+            code: self.code(),
+            error: self.to_string(),
+            request_id: telemetry::current_request_id().map(|id| id.to_string()),
+        });
         (status, body).into_response()
     }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TablesQuery {
     #[serde(default)]
     prefix: Option<String>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphVisualQuery {
     #[serde(default)]
     k: Option<usize>,
@@ -151,13 +1263,17 @@ struct GraphVisualQuery {
     node_prop: Option<String>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphOverviewQuery {
     #[serde(default)]
     limit: Option<usize>,
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphSearchQuery {
     #[serde(default)]
     q: Option<String>,
@@ -165,9 +1281,12 @@ struct GraphSearchQuery {
     entity_type: Option<String>,
     #[serde(default)]
     limit: Option<usize>,
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphSubgraphQuery {
     start_id: String,
     #[serde(default)]
@@ -178,9 +1297,26 @@ struct GraphSubgraphQuery {
     edge_limit: Option<usize>,
     #[serde(default)]
     edge_types: Option<String>,
+    /// `ndjson` streams a `center` line, then a `node` line per node and an
+    /// `edge` line per edge.
+    #[serde(default)]
+    stream: Option<StreamFormat>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GraphNeighborsQuery {
+    id: String,
+    #[serde(default)]
+    direction: Option<String>,
+    #[serde(default)]
+    edge_types: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphShortestPathQuery {
     from_id: String,
     to_id: String,
@@ -188,12 +1324,45 @@ struct GraphShortestPathQuery {
     edge_label: Option<String>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphNodeDetailQuery {
     id: String,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GraphDiffQuery {
+    /// Earlier sync run, as listed by `/api/graph/runs`.
+    from_run: i64,
+    to_run: i64,
+    /// Most entries in each added, removed and changed list.
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GraphRunsQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+const DEFAULT_GRAPH_DIFF_LIMIT: usize = 1_000;
+const MAX_GRAPH_DIFF_LIMIT: usize = 10_000;
+const DEFAULT_SYNC_RUN_LIMIT: usize = 50;
+const MAX_SYNC_RUN_LIMIT: usize = 500;
+
+/// Most ids a single `POST /api/graph/nodes` call may resolve.
+const MAX_BULK_NODE_IDS: usize = 500;
+
+#[derive(Deserialize, ToSchema)]
+struct GraphNodesRequest {
+    ids: Vec<String>,
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct HybridMultiQuery {
     #[serde(default)]
     q: Option<String>,
@@ -203,9 +1372,55 @@ struct HybridMultiQuery {
     limit: Option<usize>,
     #[serde(default)]
     alpha: Option<f32>,
+    /// Rescore the top hybrid candidates with the configured cross-encoder.
+    #[serde(default)]
+    rerank: bool,
+    /// Embed the query with this configured model instead of the default one.
+    #[serde(default)]
+    embedding_model: Option<String>,
+    /// Append the graph context of the hits: their callers and callees,
+    /// containing files and sibling functions, with decayed scores.
+    #[serde(default)]
+    graph_expand: bool,
+    /// Check when the projects of the top hits were last synced and flag those
+    /// synced longer ago than this many seconds.
+    #[serde(default)]
+    max_age: Option<u64>,
+    /// With `max_age`, return no hits while any of those projects is stale.
+    #[serde(default)]
+    block_stale: bool,
+    /// With `max_age`, queue an interactive sync of each stale project that
+    /// has none queued or running; needs the operator role.
+    #[serde(default)]
+    auto_sync: bool,
+}
+
+/// Query of the single-type lexical and vector searches.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TypedSearchQuery {
+    entity_type: String,
+    #[serde(default)]
+    q: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
 }
 
-#[derive(Deserialize)]
+impl TypedSearchQuery {
+    fn entity_type(&self) -> ApiResult<String> {
+        let entity_type = self.entity_type.trim();
+        if entity_type.is_empty() {
+            return Err(ApiError::BadRequest("entity_type is required".to_string()));
+        }
+        Ok(entity_type.to_string())
+    }
+
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(20).clamp(1, 200)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 struct SyncRequest {
     fetcher: String,
     #[serde(default)]
@@ -213,16 +1428,82 @@ struct SyncRequest {
     #[serde(default)]
     triggering_query: Option<String>,
     #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
     target_entities: Vec<EntityIdentifier>,
+    /// Restricts the sync to these entity types; forwarded to the fetcher as the
+    /// `entity_types` param.
     #[serde(default)]
+    entity_types: Vec<String>,
+    /// `{"type": "duration_secs", "seconds": ..}`, `{"type": "request_count", "count": ..}`
+    /// or `{"type": "composite", ..limits}`; unlimited by default.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
     budget: Option<SyncBudgetPayload>,
+    #[serde(default)]
+    priority: SyncPriority,
+    /// Syncs queued one after the other, each once the one before it
+    /// succeeded; the rest are dropped when one fails or runs out of budget.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<Object>)]
+    then: Vec<SyncRequest>,
+}
+
+/// Queue ordering for sync jobs; higher priorities are started first, ties in submission order.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+enum SyncPriority {
+    /// Scheduled refreshes and bulk ingestion.
+    Background,
+    #[default]
+    Normal,
+    /// Syncs an agent or user is actively waiting on, e.g. after a stale readiness check.
+    Interactive,
+}
+
+impl SyncRequest {
+    fn sync_context(&self) -> SyncContext {
+        SyncContext {
+            triggering_query: self.triggering_query.clone(),
+            target_entities: self.target_entities.clone(),
+        }
+    }
+
+    fn sync_budget(&self) -> SyncBudget {
+        SyncBudget::from(self.budget.clone().unwrap_or_default())
+    }
+
+    /// Folds `entity_types` into the fetcher params so it is part of the stored
+    /// request and of the checkpoint scope.
+    fn apply_entity_types(&mut self) -> ApiResult<()> {
+        if self.entity_types.is_empty() {
+            return Ok(());
+        }
+        if self.params.is_null() {
+            self.params = json!({});
+        }
+        let Some(params) = self.params.as_object_mut() else {
+            return Err(ApiError::BadRequest(
+                "entity_types requires object params".to_string(),
+            ));
+        };
+        if params.contains_key("entity_types") {
+            return Err(ApiError::BadRequest(
+                "entity_types given both in the request and in params".to_string(),
+            ));
+        }
+        params.insert("entity_types".to_string(), json!(self.entity_types));
+        Ok(())
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum SyncBudgetPayload {
     DurationSecs { seconds: u64 },
     RequestCount { count: u32 },
+    Composite(BudgetLimits),
 }
 
 impl From<SyncBudgetPayload> for SyncBudget {
@@ -232,29 +1513,37 @@ impl From<SyncBudgetPayload> for SyncBudget {
                 SyncBudget::ByDuration(std::time::Duration::from_secs(seconds))
             }
             SyncBudgetPayload::RequestCount { count } => SyncBudget::ByRequestCount(count),
+            SyncBudgetPayload::Composite(limits) => SyncBudget::Composite(limits),
         }
     }
 }
 
+/// No limit: a sync without a budget runs to completion.
 impl Default for SyncBudgetPayload {
     fn default() -> Self {
-        SyncBudgetPayload::RequestCount { count: 100 }
+        SyncBudgetPayload::Composite(BudgetLimits::default())
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct StatusResponse {
     db_stats: JsonValue,
-    entity_count: usize,
+    entity_count: u64,
+    /// Known entities per category: `node`, `edge` and `vector`.
+    entity_counts: BTreeMap<String, u64>,
     registered_fetchers: usize,
 }
 
-#[derive(Serialize)]
-struct SyncResponse {
-    message: String,
+#[derive(Serialize, ToSchema)]
+struct SyncAcceptedResponse {
+    job_id: i64,
+    #[schema(value_type = String, example = "queued")]
+    state: SyncJobState,
+    priority: SyncPriority,
+    status_url: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct GraphNodeSummary {
     id: String,
     entity_type: String,
@@ -262,26 +1551,47 @@ struct GraphNodeSummary {
     display_name: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct GraphOverviewResponse {
     candidates: Vec<GraphNodeSummary>,
+    next_cursor: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct GraphSearchResponse {
     candidates: Vec<GraphNodeSummary>,
+    next_cursor: Option<String>,
 }
 
-#[derive(Serialize, Clone)]
+/// One page of candidates plus the cursor for the next page, if there may be one.
+struct CandidatePage {
+    candidates: Vec<GraphNodeSummary>,
+    next_cursor: Option<GraphCursor>,
+}
+
+impl CandidatePage {
+    fn empty() -> Self {
+        Self {
+            candidates: Vec::new(),
+            next_cursor: None,
+        }
+    }
+
+    fn next_cursor_token(&self) -> Option<String> {
+        self.next_cursor.as_ref().map(GraphCursor::encode)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 struct GraphNodeDto {
     id: String,
     entity_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     display_name: Option<String>,
     properties: JsonValue,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 struct GraphEdgeDto {
     id: String,
     label: String,
@@ -290,14 +1600,46 @@ struct GraphEdgeDto {
     properties: JsonValue,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct GraphSubgraphResponse {
     center: GraphNodeDto,
     nodes: Vec<GraphNodeDto>,
     edges: Vec<GraphEdgeDto>,
 }
 
+/// A line of a `stream=ndjson` subgraph.
 #[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SubgraphLine {
+    Center { node: GraphNodeDto },
+    Node { node: GraphNodeDto },
+    Edge { edge: GraphEdgeDto },
+}
+
+#[derive(Serialize, ToSchema)]
+struct GraphNodesResponse {
+    /// Found nodes, in request order.
+    nodes: Vec<GraphNodeDto>,
+    /// Requested ids that matched no node.
+    missing: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GraphNeighborDto {
+    orientation: &'static str,
+    edge: Option<GraphEdgeDto>,
+    node_id: String,
+    node: Option<GraphNodeDto>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GraphNeighborsResponse {
+    id: String,
+    direction: &'static str,
+    neighbors: Vec<GraphNeighborDto>,
+}
+
+#[derive(Serialize, ToSchema)]
 struct GraphPathResponse {
     found: bool,
     length: usize,
@@ -305,29 +1647,36 @@ struct GraphPathResponse {
     edges: Vec<GraphEdgeDto>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct HybridMultiResponse {
     entity_types: Vec<String>,
+    #[schema(value_type = Vec<Object>)]
     hits: Vec<MultiEntitySearchHit>,
+    /// Freshness of the projects behind the top hits, when `max_age` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    freshness: Option<readiness::AnswerFreshness>,
 }
 
-type ApiResult<T> = Result<T, ApiError>;
+/// Lexical matches only, scored by BM25 with no vector component.
+#[derive(Serialize, ToSchema)]
+struct Bm25SearchResponse {
+    entity_type: String,
+    #[schema(value_type = Vec<Object>)]
+    hits: Vec<TextSearchHit>,
+}
 
-fn init_tracing() {
-    let _ = fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
-        )
-        .try_init();
+/// Nearest vectors to the embedded query, with no lexical component.
+#[derive(Serialize, ToSchema)]
+struct VectorSearchResponse {
+    entity_type: String,
+    /// Each hit carries its `distance` and a `similarity` of `1 / (1 + distance)`.
+    #[schema(value_type = Vec<Object>)]
+    hits: Vec<VectorSearchHit>,
 }
 
-const INDEX_HTML: &str = include_str!("../dashboard_ui/index.html");
-const GRAPH_HTML: &str = include_str!("../dashboard_ui/graph.html");
-const STYLES_CSS: &str = include_str!("../dashboard_ui/styles.css");
-const APP_JS: &str = include_str!("../dashboard_ui/app.js");
-const GRAPH_JS: &str = include_str!("../dashboard_ui/graph.js");
+type ApiResult<T> = Result<T, ApiError>;
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, ToSchema)]
 struct GraphTypeColorStyle {
     background: &'static str,
     border: &'static str,
@@ -335,13 +1684,14 @@ struct GraphTypeColorStyle {
     highlight_border: &'static str,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, ToSchema)]
 struct GraphTypeStyle {
     entity_type: &'static str,
     display_name: &'static str,
     font_color: &'static str,
     color: GraphTypeColorStyle,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<String>>)]
     aliases: Option<&'static [&'static str]>,
 }
 
@@ -576,138 +1926,493 @@ const GRAPH_TYPE_STYLES: &[GraphTypeStyle] = &[
     },
 ];
 
+/// Opens the store at `base_path` with the dashboard's storage settings and
+/// fetchers.
+async fn open_dashboard_storage(
+    args: &DashboardArgs,
+    base_path: &Path,
+    workspace: Option<&str>,
+) -> anyhow::Result<Arc<FStorage>> {
+    args.check_developer_privacy()?;
+    let mut config = args.store.storage_config(base_path, workspace);
+    config
+        .token_pricing
+        .extend(args.token_prices.iter().cloned());
+    config
+        .embedding_limits
+        .extend(args.embedding_limits.iter().cloned());
+    config.redaction.enabled = !args.disable_redaction;
+    config
+        .redaction
+        .rules
+        .extend(args.redaction_rules.iter().cloned());
+    config.onnx = args.onnx_config();
+    config.reranker = args.reranker_config();
+    config
+        .embedding_models
+        .extend(args.embedding_models.iter().cloned());
+    let storage = Arc::new(FStorage::new(config).await?);
+
+    #[cfg(not(feature = "fetchers"))]
+    if !args.disable_gitfetcher {
+        info!("GitFetcher not registered: fagent was built without the `fetchers` feature");
+    }
+    #[cfg(feature = "fetchers")]
+    if !args.disable_gitfetcher {
+        let policy = gitfetcher::RepoPolicy {
+            allow: args.repo_allow.clone(),
+            deny: args.repo_deny.clone(),
+            max_size_kb: args.max_repo_size_kb,
+            licenses: args.repo_licenses.clone(),
+        };
+        match gitfetcher::GitFetcher::with_default_client(args.github_token.clone()) {
+            Ok(fetcher) => {
+                let mut fetcher = fetcher
+                    .with_policy(policy)
+                    .with_workspace_limits(args.workspace_limits())
+                    .with_developer_privacy(args.developer_privacy());
+                if let Some(dir) = &args.code_index_dir {
+                    fetcher = fetcher.with_code_index_dir(dir.clone());
+                }
+                if let Some(dir) = &args.coverage_dir {
+                    fetcher = fetcher.with_coverage_dir(dir.clone());
+                }
+                if let Some(dir) = &args.benchmark_dir {
+                    fetcher = fetcher.with_benchmark_dir(dir.clone());
+                }
+                storage.register_fetcher(Arc::new(fetcher));
+                info!("GitFetcher registered");
+            }
+            Err(err) => {
+                tracing::error!("Failed to initialize GitFetcher: {}", err);
+            }
+        }
+    }
+    Ok(storage)
+}
+
 async fn run_dashboard(args: DashboardArgs) -> anyhow::Result<()> {
-    let addr: SocketAddr = args.bind.parse().context("failed to parse bind address")?;
+    let bind = DashboardBind::parse(&args.bind)?;
+    let state = dashboard_state(&args, AppState::with_sync_workers).await?;
+    let served = serve(bind, build_router(state.clone()), shutdown_signal()).await;
+    state.flush_usage().await;
+    served
+}
+
+/// Opens the store and every workspace of `args` with `new_state`, and applies
+/// the rest of the dashboard settings to the state.
+async fn dashboard_state(
+    args: &DashboardArgs,
+    new_state: fn(Arc<FStorage>, usize) -> AppState,
+) -> anyhow::Result<AppState> {
+    let storage = open_dashboard_storage(args, args.store.base_path()?, None).await?;
+    let mut state = new_state(storage, args.sync_workers);
+    for (name, base_path) in &args.workspaces {
+        let storage = open_dashboard_storage(args, base_path, Some(name))
+            .await
+            .with_context(|| format!("failed to open workspace '{name}'"))?;
+        let workspace =
+            new_state(storage, args.sync_workers).with_response_cache(args.response_cache_entries);
+        state = state
+            .with_workspace(name.clone(), workspace)
+            .map_err(anyhow::Error::msg)?;
+        info!(workspace = %name, base_path = %base_path.display(), "workspace opened");
+    }
+    for (key, role) in &args.api_keys {
+        state = state.with_api_key(key.clone(), *role);
+    }
+    for (name, base_url) in &args.federated_remotes {
+        let api_key = args
+            .federated_api_keys
+            .iter()
+            .find(|(remote, _)| remote == name)
+            .map(|(_, key)| key.clone());
+        state = state
+            .with_federated_remote(name.clone(), base_url.clone(), api_key)
+            .map_err(anyhow::Error::msg)?;
+        info!(remote = %name, url = %base_url, "federated remote registered");
+    }
+    if let Some((name, _)) = args.federated_api_keys.iter().find(|(name, _)| {
+        !args
+            .federated_remotes
+            .iter()
+            .any(|(remote, _)| remote == name)
+    }) {
+        anyhow::bail!("--federate-api-key names unknown remote '{name}'");
+    }
+    if args.api_keys.is_empty() {
+        info!("No API keys configured; the dashboard API is open to every caller");
+    }
+    if let Some(per_minute) = args.rate_limit_per_minute {
+        let mut limit = RateLimit::per_minute(per_minute);
+        if let Some(burst) = args.rate_limit_burst {
+            limit.burst = burst;
+        }
+        state = state.with_rate_limit(limit);
+    }
+    let cors = CorsOrigins::parse(&args.cors_origins).map_err(anyhow::Error::msg)?;
+    state = state.with_cors_origins(cors);
+    if let Some(date) = args.legacy_api_sunset {
+        state = state.with_legacy_api_sunset(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    state = state.with_response_cache(args.response_cache_entries);
+    if let Some(secret) = args
+        .github_webhook_secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+    {
+        state = state.with_github_webhook_secret(secret);
+    }
+    if let Some(dir) = &args.ui_dir {
+        if !dir.is_dir() {
+            anyhow::bail!("--ui-dir {} is not a directory", dir.display());
+        }
+        info!(dir = %dir.display(), "serving dashboard UI from disk");
+        state = state.with_ui_dir(dir);
+    }
+    for schedule in &args.schedules {
+        schedules::save_schedule(&state, schedule.clone())
+            .with_context(|| format!("invalid schedule '{}' in the config file", schedule.name))?;
+    }
+    Ok(state)
+}
+
+/// Serves `router` on `bind` until `shutdown` completes.
+async fn serve(
+    bind: DashboardBind,
+    router: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let addr = match bind {
+        DashboardBind::Tcp(addr) => addr,
+        #[cfg(unix)]
+        DashboardBind::Unix(path) => {
+            return unix_socket::serve(&path, router, shutdown)
+                .await
+                .context("dashboard server error");
+        }
+    };
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context("failed to bind dashboard listener")?;
 
-    let config = StorageConfig::new(&args.base_path);
-    let storage = Arc::new(FStorage::new(config).await?);
+    info!("Dashboard listening on {}", addr);
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .context("dashboard server error")?;
+
+    Ok(())
+}
+
+/// Where `--bind` asks the dashboard to listen.
+enum DashboardBind {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl DashboardBind {
+    /// Parses a socket address, or `unix:<path>` for a Unix domain socket.
+    fn parse(raw: &str) -> anyhow::Result<Self> {
+        if let Some(path) = raw.strip_prefix("unix:") {
+            if path.is_empty() {
+                anyhow::bail!("unix bind address needs a socket path, e.g. unix:/run/fagent.sock");
+            }
+            #[cfg(unix)]
+            return Ok(Self::Unix(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            anyhow::bail!("unix sockets are not supported on this platform");
+        }
+        raw.parse()
+            .map(Self::Tcp)
+            .context("failed to parse bind address")
+    }
+}
+
+/// The authorized API of one store.
+fn api_routes(state: AppState) -> Router {
+    read_routes(&state)
+        .route("/api/fetchers", get(list_fetchers))
+        .route("/api/readiness", post(readiness::check_readiness))
+        .route("/api/sync", post(trigger_sync))
+        .route("/api/onboard", post(onboard::onboard_repository))
+        .route("/api/graph/node", delete(delete_graph_node))
+        .route("/api/reports/quality", post(reports::run_quality_report))
+        .route("/api/reports/id_collisions", post(reports::run_id_audit))
+        .route(
+            "/api/identities/resolve",
+            post(identities::resolve_identities),
+        )
+        .route(
+            "/api/identities/matches/:developer_id/:identity_id",
+            put(identities::decide_identity_match),
+        )
+        .route(
+            "/api/vectors/import",
+            post(import_embeddings).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/api/sync/ws", get(jobs::sync_progress_ws))
+        .route("/api/sync/jobs", get(jobs::list_sync_jobs))
+        .route("/api/sync/jobs/:job_id", get(jobs::get_sync_job))
+        .route("/api/sync/jobs/:job_id/resume", post(jobs::resume_sync_job))
+        .route("/api/sync/history", get(jobs::list_sync_history))
+        .route("/api/sync/history/:id", get(jobs::get_sync_history))
+        .route(
+            "/api/schedules",
+            get(schedules::list_schedules).post(schedules::put_schedule),
+        )
+        .route(
+            "/api/schedules/:name",
+            get(schedules::get_schedule).delete(schedules::delete_schedule),
+        )
+        .route("/api/schedules/:name/run", post(schedules::run_schedule))
+        .route(
+            "/api/bookmarks",
+            get(bookmarks::list_bookmarks).post(bookmarks::put_bookmark),
+        )
+        .route(
+            "/api/bookmarks/:node_id",
+            delete(bookmarks::delete_bookmark),
+        )
+        .route(
+            "/api/saved_searches",
+            get(saved_searches::list_saved_searches).post(saved_searches::put_saved_search),
+        )
+        .route(
+            "/api/saved_searches/:name",
+            get(saved_searches::get_saved_search).delete(saved_searches::delete_saved_search),
+        )
+        .route(
+            "/api/saved_searches/:name/results",
+            get(saved_searches::run_saved_search),
+        )
+        .route("/api/admin/usage", get(admin::get_usage))
+        .route("/api/admin/costs", get(admin::get_costs))
+        .route("/api/admin/cache", get(response_cache::get_cache_metrics))
+        .route(
+            "/api/admin/foreign_tables",
+            get(foreign_tables::list_foreign_tables).post(foreign_tables::attach_foreign_table),
+        )
+        .route(
+            "/api/admin/foreign_tables/:name",
+            delete(foreign_tables::detach_foreign_table),
+        )
+        .route("/api/privacy/export", get(privacy::export_personal_data))
+        .route("/api/privacy/erase", post(privacy::erase_personal_data))
+        .route(
+            "/api/snapshots",
+            get(snapshots::list_snapshots).post(snapshots::create_snapshot),
+        )
+        .route(
+            "/api/snapshots/:name/mount",
+            post(snapshots::mount_snapshot).delete(snapshots::unmount_snapshot),
+        )
+        .route("/api/mounts/:name/*rest", any(snapshots::forward_to_mount))
+        .route("/api/workspaces", get(workspaces::list_workspaces))
+        .route(
+            "/api/federation/search/hybrid_all",
+            get(federation::federated_hybrid_search),
+        )
+        .route(
+            "/api/federation/graph/subgraph",
+            get(federation::federated_subgraph),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::limit_requests,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authorize,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin::track_usage,
+        ))
+        .route_layer(middleware::from_fn(telemetry::trace_request))
+        .with_state(state)
+}
 
-    if !args.disable_gitfetcher {
-        match gitfetcher::GitFetcher::with_default_client(args.github_token.clone()) {
-            Ok(fetcher) => {
-                storage.register_fetcher(Arc::new(fetcher));
-                info!("GitFetcher registered");
-            }
-            Err(err) => {
-                error!("Failed to initialize GitFetcher: {}", err);
-            }
-        }
-    }
+/// Builds the HTTP router used by the dashboard service.
+pub fn build_router(state: AppState) -> Router {
+    let api = api_routes(state.clone());
+    let workspaces = workspaces::WorkspaceRouters::new(&state);
+    let webhook_routes = webhook_routes(&state);
+
+    let cors = state.cors.layer();
+    let legacy_api = state.legacy_api;
+    let ui_override = state.ui_dir.clone();
+    let probe_routes = Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .with_state(state);
 
-    let state = AppState::new(storage);
-    let router = build_router(state);
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .context("failed to bind dashboard listener")?;
+    #[cfg(feature = "ui")]
+    let static_routes = ui::routes();
+    #[cfg(not(feature = "ui"))]
+    let static_routes = Router::new();
+    let static_routes = match ui_override {
+        Some(dir) => ui_dir::routes(&dir, static_routes),
+        None => static_routes,
+    };
 
-    info!("Dashboard listening on {}", addr);
-    axum::serve(listener, router)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .context("dashboard server error")?;
+    let router = api
+        .merge(webhook_routes)
+        .merge(probe_routes)
+        .merge(openapi::routes())
+        .merge(static_routes);
+    // The workspace and version prefixes are resolved before routing, so they
+    // wrap the finished router rather than being among its layers.
+    let router = Router::new().fallback_service(
+        middleware::from_fn_with_state(workspaces, workspaces::route_workspace).layer(router),
+    );
+    let router = match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+    Router::new()
+        .fallback_service(
+            middleware::from_fn_with_state(legacy_api, versioning::route_version).layer(router),
+        )
+        .layer(middleware::from_fn(telemetry::log_request))
+}
 
-    Ok(())
+/// Signed by GitHub instead of carrying an API key, so outside `authorize`.
+fn webhook_routes(state: &AppState) -> Router {
+    Router::new()
+        .route(
+            "/api/webhooks/github",
+            post(webhooks::github_webhook)
+                .layer(DefaultBodyLimit::max(webhooks::WEBHOOK_BODY_LIMIT)),
+        )
+        .route_layer(middleware::from_fn(telemetry::trace_request))
+        .with_state(state.clone())
 }
 
-/// Builds the HTTP router used by the dashboard service.
-pub fn build_router(state: AppState) -> Router {
-    let api = Router::new()
-        .route("/api/fetchers", get(list_fetchers))
-        .route("/api/status", get(get_status))
-        .route("/api/tables", get(list_tables))
-        .route("/api/graph/overview", get(graph_overview))
+/// Routes that only read the store; these are also served for mounted snapshots.
+fn read_routes(state: &AppState) -> Router<AppState> {
+    let cached = || middleware::from_fn_with_state(state.clone(), response_cache::cache_response);
+    let graph = Router::new()
+        .route("/api/graph/overview", get(graph_overview).layer(cached()))
         .route("/api/graph/types", get(graph_types))
+        .route(
+            "/api/graph/edge_types",
+            get(graph_edge_types).layer(cached()),
+        )
+        .route("/api/graph/stats", get(graph_stats).layer(cached()))
+        .route("/api/graph/runs", get(graph_runs))
+        .route("/api/graph/diff", get(graph_diff).layer(cached()))
         .route("/api/graph/search", get(graph_search))
         .route("/api/graph/subgraph", get(graph_subgraph))
+        .route(
+            "/api/graph/layout",
+            get(layout::graph_layout).layer(cached()),
+        )
+        .route("/api/graph/neighbors", get(graph_neighbors))
         .route("/api/graph/shortest_path", get(graph_shortest_path))
+        .route("/api/graph/similar", get(structure::graph_similar))
         .route("/api/graph/node", get(graph_node_detail))
+        .route("/api/graph/nodes", post(graph_nodes))
         .route("/api/graph/visual", get(graph_visual))
+        .route("/api/graph/export", get(export::graph_export))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            caching::graph_version,
+        ));
+    Router::new()
+        .route("/api/status", get(get_status))
+        .route("/api/tables", get(list_tables))
+        .route("/api/tables/*rest", get(query::preview_table))
+        .route("/api/debug/id", get(ids::get_stable_id))
+        .merge(graph)
         .route("/api/search/hybrid/types", get(hybrid_entity_types))
         .route("/api/search/hybrid_all", get(hybrid_multi_search))
-        .route("/api/readiness", post(check_readiness))
-        .route("/api/sync", post(trigger_sync))
-        .with_state(state);
-
-    let static_routes = Router::new()
-        .route("/", get(serve_index))
-        .route("/graph.html", get(serve_graph))
-        .route("/styles.css", get(serve_styles))
-        .route("/app.js", get(serve_app_js))
-        .route("/graph.js", get(serve_graph_js))
-        .fallback(get(serve_index));
-
-    api.merge(static_routes)
-}
-
-async fn serve_index() -> Html<&'static str> {
-    Html(INDEX_HTML)
-}
-
-async fn serve_graph() -> Html<&'static str> {
-    Html(GRAPH_HTML)
-}
-
-async fn serve_styles() -> Response {
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "text/css; charset=utf-8")
-        .body(Body::from(STYLES_CSS))
-        .unwrap()
-}
-
-async fn serve_app_js() -> Response {
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/javascript; charset=utf-8")
-        .body(Body::from(APP_JS))
-        .unwrap()
-}
-
-async fn serve_graph_js() -> Response {
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "application/javascript; charset=utf-8")
-        .body(Body::from(GRAPH_JS))
-        .unwrap()
+        .route("/api/search/bm25", get(bm25_search))
+        .route("/api/search/vector", get(vector_search))
+        .route("/api/projects/compare", get(compare::compare_projects))
+        .route("/api/projects/:id/readme", get(readme::get_project_readme))
+        .route(
+            "/api/projects/:id/related",
+            get(related::get_related_projects),
+        )
+        .route(
+            "/api/projects/:id/activity",
+            get(activity::get_project_activity),
+        )
+        .route("/api/query", post(query::run_sql_query))
+        .route("/api/reports/quality", get(reports::get_quality_report))
+        .route(
+            "/api/identities/matches",
+            get(identities::list_identity_matches),
+        )
+        .route_layer(
+            ServiceBuilder::new()
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    caching::data_etag,
+                ))
+                .layer(CompressionLayer::new()),
+        )
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/fetchers",
+    tag = "sync",
+    responses((status = 200, description = "Registered fetchers and their parameters", body = [Object]))
+)]
 async fn list_fetchers(State(state): State<AppState>) -> ApiResult<Json<Vec<FetcherCapability>>> {
     let capabilities = state.storage.list_fetchers_capability();
     Ok(Json(capabilities))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "storage",
+    responses(
+        (status = 200, body = StatusResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 async fn get_status(State(state): State<AppState>) -> ApiResult<Json<StatusResponse>> {
-    let txn = state
-        .storage
-        .engine
-        .storage
-        .graph_env
-        .read_txn()
-        .map_err(|err| ApiError::Internal(err.to_string()))?;
-
-    let stats_str = state
-        .storage
-        .engine
-        .storage
-        .get_db_stats_json(&txn)
-        .map_err(|err| ApiError::from_storage(StorageError::Graph(err)))?;
-    let stats: JsonValue =
-        serde_json::from_str(&stats_str).map_err(|err| ApiError::Internal(err.to_string()))?;
-
-    let entities = state
-        .storage
-        .list_known_entities()
+    let response = storage_status(&state.storage)
+        .await
         .map_err(ApiError::from_storage)?;
-
-    let response = StatusResponse {
-        db_stats: stats,
-        entity_count: entities.len(),
-        registered_fetchers: state.storage.list_fetchers_capability().len(),
-    };
-
     Ok(Json(response))
 }
 
+/// What `/api/status` reports, shared with `fagent status`.
+async fn storage_status(storage: &FStorage) -> fstorage::errors::Result<StatusResponse> {
+    let stats_str = storage
+        .lake
+        .read_graph(|storage, txn| Ok(storage.get_db_stats_json(txn)?))
+        .await?;
+    let db_stats: JsonValue = serde_json::from_str(&stats_str)?;
+    let entity_counts = storage.count_known_entities()?;
+
+    Ok(StatusResponse {
+        db_stats,
+        entity_count: entity_counts.values().sum(),
+        entity_counts,
+        registered_fetchers: storage.list_fetchers_capability().len(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/tables",
+    tag = "storage",
+    params(TablesQuery),
+    responses(
+        (status = 200, description = "Lake tables with their columns", body = [Object]),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 async fn list_tables(
     State(state): State<AppState>,
     Query(query): Query<TablesQuery>,
@@ -721,134 +2426,308 @@ async fn list_tables(
     Ok(Json(tables))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/stats",
+    tag = "graph",
+    responses(
+        (status = 200, description = "Node and edge counts per label, vector count, and row counts per lake table and edge type", body = Object),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
+async fn graph_stats(State(state): State<AppState>) -> ApiResult<Json<GraphStatistics>> {
+    let stats = state
+        .storage
+        .lake
+        .graph_statistics()
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/runs",
+    tag = "graph",
+    params(GraphRunsQuery),
+    responses(
+        (status = 200, description = "Sync runs that persisted data, most recent first", body = Object),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
+async fn graph_runs(
+    State(state): State<AppState>,
+    Query(query): Query<GraphRunsQuery>,
+) -> ApiResult<Json<Vec<SyncRun>>> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SYNC_RUN_LIMIT)
+        .clamp(1, MAX_SYNC_RUN_LIMIT);
+    let runs = state
+        .storage
+        .list_sync_runs(limit)
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(runs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/diff",
+    tag = "graph",
+    params(GraphDiffQuery),
+    responses(
+        (status = 200, description = "Nodes and edges added, removed and changed between the runs", body = Object),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+async fn graph_diff(
+    State(state): State<AppState>,
+    Query(query): Query<GraphDiffQuery>,
+) -> ApiResult<Json<GraphDiff>> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_GRAPH_DIFF_LIMIT)
+        .clamp(1, MAX_GRAPH_DIFF_LIMIT);
+    let diff = state
+        .storage
+        .graph_diff(query.from_run, query.to_run, limit)
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(diff))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/visual",
+    tag = "graph",
+    params(GraphVisualQuery),
+    responses(
+        (status = 200, description = "Top nodes and edges for rendering", body = Object),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
 async fn graph_visual(
     State(state): State<AppState>,
     Query(query): Query<GraphVisualQuery>,
 ) -> ApiResult<Json<JsonValue>> {
-    let txn = state
-        .storage
-        .engine
-        .storage
-        .graph_env
-        .read_txn()
-        .map_err(|err| ApiError::Internal(err.to_string()))?;
     let raw = state
         .storage
-        .engine
-        .storage
-        .nodes_edges_to_json(&txn, query.k, query.node_prop.clone())
-        .map_err(|err| ApiError::from_storage(StorageError::Graph(err)))?;
+        .lake
+        .read_graph(move |storage, txn| {
+            Ok(storage.nodes_edges_to_json(txn, query.k, query.node_prop)?)
+        })
+        .await
+        .map_err(ApiError::from_storage)?;
     let payload: JsonValue =
         serde_json::from_str(&raw).map_err(|err| ApiError::Internal(err.to_string()))?;
     Ok(Json(payload))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/types",
+    tag = "graph",
+    responses(
+        (status = 200, body = [GraphTypeStyle]),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
 async fn graph_types() -> ApiResult<Json<Vec<GraphTypeStyle>>> {
     let styles: Vec<GraphTypeStyle> = GRAPH_TYPE_STYLES.iter().cloned().collect();
     Ok(Json(styles))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/edge_types",
+    tag = "graph",
+    responses(
+        (status = 200, description = "Edge labels from the schema and the lake, with endpoint node types", body = [Object]),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
+async fn graph_edge_types(State(state): State<AppState>) -> ApiResult<Json<Vec<EdgeTypeSummary>>> {
+    let edge_types = state
+        .storage
+        .lake
+        .list_edge_types()
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(edge_types))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/overview",
+    tag = "graph",
+    params(GraphOverviewQuery),
+    responses(
+        (status = 200, body = GraphOverviewResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 400, description = "Invalid cursor", body = ErrorResponse)
+    )
+)]
 async fn graph_overview(
     State(state): State<AppState>,
     Query(query): Query<GraphOverviewQuery>,
 ) -> ApiResult<Json<GraphOverviewResponse>> {
     let limit = query.limit.unwrap_or(30).clamp(1, 300);
-    let candidates = collect_overview_candidates(&state, limit).await?;
-    Ok(Json(GraphOverviewResponse { candidates }))
+    let cursor = GraphCursor::from_query(query.cursor.as_deref())?;
+    let page = collect_overview_candidates(&state, limit, cursor).await?;
+    Ok(Json(GraphOverviewResponse {
+        next_cursor: page.next_cursor_token(),
+        candidates: page.candidates,
+    }))
 }
 
+/// Walks graph nodes in id order, starting after the cursor's node id.
 async fn collect_overview_candidates(
     state: &AppState,
     limit: usize,
-) -> ApiResult<Vec<GraphNodeSummary>> {
+    cursor: Option<GraphCursor>,
+) -> ApiResult<CandidatePage> {
     if limit == 0 {
-        return Ok(Vec::new());
+        return Ok(CandidatePage::empty());
     }
 
-    let snapshot = {
-        let txn = state
-            .storage
-            .engine
-            .storage
-            .graph_env
-            .read_txn()
-            .map_err(|err| ApiError::Internal(err.to_string()))?;
-        state
-            .storage
-            .engine
-            .storage
-            .nodes_edges_to_json(&txn, Some(limit), None)
-            .map_err(|err| ApiError::from_storage(StorageError::Graph(err)))?
+    let lower = match cursor {
+        Some(cursor) => {
+            let after = Uuid::parse_str(&cursor.after).map_err(|_| {
+                ApiError::BadRequest(format!("invalid cursor position '{}'", cursor.after))
+            })?;
+            Bound::Excluded(after.as_u128())
+        }
+        None => Bound::Unbounded,
     };
 
-    let parsed: JsonValue =
-        serde_json::from_str(&snapshot).map_err(|err| ApiError::Internal(err.to_string()))?;
     let mut candidates = Vec::new();
-    let mut seen: HashSet<String> = HashSet::new();
-    if let Some(nodes_array) = parsed.get("nodes").and_then(|value| value.as_array()) {
-        for node_value in nodes_array {
-            if candidates.len() >= limit {
-                break;
-            }
-
-            let node_id = node_value
-                .get("id")
-                .and_then(|value| value.as_str())
-                .map(|value| value.to_string());
-            let Some(node_id) = node_id else {
-                continue;
-            };
-
-            if !seen.insert(node_id.clone()) {
-                continue;
-            }
-
-            let fetched = state
-                .storage
-                .lake
-                .get_node_by_id(&node_id, None)
-                .await
-                .map_err(ApiError::from_storage)?;
-
-            let Some(node_map) = fetched else {
-                continue;
-            };
+    let mut last_id: Option<String> = None;
+    let mut exhausted = true;
+    // Node ids are read in chunks on a blocking thread, and each chunk's nodes
+    // resolved in one transaction rather than one per node.
+    loop {
+        let start = match &last_id {
+            Some(id) => Bound::Excluded(
+                Uuid::parse_str(id)
+                    .map_err(|err| ApiError::Internal(err.to_string()))?
+                    .as_u128(),
+            ),
+            None => lower,
+        };
+        let take = limit - candidates.len();
+        let chunk: Vec<String> = state
+            .storage
+            .lake
+            .read_graph(move |storage, txn| {
+                let mut ids = Vec::new();
+                for entry in storage
+                    .nodes_db
+                    .range(txn, &(start, Bound::Unbounded))?
+                    .take(take)
+                {
+                    let (id, _) = entry?;
+                    ids.push(Uuid::from_u128(id).to_string());
+                }
+                Ok(ids)
+            })
+            .await
+            .map_err(ApiError::from_storage)?;
+        if chunk.is_empty() {
+            break;
+        }
 
-            if let Some(summary) = map_node_summary(node_map) {
+        let mut fetched = state
+            .storage
+            .lake
+            .get_nodes_by_ids(&chunk)
+            .await
+            .map_err(ApiError::from_storage)?;
+        for node_id in chunk {
+            if let Some(summary) = fetched.remove(&node_id).and_then(map_node_summary) {
                 candidates.push(summary);
             }
+            last_id = Some(node_id);
+        }
+        if candidates.len() >= limit {
+            exhausted = false;
+            break;
         }
     }
 
-    Ok(candidates)
+    Ok(CandidatePage {
+        candidates,
+        next_cursor: last_id
+            .filter(|_| !exhausted)
+            .map(|id| GraphCursor::new(None, id)),
+    })
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/search",
+    tag = "graph",
+    params(GraphSearchQuery),
+    responses(
+        (status = 200, body = GraphSearchResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 400, description = "Invalid cursor", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse)
+    )
+)]
 async fn graph_search(
     State(state): State<AppState>,
     Query(query): Query<GraphSearchQuery>,
 ) -> ApiResult<Json<GraphSearchResponse>> {
-    let limit = query.limit.unwrap_or(20).clamp(1, 100);
     let term = query.q.unwrap_or_default();
+    run_graph_search(
+        &state,
+        &term,
+        query.entity_type.as_deref(),
+        query.limit,
+        query.cursor.as_deref(),
+    )
+    .await
+    .map(Json)
+}
+
+/// Matches `term` against the index tables, or lists overview nodes when
+/// neither a term nor an entity type is given.
+async fn run_graph_search(
+    state: &AppState,
+    term: &str,
+    entity_type: Option<&str>,
+    limit: Option<usize>,
+    cursor: Option<&str>,
+) -> ApiResult<GraphSearchResponse> {
+    let limit = limit.unwrap_or(20).clamp(1, 100);
     let term = term.trim();
-    let entity_type = query.entity_type.as_deref();
+    let cursor = GraphCursor::from_query(cursor)?;
 
-    let candidates = if term.is_empty() && entity_type.is_none() {
-        collect_overview_candidates(&state, limit).await?
+    let page = if term.is_empty() && entity_type.is_none() {
+        collect_overview_candidates(state, limit, cursor).await?
     } else {
-        search_candidates(&state, term, entity_type, limit).await?
+        search_candidates(state, term, entity_type, limit, cursor).await?
     };
 
-    Ok(Json(GraphSearchResponse { candidates }))
+    Ok(GraphSearchResponse {
+        next_cursor: page.next_cursor_token(),
+        candidates: page.candidates,
+    })
 }
 
+/// Scans the index tables one entity type at a time (sorted by name), each newest
+/// first with ties broken by id, resuming where the cursor stopped. A node
+/// matched under two entity types is listed once per page.
 async fn search_candidates(
     state: &AppState,
     term: &str,
     entity_type: Option<&str>,
     limit: usize,
-) -> ApiResult<Vec<GraphNodeSummary>> {
+    cursor: Option<GraphCursor>,
+) -> ApiResult<CandidatePage> {
     if limit == 0 {
-        return Ok(Vec::new());
+        return Ok(CandidatePage::empty());
     }
 
     let mut entity_types = Vec::new();
@@ -868,69 +2747,105 @@ async fn search_candidates(
     }
 
     if entity_types.is_empty() {
-        return Ok(Vec::new());
+        return Ok(CandidatePage::empty());
     }
 
     entity_types.sort();
     entity_types.dedup();
 
-    let mut seen: HashSet<String> = HashSet::new();
-    let mut results = Vec::new();
-
-    for entity in entity_types {
-        if results.len() >= limit {
-            break;
-        }
-        let remaining = limit - results.len();
-        let rows = state
-            .storage
-            .lake
-            .search_index_nodes(&entity, term, remaining)
-            .await
-            .map_err(ApiError::from_storage)?;
-
-        for row in rows {
-            if results.len() >= limit {
-                break;
-            }
-            let Some(id) = row.get("id").and_then(|value| value.as_str()) else {
-                continue;
+    let (resume_type, mut after) = match cursor {
+        Some(cursor) => {
+            let resume_type = cursor.entity_type.ok_or_else(|| {
+                ApiError::BadRequest("cursor does not belong to a search result".to_string())
+            })?;
+            let position = IndexPosition {
+                updated_at_micros: cursor.updated_at,
+                id: cursor.after,
             };
-            if !seen.insert(id.to_string()) {
-                continue;
-            }
+            (Some(resume_type), Some(position))
+        }
+        None => (None, None),
+    };
+    if let Some(resume_type) = &resume_type {
+        entity_types.retain(|entity| entity >= resume_type);
+        if entity_types.first() != Some(resume_type) {
+            after = None;
+        }
+    }
 
-            let node_map = state
+    let mut results = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for entity in entity_types {
+        loop {
+            let remaining = limit - results.len();
+            let rows = state
                 .storage
                 .lake
-                .get_node_by_id(id, Some(&entity))
+                .search_index_nodes_after(&entity, term, after.as_ref(), remaining)
                 .await
                 .map_err(ApiError::from_storage)?;
-            let Some(node_map) = node_map else {
-                continue;
-            };
+            let fetched_all = rows.len() < remaining;
+            let previous = after.clone();
+
+            for row in rows {
+                let Some(position) = IndexPosition::of(&row) else {
+                    continue;
+                };
+                let id = position.id.clone();
+                after = Some(position);
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+
+                let node_map = state
+                    .storage
+                    .lake
+                    .get_node_by_id(&id, Some(&entity))
+                    .await
+                    .map_err(ApiError::from_storage)?;
+                if let Some(summary) = node_map.and_then(map_node_summary) {
+                    results.push(summary);
+                }
+            }
 
-            if let Some(summary) = map_node_summary(node_map) {
-                results.push(summary);
+            if results.len() >= limit {
+                return Ok(CandidatePage {
+                    candidates: results,
+                    next_cursor: after.map(|position| GraphCursor {
+                        entity_type: Some(entity),
+                        after: position.id,
+                        updated_at: position.updated_at_micros,
+                    }),
+                });
+            }
+            if fetched_all || after == previous {
+                break;
             }
         }
+        after = None;
     }
 
-    Ok(results)
+    Ok(CandidatePage {
+        candidates: results,
+        next_cursor: None,
+    })
 }
 
 fn gather_hybrid_entity_types(state: &AppState) -> ApiResult<Vec<String>> {
-    let offsets = state
-        .storage
-        .catalog
-        .list_ingestion_offsets()
-        .map_err(ApiError::from_storage)?;
+    searchable_entity_types(&state.storage).map_err(ApiError::from_storage)
+}
+
+/// Node and vector types that have been ingested, sorted.
+pub(crate) fn searchable_entity_types(storage: &FStorage) -> Result<Vec<String>, StorageError> {
+    let offsets = storage.catalog.list_ingestion_offsets()?;
     let mut types = Vec::new();
     for offset in offsets {
+        // Structural embeddings are not comparable with embedded query text.
         if matches!(
             offset.category,
             EntityCategory::Node | EntityCategory::Vector
-        ) {
+        ) && offset.entity_type != NodeEmbedding::ENTITY_TYPE
+        {
             types.push(offset.entity_type);
         }
     }
@@ -939,16 +2854,45 @@ fn gather_hybrid_entity_types(state: &AppState) -> ApiResult<Vec<String>> {
     Ok(types)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/search/hybrid/types",
+    tag = "search",
+    responses(
+        (status = 200, description = "Entity types with a search index", body = [String]),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag")
+    )
+)]
 async fn hybrid_entity_types(State(state): State<AppState>) -> ApiResult<Json<Vec<String>>> {
     let types = gather_hybrid_entity_types(&state)?;
     Ok(Json(types))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/search/hybrid_all",
+    tag = "search",
+    params(HybridMultiQuery),
+    responses(
+        (status = 200, description = "Hits, best first; with `max_age`, also the freshness of their projects, and no hits if `block_stale` found one stale", body = HybridMultiResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Reranking was requested but no reranker is configured, or the embedding model is unknown", body = ErrorResponse),
+        (status = 403, description = "`auto_sync` was asked for without the operator role", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse)
+    )
+)]
 async fn hybrid_multi_search(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<HybridMultiQuery>,
 ) -> ApiResult<Json<HybridMultiResponse>> {
-    let mut entity_types: Vec<String> = query
+    // The route is a read open to viewers; queuing syncs is not.
+    if query.auto_sync && !state.access.permits(&headers, Role::Operator) {
+        return Err(ApiError::Forbidden(
+            "auto_sync queues syncs and requires the operator role".to_string(),
+        ));
+    }
+    let entity_types: Vec<String> = query
         .entity_types
         .as_deref()
         .map(|raw| {
@@ -959,43 +2903,198 @@ async fn hybrid_multi_search(
                 .collect()
         })
         .unwrap_or_default();
+    let query_text = query.q.unwrap_or_default();
+    let mut response = run_hybrid_search(
+        &state,
+        &query_text,
+        entity_types,
+        query.alpha,
+        query.limit,
+        HybridOptions {
+            rerank: query.rerank,
+            embedding_model: query.embedding_model.as_deref(),
+            graph_expand: query.graph_expand,
+        },
+    )
+    .await?;
+    if let Some(max_age) = query.max_age {
+        response.freshness = Some(readiness::check_answer_freshness(
+            &state,
+            &mut response.hits,
+            max_age,
+            query.block_stale,
+            query.auto_sync,
+        )?);
+    }
+    Ok(Json(response))
+}
+
+/// How [`run_hybrid_search`] ranks and widens its hits.
+#[derive(Clone, Copy)]
+struct HybridOptions<'a> {
+    rerank: bool,
+    embedding_model: Option<&'a str>,
+    graph_expand: bool,
+}
 
+/// Hybrid search over `entity_types`, or over every searchable type when none
+/// are given.
+async fn run_hybrid_search(
+    state: &AppState,
+    query_text: &str,
+    mut entity_types: Vec<String>,
+    alpha: Option<f32>,
+    limit: Option<usize>,
+    options: HybridOptions<'_>,
+) -> ApiResult<HybridMultiResponse> {
     if entity_types.is_empty() {
-        entity_types = gather_hybrid_entity_types(&state)?;
+        entity_types = gather_hybrid_entity_types(state)?;
     }
 
     if entity_types.is_empty() {
-        return Ok(Json(HybridMultiResponse {
+        return Ok(HybridMultiResponse {
             entity_types,
             hits: Vec::new(),
-        }));
+            freshness: None,
+        });
     }
 
-    let query_text = query.q.unwrap_or_default();
     let trimmed = query_text.trim();
     if trimmed.is_empty() {
-        return Ok(Json(HybridMultiResponse {
+        return Ok(HybridMultiResponse {
             entity_types,
             hits: Vec::new(),
-        }));
+            freshness: None,
+        });
     }
 
-    let alpha = query.alpha.unwrap_or(0.5).clamp(0.0, 1.0);
-    let limit = query.limit.unwrap_or(20).clamp(1, 200);
+    let alpha = alpha.unwrap_or(0.5).clamp(0.0, 1.0);
+    let limit = limit.unwrap_or(20).clamp(1, 200);
 
     let hits = state
         .storage
-        .search_hybrid_multi(&entity_types, trimmed, alpha, limit)
+        .search_hybrid_multi(
+            &entity_types,
+            trimmed,
+            alpha,
+            limit,
+            options.rerank,
+            options.embedding_model,
+        )
         .await
         .map_err(ApiError::from_storage)?;
+    let hits = if options.graph_expand {
+        state
+            .storage
+            .expand_search_hits(hits, limit)
+            .await
+            .map_err(ApiError::from_storage)?
+    } else {
+        hits
+    };
 
-    Ok(Json(HybridMultiResponse { entity_types, hits }))
+    Ok(HybridMultiResponse {
+        entity_types,
+        hits,
+        freshness: None,
+    })
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/search/bm25",
+    tag = "search",
+    params(TypedSearchQuery),
+    responses(
+        (status = 200, body = Bm25SearchResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+async fn bm25_search(
+    State(state): State<AppState>,
+    Query(query): Query<TypedSearchQuery>,
+) -> ApiResult<Json<Bm25SearchResponse>> {
+    let entity_type = query.entity_type()?;
+    let hits = state
+        .storage
+        .search_text_bm25(
+            &entity_type,
+            query.q.as_deref().unwrap_or_default(),
+            query.limit(),
+        )
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(Bm25SearchResponse { entity_type, hits }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/search/vector",
+    tag = "search",
+    params(TypedSearchQuery),
+    responses(
+        (status = 200, description = "Nearest vectors first; empty for a blank query", body = VectorSearchResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse)
+    )
+)]
+async fn vector_search(
+    State(state): State<AppState>,
+    Query(query): Query<TypedSearchQuery>,
+) -> ApiResult<Json<VectorSearchResponse>> {
+    let entity_type = query.entity_type()?;
+    let hits = state
+        .storage
+        .search_vectors_by_text(
+            &entity_type,
+            query.q.as_deref().unwrap_or_default(),
+            query.limit(),
+        )
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(VectorSearchResponse { entity_type, hits }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/subgraph",
+    tag = "graph",
+    params(GraphSubgraphQuery),
+    responses(
+        (status = 200, description = "The subgraph, or NDJSON lines with `stream=ndjson`", body = GraphSubgraphResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
 async fn graph_subgraph(
     State(state): State<AppState>,
     Query(query): Query<GraphSubgraphQuery>,
-) -> ApiResult<Json<GraphSubgraphResponse>> {
+) -> ApiResult<Response> {
+    let subgraph = load_subgraph(&state, &query).await?;
+    if query.stream != Some(StreamFormat::Ndjson) {
+        return Ok(Json(subgraph).into_response());
+    }
+    // The DTOs are already in memory; streaming spares the serialized copy.
+    let GraphSubgraphResponse {
+        center,
+        nodes,
+        edges,
+    } = subgraph;
+    let lines = std::iter::once(SubgraphLine::Center { node: center })
+        .chain(nodes.into_iter().map(|node| SubgraphLine::Node { node }))
+        .chain(edges.into_iter().map(|edge| SubgraphLine::Edge { edge }))
+        .map(Ok);
+    Ok(ndjson::response(futures::stream::iter(lines)))
+}
+
+/// Breadth-first neighbourhood of `query.start_id`, shared by the subgraph and
+/// export endpoints.
+async fn load_subgraph(
+    state: &AppState,
+    query: &GraphSubgraphQuery,
+) -> ApiResult<GraphSubgraphResponse> {
     let depth = query.depth.unwrap_or(1);
     let node_limit = query.node_limit.unwrap_or(150);
     let edge_limit = query.edge_limit.unwrap_or(200);
@@ -1043,13 +3142,86 @@ async fn graph_subgraph(
         }
     }
 
-    Ok(Json(GraphSubgraphResponse {
+    Ok(GraphSubgraphResponse {
         center: center_node,
         nodes: nodes.into_values().collect(),
         edges,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/neighbors",
+    tag = "graph",
+    params(GraphNeighborsQuery),
+    responses(
+        (status = 200, body = GraphNeighborsResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+async fn graph_neighbors(
+    State(state): State<AppState>,
+    Query(query): Query<GraphNeighborsQuery>,
+) -> ApiResult<Json<GraphNeighborsResponse>> {
+    let (direction, direction_name) = match query
+        .direction
+        .as_deref()
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None | Some("") | Some("both") => (NeighborDirection::Both, "both"),
+        Some("out") | Some("outgoing") => (NeighborDirection::Outgoing, "outgoing"),
+        Some("in") | Some("incoming") => (NeighborDirection::Incoming, "incoming"),
+        Some(other) => {
+            return Err(ApiError::BadRequest(format!(
+                "unknown direction '{other}', expected outgoing, incoming or both"
+            )))
+        }
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let edge_filters = parse_edge_types(query.edge_types.as_deref());
+    let edge_refs = edge_filters
+        .as_ref()
+        .map(|values| values.iter().map(String::as_str).collect::<Vec<&str>>());
+
+    let records = state
+        .storage
+        .lake
+        .neighbors(&query.id, edge_refs.as_deref(), direction, limit)
+        .await
+        .map_err(ApiError::from_storage)?;
+
+    let neighbors = records
+        .into_iter()
+        .map(|record| GraphNeighborDto {
+            orientation: match record.orientation {
+                NeighborEdgeOrientation::Outgoing => "outgoing",
+                NeighborEdgeOrientation::Incoming => "incoming",
+            },
+            edge: map_edge_record(record.edge),
+            node_id: record.node_id,
+            node: record.node.and_then(map_node_record),
+        })
+        .collect();
+
+    Ok(Json(GraphNeighborsResponse {
+        id: query.id,
+        direction: direction_name,
+        neighbors,
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/shortest_path",
+    tag = "graph",
+    params(GraphShortestPathQuery),
+    responses(
+        (status = 200, body = GraphPathResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`")
+    )
+)]
 async fn graph_shortest_path(
     State(state): State<AppState>,
     Query(query): Query<GraphShortestPathQuery>,
@@ -1093,6 +3265,17 @@ async fn graph_shortest_path(
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/graph/node",
+    tag = "graph",
+    params(GraphNodeDetailQuery),
+    responses(
+        (status = 200, body = GraphNodeDto),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
 async fn graph_node_detail(
     State(state): State<AppState>,
     Query(query): Query<GraphNodeDetailQuery>,
@@ -1110,6 +3293,76 @@ async fn graph_node_detail(
     Ok(Json(node))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/graph/node",
+    tag = "graph",
+    params(GraphNodeDetailQuery),
+    responses(
+        (status = 200, description = "Engine node, lake rows and vectors removed", body = Object),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+async fn delete_graph_node(
+    State(state): State<AppState>,
+    Query(query): Query<GraphNodeDetailQuery>,
+) -> ApiResult<Json<NodeDeletion>> {
+    let deletion = state
+        .storage
+        .delete_node(&query.id)
+        .await
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("节点 '{}' 不存在", query.id)))?;
+    info!(
+        node_id = %deletion.node_id,
+        rows_deleted = deletion.rows_deleted.values().sum::<usize>(),
+        vectors_removed = deletion.vectors_removed.len(),
+        "deleted graph node"
+    );
+    Ok(Json(deletion))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/graph/nodes",
+    tag = "graph",
+    request_body = GraphNodesRequest,
+    responses(
+        (status = 200, body = GraphNodesResponse),
+        (status = 400, description = "Too many ids", body = ErrorResponse)
+    )
+)]
+async fn graph_nodes(
+    State(state): State<AppState>,
+    Json(request): Json<GraphNodesRequest>,
+) -> ApiResult<Json<GraphNodesResponse>> {
+    if request.ids.len() > MAX_BULK_NODE_IDS {
+        return Err(ApiError::BadRequest(format!(
+            "at most {MAX_BULK_NODE_IDS} ids may be requested at once, got {}",
+            request.ids.len()
+        )));
+    }
+    let mut found = state
+        .storage
+        .lake
+        .get_nodes_by_ids(&request.ids)
+        .await
+        .map_err(ApiError::from_storage)?;
+
+    let mut nodes = Vec::with_capacity(found.len());
+    let mut missing = Vec::new();
+    for id in request.ids {
+        match found.remove(&id).and_then(map_node_record) {
+            Some(node) => nodes.push(node),
+            // Already answered for an earlier duplicate of this id.
+            None if nodes.iter().any(|node: &GraphNodeDto| node.id == id) => {}
+            None => missing.push(id),
+        }
+    }
+    Ok(Json(GraphNodesResponse { nodes, missing }))
+}
+
 fn parse_edge_types(raw: Option<&str>) -> Option<Vec<String>> {
     let values: Vec<String> = raw
         .unwrap_or_default()
@@ -1233,44 +3486,96 @@ fn infer_display_name(
     }
 }
 
-async fn check_readiness(
+#[utoipa::path(
+    post,
+    path = "/api/sync",
+    tag = "sync",
+    request_body = SyncRequest,
+    responses(
+        (status = 202, body = SyncAcceptedResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Denied by the fetcher policy", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse)
+    )
+)]
+async fn trigger_sync(
     State(state): State<AppState>,
-    Json(body): Json<Vec<EntityIdentifier>>,
-) -> ApiResult<Json<std::collections::HashMap<String, ReadinessReport>>> {
-    let readiness = state
-        .storage
-        .get_readiness(&body)
-        .await
+    Json(body): Json<SyncRequest>,
+) -> ApiResult<(StatusCode, Json<SyncAcceptedResponse>)> {
+    let accepted = queue_sync(&state, body)?;
+    Ok((StatusCode::ACCEPTED, Json(accepted)))
+}
+
+/// Validates `body` against the registered fetchers and their policies, then
+/// queues it.
+fn queue_sync(state: &AppState, mut body: SyncRequest) -> ApiResult<SyncAcceptedResponse> {
+    admit_request(&state.storage, &mut body)?;
+    let priority = body.priority;
+    let job_id = state.jobs.submit(body)?;
+    Ok(SyncAcceptedResponse {
+        job_id,
+        state: SyncJobState::Queued,
+        priority,
+        status_url: format!("/api/sync/jobs/{job_id}"),
+    })
+}
+
+/// Checks that `body` and the syncs it chains name registered fetchers that
+/// admit them, folding their entity types into their params.
+fn admit_request(storage: &FStorage, body: &mut SyncRequest) -> ApiResult<()> {
+    let registered = storage
+        .list_fetchers_capability()
+        .iter()
+        .any(|capability| capability.name == body.fetcher);
+    if !registered {
+        return Err(ApiError::from_storage(StorageError::UnknownFetcher(
+            body.fetcher.clone(),
+        )));
+    }
+
+    body.apply_entity_types()?;
+    storage
+        .admit_sync(&body.fetcher, &body.params)
         .map_err(ApiError::from_storage)?;
-    Ok(Json(readiness))
+    for next in &mut body.then {
+        admit_request(storage, next)?;
+    }
+    Ok(())
 }
 
-async fn trigger_sync(
+/// Largest accepted embedding import body; vectors make these far bigger than
+/// other API requests.
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+#[utoipa::path(
+    post,
+    path = "/api/vectors/import",
+    tag = "search",
+    request_body(
+        content = Object,
+        description = "`entity_type`, `model`, `dimension` and `rows` of vector table columns"
+    ),
+    responses(
+        (status = 200, description = "Rows written", body = Object),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+async fn import_embeddings(
     State(state): State<AppState>,
-    Json(body): Json<SyncRequest>,
-) -> ApiResult<(StatusCode, Json<SyncResponse>)> {
-    let context = SyncContext {
-        triggering_query: body.triggering_query.clone(),
-        target_entities: body.target_entities.clone(),
-    };
-    let budget = body
-        .budget
-        .map(SyncBudget::from)
-        .unwrap_or_else(|| SyncBudget::ByRequestCount(100));
-
-    state
+    Json(body): Json<EmbeddingImport>,
+) -> ApiResult<Json<EmbeddingImportReport>> {
+    let report = state
         .storage
-        .synchronizer
-        .sync(&body.fetcher, body.params.clone(), context, budget)
+        .import_embeddings(body)
         .await
         .map_err(ApiError::from_storage)?;
-
-    Ok((
-        StatusCode::OK,
-        Json(SyncResponse {
-            message: "sync completed".to_string(),
-        }),
-    ))
+    info!(
+        entity_type = %report.entity_type,
+        model = %report.model,
+        rows = report.rows,
+        "imported precomputed embeddings"
+    );
+    Ok(Json(report))
 }
 
 async fn shutdown_signal() {
@@ -0,0 +1,454 @@
+//! Read-only language server over the stored code graph.
+//!
+//! `fagent lsp` answers a small subset of the Language Server Protocol on
+//! stdin/stdout from the synced function, class, trait and data-model nodes and
+//! their CALLS edges, so an editor can navigate a repository that was never
+//! cloned locally. Documents are addressed as `fagent://<version_sha>/<path>`.
+//!
+//! Supported requests are `workspace/symbol`, `textDocument/definition` and
+//! `textDocument/references`. Definitions resolve the identifier under the
+//! cursor when the editor sent the document text, and the enclosing symbol
+//! otherwise. Call sites are not stored, so references point at the calling
+//! functions.
+
+use std::{collections::HashMap, sync::Arc};
+
+use fstorage::{
+    fetch::Fetchable,
+    schemas::generated_schemas::{Calls, Class, DataModel, Function, Trait},
+    FStorage,
+};
+use serde_json::{json, Value as JsonValue};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, info};
+
+use crate::LspArgs;
+
+const URI_SCHEME: &str = "fagent://";
+/// Most symbols returned for one request.
+const MAX_SYMBOLS: usize = 100;
+
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// LSP `SymbolKind` of functions, the only symbols with CALLS edges.
+const FUNCTION_KIND: u8 = 12;
+
+/// Entity types a definition can resolve to, with their LSP `SymbolKind`.
+const DEFINITION_TYPES: &[(&str, u8)] = &[
+    (Function::ENTITY_TYPE, FUNCTION_KIND),
+    (Class::ENTITY_TYPE, 5),
+    (Trait::ENTITY_TYPE, 11),
+    (DataModel::ENTITY_TYPE, 23),
+];
+
+pub(crate) async fn run(args: LspArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    info!("serving the code graph over LSP on stdin/stdout");
+    serve_lsp(Arc::new(storage), tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+/// Answers LSP messages read from `reader` on `writer` until the client sends
+/// `exit` or closes the stream.
+pub async fn serve_lsp<R, W>(storage: Arc<FStorage>, reader: R, mut writer: W) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut server = LspServer {
+        storage,
+        documents: HashMap::new(),
+        shut_down: false,
+    };
+    while let Some(message) = read_message(&mut reader).await? {
+        let Some(method) = message.get("method").and_then(JsonValue::as_str) else {
+            // A response to a request of ours; the server never sends any.
+            continue;
+        };
+        if method == "exit" {
+            break;
+        }
+        let params = message.get("params").cloned().unwrap_or(JsonValue::Null);
+        let Some(id) = message.get("id").cloned() else {
+            server.notify(method, params);
+            continue;
+        };
+        let response = match server.request(method, params).await {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": err.code, "message": err.message},
+            }),
+        };
+        write_message(&mut writer, &response).await?;
+    }
+    Ok(())
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<fstorage::errors::StorageError> for RpcError {
+    fn from(err: fstorage::errors::StorageError) -> Self {
+        Self::new(INTERNAL_ERROR, err.to_string())
+    }
+}
+
+/// A definition stored in the code graph. Lines are 1-based, as stored.
+#[derive(Debug, Clone, PartialEq)]
+struct CodeSymbol {
+    name: String,
+    kind: u8,
+    version_sha: String,
+    file_path: String,
+    start_line: Option<i64>,
+    end_line: Option<i64>,
+}
+
+impl CodeSymbol {
+    fn from_fields(kind: u8, field: impl Fn(&str) -> Option<JsonValue>) -> Option<Self> {
+        let text = |name: &str| field(name).and_then(|value| value.as_str().map(str::to_string));
+        Some(Self {
+            name: text("name")?,
+            kind,
+            version_sha: text("version_sha")?,
+            file_path: text("file_path")?,
+            start_line: field("start_line").and_then(|value| value.as_i64()),
+            end_line: field("end_line").and_then(|value| value.as_i64()),
+        })
+    }
+
+    fn location(&self) -> JsonValue {
+        let start = self.start_line.unwrap_or(1).max(1) - 1;
+        let end = self
+            .end_line
+            .map_or(start, |line| (line.max(1) - 1).max(start));
+        json!({
+            "uri": format!("{URI_SCHEME}{}/{}", self.version_sha, self.file_path),
+            "range": {
+                "start": {"line": start, "character": 0},
+                "end": {"line": end, "character": 0},
+            },
+        })
+    }
+
+    fn information(&self) -> JsonValue {
+        json!({
+            "name": self.name,
+            "kind": self.kind,
+            "location": self.location(),
+            "containerName": self.file_path,
+        })
+    }
+
+    fn span(&self) -> i64 {
+        self.end_line.unwrap_or(i64::MAX) - self.start_line.unwrap_or(0)
+    }
+}
+
+struct LspServer {
+    storage: Arc<FStorage>,
+    /// Text of the documents the editor has open, by URI.
+    documents: HashMap<String, String>,
+    shut_down: bool,
+}
+
+impl LspServer {
+    fn notify(&mut self, method: &str, params: JsonValue) {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string);
+        match (method, uri) {
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params
+                    .pointer("/textDocument/text")
+                    .and_then(JsonValue::as_str);
+                self.documents
+                    .insert(uri, text.unwrap_or_default().to_string());
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // Full document sync: the last change holds the whole text.
+                let text = params
+                    .get("contentChanges")
+                    .and_then(JsonValue::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(JsonValue::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(uri, text.to_string());
+                }
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+            }
+            _ => debug!(method, "ignoring LSP notification"),
+        }
+    }
+
+    async fn request(&mut self, method: &str, params: JsonValue) -> Result<JsonValue, RpcError> {
+        if self.shut_down {
+            return Err(RpcError::new(
+                INVALID_REQUEST,
+                "the server is shutting down",
+            ));
+        }
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "workspaceSymbolProvider": true,
+                },
+                "serverInfo": {"name": "fagent", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(JsonValue::Null)
+            }
+            "workspace/symbol" => {
+                let query = params
+                    .get("query")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let filter = format!("LOWER(name) LIKE {}", sql_literal(&format!("%{query}%")));
+                let symbols = self.find_symbols(DEFINITION_TYPES, &filter).await?;
+                Ok(symbols.iter().map(CodeSymbol::information).collect())
+            }
+            "textDocument/definition" => {
+                let symbols = self.definitions(&params).await?;
+                Ok(symbols.iter().map(CodeSymbol::location).collect())
+            }
+            "textDocument/references" => {
+                let include_declaration = params
+                    .pointer("/context/includeDeclaration")
+                    .and_then(JsonValue::as_bool)
+                    .unwrap_or(false);
+                let references = self.references(&params, include_declaration).await?;
+                Ok(references.iter().map(CodeSymbol::location).collect())
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unsupported method '{method}'"),
+            )),
+        }
+    }
+
+    /// Symbols defining the identifier at the request's position, those in the
+    /// same file first; without the document text, the innermost symbol
+    /// enclosing the position.
+    async fn definitions(&self, params: &JsonValue) -> Result<Vec<CodeSymbol>, RpcError> {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing textDocument.uri"))?;
+        let (Some(line), Some(character)) = (
+            params.pointer("/position/line").and_then(JsonValue::as_u64),
+            params
+                .pointer("/position/character")
+                .and_then(JsonValue::as_u64),
+        ) else {
+            return Err(RpcError::new(INVALID_PARAMS, "missing position"));
+        };
+        let Some((version_sha, file_path)) = parse_uri(uri) else {
+            return Ok(Vec::new());
+        };
+        let in_version = format!("version_sha = {}", sql_literal(version_sha));
+
+        let word = self
+            .documents
+            .get(uri)
+            .and_then(|text| word_at(text, line as usize, character as usize));
+        if let Some(word) = word {
+            let filter = format!("{in_version} AND name = {}", sql_literal(&word));
+            let mut symbols = self.find_symbols(DEFINITION_TYPES, &filter).await?;
+            symbols.sort_by_key(|symbol| symbol.file_path != file_path);
+            return Ok(symbols);
+        }
+
+        let line = line as i64 + 1;
+        let filter = format!(
+            "{in_version} AND file_path = {} AND start_line <= {line} AND end_line >= {line}",
+            sql_literal(file_path)
+        );
+        let symbols = self.find_symbols(DEFINITION_TYPES, &filter).await?;
+        Ok(symbols
+            .into_iter()
+            .min_by_key(CodeSymbol::span)
+            .into_iter()
+            .collect())
+    }
+
+    /// Functions calling the functions defined at the request's position.
+    async fn references(
+        &self,
+        params: &JsonValue,
+        include_declaration: bool,
+    ) -> Result<Vec<CodeSymbol>, RpcError> {
+        let targets: Vec<CodeSymbol> = self
+            .definitions(params)
+            .await?
+            .into_iter()
+            .filter(|symbol| symbol.kind == FUNCTION_KIND)
+            .collect();
+        let lake = &self.storage.lake;
+        let mut references = Vec::new();
+        for target in &targets {
+            if include_declaration {
+                references.push(target.clone());
+            }
+            let keys = [
+                ("version_sha", target.version_sha.as_str()),
+                ("file_path", target.file_path.as_str()),
+                ("name", target.name.as_str()),
+            ];
+            let Some(id) = lake
+                .get_node_by_keys(Function::ENTITY_TYPE, &keys)
+                .await?
+                .and_then(|node| {
+                    node.get("id")
+                        .and_then(JsonValue::as_str)
+                        .map(str::to_string)
+                })
+            else {
+                continue;
+            };
+            let callers: Vec<String> = lake
+                .get_in_edges(&id, Some(Calls::ENTITY_TYPE))
+                .await?
+                .iter()
+                .filter_map(|edge| edge.get("from_node_id").and_then(JsonValue::as_str))
+                .map(str::to_string)
+                .collect();
+            for node in lake.get_nodes_by_ids(&callers).await?.into_values() {
+                let properties = node.get("properties").and_then(JsonValue::as_object);
+                let caller = CodeSymbol::from_fields(FUNCTION_KIND, |name| match properties {
+                    Some(properties) => properties.get(name).cloned(),
+                    None => node.get(name).cloned(),
+                });
+                if let Some(caller) = caller.filter(|caller| !references.contains(caller)) {
+                    references.push(caller);
+                }
+            }
+        }
+        Ok(references)
+    }
+
+    /// Rows of each entity table in `types` matching the SQL `filter`.
+    async fn find_symbols(
+        &self,
+        types: &[(&str, u8)],
+        filter: &str,
+    ) -> Result<Vec<CodeSymbol>, RpcError> {
+        let mut symbols = Vec::new();
+        for (entity_type, kind) in types {
+            let remaining = MAX_SYMBOLS - symbols.len();
+            if remaining == 0 {
+                break;
+            }
+            let rows = self
+                .storage
+                .lake
+                .table_sql(
+                    &format!("silver/entities/{entity_type}"),
+                    &format!(
+                        "SELECT version_sha, file_path, name, start_line, end_line \
+                         FROM {{{{table}}}} WHERE {filter} ORDER BY name, file_path LIMIT {remaining}"
+                    ),
+                )
+                .await?;
+            symbols.extend(
+                rows.iter().filter_map(|row| {
+                    CodeSymbol::from_fields(*kind, |name| row.get(name).cloned())
+                }),
+            );
+        }
+        Ok(symbols)
+    }
+}
+
+/// Splits `fagent://<version_sha>/<path>` into the version and the path.
+fn parse_uri(uri: &str) -> Option<(&str, &str)> {
+    let (version_sha, file_path) = uri.strip_prefix(URI_SCHEME)?.split_once('/')?;
+    (!version_sha.is_empty() && !file_path.is_empty()).then_some((version_sha, file_path))
+}
+
+/// The identifier touching `character` on the 0-based `line` of `text`.
+fn word_at(text: &str, line: usize, character: usize) -> Option<String> {
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let cursor = character.min(chars.len());
+    let start = chars[..cursor]
+        .iter()
+        .rposition(|c| !is_word(c))
+        .map_or(0, |idx| idx + 1);
+    let end = chars[cursor..]
+        .iter()
+        .position(|c| !is_word(c))
+        .map_or(chars.len(), |idx| cursor + idx);
+    (start < end).then(|| chars[start..end].iter().collect())
+}
+
+fn sql_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Reads one `Content-Length`-framed message; `None` at end of stream.
+async fn read_message<R>(reader: &mut BufReader<R>) -> anyhow::Result<Option<JsonValue>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut content_length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+async fn write_message<W>(writer: &mut W, message: &JsonValue) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let body = serde_json::to_vec(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
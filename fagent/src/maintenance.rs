@@ -0,0 +1,84 @@
+//! `fagent maintenance`: keeps the lake of a long-running store from growing
+//! without bound. Run it with `--dry-run` first to see what it would reclaim;
+//! files it vacuums can no longer be reached by time travel.
+
+use fstorage::models::{MaintenanceOptions, MaintenanceReport};
+
+use crate::MaintenanceArgs;
+
+pub(crate) async fn run(args: MaintenanceArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let options = MaintenanceOptions {
+        dry_run: args.dry_run,
+        retention_hours: args.retention_hours,
+        target_file_size: args.target_file_size_mb.saturating_mul(1024 * 1024),
+    };
+    let report = storage.run_maintenance(&options).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render(&report));
+    }
+    Ok(())
+}
+
+fn mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn render(report: &MaintenanceReport) -> String {
+    let mut out = String::new();
+    for table in &report.tables {
+        if table.small_files < 2 && table.compacted_files == 0 && table.vacuumed_files == 0 {
+            continue;
+        }
+        out += &format!("{}\n", table.table_path);
+        if report.dry_run {
+            out += &format!("  {} small files to compact\n", table.small_files);
+        } else if table.compacted_files > 0 {
+            out += &format!(
+                "  compacted {} files into {}\n",
+                table.compacted_files, table.written_files
+            );
+        }
+        if table.vacuumed_files > 0 {
+            out += &format!(
+                "  {} {} files, {}\n",
+                if report.dry_run {
+                    "would vacuum"
+                } else {
+                    "vacuumed"
+                },
+                table.vacuumed_files,
+                mib(table.vacuumed_bytes)
+            );
+        }
+    }
+    if out.is_empty() {
+        out += "nothing to compact or vacuum\n";
+    }
+
+    let engine = &report.engine;
+    out += &format!(
+        "\n{} in {} tables {}\n",
+        mib(report.vacuumed_bytes()),
+        report.tables.len(),
+        if report.dry_run {
+            "reclaimable (dry run)"
+        } else {
+            "reclaimed"
+        }
+    );
+    if report.forgotten_checksums > 0 {
+        out += &format!(
+            "{} checksums of vacuumed files forgotten\n",
+            report.forgotten_checksums
+        );
+    }
+    out += &format!(
+        "engine: {} of {} in use\n",
+        mib(engine.used_bytes),
+        mib(engine.file_bytes)
+    );
+    out
+}
@@ -0,0 +1,64 @@
+//! `stream=ndjson` responses: one JSON document per line, written as the
+//! handler produces them rather than after the whole result is built.
+//!
+//! Every line is an object whose `type` says what it carries. The status and
+//! headers are sent before the first line, so a failure partway through ends
+//! the body with a `{"type": "error", "error": ...}` line instead.
+
+use std::{convert::Infallible, future};
+
+use axum::{
+    body::{Body, Bytes},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub(crate) const CONTENT_TYPE_NDJSON: &str = "application/x-ndjson";
+
+/// Value of the `stream` parameter asking for a streamed response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum StreamFormat {
+    /// Newline-delimited JSON.
+    Ndjson,
+}
+
+#[derive(Serialize)]
+struct ErrorLine<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    error: &'a str,
+}
+
+/// Streams `lines` as NDJSON, stopping after the first error.
+pub(crate) fn response<S, T>(lines: S) -> Response
+where
+    S: Stream<Item = Result<T, String>> + Send + 'static,
+    T: Serialize,
+{
+    let body = lines.scan(false, |failed, line| {
+        if *failed {
+            return future::ready(None);
+        }
+        let mut bytes = line
+            .and_then(|line| serde_json::to_vec(&line).map_err(|err| err.to_string()))
+            .unwrap_or_else(|error| {
+                *failed = true;
+                let line = ErrorLine {
+                    kind: "error",
+                    error: &error,
+                };
+                serde_json::to_vec(&line).expect("error line serializes")
+            });
+        bytes.push(b'\n');
+        future::ready(Some(Ok::<_, Infallible>(Bytes::from(bytes))))
+    });
+    (
+        [(CONTENT_TYPE, CONTENT_TYPE_NDJSON)],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
@@ -0,0 +1,200 @@
+//! One-call onboarding of a GitHub repository.
+//!
+//! `POST /api/onboard` probes the repository through `gitfetcher`, queues its
+//! first sync in two stages and registers a refresh schedule for it. The
+//! first stage reads the project, its versions and README, issues, pull
+//! requests and developers from GitHub in one pass; the second parses the
+//! code graph, under the caller's budget. Each stage is a job of its own, and
+//! the code stage is only queued once the first succeeded, so the project can
+//! be searched long before its code is parsed. Everything is validated before
+//! the first job is queued, and the schedule is stored last, so a rejected
+//! request leaves nothing behind.
+
+use axum::{extract::State, http::StatusCode, Json};
+use fstorage::{
+    fetch::{Fetchable, ProbeReport},
+    models::EntityIdentifier,
+    schemas::generated_schemas::Project,
+    utils,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    queue_sync,
+    readiness::{project_entity, GITHUB_PREFIX, PROJECT_FETCHER},
+    schedules::{self, ScheduleDto, ScheduleRequest},
+    ApiError, ApiResult, AppState, ErrorResponse, SyncAcceptedResponse, SyncBudgetPayload,
+    SyncPriority, SyncRequest,
+};
+
+/// Stages of the first sync, in order: name, snapshot entity groups and
+/// priority.
+const STAGES: [(&str, &[&str], SyncPriority); 2] = [
+    (
+        "metadata",
+        &["readme", "issue", "pull_request", "developer"],
+        SyncPriority::Interactive,
+    ),
+    ("code", &["code"], SyncPriority::Background),
+];
+const CODE_STAGE: &str = "code";
+/// Spreads the refreshes of repositories onboarded with the same expression
+/// over an hour.
+const REFRESH_JITTER_SECS: u64 = 3_600;
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct OnboardRequest {
+    /// `owner/name`, or the repository's `https://github.com/...` URL.
+    repo: String,
+    /// Budget of the code stage and of each refresh, in the `POST /api/sync`
+    /// format; unlimited by default.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    budget: Option<SyncBudgetPayload>,
+    /// Five-field cron expression in UTC the repository is refreshed on.
+    #[serde(default = "default_refresh_cron")]
+    refresh_cron: String,
+}
+
+fn default_refresh_cron() -> String {
+    "@daily".to_string()
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct OnboardResponse {
+    /// Node id of the project, which exists once the metadata stage is done.
+    project_id: String,
+    project_url: String,
+    /// What `gitfetcher` found upstream before anything was queued.
+    #[schema(value_type = Object)]
+    probe: ProbeReport,
+    /// Job of the first stage. Each later stage is queued as a job of its own
+    /// once the one before it succeeded, and listed under `/api/sync/jobs`.
+    job: SyncAcceptedResponse,
+    /// The stages, in the order they run.
+    stages: Vec<OnboardStage>,
+    /// The refresh schedule, named `onboard-<owner>-<name>`.
+    schedule: ScheduleDto,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct OnboardStage {
+    /// `metadata` or `code`.
+    stage: String,
+    priority: SyncPriority,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/onboard",
+    tag = "sync",
+    request_body = OnboardRequest,
+    responses(
+        (status = 202, description = "The first stage was queued and the refresh schedule registered", body = OnboardResponse),
+        (status = 400, description = "Invalid repository or cron expression, or gitfetcher is not registered", body = ErrorResponse),
+        (status = 403, description = "The repository policy refused the repository", body = ErrorResponse),
+        (status = 500, description = "The repository could not be probed", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn onboard_repository(
+    State(state): State<AppState>,
+    Json(body): Json<OnboardRequest>,
+) -> ApiResult<(StatusCode, Json<OnboardResponse>)> {
+    let (repo, project) = repo_project(&body.repo)?;
+    let params = project.params.clone().unwrap_or_default();
+    state
+        .storage
+        .admit_sync(PROJECT_FETCHER, &params)
+        .map_err(ApiError::from_storage)?;
+    let probe = state
+        .storage
+        .probe_sync(PROJECT_FETCHER, params.clone())
+        .await
+        .map_err(ApiError::from_storage)?;
+
+    let sync = |entity_types: &[&str], budget, priority| SyncRequest {
+        fetcher: PROJECT_FETCHER.to_string(),
+        params: params.clone(),
+        triggering_query: Some(format!("onboarding of {repo}")),
+        target_entities: vec![project.clone()],
+        entity_types: entity_types.iter().map(|group| group.to_string()).collect(),
+        budget,
+        priority,
+        then: Vec::new(),
+    };
+    let refresh = serde_json::to_value(sync(&[], body.budget.clone(), SyncPriority::Background))
+        .map_err(|err| ApiError::Internal(err.to_string()))?;
+    let schedule = schedules::check_schedule(
+        &state,
+        ScheduleRequest::new(
+            format!("onboard-{}", repo.replace('/', "-")),
+            body.refresh_cron,
+            refresh,
+            REFRESH_JITTER_SECS,
+        ),
+    )?;
+
+    let [metadata, code] = STAGES.map(|(stage, entity_types, priority)| {
+        let budget = (stage == CODE_STAGE).then(|| body.budget.clone()).flatten();
+        sync(entity_types, budget, priority)
+    });
+    let job = queue_sync(
+        &state,
+        SyncRequest {
+            then: vec![code],
+            ..metadata
+        },
+    )?;
+    let schedule = schedules::store_schedule(&state, &schedule)?;
+    info!(repo = %repo, schedule = %schedule.name, job_id = job.job_id, "onboarded repository");
+
+    let project_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project.uri.clone())],
+    ))
+    .to_string();
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(OnboardResponse {
+            project_id,
+            project_url: project.uri,
+            probe,
+            job,
+            stages: STAGES
+                .iter()
+                .map(|(stage, _, priority)| OnboardStage {
+                    stage: stage.to_string(),
+                    priority: *priority,
+                })
+                .collect(),
+            schedule: ScheduleDto::new(&state, schedule)?,
+        }),
+    ))
+}
+
+/// `owner/name` of the repository `raw` names, and the readiness entity of
+/// its project.
+fn repo_project(raw: &str) -> ApiResult<(String, EntityIdentifier)> {
+    let trimmed = raw.trim().trim_end_matches('/').trim_end_matches(".git");
+    let repo = trimmed.strip_prefix(GITHUB_PREFIX).unwrap_or(trimmed);
+    let mut parts = repo.split('/');
+    let valid = matches!((parts.next(), parts.next(), parts.next()), (Some(owner), Some(name), None)
+    if [owner, name].iter().all(|part| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }));
+    let project = valid
+        .then(|| project_entity(&format!("{GITHUB_PREFIX}{repo}")))
+        .flatten()
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "repo must be owner/name or a {GITHUB_PREFIX} URL, got '{raw}'"
+            ))
+        })?;
+    Ok((repo.to_string(), project))
+}
@@ -0,0 +1,246 @@
+//! OpenAPI description of the HTTP API.
+//!
+//! The document is served at `/api/openapi.json` and browsable under
+//! `/api/docs`, so agents can discover the routes without reading this crate.
+//! Bodies defined by `fstorage` models are described as free-form objects.
+//! Paths are listed under the stable `/api/v1` prefix. Not covered: the
+//! `/api/v1/sync/ws` progress socket and the `/api/v1/mounts/{name}/...`
+//! namespace, which mirrors the read routes. Extra workspaces serve this same
+//! document's paths under `/api/v1/{workspace}/...`.
+
+use axum::Router;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    activity, admin, bookmarks, compare, export, federation, foreign_tables, health, identities,
+    ids, jobs, layout, ndjson, onboard, privacy, query, readiness, readme, related, reports,
+    response_cache, saved_searches, schedules, snapshots, structure, versioning, webhooks,
+    workspaces, Bm25SearchResponse, ErrorCode, ErrorResponse, GraphEdgeDto, GraphNeighborDto,
+    GraphNeighborsResponse, GraphNodeDto, GraphNodeSummary, GraphNodesRequest, GraphNodesResponse,
+    GraphOverviewResponse, GraphPathResponse, GraphSearchResponse, GraphSubgraphResponse,
+    GraphTypeColorStyle, GraphTypeStyle, HybridMultiResponse, StatusResponse, SyncAcceptedResponse,
+    SyncPriority, SyncRequest, VectorSearchResponse,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "fagent",
+        description = "Knowledge graph storage, sync and search API."
+    ),
+    paths(
+        crate::get_status,
+        crate::list_tables,
+        workspaces::list_workspaces,
+        query::run_sql_query,
+        query::preview_table,
+        foreign_tables::list_foreign_tables,
+        foreign_tables::attach_foreign_table,
+        foreign_tables::detach_foreign_table,
+        crate::graph_overview,
+        crate::graph_types,
+        crate::graph_edge_types,
+        crate::graph_stats,
+        crate::graph_runs,
+        crate::graph_diff,
+        crate::graph_search,
+        crate::graph_subgraph,
+        crate::graph_neighbors,
+        crate::graph_shortest_path,
+        structure::graph_similar,
+        crate::graph_node_detail,
+        crate::delete_graph_node,
+        crate::graph_nodes,
+        crate::graph_visual,
+        export::graph_export,
+        layout::graph_layout,
+        federation::federated_subgraph,
+        readme::get_project_readme,
+        related::get_related_projects,
+        activity::get_project_activity,
+        compare::compare_projects,
+        bookmarks::list_bookmarks,
+        bookmarks::put_bookmark,
+        bookmarks::delete_bookmark,
+        identities::resolve_identities,
+        identities::list_identity_matches,
+        identities::decide_identity_match,
+        crate::hybrid_entity_types,
+        crate::hybrid_multi_search,
+        federation::federated_hybrid_search,
+        crate::bm25_search,
+        crate::vector_search,
+        saved_searches::list_saved_searches,
+        saved_searches::put_saved_search,
+        saved_searches::get_saved_search,
+        saved_searches::delete_saved_search,
+        saved_searches::run_saved_search,
+        crate::import_embeddings,
+        crate::list_fetchers,
+        readiness::check_readiness,
+        crate::trigger_sync,
+        onboard::onboard_repository,
+        webhooks::github_webhook,
+        jobs::list_sync_jobs,
+        jobs::get_sync_job,
+        jobs::resume_sync_job,
+        jobs::list_sync_history,
+        jobs::get_sync_history,
+        schedules::list_schedules,
+        schedules::put_schedule,
+        schedules::get_schedule,
+        schedules::delete_schedule,
+        schedules::run_schedule,
+        snapshots::list_snapshots,
+        snapshots::create_snapshot,
+        snapshots::mount_snapshot,
+        snapshots::unmount_snapshot,
+        reports::get_quality_report,
+        reports::run_quality_report,
+        reports::run_id_audit,
+        ids::get_stable_id,
+        admin::get_usage,
+        admin::get_costs,
+        response_cache::get_cache_metrics,
+        privacy::export_personal_data,
+        privacy::erase_personal_data,
+        health::healthz,
+        health::readyz,
+    ),
+    components(schemas(
+        ErrorCode,
+        ErrorResponse,
+        StatusResponse,
+        SyncRequest,
+        SyncPriority,
+        SyncAcceptedResponse,
+        GraphNodeSummary,
+        GraphOverviewResponse,
+        GraphSearchResponse,
+        GraphNodeDto,
+        GraphNodesRequest,
+        GraphNodesResponse,
+        GraphEdgeDto,
+        GraphSubgraphResponse,
+        GraphNeighborDto,
+        GraphNeighborsResponse,
+        GraphPathResponse,
+        GraphTypeColorStyle,
+        GraphTypeStyle,
+        HybridMultiResponse,
+        Bm25SearchResponse,
+        VectorSearchResponse,
+        export::ExportFormat,
+        layout::LayoutAlgorithm,
+        layout::PositionedNode,
+        layout::GraphLayoutResponse,
+        federation::OriginError,
+        federation::FederatedSearchHit,
+        federation::FederatedSearchResponse,
+        federation::FederatedNode,
+        federation::FederatedEdge,
+        federation::FederatedSubgraphResponse,
+        readme::ReadmeResponse,
+        related::RelatedProject,
+        related::RelatedProjectsResponse,
+        structure::SimilarNode,
+        structure::SimilarNodesResponse,
+        activity::ActivityPoint,
+        activity::ActivityTotals,
+        activity::ActivityResponse,
+        compare::ComparedProject,
+        compare::ActivityTrend,
+        compare::Trend,
+        compare::Overlap,
+        compare::ContributorOverlap,
+        compare::ProjectComparison,
+        bookmarks::BookmarkRequest,
+        bookmarks::BookmarkDto,
+        identities::IdentityDecisionRequest,
+        query::SqlQueryRequest,
+        ndjson::StreamFormat,
+        foreign_tables::AttachForeignTableRequest,
+        foreign_tables::ForeignTableDto,
+        saved_searches::SearchKind,
+        saved_searches::SearchParams,
+        saved_searches::SavedSearchRequest,
+        saved_searches::SavedSearchDto,
+        saved_searches::SavedSearchResults,
+        jobs::SyncJobDto,
+        jobs::ResumeSyncJobRequest,
+        jobs::SyncHistoryDto,
+        readiness::ReadinessRequest,
+        readiness::ReadinessResponse,
+        readiness::ReadinessSync,
+        readiness::ReadinessSkip,
+        readiness::AnswerFreshness,
+        readiness::ProjectFreshness,
+        onboard::OnboardRequest,
+        onboard::OnboardResponse,
+        onboard::OnboardStage,
+        schedules::ScheduleRequest,
+        schedules::ScheduleDto,
+        snapshots::CreateSnapshotRequest,
+        admin::UsageResponse,
+        admin::CostsResponse,
+        response_cache::CacheMetrics,
+        webhooks::GithubWebhookResponse,
+    )),
+    modifiers(&ApiKeyAuth, &VersionPrefix),
+    security(("api_key" = []), ("bearer" = [])),
+    tags(
+        (name = "storage", description = "Tables, status, workspaces, ad-hoc SQL and id lookups"),
+        (name = "graph", description = "Graph browsing, traversal, structural similarity, bookmarks, identity matches, project READMEs, related projects, activity and comparisons"),
+        (name = "search", description = "Hybrid, BM25 and vector search, saved searches and vector import"),
+        (name = "sync", description = "Fetchers, readiness checks, sync jobs and schedules"),
+        (name = "snapshots", description = "Named snapshots and read-only mounts"),
+        (name = "reports", description = "Data-quality reports and id audits"),
+        (name = "admin", description = "Usage and cost reports"),
+        (name = "privacy", description = "Personal-data export and erasure"),
+        (name = "health", description = "Liveness and readiness probes"),
+    )
+)]
+pub(crate) struct ApiDoc;
+
+/// Documents the two ways `auth::authorize` accepts an API key.
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Lists the routes under the stable `/api/v1` prefix; the unversioned paths
+/// the handlers are annotated with are deprecated aliases.
+struct VersionPrefix;
+
+impl Modify for VersionPrefix {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info.version = "1".to_string();
+        let paths = std::mem::take(&mut openapi.paths.paths);
+        openapi.paths.paths = paths
+            .into_iter()
+            .map(|(path, item)| (versioning::versioned_path(&path), item))
+            .collect();
+    }
+}
+
+/// `/api/openapi.json` and the Swagger UI under `/api/docs`.
+pub(crate) fn routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    SwaggerUi::new("/api/docs")
+        .url("/api/openapi.json", ApiDoc::openapi())
+        .into()
+}
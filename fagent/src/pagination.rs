@@ -0,0 +1,53 @@
+//! Opaque cursors for the paged graph endpoints.
+//!
+//! A cursor only has to remember where the previous page stopped.
+//! `/api/graph/overview` walks node ids in ascending order, so that is the last
+//! id it visited. `/api/graph/search` walks each entity type newest first with
+//! ties broken by id, so it also keeps the entity type being scanned and the
+//! `updated_at` of the last match.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::ApiError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GraphCursor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) entity_type: Option<String>,
+    pub(crate) after: String,
+    /// `updated_at` of the last match, in microseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<i64>,
+}
+
+impl GraphCursor {
+    pub(crate) fn new(entity_type: Option<String>, after: impl Into<String>) -> Self {
+        Self {
+            entity_type,
+            after: after.into(),
+            updated_at: None,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> String {
+        let raw = serde_json::to_vec(self).expect("cursor serializes to json");
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub(crate) fn decode(token: &str) -> Result<Self, ApiError> {
+        let invalid = || ApiError::BadRequest(format!("invalid cursor '{token}'"));
+        let raw = URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .map_err(|_| invalid())?;
+        serde_json::from_slice(&raw).map_err(|_| invalid())
+    }
+
+    /// Decodes an optional `cursor` query parameter, treating blanks as absent.
+    pub(crate) fn from_query(token: Option<&str>) -> Result<Option<Self>, ApiError> {
+        match token.map(str::trim).filter(|token| !token.is_empty()) {
+            Some(token) => Self::decode(token).map(Some),
+            None => Ok(None),
+        }
+    }
+}
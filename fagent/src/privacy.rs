@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use fstorage::models::{PersonalDataErasure, PersonalDataExport};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::IntoParams;
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct PrivacyQuery {
+    /// Developer login the request is about.
+    #[serde(default)]
+    login: Option<String>,
+}
+
+impl PrivacyQuery {
+    fn login(&self) -> ApiResult<&str> {
+        self.login
+            .as_deref()
+            .map(str::trim)
+            .filter(|login| !login.is_empty())
+            .ok_or_else(|| ApiError::BadRequest("login is required".to_string()))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/privacy/export",
+    tag = "privacy",
+    params(PrivacyQuery),
+    responses(
+        (status = 200, description = "Every stored row and node about the developer", body = Object),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn export_personal_data(
+    State(state): State<AppState>,
+    Query(query): Query<PrivacyQuery>,
+) -> ApiResult<Json<PersonalDataExport>> {
+    let export = state
+        .storage
+        .lake
+        .personal_data(query.login()?)
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(export))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/privacy/erase",
+    tag = "privacy",
+    params(PrivacyQuery),
+    responses(
+        (status = 200, description = "Rows and nodes deleted or scrubbed", body = Object),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn erase_personal_data(
+    State(state): State<AppState>,
+    Query(query): Query<PrivacyQuery>,
+) -> ApiResult<Json<PersonalDataErasure>> {
+    let login = query.login()?;
+    // Snapshots are scrubbed through stores of their own, so mounted ones are
+    // closed for the duration.
+    let erasure = state
+        .snapshots
+        .unmounted(&state.storage, state.storage.erase_personal_data(login))
+        .await?
+        .map_err(ApiError::from_storage)?;
+    info!(
+        login,
+        rows_deleted = erasure.rows_deleted.values().sum::<usize>(),
+        rows_updated = erasure.rows_updated.values().sum::<usize>(),
+        engine_nodes_removed = erasure.engine_nodes_removed,
+        engine_vectors_removed = erasure.engine_vectors_removed,
+        snapshots = erasure.snapshots.len(),
+        "erased personal data"
+    );
+    Ok(Json(erasure))
+}
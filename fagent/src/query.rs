@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use fstorage::{lake::TableRowStream, models::TableQueryResult};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    ndjson::{self, StreamFormat},
+    ApiError, ApiResult, AppState, ErrorResponse,
+};
+
+const DEFAULT_ROW_LIMIT: usize = 1_000;
+const MAX_ROW_LIMIT: usize = 10_000;
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const MAX_TIMEOUT_MS: u64 = 60_000;
+const DEFAULT_PREVIEW_ROWS: usize = 20;
+const MAX_PREVIEW_ROWS: usize = 1_000;
+
+#[derive(Clone, Deserialize, ToSchema)]
+pub(crate) struct SqlQueryRequest {
+    /// Lake table path, e.g. `silver/entities/project` or `foreign/<name>`.
+    table: String,
+    /// DataFusion SQL, or DuckDB SQL when the server runs with `--sql-engine duckdb`
+    /// (the response's `engine` says which ran it); `{{table}}` is replaced with
+    /// the registered table name. Attached foreign tables can be joined as
+    /// `foreign_<name>`, which always runs on DataFusion.
+    sql: String,
+    /// Row cap; without `stream` it defaults to 1000 and is at most 10000.
+    #[serde(default)]
+    limit: Option<usize>,
+    /// With `stream`, bounds planning the query rather than producing its rows.
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    /// `ndjson` streams a `columns` line, then a `row` line per row as the
+    /// query produces it, always on DataFusion and uncapped unless `limit` is set.
+    #[serde(default)]
+    stream: Option<StreamFormat>,
+}
+
+/// A line of a `stream: ndjson` query result.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum QueryLine {
+    Columns { columns: Vec<String> },
+    Row { row: HashMap<String, JsonValue> },
+}
+
+#[derive(Serialize)]
+pub(crate) struct SqlQueryResponse {
+    table: String,
+    #[serde(flatten)]
+    result: TableQueryResult,
+    row_count: usize,
+    elapsed_ms: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/query",
+    tag = "storage",
+    request_body = SqlQueryRequest,
+    responses(
+        (status = 200, description = "Columns, rows and whether the result was truncated, or NDJSON lines with `stream: ndjson`", body = Object),
+        (status = 400, body = ErrorResponse),
+        (status = 504, description = "The query ran past its timeout", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn run_sql_query(
+    State(state): State<AppState>,
+    Json(body): Json<SqlQueryRequest>,
+) -> ApiResult<Response> {
+    if body.sql.trim().is_empty() {
+        return Err(ApiError::BadRequest("sql must not be empty".to_string()));
+    }
+    let timeout = Duration::from_millis(
+        body.timeout_ms
+            .unwrap_or(DEFAULT_TIMEOUT_MS)
+            .clamp(1, MAX_TIMEOUT_MS),
+    );
+    let timed_out = |_| {
+        ApiError::Timeout(format!(
+            "query did not finish within {} ms",
+            timeout.as_millis()
+        ))
+    };
+
+    if body.stream == Some(StreamFormat::Ndjson) {
+        let query = state.storage.lake.stream_table_sql(
+            &body.table,
+            &body.sql,
+            body.limit.map(|limit| limit.max(1)),
+        );
+        let rows = tokio::time::timeout(timeout, query)
+            .await
+            .map_err(timed_out)?
+            .map_err(ApiError::from_storage)?;
+        return Ok(stream_rows(rows));
+    }
+
+    let limit = body
+        .limit
+        .unwrap_or(DEFAULT_ROW_LIMIT)
+        .clamp(1, MAX_ROW_LIMIT);
+    let started = Instant::now();
+    let query = state
+        .storage
+        .lake
+        .query_table_sql(&body.table, &body.sql, limit);
+    let result = tokio::time::timeout(timeout, query)
+        .await
+        .map_err(timed_out)?
+        .map_err(ApiError::from_storage)?;
+
+    Ok(Json(SqlQueryResponse {
+        table: body.table,
+        row_count: result.rows.len(),
+        result,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+    .into_response())
+}
+
+fn stream_rows(TableRowStream { columns, rows }: TableRowStream) -> Response {
+    let rows = rows.flat_map(|batch| {
+        let lines: Vec<Result<QueryLine, String>> = match batch {
+            Ok(rows) => rows
+                .into_iter()
+                .map(|row| Ok(QueryLine::Row { row }))
+                .collect(),
+            Err(err) => vec![Err(err.to_string())],
+        };
+        stream::iter(lines)
+    });
+    ndjson::response(stream::iter([Ok(QueryLine::Columns { columns })]).chain(rows))
+}
+
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct TablePreviewQuery {
+    /// Rows to return; defaults to 20, at most 1000.
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct TablePreviewResponse {
+    table: String,
+    limit: usize,
+    row_count: usize,
+    rows: Vec<HashMap<String, JsonValue>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/tables/{path}/preview",
+    tag = "storage",
+    params(
+        ("path" = String, Path, description = "Lake table path, e.g. `silver/entities/project`"),
+        TablePreviewQuery
+    ),
+    responses(
+        (status = 200, description = "The table's first rows", body = Object),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn preview_table(
+    State(state): State<AppState>,
+    Path(rest): Path<String>,
+    Query(query): Query<TablePreviewQuery>,
+) -> ApiResult<Json<TablePreviewResponse>> {
+    let Some(table) = rest.strip_suffix("/preview") else {
+        return Err(ApiError::NotFound(format!(
+            "no route for /api/tables/{rest}"
+        )));
+    };
+    let lake = &state.storage.lake;
+    if !lake
+        .table_exists(table)
+        .await
+        .map_err(ApiError::from_storage)?
+    {
+        return Err(ApiError::NotFound(format!("table '{table}' not found")));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PREVIEW_ROWS)
+        .clamp(1, MAX_PREVIEW_ROWS);
+    let rows = lake
+        .query_table(table, None, Some(limit))
+        .await
+        .map_err(ApiError::from_storage)?;
+
+    Ok(Json(TablePreviewResponse {
+        table: table.to_string(),
+        limit,
+        row_count: rows.len(),
+        rows,
+    }))
+}
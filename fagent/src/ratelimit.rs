@@ -0,0 +1,182 @@
+//! Token-bucket rate limiting for the expensive API routes.
+//!
+//! Starting syncs spends GitHub quota and searching calls the embedding
+//! provider, so those routes draw from a bucket per caller: the API key when
+//! it is one of the configured keys, the peer IP otherwise, or the peer's user
+//! id on a Unix socket. Keys nobody granted are ignored, so making them up
+//! does not buy fresh buckets. Other routes are never limited. The limiter is
+//! off unless a [`RateLimit`] is configured.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{admin::request_api_key, ApiError, AppState};
+
+/// Buckets above this count are pruned of callers that are back to full.
+const MAX_TRACKED_CALLERS: usize = 10_000;
+
+/// Sustained rate and burst allowed per caller on the limited routes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    /// Requests a caller may make back to back; defaults to `per_minute`.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn per_minute(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            burst: per_minute,
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        f64::from(self.per_minute) / 60.0
+    }
+}
+
+/// Whether `method path` (a route template) draws from the caller's bucket.
+fn is_limited(method: &Method, path: &str) -> bool {
+    matches!(
+        (method.as_str(), path),
+        ("POST", "/api/sync")
+            | ("POST", "/api/schedules/:name/run")
+            | ("POST", "/api/onboard")
+            | ("POST", "/api/sync/jobs/:job_id/resume")
+            | ("POST", "/api/readiness")
+            | ("GET", "/api/graph/search")
+            | ("GET", "/api/search/hybrid_all")
+            | ("GET", "/api/search/vector")
+            | ("GET", "/api/search/bm25")
+            | ("GET", "/api/saved_searches/:name/results")
+            | ("GET", "/api/federation/search/hybrid_all")
+    )
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    limit: Option<RateLimit>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit: Some(limit),
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes a token for `caller`, or returns how long until one is available.
+    fn acquire(&self, caller: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        let burst = f64::from(limit.burst.max(1));
+        let refill = limit.refill_per_sec();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CALLERS && !buckets.contains_key(caller) {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + refill * now.duration_since(bucket.updated).as_secs_f64() < burst
+            });
+        }
+
+        let bucket = buckets.entry(caller.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + refill * elapsed).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if refill <= 0.0 {
+            return Err(Duration::from_secs(60));
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill))
+    }
+}
+
+/// Route template the request is matched against. Requests forwarded to a
+/// mounted snapshot are classified by the route they reach inside the mount.
+fn route_of(request: &Request) -> String {
+    let matched = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str())
+        .unwrap_or_else(|| request.uri().path());
+    if matched == "/api/mounts/:name/*rest" {
+        let path = request.uri().path();
+        let rest = path
+            .strip_prefix("/api/mounts/")
+            .and_then(|rest| rest.find('/').map(|idx| &rest[idx..]));
+        if let Some(rest) = rest {
+            return format!("/api{rest}");
+        }
+    }
+    matched.to_string()
+}
+
+/// Bucket the request draws from.
+fn caller_of(state: &AppState, request: &Request) -> String {
+    if let Some(key) = request_api_key(request.headers()) {
+        if state.access.role_of(&key).is_some() {
+            return format!("key:{key}");
+        }
+    }
+    if let Some(ConnectInfo(addr)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        return format!("ip:{}", addr.ip());
+    }
+    #[cfg(unix)]
+    if let Some(uid) = request
+        .extensions()
+        .get::<crate::unix_socket::UnixPeer>()
+        .and_then(|peer| peer.uid)
+    {
+        return format!("uid:{uid}");
+    }
+    "ip:unknown".to_string()
+}
+
+/// Middleware answering `429 Too Many Requests` once a caller's bucket is empty.
+pub(crate) async fn limit_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_limited(request.method(), &route_of(&request)) {
+        return next.run(request).await;
+    }
+
+    let caller = caller_of(&state, &request);
+    if let Err(wait) = state.limiter.acquire(&caller, Instant::now()) {
+        let retry_after = (wait.as_secs_f64().ceil() as u64).max(1);
+        let mut response =
+            ApiError::TooManyRequests(format!("rate limit exceeded, retry in {retry_after}s"))
+                .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        return response;
+    }
+    next.run(request).await
+}
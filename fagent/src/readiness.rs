@@ -0,0 +1,398 @@
+//! Entity readiness checks.
+//!
+//! `POST /api/readiness` takes either a bare list of entities, answered with a
+//! report per entity URI, or an object with `entities` and `auto_sync`. With
+//! `auto_sync`, each entity that comes back stale or missing gets its own
+//! interactive sync through the entity's `fetcher_name` and `params`, bounded
+//! by the request's `budget`. Entities that could not be queued are listed
+//! with the reason rather than failing the whole check.
+//!
+//! Hybrid searches given a `max_age` run the same kind of check over the
+//! projects their top hits come from, see [`check_answer_freshness`], so an
+//! answer built on a repository nobody synced for weeks says so.
+//!
+//! An entity that a queued or running sync already targets is not queued
+//! again; that sync is reported in its place.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{extract::State, Json};
+use chrono::Utc;
+use fstorage::{
+    fetch::Fetchable,
+    models::{EntityIdentifier, MultiEntitySearchHit, ReadinessReport, SyncJobState},
+    schemas::generated_schemas::Project,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    queue_sync, ApiError, ApiResult, AppState, ErrorCode, ErrorResponse, SyncBudgetPayload,
+    SyncPriority, SyncRequest,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ReadinessBody {
+    Entities(Vec<EntityIdentifier>),
+    Request(ReadinessRequest),
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct ReadinessRequest {
+    #[schema(value_type = Vec<Object>)]
+    entities: Vec<EntityIdentifier>,
+    /// Queue a sync for every entity that is not fresh.
+    #[serde(default)]
+    auto_sync: bool,
+    /// Budget of each queued sync, in the `POST /api/sync` format; unlimited
+    /// by default.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    budget: Option<SyncBudgetPayload>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ReadinessResponse {
+    /// Report per entity URI.
+    #[schema(value_type = Object)]
+    readiness: HashMap<String, ReadinessReport>,
+    /// Syncs queued by `auto_sync`, or already under way for the entity.
+    syncs: Vec<ReadinessSync>,
+    /// Stale entities `auto_sync` left alone.
+    not_queued: Vec<ReadinessSkip>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub(crate) struct ReadinessSync {
+    uri: String,
+    fetcher: String,
+    job_id: i64,
+    status_url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ReadinessSkip {
+    uri: String,
+    code: ErrorCode,
+    reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub(crate) enum ReadinessReply {
+    Reports(HashMap<String, ReadinessReport>),
+    Response(ReadinessResponse),
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/readiness",
+    tag = "sync",
+    request_body(
+        content = ReadinessRequest,
+        description = "Entities to check; a bare list of entities is also accepted and answered with the `readiness` map alone"
+    ),
+    responses(
+        (status = 200, description = "Readiness report per entity and the syncs queued for stale ones", body = ReadinessResponse),
+        (status = 400, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn check_readiness(
+    State(state): State<AppState>,
+    Json(body): Json<ReadinessBody>,
+) -> ApiResult<Json<ReadinessReply>> {
+    let request = match body {
+        ReadinessBody::Entities(entities) => {
+            let readiness = state
+                .storage
+                .get_readiness(&entities)
+                .await
+                .map_err(ApiError::from_storage)?;
+            return Ok(Json(ReadinessReply::Reports(readiness)));
+        }
+        ReadinessBody::Request(request) => request,
+    };
+
+    let readiness = state
+        .storage
+        .get_readiness(&request.entities)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let mut response = ReadinessResponse {
+        readiness,
+        syncs: Vec::new(),
+        not_queued: Vec::new(),
+    };
+    if request.auto_sync {
+        queue_stale(&state, &request, &mut response)?;
+    }
+    Ok(Json(ReadinessReply::Response(response)))
+}
+
+/// Queues one sync per stale entity, recording which were queued and which
+/// were not.
+fn queue_stale(
+    state: &AppState,
+    request: &ReadinessRequest,
+    response: &mut ReadinessResponse,
+) -> ApiResult<()> {
+    let active = active_syncs(state)?;
+    let mut seen = HashSet::new();
+    for entity in &request.entities {
+        if !seen.insert(entity.uri.as_str()) {
+            continue;
+        }
+        let fresh = response
+            .readiness
+            .get(&entity.uri)
+            .is_some_and(|report| report.is_fresh);
+        if fresh {
+            continue;
+        }
+        match queue_entity_sync(state, &active, entity, request.budget.clone()) {
+            Ok(sync) => response.syncs.push(sync),
+            Err(skip) => response.not_queued.push(skip),
+        }
+    }
+    Ok(())
+}
+
+/// Queued and running jobs read when looking for syncs already under way.
+const ACTIVE_SCAN_LIMIT: usize = 1_000;
+
+/// The queued or running sync of each entity URI such a sync targets.
+fn active_syncs(state: &AppState) -> ApiResult<HashMap<String, ReadinessSync>> {
+    let mut active = HashMap::new();
+    for job_state in [SyncJobState::Running, SyncJobState::Queued] {
+        let jobs = state
+            .storage
+            .catalog
+            .list_sync_jobs(Some(job_state), ACTIVE_SCAN_LIMIT)
+            .map_err(ApiError::from_storage)?;
+        for job in jobs {
+            let Ok(request) = serde_json::from_str::<SyncRequest>(&job.request) else {
+                continue;
+            };
+            for entity in request.target_entities {
+                active.entry(entity.uri.clone()).or_insert(ReadinessSync {
+                    uri: entity.uri,
+                    fetcher: job.fetcher.clone(),
+                    job_id: job.job_id,
+                    status_url: format!("/api/sync/jobs/{}", job.job_id),
+                });
+            }
+        }
+    }
+    Ok(active)
+}
+
+/// Queues an interactive sync of `entity` through its own fetcher and params,
+/// unless `active` shows one under way already.
+fn queue_entity_sync(
+    state: &AppState,
+    active: &HashMap<String, ReadinessSync>,
+    entity: &EntityIdentifier,
+    budget: Option<SyncBudgetPayload>,
+) -> Result<ReadinessSync, ReadinessSkip> {
+    if let Some(sync) = active.get(&entity.uri) {
+        info!(
+            uri = %entity.uri,
+            job_id = sync.job_id,
+            "stale entity already has a sync under way"
+        );
+        return Ok(sync.clone());
+    }
+    let Some(fetcher) = entity.fetcher_name.clone() else {
+        return Err(ReadinessSkip {
+            uri: entity.uri.clone(),
+            code: ErrorCode::InvalidArgument,
+            reason: "entity has no fetcher_name to sync it with".to_string(),
+        });
+    };
+    let sync = SyncRequest {
+        fetcher: fetcher.clone(),
+        params: entity.params.clone().unwrap_or_default(),
+        triggering_query: None,
+        target_entities: vec![entity.clone()],
+        entity_types: Vec::new(),
+        budget,
+        priority: SyncPriority::Interactive,
+        then: Vec::new(),
+    };
+    match queue_sync(state, sync) {
+        Ok(accepted) => {
+            info!(
+                uri = %entity.uri,
+                fetcher = %fetcher,
+                job_id = accepted.job_id,
+                "queued sync for stale entity"
+            );
+            Ok(ReadinessSync {
+                uri: entity.uri.clone(),
+                fetcher,
+                job_id: accepted.job_id,
+                status_url: accepted.status_url,
+            })
+        }
+        Err(err) => Err(ReadinessSkip {
+            uri: entity.uri.clone(),
+            code: err.code(),
+            reason: err.to_string(),
+        }),
+    }
+}
+
+/// Retrieved hits, best first, whose projects an answer-freshness check reads.
+const FRESHNESS_TOP_HITS: usize = 5;
+/// Fetcher that refreshes a stale project, as for GitHub webhooks.
+pub(crate) const PROJECT_FETCHER: &str = "gitfetcher";
+pub(crate) const GITHUB_PREFIX: &str = "https://github.com/";
+
+/// How fresh the projects behind a search's top hits are.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct AnswerFreshness {
+    /// Seconds since its last sync after which a project counts as stale.
+    max_age: u64,
+    /// Projects of the top hits, ordered by their best hit.
+    projects: Vec<ProjectFreshness>,
+    /// The hits were withheld because a project is stale.
+    blocked: bool,
+    /// Syncs queued for stale projects by `auto_sync`, or already under way.
+    syncs: Vec<ReadinessSync>,
+    /// Stale projects `auto_sync` could not queue.
+    not_queued: Vec<ReadinessSkip>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ProjectFreshness {
+    project_url: String,
+    /// Top hits that belong to the project.
+    hits: usize,
+    /// Seconds since the project was last synced; absent if it never was.
+    age_seconds: Option<i64>,
+    stale: bool,
+}
+
+/// Checks when the projects of the top hits were last synced against
+/// `max_age`. A hit belongs to the project it is, or to the one its
+/// `project_url` names; hits with neither are not counted.
+///
+/// With `block`, a stale project empties `hits`. With `auto_sync`, each stale
+/// project hosted on GitHub gets an interactive repository snapshot sync,
+/// which also records it as synced once it finishes, unless one is queued or
+/// running already. Callers check that the requester may queue syncs.
+pub(crate) fn check_answer_freshness(
+    state: &AppState,
+    hits: &mut Vec<MultiEntitySearchHit>,
+    max_age: u64,
+    block: bool,
+    auto_sync: bool,
+) -> ApiResult<AnswerFreshness> {
+    let mut projects: Vec<ProjectFreshness> = Vec::new();
+    let top_hits = hits
+        .iter()
+        .filter(|hit| hit.expansion.is_none())
+        .take(FRESHNESS_TOP_HITS);
+    for url in top_hits.filter_map(hit_project) {
+        match projects
+            .iter_mut()
+            .find(|project| project.project_url == url)
+        {
+            Some(project) => project.hits += 1,
+            None => projects.push(ProjectFreshness {
+                project_url: url.to_string(),
+                hits: 1,
+                age_seconds: None,
+                stale: true,
+            }),
+        }
+    }
+
+    let now = Utc::now().timestamp();
+    for project in &mut projects {
+        let last_synced = state
+            .storage
+            .catalog
+            .get_readiness(&project.project_url)
+            .map_err(ApiError::from_storage)?
+            .and_then(|readiness| readiness.last_synced_at);
+        project.age_seconds = last_synced.map(|synced| (now - synced).max(0));
+        project.stale = project
+            .age_seconds
+            .is_none_or(|age| age.unsigned_abs() > max_age);
+    }
+
+    let mut freshness = AnswerFreshness {
+        max_age,
+        projects,
+        blocked: false,
+        syncs: Vec::new(),
+        not_queued: Vec::new(),
+    };
+    let stale: Vec<&str> = freshness
+        .projects
+        .iter()
+        .filter(|project| project.stale)
+        .map(|project| project.project_url.as_str())
+        .collect();
+    if auto_sync && !stale.is_empty() {
+        let active = active_syncs(state)?;
+        for url in &stale {
+            let queued = match project_entity(url) {
+                Some(entity) => queue_entity_sync(state, &active, &entity, None),
+                None => Err(ReadinessSkip {
+                    uri: url.to_string(),
+                    code: ErrorCode::InvalidArgument,
+                    reason: "only projects hosted on GitHub can be synced".to_string(),
+                }),
+            };
+            match queued {
+                Ok(sync) => freshness.syncs.push(sync),
+                Err(skip) => freshness.not_queued.push(skip),
+            }
+        }
+    }
+    if block && !stale.is_empty() {
+        info!(projects = ?stale, "withheld search hits from stale projects");
+        hits.clear();
+        freshness.blocked = true;
+    }
+    Ok(freshness)
+}
+
+/// URL of the project a hit is, or belongs to.
+fn hit_project(hit: &MultiEntitySearchHit) -> Option<&str> {
+    let record = hit.node.as_ref().or(hit.vector.as_ref())?;
+    let key = if hit.entity_type == Project::ENTITY_TYPE {
+        "url"
+    } else {
+        "project_url"
+    };
+    record
+        .get("properties")?
+        .get(key)?
+        .as_str()
+        .filter(|url| !url.is_empty())
+}
+
+/// The readiness entity a repository snapshot sync of a GitHub project
+/// refreshes.
+pub(crate) fn project_entity(url: &str) -> Option<EntityIdentifier> {
+    let repo = url
+        .strip_prefix(GITHUB_PREFIX)?
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+    if repo.split('/').count() != 2 {
+        return None;
+    }
+    Some(EntityIdentifier {
+        uri: url.to_string(),
+        entity_type: Project::ENTITY_TYPE.to_string(),
+        fetcher_name: Some(PROJECT_FETCHER.to_string()),
+        params: Some(json!({"mode": "repo_snapshot", "repo": repo})),
+        anchor_key: None,
+    })
+}
@@ -0,0 +1,162 @@
+//! A project's README, reassembled from its stored chunks.
+//!
+//! The git fetcher stores a README as ReadmeChunk rows, one set per fetched
+//! revision, cut at line boundaries without overlap. Joining one revision's
+//! chunks in `chunk_order` gives back the document, so clients get the text
+//! without fetching and stitching the chunks themselves.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, FixedOffset};
+use fstorage::{
+    fetch::Fetchable, schemas::generated_schemas::ReadmeChunk, utils::source_url::source_url,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+#[derive(Deserialize, IntoParams)]
+pub(crate) struct ReadmeQuery {
+    /// Revision sha to read; the most recently committed one by default.
+    #[serde(default)]
+    version: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ReadmeResponse {
+    project_id: String,
+    project_url: String,
+    /// Revision sha the text was read at.
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_file: Option<String>,
+    /// The file on GitHub at the revision, for GitHub projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_url: Option<String>,
+    text: String,
+    chunk_count: usize,
+    /// Revisions with a stored README, newest first.
+    versions: Vec<String>,
+}
+
+/// The chunks stored for one revision, keyed by `chunk_order`.
+#[derive(Default)]
+struct Revision {
+    committed_at: Option<DateTime<FixedOffset>>,
+    source_file: Option<String>,
+    chunks: BTreeMap<i64, String>,
+}
+
+fn string_field<'a>(row: &'a HashMap<String, JsonValue>, key: &str) -> Option<&'a str> {
+    row.get(key).and_then(JsonValue::as_str)
+}
+
+/// URL of the project node `id`; 404 when `id` is not a project.
+pub(crate) async fn resolve_project_url(state: &AppState, id: &str) -> ApiResult<String> {
+    let node = state
+        .storage
+        .lake
+        .get_node_by_id(id, None)
+        .await
+        .map_err(ApiError::from_storage)?
+        .filter(|node| {
+            string_field(node, "label").is_some_and(|label| label.eq_ignore_ascii_case("project"))
+        })
+        .ok_or_else(|| ApiError::NotFound(format!("project '{id}' not found")))?;
+    node.get("properties")
+        .and_then(|properties| properties.get("url"))
+        .and_then(JsonValue::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| ApiError::NotFound(format!("project '{id}' has no url")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/readme",
+    tag = "graph",
+    params(("id" = String, Path, description = "Project node id"), ReadmeQuery),
+    responses(
+        (status = 200, description = "The README at the requested revision", body = ReadmeResponse),
+        (status = 304, description = "Unchanged since the If-None-Match ETag"),
+        (status = 404, description = "No such project, or no README stored at the revision", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_project_readme(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ReadmeQuery>,
+) -> ApiResult<Json<ReadmeResponse>> {
+    let lake = &state.storage.lake;
+    let project_url = resolve_project_url(&state, &id).await?;
+
+    let rows = lake
+        .query_table(
+            &ReadmeChunk::table_name(),
+            Some(&[("project_url", project_url.as_str())]),
+            None,
+        )
+        .await
+        .map_err(ApiError::from_storage)?;
+    let mut revisions: HashMap<String, Revision> = HashMap::new();
+    for row in &rows {
+        let (Some(sha), Some(text)) =
+            (string_field(row, "revision_sha"), string_field(row, "text"))
+        else {
+            continue;
+        };
+        let order = row
+            .get("chunk_order")
+            .and_then(JsonValue::as_i64)
+            .unwrap_or_default();
+        let revision = revisions.entry(sha.to_string()).or_default();
+        revision.committed_at = revision.committed_at.or_else(|| {
+            string_field(row, "created_at")
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        });
+        if revision.source_file.is_none() {
+            revision.source_file = string_field(row, "source_file").map(str::to_string);
+        }
+        revision.chunks.insert(order, text.to_string());
+    }
+
+    let mut versions: Vec<(Option<DateTime<FixedOffset>>, String)> = revisions
+        .iter()
+        .map(|(sha, revision)| (revision.committed_at, sha.clone()))
+        .collect();
+    versions.sort_by(|a, b| b.cmp(a));
+    let versions: Vec<String> = versions.into_iter().map(|(_, sha)| sha).collect();
+
+    let version = match query.version.filter(|version| !version.trim().is_empty()) {
+        Some(version) => version,
+        None => versions
+            .first()
+            .cloned()
+            .ok_or_else(|| ApiError::NotFound(format!("no README stored for project '{id}'")))?,
+    };
+    let revision = revisions.remove(&version).ok_or_else(|| {
+        ApiError::NotFound(format!(
+            "no README stored for project '{id}' at version '{version}'"
+        ))
+    })?;
+
+    let source_url = revision
+        .source_file
+        .as_deref()
+        .and_then(|path| source_url(&project_url, &version, path, None, None));
+    Ok(Json(ReadmeResponse {
+        project_id: id,
+        project_url,
+        version,
+        source_file: revision.source_file,
+        source_url,
+        chunk_count: revision.chunks.len(),
+        text: revision.chunks.into_values().collect::<Vec<_>>().join("\n"),
+        versions,
+    }))
+}
@@ -0,0 +1,293 @@
+//! Tracked projects related to a given one.
+//!
+//! Three signals are combined, each scaled to 0..1 and weighted equally:
+//! the share of the project's libraries another project also depends on, the
+//! share of its contributors who also contribute there, and the best
+//! similarity between their README chunk embeddings. Libraries are read from
+//! the head version's files when the project has one, from every version
+//! otherwise.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use fstorage::{
+    fetch::Fetchable,
+    lake::Lake,
+    schemas::generated_schemas::{
+        Contains, ContainsContent, ContributesTo, DependsOn, HasVersion, Project, ReadmeChunk,
+    },
+    utils,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    map_node_summary, readme::resolve_project_url, ApiError, ApiResult, AppState, ErrorResponse,
+    GraphNodeSummary,
+};
+
+/// README chunks of the project compared against other projects' chunks.
+const README_CHUNKS: usize = 8;
+/// Nearest chunks looked up per README chunk.
+const README_NEIGHBORS: usize = 20;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct RelatedQuery {
+    /// Projects to return, 10 by default and at most 50.
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct RelatedProject {
+    project: GraphNodeSummary,
+    /// Combined score, 0 to 1.
+    score: f32,
+    shared_libraries: usize,
+    shared_contributors: usize,
+    /// Best similarity between the two READMEs' chunks, 0 to 1.
+    readme_similarity: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct RelatedProjectsResponse {
+    project_id: String,
+    project_url: String,
+    /// Best match first.
+    related: Vec<RelatedProject>,
+}
+
+#[derive(Default)]
+struct Signals {
+    libraries: usize,
+    contributors: usize,
+    readme: f32,
+}
+
+/// Ids at the other end of `node_id`'s `edge_type` edges.
+pub(crate) async fn linked(
+    lake: &Lake,
+    node_id: &str,
+    edge_type: &str,
+    outgoing: bool,
+) -> ApiResult<Vec<String>> {
+    let (edges, end) = if outgoing {
+        (
+            lake.get_out_edges(node_id, Some(edge_type)).await,
+            "to_node_id",
+        )
+    } else {
+        (
+            lake.get_in_edges(node_id, Some(edge_type)).await,
+            "from_node_id",
+        )
+    };
+    Ok(edges
+        .map_err(ApiError::from_storage)?
+        .iter()
+        .filter_map(|edge| edge.get(end).and_then(JsonValue::as_str))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Files of `project_id`'s head version, or of all its versions when none is
+/// marked as head.
+pub(crate) async fn head_files(lake: &Lake, project_id: &str) -> ApiResult<BTreeSet<String>> {
+    let versions = linked(lake, project_id, HasVersion::ENTITY_TYPE, true).await?;
+    let nodes = lake
+        .get_nodes_by_ids(&versions)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let heads: Vec<&String> = versions
+        .iter()
+        .filter(|id| {
+            nodes
+                .get(*id)
+                .and_then(|node| node.get("properties"))
+                .and_then(|properties| properties.get("is_head"))
+                .and_then(JsonValue::as_bool)
+                == Some(true)
+        })
+        .collect();
+    let versions = if heads.is_empty() {
+        versions.iter().collect()
+    } else {
+        heads
+    };
+
+    let mut files = BTreeSet::new();
+    for version in versions {
+        files.extend(linked(lake, version, Contains::ENTITY_TYPE, true).await?);
+    }
+    Ok(files)
+}
+
+/// Libraries the files of `project_id`'s head version depend on.
+pub(crate) async fn project_libraries(
+    lake: &Lake,
+    project_id: &str,
+) -> ApiResult<BTreeSet<String>> {
+    let mut libraries = BTreeSet::new();
+    for file in head_files(lake, project_id).await? {
+        libraries.extend(linked(lake, &file, DependsOn::ENTITY_TYPE, true).await?);
+    }
+    Ok(libraries)
+}
+
+/// Counts, per other project, the libraries it shares with `libraries`.
+async fn shared_libraries(
+    lake: &Lake,
+    libraries: &BTreeSet<String>,
+    signals: &mut HashMap<String, Signals>,
+) -> ApiResult<()> {
+    let mut project_of_version: HashMap<String, Vec<String>> = HashMap::new();
+    for library in libraries {
+        let mut dependents = HashSet::new();
+        for file in linked(lake, library, DependsOn::ENTITY_TYPE, false).await? {
+            for version in linked(lake, &file, Contains::ENTITY_TYPE, false).await? {
+                if !project_of_version.contains_key(&version) {
+                    let projects = linked(lake, &version, HasVersion::ENTITY_TYPE, false).await?;
+                    project_of_version.insert(version.clone(), projects);
+                }
+                dependents.extend(project_of_version[&version].iter().cloned());
+            }
+        }
+        for project in dependents {
+            signals.entry(project).or_default().libraries += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Best similarity between `project_id`'s README chunks and those of each
+/// project near them, by project id.
+pub(crate) async fn readme_neighbors(
+    lake: &Lake,
+    project_id: &str,
+) -> ApiResult<HashMap<String, f32>> {
+    let mut best: HashMap<String, f32> = HashMap::new();
+    let chunks = linked(lake, project_id, ContainsContent::ENTITY_TYPE, true).await?;
+    for chunk in chunks.iter().take(README_CHUNKS) {
+        let hits = lake
+            .search_vectors_near(ReadmeChunk::ENTITY_TYPE, chunk, README_NEIGHBORS)
+            .await
+            .map_err(ApiError::from_storage)?;
+        for hit in hits {
+            let Some(url) = hit
+                .vector
+                .get("properties")
+                .and_then(|properties| properties.get("project_url"))
+                .and_then(JsonValue::as_str)
+            else {
+                continue;
+            };
+            let project = Uuid::from_u128(utils::id::stable_node_id_u128(
+                Project::ENTITY_TYPE,
+                &[("url", url.to_string())],
+            ))
+            .to_string();
+            let entry = best.entry(project).or_default();
+            *entry = entry.max(hit.similarity);
+        }
+    }
+    Ok(best)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/related",
+    tag = "graph",
+    params(("id" = String, Path, description = "Project node id"), RelatedQuery),
+    responses(
+        (status = 200, description = "Related projects, best match first", body = RelatedProjectsResponse),
+        (status = 304, description = "Unchanged since the If-None-Match ETag"),
+        (status = 404, description = "No such project", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_related_projects(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<RelatedQuery>,
+) -> ApiResult<Json<RelatedProjectsResponse>> {
+    let project_url = resolve_project_url(&state, &id).await?;
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let lake = &state.storage.lake;
+
+    let mut signals: HashMap<String, Signals> = HashMap::new();
+    let libraries = project_libraries(lake, &id).await?;
+    shared_libraries(lake, &libraries, &mut signals).await?;
+
+    let contributors = linked(lake, &id, ContributesTo::ENTITY_TYPE, false).await?;
+    let contributors: BTreeSet<String> = contributors.into_iter().collect();
+    for developer in &contributors {
+        let projects: HashSet<String> = linked(lake, developer, ContributesTo::ENTITY_TYPE, true)
+            .await?
+            .into_iter()
+            .collect();
+        for project in projects {
+            signals.entry(project).or_default().contributors += 1;
+        }
+    }
+
+    for (project, similarity) in readme_neighbors(lake, &id).await? {
+        signals.entry(project).or_default().readme = similarity;
+    }
+
+    // The project's own id may differ in case from the one it was asked by.
+    let own_id = Uuid::parse_str(&id).map_or(id.clone(), |uuid| uuid.to_string());
+    signals.remove(&own_id);
+    let share = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f32 / total as f32
+        }
+    };
+    let mut scored: Vec<(String, f32, Signals)> = signals
+        .into_iter()
+        .map(|(project, signals)| {
+            let score = (share(signals.libraries, libraries.len())
+                + share(signals.contributors, contributors.len())
+                + signals.readme)
+                / 3.0;
+            (project, score, signals)
+        })
+        .filter(|(_, score, _)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let ids: Vec<String> = scored.iter().map(|(project, ..)| project.clone()).collect();
+    let mut nodes = lake
+        .get_nodes_by_ids(&ids)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let related = scored
+        .into_iter()
+        .filter_map(|(project, score, signals)| {
+            let summary = nodes.remove(&project).and_then(map_node_summary)?;
+            summary
+                .entity_type
+                .eq_ignore_ascii_case(Project::ENTITY_TYPE)
+                .then_some(RelatedProject {
+                    project: summary,
+                    score,
+                    shared_libraries: signals.libraries,
+                    shared_contributors: signals.contributors,
+                    readme_similarity: signals.readme,
+                })
+        })
+        .take(limit)
+        .collect();
+
+    Ok(Json(RelatedProjectsResponse {
+        project_id: id,
+        project_url,
+        related,
+    }))
+}
@@ -0,0 +1,204 @@
+//! `fagent repl`: an exploratory shell over one store, for poking at the lake
+//! and the graph without the dashboard.
+//!
+//! Lines starting with `:` are commands; `:help` lists them. Anything else is
+//! read-only SQL over every lake table, as with `fagent query`, and may span
+//! lines: it runs once a line ends with `;`.
+
+use std::io::Write;
+
+use fstorage::{
+    lake::{Lake, NeighborDirection, NeighborEdgeOrientation},
+    FStorage,
+};
+use serde_json::Value as JsonValue;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{map_node_summary, search_cli, searchable_entity_types, sql_cli, ReplArgs};
+
+const SEARCH_LIMIT: usize = 10;
+const SEARCH_ALPHA: f32 = 0.5;
+const NEIGHBOR_LIMIT: usize = 50;
+
+const HELP: &str = "\
+:tables            lake tables with their SQL names and row counts
+:search <query>    hybrid search over every searchable entity type
+:node <id>         one node with its properties
+:neighbors <id>    edges and nodes next to a node
+:help              this list
+:quit              leave; so do :q, :exit and Ctrl-D
+<sql>;             read-only SQL over the lake tables, named as in :tables
+";
+
+pub(crate) async fn run(args: ReplArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    println!(
+        "fagent repl on {}; :help lists commands",
+        args.store.base_path()?.display()
+    );
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut sql = String::new();
+    loop {
+        prompt(if sql.is_empty() {
+            "fagent> "
+        } else {
+            "   ...> "
+        })?;
+        let Some(line) = lines.next_line().await? else {
+            println!();
+            break;
+        };
+        let line = line.trim();
+        if sql.is_empty() {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(command) = line.strip_prefix(':') {
+                match run_command(&storage, command).await {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(err) => {
+                        eprintln!("error: {err:#}");
+                        continue;
+                    }
+                }
+            }
+        }
+
+        sql.push_str(line);
+        sql.push('\n');
+        if let Some(statement) = sql.trim_end().strip_suffix(';') {
+            if let Err(err) = run_sql(&storage, statement, args.limit).await {
+                eprintln!("error: {err:#}");
+            }
+            sql.clear();
+        }
+    }
+    Ok(())
+}
+
+fn prompt(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
+
+/// Runs one `:` command. Returns `false` once the session should end.
+async fn run_command(storage: &FStorage, command: &str) -> anyhow::Result<bool> {
+    let (name, rest) = command
+        .split_once(char::is_whitespace)
+        .map(|(name, rest)| (name, rest.trim()))
+        .unwrap_or((command, ""));
+    let argument = |usage: &str| {
+        if rest.is_empty() {
+            Err(anyhow::anyhow!("usage: :{name} {usage}"))
+        } else {
+            Ok(rest)
+        }
+    };
+    match name {
+        "tables" => print!("{}", tables(storage).await?),
+        "search" => {
+            let query = argument("<query>")?;
+            let entity_types = searchable_entity_types(storage)?;
+            let hits = storage
+                .search_hybrid_multi(
+                    &entity_types,
+                    query,
+                    SEARCH_ALPHA,
+                    SEARCH_LIMIT,
+                    false,
+                    None,
+                )
+                .await?;
+            print!("{}", search_cli::render(&hits));
+        }
+        "node" => {
+            let id = argument("<id>")?;
+            match storage.lake.get_node_by_id(id, None).await? {
+                Some(node) => println!("{}", serde_json::to_string_pretty(&node)?),
+                None => println!("no node {id}"),
+            }
+        }
+        "neighbors" => {
+            let id = argument("<id>")?;
+            print!("{}", neighbors(storage, id).await?);
+        }
+        "help" | "h" | "?" => print!("{HELP}"),
+        "quit" | "q" | "exit" => return Ok(false),
+        other => anyhow::bail!("unknown command :{other}; :help lists commands"),
+    }
+    Ok(true)
+}
+
+async fn run_sql(storage: &FStorage, sql: &str, limit: usize) -> anyhow::Result<()> {
+    let result = storage.lake.query_lake_sql(sql, limit).await?;
+    print!("{}", sql_cli::render_table(&result));
+    if result.truncated {
+        println!("(stopped at {limit} rows; restart with a larger --limit to see more)");
+    }
+    Ok(())
+}
+
+async fn tables(storage: &FStorage) -> anyhow::Result<String> {
+    let mut rows = Vec::new();
+    for table in storage.lake.list_tables("").await? {
+        let count = storage
+            .lake
+            .count_rows(&table.table_path)
+            .await?
+            .unwrap_or_default();
+        rows.push((
+            Lake::sanitize_table_alias(&table.table_path),
+            count,
+            table.table_path,
+        ));
+    }
+    if rows.is_empty() {
+        return Ok("no tables\n".to_string());
+    }
+    let width = rows
+        .iter()
+        .map(|(alias, _, _)| alias.len())
+        .max()
+        .unwrap_or(0);
+    Ok(rows
+        .iter()
+        .map(|(alias, count, path)| format!("{alias:<width$}  {count:>8}  {path}\n"))
+        .collect())
+}
+
+async fn neighbors(storage: &FStorage, id: &str) -> anyhow::Result<String> {
+    let records = storage
+        .lake
+        .neighbors(id, None, NeighborDirection::Both, NEIGHBOR_LIMIT)
+        .await?;
+    if records.is_empty() {
+        return Ok(format!("no neighbors of {id}\n"));
+    }
+    let mut out = String::new();
+    for record in records {
+        let label = record
+            .edge
+            .get("label")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("EDGE");
+        let arrow = match record.orientation {
+            NeighborEdgeOrientation::Outgoing => format!("-[{label}]->"),
+            NeighborEdgeOrientation::Incoming => format!("<-[{label}]-"),
+        };
+        match record.node.and_then(map_node_summary) {
+            Some(node) => {
+                out += &format!(
+                    "{arrow} {} ({}) {}\n",
+                    node.display_name.as_deref().unwrap_or("-"),
+                    node.entity_type,
+                    node.id
+                )
+            }
+            None => out += &format!("{arrow} {}\n", record.node_id),
+        }
+    }
+    Ok(out)
+}
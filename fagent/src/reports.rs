@@ -0,0 +1,68 @@
+//! Data-quality reports.
+//!
+//! `POST /api/reports/quality` measures every lake table and stores the result
+//! in the gold layer; `GET` returns the last stored run, so dashboards can poll
+//! it without re-scanning the lake. `POST /api/reports/id_collisions` audits the
+//! node index tables for ids shared by distinct primary keys and is not stored.
+
+use axum::{extract::State, Json};
+use fstorage::models::{IdAuditReport, QualityReport};
+
+use crate::{ApiError, ApiResult, AppState, ErrorResponse};
+
+#[utoipa::path(
+    get,
+    path = "/api/reports/quality",
+    tag = "reports",
+    responses(
+        (status = 200, description = "Per-table metrics of the last data-quality run", body = Object),
+        (status = 404, description = "The data-quality job has not run yet", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_quality_report(
+    State(state): State<AppState>,
+) -> ApiResult<Json<QualityReport>> {
+    state
+        .storage
+        .latest_quality_report()
+        .await
+        .map_err(ApiError::from_storage)?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("no data-quality report has been generated".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/reports/quality",
+    tag = "reports",
+    responses(
+        (status = 200, description = "Per-table metrics, also stored as the latest report", body = Object)
+    )
+)]
+pub(crate) async fn run_quality_report(
+    State(state): State<AppState>,
+) -> ApiResult<Json<QualityReport>> {
+    let report = state
+        .storage
+        .run_quality_report()
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/reports/id_collisions",
+    tag = "reports",
+    responses(
+        (status = 200, description = "Node ids shared by distinct primary-key sets", body = Object)
+    )
+)]
+pub(crate) async fn run_id_audit(State(state): State<AppState>) -> ApiResult<Json<IdAuditReport>> {
+    let report = state
+        .storage
+        .audit_ids()
+        .await
+        .map_err(ApiError::from_storage)?;
+    Ok(Json(report))
+}
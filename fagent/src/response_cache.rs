@@ -0,0 +1,198 @@
+//! In-process cache for the expensive read endpoints.
+//!
+//! The graph overview and statistics scan the whole engine or every lake
+//! table, and a dashboard asks for them on each page load. Their rendered
+//! bodies are kept here keyed by path and query string, and the whole cache is
+//! dropped as soon as [`fstorage::FStorage::data_version`] moves, so an entry is
+//! never served once a sync, import or erasure has landed.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header::CONTENT_TYPE, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{ApiResult, AppState};
+
+/// Entries kept unless configured otherwise.
+pub(crate) const DEFAULT_CACHE_ENTRIES: usize = 256;
+
+/// Bodies above this size are served but not kept.
+const MAX_ENTRY_BYTES: usize = 8 << 20;
+
+#[derive(Clone)]
+struct CachedResponse {
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+#[derive(Default)]
+struct Entries {
+    version: String,
+    responses: HashMap<String, CachedResponse>,
+    /// Insertion order, oldest first, for eviction.
+    order: VecDeque<String>,
+}
+
+#[derive(Default)]
+pub(crate) struct ResponseCache {
+    max_entries: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Counters describing how well the cache is doing since startup.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct CacheMetrics {
+    max_entries: usize,
+    entries: usize,
+    hits: u64,
+    misses: u64,
+    /// Times the cache was emptied because the stored data changed.
+    invalidations: u64,
+    /// Entries dropped to stay within `max_entries`.
+    evictions: u64,
+}
+
+impl ResponseCache {
+    /// A cache holding at most `max_entries` responses; 0 disables caching.
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            ..Self::default()
+        }
+    }
+
+    fn get(&self, version: &str, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.version != version {
+            if !entries.responses.is_empty() {
+                self.invalidations.fetch_add(1, Ordering::Relaxed);
+            }
+            entries.responses.clear();
+            entries.order.clear();
+            entries.version = version.to_string();
+        }
+        let cached = entries.responses.get(key).cloned();
+        let counter = if cached.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    fn put(&self, version: &str, key: String, response: CachedResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        // The data moved while the handler ran; the body may predate it.
+        if entries.version != version || entries.responses.contains_key(&key) {
+            return;
+        }
+        while entries.responses.len() >= self.max_entries {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+            entries.responses.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        entries.order.push_back(key.clone());
+        entries.responses.insert(key, response);
+    }
+
+    fn metrics(&self) -> CacheMetrics {
+        let entries = self.entries.lock().unwrap().responses.len();
+        CacheMetrics {
+            max_entries: self.max_entries,
+            entries,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Route layer serving `GET`s from the cache and filling it with `200`s.
+pub(crate) async fn cache_response(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cache = &state.response_cache;
+    if cache.max_entries == 0 || request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let key = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let version = match state.storage.data_version().await {
+        Ok(version) => version,
+        Err(err) => {
+            warn!("failed to read the data version: {}", err);
+            return next.run(request).await;
+        }
+    };
+    if let Some(cached) = cache.get(&version, &key) {
+        let mut response = Body::from(cached.body).into_response();
+        if let Some(content_type) = cached.content_type {
+            response.headers_mut().insert(CONTENT_TYPE, content_type);
+        }
+        return response;
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("failed to buffer response for {}: {}", key, err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if body.len() <= MAX_ENTRY_BYTES {
+        cache.put(
+            &version,
+            key,
+            CachedResponse {
+                content_type: parts.headers.get(CONTENT_TYPE).cloned(),
+                body: body.clone(),
+            },
+        );
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache",
+    tag = "admin",
+    responses((status = 200, body = CacheMetrics))
+)]
+pub(crate) async fn get_cache_metrics(
+    State(state): State<AppState>,
+) -> ApiResult<Json<CacheMetrics>> {
+    Ok(Json(state.response_cache.metrics()))
+}
@@ -0,0 +1,335 @@
+//! Hybrid and graph searches saved by name.
+//!
+//! The dashboard used to keep its searches only in the page, losing them on
+//! reload. A saved search stores the query text, entity types, `alpha`, limit
+//! and any dashboard-side filters in the catalog; its results endpoint re-runs
+//! it against the current graph.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use fstorage::models::SavedSearch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    run_graph_search, run_hybrid_search, schedules::validate_name, ApiError, ApiResult, AppState,
+    ErrorResponse, GraphSearchResponse, HybridMultiResponse, HybridOptions,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SearchKind {
+    /// `/api/search/hybrid_all`
+    Hybrid,
+    /// `/api/graph/search`
+    Graph,
+}
+
+impl SearchKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchKind::Hybrid => "hybrid",
+            SearchKind::Graph => "graph",
+        }
+    }
+
+    fn parse(raw: &str) -> ApiResult<Self> {
+        match raw {
+            "hybrid" => Ok(SearchKind::Hybrid),
+            "graph" => Ok(SearchKind::Graph),
+            other => Err(ApiError::Internal(format!(
+                "unknown saved search kind '{other}'"
+            ))),
+        }
+    }
+}
+
+/// What a saved search runs with; stored as the catalog row's `query`.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub(crate) struct SearchParams {
+    #[serde(default)]
+    q: String,
+    /// Graph searches take at most one entity type.
+    #[serde(default)]
+    entity_types: Vec<String>,
+    /// Hybrid searches only: weight of the vector score, 0 to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alpha: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    /// Hybrid searches only: rerank the hits with the configured cross-encoder.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    rerank: bool,
+    /// Hybrid searches only: embedding model to embed the query with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding_model: Option<String>,
+    /// Hybrid searches only: append the graph context of the hits.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    graph_expand: bool,
+    /// Filters the dashboard applies to the results, stored as given.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    filters: JsonValue,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct SavedSearchRequest {
+    name: String,
+    kind: SearchKind,
+    #[serde(flatten)]
+    params: SearchParams,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SavedSearchDto {
+    name: String,
+    kind: SearchKind,
+    #[serde(flatten)]
+    params: SearchParams,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl SavedSearchDto {
+    fn new(search: SavedSearch) -> ApiResult<Self> {
+        let params = serde_json::from_str(&search.query).map_err(|err| {
+            ApiError::Internal(format!(
+                "saved search '{}' is unreadable: {err}",
+                search.name
+            ))
+        })?;
+        Ok(Self {
+            kind: SearchKind::parse(&search.kind)?,
+            name: search.name,
+            params,
+            created_at: search.created_at,
+            updated_at: search.updated_at,
+        })
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SavedSearchResultsQuery {
+    /// Next page of a graph search.
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+/// A saved search with the results of running it now; exactly one of `hybrid`
+/// and `graph` is set, depending on its kind.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SavedSearchResults {
+    search: SavedSearchDto,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hybrid: Option<HybridMultiResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graph: Option<GraphSearchResponse>,
+}
+
+fn validate(body: &SavedSearchRequest) -> ApiResult<()> {
+    validate_name("saved search", &body.name)?;
+    let params = &body.params;
+    match body.kind {
+        SearchKind::Hybrid => {
+            if params
+                .alpha
+                .is_some_and(|alpha| !(0.0..=1.0).contains(&alpha))
+            {
+                return Err(ApiError::BadRequest(
+                    "alpha must be between 0 and 1".to_string(),
+                ));
+            }
+        }
+        SearchKind::Graph => {
+            if params.entity_types.len() > 1 {
+                return Err(ApiError::BadRequest(
+                    "graph searches take at most one entity type".to_string(),
+                ));
+            }
+            if params.alpha.is_some() {
+                return Err(ApiError::BadRequest(
+                    "alpha only applies to hybrid searches".to_string(),
+                ));
+            }
+            if params.rerank || params.embedding_model.is_some() {
+                return Err(ApiError::BadRequest(
+                    "rerank and embedding_model only apply to hybrid searches".to_string(),
+                ));
+            }
+        }
+    }
+    if !(params.filters.is_null() || params.filters.is_object()) {
+        return Err(ApiError::BadRequest(
+            "filters must be a JSON object".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn load(state: &AppState, name: &str) -> ApiResult<SavedSearchDto> {
+    let search = state
+        .storage
+        .catalog
+        .get_saved_search(name)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("saved search '{name}' not found")))?;
+    SavedSearchDto::new(search)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/saved_searches",
+    tag = "search",
+    request_body = SavedSearchRequest,
+    responses(
+        (status = 200, description = "The search was saved or replaced", body = SavedSearchDto),
+        (status = 400, description = "Invalid name or search parameters", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn put_saved_search(
+    State(state): State<AppState>,
+    Json(body): Json<SavedSearchRequest>,
+) -> ApiResult<Json<SavedSearchDto>> {
+    validate(&body)?;
+    let search = SavedSearch {
+        query: serde_json::to_string(&body.params)
+            .map_err(|err| ApiError::Internal(err.to_string()))?,
+        name: body.name,
+        kind: body.kind.as_str().to_string(),
+        created_at: 0,
+        updated_at: 0,
+    };
+    state
+        .storage
+        .catalog
+        .put_saved_search(&search)
+        .map_err(ApiError::from_storage)?;
+    info!(saved_search = %search.name, kind = %search.kind, "saved search");
+    Ok(Json(load(&state, &search.name)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved_searches",
+    tag = "search",
+    responses((status = 200, body = [SavedSearchDto]))
+)]
+pub(crate) async fn list_saved_searches(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<SavedSearchDto>>> {
+    let searches = state
+        .storage
+        .catalog
+        .list_saved_searches()
+        .map_err(ApiError::from_storage)?;
+    let searches = searches
+        .into_iter()
+        .map(SavedSearchDto::new)
+        .collect::<ApiResult<_>>()?;
+    Ok(Json(searches))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved_searches/{name}",
+    tag = "search",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, body = SavedSearchDto),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_saved_search(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<SavedSearchDto>> {
+    Ok(Json(load(&state, &name)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/saved_searches/{name}",
+    tag = "search",
+    params(("name" = String, Path)),
+    responses(
+        (status = 204, description = "The saved search was removed"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn delete_saved_search(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    let deleted = state
+        .storage
+        .catalog
+        .delete_saved_search(&name)
+        .map_err(ApiError::from_storage)?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!(
+            "saved search '{name}' not found"
+        )));
+    }
+    info!(saved_search = %name, "deleted saved search");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/saved_searches/{name}/results",
+    tag = "search",
+    params(("name" = String, Path), SavedSearchResultsQuery),
+    responses(
+        (status = 200, body = SavedSearchResults),
+        (status = 400, description = "Invalid cursor", body = ErrorResponse),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn run_saved_search(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<SavedSearchResultsQuery>,
+) -> ApiResult<Json<SavedSearchResults>> {
+    let search = load(&state, &name)?;
+    let params = &search.params;
+    let (hybrid, graph) = match search.kind {
+        SearchKind::Hybrid => {
+            let hits = run_hybrid_search(
+                &state,
+                &params.q,
+                params.entity_types.clone(),
+                params.alpha,
+                params.limit,
+                HybridOptions {
+                    rerank: params.rerank,
+                    embedding_model: params.embedding_model.as_deref(),
+                    graph_expand: params.graph_expand,
+                },
+            )
+            .await?;
+            (Some(hits), None)
+        }
+        SearchKind::Graph => {
+            let page = run_graph_search(
+                &state,
+                &params.q,
+                params.entity_types.first().map(String::as_str),
+                params.limit,
+                query.cursor.as_deref(),
+            )
+            .await?;
+            (None, Some(page))
+        }
+    };
+    Ok(Json(SavedSearchResults {
+        search,
+        hybrid,
+        graph,
+    }))
+}
@@ -0,0 +1,562 @@
+//! Cron schedules that keep synced sources up to date.
+//!
+//! A schedule is a named sync request with a cron expression, stored in the
+//! catalog so it survives restarts. A background task submits the request to
+//! the job queue whenever a schedule falls due, delayed by a random jitter so
+//! schedules sharing an expression do not all hit an upstream API in the same
+//! second. A schedule whose previous job is still queued or running skips that
+//! firing instead of piling up a second job.
+//!
+//! Only one process fires a store's schedules, so a dashboard and a daemon
+//! opened on the same store do not both submit them: the scheduler holds a
+//! lease in the catalog, renews it every tick, and another process's
+//! scheduler takes over once it lapses.
+//!
+//! Expressions have the five standard fields (minute, hour, day of month,
+//! month, day of week) and are evaluated in UTC.
+
+use std::{
+    collections::hash_map::RandomState, hash::BuildHasher, sync::OnceLock,
+    time::Duration as StdDuration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use fstorage::{
+    errors::StorageError,
+    models::{SyncJobState, SyncSchedule},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{queue_sync, ApiError, ApiResult, AppState, ErrorResponse, SyncRequest};
+
+/// How often the scheduler looks for schedules that fell due.
+const SCHEDULER_TICK: StdDuration = StdDuration::from_secs(5);
+/// Catalog lease whose holder fires the schedules.
+const SCHEDULER_LEASE: &str = "scheduler";
+/// How long the lease outlives the tick that renewed it, so another process
+/// takes over within this long of the holder stopping.
+const SCHEDULER_LEASE_SECS: i64 = 30;
+const MAX_JITTER_SECS: u64 = 3_600;
+const MAX_NAME_LEN: usize = 64;
+/// No expression is searched further ahead than this for its next match.
+const MAX_LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+const OUTCOME_QUEUED: &str = "queued";
+const OUTCOME_SKIPPED: &str = "skipped";
+
+/// Parsed five-field cron expression, one bit per allowed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CronExpr {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    /// Whether the day-of-month and day-of-week fields were restricted; when
+    /// both are, a day matching either one matches, as in crontab.
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronExpr {
+    /// Parses `*/15 * * * *`-style expressions, including `@hourly`, `@daily`,
+    /// `@weekly`, `@monthly` and `@yearly`.
+    pub(crate) fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!(
+                "cron expression '{expr}' must have 5 fields, found {}",
+                fields.len()
+            ));
+        };
+        let mut days_of_week = parse_field(dow, 0, 7)?;
+        // Both 0 and 7 mean Sunday.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)? as u32,
+            days_of_month: parse_field(dom, 1, 31)? as u32,
+            months: parse_field(month, 1, 12)? as u16,
+            days_of_week: (days_of_week & 0x7f) as u8,
+            dom_restricted: !dom.starts_with('*'),
+            dow_restricted: !dow.starts_with('*'),
+        })
+    }
+
+    /// First matching minute strictly after `after`.
+    pub(crate) fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut at = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = at + Duration::days(MAX_LOOKAHEAD_DAYS);
+        while at < limit {
+            if !self.day_matches(at) {
+                let next_day = at.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                at = Utc.from_utc_datetime(&next_day);
+                continue;
+            }
+            if self.hours & (1 << at.hour()) == 0 {
+                at = at.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << at.minute()) == 0 {
+                at += Duration::minutes(1);
+                continue;
+            }
+            return Some(at);
+        }
+        None
+    }
+
+    fn day_matches(&self, at: DateTime<Utc>) -> bool {
+        if self.months & (1 << at.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << at.day()) != 0;
+        let dow = self.days_of_week & (1 << at.weekday().num_days_from_sunday()) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+}
+
+/// Parses one comma-separated field of values, `a-b` ranges, `*` and `/step`s.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in cron field '{field}'"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |text: &str| {
+            text.parse::<u32>()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .ok_or_else(|| format!("cron field '{field}' has '{text}' outside {min}-{max}"))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/10` runs from 5 to the end of the range.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("cron field '{field}' has a descending range"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Next firing of `cron` after `now`, pushed back by up to `jitter_secs`.
+fn next_run_at(name: &str, cron: &CronExpr, jitter_secs: u64, now: DateTime<Utc>) -> Option<i64> {
+    let next = cron.next_after(now)?.timestamp();
+    let jitter = if jitter_secs == 0 {
+        0
+    } else {
+        RandomState::new().hash_one((name, next)) % (jitter_secs + 1)
+    };
+    Some(next + jitter as i64)
+}
+
+#[derive(Clone, Deserialize, ToSchema)]
+pub(crate) struct ScheduleRequest {
+    /// Letters, digits, `-`, `_` and `.`.
+    pub(crate) name: String,
+    /// Five-field cron expression in UTC, e.g. `0 */6 * * *`, or `@daily`.
+    cron: String,
+    /// The sync to submit, as for `POST /api/sync`. Its priority defaults to
+    /// `background`.
+    #[schema(value_type = Object)]
+    request: JsonValue,
+    /// Each firing is delayed by a random 0 to `jitter_secs` seconds.
+    #[serde(default)]
+    jitter_secs: u64,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl ScheduleRequest {
+    /// An enabled schedule submitting `request`, in the `POST /api/sync`
+    /// format, whenever `cron` matches.
+    pub(crate) fn new(name: String, cron: String, request: JsonValue, jitter_secs: u64) -> Self {
+        Self {
+            name,
+            cron,
+            request,
+            jitter_secs,
+            enabled: true,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ScheduleDto {
+    name: String,
+    cron: String,
+    request: JsonValue,
+    jitter_secs: u64,
+    enabled: bool,
+    next_run_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_run_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_job_id: Option<i64>,
+    /// `queued`, `skipped` because the previous job had not finished, or why
+    /// the sync was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_outcome: Option<String>,
+    /// Current state of `last_job_id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "succeeded")]
+    last_job_state: Option<SyncJobState>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl ScheduleDto {
+    pub(crate) fn new(state: &AppState, schedule: SyncSchedule) -> ApiResult<Self> {
+        let last_job_state = last_job_state(state, &schedule)?;
+        Ok(Self {
+            name: schedule.name,
+            cron: schedule.cron,
+            request: serde_json::from_str(&schedule.request).unwrap_or(JsonValue::Null),
+            jitter_secs: schedule.jitter_secs,
+            enabled: schedule.enabled,
+            next_run_at: schedule.next_run_at,
+            last_run_at: schedule.last_run_at,
+            last_job_id: schedule.last_job_id,
+            last_outcome: schedule.last_outcome,
+            last_job_state,
+            created_at: schedule.created_at,
+            updated_at: schedule.updated_at,
+        })
+    }
+}
+
+fn last_job_state(state: &AppState, schedule: &SyncSchedule) -> ApiResult<Option<SyncJobState>> {
+    let Some(job_id) = schedule.last_job_id else {
+        return Ok(None);
+    };
+    Ok(state
+        .storage
+        .catalog
+        .get_sync_job(job_id)
+        .map_err(ApiError::from_storage)?
+        .map(|job| job.state))
+}
+
+/// Checks a user-chosen name for a stored object such as a schedule; `what`
+/// names the object in the error.
+pub(crate) fn validate_name(what: &str, name: &str) -> ApiResult<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "invalid {what} name '{name}': use up to {MAX_NAME_LEN} letters, digits, '-', '_' or '.'"
+        )))
+    }
+}
+
+fn load(state: &AppState, name: &str) -> ApiResult<SyncSchedule> {
+    state
+        .storage
+        .catalog
+        .get_sync_schedule(name)
+        .map_err(ApiError::from_storage)?
+        .ok_or_else(|| ApiError::NotFound(format!("schedule '{name}' not found")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/schedules",
+    tag = "sync",
+    request_body = ScheduleRequest,
+    responses(
+        (status = 200, description = "The schedule was created or replaced", body = ScheduleDto),
+        (status = 400, description = "Invalid name, cron expression or sync request", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn put_schedule(
+    State(state): State<AppState>,
+    Json(body): Json<ScheduleRequest>,
+) -> ApiResult<Json<ScheduleDto>> {
+    let stored = save_schedule(&state, body)?;
+    Ok(Json(ScheduleDto::new(&state, stored)?))
+}
+
+/// Validates `body` and creates or replaces the schedule it names.
+pub(crate) fn save_schedule(state: &AppState, body: ScheduleRequest) -> ApiResult<SyncSchedule> {
+    let schedule = check_schedule(state, body)?;
+    store_schedule(state, &schedule)
+}
+
+/// Validates `body` and makes the schedule it describes, without storing it.
+pub(crate) fn check_schedule(state: &AppState, body: ScheduleRequest) -> ApiResult<SyncSchedule> {
+    validate_name("schedule", &body.name)?;
+    let cron = CronExpr::parse(&body.cron).map_err(ApiError::BadRequest)?;
+    if body.jitter_secs > MAX_JITTER_SECS {
+        return Err(ApiError::BadRequest(format!(
+            "jitter_secs must be at most {MAX_JITTER_SECS}"
+        )));
+    }
+    let mut request = body.request;
+    if let Some(fields) = request.as_object_mut() {
+        fields
+            .entry("priority")
+            .or_insert_with(|| json!("background"));
+    }
+    let request: SyncRequest = serde_json::from_value(request)
+        .map_err(|err| ApiError::BadRequest(format!("invalid sync request: {err}")))?;
+    let registered = state
+        .storage
+        .list_fetchers_capability()
+        .iter()
+        .any(|capability| capability.name == request.fetcher);
+    if !registered {
+        return Err(ApiError::from_storage(StorageError::UnknownFetcher(
+            request.fetcher.clone(),
+        )));
+    }
+    // Surfaces conflicting entity types now rather than at every firing.
+    request.clone().apply_entity_types()?;
+
+    let next_run_at =
+        next_run_at(&body.name, &cron, body.jitter_secs, Utc::now()).ok_or_else(|| {
+            ApiError::BadRequest(format!("cron expression '{}' never matches", body.cron))
+        })?;
+    Ok(SyncSchedule {
+        name: body.name,
+        cron: body.cron,
+        request: serde_json::to_string(&request)
+            .map_err(|err| ApiError::Internal(err.to_string()))?,
+        jitter_secs: body.jitter_secs,
+        enabled: body.enabled,
+        next_run_at,
+        last_run_at: None,
+        last_job_id: None,
+        last_outcome: None,
+        created_at: 0,
+        updated_at: 0,
+    })
+}
+
+/// Creates or replaces `schedule`, as [`check_schedule`] made it.
+pub(crate) fn store_schedule(state: &AppState, schedule: &SyncSchedule) -> ApiResult<SyncSchedule> {
+    state
+        .storage
+        .catalog
+        .put_sync_schedule(schedule)
+        .map_err(ApiError::from_storage)?;
+    info!(schedule = %schedule.name, cron = %schedule.cron, "saved sync schedule");
+    load(state, &schedule.name)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/schedules",
+    tag = "sync",
+    responses((status = 200, body = [ScheduleDto]))
+)]
+pub(crate) async fn list_schedules(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<ScheduleDto>>> {
+    let schedules = state
+        .storage
+        .catalog
+        .list_sync_schedules()
+        .map_err(ApiError::from_storage)?;
+    let schedules = schedules
+        .into_iter()
+        .map(|schedule| ScheduleDto::new(&state, schedule))
+        .collect::<ApiResult<_>>()?;
+    Ok(Json(schedules))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/schedules/{name}",
+    tag = "sync",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, body = ScheduleDto),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn get_schedule(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<ScheduleDto>> {
+    let schedule = load(&state, &name)?;
+    Ok(Json(ScheduleDto::new(&state, schedule)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/schedules/{name}",
+    tag = "sync",
+    params(("name" = String, Path)),
+    responses(
+        (status = 204, description = "The schedule was removed; jobs it submitted are kept"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn delete_schedule(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    let deleted = state
+        .storage
+        .catalog
+        .delete_sync_schedule(&name)
+        .map_err(ApiError::from_storage)?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!("schedule '{name}' not found")));
+    }
+    info!(schedule = %name, "deleted sync schedule");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/schedules/{name}/run",
+    tag = "sync",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, description = "The schedule fired; `last_outcome` tells whether a job was queued", body = ScheduleDto),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn run_schedule(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<ScheduleDto>> {
+    let schedule = load(&state, &name)?;
+    fire(&state, &schedule, Utc::now())?;
+    let schedule = load(&state, &name)?;
+    Ok(Json(ScheduleDto::new(&state, schedule)?))
+}
+
+/// Submits the schedule's sync unless its previous job is still pending, and
+/// moves it to its next run.
+fn fire(state: &AppState, schedule: &SyncSchedule, now: DateTime<Utc>) -> ApiResult<()> {
+    let previous = last_job_state(state, schedule)?;
+    let (job_id, outcome) = if previous.is_some_and(|job| !job.is_terminal()) {
+        (schedule.last_job_id, OUTCOME_SKIPPED.to_string())
+    } else {
+        let submitted = serde_json::from_str::<SyncRequest>(&schedule.request)
+            .map_err(|err| ApiError::BadRequest(format!("stored request is unreadable: {err}")))
+            .and_then(|request| queue_sync(state, request));
+        match submitted {
+            Ok(accepted) => (Some(accepted.job_id), OUTCOME_QUEUED.to_string()),
+            Err(err) => (None, format!("rejected: {err}")),
+        }
+    };
+    info!(schedule = %schedule.name, job_id, outcome, "fired sync schedule");
+
+    let next = CronExpr::parse(&schedule.cron)
+        .ok()
+        .and_then(|cron| next_run_at(&schedule.name, &cron, schedule.jitter_secs, now))
+        // An expression that no longer parses or matches stops firing.
+        .unwrap_or(i64::MAX);
+    state
+        .storage
+        .catalog
+        .record_sync_schedule_run(&schedule.name, now.timestamp(), job_id, &outcome, next)
+        .map_err(ApiError::from_storage)
+}
+
+/// Spawns the task firing schedules as they fall due.
+pub(crate) fn start_scheduler(state: AppState) {
+    tokio::spawn(run_scheduler(state));
+}
+
+/// Holder of the scheduler lease for this process, so that a scheduler
+/// restarted after a failure keeps the lease of the one before it.
+fn lease_holder() -> &'static str {
+    static HOLDER: OnceLock<String> = OnceLock::new();
+    HOLDER.get_or_init(|| Uuid::new_v4().to_string())
+}
+
+/// Fires schedules as they fall due, while this process holds the scheduler
+/// lease, until the task is dropped.
+pub(crate) async fn run_scheduler(state: AppState) {
+    let mut tick = tokio::time::interval(SCHEDULER_TICK);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut leader = false;
+    loop {
+        tick.tick().await;
+        let now = Utc::now();
+        let acquired = state.storage.catalog.acquire_lease(
+            SCHEDULER_LEASE,
+            lease_holder(),
+            now.timestamp(),
+            now.timestamp() + SCHEDULER_LEASE_SECS,
+        );
+        match &acquired {
+            Ok(true) if !leader => info!("took the scheduler lease; firing schedules"),
+            Ok(false) if leader => info!("lost the scheduler lease to another process"),
+            Ok(false) => debug!("another process holds the scheduler lease"),
+            Ok(true) => {}
+            Err(err) => warn!("failed to renew the scheduler lease: {}", err),
+        }
+        leader = acquired.unwrap_or(false);
+        if !leader {
+            continue;
+        }
+        let due = match state.storage.catalog.due_sync_schedules(now.timestamp()) {
+            Ok(due) => due,
+            Err(err) => {
+                warn!("failed to load due sync schedules: {}", err);
+                continue;
+            }
+        };
+        for schedule in due {
+            if let Err(err) = fire(&state, &schedule, now) {
+                warn!("failed to fire sync schedule {}: {}", schedule.name, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! `fagent search`: hybrid search from the shell, for scripts and for agents
+//! running next to the store.
+
+use fstorage::models::MultiEntitySearchHit;
+use serde_json::Value as JsonValue;
+
+use crate::{searchable_entity_types, SearchArgs};
+
+pub(crate) async fn run(args: SearchArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let entity_types = if args.entity_types.is_empty() {
+        searchable_entity_types(&storage)?
+    } else {
+        args.entity_types
+    };
+    let hits = storage
+        .search_hybrid_multi(
+            &entity_types,
+            &args.query,
+            args.alpha.clamp(0.0, 1.0),
+            args.limit.max(1),
+            false,
+            None,
+        )
+        .await?;
+    let hits = if args.graph_expand {
+        storage.expand_search_hits(hits, args.limit.max(1)).await?
+    } else {
+        hits
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+    } else {
+        print!("{}", render(&hits));
+    }
+    Ok(())
+}
+
+pub(crate) fn render(hits: &[MultiEntitySearchHit]) -> String {
+    if hits.is_empty() {
+        return "no hits\n".to_string();
+    }
+    let mut out = String::new();
+    for hit in hits {
+        let id = hit
+            .node
+            .as_ref()
+            .or(hit.vector.as_ref())
+            .and_then(|fields| fields.get("id"))
+            .and_then(JsonValue::as_str)
+            .unwrap_or("-");
+        out += &format!("{:.4}  {}  {}", hit.score, hit.entity_type, id);
+        if let Some(expansion) = &hit.expansion {
+            out += &format!("  (via {} of {})", expansion.via, expansion.from_id);
+        }
+        out.push('\n');
+        if let Some(summary) = hit.summary.as_deref().filter(|s| !s.is_empty()) {
+            out += &format!("    {}\n", summary.replace('\n', " "));
+        }
+    }
+    out
+}
@@ -0,0 +1,281 @@
+//! Named snapshots and read-only mounts.
+//!
+//! `POST /api/snapshots` freezes the current store. Mounting a snapshot opens it
+//! as a separate store and serves the read-only part of the API under
+//! `/api/mounts/{name}/...`, so `/api/mounts/before-upgrade/graph/overview`
+//! answers what `/api/graph/overview` did when the snapshot was taken.
+//!
+//! `fagent diff-snapshots <a> <b>` compares two snapshots offline, e.g. one
+//! taken before and one after a pipeline change.
+
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use axum::{
+    extract::{MatchedPath, Path, Request, State},
+    http::{uri::InvalidUri, StatusCode, Uri},
+    response::Response,
+    Json, Router,
+};
+use fstorage::{
+    models::{SnapshotDiff, SnapshotInfo},
+    FStorage,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tower::util::ServiceExt;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{ApiError, ApiResult, AppState, DiffSnapshotsArgs, ErrorResponse};
+
+/// Routers serving mounted snapshots, keyed by snapshot name.
+#[derive(Default)]
+pub(crate) struct SnapshotMounts {
+    routers: RwLock<HashMap<String, Router>>,
+}
+
+impl SnapshotMounts {
+    /// Runs `op` with every snapshot unmounted, for changes that reach into
+    /// the snapshots' own stores, and mounts them again afterwards.
+    pub(crate) async fn unmounted<T>(
+        &self,
+        storage: &FStorage,
+        op: impl Future<Output = T>,
+    ) -> ApiResult<T> {
+        let mut routers = self.routers.write().await;
+        let names: Vec<String> = routers.drain().map(|(name, _)| name).collect();
+        let value = op.await;
+        for name in names {
+            routers.insert(name.clone(), mount(storage, &name).await?);
+        }
+        Ok(value)
+    }
+}
+
+/// Opens the snapshot `name` and builds the read-only router serving it.
+async fn mount(storage: &FStorage, name: &str) -> ApiResult<Router> {
+    let storage = storage
+        .open_snapshot(name)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let state = AppState::read_only(Arc::new(storage));
+    Ok(crate::read_routes(&state).with_state(state))
+}
+
+#[derive(Clone, Deserialize, ToSchema)]
+pub(crate) struct CreateSnapshotRequest {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SnapshotListing {
+    #[serde(flatten)]
+    info: SnapshotInfo,
+    mounted: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/snapshots",
+    tag = "snapshots",
+    request_body = CreateSnapshotRequest,
+    responses(
+        (status = 201, description = "The snapshot and the table versions it froze", body = Object),
+        (status = 400, description = "Invalid or already used name", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn create_snapshot(
+    State(state): State<AppState>,
+    Json(body): Json<CreateSnapshotRequest>,
+) -> ApiResult<(StatusCode, Json<SnapshotInfo>)> {
+    let snapshot = state
+        .storage
+        .create_snapshot(body.name.trim(), body.description)
+        .await
+        .map_err(ApiError::from_storage)?;
+    info!(
+        snapshot = %snapshot.name,
+        tables = snapshot.table_versions.len(),
+        "created snapshot"
+    );
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/snapshots",
+    tag = "snapshots",
+    responses((status = 200, description = "Snapshots and whether each is mounted", body = [Object]))
+)]
+pub(crate) async fn list_snapshots(
+    State(state): State<AppState>,
+) -> ApiResult<Json<Vec<SnapshotListing>>> {
+    let snapshots = state
+        .storage
+        .list_snapshots()
+        .map_err(ApiError::from_storage)?;
+    let mounted = state.snapshots.routers.read().await;
+    Ok(Json(
+        snapshots
+            .into_iter()
+            .map(|info| SnapshotListing {
+                mounted: mounted.contains_key(&info.name),
+                info,
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/snapshots/{name}/mount",
+    tag = "snapshots",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, description = "Mounted under `/api/mounts/{name}/...`", body = Object),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn mount_snapshot(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<SnapshotListing>> {
+    let info =
+        fstorage::snapshot::load(&state.storage.config, &name).map_err(ApiError::from_storage)?;
+    // Held across the open so two requests cannot open the same engine twice.
+    let mut routers = state.snapshots.routers.write().await;
+    if !routers.contains_key(&name) {
+        let router = mount(&state.storage, &name).await?;
+        routers.insert(name.clone(), router);
+        info!(snapshot = %name, "mounted snapshot");
+    }
+    Ok(Json(SnapshotListing {
+        info,
+        mounted: true,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/snapshots/{name}/mount",
+    tag = "snapshots",
+    params(("name" = String, Path)),
+    responses(
+        (status = 204),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+pub(crate) async fn unmount_snapshot(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    match state.snapshots.routers.write().await.remove(&name) {
+        Some(_) => {
+            info!(snapshot = %name, "unmounted snapshot");
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(ApiError::NotFound(format!(
+            "snapshot '{name}' is not mounted"
+        ))),
+    }
+}
+
+/// Serves `/api/mounts/{name}/{rest}` as `/api/{rest}` against the mounted snapshot.
+pub(crate) async fn forward_to_mount(
+    State(state): State<AppState>,
+    Path((name, rest)): Path<(String, String)>,
+    request: Request,
+) -> ApiResult<Response> {
+    let router = state
+        .snapshots
+        .routers
+        .read()
+        .await
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("snapshot '{name}' is not mounted")))?;
+
+    let (mut parts, body) = request.into_parts();
+    // The mounted router records its own route template.
+    parts.extensions.remove::<MatchedPath>();
+    let query = parts
+        .uri
+        .query()
+        .map(|query| format!("?{query}"))
+        .unwrap_or_default();
+    parts.uri = format!("/api/{}{query}", rest.trim_start_matches('/'))
+        .parse::<Uri>()
+        .map_err(|err: InvalidUri| ApiError::BadRequest(err.to_string()))?;
+    let response = router
+        .oneshot(Request::from_parts(parts, body))
+        .await
+        .unwrap_or_else(|never| match never {});
+    Ok(response)
+}
+
+/// Prints how snapshot `b` differs from snapshot `a`.
+pub(crate) async fn run_diff(args: DiffSnapshotsArgs) -> anyhow::Result<()> {
+    let config = args.store.storage_config(args.store.base_path()?, None);
+    let diff = fstorage::snapshot::diff(&config, &args.a, &args.b, args.sample).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", render_diff(&diff));
+    }
+    Ok(())
+}
+
+fn render_diff(diff: &SnapshotDiff) -> String {
+    let mut out = format!("{} -> {}\n", diff.a, diff.b);
+    out += &format!(
+        "  entities: {} -> {} ({:+})\n",
+        diff.entity_rows_a,
+        diff.entity_rows_b,
+        delta(diff.entity_rows_a, diff.entity_rows_b)
+    );
+    out += &format!(
+        "  edges:    {} -> {} ({:+})\n",
+        diff.edge_rows_a,
+        diff.edge_rows_b,
+        delta(diff.edge_rows_a, diff.edge_rows_b)
+    );
+    out += &format!(
+        "  {} changed tables, {} unchanged\n",
+        diff.changed_tables.len(),
+        diff.unchanged_tables
+    );
+    for table in &diff.changed_tables {
+        let version = |version: Option<i64>| version.map_or("-".to_string(), |v| format!("v{v}"));
+        out += &format!(
+            "\n{} ({} -> {}): {} -> {} rows ({:+})\n",
+            table.table,
+            version(table.version_a),
+            version(table.version_b),
+            table.rows_a,
+            table.rows_b,
+            delta(table.rows_a, table.rows_b)
+        );
+        if let (Some(added), Some(removed)) = (table.added_or_changed, table.removed_or_changed) {
+            out += &format!("  {added} added or changed, {removed} removed or changed\n");
+        }
+        if let Some(note) = &table.note {
+            out += &format!("  {note}\n");
+        }
+        for row in &table.sample {
+            let mut fields: Vec<_> = row
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            fields.sort();
+            out += &format!("  ~ {}\n", fields.join(" "));
+        }
+    }
+    out
+}
+
+fn delta(a: u64, b: u64) -> i128 {
+    b as i128 - a as i128
+}
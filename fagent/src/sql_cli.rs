@@ -0,0 +1,106 @@
+//! `fagent query`: runs a read-only SQL statement against the lake from the
+//! shell, for poking at tables without starting the dashboard.
+
+use fstorage::models::TableQueryResult;
+use serde_json::Value as JsonValue;
+
+use crate::{QueryArgs, QueryOutput};
+
+pub(crate) async fn run(args: QueryArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let result = match &args.table {
+        Some(table) => {
+            storage
+                .lake
+                .query_table_sql(table, &args.sql, args.limit)
+                .await?
+        }
+        None => storage.lake.query_lake_sql(&args.sql, args.limit).await?,
+    };
+    match args.format {
+        QueryOutput::Table => print!("{}", render_table(&result)),
+        QueryOutput::Csv => print!("{}", render_csv(&result)),
+        QueryOutput::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+    }
+    if result.truncated {
+        eprintln!(
+            "note: output stopped at {} rows; raise --limit to see more",
+            args.limit
+        );
+    }
+    Ok(())
+}
+
+fn cell(value: Option<&JsonValue>) -> String {
+    match value {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn cells(result: &TableQueryResult) -> Vec<Vec<String>> {
+    result
+        .rows
+        .iter()
+        .map(|row| {
+            result
+                .columns
+                .iter()
+                .map(|column| cell(row.get(column)))
+                .collect()
+        })
+        .collect()
+}
+
+pub(crate) fn render_table(result: &TableQueryResult) -> String {
+    let rows = cells(result);
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .fold(column.chars().count(), usize::max)
+        })
+        .collect();
+    let line = |values: &[String]| {
+        let padded: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:<width$}"))
+            .collect();
+        padded.join(" | ").trim_end().to_string() + "\n"
+    };
+
+    let mut out = line(&result.columns);
+    let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    out += &rule.join("-+-");
+    out += "\n";
+    for row in &rows {
+        out += &line(row);
+    }
+    out += &format!("({} rows)\n", rows.len());
+    out
+}
+
+fn render_csv(result: &TableQueryResult) -> String {
+    let record = |values: &[String]| {
+        let fields: Vec<String> = values.iter().map(|value| csv_field(value)).collect();
+        fields.join(",") + "\n"
+    };
+    let mut out = record(&result.columns);
+    for row in cells(result) {
+        out += &record(&row);
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,188 @@
+//! `fagent status`: what `/api/status` reports, plus where each lake table's
+//! ingestion stands and how much disk it takes, without starting the dashboard.
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{storage_status, StatusArgs, StatusResponse};
+
+/// Fetchers the dashboard registers in this build, unless told not to.
+const BUILT_IN_FETCHERS: &[&str] = if cfg!(feature = "fetchers") {
+    &["gitfetcher"]
+} else {
+    &[]
+};
+
+#[derive(Serialize)]
+struct StatusReport {
+    #[serde(flatten)]
+    status: StatusResponse,
+    fetchers: &'static [&'static str],
+    tables: Vec<TableStatus>,
+}
+
+#[derive(Serialize, Default)]
+struct TableStatus {
+    table_path: String,
+    /// Set for tables the catalog tracks ingestion for.
+    entity_type: Option<String>,
+    category: Option<&'static str>,
+    last_version: Option<i64>,
+    /// `None` when the tables are not under the lake directory: in an object
+    /// store or an Iceberg catalog.
+    files: Option<u64>,
+    bytes: Option<u64>,
+}
+
+pub(crate) async fn run(args: StatusArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let mut status = storage_status(&storage).await?;
+    status.registered_fetchers = BUILT_IN_FETCHERS.len();
+
+    let mut tables: BTreeMap<String, TableStatus> = BTreeMap::new();
+    for table in storage.lake.list_tables("").await? {
+        tables.entry(table.table_path.clone()).or_default();
+    }
+    for offset in storage.catalog.list_ingestion_offsets()? {
+        let table = tables.entry(offset.table_path).or_default();
+        table.entity_type = Some(offset.entity_type);
+        table.category = Some(offset.category.as_str());
+        table.last_version = Some(offset.last_version);
+    }
+    let local = storage.config.local_delta_lake();
+    for (table_path, table) in &mut tables {
+        table.table_path = table_path.clone();
+        let dir = storage.config.lake_path.join(table_path);
+        if local && dir.is_dir() {
+            let (files, bytes) =
+                disk_usage(&dir).with_context(|| format!("failed to measure {}", dir.display()))?;
+            table.files = Some(files);
+            table.bytes = Some(bytes);
+        }
+    }
+
+    let report = StatusReport {
+        status,
+        fetchers: BUILT_IN_FETCHERS,
+        tables: tables.into_values().collect(),
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render(&report));
+    }
+    Ok(())
+}
+
+/// Files under `dir` and their total size, Delta logs included.
+fn disk_usage(dir: &Path) -> io::Result<(u64, u64)> {
+    let mut files = 0;
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let (more_files, more_bytes) = disk_usage(&entry.path())?;
+            files += more_files;
+            bytes += more_bytes;
+        } else if file_type.is_file() {
+            files += 1;
+            bytes += entry.metadata()?.len();
+        }
+    }
+    Ok((files, bytes))
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn render(report: &StatusReport) -> String {
+    let status = &report.status;
+    let mut out = format!("{} known entities", status.entity_count);
+    if !status.entity_counts.is_empty() {
+        let counts: Vec<String> = status
+            .entity_counts
+            .iter()
+            .map(|(category, count)| format!("{count} {category}"))
+            .collect();
+        out += &format!(" ({})", counts.join(", "));
+    }
+    out += "\n";
+    let fetchers = if report.fetchers.is_empty() {
+        "none".to_string()
+    } else {
+        report.fetchers.join(", ")
+    };
+    out += &format!("fetchers: {fetchers}\n");
+
+    if let Some(stats) = status
+        .db_stats
+        .as_object()
+        .filter(|stats| !stats.is_empty())
+    {
+        out += "\nengine\n";
+        let width = stats.keys().map(String::len).max().unwrap_or(0);
+        for (key, value) in stats {
+            out += &format!("  {key:<width$}  {value}\n");
+        }
+    }
+
+    if report.tables.is_empty() {
+        out += "\nno lake tables\n";
+        return out;
+    }
+    let rows: Vec<[String; 5]> = report
+        .tables
+        .iter()
+        .map(|table| {
+            [
+                table.table_path.clone(),
+                table.category.unwrap_or("-").to_string(),
+                table
+                    .last_version
+                    .map_or("-".to_string(), |version| version.to_string()),
+                table
+                    .files
+                    .map_or("-".to_string(), |files| files.to_string()),
+                table.bytes.map_or("-".to_string(), human_bytes),
+            ]
+        })
+        .collect();
+    let header = ["table", "category", "version", "files", "size"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    out += "\n";
+    for row in std::iter::once(header.map(str::to_string)).chain(rows) {
+        let [path, category, version, files, size] = row;
+        out += &format!(
+            "{path:<w0$}  {category:<w1$}  {version:>w2$}  {files:>w3$}  {size:>w4$}\n",
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+        );
+    }
+    if report.tables.iter().any(|table| table.bytes.is_some()) {
+        let total: u64 = report.tables.iter().filter_map(|table| table.bytes).sum();
+        out += &format!("\n{} in lake tables\n", human_bytes(total));
+    }
+    out
+}
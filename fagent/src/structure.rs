@@ -0,0 +1,136 @@
+//! Structural node embeddings: `fagent embed-structure` trains them offline and
+//! `GET /api/graph/similar` looks up the nodes nearest to one of them.
+//!
+//! The embeddings capture where a node sits in the graph rather than what its
+//! text says, so they find functions called from the same places or projects
+//! sharing dependencies and contributors when their descriptions differ.
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use fstorage::models::{StructuralEmbeddingOptions, StructuralEmbeddingReport};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    map_node_summary, ApiError, ApiResult, AppState, EmbedStructureArgs, ErrorResponse,
+    GraphNodeSummary,
+};
+
+pub(crate) async fn run(args: EmbedStructureArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let defaults = StructuralEmbeddingOptions::default();
+    let options = StructuralEmbeddingOptions {
+        entity_types: if args.entity_types.is_empty() {
+            defaults.entity_types
+        } else {
+            args.entity_types
+        },
+        dimensions: args.dimensions,
+        walks_per_node: args.walks_per_node,
+        walk_length: args.walk_length,
+        window: args.window,
+        negative_samples: args.negative_samples,
+        epochs: args.epochs,
+        return_param: args.return_param,
+        in_out_param: args.in_out_param,
+        seed: args.seed,
+    };
+    let report = storage.train_structural_embeddings(&options).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render(&report));
+    }
+    Ok(())
+}
+
+fn render(report: &StructuralEmbeddingReport) -> String {
+    let mut out = format!(
+        "{}: {} nodes, {} edges\n",
+        report.model, report.nodes, report.edges
+    );
+    if report.embedded.is_empty() {
+        out += "no nodes of the requested types\n";
+    }
+    for (entity_type, count) in &report.embedded {
+        out += &format!("  {entity_type}: {count} embeddings\n");
+    }
+    if report.replaced > 0 {
+        out += &format!("{} embeddings of earlier runs replaced\n", report.replaced);
+    }
+    out
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SimilarQuery {
+    /// Node id.
+    id: String,
+    /// Nodes to return, 10 by default and at most 50.
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SimilarNode {
+    node: GraphNodeSummary,
+    /// Similarity of the two structural embeddings, 0 to 1.
+    similarity: f32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct SimilarNodesResponse {
+    node_id: String,
+    /// Nodes of the same type, most similar first.
+    similar: Vec<SimilarNode>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph/similar",
+    tag = "graph",
+    params(SimilarQuery),
+    responses(
+        (status = 200, description = "Structurally similar nodes", body = SimilarNodesResponse),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Graph-Version`"),
+        (status = 400, description = "Invalid node id", body = ErrorResponse),
+        (status = 404, description = "The node has no structural embedding", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn graph_similar(
+    State(state): State<AppState>,
+    Query(query): Query<SimilarQuery>,
+) -> ApiResult<Json<SimilarNodesResponse>> {
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let neighbors = state
+        .storage
+        .structurally_similar(&query.id, limit)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let ids: Vec<String> = neighbors
+        .iter()
+        .map(|neighbor| neighbor.node_id.clone())
+        .collect();
+    let mut nodes = state
+        .storage
+        .lake
+        .get_nodes_by_ids(&ids)
+        .await
+        .map_err(ApiError::from_storage)?;
+    let similar = neighbors
+        .into_iter()
+        .filter_map(|neighbor| {
+            let node = nodes.remove(&neighbor.node_id).and_then(map_node_summary)?;
+            Some(SimilarNode {
+                node,
+                similarity: neighbor.similarity,
+            })
+        })
+        .collect();
+    Ok(Json(SimilarNodesResponse {
+        node_id: query.id.trim().to_lowercase(),
+        similar,
+    }))
+}
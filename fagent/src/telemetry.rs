@@ -0,0 +1,150 @@
+//! Tracing setup: log lines on stderr and, when an OTLP endpoint is given,
+//! spans exported to a collector.
+//!
+//! Every request gets an `http.request` span; handlers, `Lake` queries and
+//! sync phases open child spans beneath it, so one trace shows where a slow
+//! request spent its time. The span carries the request's `x-request-id`,
+//! taken from the caller or generated, which is echoed in the response
+//! headers, in error bodies and in the access log line written when the
+//! request completes.
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::Context;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use tracing::{field::Empty, info, info_span, warn, Instrument, Span};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use uuid::Uuid;
+
+const SERVICE_NAME: &str = "fagent";
+
+pub(crate) const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longer ids from callers are replaced rather than logged.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: Arc<str>;
+}
+
+/// Keeps the span exporter alive; call [`Telemetry::shutdown`] to flush it.
+pub(crate) struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Installs the global subscriber. Must run inside a Tokio runtime when
+    /// `otlp_endpoint` is set, since the gRPC exporter is driven by it.
+    pub(crate) fn init(otlp_endpoint: Option<&str>) -> anyhow::Result<Self> {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let provider = otlp_endpoint.map(build_provider).transpose()?;
+        let otel_layer = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+        });
+        let _ = tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().with_writer(std::io::stderr))
+            .with(otel_layer)
+            .try_init();
+        Ok(Self { provider })
+    }
+
+    pub(crate) fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(err) = provider.shutdown() {
+                warn!("failed to flush trace exporter: {}", err);
+            }
+        }
+    }
+}
+
+fn build_provider(endpoint: &str) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .with_context(|| format!("failed to build OTLP exporter for {endpoint}"))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+/// Id of the request handled by the current task, outside of which (e.g. in
+/// background sync jobs) there is none.
+pub(crate) fn current_request_id() -> Option<Arc<str>> {
+    REQUEST_ID.try_with(Arc::clone).ok()
+}
+
+/// Outermost middleware: assigns the request id, opens the root span of the
+/// request and logs one access line once the response is ready.
+pub(crate) async fn log_request(mut request: Request, next: Next) -> Response {
+    let id: Arc<str> = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(Arc::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string().into());
+    // `to_str` only accepts visible ASCII, and generated ids are UUIDs.
+    let header = HeaderValue::from_str(&id).expect("request id is a valid header value");
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, header.clone());
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let span = info_span!(
+        "http.request",
+        otel.name = %method,
+        http.request.method = %method,
+        url.path = %path,
+        http.route = Empty,
+        http.response.status_code = Empty,
+        request_id = %id,
+    );
+    let started = Instant::now();
+    let mut response = REQUEST_ID
+        .scope(id, next.run(request).instrument(span.clone()))
+        .await;
+    let status = response.status().as_u16();
+    let latency_ms = started.elapsed().as_millis() as u64;
+    span.record("http.response.status_code", status);
+    span.in_scope(|| {
+        if response.status().is_server_error() {
+            warn!(target: "fagent::access", %method, %path, status, latency_ms, "request failed");
+        } else {
+            info!(target: "fagent::access", %method, %path, status, latency_ms, "request");
+        }
+    });
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    response
+}
+
+/// Route middleware naming the request's span after the matched route, which
+/// is only known once routing is done.
+pub(crate) async fn trace_request(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let span = Span::current();
+    span.record(
+        "otel.name",
+        format!("{} {route}", request.method()).as_str(),
+    );
+    span.record("http.route", route.as_str());
+    next.run(request).await
+}
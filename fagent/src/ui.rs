@@ -0,0 +1,60 @@
+//! The dashboard UI compiled into the binary from `dashboard_ui/`.
+//!
+//! Client-side routes such as `/graph/<id>` have no file of their own and get
+//! `index.html`. Builds without the `ui` feature leave these routes out.
+
+use axum::{
+    body::Body,
+    http::StatusCode,
+    response::{Html, Response},
+    routing::get,
+    Router,
+};
+
+const INDEX_HTML: &str = include_str!("../dashboard_ui/index.html");
+const GRAPH_HTML: &str = include_str!("../dashboard_ui/graph.html");
+const STYLES_CSS: &str = include_str!("../dashboard_ui/styles.css");
+const APP_JS: &str = include_str!("../dashboard_ui/app.js");
+const GRAPH_JS: &str = include_str!("../dashboard_ui/graph.js");
+
+pub(crate) fn routes() -> Router {
+    Router::new()
+        .route("/", get(serve_index))
+        .route("/graph.html", get(serve_graph))
+        .route("/styles.css", get(serve_styles))
+        .route("/app.js", get(serve_app_js))
+        .route("/graph.js", get(serve_graph_js))
+        .fallback(get(serve_index))
+}
+
+async fn serve_index() -> Html<&'static str> {
+    Html(INDEX_HTML)
+}
+
+async fn serve_graph() -> Html<&'static str> {
+    Html(GRAPH_HTML)
+}
+
+async fn serve_styles() -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/css; charset=utf-8")
+        .body(Body::from(STYLES_CSS))
+        .unwrap()
+}
+
+async fn serve_app_js() -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/javascript; charset=utf-8")
+        .body(Body::from(APP_JS))
+        .unwrap()
+}
+
+async fn serve_graph_js() -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/javascript; charset=utf-8")
+        .body(Body::from(GRAPH_JS))
+        .unwrap()
+}
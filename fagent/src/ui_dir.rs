@@ -0,0 +1,43 @@
+//! Dashboard assets served from a directory instead of the binary.
+//!
+//! With `--ui-dir`, a request for a static file is answered from that
+//! directory when it holds the file, and from the built-in UI otherwise, so a
+//! directory carrying only a restyled `styles.css` is enough for branding.
+//! Paths that name no file get the directory's `index.html` when it has one,
+//! like the built-in UI does for client-side routes. Responses carry
+//! `Cache-Control: no-cache`: browsers revalidate against `Last-Modified` on
+//! every load and pick up edits without a hard refresh.
+
+use std::path::Path;
+
+use axum::{
+    http::{header::CACHE_CONTROL, HeaderValue},
+    middleware,
+    response::Response,
+    Router,
+};
+use tower_http::services::{ServeDir, ServeFile};
+
+/// Serves `dir` in front of `builtin`, the compiled-in asset routes.
+pub(crate) fn routes(dir: &Path, builtin: Router) -> Router {
+    let index = dir.join("index.html");
+    let builtin = if index.is_file() {
+        builtin.fallback_service(ServeFile::new(index))
+    } else {
+        builtin
+    };
+    Router::new()
+        .fallback_service(
+            ServeDir::new(dir)
+                .append_index_html_on_directories(true)
+                .fallback(builtin),
+        )
+        .layer(middleware::map_response(revalidate))
+}
+
+async fn revalidate(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
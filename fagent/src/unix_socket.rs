@@ -0,0 +1,92 @@
+//! Serving the dashboard on a Unix domain socket.
+//!
+//! `axum::serve` only accepts TCP listeners, so connections are accepted here
+//! and handed to hyper directly. Callers have no peer address; each request
+//! carries the peer's credentials as a [`UnixPeer`] instead, so the rate
+//! limiter can tell the users behind the socket apart.
+
+use std::{future::Future, io, os::unix::fs::FileTypeExt, path::Path};
+
+use anyhow::Context;
+use axum::{Extension, Router};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use tokio::net::UnixListener;
+use tracing::{debug, info, warn};
+
+/// Credentials of the process on the other end of a connection.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UnixPeer {
+    /// User id the process runs as, when the platform reports it.
+    pub(crate) uid: Option<u32>,
+}
+
+/// Binds `path`, replacing a socket left behind by an earlier run, and serves
+/// `router` until `shutdown` resolves. Open connections are drained before
+/// the socket file is removed.
+pub(crate) async fn serve(
+    path: &Path,
+    router: Router,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("failed to bind dashboard socket {}", path.display()))?;
+    info!("Dashboard listening on unix:{}", path.display());
+
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    warn!("failed to accept dashboard connection: {}", err);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        let peer = UnixPeer {
+            uid: stream.peer_cred().ok().map(|cred| cred.uid()),
+        };
+        let connection = auto::Builder::new(TokioExecutor::new())
+            .serve_connection_with_upgrades(
+                TokioIo::new(stream),
+                TowerToHyperService::new(router.clone().layer(Extension(peer))),
+            )
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                debug!("dashboard connection closed with error: {}", err);
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    if let Err(err) = std::fs::remove_file(path) {
+        warn!(
+            "failed to remove dashboard socket {}: {}",
+            path.display(),
+            err
+        );
+    }
+    Ok(())
+}
+
+/// Removes `path` when it is a socket, so a crashed run does not block the
+/// next bind. Anything else at the path is left alone and fails the bind.
+fn remove_stale_socket(path: &Path) -> anyhow::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display())),
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("failed to inspect {}", path.display())),
+    }
+}
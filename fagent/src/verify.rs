@@ -0,0 +1,109 @@
+//! `fagent verify-lake`: finds damaged or partially written lake files before
+//! a query trips over them. Exits with an error when any file has a problem.
+//!
+//! `fagent repair-table`: gets a table whose Delta log has malformed entries
+//! opening again, as the `CorruptLog` error suggests.
+//!
+//! `fagent validate`: checks that the catalog, the lake tables and the engine
+//! agree, and with `--repair` fixes what it can. Exits with an error when
+//! issues remain.
+
+use anyhow::bail;
+use fstorage::models::{ConsistencyReport, LakeVerification};
+
+use crate::{RepairTableArgs, ValidateArgs, VerifyLakeArgs};
+
+pub(crate) async fn run(args: VerifyLakeArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let report = storage.verify_lake().await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render(&report));
+    }
+    if !report.is_healthy() {
+        bail!("{} lake file problem(s) found", report.issues.len());
+    }
+    Ok(())
+}
+
+pub(crate) async fn run_repair(args: RepairTableArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let repair = storage.repair_table(&args.table).await?;
+    if repair.repaired_versions.is_empty() {
+        println!("{}: no malformed log entries", repair.table);
+    } else {
+        println!(
+            "{}: removed {} malformed line(s) from version(s) {:?}, saved to {}",
+            repair.table,
+            repair.quarantined_lines,
+            repair.repaired_versions,
+            repair.quarantine_dir.as_deref().unwrap_or("-")
+        );
+    }
+    println!(
+        "  checkpoint written at version {}",
+        repair.checkpoint_version
+    );
+    Ok(())
+}
+
+pub(crate) async fn run_validate(args: ValidateArgs) -> anyhow::Result<()> {
+    let storage = args.store.open().await?;
+    let report = storage.check_consistency(args.repair).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", render_consistency(&report));
+    }
+    let unrepaired = report.unrepaired();
+    if unrepaired > 0 {
+        bail!("{unrepaired} consistency issue(s) remain");
+    }
+    Ok(())
+}
+
+fn render_consistency(report: &ConsistencyReport) -> String {
+    let mut out = format!(
+        "{} offsets, {} index ids, {} edges, {} vectors checked\n",
+        report.offsets, report.index_ids, report.edges, report.vectors
+    );
+    if report.issues.is_empty() {
+        out += "no issues\n";
+    }
+    for issue in &report.issues {
+        out += &format!(
+            "\n{}{}: {}{}\n  {}\n",
+            issue.problem.as_str(),
+            if issue.repaired { " (repaired)" } else { "" },
+            issue.table_path,
+            issue
+                .id
+                .as_deref()
+                .map(|id| format!(" {id}"))
+                .unwrap_or_default(),
+            issue.detail
+        );
+    }
+    out
+}
+
+fn render(report: &LakeVerification) -> String {
+    let mut out = format!(
+        "{} files in {} tables, {} bytes\n",
+        report.files, report.tables, report.bytes
+    );
+    out += &format!(
+        "  {} checksums recorded, {} forgotten\n",
+        report.recorded, report.forgotten
+    );
+    for issue in &report.issues {
+        out += &format!(
+            "\n{}: {}\n  {}\n",
+            issue.problem.as_str(),
+            issue.path,
+            issue.detail
+        );
+    }
+    out
+}
@@ -0,0 +1,110 @@
+//! Versioned API prefix.
+//!
+//! `/api/v1/...` is the stable surface: the request and response shapes served
+//! there, as described by the OpenAPI document, only change in
+//! backwards-compatible ways until a `v2` prefix appears. The unversioned
+//! `/api/...` paths predate the prefix and answer identically, but every
+//! response says so with `Deprecation` and a `Link` to the v1 successor. Once an
+//! operator-chosen sunset has passed they answer `410 Gone` instead.
+//!
+//! Both prefixes are served by the same handlers: v1 requests are rewritten to
+//! the unversioned path before routing, so route templates seen by middleware
+//! (auth, rate limits, usage tracking) do not depend on the prefix used.
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{HeaderName, LINK},
+        uri::PathAndQuery,
+        HeaderValue, Uri,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use crate::ApiError;
+
+pub(crate) const CURRENT_PREFIX: &str = "/api/v1";
+
+/// When the unversioned paths were deprecated, as an RFC 9745 `@<unix>` date.
+const LEGACY_DEPRECATED_AT: &str = "@1792108800";
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// How the unversioned `/api/...` paths are treated.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LegacyApiPolicy {
+    /// After this instant legacy requests get `410 Gone`; announced beforehand
+    /// in the `Sunset` header.
+    pub(crate) sunset: Option<DateTime<Utc>>,
+}
+
+/// The v1 path for an unversioned route path or template.
+pub(crate) fn versioned_path(path: &str) -> String {
+    match path.strip_prefix("/api") {
+        Some(rest) => format!("{CURRENT_PREFIX}{rest}"),
+        None => path.to_string(),
+    }
+}
+
+/// Unversioned API paths; the docs are not part of the versioned surface.
+fn is_legacy(path: &str) -> bool {
+    path.starts_with("/api/")
+        && !path.starts_with("/api/docs")
+        && path != "/api/openapi.json"
+        && path != CURRENT_PREFIX
+}
+
+fn strip_version(uri: &Uri) -> Option<Uri> {
+    let rest = uri.path().strip_prefix(CURRENT_PREFIX)?;
+    if !rest.starts_with('/') {
+        return None;
+    }
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/api{rest}?{query}"),
+        None => format!("/api{rest}"),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse::<PathAndQuery>().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+/// Middleware run ahead of routing: maps v1 paths onto the handlers and marks
+/// or refuses unversioned ones.
+pub(crate) async fn route_version(
+    State(policy): State<LegacyApiPolicy>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(uri) = strip_version(request.uri()) {
+        *request.uri_mut() = uri;
+        return next.run(request).await;
+    }
+    if !is_legacy(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let successor = versioned_path(request.uri().path());
+    if policy.sunset.is_some_and(|sunset| Utc::now() >= sunset) {
+        return ApiError::Gone(format!(
+            "unversioned API paths were retired; use {successor}"
+        ))
+        .into_response();
+    }
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, HeaderValue::from_static(LEGACY_DEPRECATED_AT));
+    if let Ok(link) = HeaderValue::from_str(&format!("<{successor}>; rel=\"successor-version\"")) {
+        headers.insert(LINK, link);
+    }
+    if let Some(sunset) = policy.sunset {
+        let date = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert(SUNSET, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,449 @@
+//! GitHub webhook receiver.
+//!
+//! Deliveries are authenticated by their `X-Hub-Signature-256` HMAC rather than
+//! an API key, so the route sits outside the key-based authorization. Pushes to
+//! the default branch, issue and pull request events are turned into targeted
+//! `gitfetcher` syncs queued like any other; other events are acknowledged and
+//! dropped. A push syncs only the files it added or changed, and the files it
+//! removed are deleted from the version it creates; earlier versions keep
+//! theirs. A push whose payload does not list all of its commits cannot be
+//! diffed, so it syncs the whole snapshot of the pushed revision instead.
+//!
+//! GitHub retries and redelivers under the same `X-GitHub-Delivery` id, so a
+//! delivery id seen within [`DELIVERY_RETENTION_SECS`] is acknowledged without
+//! doing its work again.
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::collections::BTreeSet;
+
+use fstorage::{
+    fetch::Fetchable,
+    models::EntityIdentifier,
+    schemas::generated_schemas::{File, Issue, PullRequest},
+    utils,
+};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    queue_sync, ApiError, ApiResult, AppState, ErrorResponse, SyncAcceptedResponse, SyncPriority,
+    SyncRequest,
+};
+
+/// GitHub caps webhook payloads at 25 MB.
+pub(crate) const WEBHOOK_BODY_LIMIT: usize = 25 * 1024 * 1024;
+
+const FETCHER: &str = "gitfetcher";
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+const EVENT_HEADER: &str = "x-github-event";
+const DELIVERY_HEADER: &str = "x-github-delivery";
+
+/// How long a delivery id is remembered; GitHub only redelivers recent
+/// deliveries.
+const DELIVERY_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+/// Most commits a push payload lists; a push of more is truncated.
+const PUSH_COMMITS_LISTED: usize = 20;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct GithubWebhookResponse {
+    event: String,
+    /// Why nothing was queued, for deliveries that do not touch the graph.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignored: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync: Option<SyncAcceptedResponse>,
+    /// File nodes of the pushed version deleted because the push removed
+    /// their paths.
+    #[serde(skip_serializing_if = "is_zero")]
+    files_removed: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: String,
+    #[serde(default)]
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    after: String,
+    #[serde(default)]
+    deleted: bool,
+    repository: Repository,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+    /// Commits in the push, listed or not, where the payload gives it.
+    #[serde(default)]
+    size: Option<usize>,
+}
+
+impl PushEvent {
+    /// Whether `commits` may leave out some of the pushed commits.
+    fn is_truncated(&self) -> bool {
+        self.commits.len() >= PUSH_COMMITS_LISTED
+            || self.size.is_some_and(|size| size > self.commits.len())
+    }
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    #[serde(default)]
+    added: Vec<String>,
+    #[serde(default)]
+    removed: Vec<String>,
+    #[serde(default)]
+    modified: Vec<String>,
+}
+
+/// What a delivery calls for.
+struct Plan {
+    request: SyncRequest,
+    /// Version a push creates, whose files at `removed_files` are deleted
+    /// before the sync.
+    version_sha: Option<String>,
+    removed_files: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Numbered {
+    number: i64,
+}
+
+#[derive(Deserialize)]
+struct IssuesEvent {
+    issue: Numbered,
+    repository: Repository,
+}
+
+#[derive(Deserialize)]
+struct PullRequestEvent {
+    pull_request: Numbered,
+    repository: Repository,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/github",
+    tag = "sync",
+    security(()),
+    request_body(content = Object, description = "GitHub webhook payload"),
+    params(
+        ("X-GitHub-Event" = String, Header, description = "Event name, e.g. `push`"),
+        ("X-GitHub-Delivery" = Option<String>, Header, description = "Delivery id; a redelivery of a handled id is acknowledged without a sync"),
+        ("X-Hub-Signature-256" = String, Header, description = "`sha256=` HMAC of the body under the webhook secret")
+    ),
+    responses(
+        (status = 202, description = "A sync of the affected entities was queued", body = GithubWebhookResponse),
+        (status = 200, description = "Event or redelivery acknowledged without a sync", body = GithubWebhookResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 401, description = "Missing or invalid signature", body = ErrorResponse),
+        (status = 404, description = "No webhook secret is configured", body = ErrorResponse)
+    )
+)]
+pub(crate) async fn github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult<(StatusCode, Json<GithubWebhookResponse>)> {
+    let Some(secret) = state.github_webhook_secret.as_deref() else {
+        return Err(ApiError::NotFound(
+            "GitHub webhooks are not enabled".to_string(),
+        ));
+    };
+    let signature = header(&headers, SIGNATURE_HEADER)
+        .ok_or_else(|| ApiError::Unauthorized("webhook signature is missing".to_string()))?;
+    if !verify_signature(secret.as_bytes(), &body, signature) {
+        return Err(ApiError::Unauthorized(
+            "webhook signature does not match".to_string(),
+        ));
+    }
+    let event = header(&headers, EVENT_HEADER)
+        .ok_or_else(|| ApiError::BadRequest("X-GitHub-Event header is required".to_string()))?
+        .to_string();
+    let delivery = header(&headers, DELIVERY_HEADER).unwrap_or_default();
+
+    if !delivery.is_empty() {
+        let now = chrono::Utc::now().timestamp();
+        let first = state
+            .storage
+            .catalog
+            .claim_webhook_delivery(delivery, now, now - DELIVERY_RETENTION_SECS)
+            .map_err(ApiError::from_storage)?;
+        if !first {
+            info!(event, delivery, "ignored GitHub webhook redelivery");
+            return Ok(acknowledged(event, "delivery was already handled"));
+        }
+    }
+    let handled = handle_delivery(&state, event, delivery, &body).await;
+    if handled.is_err() && !delivery.is_empty() {
+        // The work was not done, so a redelivery must not be skipped.
+        if let Err(err) = state.storage.catalog.release_webhook_delivery(delivery) {
+            warn!(delivery, error = %err, "failed to forget GitHub webhook delivery");
+        }
+    }
+    handled
+}
+
+async fn handle_delivery(
+    state: &AppState,
+    event: String,
+    delivery: &str,
+    body: &[u8],
+) -> ApiResult<(StatusCode, Json<GithubWebhookResponse>)> {
+    let plan = match plan_for(&event, body)? {
+        Ok(plan) => plan,
+        Err(reason) => {
+            info!(event, delivery, reason, "ignored GitHub webhook");
+            return Ok(acknowledged(event, reason));
+        }
+    };
+    let files_removed = match &plan.version_sha {
+        Some(sha) => remove_files(state, sha, &plan.removed_files).await?,
+        None => 0,
+    };
+    let accepted = queue_sync(state, plan.request)?;
+    info!(
+        event,
+        delivery,
+        job_id = accepted.job_id,
+        files_removed,
+        "queued sync for GitHub webhook"
+    );
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(GithubWebhookResponse {
+            event,
+            ignored: None,
+            sync: Some(accepted),
+            files_removed,
+        }),
+    ))
+}
+
+fn acknowledged(event: String, reason: &str) -> (StatusCode, Json<GithubWebhookResponse>) {
+    (
+        StatusCode::OK,
+        Json(GithubWebhookResponse {
+            event,
+            ignored: Some(reason.to_string()),
+            sync: None,
+            files_removed: 0,
+        }),
+    )
+}
+
+/// Deletes the File nodes at `paths` of the version `sha`. Returns how many
+/// there were.
+async fn remove_files(state: &AppState, sha: &str, paths: &[String]) -> ApiResult<usize> {
+    let mut removed = 0;
+    for path in paths {
+        let file_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+            File::ENTITY_TYPE,
+            &[("version_sha", sha.to_string()), ("path", path.clone())],
+        ))
+        .to_string();
+        let deletion = state
+            .storage
+            .delete_node(&file_id)
+            .await
+            .map_err(ApiError::from_storage)?;
+        removed += usize::from(deletion.is_some());
+    }
+    Ok(removed)
+}
+
+/// Paths a push leaves added or changed, and paths it leaves removed, going
+/// through its commits in order.
+fn file_changes(commits: Vec<PushCommit>) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut changed = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    for commit in commits {
+        for path in commit.added.into_iter().chain(commit.modified) {
+            removed.remove(&path);
+            changed.insert(path);
+        }
+        for path in commit.removed {
+            changed.remove(&path);
+            removed.insert(path);
+        }
+    }
+    (changed, removed)
+}
+
+/// What a delivery calls for, or why it needs nothing. Each sync is limited to
+/// the entities the event names.
+fn plan_for(event: &str, body: &[u8]) -> ApiResult<Result<Plan, &'static str>> {
+    let plan = match event {
+        "push" => {
+            let push: PushEvent = parse(body)?;
+            let default_ref = push
+                .repository
+                .default_branch
+                .as_deref()
+                .map(|branch| format!("refs/heads/{branch}"));
+            if push.deleted {
+                return Ok(Err("the pushed ref was deleted"));
+            }
+            if default_ref.as_deref() != Some(push.git_ref.as_str()) {
+                return Ok(Err("push to a branch other than the default"));
+            }
+            if push.is_truncated() {
+                // The listed commits are not the whole diff, so none of it is
+                // trusted: the version is synced in full, without the removed
+                // files in it.
+                return Ok(Ok(Plan {
+                    request: webhook_sync(
+                        event,
+                        json!({
+                            "mode": "repo_snapshot",
+                            "repo": push.repository.full_name,
+                            "rev": push.after,
+                            "include_code": true,
+                        }),
+                        Vec::new(),
+                    ),
+                    version_sha: None,
+                    removed_files: Vec::new(),
+                }));
+            }
+            let (changed, removed) = file_changes(push.commits);
+            // Without changed files only the project, the version and its
+            // commit are refreshed; removed files are not fetched at all.
+            let entity_types = if changed.is_empty() {
+                json!([])
+            } else {
+                json!(["code"])
+            };
+            let targets = changed
+                .into_iter()
+                .map(|path| target(File::ENTITY_TYPE, path))
+                .collect();
+            Plan {
+                request: webhook_sync(
+                    event,
+                    json!({
+                        "mode": "repo_snapshot",
+                        "repo": push.repository.full_name,
+                        "rev": push.after,
+                        "entity_types": entity_types,
+                    }),
+                    targets,
+                ),
+                version_sha: Some(push.after),
+                removed_files: removed.into_iter().collect(),
+            }
+        }
+        "issues" => {
+            let issues: IssuesEvent = parse(body)?;
+            let uri = format!("{}#{}", issues.repository.full_name, issues.issue.number);
+            Plan {
+                request: webhook_sync(
+                    event,
+                    snapshot_params(&issues.repository, "issue"),
+                    vec![target(Issue::ENTITY_TYPE, uri)],
+                ),
+                version_sha: None,
+                removed_files: Vec::new(),
+            }
+        }
+        "pull_request" => {
+            let pull: PullRequestEvent = parse(body)?;
+            let uri = format!("{}#{}", pull.repository.full_name, pull.pull_request.number);
+            Plan {
+                request: webhook_sync(
+                    event,
+                    snapshot_params(&pull.repository, "pull_request"),
+                    vec![target(PullRequest::ENTITY_TYPE, uri)],
+                ),
+                version_sha: None,
+                removed_files: Vec::new(),
+            }
+        }
+        "ping" => return Ok(Err("ping")),
+        _ => return Ok(Err("event does not affect the graph")),
+    };
+    Ok(Ok(plan))
+}
+
+/// A snapshot of `repository` limited to the one entity group, so neither
+/// the code, the README nor the other issues and pull requests are fetched.
+fn snapshot_params(repository: &Repository, entity_type: &str) -> serde_json::Value {
+    json!({
+        "mode": "repo_snapshot",
+        "repo": repository.full_name,
+        "entity_types": [entity_type],
+    })
+}
+
+fn target(entity_type: &str, uri: String) -> EntityIdentifier {
+    EntityIdentifier {
+        uri,
+        entity_type: entity_type.to_string(),
+        fetcher_name: Some(FETCHER.to_string()),
+        params: None,
+        anchor_key: None,
+    }
+}
+
+fn webhook_sync(
+    event: &str,
+    params: serde_json::Value,
+    target_entities: Vec<EntityIdentifier>,
+) -> SyncRequest {
+    SyncRequest {
+        fetcher: FETCHER.to_string(),
+        params,
+        triggering_query: Some(format!("GitHub {event} webhook")),
+        target_entities,
+        entity_types: Vec::new(),
+        budget: None,
+        priority: SyncPriority::Normal,
+        then: Vec::new(),
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> ApiResult<T> {
+    serde_json::from_slice(body)
+        .map_err(|err| ApiError::BadRequest(format!("invalid webhook payload: {err}")))
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Checks a `sha256=<hex>` signature against the HMAC-SHA256 of `body`.
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let Some(given) = signature
+        .strip_prefix("sha256=")
+        .and_then(|digest| hex::decode(digest).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret) else {
+        return false;
+    };
+    mac.update(body);
+    // Constant time, so the comparison does not reveal how much of a guess matched.
+    mac.verify_slice(&given).is_ok()
+}
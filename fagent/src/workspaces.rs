@@ -0,0 +1,156 @@
+//! Several knowledge bases served by one dashboard process.
+//!
+//! The store opened from `--base-path` answers under `/api/...` as before. Each
+//! `--workspace name=path` opens another store, with its own sync workers,
+//! schedules and response cache, whose API is served under `/api/{name}/...`:
+//! `/api/v1/team-a/graph/overview` answers what `/api/v1/graph/overview` would
+//! for a server started on team-a's store alone. Workspaces share the server's
+//! API keys and rate limits.
+//!
+//! Like the version prefix, the workspace segment is resolved before routing,
+//! so the workspace's own router sees the same route templates as the default
+//! store does.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::{uri::PathAndQuery, Uri},
+    middleware::Next,
+    response::Response,
+    Json, Router,
+};
+use tower::util::ServiceExt;
+
+use crate::{ApiResult, AppState};
+
+/// First path segments under `/api` that belong to the default store's routes.
+const RESERVED_NAMES: &[&str] = &[
+    "admin",
+    "bookmarks",
+    "debug",
+    "docs",
+    "federation",
+    "fetchers",
+    "graph",
+    "identities",
+    "mounts",
+    "onboard",
+    "openapi.json",
+    "privacy",
+    "projects",
+    "query",
+    "readiness",
+    "reports",
+    "saved_searches",
+    "schedules",
+    "search",
+    "snapshots",
+    "status",
+    "sync",
+    "tables",
+    "vectors",
+    "webhooks",
+    "workspaces",
+];
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Checks that `name` can be used as a path segment without shadowing a route.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "workspace names must be 1 to {MAX_NAME_LEN} characters"
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "workspace name '{name}' may only use lowercase letters, digits, '-' and '_'"
+        ));
+    }
+    // `v1`, `v2`, ... are kept for API versions.
+    let version = name
+        .strip_prefix('v')
+        .is_some_and(|number| number.parse::<u32>().is_ok());
+    if RESERVED_NAMES.contains(&name) || version {
+        return Err(format!(
+            "workspace name '{name}' is reserved for an API route"
+        ));
+    }
+    Ok(())
+}
+
+/// API routers of the extra workspaces, keyed by name.
+#[derive(Clone, Default)]
+pub(crate) struct WorkspaceRouters {
+    routers: Arc<BTreeMap<String, Router>>,
+}
+
+impl WorkspaceRouters {
+    /// Builds each workspace's API with the server's keys and rate limits.
+    pub(crate) fn new(server: &AppState) -> Self {
+        let routers = server
+            .workspaces
+            .iter()
+            .map(|(name, workspace)| {
+                let state = AppState {
+                    access: Arc::clone(&server.access),
+                    limiter: Arc::clone(&server.limiter),
+                    ..workspace.clone()
+                };
+                (name.clone(), crate::api_routes(state))
+            })
+            .collect();
+        Self {
+            routers: Arc::new(routers),
+        }
+    }
+}
+
+/// Splits `/api/{workspace}/{rest}` into the workspace and `/api/{rest}`.
+fn split_workspace(uri: &Uri) -> Option<(&str, Uri)> {
+    let (name, rest) = uri.path().strip_prefix("/api/")?.split_once('/')?;
+    if rest.is_empty() {
+        return None;
+    }
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/api/{rest}?{query}"),
+        None => format!("/api/{rest}"),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse::<PathAndQuery>().ok()?);
+    Some((name, Uri::from_parts(parts).ok()?))
+}
+
+/// Middleware run ahead of routing: hands `/api/{workspace}/...` requests to
+/// that workspace's router.
+pub(crate) async fn route_workspace(
+    State(workspaces): State<WorkspaceRouters>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some((router, uri)) = split_workspace(request.uri()).and_then(|(name, uri)| {
+        let router = workspaces.routers.get(name)?;
+        Some((router.clone(), uri))
+    }) else {
+        return next.run(request).await;
+    };
+    *request.uri_mut() = uri;
+    router
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|never| match never {})
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/workspaces",
+    tag = "storage",
+    responses((status = 200, description = "Names of the workspaces served under `/api/{name}/...`", body = [String]))
+)]
+pub(crate) async fn list_workspaces(State(state): State<AppState>) -> ApiResult<Json<Vec<String>>> {
+    Ok(Json(state.workspaces.keys().cloned().collect()))
+}
@@ -4,21 +4,76 @@ use axum::{
     body::{to_bytes, Body},
     http::{Request, StatusCode},
 };
-use fagent::{build_router, AppState};
+use fagent::{build_router, AppState, CorsOrigins, RateLimit, Role};
 use fstorage::{
     config::StorageConfig,
-    fetch::{Fetchable, GraphData},
-    schemas::generated_schemas::{Commit, HasVersion, IsCommit, Project, Version},
+    embedding::EmbeddingProvider,
+    fetch::{FetchResponse, Fetchable, Fetcher, FetcherCapability, GraphData, ProbeReport},
+    models::EntityReadiness,
+    schemas::generated_schemas::{
+        Commit, Contains, ContributesTo, DependsOn, Developer, File, Function, GitIdentity,
+        HasVersion, IsCommit, Issue, Library, OpenedIssue, Project, PullRequest, ReadmeChunk,
+        Version,
+    },
     sync::DataSynchronizer,
     utils, FStorage,
 };
-use serde_json::Value;
+use serde_json::{json, Value};
 use tempfile::tempdir;
 use tower::util::ServiceExt;
 use uuid::Uuid;
 
 const BODY_LIMIT: usize = 1 << 20;
 
+struct EmptyFetcher;
+
+#[async_trait::async_trait]
+impl Fetcher for EmptyFetcher {
+    fn name(&self) -> &'static str {
+        "empty_mock"
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: "empty_mock",
+            description: "Fetcher returning an empty graph",
+            param_schema: json!({"type": "object"}),
+            produces: Vec::new(),
+            default_ttl_secs: None,
+            examples: Vec::new(),
+        }
+    }
+
+    async fn probe(&self, _params: Value) -> fstorage::errors::Result<ProbeReport> {
+        Ok(ProbeReport {
+            fresh: None,
+            remote_anchor: None,
+            local_anchor: None,
+            anchor_key: None,
+            estimated_missing: None,
+            rate_limit_left: None,
+            reason: None,
+        })
+    }
+
+    async fn fetch(
+        &self,
+        _params: Value,
+        _embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        Ok(FetchResponse::GraphData(GraphData::new()))
+    }
+
+    fn admit(&self, params: &Value) -> fstorage::errors::Result<()> {
+        if params.get("denied").and_then(Value::as_bool) == Some(true) {
+            return Err(fstorage::errors::StorageError::PolicyDenied(
+                "denied by test policy".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 async fn test_app() -> anyhow::Result<(axum::Router, tempfile::TempDir)> {
     let dir = tempdir()?;
     let config = StorageConfig::new(dir.path());
@@ -27,6 +82,117 @@ async fn test_app() -> anyhow::Result<(axum::Router, tempfile::TempDir)> {
     Ok((app, dir))
 }
 
+/// Blocks every fetch until the test hands out a permit.
+struct GatedFetcher {
+    gate: Arc<tokio::sync::Semaphore>,
+}
+
+#[async_trait::async_trait]
+impl Fetcher for GatedFetcher {
+    fn name(&self) -> &'static str {
+        "gated_mock"
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: "gated_mock",
+            ..EmptyFetcher.capability()
+        }
+    }
+
+    async fn probe(&self, params: Value) -> fstorage::errors::Result<ProbeReport> {
+        EmptyFetcher.probe(params).await
+    }
+
+    async fn fetch(
+        &self,
+        params: Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        self.gate.acquire().await.expect("gate open").forget();
+        EmptyFetcher.fetch(params, embedding_provider).await
+    }
+}
+
+/// Fails its first fetch, then behaves like [`EmptyFetcher`].
+#[derive(Default)]
+struct FlakyFetcher {
+    failed_once: std::sync::atomic::AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl Fetcher for FlakyFetcher {
+    fn name(&self) -> &'static str {
+        "flaky_mock"
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: "flaky_mock",
+            ..EmptyFetcher.capability()
+        }
+    }
+
+    async fn probe(&self, params: Value) -> fstorage::errors::Result<ProbeReport> {
+        EmptyFetcher.probe(params).await
+    }
+
+    async fn fetch(
+        &self,
+        params: Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        if !self
+            .failed_once
+            .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            return Err(fstorage::errors::StorageError::SyncError(
+                "upstream hiccup".to_string(),
+            ));
+        }
+        EmptyFetcher.fetch(params, embedding_provider).await
+    }
+}
+
+async fn post_sync(app: &axum::Router, body: Value) -> anyhow::Result<i64> {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/sync")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    let value: Value = serde_json::from_slice(&body)?;
+    Ok(value
+        .get("job_id")
+        .and_then(Value::as_i64)
+        .expect("job id returned"))
+}
+
+async fn wait_for_job_state(app: &axum::Router, job_id: i64, state: &str) -> anyhow::Result<Value> {
+    let mut job = Value::Null;
+    for _ in 0..100 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/sync/jobs/{job_id}"))
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+        job = serde_json::from_slice(&body)?;
+        if job.get("state").and_then(Value::as_str) == Some(state) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(job.get("state").and_then(Value::as_str), Some(state));
+    Ok(job)
+}
+
 #[tokio::test]
 async fn fetchers_endpoint_returns_empty_list() -> anyhow::Result<()> {
     let (app, _dir) = test_app().await?;
@@ -61,6 +227,45 @@ async fn status_endpoint_reports_counts() -> anyhow::Result<()> {
         .and_then(Value::as_u64)
         .unwrap_or_default();
     assert_eq!(count, 0);
+    assert_eq!(value["entity_counts"], json!({}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_ids_are_echoed_and_reported_in_errors() -> anyhow::Result<()> {
+    let (app, _dir) = test_app().await?;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/status")
+                .header("x-request-id", "trace-42")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-request-id"], "trace-42");
+
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/healthz").body(Body::empty())?)
+        .await?;
+    let generated = response.headers()["x-request-id"].to_str()?.to_string();
+    assert!(Uuid::parse_str(&generated).is_ok());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/graph/node?id=missing")
+                .header("x-request-id", "trace-43")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert!(response.status().is_client_error());
+    assert_eq!(response.headers()["x-request-id"], "trace-43");
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(body["request_id"], json!("trace-43"));
+    assert!(body["error"].is_string());
     Ok(())
 }
 
@@ -81,6 +286,681 @@ async fn readiness_endpoint_accepts_empty_payload() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn readiness_auto_sync_queues_stale_entities() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    storage.register_fetcher(Arc::new(EmptyFetcher));
+    let app = build_router(AppState::new(storage.clone()));
+    // A sync already queued for an entity is reported instead of a new one;
+    // written after startup, no worker picks this one up.
+    let busy = json!({
+        "fetcher": "empty_mock",
+        "target_entities": [{"uri": "repo://busy", "entity_type": "project"}]
+    });
+    let busy_job = storage
+        .catalog
+        .create_sync_job("empty_mock", &busy.to_string())?;
+
+    let body = json!({
+        "auto_sync": true,
+        "budget": {"type": "request_count", "count": 5},
+        "entities": [
+            {"uri": "repo://stale", "entity_type": "project", "fetcher_name": "empty_mock", "params": {}},
+            {"uri": "repo://busy", "entity_type": "project", "fetcher_name": "empty_mock", "params": {}},
+            {"uri": "repo://orphan", "entity_type": "project"},
+            {"uri": "repo://denied", "entity_type": "project", "fetcher_name": "empty_mock", "params": {"denied": true}}
+        ]
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/readiness")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let value: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+
+    assert_eq!(value["readiness"]["repo://stale"]["is_fresh"], json!(false));
+    let syncs = value["syncs"].as_array().expect("syncs is a list");
+    assert_eq!(syncs.len(), 2);
+    assert_eq!(syncs[0]["uri"], "repo://stale");
+    assert_eq!(syncs[1]["uri"], "repo://busy");
+    assert_eq!(syncs[1]["job_id"], json!(busy_job));
+    let not_queued = value["not_queued"]
+        .as_array()
+        .expect("not_queued is a list");
+    assert_eq!(not_queued.len(), 2);
+    assert_eq!(not_queued[0]["code"], "INVALID_ARGUMENT");
+    assert_eq!(not_queued[1]["code"], "POLICY_DENIED");
+
+    let job_id = syncs[0]["job_id"].as_i64().expect("job id");
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert_eq!(
+        job.pointer("/request/priority"),
+        Some(&json!("interactive"))
+    );
+    assert_eq!(
+        job.pointer("/request/target_entities/0/uri"),
+        Some(&json!("repo://stale"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn api_key_roles_gate_mutating_and_admin_endpoints() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let app = build_router(
+        AppState::new(storage)
+            .with_api_key("view-key", Role::Viewer)
+            .with_api_key("ops-key", Role::Operator),
+    );
+
+    let call = |method: &str, uri: &str, key: Option<&str>| {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(key) = key {
+            builder = builder.header("x-api-key", key);
+        }
+        let body = if method == "POST" { "[]" } else { "" };
+        let request = builder.body(Body::from(body)).expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response").status() }
+    };
+
+    assert_eq!(
+        call("GET", "/api/status", None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call("GET", "/api/status", Some("nope")).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call("GET", "/api/status", Some("view-key")).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        call("POST", "/api/readiness", Some("view-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call("POST", "/api/readiness", Some("ops-key")).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        call("POST", "/api/schedules", Some("view-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    // Searching is a read, but having it queue syncs is not.
+    let auto_sync = "/api/search/hybrid_all?q=x&max_age=60&auto_sync=true";
+    assert_eq!(
+        call("GET", auto_sync, Some("view-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_ne!(
+        call("GET", auto_sync, Some("ops-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_ne!(
+        call("POST", "/api/graph/nodes", Some("view-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call("GET", "/api/admin/usage", Some("ops-key")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call("POST", "/api/privacy/erase?login=octocat", Some("ops-key")).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let bearer = Request::builder()
+        .uri("/api/fetchers")
+        .header("authorization", "Bearer view-key")
+        .body(Body::empty())?;
+    assert_eq!(app.clone().oneshot(bearer).await?.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cors_allows_configured_origins_only() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let origins = CorsOrigins::parse(&["https://app.example.com"]).map_err(anyhow::Error::msg)?;
+    let app = build_router(
+        AppState::new(storage)
+            .with_api_key("view-key", Role::Viewer)
+            .with_cors_origins(origins),
+    );
+
+    // Preflights carry no API key and must not be rejected by auth.
+    let preflight = Request::builder()
+        .method("OPTIONS")
+        .uri("/api/status")
+        .header("origin", "https://app.example.com")
+        .header("access-control-request-method", "GET")
+        .header("access-control-request-headers", "x-api-key")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(preflight).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    // Identity match decisions are PUT requests.
+    let methods = response.headers()["access-control-allow-methods"].to_str()?;
+    assert!(methods.contains("PUT"), "{methods}");
+
+    let from = |origin: &str| {
+        Request::builder()
+            .uri("/api/status")
+            .header("origin", origin)
+            .header("x-api-key", "view-key")
+            .body(Body::empty())
+            .expect("request")
+    };
+    let allowed = app.clone().oneshot(from("https://app.example.com")).await?;
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(
+        allowed.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    let foreign = app
+        .clone()
+        .oneshot(from("https://evil.example.com"))
+        .await?;
+    assert!(foreign
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+
+    assert!(CorsOrigins::parse(&["app.example.com"]).is_err());
+    assert_eq!(CorsOrigins::parse(&["*"]), Ok(CorsOrigins::Any));
+    Ok(())
+}
+
+#[tokio::test]
+async fn rate_limit_throttles_searches_per_api_key() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let limit = RateLimit {
+        per_minute: 1,
+        burst: 2,
+    };
+    let app = build_router(
+        AppState::new(Arc::clone(&storage))
+            .with_api_key("noisy", Role::Viewer)
+            .with_api_key("quiet", Role::Viewer)
+            .with_rate_limit(limit),
+    );
+
+    let call = |app: &axum::Router, uri: &str, key: &str| {
+        let request = Request::builder()
+            .uri(uri)
+            .header("x-api-key", key)
+            .body(Body::empty())
+            .expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    for _ in 0..2 {
+        let response = call(&app, "/api/graph/search?q=fagent", "noisy").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let throttled = call(&app, "/api/graph/search?q=fagent", "noisy").await;
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = throttled.headers()["retry-after"].to_str()?.parse()?;
+    assert!((1..=60).contains(&retry_after));
+
+    // Other callers and unlimited routes are unaffected.
+    let other = call(&app, "/api/graph/search?q=fagent", "quiet").await;
+    assert_eq!(other.status(), StatusCode::OK);
+    let status = call(&app, "/api/status", "noisy").await;
+    assert_eq!(status.status(), StatusCode::OK);
+
+    // Keys nobody granted do not get buckets of their own.
+    let open = build_router(AppState::new(storage).with_rate_limit(limit));
+    for key in ["first", "second"] {
+        let response = call(&open, "/api/graph/search?q=fagent", key).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let made_up = call(&open, "/api/graph/search?q=fagent", "third").await;
+    assert_eq!(made_up.status(), StatusCode::TOO_MANY_REQUESTS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn health_probes_check_each_component() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    // Probes stay reachable without credentials once API keys are configured.
+    let app = build_router(AppState::new(storage).with_api_key("ops-key", Role::Operator));
+
+    let health = get_json(&app, "/healthz").await?;
+    assert_eq!(health["ok"], json!(true));
+    let components: Vec<&str> = health["components"]
+        .as_array()
+        .expect("components")
+        .iter()
+        .filter_map(|component| component["component"].as_str())
+        .collect();
+    assert_eq!(components, ["catalog", "engine"]);
+
+    let ready = get_json(&app, "/readyz").await?;
+    assert_eq!(ready["components"][2]["component"], json!("lake"));
+    assert_eq!(ready["ok"], json!(true));
+
+    std::fs::remove_dir_all(dir.path().join("lake"))?;
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/readyz").body(Body::empty())?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let ready: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(ready["components"][2]["ok"], json!(false));
+    assert!(ready["components"][2]["error"].is_string());
+    get_json(&app, "/healthz").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn openapi_document_describes_the_routes() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    // Served without credentials, like the probes.
+    let app = build_router(AppState::new(storage).with_api_key("ops-key", Role::Operator));
+
+    let doc = get_json(&app, "/api/openapi.json").await?;
+    assert!(doc["openapi"].as_str().is_some_and(|v| v.starts_with("3.")));
+    for path in [
+        "/api/v1/sync",
+        "/api/v1/graph/subgraph",
+        "/api/v1/sync/jobs/{job_id}",
+        "/healthz",
+    ] {
+        assert!(doc["paths"][path].is_object(), "missing {path}");
+    }
+    assert!(doc["paths"]["/api/sync"].is_null());
+    let sync = &doc["paths"]["/api/v1/sync"]["post"];
+    assert!(sync["responses"]["202"].is_object());
+    let schemas = &doc["components"]["schemas"];
+    assert!(schemas["SyncRequest"]["properties"]["fetcher"].is_object());
+    assert!(schemas["GraphSubgraphResponse"]["properties"]["nodes"].is_object());
+    assert!(doc["components"]["securitySchemes"]["api_key"].is_object());
+
+    let ui = app
+        .clone()
+        .oneshot(Request::builder().uri("/api/docs/").body(Body::empty())?)
+        .await?;
+    assert_eq!(ui.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn versioned_prefix_serves_routes_and_legacy_paths_are_deprecated() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let app = build_router(AppState::new(storage.clone()).with_api_key("view-key", Role::Viewer));
+    let call = |app: &axum::Router, uri: &str| {
+        let request = Request::builder()
+            .uri(uri)
+            .header("x-api-key", "view-key")
+            .body(Body::empty())
+            .expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    let current = call(&app, "/api/v1/graph/search?q=fagent&limit=5").await;
+    assert_eq!(current.status(), StatusCode::OK);
+    assert!(current.headers().get("deprecation").is_none());
+
+    let legacy = call(&app, "/api/graph/search?q=fagent&limit=5").await;
+    assert_eq!(legacy.status(), StatusCode::OK);
+    assert!(legacy.headers()["deprecation"].to_str()?.starts_with('@'));
+    assert_eq!(
+        legacy.headers()["link"],
+        "</api/v1/graph/search>; rel=\"successor-version\""
+    );
+    assert!(legacy.headers().get("sunset").is_none());
+
+    // Auth applies the same way under both prefixes.
+    let anonymous = Request::builder()
+        .uri("/api/v1/status")
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(anonymous).await?.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let retired = build_router(
+        AppState::new(storage)
+            .with_api_key("view-key", Role::Viewer)
+            .with_legacy_api_sunset(chrono::Utc::now() - chrono::Duration::days(1)),
+    );
+    assert_eq!(
+        call(&retired, "/api/status").await.status(),
+        StatusCode::GONE
+    );
+    assert_eq!(
+        call(&retired, "/api/v1/status").await.status(),
+        StatusCode::OK
+    );
+    let doc = call(&retired, "/api/openapi.json").await;
+    assert_eq!(doc.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn usage_endpoint_aggregates_calls_per_endpoint() -> anyhow::Result<()> {
+    let (app, _dir) = test_app().await?;
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/status")
+                    .header("x-api-key", "operator-key")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/usage?bucket=week")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    let value: Value = serde_json::from_slice(&body)?;
+    assert_eq!(value.get("bucket").and_then(Value::as_str), Some("week"));
+    let rows = value
+        .get("rows")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let status_row = rows
+        .iter()
+        .find(|row| row.get("endpoint").and_then(Value::as_str) == Some("GET /api/status"))
+        .expect("status endpoint usage recorded");
+    assert_eq!(status_row.get("count").and_then(Value::as_i64), Some(2));
+    let key = status_row
+        .get("api_key")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    assert!(key.starts_with("oper") && !key.contains("operator-key"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn subgraph_export_writes_graph_formats() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let project_url = "https://example.com/export";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", "v1.0.0".to_string())],
+    ))
+    .to_string();
+    let edge_id =
+        utils::id::stable_edge_id_u128(HasVersion::ENTITY_TYPE, &project_uuid, &version_uuid);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![Project {
+        name: Some("export <& \"quoted\">".to_string()),
+        ..project(project_url)
+    }]);
+    graph.add_entities(vec![Version {
+        sha: Some("v1.0.0".to_string()),
+        tag: Some("v1.0.0".to_string()),
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    graph.add_entities(vec![HasVersion {
+        id: Some(Uuid::from_u128(edge_id).to_string()),
+        from_node_id: Some(project_uuid.clone()),
+        to_node_id: Some(version_uuid.clone()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let export = |format: &str| {
+        let request = Request::builder()
+            .uri(format!(
+                "/api/graph/export?start_id={project_uuid}&depth=1&format={format}"
+            ))
+            .body(Body::empty())
+            .expect("request");
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.expect("response");
+            assert_eq!(response.status(), StatusCode::OK);
+            let content_type = response.headers()["content-type"]
+                .to_str()
+                .expect("content type")
+                .to_string();
+            let body = to_bytes(response.into_body(), BODY_LIMIT)
+                .await
+                .expect("body");
+            (
+                content_type,
+                String::from_utf8(body.to_vec()).expect("utf-8"),
+            )
+        }
+    };
+
+    let (content_type, graphml) = export("graphml").await;
+    assert_eq!(content_type, "application/graphml+xml");
+    assert!(graphml.contains(&format!("<node id=\"{version_uuid}\">")));
+    assert!(graphml.contains(&format!(
+        "source=\"{project_uuid}\" target=\"{version_uuid}\""
+    )));
+    assert!(graphml.contains("export &lt;&amp; &quot;quoted&quot;&gt;"));
+
+    let (_, dot) = export("dot").await;
+    assert!(dot.starts_with("digraph subgraph {"));
+    assert!(dot.contains(&format!("\"{project_uuid}\" -> \"{version_uuid}\"")));
+    assert!(dot.contains("export <& \\\"quoted\\\">"));
+
+    let (_, gexf) = export("gexf").await;
+    assert!(gexf.contains("<gexf xmlns=\"http://gexf.net/1.3\""));
+    assert_eq!(gexf.matches("<node id=").count(), 2);
+    assert_eq!(gexf.matches("<edge id=").count(), 1);
+
+    let request = Request::builder()
+        .uri(format!(
+            "/api/graph/export?start_id={project_uuid}&format=svg"
+        ))
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .uri(format!(
+            "/api/graph/subgraph?start_id={project_uuid}&depth=1&stream=ndjson"
+        ))
+        .body(Body::empty())?;
+    let lines = ndjson_lines(&app, request).await?;
+    assert_eq!(lines[0]["type"], json!("center"));
+    assert_eq!(lines[0]["node"]["id"], json!(project_uuid));
+    let count = |kind: &str| lines.iter().filter(|line| line["type"] == kind).count();
+    assert_eq!(count("node"), 2);
+    assert_eq!(count("edge"), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn graph_layout_positions_every_subgraph_node() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let project_url = "https://example.com/layout";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project(project_url)]);
+    let tags = ["v1", "v2", "v3"];
+    graph.add_entities(
+        tags.iter()
+            .map(|tag| Version {
+                sha: Some(tag.to_string()),
+                tag: Some(tag.to_string()),
+                is_head: None,
+                created_at: None,
+            })
+            .collect(),
+    );
+    graph.add_entities(
+        tags.iter()
+            .map(|tag| {
+                let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+                    Version::ENTITY_TYPE,
+                    &[("sha", tag.to_string())],
+                ))
+                .to_string();
+                let edge_id = utils::id::stable_edge_id_u128(
+                    HasVersion::ENTITY_TYPE,
+                    &project_uuid,
+                    &version_uuid,
+                );
+                HasVersion {
+                    id: Some(Uuid::from_u128(edge_id).to_string()),
+                    from_node_id: Some(project_uuid.clone()),
+                    to_node_id: Some(version_uuid),
+                    from_node_type: Some("project".to_string()),
+                    to_node_type: Some("version".to_string()),
+                    created_at: None,
+                    updated_at: None,
+                }
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let uri = format!("/api/graph/layout?start_id={project_uuid}&algorithm=hierarchical");
+    let layout = get_json(&app, &uri).await?;
+    assert_eq!(layout["algorithm"], json!("hierarchical"));
+    assert_eq!(layout["edges"].as_array().map(Vec::len), Some(3));
+    let nodes = layout["nodes"].as_array().expect("nodes");
+    assert_eq!(nodes.len(), 4);
+    let mut xs = Vec::new();
+    for node in nodes {
+        if node["id"] == json!(project_uuid) {
+            assert_eq!(
+                (node["x"].as_f64(), node["y"].as_f64()),
+                (Some(0.0), Some(0.0))
+            );
+        } else {
+            assert_eq!(node["entity_type"], json!(Version::ENTITY_TYPE));
+            assert!(node["y"].as_f64().expect("y") > 0.0);
+            xs.push(node["x"].as_f64().expect("x"));
+        }
+    }
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+    assert_eq!(xs.len(), 3, "versions should not overlap: {xs:?}");
+
+    let uri = format!("/api/graph/layout?start_id={project_uuid}&iterations=50");
+    let first = get_json(&app, &uri).await?;
+    assert_eq!(first["algorithm"], json!("force"));
+    assert!(first["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .all(|node| node["x"].as_f64().is_some_and(f64::is_finite)));
+    assert_eq!(get_json(&app, &uri).await?, first);
+    Ok(())
+}
+
+/// Sends `request` and parses its NDJSON body, one value per line.
+async fn ndjson_lines(app: &axum::Router, request: Request<Body>) -> anyhow::Result<Vec<Value>> {
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    body.split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| Ok(serde_json::from_slice(line)?))
+        .collect()
+}
+
+#[tokio::test]
+async fn bulk_node_lookup_returns_found_and_missing_ids() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/first"),
+        project("https://example.com/second"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let id_of = |url: &str| {
+        Uuid::from_u128(utils::id::stable_node_id_u128(
+            Project::ENTITY_TYPE,
+            &[("url", url.to_string())],
+        ))
+        .to_string()
+    };
+    let first = id_of("https://example.com/first");
+    let second = id_of("https://example.com/second");
+    let unknown = Uuid::new_v4().to_string();
+
+    let lookup = |ids: Value| {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/graph/nodes")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "ids": ids }).to_string()))
+            .expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    let response = lookup(json!([second, unknown, first])).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    let ids: Vec<&str> = body["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .filter_map(|node| node["id"].as_str())
+        .collect();
+    assert_eq!(ids, vec![second.as_str(), first.as_str()]);
+    assert!(body["nodes"][0]["entity_type"].is_string());
+    assert_eq!(body["missing"], json!([unknown]));
+
+    let too_many: Vec<String> = (0..501).map(|idx| idx.to_string()).collect();
+    let response = lookup(json!(too_many)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
 #[tokio::test]
 async fn shortest_path_endpoint_reports_paths() -> anyhow::Result<()> {
     let dir = tempdir()?;
@@ -184,8 +1064,51 @@ async fn shortest_path_endpoint_reports_paths() -> anyhow::Result<()> {
         2
     );
 
-    let router = build_router(AppState::new(storage));
-    let filtered_request = Request::builder()
+    let router = build_router(AppState::new(storage.clone()));
+    let neighbors = get_json(&router, &format!("/api/graph/neighbors?id={version_uuid}")).await?;
+    let mut seen: Vec<(String, String)> = neighbors["neighbors"]
+        .as_array()
+        .expect("neighbors array")
+        .iter()
+        .map(|neighbor| {
+            (
+                neighbor["orientation"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                neighbor["node_id"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec![
+            ("incoming".to_string(), project_uuid.clone()),
+            ("outgoing".to_string(), commit_uuid.clone()),
+        ]
+    );
+    assert!(neighbors["neighbors"][0]["edge"]["label"].is_string());
+
+    let outgoing = get_json(
+        &router,
+        &format!("/api/graph/neighbors?id={version_uuid}&direction=out"),
+    )
+    .await?;
+    assert_eq!(outgoing["direction"], json!("outgoing"));
+    assert_eq!(outgoing["neighbors"].as_array().map(Vec::len), Some(1));
+    assert_eq!(outgoing["neighbors"][0]["node"]["id"], json!(&commit_uuid));
+
+    let bad_direction = Request::builder()
+        .uri(format!(
+            "/api/graph/neighbors?id={version_uuid}&direction=sideways"
+        ))
+        .body(Body::empty())?;
+    let response = router.clone().oneshot(bad_direction).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let router = build_router(AppState::new(storage));
+    let filtered_request = Request::builder()
         .uri(format!(
             "/api/graph/shortest_path?from_id={}&to_id={}&edge_label=edge_containscontent",
             project_uuid, commit_uuid
@@ -205,3 +1128,2954 @@ async fn shortest_path_endpoint_reports_paths() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn privacy_endpoints_export_and_erase_developer_data() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let developer_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Developer::ENTITY_TYPE,
+        &[
+            ("platform", "github".to_string()),
+            ("account_id", "42".to_string()),
+            ("login", "octocat".to_string()),
+        ],
+    ))
+    .to_string();
+    let issue_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Issue::ENTITY_TYPE,
+        &[
+            ("project_url", "https://github.com/acme/widgets".to_string()),
+            ("number", "7".to_string()),
+        ],
+    ))
+    .to_string();
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![serde_json::from_value::<Developer>(json!({
+        "platform": "github",
+        "account_id": "42",
+        "login": "octocat",
+        "name": "The Octocat",
+        "email": "octocat@example.com",
+    }))?]);
+    graph.add_entities(vec![serde_json::from_value::<Issue>(json!({
+        "project_url": "https://github.com/acme/widgets",
+        "number": 7,
+        "title": "Widgets wobble",
+        "author_login": "octocat",
+        "author_id": "42",
+    }))?]);
+    graph.add_entities(vec![serde_json::from_value::<Issue>(json!({
+        "project_url": "https://github.com/acme/widgets",
+        "number": 8,
+        "title": "Widgets wobble less",
+        "author_login": "hubot",
+        "assignees": "[\"hubot\",\"octocat\"]",
+    }))?]);
+    graph.add_entities(vec![serde_json::from_value::<GitIdentity>(json!({
+        "email": "octocat@example.com",
+        "name": "The Octocat",
+    }))?]);
+    graph.add_entities(vec![serde_json::from_value::<OpenedIssue>(json!({
+        "id": Uuid::from_u128(utils::id::stable_edge_id_u128(
+            OpenedIssue::ENTITY_TYPE,
+            &developer_id,
+            &issue_id,
+        ))
+        .to_string(),
+        "from_node_id": developer_id,
+        "to_node_id": issue_id,
+        "from_node_type": "developer",
+        "to_node_type": "issue",
+    }))?]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let app = build_router(AppState::new(storage));
+    let export = get_json(&app, "/api/privacy/export?login=octocat").await?;
+    assert_eq!(export["node_ids"].as_array().map(Vec::len), Some(2));
+    let tables: Vec<&str> = export["tables"]
+        .as_array()
+        .expect("tables")
+        .iter()
+        .filter_map(|table| table["table"].as_str())
+        .collect();
+    assert!(tables.contains(&"silver/entities/developer"));
+    assert!(tables.contains(&"silver/entities/issue"));
+    assert!(tables.contains(&"silver/entities/gitidentity"));
+    assert!(tables
+        .iter()
+        .any(|table| table.starts_with("silver/edges/")));
+    assert_eq!(export["engine_nodes"].as_array().map(Vec::len), Some(2));
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/privacy/erase?login=octocat")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let erasure: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(
+        erasure["rows_deleted"]["silver/entities/developer"],
+        json!(1)
+    );
+    assert_eq!(
+        erasure["rows_deleted"]["silver/entities/gitidentity"],
+        json!(1)
+    );
+    // The authored issue loses its author, the other one its assignee.
+    assert_eq!(erasure["rows_updated"]["silver/entities/issue"], json!(2));
+    assert_eq!(erasure["engine_nodes_removed"], json!(2));
+    assert_eq!(erasure["engine_nodes_updated"], json!(2));
+
+    let after = get_json(&app, "/api/privacy/export?login=octocat").await?;
+    assert_eq!(after["node_ids"], json!([]));
+    assert_eq!(after["tables"], json!([]));
+
+    let request = Request::builder()
+        .uri("/api/privacy/export")
+        .body(Body::empty())?;
+    assert_eq!(
+        app.oneshot(request).await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+    Ok(())
+}
+
+fn project(url: &str) -> Project {
+    Project {
+        url: Some(url.to_string()),
+        name: None,
+        description: None,
+        language: None,
+        stars: None,
+        forks: None,
+    }
+}
+
+#[tokio::test]
+async fn graph_payloads_are_compressed_and_revalidated() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/first"),
+        project("https://example.com/second"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage.clone()));
+
+    let visual = |if_none_match: Option<&str>| {
+        let mut builder = Request::builder()
+            .uri("/api/graph/visual")
+            .header("accept-encoding", "gzip");
+        if let Some(tag) = if_none_match {
+            builder = builder.header("if-none-match", tag);
+        }
+        let request = builder.body(Body::empty()).expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    let first = visual(None).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["content-encoding"], "gzip");
+    let etag = first.headers()["etag"].to_str()?.to_string();
+    assert!(etag.starts_with("W/\""));
+
+    let cached = visual(Some(&etag)).await;
+    assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(cached.headers()["etag"].to_str()?, etag);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/third")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let changed = visual(Some(&etag)).await;
+    assert_eq!(changed.status(), StatusCode::OK);
+    assert_ne!(changed.headers()["etag"].to_str()?, etag);
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_responses_revalidate_against_lake_writes() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/first")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage.clone()));
+
+    let tables = |if_none_match: Option<&str>| {
+        let mut builder = Request::builder()
+            .uri("/api/v1/tables")
+            .header("accept-encoding", "br");
+        if let Some(tag) = if_none_match {
+            builder = builder.header("if-none-match", tag);
+        }
+        let request = builder.body(Body::empty()).expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    let first = tables(None).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["content-encoding"], "br");
+    assert_eq!(first.headers()["cache-control"], "no-cache");
+    let etag = first.headers()["etag"].to_str()?.to_string();
+    assert_eq!(tables(Some(&etag)).await.status(), StatusCode::NOT_MODIFIED);
+
+    // A lake-only write leaves the engine untouched but still changes the tag.
+    storage
+        .lake
+        .write_edges(
+            "HAS_VERSION",
+            vec![HasVersion {
+                id: Some(Uuid::from_u128(7).to_string()),
+                from_node_id: Some(Uuid::from_u128(1).to_string()),
+                to_node_id: Some(Uuid::from_u128(2).to_string()),
+                from_node_type: Some("project".to_string()),
+                to_node_type: Some("version".to_string()),
+                created_at: None,
+                updated_at: None,
+            }],
+        )
+        .await?;
+    let changed = tables(Some(&etag)).await;
+    assert_eq!(changed.status(), StatusCode::OK);
+    assert_ne!(changed.headers()["etag"].to_str()?, etag);
+
+    let query = Request::builder()
+        .method("POST")
+        .uri("/api/v1/query")
+        .header("content-type", "application/json")
+        .header("if-none-match", "*")
+        .body(Body::from(
+            json!({
+                "table": "silver/entities/project",
+                "sql": "SELECT count(*) AS n FROM {{table}}"
+            })
+            .to_string(),
+        ))?;
+    let response = app.clone().oneshot(query).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("etag").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn graph_stats_count_nodes_and_table_rows() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/first"),
+        project("https://example.com/second"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let stats = get_json(&app, "/api/v1/graph/stats").await?;
+    assert_eq!(stats["nodes_by_label"][Project::ENTITY_TYPE], json!(2));
+    assert_eq!(stats["edges_by_label"], json!({}));
+    assert_eq!(stats["edge_rows"], json!({}));
+    assert_eq!(stats["table_rows"][Project::table_name()], json!(2));
+    assert!(stats["vector_count"].is_u64());
+
+    let status = get_json(&app, "/api/v1/status").await?;
+    // The entity table and its id index.
+    assert_eq!(status["entity_counts"], json!({"node": 2}));
+    assert_eq!(status["entity_count"], json!(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn table_preview_returns_first_rows() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/first"),
+        project("https://example.com/second"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let preview = get_json(
+        &app,
+        "/api/v1/tables/silver/entities/project/preview?limit=1",
+    )
+    .await?;
+    assert_eq!(preview["table"], json!("silver/entities/project"));
+    assert_eq!(preview["row_count"], json!(1));
+    let url = preview["rows"][0]["url"].as_str().expect("url column");
+    assert!(url.starts_with("https://example.com/"));
+
+    for (uri, status) in [
+        (
+            "/api/v1/tables/silver/entities/missing/preview",
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            "/api/v1/tables/silver/../project/preview",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/api/v1/tables/silver/entities/project",
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let request = Request::builder().uri(uri).body(Body::empty())?;
+        assert_eq!(
+            app.clone().oneshot(request).await?.status(),
+            status,
+            "{uri}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn graph_diff_reports_what_a_sync_run_changed() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let node_id = |url: &str| {
+        Uuid::from_u128(utils::id::stable_node_id_u128(
+            Project::ENTITY_TYPE,
+            &[("url", url.to_string())],
+        ))
+        .to_string()
+    };
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/renamed"),
+        project("https://example.com/untouched"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        Project {
+            name: Some("renamed".to_string()),
+            ..project("https://example.com/renamed")
+        },
+        project("https://example.com/added"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let runs = get_json(&app, "/api/v1/graph/runs").await?;
+    let runs = runs.as_array().expect("run list");
+    assert_eq!(runs.len(), 2);
+    let to_run = runs[0]["run_id"].as_i64().expect("run id");
+    let from_run = runs[1]["run_id"].as_i64().expect("run id");
+    assert!(runs[0]["table_versions"][Project::table_name()].is_i64());
+
+    let diff = get_json(
+        &app,
+        &format!("/api/v1/graph/diff?from_run={from_run}&to_run={to_run}"),
+    )
+    .await?;
+    let change = |url: &str| json!({"id": node_id(url), "label": Project::ENTITY_TYPE});
+    assert_eq!(
+        diff["nodes"]["added"],
+        json!([change("https://example.com/added")])
+    );
+    assert_eq!(
+        diff["nodes"]["changed"],
+        json!([change("https://example.com/renamed")])
+    );
+    assert_eq!(diff["nodes"]["removed"], json!([]));
+    assert_eq!(diff["edges"]["added"], json!([]));
+    assert_eq!(diff["truncated"], json!(false));
+
+    let reversed = get_json(
+        &app,
+        &format!("/api/v1/graph/diff?from_run={to_run}&to_run={from_run}"),
+    )
+    .await?;
+    assert_eq!(
+        reversed["nodes"]["removed"],
+        json!([change("https://example.com/added")])
+    );
+
+    let request = Request::builder()
+        .uri(format!(
+            "/api/v1/graph/diff?from_run={from_run}&to_run={}",
+            to_run + 1
+        ))
+        .body(Body::empty())?;
+    assert_eq!(app.oneshot(request).await?.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn deleting_a_node_removes_its_rows_and_edges() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let project_url = "https://example.com/other";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", "v1.0.0".to_string())],
+    ))
+    .to_string();
+    let edge_id =
+        utils::id::stable_edge_id_u128(HasVersion::ENTITY_TYPE, &project_uuid, &version_uuid);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project(project_url),
+        project("https://example.com/kept"),
+    ]);
+    graph.add_entities(vec![Version {
+        sha: Some("v1.0.0".to_string()),
+        tag: Some("v1.0.0".to_string()),
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    graph.add_entities(vec![HasVersion {
+        id: Some(Uuid::from_u128(edge_id).to_string()),
+        from_node_id: Some(project_uuid.clone()),
+        to_node_id: Some(version_uuid.clone()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let graph_version = storage.graph_version()?;
+    let app = build_router(AppState::new(Arc::clone(&storage)));
+
+    let delete = |id: &str| {
+        Request::builder()
+            .method("DELETE")
+            .uri(format!("/api/v1/graph/node?id={id}"))
+            .body(Body::empty())
+            .expect("request")
+    };
+    let response = app.clone().oneshot(delete(&project_uuid)).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    let deletion: Value = serde_json::from_slice(&body)?;
+    assert_eq!(deletion["entity_type"], json!(Project::ENTITY_TYPE));
+    assert_eq!(deletion["engine_node_removed"], json!(true));
+    assert_eq!(
+        deletion["rows_deleted"],
+        json!({
+            (Project::table_name()): 1,
+            "silver/index/project": 1,
+            (HasVersion::table_name()): 1,
+        })
+    );
+    assert_eq!(storage.graph_version()?, graph_version + 1);
+
+    let request = Request::builder()
+        .uri(format!("/api/v1/graph/node?id={project_uuid}"))
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(request).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+    get_json(&app, &format!("/api/v1/graph/node?id={version_uuid}")).await?;
+    let preview = get_json(&app, "/api/v1/tables/silver/entities/project/preview").await?;
+    assert_eq!(preview["row_count"], json!(1));
+    assert_eq!(preview["rows"][0]["url"], json!("https://example.com/kept"));
+
+    let response = app.clone().oneshot(delete(&project_uuid)).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app.oneshot(delete("not-a-uuid")).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn expensive_reads_are_cached_until_the_data_changes() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/first")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage.clone()));
+
+    let first = get_json(&app, "/api/v1/graph/stats").await?;
+    let second = get_json(&app, "/api/v1/graph/stats").await?;
+    assert_eq!(first, second);
+    let metrics = get_json(&app, "/api/v1/admin/cache").await?;
+    assert_eq!(metrics["hits"], json!(1));
+    assert_eq!(metrics["misses"], json!(1));
+    assert_eq!(metrics["entries"], json!(1));
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/second")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let refreshed = get_json(&app, "/api/v1/graph/stats").await?;
+    assert_eq!(refreshed["nodes_by_label"][Project::ENTITY_TYPE], json!(2));
+    let metrics = get_json(&app, "/api/v1/admin/cache").await?;
+    assert_eq!(metrics["misses"], json!(2));
+    assert_eq!(metrics["invalidations"], json!(1));
+
+    let uncached = build_router(AppState::new(storage).with_response_cache(0));
+    get_json(&uncached, "/api/v1/graph/stats").await?;
+    let metrics = get_json(&uncached, "/api/v1/admin/cache").await?;
+    assert_eq!(metrics["entries"], json!(0));
+    assert_eq!(metrics["misses"], json!(0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn edge_type_catalog_lists_schema_labels() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let app = build_router(AppState::new(storage));
+
+    let edge_types = get_json(&app, "/api/v1/graph/edge_types").await?;
+    let has_version = edge_types
+        .as_array()
+        .expect("edge type list")
+        .iter()
+        .find(|entry| entry["label"] == json!(HasVersion::ENTITY_TYPE))
+        .expect("schema edge listed");
+    assert_eq!(has_version["from_types"], json!([Project::ENTITY_TYPE]));
+    assert_eq!(has_version["to_types"], json!([Version::ENTITY_TYPE]));
+    assert_eq!(has_version["in_schema"], json!(true));
+    assert!(has_version["table_path"].is_null());
+    Ok(())
+}
+
+#[tokio::test]
+async fn graph_routes_skip_work_for_current_graph_version() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/first")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage.clone()));
+
+    let overview = |if_graph_version: Option<&str>| {
+        let mut builder = Request::builder().uri("/api/v1/graph/overview");
+        if let Some(version) = if_graph_version {
+            builder = builder.header("if-graph-version", version);
+        }
+        let request = builder.body(Body::empty()).expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    let version = storage.graph_version()?.to_string();
+    assert_ne!(version, "0");
+    let first = overview(None).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()["graph-version"], version.as_str());
+    let unchanged = overview(Some(version.as_str())).await;
+    assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(unchanged.headers()["graph-version"], version.as_str());
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/second")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let changed = overview(Some(version.as_str())).await;
+    assert_eq!(changed.status(), StatusCode::OK);
+    assert_eq!(
+        changed.headers()["graph-version"],
+        storage.graph_version()?.to_string().as_str()
+    );
+    assert_ne!(changed.headers()["graph-version"], version.as_str());
+
+    let request = Request::builder()
+        .uri("/api/v1/tables")
+        .body(Body::empty())?;
+    let tables = app.clone().oneshot(request).await?;
+    assert!(tables.headers().get("graph-version").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn mounted_snapshots_serve_the_frozen_graph() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/first")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let app = build_router(AppState::new(storage.clone()));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/snapshots")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"name": "before-second", "description": "one project"}).to_string(),
+        ))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert!(created["table_versions"]["silver/entities/project"].is_i64());
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/second")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let mount = Request::builder()
+        .method("POST")
+        .uri("/api/snapshots/before-second/mount")
+        .body(Body::empty())?;
+    assert_eq!(app.clone().oneshot(mount).await?.status(), StatusCode::OK);
+    let listed = get_json(&app, "/api/snapshots").await?;
+    assert_eq!(listed[0]["name"], json!("before-second"));
+    assert_eq!(listed[0]["mounted"], json!(true));
+
+    let count = json!({
+        "table": "silver/entities/project",
+        "sql": "SELECT count(*) AS n FROM {{table}}",
+    });
+    let (status, live) = post_query(&app, count.clone()).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(live["rows"][0]["n"], json!(2));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/mounts/before-second/query")
+        .header("content-type", "application/json")
+        .body(Body::from(count.to_string()))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let frozen: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(frozen["rows"][0]["n"], json!(1));
+
+    let second_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", "https://example.com/second".to_string())],
+    ));
+    get_json(&app, &format!("/api/graph/node?id={second_id}")).await?;
+    let request = Request::builder()
+        .uri(format!(
+            "/api/mounts/before-second/graph/node?id={second_id}"
+        ))
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(request).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // Mounts are read-only: sync routes are not served under the namespace.
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/mounts/before-second/sync")
+        .header("content-type", "application/json")
+        .body(Body::from("[]"))?;
+    assert_eq!(
+        app.clone().oneshot(request).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let duplicate = Request::builder()
+        .method("POST")
+        .uri("/api/snapshots")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"name": "before-second"}).to_string()))?;
+    assert_eq!(
+        app.clone().oneshot(duplicate).await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let unmount = Request::builder()
+        .method("DELETE")
+        .uri("/api/snapshots/before-second/mount")
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(unmount).await?.status(),
+        StatusCode::NO_CONTENT
+    );
+    let request = Request::builder()
+        .uri("/api/mounts/before-second/status")
+        .body(Body::empty())?;
+    assert_eq!(app.oneshot(request).await?.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn snapshot_diff_reports_changed_tables_and_nodes() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/first")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    storage.create_snapshot("before", None).await?;
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/second")]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    storage.create_snapshot("after", None).await?;
+
+    let diff = fstorage::snapshot::diff(&storage.config, "before", "after", 5).await?;
+    assert_eq!(diff.entity_rows_a + 1, diff.entity_rows_b);
+    let table = diff
+        .changed_tables
+        .iter()
+        .find(|table| table.table == "silver/entities/project")
+        .expect("project table changed");
+    assert_eq!((table.rows_a, table.rows_b), (1, 2));
+    assert_eq!(table.added_or_changed, Some(1));
+    assert_eq!(table.removed_or_changed, Some(0));
+    assert_eq!(table.sample.len(), 1);
+    assert_eq!(table.sample[0]["url"], json!("https://example.com/second"));
+
+    let same = fstorage::snapshot::diff(&storage.config, "after", "after", 5).await?;
+    assert!(same.changed_tables.is_empty());
+    assert_eq!(
+        same.unchanged_tables,
+        diff.changed_tables.len() + diff.unchanged_tables
+    );
+    Ok(())
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> anyhow::Result<Value> {
+    let request = Request::builder().uri(uri).body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK, "GET {uri}");
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Follows `next_cursor` until it runs out, returning the ids of every page.
+async fn collect_pages(app: &axum::Router, base: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut pages = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let uri = match &cursor {
+            Some(cursor) => format!("{base}&cursor={cursor}"),
+            None => base.to_string(),
+        };
+        let page = get_json(app, &uri).await?;
+        let ids = page["candidates"]
+            .as_array()
+            .expect("candidates array")
+            .iter()
+            .filter_map(|candidate| candidate["id"].as_str().map(str::to_string))
+            .collect();
+        pages.push(ids);
+        match page["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return Ok(pages),
+        }
+        assert!(pages.len() < 10, "pagination did not terminate");
+    }
+}
+
+#[tokio::test]
+async fn graph_overview_and_search_page_with_cursors() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = StorageConfig::new(dir.path());
+    let storage = Arc::new(FStorage::new(config).await?);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(
+        (0..5)
+            .map(|index| Project {
+                url: Some(format!("https://example.com/paged-{index}")),
+                name: Some(format!("paged-{index}")),
+                description: None,
+                language: Some("Rust".to_string()),
+                stars: Some(index),
+                forks: None,
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    for base in [
+        "/api/graph/overview?limit=2",
+        "/api/graph/search?q=paged&entity_type=project&limit=2",
+    ] {
+        let pages = collect_pages(&app, base).await?;
+        let sizes: Vec<usize> = pages.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 2, 1], "{base}");
+        let mut ids: Vec<String> = pages.into_iter().flatten().collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 5, "{base} should not repeat candidates");
+    }
+
+    let request = Request::builder()
+        .uri("/api/graph/overview?cursor=not-a-cursor")
+        .body(Body::empty())?;
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+async fn post_query(app: &axum::Router, body: Value) -> anyhow::Result<(StatusCode, Value)> {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/query")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    let response = app.clone().oneshot(request).await?;
+    let status = response.status();
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    Ok((status, serde_json::from_slice(&body)?))
+}
+
+#[tokio::test]
+async fn query_endpoint_runs_capped_sql() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = StorageConfig::new(dir.path());
+    let storage = Arc::new(FStorage::new(config).await?);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(
+        ["alpha", "beta", "gamma"]
+            .into_iter()
+            .map(|name| Project {
+                url: Some(format!("https://example.com/{name}")),
+                name: Some(name.to_string()),
+                description: None,
+                language: None,
+                stars: None,
+                forks: None,
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let (status, value) = post_query(
+        &app,
+        json!({
+            "table": Project::table_name(),
+            "sql": "SELECT name FROM {{table}} ORDER BY name",
+            "limit": 2
+        }),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["columns"], json!(["name"]));
+    assert_eq!(value["rows"], json!([{"name": "alpha"}, {"name": "beta"}]));
+    assert_eq!(value["row_count"], json!(2));
+    assert_eq!(value["truncated"], json!(true));
+    assert_eq!(value["engine"], json!("datafusion"));
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/query")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "table": Project::table_name(),
+                "sql": "SELECT name FROM {{table}} ORDER BY name",
+                "stream": "ndjson"
+            })
+            .to_string(),
+        ))?;
+    let lines = ndjson_lines(&app, request).await?;
+    assert_eq!(lines[0], json!({"type": "columns", "columns": ["name"]}));
+    assert_eq!(
+        lines[1..],
+        [
+            json!({"type": "row", "row": {"name": "alpha"}}),
+            json!({"type": "row", "row": {"name": "beta"}}),
+            json!({"type": "row", "row": {"name": "gamma"}}),
+        ]
+    );
+
+    let (status, _) = post_query(
+        &app,
+        json!({"table": Project::table_name(), "sql": "DROP TABLE {{table}}"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = post_query(
+        &app,
+        json!({"table": "silver/entities/missing", "sql": "SELECT 1"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "ENTITY_NOT_FOUND");
+
+    Ok(())
+}
+
+#[cfg(feature = "duckdb")]
+#[tokio::test]
+async fn query_endpoint_runs_on_duckdb_when_configured() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let mut config = StorageConfig::new(dir.path());
+    config.sql_engine = fstorage::sql_engine::SqlEngine::DuckDb;
+    let storage = Arc::new(FStorage::new(config).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(
+        ["alpha", "beta", "gamma"]
+            .into_iter()
+            .map(|name| Project {
+                url: Some(format!("https://example.com/{name}")),
+                name: Some(name.to_string()),
+                description: None,
+                language: None,
+                stars: Some(3),
+                forks: None,
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let (status, value) = post_query(
+        &app,
+        json!({
+            "table": Project::table_name(),
+            "sql": "SELECT name, stars * 1.5 AS weighted FROM {{table}} ORDER BY name",
+            "limit": 2
+        }),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK, "{value}");
+    assert_eq!(value["engine"], json!("duckdb"));
+    assert_eq!(
+        value["rows"],
+        json!([{"name": "alpha", "weighted": 4.5}, {"name": "beta", "weighted": 4.5}])
+    );
+    assert_eq!(value["truncated"], json!(true));
+
+    for sql in [
+        "DROP VIEW {{table}}",
+        "SELECT * FROM read_csv('/etc/passwd')",
+        "SELECT 1; SET enable_external_access = true",
+    ] {
+        let (status, _) =
+            post_query(&app, json!({"table": Project::table_name(), "sql": sql})).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{sql}");
+    }
+
+    let (status, _) = post_query(
+        &app,
+        json!({"table": "silver/entities/missing", "sql": "SELECT 1"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+async fn store_with_projects(
+    dir: &std::path::Path,
+    names: &[&str],
+) -> anyhow::Result<Arc<FStorage>> {
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir)).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(
+        names
+            .iter()
+            .map(|name| project(&format!("https://example.com/{name}")))
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    Ok(storage)
+}
+
+#[tokio::test]
+async fn foreign_tables_join_in_queries_and_stay_read_only() -> anyhow::Result<()> {
+    let lakehouse_dir = tempdir()?;
+    store_with_projects(lakehouse_dir.path(), &["beta", "gamma"]).await?;
+    let dir = tempdir()?;
+    let storage = store_with_projects(dir.path(), &["alpha", "beta"]).await?;
+    let app = build_router(AppState::new(storage.clone()));
+
+    let uri = lakehouse_dir
+        .path()
+        .join("lake")
+        .join(Project::table_name());
+    let attach = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/foreign_tables")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+    };
+    let response = app
+        .clone()
+        .oneshot(attach(json!({"name": "upstream", "uri": uri}))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let attached: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(attached["path"], json!("foreign/upstream"));
+    assert_eq!(attached["sql_name"], json!("foreign_upstream"));
+
+    let response = app
+        .clone()
+        .oneshot(attach(json!({"name": "Bad-Name", "uri": uri}))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let missing = dir.path().join("missing");
+    let response = app
+        .clone()
+        .oneshot(attach(json!({"name": "missing", "uri": missing}))?)
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let listed = get_json(&app, "/api/admin/foreign_tables").await?;
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+    assert_eq!(listed[0]["name"], json!("upstream"));
+
+    let (status, value) = post_query(
+        &app,
+        json!({
+            "table": Project::table_name(),
+            "sql": "SELECT p.name FROM {{table}} p \
+                    JOIN foreign_upstream u ON p.url = u.url ORDER BY p.name"
+        }),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["rows"], json!([{"name": "beta"}]));
+
+    let (status, value) = post_query(
+        &app,
+        json!({"table": "foreign/upstream", "sql": "SELECT count(*) AS n FROM {{table}}"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(value["rows"], json!([{"n": 2}]));
+    assert!(storage
+        .lake
+        .get_or_create_table("foreign/upstream")
+        .await
+        .is_err());
+
+    let detach = || {
+        Request::builder()
+            .method("DELETE")
+            .uri("/api/admin/foreign_tables/upstream")
+            .body(Body::empty())
+    };
+    let response = app.clone().oneshot(detach()?).await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app.clone().oneshot(detach()?).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let (status, _) = post_query(
+        &app,
+        json!({"table": "foreign/upstream", "sql": "SELECT 1"}),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_endpoint_queues_jobs_and_reports_outcome() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = StorageConfig::new(dir.path());
+    let storage = Arc::new(FStorage::new(config).await?);
+    storage.register_fetcher(Arc::new(EmptyFetcher));
+    let app = build_router(AppState::new(storage));
+
+    let unknown = Request::builder()
+        .method("POST")
+        .uri("/api/sync")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"fetcher":"missing"}"#))?;
+    let response = app.clone().oneshot(unknown).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(body["code"], "FETCHER_UNKNOWN");
+
+    let denied = Request::builder()
+        .method("POST")
+        .uri("/api/sync")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"fetcher": "empty_mock", "params": {"denied": true}}).to_string(),
+        ))?;
+    let response = app.clone().oneshot(denied).await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(body["code"], "POLICY_DENIED");
+
+    let job_id = post_sync(&app, json!({"fetcher": "empty_mock", "params": {}})).await?;
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert!(job.get("task_id").and_then(Value::as_i64).is_some());
+    assert!(job.pointer("/result/budget").is_some());
+    assert_eq!(
+        job.pointer("/progress/phase").and_then(Value::as_str),
+        Some("engine_ingest")
+    );
+
+    let selective = post_sync(
+        &app,
+        json!({"fetcher": "empty_mock", "params": {}, "entity_types": ["issue"]}),
+    )
+    .await?;
+    let selective = wait_for_job_state(&app, selective, "succeeded").await?;
+    assert_eq!(
+        selective.pointer("/request/params/entity_types"),
+        Some(&json!(["issue"]))
+    );
+
+    let conflicting = Request::builder()
+        .method("POST")
+        .uri("/api/sync")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"fetcher": "empty_mock", "params": [1], "entity_types": ["issue"]}).to_string(),
+        ))?;
+    let response = app.clone().oneshot(conflicting).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/sync/jobs?state=succeeded")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+    let jobs: Value = serde_json::from_slice(&body)?;
+    assert_eq!(
+        jobs.as_array().map(|jobs| jobs
+            .iter()
+            .filter_map(|job| job.get("job_id")?.as_i64())
+            .collect::<Vec<_>>()),
+        Some(vec![selective["job_id"].as_i64().unwrap(), job_id])
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/sync/jobs/9999")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_history_lists_runs_with_their_outcome() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    storage.register_fetcher(Arc::new(EmptyFetcher));
+    let app = build_router(AppState::new(storage));
+
+    let first = post_sync(&app, json!({"fetcher": "empty_mock", "params": {}})).await?;
+    wait_for_job_state(&app, first, "succeeded").await?;
+    let second = post_sync(
+        &app,
+        json!({"fetcher": "empty_mock", "params": {}, "entity_types": ["issue"]}),
+    )
+    .await?;
+    let second = wait_for_job_state(&app, second, "succeeded").await?;
+
+    let history = get_json(&app, "/api/sync/history?fetcher=empty_mock").await?;
+    let entries = history.as_array().expect("history is a list");
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["task_id"], second["task_id"]);
+    assert_ne!(entries[0]["params_hash"], entries[1]["params_hash"]);
+    for entry in entries {
+        assert_eq!(entry["state"], "succeeded");
+        assert_eq!(entry["total_rows"], 0);
+        assert!(entry["finished_at"].as_i64() >= entry["started_at"].as_i64());
+    }
+
+    let id = entries[1]["id"].as_i64().unwrap();
+    let entry = get_json(&app, &format!("/api/sync/history/{id}")).await?;
+    assert_eq!(entry["fetcher"], "empty_mock");
+    let older = get_json(&app, &format!("/api/sync/history?before={id}")).await?;
+    assert_eq!(older, json!([]));
+    let other = get_json(&app, "/api/sync/history?fetcher=gitfetcher").await?;
+    assert_eq!(other, json!([]));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/sync/history/9999")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+/// Stands in for the GitHub fetcher webhook deliveries are routed to.
+struct GitMock;
+
+#[async_trait::async_trait]
+impl Fetcher for GitMock {
+    fn name(&self) -> &'static str {
+        "gitfetcher"
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: "gitfetcher",
+            ..EmptyFetcher.capability()
+        }
+    }
+
+    async fn probe(&self, params: Value) -> fstorage::errors::Result<ProbeReport> {
+        EmptyFetcher.probe(params).await
+    }
+
+    async fn fetch(
+        &self,
+        params: Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        EmptyFetcher.fetch(params, embedding_provider).await
+    }
+}
+
+/// `X-Hub-Signature-256` value for `body`, computed as GitHub does.
+fn github_signature(secret: &str, body: &str) -> String {
+    use hmac::{Hmac, Mac};
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("any key size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[tokio::test]
+async fn github_webhooks_queue_targeted_syncs() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    storage.register_fetcher(Arc::new(GitMock));
+    let secret = "webhook-secret";
+    let app = build_router(AppState::new(Arc::clone(&storage)).with_github_webhook_secret(secret));
+
+    // A file the push below removes, at a version synced before and at the
+    // version the push creates.
+    let project_url = "https://github.com/acme/widgets";
+    let mut graph = GraphData::new();
+    graph.add_entities(Vec::from(["abc000", "def456"].map(|sha| File {
+        version_sha: Some(sha.to_string()),
+        path: Some("src/old.rs".to_string()),
+        language: Some("rust".to_string()),
+    })));
+    storage.synchronizer.process_graph_data(graph).await?;
+    let [old_file, pushed_file] = ["abc000", "def456"].map(|sha| {
+        Uuid::from_u128(utils::id::stable_node_id_u128(
+            File::ENTITY_TYPE,
+            &[
+                ("version_sha", sha.to_string()),
+                ("path", "src/old.rs".to_string()),
+            ],
+        ))
+        .to_string()
+    });
+
+    let deliveries = std::sync::atomic::AtomicUsize::new(0);
+    let deliver = |event: &str, payload: Value, signature: Option<String>| {
+        let body = payload.to_string();
+        let signature = signature.unwrap_or_else(|| github_signature(secret, &body));
+        let delivery = deliveries.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/webhooks/github")
+            .header("content-type", "application/json")
+            .header("x-github-event", event)
+            .header("x-github-delivery", format!("delivery-{delivery}"))
+            .header("x-hub-signature-256", signature)
+            .body(Body::from(body))
+            .expect("request");
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.expect("response");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT)
+                .await
+                .expect("body");
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }
+    };
+    let repository = json!({
+        "full_name": "acme/widgets",
+        "html_url": project_url,
+        "default_branch": "main",
+    });
+
+    let (status, _) = deliver("ping", json!({"zen": "hi"}), Some("sha256=00".to_string())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = deliver("ping", json!({"zen": "hi"}), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ignored"], json!("ping"));
+
+    let (status, body) = deliver(
+        "push",
+        json!({
+            "ref": "refs/heads/feature",
+            "after": "abc123",
+            "repository": repository,
+            "commits": [],
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["ignored"].is_string());
+
+    let (status, body) = deliver(
+        "issues",
+        json!({"action": "opened", "issue": {"number": 7}, "repository": repository}),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let job_id = body["sync"]["job_id"].as_i64().expect("job id");
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert_eq!(job.pointer("/request/fetcher"), Some(&json!("gitfetcher")));
+    assert_eq!(
+        job.pointer("/request/params/repo"),
+        Some(&json!("acme/widgets"))
+    );
+    assert_eq!(
+        job.pointer("/request/target_entities/0/uri"),
+        Some(&json!("acme/widgets#7"))
+    );
+    assert_eq!(
+        job.pointer("/request/params/entity_types"),
+        Some(&json!(["issue"]))
+    );
+
+    let (status, body) = deliver(
+        "push",
+        json!({
+            "ref": "refs/heads/main",
+            "after": "def456",
+            "repository": repository,
+            "commits": [
+                {"added": ["src/new.rs"], "modified": ["src/lib.rs"], "removed": []},
+                {"added": [], "modified": ["src/lib.rs"], "removed": ["src/old.rs"]},
+            ],
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(body["files_removed"], json!(1));
+    assert!(storage
+        .lake
+        .get_nodes_by_ids(std::slice::from_ref(&pushed_file))
+        .await?
+        .is_empty());
+    // The earlier version keeps the file.
+    assert_eq!(
+        storage
+            .lake
+            .get_nodes_by_ids(std::slice::from_ref(&old_file))
+            .await?
+            .len(),
+        1
+    );
+    let job_id = body["sync"]["job_id"].as_i64().expect("job id");
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert_eq!(job.pointer("/request/params/rev"), Some(&json!("def456")));
+    let files: Vec<&str> = job
+        .pointer("/request/target_entities")
+        .and_then(Value::as_array)
+        .expect("targets")
+        .iter()
+        .filter_map(|target| target["uri"].as_str())
+        .collect();
+    assert_eq!(files, vec!["src/lib.rs", "src/new.rs"]);
+
+    // A push listing fewer commits than it has syncs the whole revision.
+    let (status, body) = deliver(
+        "push",
+        json!({
+            "ref": "refs/heads/main",
+            "after": "fed789",
+            "repository": repository,
+            "size": 25,
+            "commits": [{"added": [], "modified": ["src/lib.rs"], "removed": ["src/gone.rs"]}],
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(body.get("files_removed").is_none());
+    let job_id = body["sync"]["job_id"].as_i64().expect("job id");
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert_eq!(job.pointer("/request/params/rev"), Some(&json!("fed789")));
+    assert_eq!(
+        job.pointer("/request/params/include_code"),
+        Some(&json!(true))
+    );
+    assert_eq!(job.pointer("/request/target_entities"), Some(&json!([])));
+
+    // GitHub retries under the same delivery id; the retry is only acknowledged.
+    let redeliver = |delivery: &str| {
+        let body = json!({"action": "closed", "issue": {"number": 7}, "repository": repository})
+            .to_string();
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/webhooks/github")
+            .header("x-github-event", "issues")
+            .header("x-github-delivery", delivery)
+            .header("x-hub-signature-256", github_signature(secret, &body))
+            .body(Body::from(body))
+            .expect("request")
+    };
+    let first = app.clone().oneshot(redeliver("retried")).await?;
+    assert_eq!(first.status(), StatusCode::ACCEPTED);
+    let retry = app.clone().oneshot(redeliver("retried")).await?;
+    assert_eq!(retry.status(), StatusCode::OK);
+    let body = to_bytes(retry.into_body(), BODY_LIMIT).await?;
+    let body: Value = serde_json::from_slice(&body)?;
+    assert_eq!(body["ignored"], json!("delivery was already handled"));
+
+    // The signature stands in for an API key; without a secret there is no route.
+    let body = json!({"zen": "hi"}).to_string();
+    let ping = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/webhooks/github")
+            .header("x-github-event", "ping")
+            .header("x-hub-signature-256", github_signature(secret, &body))
+            .body(Body::from(body.clone()))
+            .expect("request")
+    };
+    let guarded = build_router(
+        AppState::new(Arc::clone(&storage))
+            .with_github_webhook_secret(secret)
+            .with_api_key("viewer-key", Role::Viewer),
+    );
+    assert_eq!(guarded.oneshot(ping()).await?.status(), StatusCode::OK);
+    let disabled = build_router(AppState::new(storage));
+    assert_eq!(
+        disabled.oneshot(ping()).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_queue_starts_interactive_jobs_first() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = StorageConfig::new(dir.path());
+    let storage = Arc::new(FStorage::new(config).await?);
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    storage.register_fetcher(Arc::new(GatedFetcher {
+        gate: Arc::clone(&gate),
+    }));
+    let app = build_router(AppState::with_sync_workers(storage, 1));
+
+    // Occupy the only worker so the next two jobs have to queue up.
+    let blocker = post_sync(&app, json!({"fetcher": "gated_mock"})).await?;
+    wait_for_job_state(&app, blocker, "running").await?;
+    let background = post_sync(
+        &app,
+        json!({"fetcher": "gated_mock", "priority": "background"}),
+    )
+    .await?;
+    let interactive = post_sync(
+        &app,
+        json!({"fetcher": "gated_mock", "priority": "interactive"}),
+    )
+    .await?;
+
+    gate.add_permits(3);
+    let task_of = |job: &Value| job.get("task_id").and_then(Value::as_i64);
+    let blocker_task = task_of(&wait_for_job_state(&app, blocker, "succeeded").await?);
+    let background_task = task_of(&wait_for_job_state(&app, background, "succeeded").await?);
+    let interactive_task = task_of(&wait_for_job_state(&app, interactive, "succeeded").await?);
+    assert!(blocker_task < interactive_task);
+    assert!(interactive_task < background_task);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_sync_jobs_can_be_resumed() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = StorageConfig::new(dir.path());
+    let storage = Arc::new(FStorage::new(config).await?);
+    storage.register_fetcher(Arc::new(FlakyFetcher::default()));
+    let app = build_router(AppState::new(storage));
+
+    let job_id = post_sync(&app, json!({"fetcher": "flaky_mock"})).await?;
+    let failed = wait_for_job_state(&app, job_id, "failed").await?;
+    assert!(failed
+        .get("error")
+        .and_then(Value::as_str)
+        .is_some_and(|err| err.contains("upstream hiccup")));
+
+    let resume = Request::builder()
+        .method("POST")
+        .uri(format!("/api/sync/jobs/{job_id}/resume"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"budget": {"type": "request_count", "count": 5}}).to_string(),
+        ))?;
+    let response = app.clone().oneshot(resume).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let job = wait_for_job_state(&app, job_id, "succeeded").await?;
+    assert!(job.get("error").is_none());
+    assert_eq!(
+        job.pointer("/request/budget/count").and_then(Value::as_u64),
+        Some(5)
+    );
+
+    let again = Request::builder()
+        .method("POST")
+        .uri(format!("/api/sync/jobs/{job_id}/resume"))
+        .body(Body::empty())?;
+    let response = app.oneshot(again).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn embedding_import_writes_vectors_without_a_provider() -> anyhow::Result<()> {
+    let (app, _dir) = test_app().await?;
+    let import = |dimension: usize, embedding: Value| {
+        json!({
+            "entity_type": "readmechunk",
+            "model": "in-house-v2",
+            "dimension": dimension,
+            "rows": [{
+                "embedding_id": "readme-0",
+                "project_url": "https://example.com/imported",
+                "text": "An imported chunk",
+                "embedding": embedding,
+            }],
+        })
+    };
+    let post = |body: Value| {
+        let app = app.clone();
+        async move {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/vectors/import")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))?;
+            let response = app.oneshot(request).await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+            anyhow::Ok((status, serde_json::from_slice::<Value>(&body)?))
+        }
+    };
+
+    let (status, report) = post(import(3, json!([0.1, 0.2, 0.3]))).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["rows"], json!(1));
+
+    let (status, rows) = post_query(
+        &app,
+        json!({
+            "table": "silver/vectors/readmechunk",
+            "sql": "SELECT embedding_model, array_length(embedding) AS dimension FROM {{table}}",
+        }),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rows["rows"][0]["embedding_model"], json!("in-house-v2"));
+    assert_eq!(rows["rows"][0]["dimension"], json!(3));
+
+    // Declared and actual dimensions disagree.
+    let (status, _) = post(import(4, json!([0.1, 0.2, 0.3]))).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // Consistent, but the table already holds 3-dimensional vectors.
+    let (status, _) = post(import(4, json!([0.1, 0.2, 0.3, 0.4]))).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn quality_report_counts_orphans_and_null_fields() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", "https://example.com/linked".to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", "v1.0.0".to_string())],
+    ))
+    .to_string();
+    let edge_id =
+        utils::id::stable_edge_id_u128(HasVersion::ENTITY_TYPE, &project_uuid, &version_uuid);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/linked"),
+        project("https://example.com/orphan"),
+    ]);
+    graph.add_entities(vec![Version {
+        sha: Some("v1.0.0".to_string()),
+        tag: Some("v1.0.0".to_string()),
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    graph.add_entities(vec![HasVersion {
+        id: Some(Uuid::from_u128(edge_id).to_string()),
+        from_node_id: Some(project_uuid),
+        to_node_id: Some(version_uuid),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let request = Request::builder()
+        .uri("/api/v1/reports/quality")
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(request).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/reports/quality")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let generated: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+
+    let report = get_json(&app, "/api/v1/reports/quality").await?;
+    assert_eq!(report["generated_at"], generated["generated_at"]);
+    let tables = report["tables"].as_array().expect("table list");
+    let table = |entity_type: &str| {
+        tables
+            .iter()
+            .find(|table| table["entity_type"] == entity_type)
+            .unwrap_or_else(|| panic!("no quality row for {entity_type}"))
+    };
+
+    let projects = table(Project::ENTITY_TYPE);
+    assert_eq!(projects["category"], "node");
+    assert_eq!(projects["rows"], 2);
+    assert_eq!(projects["orphan_nodes"], 1);
+    assert_eq!(projects["id_parse_failures"], 0);
+    assert_eq!(projects["missing_embeddings"], Value::Null);
+    let null_fields = projects["mostly_null_fields"].as_array().expect("fields");
+    assert!(null_fields.contains(&json!("description")));
+    assert!(!null_fields.contains(&json!("url")));
+
+    assert_eq!(table(Version::ENTITY_TYPE)["orphan_nodes"], 0);
+    let edges = table("has_version");
+    assert_eq!(edges["category"], "edge");
+    assert_eq!(edges["rows"], 1);
+    assert_eq!(edges["id_parse_failures"], 0);
+    assert_eq!(edges["orphan_nodes"], Value::Null);
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_schedules_fire_without_overlapping() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    storage.register_fetcher(Arc::new(GatedFetcher {
+        gate: Arc::clone(&gate),
+    }));
+    let app = build_router(AppState::with_sync_workers(storage, 1));
+    let send = |method: &str, uri: &str, body: Option<Value>| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())));
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request?).await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+            let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            anyhow::Ok((status, body))
+        }
+    };
+    let schedule = |cron: &str, fetcher: &str| {
+        json!({
+            "name": "nightly",
+            "cron": cron,
+            "jitter_secs": 30,
+            "request": {"fetcher": fetcher, "params": {"repo": "octo/repo"}},
+        })
+    };
+
+    let (status, _) = send(
+        "POST",
+        "/api/v1/schedules",
+        Some(schedule("61 * * * *", "gated_mock")),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        "POST",
+        "/api/v1/schedules",
+        Some(schedule("@daily", "missing")),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let before = chrono::Utc::now().timestamp();
+    let (status, created) = send(
+        "POST",
+        "/api/v1/schedules",
+        Some(schedule("0 3 * * *", "gated_mock")),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    let next_run_at = created["next_run_at"].as_i64().expect("next run");
+    assert!(next_run_at > before && next_run_at <= before + 86_400 + 30);
+    assert_eq!(next_run_at.rem_euclid(86_400) / 60, 3 * 60);
+    assert_eq!(created["request"]["priority"], "background");
+    assert_eq!(created["enabled"], true);
+
+    let (status, fired) = send("POST", "/api/v1/schedules/nightly/run", None).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fired["last_outcome"], "queued");
+    let job_id = fired["last_job_id"].as_i64().expect("job id");
+    wait_for_job_state(&app, job_id, "running").await?;
+
+    // The first job is still running, so this firing is skipped.
+    let (_, skipped) = send("POST", "/api/v1/schedules/nightly/run", None).await?;
+    assert_eq!(skipped["last_outcome"], "skipped");
+    assert_eq!(skipped["last_job_state"], "running");
+    assert_eq!(skipped["last_job_id"].as_i64(), Some(job_id));
+
+    gate.add_permits(1);
+    wait_for_job_state(&app, job_id, "succeeded").await?;
+    let (_, fired) = send("POST", "/api/v1/schedules/nightly/run", None).await?;
+    assert_eq!(fired["last_outcome"], "queued");
+    assert_ne!(fired["last_job_id"].as_i64(), Some(job_id));
+
+    let listed = get_json(&app, "/api/v1/schedules").await?;
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+    let (status, _) = send("DELETE", "/api/v1/schedules/nightly", None).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send("GET", "/api/v1/schedules/nightly", None).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    gate.add_permits(1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn saved_searches_are_stored_and_rerun() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/saved-a"),
+        project("https://example.com/saved-b"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+    let send = |method: &str, uri: &str, body: Option<Value>| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())));
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request?).await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+            let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            anyhow::Ok((status, body))
+        }
+    };
+
+    let (status, _) = send(
+        "POST",
+        "/api/v1/saved_searches",
+        Some(json!({"name": "two types", "kind": "graph", "entity_types": ["project", "version"]})),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let saved = json!({
+        "name": "saved-projects",
+        "kind": "graph",
+        "q": "saved",
+        "entity_types": ["project"],
+        "limit": 1,
+        "filters": {"language": "Rust"},
+    });
+    let (status, created) = send("POST", "/api/v1/saved_searches", Some(saved)).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["filters"]["language"], "Rust");
+    let (_, replaced) = send(
+        "POST",
+        "/api/v1/saved_searches",
+        Some(json!({"name": "saved-projects", "kind": "graph", "q": "saved", "entity_types": ["project"], "limit": 2})),
+    )
+    .await?;
+    assert_eq!(replaced["created_at"], created["created_at"]);
+    assert_eq!(replaced["limit"], 2);
+
+    let results = get_json(&app, "/api/v1/saved_searches/saved-projects/results").await?;
+    assert_eq!(results["search"]["name"], "saved-projects");
+    assert_eq!(
+        results["graph"]["candidates"].as_array().map(Vec::len),
+        Some(2)
+    );
+    assert!(results.get("hybrid").is_none());
+
+    let listed = get_json(&app, "/api/v1/saved_searches").await?;
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+    let (status, _) = send("DELETE", "/api/v1/saved_searches/saved-projects", None).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send("GET", "/api/v1/saved_searches/saved-projects/results", None).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn bookmarks_pin_nodes_with_notes() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/pinned"),
+        project("https://example.com/other"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage).with_api_key("view-key", Role::Viewer));
+    let send = |method: &str, uri: &str, body: Option<Value>| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .header("x-api-key", "view-key")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())));
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request?).await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+            let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            anyhow::Ok((status, body))
+        }
+    };
+    let node_id = |url: &str| {
+        Uuid::from_u128(utils::id::stable_node_id_u128(
+            Project::ENTITY_TYPE,
+            &[("url", url.to_string())],
+        ))
+        .to_string()
+    };
+    let pinned = node_id("https://example.com/pinned");
+    let other = node_id("https://example.com/other");
+
+    let (status, _) = send(
+        "POST",
+        "/api/v1/bookmarks",
+        Some(json!({"node_id": Uuid::nil().to_string()})),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, created) = send(
+        "POST",
+        "/api/v1/bookmarks",
+        Some(json!({"node_id": pinned, "note": "check the release cadence"})),
+    )
+    .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["node"]["entity_type"], Project::ENTITY_TYPE);
+    assert_eq!(created["note"], "check the release cadence");
+    let (_, renoted) = send(
+        "POST",
+        "/api/v1/bookmarks",
+        Some(json!({"node_id": pinned, "note": "done"})),
+    )
+    .await?;
+    assert_eq!(renoted["note"], "done");
+    assert_eq!(renoted["created_at"], created["created_at"]);
+    send("POST", "/api/v1/bookmarks", Some(json!({"node_id": other}))).await?;
+
+    let (_, listed) = send("GET", "/api/v1/bookmarks", None).await?;
+    let ids: Vec<&str> = listed
+        .as_array()
+        .expect("bookmarks")
+        .iter()
+        .filter_map(|bookmark| bookmark["node_id"].as_str())
+        .collect();
+    assert_eq!(ids.len(), 2);
+
+    // Viewers manage their own bookmarks but cannot delete graph data.
+    let (status, _) = send("DELETE", &format!("/api/v1/graph/node?id={other}"), None).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send("DELETE", &format!("/api/v1/bookmarks/{pinned}"), None).await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send("DELETE", &format!("/api/v1/bookmarks/{pinned}"), None).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn workspaces_serve_separate_stores_under_their_prefix() -> anyhow::Result<()> {
+    let default_dir = tempdir()?;
+    let team_dir = tempdir()?;
+    let default_store = Arc::new(FStorage::new(StorageConfig::new(default_dir.path())).await?);
+    let team_store = Arc::new(FStorage::new(StorageConfig::new(team_dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project("https://example.com/team-a")]);
+    team_store.synchronizer.process_graph_data(graph).await?;
+
+    assert!(AppState::new(Arc::clone(&default_store))
+        .with_workspace("graph", AppState::new(Arc::clone(&team_store)))
+        .is_err());
+    let app = build_router(
+        AppState::new(default_store)
+            .with_api_key("view-key", Role::Viewer)
+            .with_workspace("team-a", AppState::new(team_store))
+            .map_err(anyhow::Error::msg)?,
+    );
+    let call = |uri: &str, key: Option<&str>| {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(key) = key {
+            builder = builder.header("x-api-key", key);
+        }
+        let request = builder.body(Body::empty()).expect("request");
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT).await?;
+            let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            anyhow::Ok((status, body))
+        }
+    };
+
+    let (_, workspaces) = call("/api/v1/workspaces", Some("view-key")).await?;
+    assert_eq!(workspaces, json!(["team-a"]));
+    let (status, team) = call("/api/v1/team-a/status", Some("view-key")).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(team["entity_count"], 1);
+    let (_, default) = call("/api/v1/status", Some("view-key")).await?;
+    assert_eq!(default["entity_count"], 0);
+
+    // The server's keys guard every workspace.
+    let (status, _) = call("/api/v1/team-a/status", None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn bm25_endpoint_returns_lexical_hits_for_one_type() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let function = |name: &str, signature: &str| Function {
+        version_sha: Some("sha-1".to_string()),
+        file_path: Some("src/lib.rs".to_string()),
+        name: Some(name.to_string()),
+        signature: Some(signature.to_string()),
+        start_line: Some(1),
+        end_line: Some(5),
+        is_component: Some(false),
+    };
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        function("rank", "fn rank()"),
+        function("search", "fn search_engine()"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let found = get_json(
+        &app,
+        &format!(
+            "/api/v1/search/bm25?entity_type={}&q=search%20engine&limit=5",
+            Function::ENTITY_TYPE
+        ),
+    )
+    .await?;
+    assert_eq!(found["entity_type"], Function::ENTITY_TYPE);
+    let hits = found["hits"].as_array().expect("hits");
+    assert!(!hits.is_empty());
+    assert!(hits.iter().all(|hit| hit["score"].is_number()));
+    assert_eq!(hits[0]["node"]["label"], Function::ENTITY_TYPE);
+
+    // Another entity type filters out every function match.
+    let other = get_json(
+        &app,
+        &format!(
+            "/api/v1/search/bm25?entity_type={}&q=search%20engine",
+            Project::ENTITY_TYPE
+        ),
+    )
+    .await?;
+    assert_eq!(other["hits"], json!([]));
+
+    let missing = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/search/bm25?q=search")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(missing.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn vector_endpoint_is_rate_limited_like_hybrid_search() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let limit = RateLimit {
+        per_minute: 1,
+        burst: 1,
+    };
+    let app = build_router(AppState::new(storage).with_rate_limit(limit));
+    let call = |uri: &str, key: &str| {
+        let request = Request::builder()
+            .uri(uri)
+            .header("x-api-key", key)
+            .body(Body::empty())
+            .expect("request");
+        let app = app.clone();
+        async move { app.oneshot(request).await.expect("response") }
+    };
+
+    // A blank query is answered without calling the embedding provider.
+    let response = call("/api/v1/search/vector?entity_type=readmechunk", "agent").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+    assert_eq!(body, json!({"entity_type": "readmechunk", "hits": []}));
+
+    let throttled = call("/api/v1/search/vector?entity_type=readmechunk", "agent").await;
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let missing = call("/api/v1/search/vector?q=parser", "other").await;
+    assert_eq!(missing.status(), StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+fn readme_chunk(sha: &str, order: i64, text: &str, committed_at: &str) -> ReadmeChunk {
+    ReadmeChunk {
+        id: None,
+        project_url: Some("https://github.com/example/readme".to_string()),
+        revision_sha: Some(sha.to_string()),
+        source_file: Some("README.md".to_string()),
+        start_line: None,
+        end_line: None,
+        text: Some(text.to_string()),
+        embedding: Some(vec![0.5_f32, 0.25_f32, 0.25_f32]),
+        embedding_model: Some("fixture".to_string()),
+        embedding_id: Some(format!("{sha}-{order}")),
+        token_count: None,
+        chunk_order: Some(order),
+        created_at: Some(committed_at.parse().expect("timestamp")),
+        updated_at: None,
+    }
+}
+
+#[tokio::test]
+async fn project_readme_is_stitched_from_its_chunks() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let project_url = "https://github.com/example/readme";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project(project_url)]);
+    // Chunks arrive out of order; the newer revision is listed first.
+    graph.add_entities(vec![
+        readme_chunk("old", 1, "Old body.", "2024-01-01T00:00:00Z"),
+        readme_chunk("old", 0, "# Old", "2024-01-01T00:00:00Z"),
+        readme_chunk("new", 2, "Usage.", "2024-06-01T00:00:00Z"),
+        readme_chunk("new", 0, "# New", "2024-06-01T00:00:00Z"),
+        readme_chunk("new", 1, "", "2024-06-01T00:00:00Z"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let latest = get_json(&app, &format!("/api/v1/projects/{project_uuid}/readme")).await?;
+    assert_eq!(latest["project_url"], project_url);
+    assert_eq!(latest["version"], "new");
+    assert_eq!(latest["text"], "# New\n\nUsage.");
+    assert_eq!(latest["chunk_count"], 3);
+    assert_eq!(latest["source_file"], "README.md");
+    assert_eq!(
+        latest["source_url"],
+        "https://github.com/example/readme/blob/new/README.md"
+    );
+    assert_eq!(latest["versions"], json!(["new", "old"]));
+
+    let old = get_json(
+        &app,
+        &format!("/api/v1/projects/{project_uuid}/readme?version=old"),
+    )
+    .await?;
+    assert_eq!(old["text"], "# Old\nOld body.");
+
+    for uri in [
+        format!("/api/v1/projects/{project_uuid}/readme?version=missing"),
+        format!("/api/v1/projects/{}/readme", Uuid::nil()),
+    ] {
+        let request = Request::builder().uri(&uri).body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "GET {uri}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn related_projects_rank_shared_libraries_and_contributors() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let node_id = |entity_type: &str, keys: &[(&str, &str)]| {
+        let keys: Vec<(&str, String)> = keys
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+        Uuid::from_u128(utils::id::stable_node_id_u128(entity_type, &keys)).to_string()
+    };
+    let edge = |entity_type: &str, from: &str, from_type: &str, to: &str, to_type: &str| {
+        json!({
+            "id": Uuid::from_u128(utils::id::stable_edge_id_u128(entity_type, from, to)).to_string(),
+            "from_node_id": from,
+            "to_node_id": to,
+            "from_node_type": from_type,
+            "to_node_type": to_type,
+        })
+    };
+
+    let urls = [
+        "https://github.com/example/a",
+        "https://github.com/example/b",
+        "https://github.com/example/c",
+    ];
+    let [a, b, c] = urls.map(|url| node_id(Project::ENTITY_TYPE, &[("url", url)]));
+    let developer = node_id(
+        Developer::ENTITY_TYPE,
+        &[
+            ("platform", "github"),
+            ("account_id", "7"),
+            ("login", "dev"),
+        ],
+    );
+    let serde = node_id(Library::ENTITY_TYPE, &[("name", "serde")]);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(urls.iter().map(|url| project(url)).collect());
+    graph.add_entities(vec![serde_json::from_value::<Developer>(json!({
+        "platform": "github",
+        "account_id": "7",
+        "login": "dev",
+    }))?]);
+    graph.add_entities(vec![serde_json::from_value::<Library>(
+        json!({"name": "serde"}),
+    )?]);
+    // A and B both depend on serde from their head version's manifest.
+    for (project_id, sha) in [(&a, "a1"), (&b, "b1")] {
+        let version = node_id(Version::ENTITY_TYPE, &[("sha", sha)]);
+        let file = node_id(
+            File::ENTITY_TYPE,
+            &[("version_sha", sha), ("path", "Cargo.toml")],
+        );
+        graph.add_entities(vec![Version {
+            sha: Some(sha.to_string()),
+            tag: None,
+            is_head: Some(true),
+            created_at: None,
+        }]);
+        graph.add_entities(vec![serde_json::from_value::<File>(json!({
+            "version_sha": sha,
+            "path": "Cargo.toml",
+        }))?]);
+        graph.add_entities(vec![serde_json::from_value::<HasVersion>(edge(
+            HasVersion::ENTITY_TYPE,
+            project_id,
+            "project",
+            &version,
+            "version",
+        ))?]);
+        graph.add_entities(vec![serde_json::from_value::<Contains>(edge(
+            Contains::ENTITY_TYPE,
+            &version,
+            "version",
+            &file,
+            "file",
+        ))?]);
+        graph.add_entities(vec![serde_json::from_value::<DependsOn>(edge(
+            DependsOn::ENTITY_TYPE,
+            &file,
+            "file",
+            &serde,
+            "library",
+        ))?]);
+    }
+    for project_id in [&a, &b, &c] {
+        graph.add_entities(vec![serde_json::from_value::<ContributesTo>(edge(
+            ContributesTo::ENTITY_TYPE,
+            &developer,
+            "developer",
+            project_id,
+            "project",
+        ))?]);
+    }
+    storage.synchronizer.process_graph_data(graph).await?;
+    let app = build_router(AppState::new(storage));
+
+    let related = get_json(&app, &format!("/api/v1/projects/{a}/related")).await?;
+    assert_eq!(related["project_url"], urls[0]);
+    let related = related["related"].as_array().expect("related array");
+    assert_eq!(related.len(), 2, "{related:?}");
+    assert_eq!(related[0]["project"]["id"], b.as_str());
+    assert_eq!(related[0]["shared_libraries"], 1);
+    assert_eq!(related[0]["shared_contributors"], 1);
+    assert_eq!(related[1]["project"]["id"], c.as_str());
+    assert_eq!(related[1]["shared_libraries"], 0);
+    assert_eq!(related[1]["shared_contributors"], 1);
+    assert!(related[0]["score"].as_f64() > related[1]["score"].as_f64());
+
+    let limited = get_json(&app, &format!("/api/v1/projects/{a}/related?limit=1")).await?;
+    assert_eq!(limited["related"].as_array().map(Vec::len), Some(1));
+
+    let request = Request::builder()
+        .uri(format!("/api/v1/projects/{developer}/related"))
+        .body(Body::empty())?;
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn project_activity_counts_daily_commits_issues_and_pull_requests() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let project_url = "https://github.com/example/activity";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", "head".to_string())],
+    ))
+    .to_string();
+    let commit_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Commit::ENTITY_TYPE,
+        &[("sha", "c1".to_string())],
+    ))
+    .to_string();
+    let now = chrono::Utc::now();
+    let two_days_ago = now - chrono::Duration::days(2);
+    let long_ago = now - chrono::Duration::days(30);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project(project_url)]);
+    graph.add_entities(vec![Version {
+        sha: Some("head".to_string()),
+        tag: None,
+        is_head: Some(true),
+        created_at: Some(now),
+    }]);
+    graph.add_entities(vec![Commit {
+        sha: Some("c1".to_string()),
+        message: Some("Fix wobble".to_string()),
+        committed_at: Some(now),
+    }]);
+    graph.add_entities(vec![HasVersion {
+        id: Some(
+            Uuid::from_u128(utils::id::stable_edge_id_u128(
+                HasVersion::ENTITY_TYPE,
+                &project_uuid,
+                &version_uuid,
+            ))
+            .to_string(),
+        ),
+        from_node_id: Some(project_uuid.clone()),
+        to_node_id: Some(version_uuid.clone()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    graph.add_entities(vec![IsCommit {
+        id: Some(
+            Uuid::from_u128(utils::id::stable_edge_id_u128(
+                IsCommit::ENTITY_TYPE,
+                &version_uuid,
+                &commit_uuid,
+            ))
+            .to_string(),
+        ),
+        from_node_id: Some(version_uuid.clone()),
+        to_node_id: Some(commit_uuid.clone()),
+        from_node_type: Some("version".to_string()),
+        to_node_type: Some("commit".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    // Two issues two days ago, one outside a week's window.
+    for (number, created_at) in [(1, two_days_ago), (2, two_days_ago), (3, long_ago)] {
+        graph.add_entities(vec![serde_json::from_value::<Issue>(json!({
+            "project_url": project_url,
+            "number": number,
+            "title": "Widgets wobble",
+            "created_at": created_at,
+        }))?]);
+    }
+    graph.add_entities(vec![serde_json::from_value::<PullRequest>(json!({
+        "project_url": project_url,
+        "number": 4,
+        "title": "Stop the wobble",
+        "created_at": now,
+    }))?]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    storage.materialize_activity().await?;
+    let app = build_router(AppState::new(storage));
+
+    let activity = get_json(
+        &app,
+        &format!("/api/v1/projects/{project_uuid}/activity?window=1w"),
+    )
+    .await?;
+    assert_eq!(activity["window_days"], 7);
+    let series = activity["series"].as_array().expect("series array");
+    assert_eq!(series.len(), 7);
+    assert_eq!(series[6]["date"], now.format("%Y-%m-%d").to_string());
+    assert_eq!(series[6]["commits"], 1);
+    assert_eq!(series[6]["pull_requests"], 1);
+    assert_eq!(
+        series[4]["date"],
+        two_days_ago.format("%Y-%m-%d").to_string()
+    );
+    assert_eq!(series[4]["issues"], 2);
+    assert_eq!(
+        activity["totals"],
+        json!({"commits": 1, "issues": 2, "pull_requests": 1})
+    );
+
+    let default_window =
+        get_json(&app, &format!("/api/v1/projects/{project_uuid}/activity")).await?;
+    assert_eq!(default_window["series"].as_array().map(Vec::len), Some(90));
+    assert_eq!(default_window["totals"]["issues"], 3);
+
+    for (uri, status) in [
+        (
+            format!("/api/v1/projects/{project_uuid}/activity?window=soon"),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            format!("/api/v1/projects/{}/activity", Uuid::nil()),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let request = Request::builder().uri(&uri).body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), status, "GET {uri}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn comparing_projects_splits_libraries_languages_and_contributors() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let node_id = |entity_type: &str, keys: &[(&str, &str)]| {
+        let keys: Vec<(&str, String)> = keys
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+        Uuid::from_u128(utils::id::stable_node_id_u128(entity_type, &keys)).to_string()
+    };
+    let edge = |entity_type: &str, from: &str, from_type: &str, to: &str, to_type: &str| {
+        json!({
+            "id": Uuid::from_u128(utils::id::stable_edge_id_u128(entity_type, from, to)).to_string(),
+            "from_node_id": from,
+            "to_node_id": to,
+            "from_node_type": from_type,
+            "to_node_type": to_type,
+        })
+    };
+
+    let urls = [
+        "https://github.com/example/a",
+        "https://github.com/example/b",
+    ];
+    let [a, b] = urls.map(|url| node_id(Project::ENTITY_TYPE, &[("url", url)]));
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        Project {
+            language: Some("Rust".to_string()),
+            ..project(urls[0])
+        },
+        Project {
+            language: Some("Go".to_string()),
+            ..project(urls[1])
+        },
+    ]);
+    // A's manifest pulls in serde and tokio, B's Go module serde only.
+    for (project_id, sha, path, language, libraries) in [
+        (&a, "a1", "Cargo.toml", "toml", &["serde", "tokio"][..]),
+        (&b, "b1", "go.mod", "go", &["serde"][..]),
+    ] {
+        let version = node_id(Version::ENTITY_TYPE, &[("sha", sha)]);
+        let file = node_id(File::ENTITY_TYPE, &[("version_sha", sha), ("path", path)]);
+        graph.add_entities(vec![Version {
+            sha: Some(sha.to_string()),
+            tag: None,
+            is_head: Some(true),
+            created_at: None,
+        }]);
+        graph.add_entities(vec![serde_json::from_value::<File>(json!({
+            "version_sha": sha,
+            "path": path,
+            "language": language,
+        }))?]);
+        graph.add_entities(vec![serde_json::from_value::<HasVersion>(edge(
+            HasVersion::ENTITY_TYPE,
+            project_id,
+            "project",
+            &version,
+            "version",
+        ))?]);
+        graph.add_entities(vec![serde_json::from_value::<Contains>(edge(
+            Contains::ENTITY_TYPE,
+            &version,
+            "version",
+            &file,
+            "file",
+        ))?]);
+        for name in libraries {
+            graph.add_entities(vec![serde_json::from_value::<Library>(
+                json!({"name": name}),
+            )?]);
+            graph.add_entities(vec![serde_json::from_value::<DependsOn>(edge(
+                DependsOn::ENTITY_TYPE,
+                &file,
+                "file",
+                &node_id(Library::ENTITY_TYPE, &[("name", name)]),
+                "library",
+            ))?]);
+        }
+    }
+    // One developer works on both, another on B only.
+    for (account_id, login, projects) in [("7", "dev", vec![&a, &b]), ("8", "gopher", vec![&b])] {
+        let developer = node_id(
+            Developer::ENTITY_TYPE,
+            &[
+                ("platform", "github"),
+                ("account_id", account_id),
+                ("login", login),
+            ],
+        );
+        graph.add_entities(vec![serde_json::from_value::<Developer>(json!({
+            "platform": "github",
+            "account_id": account_id,
+            "login": login,
+        }))?]);
+        for project_id in projects {
+            graph.add_entities(vec![serde_json::from_value::<ContributesTo>(edge(
+                ContributesTo::ENTITY_TYPE,
+                &developer,
+                "developer",
+                project_id,
+                "project",
+            ))?]);
+        }
+    }
+    storage.synchronizer.process_graph_data(graph).await?;
+    storage.materialize_activity().await?;
+    let app = build_router(AppState::new(storage));
+
+    let comparison = get_json(
+        &app,
+        &format!("/api/v1/projects/compare?a={a}&b={b}&window=2w"),
+    )
+    .await?;
+    assert_eq!(comparison["window_days"], 14);
+    assert_eq!(comparison["a"]["project"]["id"], a.as_str());
+    assert_eq!(comparison["a"]["project_url"], urls[0]);
+    assert_eq!(comparison["a"]["language"], "Rust");
+    assert_eq!(comparison["a"]["file_languages"], json!({"toml": 1}));
+    assert_eq!(comparison["a"]["libraries"], 2);
+    assert_eq!(comparison["b"]["contributors"], 2);
+    assert_eq!(comparison["b"]["activity"]["trend"], "steady");
+    assert_eq!(
+        comparison["dependencies"],
+        json!({"shared": ["serde"], "only_a": ["tokio"], "only_b": [], "jaccard": 0.5})
+    );
+    assert_eq!(
+        comparison["languages"]["shared"].as_array().map(Vec::len),
+        Some(0)
+    );
+    assert_eq!(comparison["languages"]["only_b"], json!(["Go", "go"]));
+    let contributors = &comparison["contributors"];
+    assert_eq!(contributors["shared"].as_array().map(Vec::len), Some(1));
+    assert_eq!(contributors["only_a"], 0);
+    assert_eq!(contributors["only_b"], 1);
+    assert_eq!(contributors["jaccard"], 0.5);
+    assert!(comparison.get("readme_similarity").is_none());
+
+    for (uri, status) in [
+        (
+            format!("/api/v1/projects/compare?a={a}&b={}", a.to_uppercase()),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            format!("/api/v1/projects/compare?a={a}&b={}", Uuid::nil()),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let request = Request::builder().uri(&uri).body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), status, "GET {uri}");
+    }
+    Ok(())
+}
+
+/// A store holding `project_url` and its version `sha`.
+async fn store_with_version(
+    dir: &std::path::Path,
+    project_url: &str,
+    sha: &str,
+) -> anyhow::Result<Arc<FStorage>> {
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir)).await?);
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", sha.to_string())],
+    ))
+    .to_string();
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![project(project_url)]);
+    graph.add_entities(vec![Version {
+        sha: Some(sha.to_string()),
+        tag: None,
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    graph.add_entities(vec![HasVersion {
+        id: Some(
+            Uuid::from_u128(utils::id::stable_edge_id_u128(
+                HasVersion::ENTITY_TYPE,
+                &project_uuid,
+                &version_uuid,
+            ))
+            .to_string(),
+        ),
+        from_node_id: Some(project_uuid),
+        to_node_id: Some(version_uuid),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    Ok(storage)
+}
+
+#[tokio::test]
+async fn federated_subgraph_merges_remote_neighbourhoods() -> anyhow::Result<()> {
+    let project_url = "https://github.com/example/federated";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+
+    let remote_dir = tempdir()?;
+    let remote_storage = store_with_version(remote_dir.path(), project_url, "remote-sha").await?;
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+    let remote_addr = listener.local_addr()?;
+    let remote_app =
+        build_router(AppState::new(remote_storage).with_api_key("remote-key", Role::Viewer));
+    tokio::spawn(async move { axum::serve(listener, remote_app).await });
+    // Nothing listens on the unreachable remote's port once its listener is dropped.
+    let unreachable_addr = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+        .await?
+        .local_addr()?;
+
+    let local_dir = tempdir()?;
+    let local_storage = store_with_version(local_dir.path(), project_url, "local-sha").await?;
+    let state = AppState::new(local_storage)
+        .with_federated_remote(
+            "team-b",
+            format!("http://{remote_addr}"),
+            Some("remote-key".to_string()),
+        )
+        .map_err(anyhow::Error::msg)?
+        .with_federated_remote("offline", format!("http://{unreachable_addr}"), None)
+        .map_err(anyhow::Error::msg)?;
+    let app = build_router(state);
+
+    let merged = get_json(
+        &app,
+        &format!("/api/v1/federation/graph/subgraph?start_id={project_uuid}&depth=1"),
+    )
+    .await?;
+    assert_eq!(merged["center"]["id"], project_uuid.as_str());
+    assert_eq!(merged["center"]["origins"], json!(["local", "team-b"]));
+    let origins_of = |sha: &str| {
+        merged["nodes"]
+            .as_array()
+            .expect("nodes array")
+            .iter()
+            .find(|node| node["properties"]["sha"] == sha)
+            .map(|node| node["origins"].clone())
+    };
+    assert_eq!(origins_of("local-sha"), Some(json!(["local"])));
+    assert_eq!(origins_of("remote-sha"), Some(json!(["team-b"])));
+    assert_eq!(merged["edges"].as_array().map(Vec::len), Some(2));
+    assert_eq!(merged["errors"].as_array().map(Vec::len), Some(1));
+    assert_eq!(merged["errors"][0]["origin"], "offline");
+
+    let request = Request::builder()
+        .uri(format!(
+            "/api/v1/federation/graph/subgraph?start_id={}",
+            Uuid::nil()
+        ))
+        .body(Body::empty())?;
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[cfg(feature = "ui")]
+#[tokio::test]
+async fn ui_dir_overrides_built_in_assets() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let ui_dir = tempdir()?;
+    std::fs::write(ui_dir.path().join("styles.css"), "body { color: teal; }")?;
+    std::fs::write(ui_dir.path().join("logo.svg"), "<svg/>")?;
+    let app = build_router(AppState::new(storage).with_ui_dir(ui_dir.path()));
+
+    let get = |uri: &str| {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("request");
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.expect("response");
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let cache_control = response
+                .headers()
+                .get("cache-control")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT)
+                .await
+                .expect("body");
+            (
+                status,
+                content_type,
+                cache_control,
+                String::from_utf8_lossy(&body).to_string(),
+            )
+        }
+    };
+
+    let (status, content_type, cache_control, body) = get("/styles.css").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/css"), "{content_type}");
+    assert_eq!(cache_control, "no-cache");
+    assert_eq!(body, "body { color: teal; }");
+
+    let (_, content_type, _, _) = get("/logo.svg").await;
+    assert_eq!(content_type, "image/svg+xml");
+
+    // Files the directory lacks come from the built-in UI.
+    let (status, content_type, _, body) = get("/app.js").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("application/javascript"));
+    assert!(!body.is_empty());
+    let (status, content_type, _, _) = get("/graph/some-node").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/html"));
+
+    // The API is not shadowed by the directory.
+    get_json(&app, "/api/v1/status").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn hybrid_search_rejects_unconfigured_rerankers_and_models() -> anyhow::Result<()> {
+    let (app, _dir) = test_app().await?;
+    for (query, message) in [
+        ("rerank=true", "no reranker"),
+        ("embedding_model=no-such-model", "unknown embedding model"),
+    ] {
+        let request = Request::builder()
+            .uri(format!(
+                "/api/v1/search/hybrid_all?q=parser&entity_types=function&{query}"
+            ))
+            .body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), BODY_LIMIT).await?)?;
+        assert!(
+            body["error"]
+                .as_str()
+                .is_some_and(|error| error.contains(message)),
+            "{query}: {body}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn hybrid_search_flags_and_blocks_stale_projects() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let (fresh, stale) = (
+        "https://github.com/example/fresh",
+        "https://github.com/example/stale",
+    );
+    let chunk = |project_url: &str| ReadmeChunk {
+        id: None,
+        project_url: Some(project_url.to_string()),
+        revision_sha: Some("rev".to_string()),
+        source_file: Some("README.md".to_string()),
+        start_line: Some(1),
+        end_line: Some(2),
+        text: Some(format!("freshness guardrail notes for {project_url}")),
+        embedding: Some(vec![0.5_f32, 0.25_f32, 0.25_f32]),
+        embedding_model: Some("fixture".to_string()),
+        embedding_id: Some(project_url.to_string()),
+        token_count: None,
+        chunk_order: Some(0),
+        created_at: Some(chrono::Utc::now()),
+        updated_at: None,
+    };
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![chunk(fresh), chunk(stale)]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    storage.catalog.upsert_readiness(&EntityReadiness {
+        entity_uri: fresh.to_string(),
+        entity_type: Project::ENTITY_TYPE.to_string(),
+        last_synced_at: Some(chrono::Utc::now().timestamp() - 60),
+        ttl_seconds: Some(3600),
+        coverage_metrics: "{}".to_string(),
+    })?;
+    let app = build_router(AppState::new(storage));
+    let search = format!(
+        "/api/v1/search/hybrid_all?q=freshness+guardrail&entity_types={}",
+        ReadmeChunk::ENTITY_TYPE
+    );
+
+    let plain = get_json(&app, &search).await?;
+    assert!(plain.get("freshness").is_none());
+
+    let annotated = get_json(&app, &format!("{search}&max_age=3600")).await?;
+    assert_eq!(annotated["hits"].as_array().map(Vec::len), Some(2));
+    let mut projects: Vec<(String, bool, bool)> = annotated["freshness"]["projects"]
+        .as_array()
+        .expect("projects is a list")
+        .iter()
+        .map(|project| {
+            (
+                project["project_url"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                project["stale"] == json!(true),
+                project["age_seconds"].is_i64(),
+            )
+        })
+        .collect();
+    projects.sort();
+    assert_eq!(
+        projects,
+        vec![
+            (fresh.to_string(), false, true),
+            (stale.to_string(), true, false),
+        ]
+    );
+    assert_eq!(annotated["freshness"]["blocked"], json!(false));
+    assert_eq!(annotated["freshness"]["syncs"], json!([]));
+
+    let blocked = get_json(
+        &app,
+        &format!("{search}&max_age=3600&block_stale=true&auto_sync=true"),
+    )
+    .await?;
+    assert_eq!(blocked["hits"], json!([]));
+    assert_eq!(blocked["freshness"]["blocked"], json!(true));
+    // No GitHub fetcher is registered, so the stale project cannot be synced.
+    let not_queued = &blocked["freshness"]["not_queued"];
+    assert_eq!(not_queued.as_array().map(Vec::len), Some(1));
+    assert_eq!(not_queued[0]["uri"], stale);
+    assert_eq!(not_queued[0]["code"], "FETCHER_UNKNOWN");
+
+    let lenient = get_json(&app, &format!("{search}&max_age=86400000")).await?;
+    let stale_count = lenient["freshness"]["projects"]
+        .as_array()
+        .expect("projects is a list")
+        .iter()
+        .filter(|project| project["stale"] == json!(true))
+        .count();
+    assert_eq!(stale_count, 1, "a project never synced is always stale");
+    Ok(())
+}
+
+#[tokio::test]
+async fn debug_id_matches_the_ids_sync_assigns() -> anyhow::Result<()> {
+    let (app, _dir) = test_app().await?;
+    let url = "https://example.com/debug-id";
+    let expected = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", url.to_string())],
+    ));
+    let node = get_json(
+        &app,
+        &format!("/api/v1/debug/id?entity_type=project&url={url}"),
+    )
+    .await?;
+    assert_eq!(node["id"], expected.to_string());
+    assert_eq!(node["name"], format!("project|url={url}"));
+
+    let edge = get_json(&app, "/api/v1/debug/id?edge=has_version&from=a&to=b").await?;
+    let expected = Uuid::from_u128(utils::id::stable_edge_id_u128("has_version", "a", "b"));
+    assert_eq!(edge["id"], expected.to_string());
+
+    for query in [
+        "entity_type=project",
+        "entity_type=project&url=x&stars=3",
+        "edge=x&from=a",
+    ] {
+        let request = Request::builder()
+            .uri(format!("/api/v1/debug/id?{query}"))
+            .body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn onboarding_queues_staged_syncs_and_a_refresh_schedule() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    storage.register_fetcher(Arc::new(GitMock));
+    let app = build_router(AppState::new(Arc::clone(&storage)));
+    let onboard = |body: Value| {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/onboard")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("request");
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.expect("response");
+            let status = response.status();
+            let body = to_bytes(response.into_body(), BODY_LIMIT)
+                .await
+                .expect("body");
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, body)
+        }
+    };
+
+    for repo in [
+        "example",
+        "example/onboarded/extra",
+        "https://gitlab.com/a/b",
+    ] {
+        let (status, _) = onboard(json!({"repo": repo})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{repo}");
+    }
+    let (status, _) = onboard(json!({"repo": "example/onboarded", "refresh_cron": "nope"})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let jobs = get_json(&app, "/api/sync/jobs").await?;
+    assert_eq!(jobs, json!([]), "rejected requests queue nothing");
+
+    let project_url = "https://github.com/example/onboarded";
+    let (status, body) = onboard(json!({"repo": format!("{project_url}.git")})).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    assert_eq!(body["project_url"], json!(project_url));
+    assert_eq!(
+        body["project_id"],
+        json!(Uuid::from_u128(utils::id::stable_node_id_u128(
+            Project::ENTITY_TYPE,
+            &[("url", project_url.to_string())],
+        ))
+        .to_string())
+    );
+    assert_eq!(body["schedule"]["name"], json!("onboard-example-onboarded"));
+    assert_eq!(body["schedule"]["cron"], json!("@daily"));
+    let stages: Vec<(&str, &str)> = body["stages"]
+        .as_array()
+        .expect("stages")
+        .iter()
+        .map(|stage| {
+            (
+                stage["stage"].as_str().unwrap_or_default(),
+                stage["priority"].as_str().unwrap_or_default(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        stages,
+        vec![("metadata", "interactive"), ("code", "background")]
+    );
+    // The code stage is only queued once the metadata stage succeeded.
+    let metadata_job = body["job"]["job_id"].as_i64().expect("job id");
+    let metadata = wait_for_job_state(&app, metadata_job, "succeeded").await?;
+    assert_eq!(
+        metadata.pointer("/request/then/0/entity_types"),
+        Some(&json!(["code"]))
+    );
+    let mut code_job = None;
+    for _ in 0..100 {
+        let jobs = get_json(&app, "/api/sync/jobs").await?;
+        code_job = jobs
+            .as_array()
+            .expect("jobs")
+            .iter()
+            .map(|job| job["job_id"].as_i64().expect("job id"))
+            .find(|job_id| *job_id != metadata_job);
+        if code_job.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let code_job = code_job.expect("the code stage was queued");
+    let code = wait_for_job_state(&app, code_job, "succeeded").await?;
+    assert_eq!(
+        code.pointer("/request/entity_types"),
+        Some(&json!(["code"]))
+    );
+    let readiness = storage
+        .catalog
+        .get_readiness(project_url)?
+        .expect("project readiness recorded");
+    assert!(readiness.last_synced_at.is_some());
+    Ok(())
+}
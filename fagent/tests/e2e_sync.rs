@@ -11,6 +11,7 @@
 //!   * Introduce helpers that cross-check hot/cold row counts for each entity.
 //!   * Promote GitHub + proxy environment discovery into reusable fixtures.
 //!   * Extend sync verification to inspect vector + edge materialization.
+#![cfg(feature = "fetchers")]
 
 use std::{io::ErrorKind, net::SocketAddr, sync::Arc, time::Duration};
 
@@ -149,8 +150,31 @@ async fn end_to_end_repo_snapshot_sync() -> Result<()> {
         status.is_success(),
         "sync endpoint returned {status}, body: {body_text}"
     );
-
-    sleep(Duration::from_secs(5)).await;
+    let accepted: serde_json::Value =
+        serde_json::from_str(&body_text).context("sync response is not JSON")?;
+    let status_url = accepted
+        .get("status_url")
+        .and_then(|value| value.as_str())
+        .context("sync response lacks status_url")?;
+
+    let job = loop {
+        let job: serde_json::Value = client
+            .get(format!("{}{}", server.base_url(), status_url))
+            .send()
+            .await
+            .context("failed to poll sync job")?
+            .json()
+            .await
+            .context("sync job response is not JSON")?;
+        match job.get("state").and_then(|value| value.as_str()) {
+            Some("queued") | Some("running") => sleep(Duration::from_secs(2)).await,
+            _ => break job,
+        }
+    };
+    anyhow::ensure!(
+        job.get("state").and_then(|value| value.as_str()) == Some("succeeded"),
+        "sync job did not succeed: {job}"
+    );
 
     // Placeholder: verify cold (Delta) and hot (Helix) layers converge.
     // TODO: add concrete assertions comparing lake and engine state once helper APIs are available.
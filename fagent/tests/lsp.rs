@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use fagent::serve_lsp;
+use fstorage::{
+    config::StorageConfig,
+    fetch::{Fetchable, GraphData},
+    schemas::generated_schemas::{Calls, Function},
+    sync::DataSynchronizer,
+    utils, FStorage,
+};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use uuid::Uuid;
+
+const SHA: &str = "0123abcd";
+
+fn function(file_path: &str, name: &str, start_line: i32, end_line: i32) -> Function {
+    Function {
+        version_sha: Some(SHA.to_string()),
+        file_path: Some(file_path.to_string()),
+        name: Some(name.to_string()),
+        signature: Some(format!("fn {name}()")),
+        start_line: Some(start_line),
+        end_line: Some(end_line),
+        is_component: Some(false),
+    }
+}
+
+fn function_id(file_path: &str, name: &str) -> String {
+    Uuid::from_u128(utils::id::stable_node_id_u128(
+        Function::ENTITY_TYPE,
+        &[
+            ("version_sha", SHA.to_string()),
+            ("file_path", file_path.to_string()),
+            ("name", name.to_string()),
+        ],
+    ))
+    .to_string()
+}
+
+async fn send<W: AsyncWriteExt + Unpin>(writer: &mut W, message: Value) -> anyhow::Result<()> {
+    let body = message.to_string();
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n{body}", body.len()).as_bytes())
+        .await?;
+    Ok(())
+}
+
+async fn receive<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> anyhow::Result<Value> {
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse()?;
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+fn location_lines(result: &Value) -> Vec<(String, u64)> {
+    let mut lines: Vec<(String, u64)> = result
+        .as_array()
+        .expect("locations")
+        .iter()
+        .map(|location| {
+            (
+                location["uri"].as_str().unwrap_or_default().to_string(),
+                location["range"]["start"]["line"]
+                    .as_u64()
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    lines.sort();
+    lines
+}
+
+#[tokio::test]
+async fn lsp_serves_definitions_and_callers_from_the_code_graph() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        function("src/lib.rs", "parse", 10, 20),
+        function("src/main.rs", "main", 1, 5),
+        function("src/cli.rs", "run", 3, 9),
+    ]);
+    graph.add_entities(
+        [("src/main.rs", "main"), ("src/cli.rs", "run")]
+            .into_iter()
+            .map(|(file_path, name)| Calls {
+                id: None,
+                from_node_id: Some(function_id(file_path, name)),
+                to_node_id: Some(function_id("src/lib.rs", "parse")),
+                from_node_type: Some(Function::ENTITY_TYPE.to_string()),
+                to_node_type: Some(Function::ENTITY_TYPE.to_string()),
+                created_at: None,
+                updated_at: None,
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server);
+    let served = tokio::spawn(serve_lsp(storage, server_read, server_write));
+    let (client_read, mut client_write) = tokio::io::split(client);
+    let mut client_read = BufReader::new(client_read);
+
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+    )
+    .await?;
+    let initialized = receive(&mut client_read).await?;
+    assert_eq!(
+        initialized["result"]["capabilities"]["referencesProvider"],
+        true
+    );
+
+    let query = json!({"query": "PAR"});
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "workspace/symbol", "params": query}),
+    )
+    .await?;
+    let symbols = receive(&mut client_read).await?;
+    assert_eq!(symbols["result"][0]["name"], "parse");
+    assert_eq!(symbols["result"].as_array().map(Vec::len), Some(1));
+
+    let main_uri = format!("fagent://{SHA}/src/main.rs");
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": main_uri, "languageId": "rust", "version": 1,
+                "text": "fn main() {\n    parse();\n}\n"},
+        }}),
+    )
+    .await?;
+    let position =
+        json!({"textDocument": {"uri": main_uri}, "position": {"line": 1, "character": 6}});
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": position}),
+    )
+    .await?;
+    let definition = receive(&mut client_read).await?;
+    assert_eq!(
+        location_lines(&definition["result"]),
+        vec![(format!("fagent://{SHA}/src/lib.rs"), 9)]
+    );
+
+    // Without the document text, the enclosing function is the target.
+    let lib_position = json!({
+        "textDocument": {"uri": format!("fagent://{SHA}/src/lib.rs")},
+        "position": {"line": 14, "character": 0},
+        "context": {"includeDeclaration": false},
+    });
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "textDocument/references", "params": lib_position}),
+    )
+    .await?;
+    let references = receive(&mut client_read).await?;
+    assert_eq!(
+        location_lines(&references["result"]),
+        vec![
+            (format!("fagent://{SHA}/src/cli.rs"), 2),
+            (format!("fagent://{SHA}/src/main.rs"), 0),
+        ]
+    );
+
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 5, "method": "textDocument/hover", "params": position}),
+    )
+    .await?;
+    let unsupported = receive(&mut client_read).await?;
+    assert_eq!(unsupported["error"]["code"], -32601);
+
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "id": 6, "method": "shutdown"}),
+    )
+    .await?;
+    assert_eq!(receive(&mut client_read).await?["result"], Value::Null);
+    send(
+        &mut client_write,
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    )
+    .await?;
+    served.await??;
+    Ok(())
+}
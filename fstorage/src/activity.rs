@@ -0,0 +1,272 @@
+//! Daily activity counts per project.
+//!
+//! [`materialize`] counts commits, issues and pull requests per project and
+//! UTC day from the ingested tables and stores them in [`ACTIVITY_TABLE`], so
+//! a timeline is a single filtered read. Issues and pull requests carry their
+//! `project_url`; a commit belongs to the projects whose versions point at it
+//! (`HAS_VERSION` then `IS_COMMIT`) and to those of the pull requests it
+//! implements. Each commit is counted once per project.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::Fetchable;
+use crate::lake::Lake;
+use crate::models::ActivityDay;
+use crate::schemas::generated_schemas::{
+    Commit, HasVersion, ImplementsPr, IsCommit, Issue, Project, PullRequest,
+};
+use crate::utils::id::stable_node_id_u128;
+use chrono::{DateTime, Duration, NaiveDate};
+use deltalake::arrow::array::{ArrayRef, Int64Array, StringArray};
+use deltalake::arrow::datatypes::{DataType, Field, Schema};
+use deltalake::arrow::record_batch::RecordBatch;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Gold table holding one row per project and day with any activity.
+pub const ACTIVITY_TABLE: &str = "gold/activity";
+
+type DailyCounts = BTreeMap<(String, NaiveDate), ActivityDay>;
+
+/// Recounts the activity of every project and upserts it into
+/// [`ACTIVITY_TABLE`]. Returns the number of (project, day) rows written.
+pub async fn materialize(lake: &Lake) -> Result<usize> {
+    let mut counts = DailyCounts::new();
+
+    let mut pull_request_projects = HashMap::new();
+    for row in lake
+        .table_sql(
+            &PullRequest::table_name(),
+            "SELECT project_url, number, created_at FROM {{table}}",
+        )
+        .await?
+    {
+        let (Some(url), Some(number)) = (text(&row, "project_url"), row.get("number")) else {
+            continue;
+        };
+        let id = node_id(
+            PullRequest::ENTITY_TYPE,
+            &[("project_url", url), ("number", &number.to_string())],
+        );
+        pull_request_projects.insert(id, url.to_string());
+        if let Some(day) = day(&row, "created_at") {
+            entry(&mut counts, url, day).pull_requests += 1;
+        }
+    }
+
+    for row in lake
+        .table_sql(
+            &Issue::table_name(),
+            "SELECT project_url, created_at FROM {{table}}",
+        )
+        .await?
+    {
+        if let (Some(url), Some(day)) = (text(&row, "project_url"), day(&row, "created_at")) {
+            entry(&mut counts, url, day).issues += 1;
+        }
+    }
+
+    let commit_projects = commit_projects(lake, &pull_request_projects).await?;
+    for row in lake
+        .table_sql(
+            &Commit::table_name(),
+            "SELECT sha, committed_at FROM {{table}}",
+        )
+        .await?
+    {
+        let (Some(sha), Some(day)) = (text(&row, "sha"), day(&row, "committed_at")) else {
+            continue;
+        };
+        let id = node_id(Commit::ENTITY_TYPE, &[("sha", sha)]);
+        for url in commit_projects.get(&id).into_iter().flatten() {
+            entry(&mut counts, url, day).commits += 1;
+        }
+    }
+
+    let written = counts.len();
+    if written > 0 {
+        lake.write_batches(
+            ACTIVITY_TABLE,
+            vec![to_batch(&counts)?],
+            Some(vec!["project_url".to_string(), "day".to_string()]),
+        )
+        .await?;
+    }
+    Ok(written)
+}
+
+/// One entry per day from `since` through `until`, oldest first; days without
+/// stored activity are zero.
+pub async fn timeline(
+    lake: &Lake,
+    project_url: &str,
+    since: NaiveDate,
+    until: NaiveDate,
+) -> Result<Vec<ActivityDay>> {
+    let rows = lake
+        .query_table(ACTIVITY_TABLE, Some(&[("project_url", project_url)]), None)
+        .await?;
+    let count = |row: &HashMap<String, JsonValue>, key: &str| {
+        row.get(key).and_then(JsonValue::as_u64).unwrap_or_default()
+    };
+    let stored: HashMap<&str, &HashMap<String, JsonValue>> = rows
+        .iter()
+        .filter_map(|row| Some((text(row, "day")?, row)))
+        .collect();
+
+    let mut days = Vec::new();
+    let mut date = since;
+    while date <= until {
+        let key = date.format("%Y-%m-%d").to_string();
+        days.push(match stored.get(key.as_str()) {
+            Some(row) => ActivityDay {
+                commits: count(row, "commits"),
+                issues: count(row, "issues"),
+                pull_requests: count(row, "pull_requests"),
+                date: key,
+            },
+            None => ActivityDay {
+                date: key,
+                ..ActivityDay::default()
+            },
+        });
+        date += Duration::days(1);
+    }
+    Ok(days)
+}
+
+/// Project URLs each commit id belongs to.
+async fn commit_projects(
+    lake: &Lake,
+    pull_request_projects: &HashMap<String, String>,
+) -> Result<HashMap<String, HashSet<String>>> {
+    let mut project_urls = HashMap::new();
+    for row in lake
+        .table_sql(&Project::table_name(), "SELECT url FROM {{table}}")
+        .await?
+    {
+        if let Some(url) = text(&row, "url") {
+            project_urls.insert(
+                node_id(Project::ENTITY_TYPE, &[("url", url)]),
+                url.to_string(),
+            );
+        }
+    }
+
+    let mut version_projects: HashMap<String, Vec<String>> = HashMap::new();
+    for (project, version) in edges(lake, HasVersion::ENTITY_TYPE).await? {
+        if let Some(url) = project_urls.get(&project) {
+            version_projects
+                .entry(version)
+                .or_default()
+                .push(url.clone());
+        }
+    }
+
+    let mut commit_projects: HashMap<String, HashSet<String>> = HashMap::new();
+    for (version, commit) in edges(lake, IsCommit::ENTITY_TYPE).await? {
+        if let Some(urls) = version_projects.get(&version) {
+            commit_projects
+                .entry(commit)
+                .or_default()
+                .extend(urls.iter().cloned());
+        }
+    }
+    for (commit, pull_request) in edges(lake, ImplementsPr::ENTITY_TYPE).await? {
+        if let Some(url) = pull_request_projects.get(&pull_request) {
+            commit_projects
+                .entry(commit)
+                .or_default()
+                .insert(url.clone());
+        }
+    }
+    Ok(commit_projects)
+}
+
+/// (from, to) node ids of every stored edge of `entity_type`.
+async fn edges(lake: &Lake, entity_type: &str) -> Result<Vec<(String, String)>> {
+    let table = format!(
+        "silver/edges/{}",
+        entity_type.strip_prefix("edge_").unwrap_or(entity_type)
+    );
+    Ok(lake
+        .table_sql(&table, "SELECT from_node_id, to_node_id FROM {{table}}")
+        .await?
+        .iter()
+        .filter_map(|row| {
+            Some((
+                text(row, "from_node_id")?.to_string(),
+                text(row, "to_node_id")?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+fn node_id(entity_type: &str, keys: &[(&str, &str)]) -> String {
+    let keys: Vec<(&str, String)> = keys
+        .iter()
+        .map(|(key, value)| (*key, value.to_string()))
+        .collect();
+    Uuid::from_u128(stable_node_id_u128(entity_type, &keys)).to_string()
+}
+
+fn text<'a>(row: &'a HashMap<String, JsonValue>, key: &str) -> Option<&'a str> {
+    row.get(key).and_then(JsonValue::as_str)
+}
+
+/// UTC day of the RFC 3339 timestamp in `key`.
+fn day(row: &HashMap<String, JsonValue>, key: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(text(row, key)?)
+        .ok()
+        .map(|at| at.naive_utc().date())
+}
+
+fn entry<'a>(
+    counts: &'a mut DailyCounts,
+    project_url: &str,
+    day: NaiveDate,
+) -> &'a mut ActivityDay {
+    counts
+        .entry((project_url.to_string(), day))
+        .or_insert_with(|| ActivityDay {
+            date: day.format("%Y-%m-%d").to_string(),
+            ..ActivityDay::default()
+        })
+}
+
+fn to_batch(counts: &DailyCounts) -> Result<RecordBatch> {
+    let count = |value: fn(&ActivityDay) -> u64| -> ArrayRef {
+        Arc::new(Int64Array::from(
+            counts
+                .values()
+                .map(|day| value(day) as i64)
+                .collect::<Vec<_>>(),
+        ))
+    };
+    let schema = Schema::new(vec![
+        Field::new("project_url", DataType::Utf8, false),
+        Field::new("day", DataType::Utf8, false),
+        Field::new("commits", DataType::Int64, false),
+        Field::new("issues", DataType::Int64, false),
+        Field::new("pull_requests", DataType::Int64, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(
+            counts
+                .keys()
+                .map(|(url, _)| url.as_str())
+                .collect::<Vec<_>>(),
+        )),
+        Arc::new(StringArray::from(
+            counts
+                .values()
+                .map(|day| day.date.as_str())
+                .collect::<Vec<_>>(),
+        )),
+        count(|day| day.commits),
+        count(|day| day.issues),
+        count(|day| day.pull_requests),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).map_err(|e| StorageError::Other(e.into()))
+}
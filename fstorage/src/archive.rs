@@ -0,0 +1,166 @@
+//! Store archives: the whole catalog, lake and engine in one tar file, for
+//! moving a knowledge base to another machine.
+//!
+//! An archive holds a storage root (`catalog.sqlite`, `lake/`, `engine/`)
+//! captured the way a snapshot is, plus an `archive.json` manifest. Importing
+//! unpacks it next to the target directory and checks the manifest before
+//! moving it into place, so a refused archive leaves nothing behind. Archives
+//! written by a build with different generated schemas are refused, since the
+//! lake tables and engine labels would not match what this build expects.
+
+use crate::catalog::Catalog;
+use crate::config::StorageConfig;
+use crate::errors::{Result, StorageError};
+use crate::models::ArchiveManifest;
+use crate::schema_registry::SCHEMA_REGISTRY;
+use crate::snapshot;
+use helix_db::helix_engine::traversal_core::HelixGraphEngine;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "archive.json";
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Writes the store described by `config` to the tar file `out`, which must
+/// not exist yet. The caller must keep writers out for the duration.
+pub(crate) fn export(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    out: &Path,
+) -> Result<ArchiveManifest> {
+    if out.exists() {
+        return Err(StorageError::InvalidArg(format!(
+            "{} already exists",
+            out.display()
+        )));
+    }
+    // Staged under the snapshot root so lake files can be hard-linked; hidden
+    // names are not listed as snapshots.
+    let root = config.snapshot_root();
+    fs::create_dir_all(&root)?;
+    let staging = tempfile::Builder::new()
+        .prefix(".archive")
+        .tempdir_in(&root)?;
+
+    let table_versions = snapshot::copy_store(config, catalog, engine, staging.path())?;
+    let manifest = ArchiveManifest {
+        format_version: FORMAT_VERSION,
+        fstorage_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_fingerprint: SCHEMA_REGISTRY.fingerprint(),
+        created_at: chrono::Utc::now().timestamp(),
+        graph_version: catalog.get_graph_version()?,
+        table_versions,
+    };
+
+    let mut partial = out.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = std::path::PathBuf::from(partial);
+    let written = write_tar(staging.path(), &manifest, &partial);
+    match written {
+        Ok(()) => fs::rename(&partial, out)?,
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+    }
+    Ok(manifest)
+}
+
+fn write_tar(dir: &Path, manifest: &ArchiveManifest, out: &Path) -> Result<()> {
+    let mut builder = tar::Builder::new(BufWriter::new(File::create(out)?));
+    // The manifest goes first so it can be read without unpacking the rest.
+    let raw = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(raw.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, raw.as_slice())?;
+    let layout = StorageConfig::new(dir);
+    for path in [&layout.catalog_path, &layout.lake_path, &layout.engine_path] {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if path.is_dir() {
+            builder.append_dir_all(name, path)?;
+        } else {
+            builder.append_path_with_name(path, name)?;
+        }
+    }
+    builder.into_inner()?.flush()?;
+    Ok(())
+}
+
+/// Restores the archive `archive` as the store at `base_path`, which must be
+/// missing or empty. Nothing is written to `base_path` when the archive is
+/// refused.
+pub fn import(archive: &Path, base_path: &Path) -> Result<ArchiveManifest> {
+    if fs::read_dir(base_path).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(StorageError::InvalidArg(format!(
+            "{} is not empty",
+            base_path.display()
+        )));
+    }
+    let manifest = read_manifest(archive)?;
+    check(&manifest)?;
+
+    let parent = match base_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    let staging = tempfile::Builder::new()
+        .prefix(".import")
+        .tempdir_in(parent)?;
+    // `unpack` refuses entries that would land outside the directory.
+    tar::Archive::new(File::open(archive)?).unpack(staging.path())?;
+    fs::remove_file(staging.path().join(MANIFEST_FILE))?;
+    let layout = StorageConfig::new(staging.path());
+    if !layout.catalog_path.is_file() || !layout.engine_path.is_dir() {
+        return Err(StorageError::InvalidArg(format!(
+            "{} has no catalog or engine",
+            archive.display()
+        )));
+    }
+
+    if base_path.exists() {
+        fs::remove_dir(base_path)?;
+    }
+    fs::rename(staging.keep(), base_path)?;
+    Ok(manifest)
+}
+
+/// Reads `archive.json` from the front of `archive`.
+pub fn read_manifest(archive: &Path) -> Result<ArchiveManifest> {
+    let mut tar = tar::Archive::new(File::open(archive)?);
+    let mut entries = tar.entries()?;
+    if let Some(entry) = entries.next() {
+        let entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST_FILE {
+            return Ok(serde_json::from_reader(entry)?);
+        }
+    }
+    Err(StorageError::InvalidArg(format!(
+        "{} is not a store archive: it does not start with {MANIFEST_FILE}",
+        archive.display()
+    )))
+}
+
+fn check(manifest: &ArchiveManifest) -> Result<()> {
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(StorageError::InvalidArg(format!(
+            "unsupported archive format version {}",
+            manifest.format_version
+        )));
+    }
+    let fingerprint = SCHEMA_REGISTRY.fingerprint();
+    if manifest.schema_fingerprint != fingerprint {
+        return Err(StorageError::InvalidArg(format!(
+            "archive was written by fstorage {} with schema {}, this build has schema {}",
+            manifest.fstorage_version, manifest.schema_fingerprint, fingerprint
+        )));
+    }
+    Ok(())
+}
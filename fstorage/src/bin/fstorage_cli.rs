@@ -1,20 +1,21 @@
 use std::collections::HashMap;
 use std::fmt::Write as _;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{Context, Result, anyhow, bail};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
 use clap::{Parser, Subcommand, ValueEnum, builder::ArgAction};
+use deltalake::DeltaTable;
 use deltalake::arrow::array::{Array, ArrayRef};
 use deltalake::arrow::util::pretty::pretty_format_batches;
 use deltalake::datafusion::datasource::TableProvider;
 use deltalake::datafusion::execution::context::{SessionConfig, SessionContext};
-use deltalake::open_table;
 use fstorage::FStorage;
 use fstorage::config::StorageConfig;
 use fstorage::lake::{NeighborDirection, NeighborRecord, Subgraph};
+use fstorage::lake_store::LakeStore;
 use fstorage::models::{IngestionOffset, TableSummary};
 use helix_db::helix_engine::storage_core::{
     graph_visualization::GraphVisualization, storage_methods::StorageMethods,
@@ -28,7 +29,6 @@ use helix_db::utils::id::ID;
 use helix_db::utils::items::Node;
 use log::LevelFilter;
 use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
-use url::Url;
 use uuid::Uuid;
 
 #[derive(Parser, Debug)]
@@ -285,7 +285,7 @@ async fn run() -> Result<()> {
 }
 
 async fn handle_cold(storage: &FStorage, command: ColdCommand) -> Result<()> {
-    let lake_root = storage.config.lake_path.clone();
+    let lake = storage.lake.store();
     match command {
         ColdCommand::List { prefix, counts } => {
             let tables =
@@ -309,7 +309,7 @@ async fn handle_cold(storage: &FStorage, command: ColdCommand) -> Result<()> {
             for table in tables {
                 let row_count = if counts {
                     Some(
-                        delta_row_count(&lake_root, &table.table_path)
+                        delta_row_count(lake, &table.table_path)
                             .await
                             .with_context(|| {
                                 format!("failed to count rows for '{}'", table.table_path)
@@ -333,7 +333,7 @@ async fn handle_cold(storage: &FStorage, command: ColdCommand) -> Result<()> {
             Ok(())
         }
         ColdCommand::Show { table, limit, json } => {
-            let batches = read_table_batches(&lake_root, &table, limit)
+            let batches = read_table_batches(lake, &table, limit)
                 .await
                 .with_context(|| format!("failed to read table '{}'", table))?;
 
@@ -353,7 +353,7 @@ async fn handle_cold(storage: &FStorage, command: ColdCommand) -> Result<()> {
             Ok(())
         }
         ColdCommand::Schema { table } => {
-            let summary = open_table_summary(&lake_root, &table)
+            let summary = open_table_summary(lake, &table)
                 .await
                 .with_context(|| format!("failed to load schema for '{}'", table))?;
             println!("Table: {}", summary.table_path);
@@ -585,27 +585,17 @@ async fn handle_summary(storage: &FStorage, json: bool) -> Result<()> {
     Ok(())
 }
 
-async fn delta_row_count(lake_root: &Path, table_path: &str) -> Result<usize> {
-    let batches = read_table_batches(lake_root, table_path, 0).await?;
+async fn delta_row_count(lake: &LakeStore, table_path: &str) -> Result<usize> {
+    let batches = read_table_batches(lake, table_path, 0).await?;
     Ok(batches.iter().map(|batch| batch.num_rows()).sum())
 }
 
 async fn read_table_batches(
-    lake_root: &Path,
+    lake: &LakeStore,
     table_path: &str,
     limit: usize,
 ) -> Result<Vec<deltalake::arrow::record_batch::RecordBatch>> {
-    let path = lake_root.join(table_path);
-    if !path.exists() {
-        bail!(
-            "Delta table '{}' does not exist at {}",
-            table_path,
-            path.display()
-        );
-    }
-    let url = Url::from_file_path(&path)
-        .map_err(|_| anyhow!("invalid table path '{}'", path.display()))?;
-    let table = open_table(url).await?;
+    let table = open_delta_table(lake, table_path).await?;
     let session = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
     let table_provider: Arc<dyn TableProvider> = Arc::new(table);
     let df = session.read_table(table_provider)?;
@@ -618,18 +608,18 @@ async fn read_table_batches(
     Ok(batches)
 }
 
-async fn open_table_summary(lake_root: &Path, table_path: &str) -> Result<TableSummary> {
-    let path = lake_root.join(table_path);
-    if !path.exists() {
-        bail!(
+async fn open_delta_table(lake: &LakeStore, table_path: &str) -> Result<DeltaTable> {
+    lake.open_table(table_path).await?.ok_or_else(|| {
+        anyhow!(
             "Delta table '{}' does not exist at {}",
             table_path,
-            path.display()
-        );
-    }
-    let url = Url::from_file_path(&path)
-        .map_err(|_| anyhow!("invalid table path '{}'", path.display()))?;
-    let table = open_table(url).await?;
+            lake.describe(table_path)
+        )
+    })
+}
+
+async fn open_table_summary(lake: &LakeStore, table_path: &str) -> Result<TableSummary> {
+    let table = open_delta_table(lake, table_path).await?;
     let schema = table.schema();
     let mut columns = Vec::new();
     for field in schema.fields() {
@@ -651,10 +641,10 @@ async fn list_nodes_by_label(
     label: &str,
     limit: usize,
 ) -> Result<Vec<JsonValue>> {
-    let lake_root = storage.config.lake_path.clone();
+    let lake = storage.lake.store();
     let table_path = format!("silver/index/{label}");
 
-    let (batches, cold_available) = match read_table_batches(&lake_root, &table_path, limit).await {
+    let (batches, cold_available) = match read_table_batches(lake, &table_path, limit).await {
         Ok(batches) => (batches, true),
         Err(err) => {
             log::warn!(
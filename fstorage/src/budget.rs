@@ -0,0 +1,165 @@
+//! Enforcement of composite sync budgets.
+//!
+//! One [`BudgetTracker`] is shared by the synchronizer and the metered embedding
+//! provider of a sync run. Requests are charged for the fetch invocation itself
+//! and for every embedding batch; tokens and cost are read from the run's
+//! [`UsageMeter`].
+
+use crate::costs::{approximate_tokens, PricingTable, UsageMeter};
+use crate::errors::{Result, StorageError};
+use crate::models::{BudgetConstraint, BudgetLimits, BudgetUsage};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub struct BudgetTracker {
+    limits: BudgetLimits,
+    started: Instant,
+    requests: AtomicU64,
+    meter: Arc<UsageMeter>,
+    pricing: Arc<PricingTable>,
+    exhausted: Mutex<Option<BudgetConstraint>>,
+}
+
+impl BudgetTracker {
+    pub fn new(limits: BudgetLimits, meter: Arc<UsageMeter>, pricing: Arc<PricingTable>) -> Self {
+        Self {
+            limits,
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            meter,
+            pricing,
+            exhausted: Mutex::new(None),
+        }
+    }
+
+    pub fn limits(&self) -> &BudgetLimits {
+        &self.limits
+    }
+
+    pub fn charge_requests(&self, count: u64) {
+        self.requests.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Time left before the duration limit is hit, if one is set.
+    pub fn remaining_duration(&self) -> Option<Duration> {
+        self.limits
+            .max_duration_secs
+            .map(|secs| Duration::from_secs(secs).saturating_sub(self.started.elapsed()))
+    }
+
+    /// Verifies that one more request fits into the budget.
+    pub fn check_request(&self) -> Result<()> {
+        if let Some(max) = self.limits.max_requests {
+            if self.requests.load(Ordering::Relaxed) + 1 > max as u64 {
+                return Err(self.exhaust(BudgetConstraint::Requests));
+            }
+        }
+        if self.remaining_duration() == Some(Duration::ZERO) {
+            return Err(self.exhaust(BudgetConstraint::Duration));
+        }
+        Ok(())
+    }
+
+    /// Verifies that embedding `texts` fits into the request, token and cost limits.
+    pub fn check_embedding(&self, texts: &[String]) -> Result<()> {
+        self.check_request()?;
+        let upcoming = approximate_tokens(texts);
+        if let Some(max) = self.limits.max_embedding_tokens {
+            if self.meter.tokens() + upcoming > max {
+                return Err(self.exhaust(BudgetConstraint::EmbeddingTokens));
+            }
+        }
+        if let Some(max) = self.limits.max_cost_usd {
+            if self.meter.projected_cost(upcoming, &self.pricing) > max {
+                return Err(self.exhaust(BudgetConstraint::Cost));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `constraint` as the binding one and returns the matching error.
+    pub fn exhaust(&self, constraint: BudgetConstraint) -> StorageError {
+        let mut guard = self.exhausted.lock().unwrap();
+        guard.get_or_insert(constraint);
+        StorageError::BudgetExhausted(constraint)
+    }
+
+    pub fn binding_constraint(&self) -> Option<BudgetConstraint> {
+        *self.exhausted.lock().unwrap()
+    }
+
+    pub fn usage(&self) -> BudgetUsage {
+        BudgetUsage {
+            limits: self.limits.clone(),
+            requests: self.requests.load(Ordering::Relaxed),
+            elapsed_secs: self.started.elapsed().as_secs_f64(),
+            embedding_tokens: self.meter.tokens(),
+            cost_usd: self.meter.summary(&self.pricing).cost_usd,
+            binding_constraint: self.binding_constraint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(limits: BudgetLimits) -> (BudgetTracker, Arc<UsageMeter>) {
+        let meter = Arc::new(UsageMeter::new(
+            "openai",
+            Some("text-embedding-ada-002".into()),
+        ));
+        let tracker = BudgetTracker::new(
+            limits,
+            Arc::clone(&meter),
+            Arc::new(PricingTable::default()),
+        );
+        (tracker, meter)
+    }
+
+    #[test]
+    fn first_exhausted_limit_is_binding() {
+        let (tracker, meter) = tracker(BudgetLimits {
+            max_requests: Some(10),
+            max_embedding_tokens: Some(100),
+            ..Default::default()
+        });
+
+        let texts = vec!["x".repeat(300)];
+        tracker.check_embedding(&texts).unwrap();
+        tracker.charge_requests(1);
+        meter.record(&texts);
+
+        let err = tracker.check_embedding(&texts).unwrap_err();
+        assert!(matches!(
+            err,
+            StorageError::BudgetExhausted(BudgetConstraint::EmbeddingTokens)
+        ));
+
+        tracker.charge_requests(10);
+        let _ = tracker.check_request();
+        let usage = tracker.usage();
+        assert_eq!(usage.embedding_tokens, 75);
+        assert_eq!(
+            usage.binding_constraint,
+            Some(BudgetConstraint::EmbeddingTokens)
+        );
+    }
+
+    #[test]
+    fn request_limit_allows_exactly_max_requests() {
+        let (tracker, _meter) = tracker(BudgetLimits {
+            max_requests: Some(1),
+            ..Default::default()
+        });
+        tracker.check_request().unwrap();
+        tracker.charge_requests(1);
+        assert!(tracker.binding_constraint().is_none());
+        assert!(tracker.check_request().is_err());
+        assert_eq!(
+            tracker.binding_constraint(),
+            Some(BudgetConstraint::Requests)
+        );
+    }
+}
@@ -1,11 +1,20 @@
 use crate::config::StorageConfig;
-use crate::errors::Result;
+use crate::errors::{Result, StorageError};
 use crate::fetch::EntityCategory;
-use crate::models::{ApiBudget, EntityReadiness, IngestionOffset, SourceAnchor};
-use rusqlite::{params, Connection};
+use crate::models::{
+    ApiBudget, ApiUsageAggregate, Bookmark, CostAggregate, CostSummary, EntityReadiness,
+    FileChecksum, ForeignTable, IdentityDecision, IngestionOffset, SavedSearch, SourceAnchor,
+    SyncHistoryEntry, SyncJob, SyncJobState, SyncRun, SyncSchedule, UsageBucket,
+};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde_json;
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+const SYNC_SCHEDULE_COLUMNS: &str = "name, cron, request, jitter_secs, enabled, next_run_at, \
+     last_run_at, last_job_id, last_outcome, created_at, updated_at";
+
 pub struct Catalog {
     conn: Arc<Mutex<Connection>>,
 }
@@ -57,6 +66,121 @@ impl Catalog {
                 updated_at INTEGER NOT NULL,
                 PRIMARY KEY (entity_uri, fetcher, anchor_key)
             );
+            CREATE TABLE IF NOT EXISTS api_usage (
+                day INTEGER NOT NULL,
+                endpoint TEXT NOT NULL,
+                api_key TEXT NOT NULL,
+                count INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, endpoint, api_key)
+            );
+            CREATE TABLE IF NOT EXISTS cost_records (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                scope_ref TEXT,
+                provider TEXT NOT NULL,
+                model TEXT,
+                requests INTEGER NOT NULL,
+                tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL,
+                recorded_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sync_jobs (
+                job_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                fetcher TEXT NOT NULL,
+                request TEXT NOT NULL,
+                state TEXT NOT NULL,
+                progress TEXT,
+                task_id INTEGER,
+                result TEXT,
+                error TEXT,
+                created_at INTEGER NOT NULL,
+                started_at INTEGER,
+                finished_at INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS sync_checkpoints (
+                scope TEXT NOT NULL,
+                entry_key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (scope, entry_key)
+            );
+            CREATE TABLE IF NOT EXISTS graph_version (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                version INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sync_runs (
+                run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                graph_version INTEGER NOT NULL,
+                table_versions TEXT NOT NULL,
+                finished_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sync_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                fetcher TEXT NOT NULL,
+                params_hash TEXT NOT NULL,
+                task_id INTEGER NOT NULL,
+                state TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                finished_at INTEGER,
+                rows TEXT NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_sync_history_fetcher ON sync_history (fetcher, id);
+            CREATE TABLE IF NOT EXISTS sync_schedules (
+                name TEXT PRIMARY KEY,
+                cron TEXT NOT NULL,
+                request TEXT NOT NULL,
+                jitter_secs INTEGER NOT NULL,
+                enabled INTEGER NOT NULL,
+                next_run_at INTEGER NOT NULL,
+                last_run_at INTEGER,
+                last_job_id INTEGER,
+                last_outcome TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bookmarks (
+                node_id TEXT PRIMARY KEY,
+                note TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS saved_searches (
+                name TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                query TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS foreign_tables (
+                name TEXT PRIMARY KEY,
+                uri TEXT NOT NULL,
+                attached_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS lake_file_checksums (
+                path TEXT PRIMARY KEY,
+                table_path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                sha256 TEXT NOT NULL,
+                recorded_at INTEGER NOT NULL,
+                verified_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS identity_decisions (
+                developer_id TEXT NOT NULL,
+                identity_id TEXT NOT NULL,
+                status TEXT NOT NULL,
+                decided_at INTEGER NOT NULL,
+                PRIMARY KEY (developer_id, identity_id)
+            );
+            CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                delivery_id TEXT PRIMARY KEY,
+                received_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS leases (
+                name TEXT PRIMARY KEY,
+                holder TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
             COMMIT;",
         )?;
         Ok(())
@@ -202,6 +326,20 @@ impl Catalog {
         Ok(results)
     }
 
+    /// Number of known entity tables per category, without loading their rows.
+    pub fn count_ingestion_offsets_by_category(&self) -> Result<BTreeMap<String, u64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT category, COUNT(*) FROM ingestion_offsets GROUP BY category")?;
+        let mut counts = BTreeMap::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let count: i64 = row.get(1)?;
+            counts.insert(row.get::<_, String>(0)?, count.max(0) as u64);
+        }
+        Ok(counts)
+    }
+
     pub fn update_ingestion_offset(&self, table_path: &str, last_version: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
@@ -273,126 +411,1565 @@ impl Catalog {
         )?;
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::StorageConfig;
-    use crate::models::EntityReadiness;
-    use tempfile::tempdir;
+    /// Increments the usage counter for `endpoint`/`api_key` on the current UTC day.
+    pub fn record_api_usage(&self, endpoint: &str, api_key: &str) -> Result<()> {
+        self.record_api_usage_at(endpoint, api_key, chrono::Utc::now().timestamp())
+    }
 
-    fn setup() -> (Catalog, tempfile::TempDir) {
-        let dir = tempdir().unwrap();
-        let config = StorageConfig::new(dir.path());
-        let catalog = Catalog::new(&config).unwrap();
-        catalog.initialize_schema().unwrap();
-        (catalog, dir)
+    pub fn record_api_usage_at(&self, endpoint: &str, api_key: &str, timestamp: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let day = timestamp - timestamp.rem_euclid(86_400);
+        conn.execute(
+            "INSERT INTO api_usage (day, endpoint, api_key, count)
+             VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT(day, endpoint, api_key) DO UPDATE SET
+                count = count + 1",
+            params![day, endpoint, api_key],
+        )?;
+        Ok(())
     }
 
-    #[test]
-    fn test_readiness_crud() {
-        let (catalog, _dir) = setup();
+    /// Adds the calls counted per `(day, endpoint, api_key)` in one
+    /// transaction; `day` is the unix time the UTC day starts at.
+    pub fn add_api_usage(&self, counts: &[(i64, String, String, i64)]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (day, endpoint, api_key, count) in counts {
+            tx.execute(
+                "INSERT INTO api_usage (day, endpoint, api_key, count)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(day, endpoint, api_key) DO UPDATE SET
+                    count = count + excluded.count",
+                params![day, endpoint, api_key, count],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
 
-        // Test insert
-        let readiness = EntityReadiness {
-            entity_uri: "test_uri".to_string(),
-            entity_type: "repo".to_string(),
-            last_synced_at: Some(12345),
-            ttl_seconds: Some(3600),
-            coverage_metrics: "{}".to_string(),
-        };
-        let result = catalog.upsert_readiness(&readiness);
-        assert!(result.is_ok());
+    /// Aggregates usage counters into `bucket`-sized windows, optionally filtered by
+    /// time range (inclusive `since`, exclusive `until`), endpoint and key.
+    pub fn aggregate_api_usage(
+        &self,
+        bucket: UsageBucket,
+        since: Option<i64>,
+        until: Option<i64>,
+        endpoint: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<Vec<ApiUsageAggregate>> {
+        let bucket_expr = Self::bucket_expr(bucket, "day");
+        let sql = format!(
+            "SELECT {bucket_expr} AS bucket_start, endpoint, api_key, SUM(count)
+             FROM api_usage
+             WHERE (?1 IS NULL OR day >= ?1)
+               AND (?2 IS NULL OR day < ?2)
+               AND (?3 IS NULL OR endpoint = ?3)
+               AND (?4 IS NULL OR api_key = ?4)
+             GROUP BY bucket_start, endpoint, api_key
+             ORDER BY bucket_start ASC, endpoint ASC, api_key ASC"
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![since, until, endpoint, api_key])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(ApiUsageAggregate {
+                bucket_start: row.get(0)?,
+                endpoint: row.get(1)?,
+                api_key: row.get(2)?,
+                count: row.get(3)?,
+            });
+        }
+        Ok(results)
+    }
 
-        // Test get
-        let fetched = catalog.get_readiness("test_uri").unwrap();
-        assert!(fetched.is_some());
-        let fetched = fetched.unwrap();
-        assert_eq!(fetched.entity_uri, "test_uri");
-        assert_eq!(fetched.last_synced_at, Some(12345));
+    /// SQL expression mapping a Unix timestamp column onto the start of its bucket.
+    fn bucket_expr(bucket: UsageBucket, column: &str) -> String {
+        match bucket {
+            UsageBucket::Day => format!("({column} - ({column} % 86400))"),
+            // 1970-01-01 was a Thursday; shift so that buckets start on Monday.
+            UsageBucket::Week => {
+                format!("({column} - ({column} % 86400) - ((({column} / 86400) + 3) % 7) * 86400)")
+            }
+            UsageBucket::Month => {
+                format!("CAST(strftime('%s', {column}, 'unixepoch', 'start of month') AS INTEGER)")
+            }
+        }
+    }
 
-        // Test update
-        let updated_readiness = EntityReadiness {
-            entity_uri: "test_uri".to_string(),
-            entity_type: "repo".to_string(),
-            last_synced_at: Some(54321),
-            ttl_seconds: Some(3600),
-            coverage_metrics: "{}".to_string(),
-        };
-        let result = catalog.upsert_readiness(&updated_readiness);
-        assert!(result.is_ok());
+    pub fn insert_cost_record(
+        &self,
+        scope: &str,
+        scope_ref: Option<&str>,
+        summary: &CostSummary,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let recorded_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO cost_records (scope, scope_ref, provider, model, requests, tokens, cost_usd, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                scope,
+                scope_ref,
+                summary.provider,
+                summary.model,
+                summary.requests as i64,
+                summary.tokens as i64,
+                summary.cost_usd,
+                recorded_at,
+            ],
+        )?;
+        Ok(())
+    }
 
-        let fetched_updated = catalog.get_readiness("test_uri").unwrap().unwrap();
-        assert_eq!(fetched_updated.last_synced_at, Some(54321));
+    pub fn list_cost_records(&self, scope: &str, scope_ref: &str) -> Result<Vec<CostSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT provider, model, requests, tokens, cost_usd
+             FROM cost_records WHERE scope = ?1 AND scope_ref = ?2 ORDER BY id ASC",
+        )?;
+        let mut rows = stmt.query(params![scope, scope_ref])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            let requests: i64 = row.get(2)?;
+            let tokens: i64 = row.get(3)?;
+            results.push(CostSummary {
+                provider: row.get(0)?,
+                model: row.get(1)?,
+                requests: requests.max(0) as u64,
+                tokens: tokens.max(0) as u64,
+                cost_usd: row.get(4)?,
+            });
+        }
+        Ok(results)
     }
 
-    #[test]
-    fn test_task_log_crud() {
-        let (catalog, _dir) = setup();
+    /// Aggregates recorded costs per bucket, scope (`sync`/`search`), provider and model.
+    pub fn aggregate_costs(
+        &self,
+        bucket: UsageBucket,
+        since: Option<i64>,
+        until: Option<i64>,
+        scope: Option<&str>,
+    ) -> Result<Vec<CostAggregate>> {
+        let bucket_expr = Self::bucket_expr(bucket, "recorded_at");
+        let sql = format!(
+            "SELECT {bucket_expr} AS bucket_start, scope, provider, model,
+                    SUM(requests), SUM(tokens), SUM(cost_usd)
+             FROM cost_records
+             WHERE (?1 IS NULL OR recorded_at >= ?1)
+               AND (?2 IS NULL OR recorded_at < ?2)
+               AND (?3 IS NULL OR scope = ?3)
+             GROUP BY bucket_start, scope, provider, model
+             ORDER BY bucket_start ASC, scope ASC, provider ASC"
+        );
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![since, until, scope])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(CostAggregate {
+                bucket_start: row.get(0)?,
+                scope: row.get(1)?,
+                provider: row.get(2)?,
+                model: row.get(3)?,
+                requests: row.get(4)?,
+                tokens: row.get(5)?,
+                cost_usd: row.get(6)?,
+            });
+        }
+        Ok(results)
+    }
 
-        // Test create
-        let task_id = catalog.create_task_log("test_task");
-        assert!(task_id.is_ok());
-        let task_id = task_id.unwrap();
-        assert_eq!(task_id, 1);
+    pub fn create_sync_job(&self, fetcher: &str, request_json: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let created_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO sync_jobs (fetcher, request, state, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                fetcher,
+                request_json,
+                SyncJobState::Queued.as_str(),
+                created_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
 
-        // Test update
-        let result = catalog.update_task_log_status(task_id, "SUCCESS", "Done");
-        assert!(result.is_ok());
+    pub fn mark_sync_job_running(&self, job_id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let started_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "UPDATE sync_jobs SET state = ?1, started_at = ?2 WHERE job_id = ?3",
+            params![SyncJobState::Running.as_str(), started_at, job_id],
+        )?;
+        Ok(())
     }
 
-    #[test]
-    fn test_ingestion_offsets_crud() {
-        let (catalog, _dir) = setup();
+    pub fn update_sync_job_progress(&self, job_id: i64, progress_json: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sync_jobs SET progress = ?1 WHERE job_id = ?2",
+            params![progress_json, job_id],
+        )?;
+        Ok(())
+    }
 
-        let ensure = catalog.ensure_ingestion_offset(
-            "silver/entities/project",
-            "project",
-            crate::fetch::EntityCategory::Node,
-            &vec!["url".to_string()],
-        );
-        assert!(ensure.is_ok());
+    pub fn finish_sync_job(
+        &self,
+        job_id: i64,
+        state: SyncJobState,
+        task_id: Option<i64>,
+        result_json: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let finished_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "UPDATE sync_jobs SET state = ?1, task_id = ?2, result = ?3, error = ?4, finished_at = ?5
+             WHERE job_id = ?6",
+            params![
+                state.as_str(),
+                task_id,
+                result_json,
+                error,
+                finished_at,
+                job_id
+            ],
+        )?;
+        Ok(())
+    }
 
-        let offset = catalog
-            .get_ingestion_offset("silver/entities/project")
-            .unwrap()
-            .unwrap();
-        assert_eq!(offset.table_path, "silver/entities/project");
-        assert_eq!(offset.entity_type, "project");
-        assert_eq!(offset.category, crate::fetch::EntityCategory::Node);
-        assert_eq!(offset.primary_keys, vec!["url".to_string()]);
-        assert_eq!(offset.last_version, -1);
+    pub fn get_sync_job(&self, job_id: i64) -> Result<Option<SyncJob>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT job_id, fetcher, request, state, progress, task_id, result, error,
+                    created_at, started_at, finished_at
+             FROM sync_jobs WHERE job_id = ?1",
+        )?;
+        let mut rows = stmt.query(params![job_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::map_sync_job_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        let list = catalog.list_ingestion_offsets().unwrap();
-        assert_eq!(list.len(), 1);
+    /// Lists the most recent jobs first, optionally restricted to one state.
+    pub fn list_sync_jobs(
+        &self,
+        state: Option<SyncJobState>,
+        limit: usize,
+    ) -> Result<Vec<SyncJob>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT job_id, fetcher, request, state, progress, task_id, result, error,
+                    created_at, started_at, finished_at
+             FROM sync_jobs
+             WHERE (?1 IS NULL OR state = ?1)
+             ORDER BY job_id DESC
+             LIMIT ?2",
+        )?;
+        let mut rows = stmt.query(params![state.map(|s| s.as_str()), limit as i64])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_sync_job_row(row)?);
+        }
+        Ok(results)
+    }
 
-        catalog
-            .update_ingestion_offset("silver/entities/project", 5)
-            .unwrap();
-        let updated = catalog
-            .get_ingestion_offset("silver/entities/project")
-            .unwrap()
-            .unwrap();
-        assert_eq!(updated.last_version, 5);
+    /// Puts a finished job back in the queue, keeping its id and creation time.
+    pub fn requeue_sync_job(&self, job_id: i64, request_json: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sync_jobs SET state = ?1, request = ?2, progress = NULL, task_id = NULL,
+                result = NULL, error = NULL, started_at = NULL, finished_at = NULL
+             WHERE job_id = ?3",
+            params![SyncJobState::Queued.as_str(), request_json, job_id],
+        )?;
+        Ok(())
+    }
 
-        catalog
-            .ensure_ingestion_offset(
-                "silver/vectors/readme_chunk",
-                "readme_chunk",
-                crate::fetch::EntityCategory::Vector,
-                &vec!["id".to_string()],
+    /// Fails jobs left `running` by a previous process; returns how many were touched.
+    pub fn fail_interrupted_sync_jobs(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let finished_at = chrono::Utc::now().timestamp();
+        let updated = conn.execute(
+            "UPDATE sync_jobs SET state = ?1, error = 'interrupted by restart', finished_at = ?2
+             WHERE state = ?3",
+            params![
+                SyncJobState::Failed.as_str(),
+                finished_at,
+                SyncJobState::Running.as_str()
+            ],
+        )?;
+        Ok(updated)
+    }
+
+    pub fn get_checkpoint_entry(&self, scope: &str, entry_key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT value FROM sync_checkpoints WHERE scope = ?1 AND entry_key = ?2")?;
+        let mut rows = stmt.query(params![scope, entry_key])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn put_checkpoint_entry(&self, scope: &str, entry_key: &str, value: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO sync_checkpoints (scope, entry_key, value, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(scope, entry_key) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at",
+            params![scope, entry_key, value, updated_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn count_checkpoint_entries(&self, scope: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM sync_checkpoints WHERE scope = ?1",
+            params![scope],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn clear_checkpoint(&self, scope: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM sync_checkpoints WHERE scope = ?1",
+            params![scope],
+        )?;
+        Ok(removed)
+    }
+
+    /// Drops every checkpoint whose newest entry was written before `cutoff`
+    /// (unix seconds), returning the number of entries removed.
+    pub fn expire_checkpoints(&self, cutoff: i64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM sync_checkpoints WHERE scope IN (
+                SELECT scope FROM sync_checkpoints
+                GROUP BY scope
+                HAVING MAX(updated_at) < ?1
+             )",
+            params![cutoff],
+        )?;
+        Ok(removed)
+    }
+
+    /// Drops every checkpoint with an entry containing `needle`, returning the
+    /// number of entries removed.
+    pub fn clear_checkpoints_containing(&self, needle: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM sync_checkpoints WHERE scope IN (
+                SELECT scope FROM sync_checkpoints WHERE instr(value, ?1) > 0
+             )",
+            params![needle],
+        )?;
+        Ok(removed)
+    }
+
+    /// Number of graph updates persisted so far; 0 for a fresh store.
+    pub fn get_graph_version(&self) -> Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        let version: Option<i64> = conn
+            .query_row(
+                "SELECT version FROM graph_version WHERE id = 1",
+                [],
+                |row| row.get(0),
             )
-            .unwrap();
+            .optional()?;
+        Ok(version.unwrap_or_default().max(0) as u64)
+    }
 
-        let vector_offset = catalog
-            .get_ingestion_offset("silver/vectors/readme_chunk")
-            .unwrap()
-            .unwrap();
-        assert_eq!(vector_offset.category, crate::fetch::EntityCategory::Vector);
-        assert_eq!(vector_offset.primary_keys, vec!["id".to_string()]);
+    /// Records one more graph update and returns the new version.
+    pub fn bump_graph_version(&self) -> Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        let version: i64 = conn.query_row(
+            "INSERT INTO graph_version (id, version) VALUES (1, 1)
+             ON CONFLICT(id) DO UPDATE SET version = version + 1
+             RETURNING version",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(version.max(0) as u64)
+    }
 
-        let list = catalog.list_ingestion_offsets().unwrap();
-        assert_eq!(list.len(), 2);
+    /// Records a finished sync and the table versions it left behind; returns
+    /// the new run id.
+    pub fn record_sync_run(
+        &self,
+        graph_version: u64,
+        table_versions: &BTreeMap<String, i64>,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let finished_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO sync_runs (graph_version, table_versions, finished_at) VALUES (?1, ?2, ?3)",
+            params![
+                graph_version as i64,
+                serde_json::to_string(table_versions)?,
+                finished_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_sync_run(&self, run_id: i64) -> Result<Option<SyncRun>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT run_id, graph_version, table_versions, finished_at
+             FROM sync_runs WHERE run_id = ?1",
+        )?;
+        let mut rows = stmt.query(params![run_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_sync_run_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists the most recent runs first.
+    pub fn list_sync_runs(&self, limit: usize) -> Result<Vec<SyncRun>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT run_id, graph_version, table_versions, finished_at
+             FROM sync_runs ORDER BY run_id DESC LIMIT ?1",
+        )?;
+        let mut rows = stmt.query(params![limit as i64])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_sync_run_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Records the start of a sync; returns the history entry id.
+    pub fn start_sync_history(
+        &self,
+        fetcher: &str,
+        params_hash: &str,
+        task_id: i64,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let started_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO sync_history (fetcher, params_hash, task_id, state, started_at, rows)
+             VALUES (?1, ?2, ?3, ?4, ?5, '{}')",
+            params![
+                fetcher,
+                params_hash,
+                task_id,
+                SyncJobState::Running.as_str(),
+                started_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn finish_sync_history(
+        &self,
+        id: i64,
+        state: SyncJobState,
+        rows: &BTreeMap<String, u64>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let finished_at = chrono::Utc::now().timestamp();
+        conn.execute(
+            "UPDATE sync_history SET state = ?1, finished_at = ?2, rows = ?3, error = ?4
+             WHERE id = ?5",
+            params![
+                state.as_str(),
+                finished_at,
+                serde_json::to_string(rows)?,
+                error,
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_sync_history(&self, id: i64) -> Result<Option<SyncHistoryEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, fetcher, params_hash, task_id, state, started_at, finished_at, rows, error
+             FROM sync_history WHERE id = ?1",
+        )?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_sync_history_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists the most recent syncs first, optionally of one fetcher only,
+    /// starting below the id `before` when given.
+    pub fn list_sync_history(
+        &self,
+        fetcher: Option<&str>,
+        before: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<SyncHistoryEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, fetcher, params_hash, task_id, state, started_at, finished_at, rows, error
+             FROM sync_history
+             WHERE (?1 IS NULL OR fetcher = ?1) AND (?2 IS NULL OR id < ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )?;
+        let mut rows = stmt.query(params![fetcher, before, limit as i64])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_sync_history_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Fails history entries left `running` by a previous process; returns how many were touched.
+    pub fn fail_interrupted_sync_history(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let finished_at = chrono::Utc::now().timestamp();
+        let updated = conn.execute(
+            "UPDATE sync_history SET state = ?1, error = 'interrupted by restart', finished_at = ?2
+             WHERE state = ?3",
+            params![
+                SyncJobState::Failed.as_str(),
+                finished_at,
+                SyncJobState::Running.as_str()
+            ],
+        )?;
+        Ok(updated)
+    }
+
+    /// Creates or replaces the schedule `schedule.name`. The outcome of its last
+    /// firing is kept when an existing schedule is replaced.
+    pub fn put_sync_schedule(&self, schedule: &SyncSchedule) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sync_schedules
+                (name, cron, request, jitter_secs, enabled, next_run_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+             ON CONFLICT(name) DO UPDATE SET
+                cron = excluded.cron,
+                request = excluded.request,
+                jitter_secs = excluded.jitter_secs,
+                enabled = excluded.enabled,
+                next_run_at = excluded.next_run_at,
+                updated_at = excluded.updated_at",
+            params![
+                schedule.name,
+                schedule.cron,
+                schedule.request,
+                schedule.jitter_secs as i64,
+                schedule.enabled,
+                schedule.next_run_at,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_sync_schedule(&self, name: &str) -> Result<Option<SyncSchedule>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SYNC_SCHEDULE_COLUMNS} FROM sync_schedules WHERE name = ?1"
+        ))?;
+        let mut rows = stmt.query(params![name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_sync_schedule_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists all schedules by name.
+    pub fn list_sync_schedules(&self) -> Result<Vec<SyncSchedule>> {
+        self.query_sync_schedules("ORDER BY name", params![])
+    }
+
+    /// Enabled schedules whose next firing is at or before `now`, earliest first.
+    pub fn due_sync_schedules(&self, now: i64) -> Result<Vec<SyncSchedule>> {
+        self.query_sync_schedules(
+            "WHERE enabled = 1 AND next_run_at <= ?1 ORDER BY next_run_at, name",
+            params![now],
+        )
+    }
+
+    fn query_sync_schedules(
+        &self,
+        clauses: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SyncSchedule>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SYNC_SCHEDULE_COLUMNS} FROM sync_schedules {clauses}"
+        ))?;
+        let mut rows = stmt.query(params)?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_sync_schedule_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Returns whether a schedule was removed.
+    pub fn delete_sync_schedule(&self, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM sync_schedules WHERE name = ?1", params![name])?;
+        Ok(deleted > 0)
+    }
+
+    /// Records a firing of `name` and moves it to its next run.
+    pub fn record_sync_schedule_run(
+        &self,
+        name: &str,
+        ran_at: i64,
+        job_id: Option<i64>,
+        outcome: &str,
+        next_run_at: i64,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sync_schedules
+             SET last_run_at = ?2, last_job_id = ?3, last_outcome = ?4, next_run_at = ?5
+             WHERE name = ?1",
+            params![name, ran_at, job_id, outcome, next_run_at],
+        )?;
+        Ok(())
+    }
+
+    /// Creates or replaces the saved search `search.name`, keeping its creation
+    /// time when it already exists.
+    pub fn put_saved_search(&self, search: &SavedSearch) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO saved_searches (name, kind, query, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                kind = excluded.kind,
+                query = excluded.query,
+                updated_at = excluded.updated_at",
+            params![
+                search.name,
+                search.kind,
+                search.query,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_saved_search(&self, name: &str) -> Result<Option<SavedSearch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, kind, query, created_at, updated_at FROM saved_searches WHERE name = ?1",
+        )?;
+        let mut rows = stmt.query(params![name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_saved_search_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists all saved searches by name.
+    pub fn list_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, kind, query, created_at, updated_at FROM saved_searches ORDER BY name",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_saved_search_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Returns whether a saved search was removed.
+    pub fn delete_saved_search(&self, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM saved_searches WHERE name = ?1", params![name])?;
+        Ok(deleted > 0)
+    }
+
+    /// Pins `bookmark.node_id`, or replaces the note of an existing bookmark.
+    pub fn put_bookmark(&self, bookmark: &Bookmark) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO bookmarks (node_id, note, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)
+             ON CONFLICT(node_id) DO UPDATE SET
+                note = excluded.note,
+                updated_at = excluded.updated_at",
+            params![
+                bookmark.node_id,
+                bookmark.note,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_bookmark(&self, node_id: &str) -> Result<Option<Bookmark>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id, note, created_at, updated_at FROM bookmarks WHERE node_id = ?1",
+        )?;
+        let mut rows = stmt.query(params![node_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_bookmark_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists bookmarks in the order they were created.
+    pub fn list_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT node_id, note, created_at, updated_at FROM bookmarks
+             ORDER BY created_at, node_id",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_bookmark_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Returns whether a bookmark was removed.
+    pub fn delete_bookmark(&self, node_id: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM bookmarks WHERE node_id = ?1", params![node_id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Attaches `uri` as the foreign table `name`, replacing an earlier attachment
+    /// of the same name.
+    pub fn put_foreign_table(&self, name: &str, uri: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO foreign_tables (name, uri, attached_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET
+                uri = excluded.uri,
+                attached_at = excluded.attached_at",
+            params![name, uri, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_foreign_table(&self, name: &str) -> Result<Option<ForeignTable>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT name, uri, attached_at FROM foreign_tables WHERE name = ?1")?;
+        let mut rows = stmt.query(params![name])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::map_foreign_table_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists foreign tables by name.
+    pub fn list_foreign_tables(&self) -> Result<Vec<ForeignTable>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT name, uri, attached_at FROM foreign_tables ORDER BY name")?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Self::map_foreign_table_row(row)?);
+        }
+        Ok(results)
+    }
+
+    /// Returns whether a foreign table was detached.
+    pub fn delete_foreign_table(&self, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM foreign_tables WHERE name = ?1", params![name])?;
+        Ok(deleted > 0)
+    }
+
+    /// Records the webhook delivery `delivery_id`, first forgetting deliveries
+    /// received before `expire_before`. Returns false when it was already
+    /// recorded, i.e. the delivery is a redelivery.
+    pub fn claim_webhook_delivery(
+        &self,
+        delivery_id: &str,
+        received_at: i64,
+        expire_before: i64,
+    ) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE received_at < ?1",
+            params![expire_before],
+        )?;
+        let inserted = conn.execute(
+            "INSERT INTO webhook_deliveries (delivery_id, received_at) VALUES (?1, ?2)
+             ON CONFLICT(delivery_id) DO NOTHING",
+            params![delivery_id, received_at],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Takes the lease `name` for `holder` until `expires_at`, or renews it
+    /// when `holder` has it already. Returns whether `holder` now has it: a
+    /// lease another holder has is only taken once it expired by `now`.
+    pub fn acquire_lease(
+        &self,
+        name: &str,
+        holder: &str,
+        now: i64,
+        expires_at: i64,
+    ) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let acquired = conn.execute(
+            "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?4)
+             ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE leases.holder = excluded.holder OR leases.expires_at <= ?3",
+            params![name, holder, now, expires_at],
+        )?;
+        Ok(acquired > 0)
+    }
+
+    /// Forgets the delivery `delivery_id`, so that a redelivery is handled
+    /// again. Returns whether it was recorded.
+    pub fn release_webhook_delivery(&self, delivery_id: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM webhook_deliveries WHERE delivery_id = ?1",
+            params![delivery_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Records the checksum of a lake file, replacing an earlier record of it.
+    pub fn put_file_checksum(&self, checksum: &FileChecksum) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO lake_file_checksums (path, table_path, size, sha256, recorded_at, verified_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(path) DO UPDATE SET
+                table_path = excluded.table_path,
+                size = excluded.size,
+                sha256 = excluded.sha256,
+                recorded_at = excluded.recorded_at,
+                verified_at = excluded.verified_at",
+            params![
+                checksum.path,
+                checksum.table_path,
+                checksum.size as i64,
+                checksum.sha256,
+                checksum.recorded_at,
+                checksum.verified_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Lists recorded lake file checksums by path.
+    pub fn list_file_checksums(&self) -> Result<Vec<FileChecksum>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT path, table_path, size, sha256, recorded_at, verified_at
+             FROM lake_file_checksums ORDER BY path",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            let size: i64 = row.get(2)?;
+            results.push(FileChecksum {
+                path: row.get(0)?,
+                table_path: row.get(1)?,
+                size: size.max(0) as u64,
+                sha256: row.get(3)?,
+                recorded_at: row.get(4)?,
+                verified_at: row.get(5)?,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Forgets the checksums of `paths`, e.g. files a vacuum removed.
+    pub fn delete_file_checksums(&self, paths: &[String]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        for path in paths {
+            deleted += tx.execute(
+                "DELETE FROM lake_file_checksums WHERE path = ?1",
+                params![path],
+            )?;
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Records a reviewer's verdict on a developer/git identity pair, replacing
+    /// an earlier one.
+    pub fn put_identity_decision(&self, decision: &IdentityDecision) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO identity_decisions (developer_id, identity_id, status, decided_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(developer_id, identity_id) DO UPDATE SET
+                status = excluded.status,
+                decided_at = excluded.decided_at",
+            params![
+                decision.developer_id,
+                decision.identity_id,
+                decision.status.as_str(),
+                decision.decided_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_identity_decisions(&self) -> Result<Vec<IdentityDecision>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT developer_id, identity_id, status, decided_at FROM identity_decisions
+             ORDER BY decided_at, developer_id, identity_id",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            let status: String = row.get(2)?;
+            results.push(IdentityDecision {
+                developer_id: row.get(0)?,
+                identity_id: row.get(1)?,
+                status: status.parse()?,
+                decided_at: row.get(3)?,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Runs a trivial query to prove the database is reachable and readable.
+    pub fn ping(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        Ok(())
+    }
+
+    /// Writes a consistent copy of the whole catalog database to `path`.
+    pub fn backup_to(&self, path: &Path) -> Result<()> {
+        let target = path
+            .to_str()
+            .ok_or_else(|| StorageError::Config(format!("Non-UTF8 backup path: {path:?}")))?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute("VACUUM INTO ?1", params![target])?;
+        Ok(())
+    }
+
+    /// Whether the catalog has no tables yet, i.e. the store is being created.
+    pub(crate) fn is_empty(&self) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(tables == 0)
+    }
+
+    /// On-disk layout version of the store, kept as the catalog's
+    /// `user_version`; see [`crate::migrate`].
+    pub fn layout_version(&self) -> Result<u32> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    pub(crate) fn set_layout_version(&self, version: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute_batch(&format!("PRAGMA user_version = {version};"))?;
+        Ok(())
+    }
+
+    /// Runs `sql` and records `version` as the layout version in one
+    /// transaction, so a failed upgrade leaves the catalog as it was.
+    pub(crate) fn migrate_schema(&self, sql: &str, version: u32) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.execute_batch(&format!("PRAGMA user_version = {version};"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Points the rows naming the lake table `from` at `to` and records
+    /// `version`, after the table directory has been moved.
+    pub(crate) fn move_table_path(&self, from: &str, to: &str, version: u32) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE ingestion_offsets SET table_path = ?2 WHERE table_path = ?1",
+            params![from, to],
+        )?;
+        tx.execute(
+            "UPDATE lake_file_checksums
+             SET table_path = ?2, path = ?2 || substr(path, length(?1) + 1)
+             WHERE table_path = ?1",
+            params![from, to],
+        )?;
+        tx.execute_batch(&format!("PRAGMA user_version = {version};"))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn map_sync_job_row(row: &rusqlite::Row<'_>) -> Result<SyncJob> {
+        let state: String = row.get(3)?;
+        Ok(SyncJob {
+            job_id: row.get(0)?,
+            fetcher: row.get(1)?,
+            request: row.get(2)?,
+            state: state.parse()?,
+            progress: row.get(4)?,
+            task_id: row.get(5)?,
+            result: row.get(6)?,
+            error: row.get(7)?,
+            created_at: row.get(8)?,
+            started_at: row.get(9)?,
+            finished_at: row.get(10)?,
+        })
+    }
+
+    fn map_sync_schedule_row(row: &rusqlite::Row<'_>) -> Result<SyncSchedule> {
+        let jitter_secs: i64 = row.get(3)?;
+        Ok(SyncSchedule {
+            name: row.get(0)?,
+            cron: row.get(1)?,
+            request: row.get(2)?,
+            jitter_secs: jitter_secs.max(0) as u64,
+            enabled: row.get(4)?,
+            next_run_at: row.get(5)?,
+            last_run_at: row.get(6)?,
+            last_job_id: row.get(7)?,
+            last_outcome: row.get(8)?,
+            created_at: row.get(9)?,
+            updated_at: row.get(10)?,
+        })
+    }
+
+    fn map_bookmark_row(row: &rusqlite::Row<'_>) -> Result<Bookmark> {
+        Ok(Bookmark {
+            node_id: row.get(0)?,
+            note: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+        })
+    }
+
+    fn map_saved_search_row(row: &rusqlite::Row<'_>) -> Result<SavedSearch> {
+        Ok(SavedSearch {
+            name: row.get(0)?,
+            kind: row.get(1)?,
+            query: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    }
+
+    fn map_foreign_table_row(row: &rusqlite::Row<'_>) -> Result<ForeignTable> {
+        Ok(ForeignTable {
+            name: row.get(0)?,
+            uri: row.get(1)?,
+            attached_at: row.get(2)?,
+        })
+    }
+
+    fn map_sync_history_row(row: &rusqlite::Row<'_>) -> Result<SyncHistoryEntry> {
+        let state: String = row.get(4)?;
+        let rows: String = row.get(7)?;
+        Ok(SyncHistoryEntry {
+            id: row.get(0)?,
+            fetcher: row.get(1)?,
+            params_hash: row.get(2)?,
+            task_id: row.get(3)?,
+            state: state.parse()?,
+            started_at: row.get(5)?,
+            finished_at: row.get(6)?,
+            rows: serde_json::from_str(&rows)?,
+            error: row.get(8)?,
+        })
+    }
+
+    fn map_sync_run_row(row: &rusqlite::Row<'_>) -> Result<SyncRun> {
+        let graph_version: i64 = row.get(1)?;
+        let table_versions: String = row.get(2)?;
+        Ok(SyncRun {
+            run_id: row.get(0)?,
+            graph_version: graph_version.max(0) as u64,
+            table_versions: serde_json::from_str(&table_versions)?,
+            finished_at: row.get(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StorageConfig;
+    use crate::models::EntityReadiness;
+    use tempfile::tempdir;
+
+    fn setup() -> (Catalog, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path());
+        let catalog = Catalog::new(&config).unwrap();
+        catalog.initialize_schema().unwrap();
+        (catalog, dir)
+    }
+
+    #[test]
+    fn test_readiness_crud() {
+        let (catalog, _dir) = setup();
+
+        // Test insert
+        let readiness = EntityReadiness {
+            entity_uri: "test_uri".to_string(),
+            entity_type: "repo".to_string(),
+            last_synced_at: Some(12345),
+            ttl_seconds: Some(3600),
+            coverage_metrics: "{}".to_string(),
+        };
+        let result = catalog.upsert_readiness(&readiness);
+        assert!(result.is_ok());
+
+        // Test get
+        let fetched = catalog.get_readiness("test_uri").unwrap();
+        assert!(fetched.is_some());
+        let fetched = fetched.unwrap();
+        assert_eq!(fetched.entity_uri, "test_uri");
+        assert_eq!(fetched.last_synced_at, Some(12345));
+
+        // Test update
+        let updated_readiness = EntityReadiness {
+            entity_uri: "test_uri".to_string(),
+            entity_type: "repo".to_string(),
+            last_synced_at: Some(54321),
+            ttl_seconds: Some(3600),
+            coverage_metrics: "{}".to_string(),
+        };
+        let result = catalog.upsert_readiness(&updated_readiness);
+        assert!(result.is_ok());
+
+        let fetched_updated = catalog.get_readiness("test_uri").unwrap().unwrap();
+        assert_eq!(fetched_updated.last_synced_at, Some(54321));
+    }
+
+    #[test]
+    fn webhook_deliveries_are_claimed_once() {
+        let (catalog, _dir) = setup();
+        assert!(catalog.claim_webhook_delivery("a", 100, 0).unwrap());
+        assert!(!catalog.claim_webhook_delivery("a", 101, 0).unwrap());
+        assert!(catalog.release_webhook_delivery("a").unwrap());
+        assert!(catalog.claim_webhook_delivery("a", 102, 0).unwrap());
+        // Expired deliveries can be claimed again.
+        assert!(catalog.claim_webhook_delivery("a", 200, 150).unwrap());
+    }
+
+    #[test]
+    fn leases_have_one_holder_until_they_expire() {
+        let (catalog, _dir) = setup();
+        assert!(catalog.acquire_lease("scheduler", "a", 100, 130).unwrap());
+        assert!(!catalog.acquire_lease("scheduler", "b", 110, 140).unwrap());
+        assert!(catalog.acquire_lease("scheduler", "a", 120, 150).unwrap());
+        assert!(!catalog.acquire_lease("scheduler", "b", 140, 170).unwrap());
+        assert!(catalog.acquire_lease("scheduler", "b", 150, 180).unwrap());
+        assert!(!catalog.acquire_lease("scheduler", "a", 160, 190).unwrap());
+    }
+
+    #[test]
+    fn graph_version_only_moves_forward() {
+        let (catalog, _dir) = setup();
+        assert_eq!(catalog.get_graph_version().unwrap(), 0);
+        assert_eq!(catalog.bump_graph_version().unwrap(), 1);
+        assert_eq!(catalog.bump_graph_version().unwrap(), 2);
+        assert_eq!(catalog.get_graph_version().unwrap(), 2);
+    }
+
+    #[test]
+    fn sync_history_records_outcomes_newest_first() {
+        let (catalog, _dir) = setup();
+        let first = catalog.start_sync_history("git", "a1", 7).unwrap();
+        let second = catalog.start_sync_history("econ", "b2", 8).unwrap();
+        let rows = BTreeMap::from([("project".to_string(), 3)]);
+        catalog
+            .finish_sync_history(first, SyncJobState::Succeeded, &rows, None)
+            .unwrap();
+
+        let entry = catalog.get_sync_history(first).unwrap().unwrap();
+        assert_eq!(entry.state, SyncJobState::Succeeded);
+        assert_eq!(entry.rows, rows);
+        assert!(entry.finished_at.is_some());
+        assert!(catalog.get_sync_history(second + 1).unwrap().is_none());
+
+        let all = catalog.list_sync_history(None, None, 10).unwrap();
+        let ids: Vec<i64> = all.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![second, first]);
+        let git = catalog.list_sync_history(Some("git"), None, 10).unwrap();
+        assert_eq!(git.len(), 1);
+        assert!(catalog
+            .list_sync_history(None, Some(first), 10)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(catalog.fail_interrupted_sync_history().unwrap(), 1);
+        let interrupted = catalog.get_sync_history(second).unwrap().unwrap();
+        assert_eq!(interrupted.state, SyncJobState::Failed);
+        assert_eq!(interrupted.error.as_deref(), Some("interrupted by restart"));
+    }
+
+    #[test]
+    fn sync_runs_keep_their_table_versions() {
+        let (catalog, _dir) = setup();
+        let first = BTreeMap::from([("silver/entities/project".to_string(), 0)]);
+        let second = BTreeMap::from([
+            ("silver/entities/project".to_string(), 1),
+            ("silver/edges/calls".to_string(), 0),
+        ]);
+        let first_id = catalog.record_sync_run(1, &first).unwrap();
+        let second_id = catalog.record_sync_run(2, &second).unwrap();
+        assert!(second_id > first_id);
+
+        let run = catalog.get_sync_run(first_id).unwrap().unwrap();
+        assert_eq!(run.graph_version, 1);
+        assert_eq!(run.table_versions, first);
+        assert!(catalog.get_sync_run(second_id + 1).unwrap().is_none());
+
+        let runs = catalog.list_sync_runs(10).unwrap();
+        let ids: Vec<i64> = runs.iter().map(|run| run.run_id).collect();
+        assert_eq!(ids, vec![second_id, first_id]);
+        assert_eq!(runs[0].table_versions, second);
+    }
+
+    #[test]
+    fn sync_schedules_keep_their_last_run_when_replaced() {
+        let (catalog, _dir) = setup();
+        let mut schedule = SyncSchedule {
+            name: "nightly".to_string(),
+            cron: "0 3 * * *".to_string(),
+            request: "{}".to_string(),
+            jitter_secs: 60,
+            enabled: true,
+            next_run_at: 100,
+            last_run_at: None,
+            last_job_id: None,
+            last_outcome: None,
+            created_at: 0,
+            updated_at: 0,
+        };
+        catalog.put_sync_schedule(&schedule).unwrap();
+        assert_eq!(catalog.due_sync_schedules(99).unwrap().len(), 0);
+        assert_eq!(catalog.due_sync_schedules(100).unwrap().len(), 1);
+
+        catalog
+            .record_sync_schedule_run("nightly", 100, Some(7), "queued", 200)
+            .unwrap();
+        schedule.cron = "0 4 * * *".to_string();
+        schedule.next_run_at = 300;
+        catalog.put_sync_schedule(&schedule).unwrap();
+        let stored = catalog.get_sync_schedule("nightly").unwrap().unwrap();
+        assert_eq!(stored.cron, "0 4 * * *");
+        assert_eq!(stored.next_run_at, 300);
+        assert_eq!(stored.last_job_id, Some(7));
+        assert_eq!(stored.last_outcome.as_deref(), Some("queued"));
+
+        schedule.name = "disabled".to_string();
+        schedule.enabled = false;
+        catalog.put_sync_schedule(&schedule).unwrap();
+        assert_eq!(catalog.due_sync_schedules(1_000).unwrap().len(), 1);
+        let names: Vec<String> = catalog
+            .list_sync_schedules()
+            .unwrap()
+            .into_iter()
+            .map(|schedule| schedule.name)
+            .collect();
+        assert_eq!(names, vec!["disabled", "nightly"]);
+
+        assert!(catalog.delete_sync_schedule("nightly").unwrap());
+        assert!(!catalog.delete_sync_schedule("nightly").unwrap());
+        assert!(catalog.get_sync_schedule("nightly").unwrap().is_none());
+    }
+
+    #[test]
+    fn saved_searches_keep_their_creation_time_when_replaced() {
+        let (catalog, _dir) = setup();
+        let mut search = SavedSearch {
+            name: "rust-graphs".to_string(),
+            kind: "hybrid".to_string(),
+            query: r#"{"q":"graph"}"#.to_string(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        catalog.put_saved_search(&search).unwrap();
+        let created_at = catalog
+            .get_saved_search("rust-graphs")
+            .unwrap()
+            .unwrap()
+            .created_at;
+
+        search.kind = "graph".to_string();
+        catalog.put_saved_search(&search).unwrap();
+        let stored = catalog.get_saved_search("rust-graphs").unwrap().unwrap();
+        assert_eq!(stored.kind, "graph");
+        assert_eq!(stored.created_at, created_at);
+        assert_eq!(catalog.list_saved_searches().unwrap().len(), 1);
+
+        assert!(catalog.delete_saved_search("rust-graphs").unwrap());
+        assert!(!catalog.delete_saved_search("rust-graphs").unwrap());
+        assert!(catalog.get_saved_search("rust-graphs").unwrap().is_none());
+    }
+
+    #[test]
+    fn bookmarks_replace_their_note_in_place() {
+        let (catalog, _dir) = setup();
+        let mut bookmark = Bookmark {
+            node_id: "node-b".to_string(),
+            note: Some("first look".to_string()),
+            created_at: 0,
+            updated_at: 0,
+        };
+        catalog.put_bookmark(&bookmark).unwrap();
+        bookmark.node_id = "node-a".to_string();
+        catalog.put_bookmark(&bookmark).unwrap();
+
+        bookmark.note = None;
+        catalog.put_bookmark(&bookmark).unwrap();
+        let stored = catalog.get_bookmark("node-a").unwrap().unwrap();
+        assert_eq!(stored.note, None);
+        let ids: Vec<String> = catalog
+            .list_bookmarks()
+            .unwrap()
+            .into_iter()
+            .map(|bookmark| bookmark.node_id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+
+        assert!(catalog.delete_bookmark("node-b").unwrap());
+        assert!(!catalog.delete_bookmark("node-b").unwrap());
+    }
+
+    #[test]
+    fn test_task_log_crud() {
+        let (catalog, _dir) = setup();
+
+        // Test create
+        let task_id = catalog.create_task_log("test_task");
+        assert!(task_id.is_ok());
+        let task_id = task_id.unwrap();
+        assert_eq!(task_id, 1);
+
+        // Test update
+        let result = catalog.update_task_log_status(task_id, "SUCCESS", "Done");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_ingestion_offsets_crud() {
+        let (catalog, _dir) = setup();
+
+        let ensure = catalog.ensure_ingestion_offset(
+            "silver/entities/project",
+            "project",
+            crate::fetch::EntityCategory::Node,
+            &vec!["url".to_string()],
+        );
+        assert!(ensure.is_ok());
+
+        let offset = catalog
+            .get_ingestion_offset("silver/entities/project")
+            .unwrap()
+            .unwrap();
+        assert_eq!(offset.table_path, "silver/entities/project");
+        assert_eq!(offset.entity_type, "project");
+        assert_eq!(offset.category, crate::fetch::EntityCategory::Node);
+        assert_eq!(offset.primary_keys, vec!["url".to_string()]);
+        assert_eq!(offset.last_version, -1);
+
+        let list = catalog.list_ingestion_offsets().unwrap();
+        assert_eq!(list.len(), 1);
+
+        catalog
+            .update_ingestion_offset("silver/entities/project", 5)
+            .unwrap();
+        let updated = catalog
+            .get_ingestion_offset("silver/entities/project")
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.last_version, 5);
+
+        catalog
+            .ensure_ingestion_offset(
+                "silver/vectors/readme_chunk",
+                "readme_chunk",
+                crate::fetch::EntityCategory::Vector,
+                &vec!["id".to_string()],
+            )
+            .unwrap();
+
+        let vector_offset = catalog
+            .get_ingestion_offset("silver/vectors/readme_chunk")
+            .unwrap()
+            .unwrap();
+        assert_eq!(vector_offset.category, crate::fetch::EntityCategory::Vector);
+        assert_eq!(vector_offset.primary_keys, vec!["id".to_string()]);
+
+        let list = catalog.list_ingestion_offsets().unwrap();
+        assert_eq!(list.len(), 2);
+        let counts = catalog.count_ingestion_offsets_by_category().unwrap();
+        assert_eq!(counts.get("node"), Some(&1));
+        assert_eq!(counts.get("vector"), Some(&1));
+        assert_eq!(counts.get("edge"), None);
+    }
+
+    #[test]
+    fn test_api_usage_buckets() {
+        let (catalog, _dir) = setup();
+
+        // 2024-01-01 (Monday) and 2024-01-03 fall into the same week, 2024-02-01 does not.
+        let jan_1 = 1_704_067_200;
+        let jan_3 = jan_1 + 2 * 86_400 + 3_600;
+        let feb_1 = 1_706_745_600;
+
+        catalog
+            .record_api_usage_at("/api/status", "key-a", jan_1)
+            .unwrap();
+        catalog
+            .record_api_usage_at("/api/status", "key-a", jan_1 + 60)
+            .unwrap();
+        catalog
+            .record_api_usage_at("/api/status", "key-a", jan_3)
+            .unwrap();
+        catalog
+            .record_api_usage_at("/api/status", "key-b", feb_1)
+            .unwrap();
+
+        let daily = catalog
+            .aggregate_api_usage(UsageBucket::Day, None, None, None, None)
+            .unwrap();
+        assert_eq!(daily.len(), 3);
+        assert_eq!(daily[0].bucket_start, jan_1);
+        assert_eq!(daily[0].count, 2);
+
+        let weekly = catalog
+            .aggregate_api_usage(
+                UsageBucket::Week,
+                None,
+                None,
+                Some("/api/status"),
+                Some("key-a"),
+            )
+            .unwrap();
+        assert_eq!(weekly.len(), 1);
+        assert_eq!(weekly[0].bucket_start, jan_1);
+        assert_eq!(weekly[0].count, 3);
+
+        let monthly = catalog
+            .aggregate_api_usage(UsageBucket::Month, Some(jan_1 + 86_400), None, None, None)
+            .unwrap();
+        assert_eq!(monthly.len(), 2);
+        assert_eq!(monthly[0].bucket_start, jan_1);
+        assert_eq!(monthly[0].count, 1);
+        assert_eq!(monthly[1].bucket_start, feb_1);
+    }
+
+    #[test]
+    fn test_cost_records_aggregate_by_scope() {
+        let (catalog, _dir) = setup();
+        let summary = CostSummary {
+            provider: "openai".to_string(),
+            model: Some("text-embedding-3-small".to_string()),
+            requests: 2,
+            tokens: 1500,
+            cost_usd: 0.03,
+        };
+        catalog
+            .insert_cost_record("sync", Some("1"), &summary)
+            .unwrap();
+        catalog
+            .insert_cost_record("sync", Some("2"), &summary)
+            .unwrap();
+        catalog
+            .insert_cost_record("search", Some("Project"), &summary)
+            .unwrap();
+
+        let per_task = catalog.list_cost_records("sync", "1").unwrap();
+        assert_eq!(per_task.len(), 1);
+        assert_eq!(per_task[0].tokens, 1500);
+
+        let sync_rows = catalog
+            .aggregate_costs(UsageBucket::Day, None, None, Some("sync"))
+            .unwrap();
+        assert_eq!(sync_rows.len(), 1);
+        assert_eq!(sync_rows[0].requests, 4);
+        assert_eq!(sync_rows[0].tokens, 3000);
+        assert!((sync_rows[0].cost_usd - 0.06).abs() < 1e-9);
+
+        let all_rows = catalog
+            .aggregate_costs(UsageBucket::Month, None, None, None)
+            .unwrap();
+        assert_eq!(all_rows.len(), 2);
+    }
+
+    #[test]
+    fn test_sync_job_lifecycle() {
+        let (catalog, _dir) = setup();
+        let first = catalog
+            .create_sync_job("mock", r#"{"fetcher":"mock"}"#)
+            .unwrap();
+        let second = catalog.create_sync_job("mock", "{}").unwrap();
+
+        let job = catalog.get_sync_job(first).unwrap().unwrap();
+        assert_eq!(job.state, SyncJobState::Queued);
+        assert!(job.started_at.is_none());
+
+        catalog.mark_sync_job_running(first).unwrap();
+        catalog
+            .update_sync_job_progress(first, r#"{"phase":"fetch"}"#)
+            .unwrap();
+        catalog
+            .finish_sync_job(first, SyncJobState::Succeeded, Some(7), Some("{}"), None)
+            .unwrap();
+        let job = catalog.get_sync_job(first).unwrap().unwrap();
+        assert_eq!(job.state, SyncJobState::Succeeded);
+        assert_eq!(job.task_id, Some(7));
+        assert_eq!(job.progress.as_deref(), Some(r#"{"phase":"fetch"}"#));
+        assert!(job.finished_at.is_some());
+
+        catalog.mark_sync_job_running(second).unwrap();
+        assert_eq!(catalog.fail_interrupted_sync_jobs().unwrap(), 1);
+
+        let recent = catalog.list_sync_jobs(None, 10).unwrap();
+        assert_eq!(
+            recent.iter().map(|job| job.job_id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        let failed = catalog
+            .list_sync_jobs(Some(SyncJobState::Failed), 10)
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error.as_deref(), Some("interrupted by restart"));
+
+        catalog
+            .requeue_sync_job(second, r#"{"retry":true}"#)
+            .unwrap();
+        let requeued = catalog.get_sync_job(second).unwrap().unwrap();
+        assert_eq!(requeued.state, SyncJobState::Queued);
+        assert_eq!(requeued.request, r#"{"retry":true}"#);
+        assert!(requeued.error.is_none() && requeued.finished_at.is_none());
+        assert!(catalog.get_sync_job(999).unwrap().is_none());
     }
 }
@@ -1,11 +1,65 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::embedding::{EmbeddingModelConfig, OnnxConfig};
+use crate::engine_map::EngineConfig;
+use crate::lake_store::LakeStoreConfig;
+use crate::redaction::RedactionConfig;
+use crate::rerank::RerankerConfig;
+use crate::sql_engine::SqlEngine;
+use crate::table_format::TableFormat;
+use crate::throttle::ProviderLimits;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct StorageConfig {
     pub lake_path: PathBuf,
+    /// Object store the Delta tables are kept in instead of `lake_path`.
+    #[serde(default)]
+    pub lake_store: Option<LakeStoreConfig>,
     pub catalog_path: PathBuf,
     pub engine_path: PathBuf,
+    /// Where named snapshots are kept; defaults to `snapshots` next to the lake.
+    #[serde(default)]
+    pub snapshot_path: Option<PathBuf>,
+    /// Per-1k-token USD prices overriding the built-in table, keyed by
+    /// `provider[:model]`; rerank endpoints are priced as `http[:model]`.
+    #[serde(default)]
+    pub token_pricing: HashMap<String, f64>,
+    /// Requests and tokens per minute allowed to the embedding provider, keyed by `provider[:model]`.
+    #[serde(default)]
+    pub embedding_limits: HashMap<String, ProviderLimits>,
+    /// Secret patterns scrubbed from ingested text before storage and embedding.
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Local ONNX model used for embeddings instead of the default provider.
+    #[serde(default)]
+    pub onnx: Option<OnnxConfig>,
+    /// Further embedding providers a search may pick by model name instead of
+    /// the default one.
+    #[serde(default)]
+    pub embedding_models: Vec<EmbeddingModelConfig>,
+    /// Cross-encoder that hybrid searches can rerank their candidates with.
+    #[serde(default)]
+    pub reranker: Option<RerankerConfig>,
+    /// Format of the silver and gold tables; Delta unless configured otherwise.
+    #[serde(default)]
+    pub table_format: TableFormat,
+    /// Engine for ad-hoc SQL over lake tables; DataFusion unless configured otherwise.
+    #[serde(default)]
+    pub sql_engine: SqlEngine,
+    /// LMDB tuning of the graph engine: map size and growth, commit flushing,
+    /// a cap on concurrent readers.
+    #[serde(default)]
+    pub engine: EngineConfig,
+    /// Upgrade a store written with an older on-disk layout when it opens,
+    /// after saving it as a snapshot. When off, such a store is not opened.
+    #[serde(default = "default_auto_migrate")]
+    pub auto_migrate: bool,
+}
+
+fn default_auto_migrate() -> bool {
+    true
 }
 
 impl StorageConfig {
@@ -13,8 +67,32 @@ impl StorageConfig {
         let base_path = base_path.into();
         Self {
             lake_path: base_path.join("lake"),
+            lake_store: None,
             catalog_path: base_path.join("catalog.sqlite"),
             engine_path: base_path.join("engine"),
+            snapshot_path: Some(base_path.join("snapshots")),
+            token_pricing: HashMap::new(),
+            embedding_limits: HashMap::new(),
+            redaction: RedactionConfig::default(),
+            onnx: None,
+            embedding_models: Vec::new(),
+            reranker: None,
+            table_format: TableFormat::Delta,
+            sql_engine: SqlEngine::DataFusion,
+            engine: EngineConfig::default(),
+            auto_migrate: default_auto_migrate(),
         }
     }
+
+    /// Whether the lake tables are Delta tables in `lake_path`, which
+    /// snapshots, archives and file checksums copy and read directly.
+    pub fn local_delta_lake(&self) -> bool {
+        self.table_format == TableFormat::Delta && self.lake_store.is_none()
+    }
+
+    pub fn snapshot_root(&self) -> PathBuf {
+        self.snapshot_path
+            .clone()
+            .unwrap_or_else(|| self.lake_path.with_file_name("snapshots"))
+    }
 }
@@ -0,0 +1,329 @@
+//! Cross-checks of what the catalog, the lake tables and the engine each
+//! record about the same data.
+//!
+//! A sync writes to the lake first and to the engine after, so a crash between
+//! the two, a restored backup of one of them or a table edited by hand leaves
+//! them disagreeing without any error until a lookup comes back empty. A check
+//! looks for catalog offsets past their table's latest version, `silver/index`
+//! ids the engine has no node for, edge rows with an endpoint the engine lacks,
+//! and vector rows no edge leads to. Offsets behind their table are expected:
+//! syncs feed the engine directly, and only the full ETL from the lake moves
+//! them.
+//!
+//! With repair, offsets ahead are rewound so the next full ETL replays their
+//! table, entity tables with unresolved ids are loaded into the engine again
+//! before edges are checked, and dangling edges and orphan vectors are
+//! deleted. Tables the lake has lost are only reported.
+
+use crate::catalog::Catalog;
+use crate::errors::{Result, StorageError};
+use crate::lake::Lake;
+use crate::models::{ConsistencyIssue, ConsistencyProblem, ConsistencyReport, TableSummary};
+use crate::schema_registry::vector_rules;
+use crate::sync::FStorageSynchronizer;
+use helix_db::helix_engine::storage_core::storage_methods::StorageMethods;
+use helix_db::helix_engine::types::{GraphError, VectorError};
+use helix_db::helix_engine::vector_core::hnsw::HNSW;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use uuid::Uuid;
+
+const INDEX_PREFIX: &str = "silver/index";
+const EDGE_PREFIX: &str = "silver/edges";
+const VECTOR_PREFIX: &str = "silver/vectors";
+
+/// Runs every check in turn and, with `repair`, fixes what each one found
+/// before the next starts.
+pub async fn check(
+    catalog: &Catalog,
+    lake: &Lake,
+    synchronizer: &FStorageSynchronizer,
+    repair: bool,
+) -> Result<ConsistencyReport> {
+    let mut report = ConsistencyReport {
+        checked_at: chrono::Utc::now().timestamp(),
+        repair,
+        offsets: 0,
+        index_ids: 0,
+        edges: 0,
+        vectors: 0,
+        issues: Vec::new(),
+    };
+    check_offsets(catalog, lake, repair, &mut report).await?;
+    check_index_ids(lake, synchronizer, repair, &mut report).await?;
+    let endpoints = check_edges(lake, repair, &mut report).await?;
+    check_vectors(lake, &endpoints, repair, &mut report).await?;
+    Ok(report)
+}
+
+async fn check_offsets(
+    catalog: &Catalog,
+    lake: &Lake,
+    repair: bool,
+    report: &mut ConsistencyReport,
+) -> Result<()> {
+    for offset in catalog.list_ingestion_offsets()? {
+        report.offsets += 1;
+        let Some(version) = lake.table_version(&offset.table_path).await? else {
+            report.issues.push(ConsistencyIssue {
+                problem: ConsistencyProblem::MissingTable,
+                table_path: offset.table_path,
+                id: None,
+                detail: format!(
+                    "the catalog tracks it as the {} table of {}",
+                    offset.category.as_str(),
+                    offset.entity_type
+                ),
+                repaired: false,
+            });
+            continue;
+        };
+        if offset.last_version <= version {
+            continue;
+        }
+        if repair {
+            catalog.update_ingestion_offset(&offset.table_path, -1)?;
+        }
+        report.issues.push(ConsistencyIssue {
+            problem: ConsistencyProblem::OffsetAhead,
+            detail: format!(
+                "ingested up to version {}, but the table is at version {version}",
+                offset.last_version
+            ),
+            table_path: offset.table_path,
+            id: None,
+            repaired: repair,
+        });
+    }
+    Ok(())
+}
+
+async fn check_index_ids(
+    lake: &Lake,
+    synchronizer: &FStorageSynchronizer,
+    repair: bool,
+    report: &mut ConsistencyReport,
+) -> Result<()> {
+    for table in lake.list_tables(INDEX_PREFIX).await? {
+        let Some(entity_type) = table_entity(&table, INDEX_PREFIX) else {
+            continue;
+        };
+        let rows = lake
+            .table_sql(
+                &table.table_path,
+                "SELECT DISTINCT id FROM {{table}} WHERE id IS NOT NULL",
+            )
+            .await?;
+        let ids = string_column(&rows, "id");
+        report.index_ids += ids.len() as u64;
+        let missing = missing_from_engine(lake, ids, false).await?;
+        if missing.is_empty() {
+            continue;
+        }
+
+        let entity_table = format!("silver/entities/{entity_type}");
+        let still_missing = if repair {
+            match synchronizer.reload_table_into_engine(&entity_table).await {
+                Ok(rows) => {
+                    log::info!("{entity_table}: loaded {rows} rows into the engine again");
+                    missing_from_engine(lake, missing.clone(), false).await?
+                }
+                Err(StorageError::NotFound(detail)) => {
+                    log::warn!("{entity_table}: cannot reload: {detail}");
+                    missing.clone()
+                }
+                Err(err) => return Err(err),
+            }
+        } else {
+            missing.clone()
+        };
+        for id in missing {
+            let repaired = !still_missing.contains(&id);
+            report.issues.push(ConsistencyIssue {
+                problem: ConsistencyProblem::UnresolvedIndexId,
+                table_path: table.table_path.clone(),
+                detail: if repaired {
+                    format!("node loaded again from {entity_table}")
+                } else {
+                    format!("the engine has no {entity_type} node with this id")
+                },
+                id: Some(id),
+                repaired,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reports edge rows with an endpoint the engine has neither as a node nor as
+/// a vector. Returns every endpoint of every edge row.
+async fn check_edges(
+    lake: &Lake,
+    repair: bool,
+    report: &mut ConsistencyReport,
+) -> Result<BTreeSet<String>> {
+    let mut endpoints = BTreeSet::new();
+    for table in lake.list_tables(EDGE_PREFIX).await? {
+        if table_entity(&table, EDGE_PREFIX).is_none()
+            || !has_columns(&table, &["id", "from_node_id", "to_node_id"])
+        {
+            continue;
+        }
+        let rows = lake
+            .table_sql(
+                &table.table_path,
+                "SELECT id, from_node_id, to_node_id FROM {{table}}",
+            )
+            .await?;
+        report.edges += rows.len() as u64;
+        let table_endpoints: BTreeSet<String> = string_column(&rows, "from_node_id")
+            .into_iter()
+            .chain(string_column(&rows, "to_node_id"))
+            .collect();
+        let missing = missing_from_engine(lake, table_endpoints.clone(), true).await?;
+        endpoints.extend(table_endpoints);
+        if missing.is_empty() {
+            continue;
+        }
+
+        let mut dangling: BTreeMap<String, String> = BTreeMap::new();
+        for row in &rows {
+            let text = |column: &str| row.get(column).and_then(JsonValue::as_str);
+            let Some(id) = text("id") else {
+                continue;
+            };
+            let endpoint = [text("from_node_id"), text("to_node_id")]
+                .into_iter()
+                .flatten()
+                .find(|endpoint| missing.contains(*endpoint));
+            if let Some(endpoint) = endpoint {
+                dangling.insert(id.to_string(), endpoint.to_string());
+            }
+        }
+        if repair {
+            let ids: BTreeSet<String> = dangling.keys().cloned().collect();
+            lake.delete_edges(&table.table_path, &ids).await?;
+        }
+        report
+            .issues
+            .extend(dangling.into_iter().map(|(id, endpoint)| ConsistencyIssue {
+                problem: ConsistencyProblem::DanglingEdge,
+                table_path: table.table_path.clone(),
+                id: Some(id),
+                detail: format!("endpoint {endpoint} is not in the engine"),
+                repaired: repair,
+            }));
+    }
+    Ok(endpoints)
+}
+
+/// Reports vector rows that no edge row leads to, for vector types whose
+/// rules draw edges to them.
+async fn check_vectors(
+    lake: &Lake,
+    endpoints: &BTreeSet<String>,
+    repair: bool,
+    report: &mut ConsistencyReport,
+) -> Result<()> {
+    for table in lake.list_tables(VECTOR_PREFIX).await? {
+        let Some(vector_type) = table_entity(&table, VECTOR_PREFIX) else {
+            continue;
+        };
+        if !has_columns(&table, &["id"]) {
+            continue;
+        }
+        let rows = lake
+            .table_sql(
+                &table.table_path,
+                "SELECT id FROM {{table}} WHERE id IS NOT NULL",
+            )
+            .await?;
+        report.vectors += rows.len() as u64;
+        if vector_rules(vector_type).is_none_or(|rules| rules.rules.is_empty()) {
+            continue;
+        }
+        let orphans: BTreeSet<String> = string_column(&rows, "id")
+            .into_iter()
+            .filter(|id| !endpoints.contains(id))
+            .collect();
+        if orphans.is_empty() {
+            continue;
+        }
+        if repair {
+            lake.delete_vectors(vector_type, &orphans).await?;
+        }
+        report
+            .issues
+            .extend(orphans.into_iter().map(|id| ConsistencyIssue {
+                problem: ConsistencyProblem::OrphanVector,
+                table_path: table.table_path.clone(),
+                id: Some(id),
+                detail: format!("no edge leads to this {vector_type} vector"),
+                repaired: repair,
+            }));
+    }
+    Ok(())
+}
+
+/// Entity type of a table directly under `prefix`.
+fn table_entity<'a>(table: &'a TableSummary, prefix: &str) -> Option<&'a str> {
+    table
+        .table_path
+        .strip_prefix(prefix)?
+        .strip_prefix('/')
+        .filter(|rest| !rest.contains('/'))
+}
+
+fn has_columns(table: &TableSummary, columns: &[&str]) -> bool {
+    columns
+        .iter()
+        .all(|column| table.columns.iter().any(|field| field.name == *column))
+}
+
+fn string_column(rows: &[HashMap<String, JsonValue>], column: &str) -> BTreeSet<String> {
+    rows.iter()
+        .filter_map(|row| row.get(column).and_then(JsonValue::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Which of `ids` the engine has no node for, nor a vector when `vectors`.
+/// Ids that are not UUIDs are always missing.
+async fn missing_from_engine(
+    lake: &Lake,
+    ids: BTreeSet<String>,
+    vectors: bool,
+) -> Result<BTreeSet<String>> {
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+    lake.read_graph(move |storage, txn| {
+        let mut missing = BTreeSet::new();
+        for id in ids {
+            let Ok(uuid) = Uuid::parse_str(&id) else {
+                missing.insert(id);
+                continue;
+            };
+            let key = uuid.as_u128();
+            let node = match storage.get_node(txn, &key) {
+                Ok(_) => true,
+                Err(GraphError::NodeNotFound) => false,
+                Err(err) => return Err(StorageError::Graph(err)),
+            };
+            let vector = !node
+                && vectors
+                && match storage.vectors.get_vector(txn, key, 0, true) {
+                    Ok(_) => true,
+                    Err(VectorError::VectorNotFound(_)) | Err(VectorError::EntryPointNotFound) => {
+                        false
+                    }
+                    Err(err) => return Err(StorageError::Graph(err.into())),
+                };
+            if !node && !vector {
+                missing.insert(id);
+            }
+        }
+        Ok(missing)
+    })
+    .await
+}
@@ -0,0 +1,252 @@
+//! Token and request accounting for embedding providers and rerankers.
+//!
+//! A [`UsageMeter`] is created per sync run or search call; wrapping the shared
+//! provider in a [`MeteredEmbeddingProvider`] attributes every `embed` call to it.
+//! Tokens are those the provider reports having billed, or an estimate from
+//! the text's length when its response does not say.
+
+use crate::budget::BudgetTracker;
+use crate::embedding::EmbeddingProvider;
+use crate::errors::Result;
+use crate::models::CostSummary;
+use crate::rerank::Reranker;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// USD price per 1k tokens, keyed by `provider:model` or bare `provider`.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    per_1k_tokens: HashMap<String, f64>,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        let per_1k_tokens = [
+            ("openai:text-embedding-ada-002", 0.0001),
+            ("openai:text-embedding-3-small", 0.00002),
+            ("openai:text-embedding-3-large", 0.00013),
+            ("fastembed", 0.0),
+            ("null", 0.0),
+        ]
+        .into_iter()
+        .map(|(key, price)| (key.to_string(), price))
+        .collect();
+        Self { per_1k_tokens }
+    }
+}
+
+impl PricingTable {
+    /// Returns the default table with `overrides` applied on top.
+    pub fn with_overrides(overrides: &HashMap<String, f64>) -> Self {
+        let mut table = Self::default();
+        for (key, price) in overrides {
+            table.per_1k_tokens.insert(key.clone(), *price);
+        }
+        table
+    }
+
+    pub fn price_per_1k(&self, provider: &str, model: Option<&str>) -> f64 {
+        model
+            .and_then(|model| self.per_1k_tokens.get(&format!("{provider}:{model}")))
+            .or_else(|| self.per_1k_tokens.get(provider))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// Rough token estimate (~4 characters per token) used when providers do not report usage.
+pub fn approximate_tokens(texts: &[String]) -> u64 {
+    texts
+        .iter()
+        .map(|text| (text.chars().count() as u64).div_ceil(4).max(1))
+        .sum()
+}
+
+#[derive(Debug)]
+pub struct UsageMeter {
+    provider: String,
+    model: Option<String>,
+    requests: AtomicU64,
+    tokens: AtomicU64,
+}
+
+impl UsageMeter {
+    pub fn new(provider: impl Into<String>, model: Option<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            model,
+            requests: AtomicU64::new(0),
+            tokens: AtomicU64::new(0),
+        }
+    }
+
+    pub fn for_provider(provider: &dyn EmbeddingProvider) -> Self {
+        Self::new(
+            provider.provider_name(),
+            provider.model_name().map(str::to_string),
+        )
+    }
+
+    pub fn for_reranker(reranker: &dyn Reranker) -> Self {
+        Self::new(
+            reranker.provider_name(),
+            reranker.model_name().map(str::to_string),
+        )
+    }
+
+    /// Records a request for `texts` at their estimated token count.
+    pub fn record(&self, texts: &[String]) {
+        self.record_tokens(approximate_tokens(texts));
+    }
+
+    /// Records a request that was billed `tokens`.
+    pub fn record_tokens(&self, tokens: u64) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.tokens.fetch_add(tokens, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn tokens(&self) -> u64 {
+        self.tokens.load(Ordering::Relaxed)
+    }
+
+    /// Cost of the usage so far plus `extra_tokens` that are about to be spent.
+    pub fn projected_cost(&self, extra_tokens: u64, pricing: &PricingTable) -> f64 {
+        let price = pricing.price_per_1k(&self.provider, self.model.as_deref());
+        (self.tokens() + extra_tokens) as f64 / 1000.0 * price
+    }
+
+    pub fn summary(&self, pricing: &PricingTable) -> CostSummary {
+        CostSummary {
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            requests: self.requests(),
+            tokens: self.tokens(),
+            cost_usd: self.projected_cost(0, pricing),
+        }
+    }
+}
+
+/// Delegates to an inner provider while recording usage on a shared meter.
+pub struct MeteredEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    meter: Arc<UsageMeter>,
+    budget: Option<Arc<BudgetTracker>>,
+}
+
+impl MeteredEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, meter: Arc<UsageMeter>) -> Self {
+        Self {
+            inner,
+            meter,
+            budget: None,
+        }
+    }
+
+    /// Refuses further batches once `budget` is exhausted.
+    pub fn with_budget(mut self, budget: Arc<BudgetTracker>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MeteredEmbeddingProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        if texts.is_empty() {
+            return self.inner.embed(texts).await;
+        }
+        if let Some(budget) = &self.budget {
+            budget.check_embedding(&texts)?;
+            budget.charge_requests(1);
+        }
+        let estimate = approximate_tokens(&texts);
+        let embedded = self.inner.embed_with_usage(texts).await;
+        // A failed request may still have been billed; count it as estimated.
+        let tokens = match &embedded {
+            Ok((_, Some(reported))) => *reported,
+            _ => estimate,
+        };
+        self.meter.record_tokens(tokens);
+        Ok(embedded?.0)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedding::NullEmbeddingProvider;
+
+    #[tokio::test]
+    async fn metered_provider_prices_usage() {
+        let meter = Arc::new(UsageMeter::new(
+            "openai",
+            Some("text-embedding-ada-002".into()),
+        ));
+        let provider =
+            MeteredEmbeddingProvider::new(Arc::new(NullEmbeddingProvider), Arc::clone(&meter));
+
+        provider
+            .embed(vec!["a".repeat(4000), "b".repeat(3)])
+            .await
+            .unwrap();
+        provider.embed(Vec::new()).await.unwrap();
+
+        let summary = meter.summary(&PricingTable::default());
+        assert_eq!(summary.requests, 1);
+        assert_eq!(summary.tokens, 1001);
+        assert!((summary.cost_usd - 0.0001001).abs() < 1e-12);
+
+        let mut overrides = HashMap::new();
+        overrides.insert("openai".to_string(), 1.0);
+        overrides.insert("openai:text-embedding-ada-002".to_string(), 2.0);
+        let pricing = PricingTable::with_overrides(&overrides);
+        assert_eq!(
+            pricing.price_per_1k("openai", Some("text-embedding-ada-002")),
+            2.0
+        );
+        assert_eq!(pricing.price_per_1k("openai", Some("other")), 1.0);
+        assert_eq!(pricing.price_per_1k("unknown", None), 0.0);
+    }
+
+    struct ReportingProvider;
+
+    #[async_trait]
+    impl EmbeddingProvider for ReportingProvider {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+            Ok(vec![vec![]; texts.len()])
+        }
+
+        async fn embed_with_usage(
+            &self,
+            texts: Vec<String>,
+        ) -> Result<(Vec<Vec<f64>>, Option<u64>)> {
+            Ok((vec![vec![]; texts.len()], Some(7)))
+        }
+    }
+
+    #[tokio::test]
+    async fn reported_usage_replaces_the_estimate() {
+        let meter = Arc::new(UsageMeter::new("openai", None));
+        let provider =
+            MeteredEmbeddingProvider::new(Arc::new(ReportingProvider), Arc::clone(&meter));
+
+        provider.embed(vec!["a".repeat(4000)]).await.unwrap();
+
+        assert_eq!(meter.requests(), 1);
+        assert_eq!(meter.tokens(), 7);
+    }
+}
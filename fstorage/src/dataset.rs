@@ -0,0 +1,312 @@
+//! Project datasets: one project's part of the lake as Parquet files plus a
+//! `manifest.json`, for handing a project from one store to another.
+//!
+//! A dataset holds, in their lake tables' own schemas, the entity and vector
+//! rows of the nodes [`Lake::project_members`] finds and the edges between
+//! them. Importing feeds the rows through the regular sync pipeline, so the
+//! receiving store rebuilds its indexes and engine the way a sync would.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::{AnyFetchable, EntityCategory, GraphData};
+use crate::lake::Lake;
+use crate::models::{DatasetFile, DatasetManifest};
+use crate::schema_registry::SCHEMA_REGISTRY;
+use deltalake::arrow::array::BooleanArray;
+use deltalake::arrow::compute::filter_record_batch;
+use deltalake::arrow::record_batch::RecordBatch;
+use deltalake::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use deltalake::parquet::arrow::ArrowWriter;
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Component, Path};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const FORMAT_VERSION: u32 = 1;
+const EDGE_TABLE_PREFIX: &str = "silver/edges/";
+/// Rows handed to the sync pipeline at a time on import.
+const IMPORT_BATCH_ROWS: usize = 50_000;
+
+/// Writes the dataset of the project at `project_url` to `out`, which must be
+/// missing or empty. Tables are read and written a batch at a time.
+pub(crate) async fn export_project(
+    lake: &Lake,
+    project_url: &str,
+    out: &Path,
+) -> Result<DatasetManifest> {
+    let project_url = project_url.trim();
+    if project_url.is_empty() {
+        return Err(StorageError::InvalidArg(
+            "project url must not be empty".to_string(),
+        ));
+    }
+    if fs::read_dir(out).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(StorageError::InvalidArg(format!(
+            "{} is not empty",
+            out.display()
+        )));
+    }
+    let (project_id, members) = lake.project_members(project_url).await?;
+    let all: HashSet<&str> = members.values().flatten().map(String::as_str).collect();
+
+    // Nodes before vectors before edges: an import then writes what vector
+    // rules and edges point at before them.
+    let mut files = Vec::new();
+    for (category, dir) in [
+        (EntityCategory::Node, "entities"),
+        (EntityCategory::Vector, "vectors"),
+    ] {
+        for (entity_type, ids) in &members {
+            let Some(meta) = SCHEMA_REGISTRY
+                .entity(entity_type)
+                .filter(|meta| meta.category == category)
+            else {
+                continue;
+            };
+            let file = DatasetFile {
+                path: format!("{dir}/{entity_type}.parquet"),
+                table: meta.table_name.to_string(),
+                entity_type: entity_type.clone(),
+                category: category.as_str().to_string(),
+                rows: 0,
+            };
+            let written = write_rows(lake, out, file, |batch| {
+                let keep = if category == EntityCategory::Node {
+                    Lake::batch_node_ids(entity_type, batch)?
+                        .iter()
+                        .map(|id| Some(id.as_ref().is_some_and(|id| ids.contains(id))))
+                        .collect()
+                } else {
+                    ids_in(batch, "id", ids)?
+                };
+                Ok(keep)
+            })
+            .await?;
+            files.extend(written);
+        }
+    }
+
+    for summary in lake.list_tables(EDGE_TABLE_PREFIX).await? {
+        let Some(edge_type) = summary.table_path.strip_prefix(EDGE_TABLE_PREFIX) else {
+            continue;
+        };
+        let file = DatasetFile {
+            path: format!("edges/{edge_type}.parquet"),
+            entity_type: format!("edge_{edge_type}"),
+            table: summary.table_path.clone(),
+            category: EntityCategory::Edge.as_str().to_string(),
+            rows: 0,
+        };
+        let written = write_rows(lake, out, file, |batch| {
+            let from = ids_in(batch, "from_node_id", &all)?;
+            let to = ids_in(batch, "to_node_id", &all)?;
+            Ok(from
+                .iter()
+                .zip(to.iter())
+                .map(|(from, to)| Some(from == Some(true) && to == Some(true)))
+                .collect())
+        })
+        .await?;
+        files.extend(written);
+    }
+
+    let manifest = DatasetManifest {
+        format_version: FORMAT_VERSION,
+        project_url: project_url.to_string(),
+        project_id,
+        exported_at: chrono::Utc::now().timestamp(),
+        files,
+    };
+    fs::create_dir_all(out)?;
+    fs::write(
+        out.join(MANIFEST_FILE),
+        serde_json::to_vec_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+/// Whether each row's `column` is one of `ids`; all false without the column.
+fn ids_in<S: std::borrow::Borrow<str> + Eq + std::hash::Hash>(
+    batch: &RecordBatch,
+    column: &str,
+    ids: &HashSet<S>,
+) -> Result<BooleanArray> {
+    let Some(values) = Lake::string_column(batch, column)? else {
+        return Ok(BooleanArray::from(vec![false; batch.num_rows()]));
+    };
+    Ok(values
+        .iter()
+        .map(|value| Some(value.is_some_and(|value| ids.contains(value))))
+        .collect())
+}
+
+/// Writes the rows of `file.table` that `keep` selects to `file.path` under
+/// `out`. Nothing is written, and `None` returned, when it selects none.
+async fn write_rows(
+    lake: &Lake,
+    out: &Path,
+    mut file: DatasetFile,
+    keep: impl Fn(&RecordBatch) -> Result<BooleanArray>,
+) -> Result<Option<DatasetFile>> {
+    let Some(mut batches) = lake.scan_table(&file.table).await? else {
+        return Ok(None);
+    };
+    let mut writer = None;
+    while let Some(batch) = batches.next().await {
+        let batch = batch.map_err(|e| StorageError::Other(e.into()))?;
+        let batch = filter_record_batch(&batch, &keep(&batch)?)?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        let writer = match &mut writer {
+            Some(writer) => writer,
+            empty => {
+                let path = out.join(&file.path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                empty.insert(
+                    ArrowWriter::try_new(File::create(path)?, batch.schema(), None)
+                        .map_err(|e| StorageError::Other(e.into()))?,
+                )
+            }
+        };
+        writer
+            .write(&batch)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        file.rows += batch.num_rows() as u64;
+    }
+    let Some(writer) = writer else {
+        return Ok(None);
+    };
+    writer.close().map_err(|e| StorageError::Other(e.into()))?;
+    Ok(Some(file))
+}
+
+/// Reads and checks the manifest of the dataset in `dir`.
+pub(crate) fn read_manifest(dir: &Path) -> Result<DatasetManifest> {
+    let raw = match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(StorageError::NotFound(format!(
+                "no {MANIFEST_FILE} in {}",
+                dir.display()
+            )));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let manifest: DatasetManifest = serde_json::from_slice(&raw)?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(StorageError::InvalidArg(format!(
+            "unsupported dataset format version {}",
+            manifest.format_version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// The rows of `file` as sync pipeline input, at most
+/// [`IMPORT_BATCH_ROWS`] at a time.
+pub(crate) fn read_file(
+    dir: &Path,
+    file: &DatasetFile,
+) -> Result<impl Iterator<Item = Result<GraphData>>> {
+    let path = Path::new(&file.path);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(StorageError::InvalidArg(format!(
+            "dataset file '{}' is outside the dataset",
+            file.path
+        )));
+    }
+    let target = Target::of(file)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(dir.join(path))?)
+        .map_err(|e| StorageError::Other(e.into()))?
+        .with_batch_size(IMPORT_BATCH_ROWS)
+        .build()
+        .map_err(|e| StorageError::Other(e.into()))?;
+    Ok(reader.map(move |batch| {
+        let mut graph = GraphData::new();
+        graph.entities.push(Box::new(DatasetRows {
+            target: target.clone(),
+            batch: batch?,
+        }));
+        Ok(graph)
+    }))
+}
+
+/// Registry entry a dataset file's rows are written as.
+#[derive(Clone)]
+struct Target {
+    entity_type: &'static str,
+    category: EntityCategory,
+    table: String,
+    primary_keys: Vec<&'static str>,
+}
+
+impl Target {
+    fn of(file: &DatasetFile) -> Result<Self> {
+        let unknown = || {
+            StorageError::InvalidArg(format!(
+                "dataset file '{}' holds unknown {} type '{}'",
+                file.path, file.category, file.entity_type
+            ))
+        };
+        match file.category.parse::<EntityCategory>()? {
+            EntityCategory::Edge => {
+                let edge = SCHEMA_REGISTRY
+                    .edge(&file.entity_type)
+                    .and_then(|edges| edges.first())
+                    .ok_or_else(unknown)?;
+                let suffix = edge
+                    .edge_type
+                    .strip_prefix("edge_")
+                    .unwrap_or(edge.edge_type);
+                Ok(Self {
+                    entity_type: edge.edge_type,
+                    category: EntityCategory::Edge,
+                    table: format!("{EDGE_TABLE_PREFIX}{suffix}"),
+                    primary_keys: vec!["id"],
+                })
+            }
+            category => {
+                let meta = SCHEMA_REGISTRY
+                    .entity(&file.entity_type)
+                    .filter(|meta| meta.category == category)
+                    .ok_or_else(unknown)?;
+                Ok(Self {
+                    entity_type: meta.entity_type,
+                    category,
+                    table: meta.table_name.to_string(),
+                    primary_keys: meta.primary_keys.to_vec(),
+                })
+            }
+        }
+    }
+}
+
+/// Rows of a dataset file, handed to the sync pipeline like fetched entities.
+struct DatasetRows {
+    target: Target,
+    batch: RecordBatch,
+}
+
+impl AnyFetchable for DatasetRows {
+    fn to_record_batch_any(&self) -> Result<RecordBatch> {
+        Ok(self.batch.clone())
+    }
+    fn entity_type_any(&self) -> &'static str {
+        self.target.entity_type
+    }
+    fn category_any(&self) -> EntityCategory {
+        self.target.category
+    }
+    fn primary_keys_any(&self) -> Vec<&'static str> {
+        self.target.primary_keys.clone()
+    }
+    fn table_name(&self) -> String {
+        self.target.table.clone()
+    }
+}
@@ -1,149 +1,240 @@
 use crate::errors::{Result, StorageError};
 use async_trait::async_trait;
-use fastembed::{InitOptions, TextEmbedding};
-use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use tokio::task;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "fastembed")]
+mod fastembed;
+#[cfg(feature = "fastembed")]
+mod onnx;
+#[cfg(feature = "openai")]
+mod openai;
+
+#[cfg(feature = "fastembed")]
+pub use self::fastembed::FastEmbedProvider;
+#[cfg(feature = "fastembed")]
+pub use onnx::OnnxProvider;
+#[cfg(feature = "openai")]
+pub use openai::OpenAIProvider;
+
+const DEFAULT_MAX_LENGTH: usize = 512;
 
 #[async_trait]
 pub trait EmbeddingProvider: Send + Sync {
     async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>>;
-}
 
-pub struct NullEmbeddingProvider;
+    /// Like [`embed`](Self::embed), along with the tokens the provider billed
+    /// for `texts` when its response reports them.
+    async fn embed_with_usage(&self, texts: Vec<String>) -> Result<(Vec<Vec<f64>>, Option<u64>)> {
+        Ok((self.embed(texts).await?, None))
+    }
 
-#[async_trait]
-impl EmbeddingProvider for NullEmbeddingProvider {
-    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
-        Ok(vec![vec![]; texts.len()])
+    /// Short identifier used for cost accounting (e.g. `openai`).
+    fn provider_name(&self) -> &str {
+        "unknown"
     }
-}
 
-#[derive(Serialize)]
-struct OpenAIRequest {
-    input: Vec<String>,
-    model: String,
+    /// Model identifier, if the provider exposes one.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
 }
 
-#[derive(Deserialize)]
-struct OpenAIEmbedding {
-    embedding: Vec<f64>,
+/// An embedding provider searches can select by model name, next to the one
+/// chosen when the store opens.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum EmbeddingModelConfig {
+    /// An OpenAI embedding model; the key is read from `OPENAI_API_KEY`.
+    OpenAi { model: String },
+    /// A local FastEmbed model, by model code such as `BAAI/bge-small-en-v1.5`.
+    FastEmbed { model: String },
 }
 
-#[derive(Deserialize)]
-struct OpenAIResponse {
-    data: Vec<OpenAIEmbedding>,
-}
+impl EmbeddingModelConfig {
+    /// The name searches select the provider by.
+    pub fn model(&self) -> &str {
+        match self {
+            Self::OpenAi { model } | Self::FastEmbed { model } => model,
+        }
+    }
 
-pub struct OpenAIProvider {
-    api_key: String,
-    model: String,
-    client: reqwest::Client,
+    pub fn build(&self) -> Result<Arc<dyn EmbeddingProvider>> {
+        Ok(match self {
+            #[cfg(feature = "openai")]
+            Self::OpenAi { model } => {
+                let key = std::env::var("OPENAI_API_KEY").map_err(|_| {
+                    StorageError::Config(format!(
+                        "embedding model '{model}' needs OPENAI_API_KEY to be set"
+                    ))
+                })?;
+                Arc::new(OpenAIProvider::new(model.clone(), key))
+            }
+            #[cfg(not(feature = "openai"))]
+            Self::OpenAi { model } => {
+                return Err(StorageError::Config(format!(
+                    "embedding model '{model}' needs fstorage built with the `openai` feature"
+                )));
+            }
+            #[cfg(feature = "fastembed")]
+            Self::FastEmbed { model } => Arc::new(FastEmbedProvider::from_code(model)?),
+            #[cfg(not(feature = "fastembed"))]
+            Self::FastEmbed { model } => {
+                return Err(StorageError::Config(format!(
+                    "embedding model '{model}' needs fstorage built with the `fastembed` feature"
+                )));
+            }
+        })
+    }
 }
 
-impl OpenAIProvider {
-    pub fn new(model: String, api_key: String) -> Self {
-        Self {
-            api_key,
-            model,
-            client: reqwest::Client::new(),
+impl std::str::FromStr for EmbeddingModelConfig {
+    type Err = String;
+
+    /// Parses `openai:<model>` or `fastembed:<model>`.
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, model) = raw
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("expected openai:<model> or fastembed:<model>, got '{raw}'"))?;
+        let model = model.trim().to_string();
+        if model.is_empty() {
+            return Err(format!("missing model name in '{raw}'"));
+        }
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi { model }),
+            "fastembed" => Ok(Self::FastEmbed { model }),
+            other => Err(format!(
+                "unknown embedding provider '{other}', expected openai or fastembed"
+            )),
         }
     }
 }
 
+pub struct NullEmbeddingProvider;
+
 #[async_trait]
-impl EmbeddingProvider for OpenAIProvider {
+impl EmbeddingProvider for NullEmbeddingProvider {
     async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
-        if texts.is_empty() {
-            return Ok(Vec::new());
-        }
+        Ok(vec![vec![]; texts.len()])
+    }
 
-        let request_payload = OpenAIRequest {
-            input: texts,
-            model: self.model.clone(),
-        };
-
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/embeddings")
-            .bearer_auth(&self.api_key)
-            .json(&request_payload)
-            .send()
-            .await
-            .map_err(|e| StorageError::SyncError(format!("OpenAI API request failed: {}", e)))?;
-
-        if !response.status().is_success() {
-            let error_body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(StorageError::SyncError(format!(
-                "OpenAI API returned an error: {}",
-                error_body
-            )));
-        }
+    fn provider_name(&self) -> &str {
+        "null"
+    }
+}
 
-        let openai_response = response.json::<OpenAIResponse>().await.map_err(|e| {
-            StorageError::SyncError(format!("Failed to parse OpenAI response: {}", e))
-        })?;
+/// How token embeddings are reduced to one vector per text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnnxPooling {
+    /// Average over non-padding tokens, as most sentence-transformers use.
+    #[default]
+    Mean,
+    /// The first (`[CLS]`) token, as BGE and E5 models use.
+    Cls,
+    /// The model output is already one vector per text.
+    None,
+}
 
-        let embeddings = openai_response
-            .data
-            .into_iter()
-            .map(|data| data.embedding)
-            .collect();
+/// Where inference runs. Falls back to the CPU when the device is unavailable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum OnnxDevice {
+    #[default]
+    Cpu,
+    Cuda {
+        #[serde(default)]
+        device_id: i32,
+    },
+    DirectMl {
+        #[serde(default)]
+        device_id: i32,
+    },
+}
 
-        Ok(embeddings)
+impl std::str::FromStr for OnnxDevice {
+    type Err = String;
+
+    /// Parses `cpu`, `cuda`, `cuda:1`, `directml` or `directml:0`.
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        let raw = raw.trim().to_ascii_lowercase();
+        let (kind, device) = raw.split_once(':').unwrap_or((raw.as_str(), "0"));
+        let device_id = device
+            .parse::<i32>()
+            .map_err(|_| format!("invalid device id '{device}'"))?;
+        match kind {
+            "cpu" => Ok(Self::Cpu),
+            "cuda" => Ok(Self::Cuda { device_id }),
+            "directml" | "dml" => Ok(Self::DirectMl { device_id }),
+            other => Err(format!(
+                "unknown device '{other}', expected cpu, cuda[:id] or directml[:id]"
+            )),
+        }
     }
 }
 
-pub struct FastEmbedProvider {
-    model: Arc<Mutex<TextEmbedding>>,
+/// A local model for `OnnxProvider`, which needs the `fastembed` feature.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OnnxConfig {
+    pub model_path: PathBuf,
+    /// Defaults to `tokenizer.json` next to the model.
+    #[serde(default)]
+    pub tokenizer_path: Option<PathBuf>,
+    /// Name recorded for cost accounting; defaults to the model file stem.
+    #[serde(default)]
+    pub model_name: Option<String>,
+    #[serde(default)]
+    pub pooling: OnnxPooling,
+    /// L2-normalize the pooled vectors.
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+    /// Longer texts are truncated to this many tokens.
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    /// Model output to read; defaults to the first one.
+    #[serde(default)]
+    pub output_name: Option<String>,
+    #[serde(default)]
+    pub device: OnnxDevice,
 }
 
-impl FastEmbedProvider {
-    pub fn new_default() -> Result<Self> {
-        Self::new_with_options(Default::default())
-    }
-
-    pub fn new_with_model(model: fastembed::EmbeddingModel) -> Result<Self> {
-        Self::new_with_options(InitOptions::new(model))
-    }
+fn default_normalize() -> bool {
+    true
+}
 
-    pub fn new_with_options(options: InitOptions) -> Result<Self> {
-        let embedding = TextEmbedding::try_new(options).map_err(|e| {
-            StorageError::SyncError(format!("Failed to initialize FastEmbed model: {}", e))
-        })?;
-        Ok(Self {
-            model: Arc::new(Mutex::new(embedding)),
-        })
-    }
+fn default_max_length() -> usize {
+    DEFAULT_MAX_LENGTH
 }
 
-#[async_trait]
-impl EmbeddingProvider for FastEmbedProvider {
-    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
-        if texts.is_empty() {
-            return Ok(Vec::new());
+impl OnnxConfig {
+    pub fn new(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            tokenizer_path: None,
+            model_name: None,
+            pooling: OnnxPooling::default(),
+            normalize: default_normalize(),
+            max_length: DEFAULT_MAX_LENGTH,
+            output_name: None,
+            device: OnnxDevice::default(),
         }
+    }
+}
 
-        let model = Arc::clone(&self.model);
-        let embeddings = task::spawn_blocking(move || -> Result<Vec<Vec<f64>>> {
-            let mut guard = model
-                .lock()
-                .map_err(|_| StorageError::SyncError("FastEmbed model mutex poisoned".into()))?;
-            let document_refs: Vec<_> = texts.iter().map(|s| s.as_str()).collect();
-            let vectors = guard.embed(document_refs, None).map_err(|e| {
-                StorageError::SyncError(format!("FastEmbed embedding failed: {}", e))
-            })?;
-            Ok(vectors
-                .into_iter()
-                .map(|vec| vec.into_iter().map(|value| value as f64).collect())
-                .collect())
-        })
-        .await
-        .map_err(|e| StorageError::SyncError(format!("FastEmbed task join error: {}", e)))??;
-
-        Ok(embeddings)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_parse_with_optional_ids() {
+        assert_eq!("cpu".parse(), Ok(OnnxDevice::Cpu));
+        assert_eq!("CUDA:1".parse(), Ok(OnnxDevice::Cuda { device_id: 1 }));
+        assert_eq!(
+            "directml".parse(),
+            Ok(OnnxDevice::DirectMl { device_id: 0 })
+        );
+        assert!("tpu".parse::<OnnxDevice>().is_err());
     }
 }
@@ -0,0 +1,88 @@
+//! Local embedding models run by FastEmbed.
+
+use super::EmbeddingProvider;
+use crate::errors::{Result, StorageError};
+use async_trait::async_trait;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use std::sync::{Arc, Mutex};
+use tokio::task;
+
+pub struct FastEmbedProvider {
+    model: Arc<Mutex<TextEmbedding>>,
+    model_name: Option<String>,
+}
+
+impl FastEmbedProvider {
+    pub fn new_default() -> Result<Self> {
+        Self::new_with_options(Default::default())
+    }
+
+    pub fn new_with_model(model: EmbeddingModel) -> Result<Self> {
+        let model_name = format!("{:?}", model);
+        let mut provider = Self::new_with_options(InitOptions::new(model))?;
+        provider.model_name = Some(model_name);
+        Ok(provider)
+    }
+
+    /// Loads the model with FastEmbed code `code`, such as
+    /// `BAAI/bge-small-en-v1.5`, recording the code as the model name.
+    pub(super) fn from_code(code: &str) -> Result<Self> {
+        let model = TextEmbedding::list_supported_models()
+            .into_iter()
+            .find(|info| {
+                info.model_code.eq_ignore_ascii_case(code)
+                    || format!("{:?}", info.model).eq_ignore_ascii_case(code)
+            })
+            .map(|info| info.model)
+            .ok_or_else(|| StorageError::Config(format!("unknown FastEmbed model '{code}'")))?;
+        let mut provider = Self::new_with_model(model)?;
+        provider.model_name = Some(code.to_string());
+        Ok(provider)
+    }
+
+    pub fn new_with_options(options: InitOptions) -> Result<Self> {
+        let embedding = TextEmbedding::try_new(options).map_err(|e| {
+            StorageError::Embedding(format!("Failed to initialize FastEmbed model: {}", e))
+        })?;
+        Ok(Self {
+            model: Arc::new(Mutex::new(embedding)),
+            model_name: None,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for FastEmbedProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = Arc::clone(&self.model);
+        let embeddings = task::spawn_blocking(move || -> Result<Vec<Vec<f64>>> {
+            let mut guard = model
+                .lock()
+                .map_err(|_| StorageError::Embedding("FastEmbed model mutex poisoned".into()))?;
+            let document_refs: Vec<_> = texts.iter().map(|s| s.as_str()).collect();
+            let vectors = guard.embed(document_refs, None).map_err(|e| {
+                StorageError::Embedding(format!("FastEmbed embedding failed: {}", e))
+            })?;
+            Ok(vectors
+                .into_iter()
+                .map(|vec| vec.into_iter().map(|value| value as f64).collect())
+                .collect())
+        })
+        .await
+        .map_err(|e| StorageError::Embedding(format!("FastEmbed task join error: {}", e)))??;
+
+        Ok(embeddings)
+    }
+
+    fn provider_name(&self) -> &str {
+        "fastembed"
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model_name.as_deref()
+    }
+}
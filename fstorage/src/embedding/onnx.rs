@@ -0,0 +1,287 @@
+//! Local sentence-transformer models exported to ONNX.
+//!
+//! Unlike [`super::FastEmbedProvider`], any model can be loaded: point
+//! [`OnnxConfig`] at the `.onnx` file and its `tokenizer.json`, pick the pooling
+//! the model was trained with, and optionally run it on CUDA or DirectML. GPU
+//! support needs fstorage built with the `cuda` or `directml` feature.
+
+use super::{EmbeddingProvider, OnnxConfig, OnnxDevice, OnnxPooling};
+use crate::errors::{Result, StorageError};
+use async_trait::async_trait;
+use ort::execution_providers::{
+    CPUExecutionProvider, CUDAExecutionProvider, DirectMLExecutionProvider,
+    ExecutionProviderDispatch,
+};
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use tokio::task;
+
+impl OnnxConfig {
+    fn tokenizer_path(&self) -> PathBuf {
+        self.tokenizer_path
+            .clone()
+            .unwrap_or_else(|| self.model_path.with_file_name("tokenizer.json"))
+    }
+
+    fn execution_providers(&self) -> Vec<ExecutionProviderDispatch> {
+        let mut providers = match self.device {
+            OnnxDevice::Cpu => Vec::new(),
+            OnnxDevice::Cuda { device_id } => vec![CUDAExecutionProvider::default()
+                .with_device_id(device_id)
+                .build()],
+            OnnxDevice::DirectMl { device_id } => vec![DirectMLExecutionProvider::default()
+                .with_device_id(device_id)
+                .build()],
+        };
+        providers.push(CPUExecutionProvider::default().build());
+        providers
+    }
+}
+
+struct OnnxModel {
+    session: Session,
+    tokenizer: Tokenizer,
+    /// Whether the graph takes `token_type_ids`; BERT exports do, others don't.
+    takes_token_types: bool,
+    output_name: String,
+    pooling: OnnxPooling,
+    normalize: bool,
+}
+
+pub struct OnnxProvider {
+    model: Arc<Mutex<OnnxModel>>,
+    model_name: String,
+}
+
+impl OnnxProvider {
+    pub fn new(config: OnnxConfig) -> Result<Self> {
+        let load_error = |what: &str, err: String| {
+            StorageError::Embedding(format!("Failed to load ONNX {what}: {err}"))
+        };
+
+        let mut tokenizer = Tokenizer::from_file(config.tokenizer_path())
+            .map_err(|err| load_error("tokenizer", err.to_string()))?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_length,
+                ..Default::default()
+            }))
+            .map_err(|err| load_error("tokenizer", err.to_string()))?;
+
+        let session = Session::builder()
+            .and_then(|builder| builder.with_execution_providers(config.execution_providers()))
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|mut builder| builder.commit_from_file(&config.model_path))
+            .map_err(|err| load_error("model", err.to_string()))?;
+
+        let takes_token_types = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+        let output_name = match &config.output_name {
+            Some(name) => {
+                if !session.outputs.iter().any(|output| &output.name == name) {
+                    return Err(load_error(
+                        "model",
+                        format!("it has no output named '{name}'"),
+                    ));
+                }
+                name.clone()
+            }
+            None => session
+                .outputs
+                .first()
+                .map(|output| output.name.clone())
+                .ok_or_else(|| load_error("model", "it has no outputs".to_string()))?,
+        };
+        let model_name = config.model_name.clone().unwrap_or_else(|| {
+            config
+                .model_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "onnx".to_string())
+        });
+
+        Ok(Self {
+            model: Arc::new(Mutex::new(OnnxModel {
+                session,
+                tokenizer,
+                takes_token_types,
+                output_name,
+                pooling: config.pooling,
+                normalize: config.normalize,
+            })),
+            model_name,
+        })
+    }
+}
+
+impl OnnxModel {
+    fn embed(&mut self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        let inference_error =
+            |err: String| StorageError::Embedding(format!("ONNX embedding failed: {err}"));
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts, true)
+            .map_err(|err| inference_error(err.to_string()))?;
+        let batch = encodings.len();
+        let seq_len = encodings.first().map_or(0, |encoding| encoding.len());
+        let column = |select: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|encoding| select(encoding).iter().map(|&value| value as i64))
+                .collect()
+        };
+        let ids = column(tokenizers::Encoding::get_ids);
+        let mask = column(tokenizers::Encoding::get_attention_mask);
+
+        let tensor = |values: Vec<i64>| -> Result<SessionInputValue<'static>> {
+            Tensor::from_array(([batch, seq_len], values))
+                .map(Into::into)
+                .map_err(|err| inference_error(err.to_string()))
+        };
+        let mut inputs: Vec<(Cow<'static, str>, SessionInputValue<'static>)> = vec![
+            ("input_ids".into(), tensor(ids)?),
+            ("attention_mask".into(), tensor(mask.clone())?),
+        ];
+        if self.takes_token_types {
+            inputs.push((
+                "token_type_ids".into(),
+                tensor(column(tokenizers::Encoding::get_type_ids))?,
+            ));
+        }
+
+        let outputs = self
+            .session
+            .run(inputs)
+            .map_err(|err| inference_error(err.to_string()))?;
+        let (shape, values) = outputs[self.output_name.as_str()]
+            .try_extract_tensor::<f32>()
+            .map_err(|err| inference_error(err.to_string()))?;
+
+        let mut vectors = match (shape.len(), self.pooling) {
+            (2, _) | (_, OnnxPooling::None) => {
+                let hidden = *shape.last().unwrap_or(&0) as usize;
+                values
+                    .chunks(hidden.max(1))
+                    .take(batch)
+                    .map(|row| row.iter().map(|&value| value as f64).collect())
+                    .collect::<Vec<Vec<f64>>>()
+            }
+            (3, pooling) => {
+                let hidden = shape[2] as usize;
+                pool(values, &mask, batch, seq_len, hidden, pooling)
+            }
+            _ => {
+                return Err(inference_error(format!(
+                    "unexpected output shape {:?}",
+                    &shape[..]
+                )));
+            }
+        };
+        if self.normalize {
+            vectors.iter_mut().for_each(|vector| l2_normalize(vector));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Reduces `[batch, seq_len, hidden]` token embeddings to one vector per text.
+fn pool(
+    values: &[f32],
+    mask: &[i64],
+    batch: usize,
+    seq_len: usize,
+    hidden: usize,
+    pooling: OnnxPooling,
+) -> Vec<Vec<f64>> {
+    (0..batch)
+        .map(|row| {
+            let tokens = &values[row * seq_len * hidden..(row + 1) * seq_len * hidden];
+            match pooling {
+                OnnxPooling::Cls | OnnxPooling::None => {
+                    tokens[..hidden].iter().map(|&value| value as f64).collect()
+                }
+                OnnxPooling::Mean => {
+                    let mut sum = vec![0.0f64; hidden];
+                    let mut count = 0.0f64;
+                    for (token, embedding) in tokens.chunks(hidden).enumerate() {
+                        if mask[row * seq_len + token] == 0 {
+                            continue;
+                        }
+                        count += 1.0;
+                        for (acc, &value) in sum.iter_mut().zip(embedding) {
+                            *acc += value as f64;
+                        }
+                    }
+                    sum.iter().map(|value| value / count.max(1.0)).collect()
+                }
+            }
+        })
+        .collect()
+}
+
+fn l2_normalize(vector: &mut [f64]) {
+    let norm = vector.iter().map(|value| value * value).sum::<f64>().sqrt();
+    if norm > f64::EPSILON {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OnnxProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = Arc::clone(&self.model);
+        task::spawn_blocking(move || {
+            model
+                .lock()
+                .map_err(|_| StorageError::Embedding("ONNX model mutex poisoned".into()))?
+                .embed(texts)
+        })
+        .await
+        .map_err(|e| StorageError::Embedding(format!("ONNX task join error: {}", e)))?
+    }
+
+    fn provider_name(&self) -> &str {
+        "onnx"
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mean_pooling_skips_padding_tokens() {
+        // Two texts, three tokens, two dimensions; the second text has one pad token.
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 1.0, 1.0, 3.0, 3.0, 9.0, 9.0];
+        let mask = [1, 1, 1, 1, 1, 0];
+        let pooled = pool(&values, &mask, 2, 3, 2, OnnxPooling::Mean);
+        assert_eq!(pooled, vec![vec![3.0, 4.0], vec![2.0, 2.0]]);
+
+        let cls = pool(&values, &mask, 2, 3, 2, OnnxPooling::Cls);
+        assert_eq!(cls, vec![vec![1.0, 2.0], vec![1.0, 1.0]]);
+
+        let mut vector = vec![3.0, 4.0];
+        l2_normalize(&mut vector);
+        assert_eq!(vector, vec![0.6, 0.8]);
+    }
+}
@@ -0,0 +1,508 @@
+//! Tuning and growing the graph engine's LMDB environment.
+//!
+//! LMDB reserves its whole map up front and fails a write transaction with
+//! `MDB_MAP_FULL` once the data outgrows it. [`EngineMap::write`] catches that,
+//! doubles the map up to the configured ceiling and runs the transaction
+//! again, so a large ingest does not abort halfway through a sync.
+//!
+//! Resizing remaps the data file, which is only sound while no transaction is
+//! open anywhere in the process. Every engine transaction therefore holds the
+//! map shared through [`EngineMap::hold`] (writes through [`EngineMap::write`]
+//! do so themselves) and a resize waits for them to finish.
+//!
+//! [`EngineConfig`] sets the starting map size, how commits are flushed and
+//! how many read transactions may be open at once. The engine opens the
+//! environment itself with a fixed reader table, so `max_readers` can only
+//! lower that limit: read transactions beyond it wait for a slot instead of
+//! failing with `MDB_READERS_FULL`.
+//!
+//! Every write that changes the engine runs the hook set with
+//! [`EngineMap::on_commit`] once committed; the store bumps its graph version
+//! there.
+//!
+//! [`EngineMap::hold`] blocks while a resize runs or every reader slot is
+//! taken, so engine reads never take it on the async runtime: they run on
+//! blocking threads through [`crate::lake::Lake::read_graph`], and
+//! `read_workers` bounds how many of those threads they may occupy at once.
+
+use std::sync::{Arc, Condvar, Mutex, OnceLock, RwLock, RwLockReadGuard};
+
+use heed3::{Env, EnvFlags, FlagSetMode, MdbError, RwTxn};
+use helix_db::helix_engine::types::GraphError;
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::errors::{Result, StorageError};
+
+/// Map ceiling used when `EngineConfig::map_ceiling` is unset: 1 TiB.
+pub const DEFAULT_ENGINE_MAP_CEILING: u64 = 1 << 40;
+
+/// Blocking graph reads run at once when `EngineConfig::read_workers` is unset.
+pub const DEFAULT_ENGINE_READ_WORKERS: usize = 8;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EngineConfig {
+    /// Map size in bytes to open the engine with; the engine's default when
+    /// unset. Never shrinks the map below the data already stored.
+    #[serde(default)]
+    pub map_size: Option<u64>,
+    /// Size in bytes the map may grow to when writes fill it;
+    /// [`DEFAULT_ENGINE_MAP_CEILING`] unless configured.
+    #[serde(default)]
+    pub map_ceiling: Option<u64>,
+    #[serde(default)]
+    pub sync_mode: EngineSyncMode,
+    /// Cap on read transactions open at once. The engine's reader table is
+    /// fixed, so this can only lower its limit, never raise it.
+    #[serde(default)]
+    pub max_readers: Option<u32>,
+    /// Graph reads run on blocking threads at once;
+    /// [`DEFAULT_ENGINE_READ_WORKERS`] unless configured.
+    #[serde(default)]
+    pub read_workers: Option<usize>,
+}
+
+impl EngineConfig {
+    pub fn map_ceiling(&self) -> u64 {
+        self.map_ceiling.unwrap_or(DEFAULT_ENGINE_MAP_CEILING)
+    }
+
+    pub fn read_workers(&self) -> usize {
+        self.read_workers
+            .unwrap_or(DEFAULT_ENGINE_READ_WORKERS)
+            .max(1)
+    }
+}
+
+/// How engine commits reach the disk, from safest to fastest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineSyncMode {
+    /// Data and metadata are flushed on every commit.
+    #[default]
+    Full,
+    /// The metadata flush is skipped; a crash can undo the last commit but
+    /// leaves the store consistent.
+    NoMetaSync,
+    /// Flushes are left to the OS; a crash can undo recent commits, or corrupt
+    /// the store on filesystems that reorder writes. For bulk ingests that can
+    /// be rerun.
+    NoSync,
+    /// Flushes of a writable map are started asynchronously; LMDB ignores it
+    /// for read-only maps.
+    MapAsync,
+}
+
+impl EngineSyncMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EngineSyncMode::Full => "full",
+            EngineSyncMode::NoMetaSync => "no_meta_sync",
+            EngineSyncMode::NoSync => "no_sync",
+            EngineSyncMode::MapAsync => "map_async",
+        }
+    }
+
+    fn flags(&self) -> EnvFlags {
+        match self {
+            EngineSyncMode::Full => EnvFlags::empty(),
+            EngineSyncMode::NoMetaSync => EnvFlags::NO_META_SYNC,
+            EngineSyncMode::NoSync => EnvFlags::NO_SYNC,
+            EngineSyncMode::MapAsync => EnvFlags::MAP_ASYNC,
+        }
+    }
+}
+
+impl std::str::FromStr for EngineSyncMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "full" => Ok(EngineSyncMode::Full),
+            "no_meta_sync" => Ok(EngineSyncMode::NoMetaSync),
+            "no_sync" => Ok(EngineSyncMode::NoSync),
+            "map_async" => Ok(EngineSyncMode::MapAsync),
+            other => Err(format!(
+                "unknown engine sync mode '{other}', expected full, no_meta_sync, no_sync or map_async"
+            )),
+        }
+    }
+}
+
+/// Applies `config` to the freshly opened `env`. Must run before any
+/// transaction is opened on it.
+pub(crate) fn configure(env: &Env, config: &EngineConfig) -> Result<()> {
+    let ceiling = config.map_ceiling();
+    if let Some(map_size) = config.map_size {
+        if map_size > ceiling {
+            return Err(StorageError::Config(format!(
+                "engine map size {map_size} exceeds its ceiling {ceiling}"
+            )));
+        }
+        // SAFETY: the environment was just opened and has no transactions.
+        unsafe { env.resize(map_size as usize)? };
+    }
+    if let Some(max_readers) = config.max_readers {
+        let available = env.info().maximum_number_of_readers;
+        if max_readers == 0 || max_readers > available {
+            return Err(StorageError::Config(format!(
+                "engine max_readers must be between 1 and {available}, got {max_readers}"
+            )));
+        }
+    }
+    let flags = config.sync_mode.flags();
+    if !flags.is_empty() {
+        // SAFETY: the sync flags may change at any time; no other flag is set.
+        unsafe { env.set_flags(flags, FlagSetMode::Enable)? };
+    }
+    Ok(())
+}
+
+/// Run after a write transaction that changed the engine has committed.
+type CommitHook = Box<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// Serializes resizes of the engine map against its transactions.
+pub struct EngineMap {
+    gate: RwLock<()>,
+    ceiling: u64,
+    readers: Option<ReaderSlots>,
+    workers: Arc<Semaphore>,
+    on_commit: OnceLock<CommitHook>,
+}
+
+/// Keeps the map at its size, and a reader slot if they are limited, until
+/// dropped.
+pub struct EngineHold<'a> {
+    _gate: RwLockReadGuard<'a, ()>,
+    slot: Option<&'a ReaderSlots>,
+}
+
+struct ReaderSlots {
+    max: u32,
+    open: Mutex<u32>,
+    freed: Condvar,
+}
+
+impl EngineMap {
+    pub fn new(config: &EngineConfig) -> Self {
+        Self {
+            gate: RwLock::new(()),
+            ceiling: config.map_ceiling(),
+            readers: config.max_readers.map(|max| ReaderSlots {
+                max,
+                open: Mutex::new(0),
+                freed: Condvar::new(),
+            }),
+            workers: Arc::new(Semaphore::new(config.read_workers())),
+            on_commit: OnceLock::new(),
+        }
+    }
+
+    /// Sets the hook [`EngineMap::write`] runs after each commit that changed
+    /// the engine. Only the first hook set is kept.
+    pub fn on_commit(&self, hook: impl Fn() -> Result<()> + Send + Sync + 'static) {
+        if self.on_commit.set(Box::new(hook)).is_err() {
+            log::warn!("engine commit hook already set; keeping the first one");
+        }
+    }
+
+    /// Waits for one of the `read_workers` slots for a blocking graph read,
+    /// without blocking the runtime. The slot is freed when the permit drops.
+    pub async fn read_worker(&self) -> OwnedSemaphorePermit {
+        Arc::clone(&self.workers)
+            .acquire_owned()
+            .await
+            .expect("engine read worker semaphore is never closed")
+    }
+
+    /// Keeps the map from being resized until the guard is dropped, waiting
+    /// for a reader slot first when `max_readers` is set. Take it before
+    /// opening a read transaction and keep it past the transaction's end; it
+    /// must not be taken again on the same thread while held. It blocks the
+    /// thread, so async code reads through [`crate::lake::Lake::read_graph`].
+    pub fn hold(&self) -> EngineHold<'_> {
+        let slot = self.readers.as_ref().map(|slots| {
+            let mut open = slots.open.lock().unwrap_or_else(|p| p.into_inner());
+            while *open >= slots.max {
+                open = slots.freed.wait(open).unwrap_or_else(|p| p.into_inner());
+            }
+            *open += 1;
+            slots
+        });
+        EngineHold {
+            _gate: self.shared(),
+            slot,
+        }
+    }
+
+    fn shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.gate
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs `op` in a write transaction on `env` and commits it, then runs the
+    /// [`EngineMap::on_commit`] hook if the commit changed anything. When the
+    /// map fills up, the transaction is aborted, the map grown and `op` run
+    /// again from the start, so it must not keep state across attempts.
+    pub fn write<T>(
+        &self,
+        env: &Env,
+        mut op: impl FnMut(&mut RwTxn<'_>) -> Result<T>,
+    ) -> Result<T> {
+        loop {
+            let (map_size, attempt) = {
+                let _held = self.shared();
+                let map_size = env.info().map_size as u64;
+                let attempt = env
+                    .write_txn()
+                    .map_err(StorageError::from)
+                    .and_then(|mut txn| {
+                        // No other writer can commit while `txn` is open, and
+                        // LMDB commits a transaction that wrote nothing
+                        // without taking a new id.
+                        let before = env.info().last_txn_id;
+                        let value = op(&mut txn)?;
+                        txn.commit()?;
+                        Ok((value, env.info().last_txn_id != before))
+                    });
+                (map_size, attempt)
+            };
+            match attempt {
+                Err(err) if is_map_full(&err) => self.grow(env, map_size)?,
+                Err(err) => return Err(err),
+                Ok((value, changed)) => {
+                    if let Some(hook) = self.on_commit.get().filter(|_| changed) {
+                        hook()?;
+                    }
+                    return Ok(value);
+                }
+            }
+        }
+    }
+
+    /// Doubles the map from `full_size`, unless another writer already grew it.
+    fn grow(&self, env: &Env, full_size: u64) -> Result<()> {
+        let _exclusive = self
+            .gate
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if env.info().map_size as u64 > full_size {
+            return Ok(());
+        }
+        if full_size >= self.ceiling {
+            return Err(StorageError::EngineMapFull {
+                size: full_size,
+                ceiling: self.ceiling,
+            });
+        }
+        let new_size = full_size.saturating_mul(2).min(self.ceiling);
+        log::warn!(
+            "Graph engine map is full at {} bytes; growing it to {} bytes",
+            full_size,
+            new_size
+        );
+        // SAFETY: the exclusive gate guarantees no transaction of this process
+        // is open on `env`.
+        unsafe { env.resize(new_size as usize)? };
+        Ok(())
+    }
+}
+
+impl Drop for EngineHold<'_> {
+    fn drop(&mut self) {
+        if let Some(slots) = self.slot {
+            *slots.open.lock().unwrap_or_else(|p| p.into_inner()) -= 1;
+            slots.freed.notify_one();
+        }
+    }
+}
+
+/// Whether `err` is LMDB refusing a write because the map is full, either
+/// directly or wrapped by the graph engine. The engine keeps only the message
+/// of the heed errors it wraps, so those are compared with the message of
+/// [`MdbError::MapFull`].
+pub(crate) fn is_map_full(err: &StorageError) -> bool {
+    match err {
+        StorageError::Heed(heed3::Error::Mdb(MdbError::MapFull)) => true,
+        StorageError::Graph(GraphError::StorageError(message)) => {
+            *message == heed3::Error::Mdb(MdbError::MapFull).to_string()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heed3::types::Bytes;
+    use heed3::{Database, EnvOpenOptions};
+
+    const MIB: u64 = 1 << 20;
+
+    fn open_env(dir: &std::path::Path, map_size: u64) -> Env {
+        unsafe {
+            EnvOpenOptions::new()
+                .map_size(map_size as usize)
+                .max_dbs(1)
+                .open(dir)
+                .unwrap()
+        }
+    }
+
+    fn with_ceiling(ceiling: u64) -> EngineConfig {
+        EngineConfig {
+            map_ceiling: Some(ceiling),
+            ..EngineConfig::default()
+        }
+    }
+
+    #[test]
+    fn write_grows_a_full_map_and_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = open_env(dir.path(), MIB);
+        let map = EngineMap::new(&with_ceiling(64 * MIB));
+        let db: Database<Bytes, Bytes> = map
+            .write(&env, |txn| Ok(env.create_database(txn, None)?))
+            .unwrap();
+
+        let value = vec![7u8; 64 * 1024];
+        let mut attempts = 0;
+        map.write(&env, |txn| {
+            attempts += 1;
+            for key in 0u32..64 {
+                db.put(txn, &key.to_be_bytes(), &value)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(attempts > 1);
+        assert!(env.info().map_size as u64 > MIB);
+        let _held = map.hold();
+        let txn = env.read_txn().unwrap();
+        assert_eq!(db.len(&txn).unwrap(), 64);
+    }
+
+    #[test]
+    fn write_stops_at_the_ceiling() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = open_env(dir.path(), MIB);
+        let map = EngineMap::new(&with_ceiling(2 * MIB));
+        let db: Database<Bytes, Bytes> = map
+            .write(&env, |txn| Ok(env.create_database(txn, None)?))
+            .unwrap();
+
+        let value = vec![7u8; 64 * 1024];
+        let err = map
+            .write(&env, |txn| {
+                for key in 0u32..64 {
+                    db.put(txn, &key.to_be_bytes(), &value)?;
+                }
+                Ok(())
+            })
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            StorageError::EngineMapFull { ceiling, .. } if ceiling == 2 * MIB
+        ));
+    }
+
+    #[test]
+    fn commit_hook_runs_for_writes_that_change_the_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = open_env(dir.path(), MIB);
+        let map = EngineMap::new(&with_ceiling(2 * MIB));
+        let commits = Arc::new(Mutex::new(0));
+        let counted = Arc::clone(&commits);
+        map.on_commit(move || {
+            *counted.lock().unwrap() += 1;
+            Ok(())
+        });
+
+        let db: Database<Bytes, Bytes> = map
+            .write(&env, |txn| Ok(env.create_database(txn, None)?))
+            .unwrap();
+        map.write(&env, |txn| Ok(db.put(txn, b"key", b"value")?))
+            .unwrap();
+        map.write(&env, |txn| Ok(db.get(txn, b"key")?.is_some()))
+            .unwrap();
+
+        assert_eq!(*commits.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn map_full_is_recognized_through_the_graph_engine() {
+        let full = || heed3::Error::Mdb(MdbError::MapFull);
+        assert!(is_map_full(&StorageError::Heed(full())));
+        assert!(is_map_full(&StorageError::Graph(GraphError::from(full()))));
+        assert!(!is_map_full(&StorageError::Graph(GraphError::from(
+            heed3::Error::Mdb(MdbError::ReadersFull)
+        ))));
+        assert!(!is_map_full(&StorageError::Graph(GraphError::New(
+            "MDB_MAP_FULL".to_string()
+        ))));
+    }
+
+    #[test]
+    fn configure_sizes_the_map_and_checks_the_reader_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = open_env(dir.path(), MIB);
+        let config = EngineConfig {
+            map_size: Some(8 * MIB),
+            sync_mode: EngineSyncMode::NoSync,
+            ..EngineConfig::default()
+        };
+        configure(&env, &config).unwrap();
+        assert_eq!(env.info().map_size as u64, 8 * MIB);
+
+        let available = env.info().maximum_number_of_readers;
+        let too_many = EngineConfig {
+            max_readers: Some(available + 1),
+            ..EngineConfig::default()
+        };
+        assert!(matches!(
+            configure(&env, &too_many),
+            Err(StorageError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn hold_waits_for_a_free_reader_slot() {
+        let map = EngineMap::new(&EngineConfig {
+            max_readers: Some(1),
+            ..EngineConfig::default()
+        });
+        let first = map.hold();
+        std::thread::scope(|scope| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            scope.spawn(|| {
+                let _second = map.hold();
+                sender.send(()).unwrap();
+            });
+            let pending = receiver.recv_timeout(std::time::Duration::from_millis(100));
+            assert!(pending.is_err());
+            drop(first);
+            receiver
+                .recv_timeout(std::time::Duration::from_secs(5))
+                .unwrap();
+        });
+    }
+
+    #[tokio::test]
+    async fn read_worker_waits_for_a_free_slot() {
+        let map = EngineMap::new(&EngineConfig {
+            read_workers: Some(1),
+            ..EngineConfig::default()
+        });
+        let first = map.read_worker().await;
+        let waiting =
+            tokio::time::timeout(std::time::Duration::from_millis(50), map.read_worker()).await;
+        assert!(waiting.is_err());
+        drop(first);
+        tokio::time::timeout(std::time::Duration::from_secs(5), map.read_worker())
+            .await
+            .unwrap();
+    }
+}
@@ -35,6 +35,30 @@ pub enum StorageError {
     #[error("Synchronization failed: {0}")]
     SyncError(String),
 
+    #[error("Fetcher '{0}' is not registered")]
+    UnknownFetcher(String),
+
+    #[error("Embedding failed: {0}")]
+    Embedding(String),
+
+    #[error(
+        "Delta log of table '{table}' is damaged ({detail}); run `fagent repair-table {table}` \
+         to quarantine the malformed entries and rebuild its checkpoint"
+    )]
+    CorruptLog { table: String, detail: String },
+
+    #[error("Sync budget exhausted: {0}")]
+    BudgetExhausted(crate::models::BudgetConstraint),
+
+    #[error("Sync denied by policy: {0}")]
+    PolicyDenied(String),
+
+    #[error(
+        "Graph engine map is full at {size} bytes and may not grow past its {ceiling} byte \
+         ceiling; raise `engine.map_ceiling` to allow more"
+    )]
+    EngineMapFull { size: u64, ceiling: u64 },
+
     #[error("Heed operation failed: {0}")]
     Heed(#[from] heed3::Error),
 
@@ -114,7 +114,9 @@ pub enum FetchResponse {
     },
 }
 
+use crate::checkpoint::SyncCheckpoint;
 use crate::embedding::EmbeddingProvider;
+use crate::models::EntityIdentifier;
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -157,4 +159,39 @@ pub trait Fetcher: Send + Sync {
         params: serde_json::Value,
         embedding_provider: Arc<dyn EmbeddingProvider>,
     ) -> Result<FetchResponse>;
+
+    /// Entry point used by the synchronizer. Fetchers with expensive pagination
+    /// override it to record their progress in `checkpoint` and to pick it up again
+    /// when a stopped sync is retried; the default ignores the checkpoint.
+    async fn fetch_resumable(
+        &self,
+        params: serde_json::Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+        checkpoint: Arc<SyncCheckpoint>,
+    ) -> Result<FetchResponse> {
+        let _ = checkpoint;
+        self.fetch(params, embedding_provider).await
+    }
+
+    /// Cheap check run before a sync is queued, so requests the fetcher's policy
+    /// forbids are refused up front with [`StorageError::PolicyDenied`]. Fetchers
+    /// must enforce the same policy again while fetching.
+    ///
+    /// [`StorageError::PolicyDenied`]: crate::errors::StorageError::PolicyDenied
+    fn admit(&self, params: &serde_json::Value) -> Result<()> {
+        let _ = params;
+        Ok(())
+    }
+
+    /// Narrows `params` to the target entities of a sync. Fetchers that can fetch
+    /// individual entities translate the identifiers they understand into their own
+    /// params and ignore the rest; the default fetches everything `params` describe.
+    fn scope_params(
+        &self,
+        params: serde_json::Value,
+        targets: &[EntityIdentifier],
+    ) -> Result<serde_json::Value> {
+        let _ = targets;
+        Ok(params)
+    }
 }
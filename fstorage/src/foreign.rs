@@ -0,0 +1,68 @@
+//! External Delta tables attached to the lake without copying them.
+//!
+//! Attaching a foreign table records a name and a Delta table URI in the
+//! catalog. The table then reads like any lake table under the path
+//! `foreign/<name>`: previews, [`Lake::query_table`](crate::lake::Lake::query_table)
+//! and [`Lake::table_sql`](crate::lake::Lake::table_sql) resolve it to the URI.
+//! Any SQL those run can also name it as `foreign_<name>`, which is how lakehouse
+//! data gets joined against the code graph tables. Writes to `foreign/` paths
+//! are refused; the owner of the table keeps writing it where it lives.
+//!
+//! Transforms run synchronously on fetched batches, so one that needs foreign
+//! data reads it through `Lake::table_sql` when it is built and keeps what it
+//! needs in memory.
+
+use crate::errors::{Result, StorageError};
+use url::Url;
+
+/// Lake path prefix under which foreign tables are read.
+pub const FOREIGN_PREFIX: &str = "foreign/";
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Lake path of the foreign table `name`.
+pub fn table_path(name: &str) -> String {
+    format!("{FOREIGN_PREFIX}{name}")
+}
+
+/// Name SQL refers to the foreign table `name` by.
+pub fn sql_name(name: &str) -> String {
+    format!("foreign_{name}")
+}
+
+/// Foreign table names double as SQL identifiers: a lowercase letter followed
+/// by lowercase letters, digits or `_`.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = name.len() <= MAX_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidArg(format!(
+            "invalid foreign table name '{name}': use up to {MAX_NAME_LEN} lowercase letters, \
+             digits or '_', starting with a letter"
+        )))
+    }
+}
+
+/// Parses a table URI, taking an absolute path for the local directory it names.
+pub fn parse_uri(uri: &str) -> Result<Url> {
+    let uri = uri.trim();
+    let path = std::path::Path::new(uri);
+    if path.is_absolute() {
+        return Url::from_directory_path(path)
+            .map_err(|_| StorageError::InvalidArg(format!("invalid table path '{uri}'")));
+    }
+    match Url::parse(uri) {
+        Ok(url) if url.cannot_be_a_base() => Err(StorageError::InvalidArg(format!(
+            "invalid table URI '{uri}'"
+        ))),
+        Ok(url) => Ok(url),
+        Err(err) => Err(StorageError::InvalidArg(format!(
+            "invalid table URI '{uri}': {err}"
+        ))),
+    }
+}
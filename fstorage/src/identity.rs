@@ -0,0 +1,617 @@
+//! Identity resolution between `Developer` nodes and the git identities
+//! commits are authored under.
+//!
+//! GitHub accounts and commit authors are stored as separate facts: a
+//! developer carries a login and perhaps a public name and email, a
+//! `GitIdentity` only the name and email git recorded. A resolution run links
+//! the pairs that look like the same person with `same_as` edges scored by how
+//! they matched:
+//!
+//! - `email`: the developer's email equals the identity's (1.0). Emails hashed
+//!   by a developer privacy mode still compare equal.
+//! - `noreply`: the identity uses the developer's GitHub noreply address,
+//!   `[id+]login@users.noreply.github.com` (0.95 by account id, 0.9 by login).
+//! - `name`: both carry the same name of at least two words, in order (0.8) or
+//!   reordered (0.7).
+//! - `email_login`: the local part of the identity's email is the login (0.6).
+//!
+//! Scores are split between developers a signal fits equally well, and links
+//! under [`MIN_CONFIDENCE`] are not suggested. Reviewers confirm or reject
+//! links through [`decide`]; verdicts are kept in the catalog, so a confirmed
+//! link survives later runs and a rejected one is never suggested again.
+
+use crate::catalog::Catalog;
+use crate::errors::{Result, StorageError};
+use crate::fetch::{Fetchable, GraphData};
+use crate::lake::Lake;
+use crate::models::{
+    IdentityDecision, IdentityMatch, IdentityMatchStatus, IdentityResolutionReport,
+};
+use crate::schemas::generated_schemas::{Developer, GitIdentity, SameAs};
+use crate::sync::{DataSynchronizer, FStorageSynchronizer};
+use crate::utils::id::{stable_edge_id_u128, stable_node_id_u128};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Suggestions scoring lower than this are dropped.
+pub const MIN_CONFIDENCE: f64 = 0.5;
+
+const NOREPLY_DOMAIN: &str = "@users.noreply.github.com";
+
+struct DeveloperNode {
+    account_id: String,
+    login: String,
+    name: Option<String>,
+    email: Option<String>,
+}
+
+struct IdentityNode {
+    email: String,
+    name: Option<String>,
+}
+
+type Pair = (String, String);
+
+pub(crate) fn same_as_table() -> String {
+    format!(
+        "silver/edges/{}",
+        SameAs::ENTITY_TYPE.trim_start_matches("edge_")
+    )
+}
+
+/// Runs identity resolution over every developer and git identity in the
+/// lake and brings the `same_as` edges in line with it.
+pub async fn resolve(
+    catalog: &Catalog,
+    lake: &Lake,
+    synchronizer: &FStorageSynchronizer,
+) -> Result<IdentityResolutionReport> {
+    let developers = load_developers(lake).await?;
+    let identities = load_identities(lake).await?;
+    let existing = load_links(lake).await?;
+    let decisions: HashMap<Pair, IdentityMatchStatus> = catalog
+        .list_identity_decisions()?
+        .into_iter()
+        .map(|decision| {
+            (
+                (decision.developer_id, decision.identity_id),
+                decision.status,
+            )
+        })
+        .collect();
+
+    let mut links: BTreeMap<Pair, (String, f64)> = score(&developers, &identities)
+        .into_iter()
+        .map(|(pair, (method, confidence))| (pair, (method.to_string(), confidence)))
+        .collect();
+    let mut rejected_skipped = 0;
+    links.retain(|pair, _| {
+        let rejected = decisions.get(pair) == Some(&IdentityMatchStatus::Rejected);
+        rejected_skipped += u64::from(rejected);
+        !rejected
+    });
+    // Confirmed links stay even when the signal that suggested them is gone.
+    for (pair, status) in &decisions {
+        if *status == IdentityMatchStatus::Confirmed
+            && developers.contains_key(&pair.0)
+            && identities.contains_key(&pair.1)
+        {
+            links.entry(pair.clone()).or_insert_with(|| {
+                let method = existing
+                    .get(pair)
+                    .map_or("manual", |link| link.method.as_str());
+                (method.to_string(), 1.0)
+            });
+        }
+    }
+
+    let mut matches: Vec<IdentityMatch> = links
+        .into_iter()
+        .map(|((developer_id, identity_id), (method, confidence))| {
+            let status = match decisions.get(&(developer_id.clone(), identity_id.clone())) {
+                Some(IdentityMatchStatus::Confirmed) => IdentityMatchStatus::Confirmed,
+                _ => IdentityMatchStatus::Suggested,
+            };
+            IdentityMatch {
+                login: developers.get(&developer_id).map(|node| node.login.clone()),
+                email: identities.get(&identity_id).map(|node| node.email.clone()),
+                confidence: if status == IdentityMatchStatus::Confirmed {
+                    1.0
+                } else {
+                    confidence
+                },
+                developer_id,
+                identity_id,
+                method,
+                status,
+            }
+        })
+        .collect();
+    write_links(synchronizer, &matches).await?;
+
+    let mut stale_removed = 0;
+    let table = same_as_table();
+    for pair in existing.keys() {
+        let kept = matches
+            .iter()
+            .any(|link| link.developer_id == pair.0 && link.identity_id == pair.1);
+        if !kept && lake.delete_edge(&table, &edge_id(&pair.0, &pair.1)).await? {
+            stale_removed += 1;
+        }
+    }
+
+    sort_matches(&mut matches);
+    Ok(IdentityResolutionReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        developers: developers.len() as u64,
+        identities: identities.len() as u64,
+        matches,
+        rejected_skipped,
+        stale_removed,
+    })
+}
+
+/// The `same_as` links in the graph, followed by the pairs reviewers
+/// rejected.
+pub async fn list(catalog: &Catalog, lake: &Lake) -> Result<Vec<IdentityMatch>> {
+    let developers = load_developers(lake).await?;
+    let identities = load_identities(lake).await?;
+    let mut matches: Vec<IdentityMatch> = load_links(lake).await?.into_values().collect();
+    let mut rejected: Vec<IdentityMatch> = catalog
+        .list_identity_decisions()?
+        .into_iter()
+        .filter(|decision| decision.status == IdentityMatchStatus::Rejected)
+        .map(|decision| IdentityMatch {
+            developer_id: decision.developer_id,
+            identity_id: decision.identity_id,
+            login: None,
+            email: None,
+            method: "manual".to_string(),
+            confidence: 0.0,
+            status: IdentityMatchStatus::Rejected,
+        })
+        .collect();
+    for link in matches.iter_mut().chain(rejected.iter_mut()) {
+        link.login = developers
+            .get(&link.developer_id)
+            .map(|node| node.login.clone());
+        link.email = identities
+            .get(&link.identity_id)
+            .map(|node| node.email.clone());
+    }
+    sort_matches(&mut matches);
+    matches.append(&mut rejected);
+    Ok(matches)
+}
+
+/// Confirms or rejects the link between `developer_id` and `identity_id`,
+/// whether or not it was suggested. A confirmed link is written at full
+/// confidence; a rejected one is removed from the graph.
+pub async fn decide(
+    catalog: &Catalog,
+    lake: &Lake,
+    synchronizer: &FStorageSynchronizer,
+    developer_id: &str,
+    identity_id: &str,
+    status: IdentityMatchStatus,
+) -> Result<IdentityMatch> {
+    if status == IdentityMatchStatus::Suggested {
+        return Err(StorageError::InvalidArg(
+            "a decision either confirms or rejects a match".to_string(),
+        ));
+    }
+    let developer_id = parse_id("developer", developer_id)?;
+    let identity_id = parse_id("git identity", identity_id)?;
+    let developers = load_developers(lake).await?;
+    let identities = load_identities(lake).await?;
+    let developer = developers
+        .get(&developer_id)
+        .ok_or_else(|| StorageError::NotFound(format!("developer '{developer_id}'")))?;
+    let identity = identities
+        .get(&identity_id)
+        .ok_or_else(|| StorageError::NotFound(format!("git identity '{identity_id}'")))?;
+
+    let pair = (developer_id.clone(), identity_id.clone());
+    let existing = load_links(lake).await?.remove(&pair);
+    catalog.put_identity_decision(&IdentityDecision {
+        developer_id: developer_id.clone(),
+        identity_id: identity_id.clone(),
+        status,
+        decided_at: chrono::Utc::now().timestamp(),
+    })?;
+
+    let link = IdentityMatch {
+        login: Some(developer.login.clone()),
+        email: Some(identity.email.clone()),
+        method: existing
+            .as_ref()
+            .map_or_else(|| "manual".to_string(), |link| link.method.clone()),
+        confidence: match status {
+            IdentityMatchStatus::Confirmed => 1.0,
+            _ => existing.as_ref().map_or(0.0, |link| link.confidence),
+        },
+        developer_id,
+        identity_id,
+        status,
+    };
+    if status == IdentityMatchStatus::Confirmed {
+        write_links(synchronizer, std::slice::from_ref(&link)).await?;
+    } else {
+        lake.delete_edge(&same_as_table(), &edge_id(&pair.0, &pair.1))
+            .await?;
+    }
+    Ok(link)
+}
+
+/// Best link per developer/identity pair, as `(method, confidence)`.
+fn score(
+    developers: &BTreeMap<String, DeveloperNode>,
+    identities: &BTreeMap<String, IdentityNode>,
+) -> BTreeMap<Pair, (&'static str, f64)> {
+    let mut by_email: HashMap<String, Vec<&String>> = HashMap::new();
+    let mut by_login: HashMap<String, Vec<&String>> = HashMap::new();
+    let mut by_account: HashMap<&str, Vec<&String>> = HashMap::new();
+    let mut by_name: HashMap<Vec<String>, Vec<(&String, Vec<String>)>> = HashMap::new();
+    for (id, developer) in developers {
+        if let Some(email) = developer.email.as_deref().map(normalize_email) {
+            if !email.is_empty() {
+                by_email.entry(email).or_default().push(id);
+            }
+        }
+        by_login
+            .entry(developer.login.to_lowercase())
+            .or_default()
+            .push(id);
+        by_account
+            .entry(developer.account_id.as_str())
+            .or_default()
+            .push(id);
+        if let Some(words) = developer.name.as_deref().and_then(name_words) {
+            let mut key = words.clone();
+            key.sort();
+            by_name.entry(key).or_default().push((id, words));
+        }
+    }
+
+    let mut best: BTreeMap<Pair, (&'static str, f64)> = BTreeMap::new();
+    let mut offer = |developer: &String, identity: &String, method, confidence: f64| {
+        if confidence < MIN_CONFIDENCE {
+            return;
+        }
+        let entry = best
+            .entry((developer.clone(), identity.clone()))
+            .or_insert((method, confidence));
+        if confidence > entry.1 {
+            *entry = (method, confidence);
+        }
+    };
+    for (identity_id, identity) in identities {
+        let email = normalize_email(&identity.email);
+        if let Some(matched) = by_email.get(&email) {
+            for developer in matched {
+                offer(developer, identity_id, "email", 1.0 / matched.len() as f64);
+            }
+        }
+
+        let local = email.split('@').next().unwrap_or_default();
+        if let Some(noreply) = email.strip_suffix(NOREPLY_DOMAIN) {
+            let (account, login) = match noreply.split_once('+') {
+                Some((account, login)) => (Some(account), login),
+                None => (None, noreply),
+            };
+            if let Some(matched) = account.and_then(|account| by_account.get(account)) {
+                for developer in matched {
+                    offer(
+                        developer,
+                        identity_id,
+                        "noreply",
+                        0.95 / matched.len() as f64,
+                    );
+                }
+            }
+            if let Some(matched) = by_login.get(login) {
+                for developer in matched {
+                    offer(
+                        developer,
+                        identity_id,
+                        "noreply",
+                        0.9 / matched.len() as f64,
+                    );
+                }
+            }
+        } else {
+            let login = local.split('+').next().unwrap_or_default();
+            if let Some(matched) = by_login.get(login) {
+                for developer in matched {
+                    offer(
+                        developer,
+                        identity_id,
+                        "email_login",
+                        0.6 / matched.len() as f64,
+                    );
+                }
+            }
+        }
+
+        if let Some(words) = identity.name.as_deref().and_then(name_words) {
+            let mut key = words.clone();
+            key.sort();
+            if let Some(matched) = by_name.get(&key) {
+                for (developer, developer_words) in matched {
+                    let confidence = if *developer_words == words { 0.8 } else { 0.7 };
+                    offer(
+                        developer,
+                        identity_id,
+                        "name",
+                        confidence / matched.len() as f64,
+                    );
+                }
+            }
+        }
+    }
+    best
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Lowercased alphanumeric words of `name`; `None` for names of fewer than
+/// two words, which say too little about who someone is.
+fn name_words(name: &str) -> Option<Vec<String>> {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    (words.len() >= 2).then_some(words)
+}
+
+fn sort_matches(matches: &mut [IdentityMatch]) {
+    matches.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.login.cmp(&b.login))
+            .then_with(|| a.email.cmp(&b.email))
+    });
+}
+
+fn parse_id(kind: &str, id: &str) -> Result<String> {
+    Uuid::parse_str(id.trim())
+        .map(|id| id.to_string())
+        .map_err(|_| StorageError::InvalidArg(format!("invalid {kind} id '{id}'")))
+}
+
+fn edge_id(developer_id: &str, identity_id: &str) -> String {
+    Uuid::from_u128(stable_edge_id_u128(
+        SameAs::ENTITY_TYPE,
+        developer_id,
+        identity_id,
+    ))
+    .to_string()
+}
+
+fn text(row: &HashMap<String, JsonValue>, column: &str) -> Option<String> {
+    row.get(column)
+        .and_then(JsonValue::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+async fn load_developers(lake: &Lake) -> Result<BTreeMap<String, DeveloperNode>> {
+    let rows = lake
+        .table_sql(
+            &Developer::table_name(),
+            "SELECT platform, account_id, login, name, email FROM {{table}}",
+        )
+        .await?;
+    let mut developers = BTreeMap::new();
+    for row in rows {
+        let (Some(platform), Some(account_id), Some(login)) = (
+            text(&row, "platform"),
+            text(&row, "account_id"),
+            text(&row, "login"),
+        ) else {
+            continue;
+        };
+        let id = stable_node_id_u128(
+            Developer::ENTITY_TYPE,
+            &[
+                ("platform", platform),
+                ("account_id", account_id.clone()),
+                ("login", login.clone()),
+            ],
+        );
+        developers.insert(
+            Uuid::from_u128(id).to_string(),
+            DeveloperNode {
+                account_id,
+                login,
+                name: text(&row, "name"),
+                email: text(&row, "email"),
+            },
+        );
+    }
+    Ok(developers)
+}
+
+async fn load_identities(lake: &Lake) -> Result<BTreeMap<String, IdentityNode>> {
+    let rows = lake
+        .table_sql(
+            &GitIdentity::table_name(),
+            "SELECT email, name FROM {{table}}",
+        )
+        .await?;
+    let mut identities = BTreeMap::new();
+    for row in rows {
+        let Some(email) = text(&row, "email") else {
+            continue;
+        };
+        let id = stable_node_id_u128(GitIdentity::ENTITY_TYPE, &[("email", email.clone())]);
+        identities.insert(
+            Uuid::from_u128(id).to_string(),
+            IdentityNode {
+                email,
+                name: text(&row, "name"),
+            },
+        );
+    }
+    Ok(identities)
+}
+
+/// The `same_as` edges in the lake by developer/identity pair.
+async fn load_links(lake: &Lake) -> Result<BTreeMap<Pair, IdentityMatch>> {
+    let rows = lake
+        .table_sql(
+            &same_as_table(),
+            "SELECT from_node_id, to_node_id, method, confidence, status FROM {{table}}",
+        )
+        .await?;
+    let mut links = BTreeMap::new();
+    for row in rows {
+        let (Some(developer_id), Some(identity_id)) =
+            (text(&row, "from_node_id"), text(&row, "to_node_id"))
+        else {
+            continue;
+        };
+        let status = text(&row, "status")
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(IdentityMatchStatus::Suggested);
+        links.insert(
+            (developer_id.clone(), identity_id.clone()),
+            IdentityMatch {
+                developer_id,
+                identity_id,
+                login: None,
+                email: None,
+                method: text(&row, "method").unwrap_or_else(|| "manual".to_string()),
+                confidence: row
+                    .get("confidence")
+                    .and_then(JsonValue::as_f64)
+                    .unwrap_or_default(),
+                status,
+            },
+        );
+    }
+    Ok(links)
+}
+
+async fn write_links(synchronizer: &FStorageSynchronizer, links: &[IdentityMatch]) -> Result<()> {
+    if links.is_empty() {
+        return Ok(());
+    }
+    let now = chrono::Utc::now();
+    let edges = links
+        .iter()
+        .map(|link| SameAs {
+            id: Some(edge_id(&link.developer_id, &link.identity_id)),
+            from_node_id: Some(link.developer_id.clone()),
+            to_node_id: Some(link.identity_id.clone()),
+            from_node_type: Some(Developer::ENTITY_TYPE.to_string()),
+            to_node_type: Some(GitIdentity::ENTITY_TYPE.to_string()),
+            created_at: Some(now),
+            updated_at: Some(now),
+            method: Some(link.method.clone()),
+            confidence: Some(link.confidence),
+            status: Some(link.status.as_str().to_string()),
+        })
+        .collect();
+    let mut graph = GraphData::new();
+    graph.add_entities::<SameAs>(edges);
+    synchronizer.process_graph_data(graph).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn developer(
+        account_id: &str,
+        login: &str,
+        name: Option<&str>,
+        email: Option<&str>,
+    ) -> DeveloperNode {
+        DeveloperNode {
+            account_id: account_id.to_string(),
+            login: login.to_string(),
+            name: name.map(str::to_string),
+            email: email.map(str::to_string),
+        }
+    }
+
+    fn identity(email: &str, name: Option<&str>) -> IdentityNode {
+        IdentityNode {
+            email: email.to_string(),
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn scores_each_signal_and_keeps_the_best() {
+        let developers = BTreeMap::from([
+            (
+                "d1".to_string(),
+                developer(
+                    "1",
+                    "Octocat",
+                    Some("Mona Lisa Octocat"),
+                    Some("Mona@Example.com"),
+                ),
+            ),
+            (
+                "d2".to_string(),
+                developer("2", "hubot", Some("Hubot"), None),
+            ),
+        ]);
+        let identities = BTreeMap::from([
+            (
+                "i1".to_string(),
+                identity("mona@example.com", Some("Mona Lisa Octocat")),
+            ),
+            (
+                "i2".to_string(),
+                identity("2+hubot@users.noreply.github.com", None),
+            ),
+            ("i3".to_string(), identity("octocat@work.example", None)),
+            (
+                "i4".to_string(),
+                identity("someone@else.example", Some("Octocat Mona Lisa")),
+            ),
+            // Single-word names say too little to suggest anything.
+            ("i5".to_string(), identity("bot@ci.example", Some("Hubot"))),
+        ]);
+        let scored = score(&developers, &identities);
+        let link = |developer: &str, identity: &str| {
+            scored
+                .get(&(developer.to_string(), identity.to_string()))
+                .copied()
+        };
+        assert_eq!(link("d1", "i1"), Some(("email", 1.0)));
+        assert_eq!(link("d2", "i2"), Some(("noreply", 0.95)));
+        assert_eq!(link("d1", "i3"), Some(("email_login", 0.6)));
+        assert_eq!(link("d1", "i4"), Some(("name", 0.7)));
+        assert_eq!(link("d2", "i5"), None);
+        assert_eq!(scored.len(), 4);
+    }
+
+    #[test]
+    fn ambiguous_names_fall_under_the_threshold() {
+        let developers = BTreeMap::from([
+            (
+                "d1".to_string(),
+                developer("1", "jdoe", Some("Jane Doe"), None),
+            ),
+            (
+                "d2".to_string(),
+                developer("2", "jane-d", Some("Jane Doe"), None),
+            ),
+        ]);
+        let identities = BTreeMap::from([(
+            "i1".to_string(),
+            identity("jane@example.com", Some("Jane Doe")),
+        )]);
+        assert!(score(&developers, &identities).is_empty());
+    }
+}
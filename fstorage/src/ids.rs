@@ -0,0 +1,138 @@
+//! Stable ids as the synchronizer derives them, and an audit of the node index
+//! tables for ids that more than one entity ended up with.
+//!
+//! A node id is a UUIDv5 of `entity_type|key=value|...` over the entity's
+//! primary keys in schema order; an edge id one of `label|from|to`. Key values
+//! are joined without escaping, so for entities with several keys a value
+//! containing `|` or `=` can give two different key sets the same name, and
+//! the engine then merges both into one node. [`audit`] finds those rows in
+//! the `silver/index` tables, where each key set keeps its own row.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::EntityCategory;
+use crate::lake::Lake;
+use crate::models::{IdAuditReport, IdCollision, StableId};
+use crate::schema_registry::SCHEMA_REGISTRY;
+use crate::utils::id::{stable_edge_id_u128, stable_node_id_name, uuid_v5_u128};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+const INDEX_PREFIX: &str = "silver/index";
+
+/// Id of the `entity_type` node with primary keys `keys`, which must name
+/// every primary key of the type and nothing else.
+pub fn node_id(entity_type: &str, keys: &BTreeMap<String, String>) -> Result<StableId> {
+    let entity = SCHEMA_REGISTRY
+        .entity(entity_type)
+        .filter(|entity| entity.category == EntityCategory::Node)
+        .ok_or_else(|| StorageError::InvalidArg(format!("unknown node type '{entity_type}'")))?;
+    if entity.primary_keys.is_empty() {
+        return Err(StorageError::InvalidArg(format!(
+            "{entity_type} has no primary keys; its nodes carry their own ids"
+        )));
+    }
+    let expected = entity.primary_keys.join(", ");
+    if let Some(unknown) = keys
+        .keys()
+        .find(|key| !entity.primary_keys.contains(&key.as_str()))
+    {
+        return Err(StorageError::InvalidArg(format!(
+            "'{unknown}' is not a primary key of {entity_type}; expected {expected}"
+        )));
+    }
+    let key_values = entity
+        .primary_keys
+        .iter()
+        .map(|key| {
+            keys.get(*key)
+                .map(|value| (*key, value.clone()))
+                .ok_or_else(|| {
+                    StorageError::InvalidArg(format!(
+                        "missing primary key '{key}' of {entity_type}; expected {expected}"
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let name = stable_node_id_name(entity_type, &key_values);
+    Ok(StableId {
+        id: Uuid::from_u128(uuid_v5_u128(Uuid::NAMESPACE_OID, &name)).to_string(),
+        name,
+    })
+}
+
+/// Id of the `label` edge from node `from` to node `to`.
+pub fn edge_id(label: &str, from: &str, to: &str) -> StableId {
+    StableId {
+        id: Uuid::from_u128(stable_edge_id_u128(label, from, to)).to_string(),
+        name: format!("{label}|{from}|{to}"),
+    }
+}
+
+/// Scans every `silver/index` table for ids shared by distinct key sets.
+pub async fn audit(lake: &Lake) -> Result<IdAuditReport> {
+    let mut report = IdAuditReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        tables: 0,
+        rows: 0,
+        collisions: Vec::new(),
+    };
+    for table in lake.list_tables(INDEX_PREFIX).await? {
+        let Some(entity_type) = table
+            .table_path
+            .strip_prefix(INDEX_PREFIX)
+            .and_then(|rest| rest.strip_prefix('/'))
+        else {
+            continue;
+        };
+        report.tables += 1;
+        report.rows += lake
+            .count_rows(&table.table_path)
+            .await?
+            .unwrap_or_default();
+
+        // Only ids on more than one row can collide; rows repeating a key set
+        // are merge leftovers, not collisions, and are folded below.
+        let rows = lake
+            .table_sql(
+                &table.table_path,
+                "SELECT * FROM {{table}} WHERE id IN \
+                 (SELECT id FROM {{table}} GROUP BY id HAVING COUNT(*) > 1)",
+            )
+            .await?;
+        let mut by_id: BTreeMap<String, BTreeSet<BTreeMap<String, Option<String>>>> =
+            BTreeMap::new();
+        for mut row in rows {
+            let Some(id) = row
+                .remove("id")
+                .and_then(|id| id.as_str().map(str::to_string))
+            else {
+                continue;
+            };
+            row.remove("updated_at");
+            let keys = row
+                .into_iter()
+                .map(|(column, value)| {
+                    let value = match value {
+                        JsonValue::Null => None,
+                        JsonValue::String(text) => Some(text),
+                        other => Some(other.to_string()),
+                    };
+                    (column, value)
+                })
+                .collect();
+            by_id.entry(id).or_default().insert(keys);
+        }
+        report.collisions.extend(
+            by_id
+                .into_iter()
+                .filter(|(_, key_sets)| key_sets.len() > 1)
+                .map(|(id, key_sets)| IdCollision {
+                    entity_type: entity_type.to_string(),
+                    id,
+                    key_sets: key_sets.into_iter().collect(),
+                }),
+        );
+    }
+    Ok(report)
+}
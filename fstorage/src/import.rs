@@ -0,0 +1,210 @@
+//! Import of embeddings computed outside fstorage.
+//!
+//! Teams with their own embedding infrastructure send vector rows that already
+//! carry an `embedding`. The rows are checked against the schema registry and
+//! against the vectors stored for the same type, then written through the
+//! regular sync pipeline; no embedding provider is called.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::{EntityCategory, Fetchable, GraphData};
+use crate::lake::Lake;
+use crate::schema_registry::{EntityMetadata, SCHEMA_REGISTRY};
+use crate::schemas::generated_schemas::{CodeChunk, IssueDoc, PrDoc, ReadmeChunk};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+
+/// A batch of precomputed vectors for one vector entity type.
+///
+/// Rows use the column names of the vector table. Each needs an `embedding` of
+/// `dimension` values and an `embedding_id`, which is the upsert key: importing
+/// a row whose `embedding_id` is already stored updates the lake row and keeps
+/// the engine vector it was first indexed with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingImport {
+    pub entity_type: String,
+    /// Model that produced the embeddings; stamped on rows that omit it.
+    pub model: String,
+    pub dimension: usize,
+    pub rows: Vec<Map<String, JsonValue>>,
+}
+
+impl EmbeddingImport {
+    /// Checks the rows against the registry entry for `entity_type`.
+    pub(crate) fn validate(&mut self) -> Result<&'static EntityMetadata> {
+        let meta = SCHEMA_REGISTRY
+            .entity(&self.entity_type)
+            .filter(|meta| meta.category == EntityCategory::Vector)
+            .ok_or_else(|| {
+                StorageError::InvalidArg(format!(
+                    "'{}' is not a vector entity type",
+                    self.entity_type
+                ))
+            })?;
+        if self.model.trim().is_empty() {
+            return Err(StorageError::InvalidArg(
+                "model must not be empty".to_string(),
+            ));
+        }
+        if self.dimension == 0 {
+            return Err(StorageError::InvalidArg(
+                "dimension must be positive".to_string(),
+            ));
+        }
+
+        for (idx, row) in self.rows.iter_mut().enumerate() {
+            let invalid = |reason: String| StorageError::InvalidArg(format!("row {idx}: {reason}"));
+            if let Some(field) = row.keys().find(|key| !meta.fields.contains(&key.as_str())) {
+                return Err(invalid(format!(
+                    "unknown field '{field}' for {}",
+                    meta.entity_type
+                )));
+            }
+            let values = row
+                .get("embedding")
+                .and_then(JsonValue::as_array)
+                .ok_or_else(|| invalid("embedding must be an array of numbers".to_string()))?;
+            if values.len() != self.dimension {
+                return Err(invalid(format!(
+                    "embedding has {} values, expected {}",
+                    values.len(),
+                    self.dimension
+                )));
+            }
+            if !values
+                .iter()
+                .all(|value| value.as_f64().is_some_and(f64::is_finite))
+            {
+                return Err(invalid(
+                    "embedding values must be finite numbers".to_string(),
+                ));
+            }
+            if !row
+                .get("embedding_id")
+                .and_then(JsonValue::as_str)
+                .is_some_and(|id| !id.trim().is_empty())
+            {
+                return Err(invalid("embedding_id is required".to_string()));
+            }
+            match row.get("embedding_model") {
+                None | Some(JsonValue::Null) => {
+                    row.insert(
+                        "embedding_model".to_string(),
+                        JsonValue::String(self.model.clone()),
+                    );
+                }
+                Some(JsonValue::String(model)) if *model == self.model => {}
+                Some(other) => {
+                    return Err(invalid(format!(
+                        "embedding_model {other} differs from the batch model '{}'",
+                        self.model
+                    )));
+                }
+            }
+        }
+        Ok(meta)
+    }
+
+    /// Rejects the batch when the table already holds vectors of another
+    /// dimension or model, which would make them incomparable in search.
+    pub(crate) async fn check_stored(&self, lake: &Lake, meta: &EntityMetadata) -> Result<()> {
+        let stored = lake
+            .table_sql(
+                meta.table_name,
+                "SELECT embedding_model, array_length(embedding) AS dimension FROM {{table}} \
+                 WHERE array_length(embedding) > 0 LIMIT 1",
+            )
+            .await?;
+        let Some(row) = stored.first() else {
+            return Ok(());
+        };
+        if let Some(dimension) = row.get("dimension").and_then(JsonValue::as_u64) {
+            if dimension as usize != self.dimension {
+                return Err(StorageError::InvalidArg(format!(
+                    "{} vectors are stored with dimension {dimension}, not {}",
+                    meta.entity_type, self.dimension
+                )));
+            }
+        }
+        if let Some(model) = row.get("embedding_model").and_then(JsonValue::as_str) {
+            if model != self.model {
+                return Err(StorageError::InvalidArg(format!(
+                    "{} vectors are stored from model '{model}', not '{}'",
+                    meta.entity_type, self.model
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_graph(self) -> Result<GraphData> {
+        let mut graph = GraphData::new();
+        match self.entity_type.as_str() {
+            ReadmeChunk::ENTITY_TYPE => graph.add_entities(parse_rows::<ReadmeChunk>(self.rows)?),
+            CodeChunk::ENTITY_TYPE => graph.add_entities(parse_rows::<CodeChunk>(self.rows)?),
+            IssueDoc::ENTITY_TYPE => graph.add_entities(parse_rows::<IssueDoc>(self.rows)?),
+            PrDoc::ENTITY_TYPE => graph.add_entities(parse_rows::<PrDoc>(self.rows)?),
+            other => {
+                return Err(StorageError::InvalidArg(format!(
+                    "importing '{other}' vectors is not supported"
+                )));
+            }
+        }
+        Ok(graph)
+    }
+}
+
+fn parse_rows<T: DeserializeOwned>(rows: Vec<Map<String, JsonValue>>) -> Result<Vec<T>> {
+    rows.into_iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            serde_json::from_value(JsonValue::Object(row))
+                .map_err(|err| StorageError::InvalidArg(format!("row {idx}: {err}")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn import(rows: JsonValue) -> EmbeddingImport {
+        serde_json::from_value(json!({
+            "entity_type": "readmechunk",
+            "model": "in-house-v2",
+            "dimension": 3,
+            "rows": rows,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validation_checks_registry_and_dimension() {
+        let mut ok = import(json!([{
+            "embedding_id": "a",
+            "project_url": "https://example.com/x",
+            "embedding": [0.1, 0.2, 0.3],
+        }]));
+        ok.validate().unwrap();
+        assert_eq!(ok.rows[0]["embedding_model"], json!("in-house-v2"));
+        assert_eq!(ok.into_graph().unwrap().entities.len(), 1);
+
+        let mut short = import(json!([{"embedding_id": "a", "embedding": [0.1, 0.2]}]));
+        assert!(short.validate().is_err());
+
+        let mut unknown = import(json!([
+            {"embedding_id": "a", "embedding": [0.1, 0.2, 0.3], "colour": "red"}
+        ]));
+        assert!(unknown.validate().is_err());
+
+        let mut other_model = import(json!([
+            {"embedding_id": "a", "embedding": [0.1, 0.2, 0.3], "embedding_model": "other"}
+        ]));
+        assert!(other_model.validate().is_err());
+
+        let mut not_vector = import(json!([]));
+        not_vector.entity_type = "project".to_string();
+        assert!(not_vector.validate().is_err());
+    }
+}
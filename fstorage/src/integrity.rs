@@ -0,0 +1,159 @@
+//! Integrity checks of the Parquet files behind the lake's Delta tables.
+//!
+//! The first pass that sees a data file records its size and SHA-256 in the
+//! catalog, and later passes compare against that record. Delta never rewrites
+//! a data file, so any difference is damage such as bit-rot. Every file is also
+//! decoded in full, which catches truncated and partially written files on
+//! their first pass too, and files the Delta log references but that are gone
+//! are reported as missing.
+
+use crate::catalog::Catalog;
+use crate::config::StorageConfig;
+use crate::errors::{Result, StorageError};
+use crate::models::{FileChecksum, FileIssue, FileProblem, LakeVerification};
+use crate::table_format::{delta, DeltaTables, TableStore};
+use deltalake::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Checks every data file of every Delta table under the lake directory,
+/// records checksums of files seen for the first time and forgets those of
+/// files no longer in use.
+pub async fn verify(config: &StorageConfig, catalog: &Catalog) -> Result<LakeVerification> {
+    if !config.local_delta_lake() {
+        return Err(StorageError::InvalidArg(
+            "lake verification reads the Delta tables under the lake path".to_string(),
+        ));
+    }
+    let mut known: HashMap<String, FileChecksum> = catalog
+        .list_file_checksums()?
+        .into_iter()
+        .map(|checksum| (checksum.path.clone(), checksum))
+        .collect();
+    let mut report = LakeVerification {
+        verified_at: chrono::Utc::now().timestamp(),
+        tables: 0,
+        files: 0,
+        bytes: 0,
+        recorded: 0,
+        forgotten: 0,
+        issues: Vec::new(),
+    };
+    let mut unchecked_tables = HashSet::new();
+
+    for table_path in DeltaTables::local(&config.lake_path)?.list("").await? {
+        report.tables += 1;
+        let files = match delta::data_files(&config.lake_path.join(&table_path)).await {
+            Ok(files) => files.unwrap_or_default(),
+            Err(err) => {
+                report.issues.push(FileIssue {
+                    path: table_path.clone(),
+                    table_path: table_path.clone(),
+                    problem: FileProblem::UnreadableLog,
+                    detail: err.to_string(),
+                });
+                unchecked_tables.insert(table_path);
+                continue;
+            }
+        };
+        for file in files {
+            report.files += 1;
+            let path = file
+                .strip_prefix(&config.lake_path)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .into_owned();
+            let previous = known.remove(&path);
+            let issue = |problem, detail| FileIssue {
+                path: path.clone(),
+                table_path: table_path.clone(),
+                problem,
+                detail,
+            };
+            let checked = tokio::task::spawn_blocking(move || check_file(file))
+                .await
+                .map_err(|e| StorageError::Other(e.into()))?;
+            let (size, sha256, decode_error) = match checked {
+                Ok(checked) => checked,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    report
+                        .issues
+                        .push(issue(FileProblem::Missing, err.to_string()));
+                    continue;
+                }
+                Err(err) => {
+                    report
+                        .issues
+                        .push(issue(FileProblem::Unreadable, err.to_string()));
+                    continue;
+                }
+            };
+            report.bytes += size;
+
+            match previous {
+                Some(previous) if previous.size != size || previous.sha256 != sha256 => {
+                    report.issues.push(issue(
+                        FileProblem::ChecksumMismatch,
+                        format!(
+                            "recorded {} bytes with sha256 {}, found {size} bytes with sha256 {sha256}",
+                            previous.size, previous.sha256
+                        ),
+                    ));
+                    continue;
+                }
+                Some(previous) => catalog.put_file_checksum(&FileChecksum {
+                    verified_at: report.verified_at,
+                    ..previous
+                })?,
+                // A file that does not decode gets no record, so the damage
+                // is not taken for its original content.
+                None if decode_error.is_none() => {
+                    catalog.put_file_checksum(&FileChecksum {
+                        path: path.clone(),
+                        table_path: table_path.clone(),
+                        size,
+                        sha256,
+                        recorded_at: report.verified_at,
+                        verified_at: report.verified_at,
+                    })?;
+                    report.recorded += 1;
+                }
+                None => {}
+            }
+            if let Some(detail) = decode_error {
+                report.issues.push(issue(FileProblem::Unreadable, detail));
+            }
+        }
+    }
+
+    let stale: Vec<String> = known
+        .into_values()
+        .filter(|checksum| !unchecked_tables.contains(&checksum.table_path))
+        .map(|checksum| checksum.path)
+        .collect();
+    report.forgotten = catalog.delete_file_checksums(&stale)? as u64;
+    Ok(report)
+}
+
+/// Size and SHA-256 of the file at `path` and, when it does not decode as
+/// Parquet, why not.
+fn check_file(path: PathBuf) -> io::Result<(u64, String, Option<String>)> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut File::open(&path)?, &mut hasher)?;
+    let sha256 = format!("{:x}", hasher.finalize());
+    Ok((size, sha256, decode(&path).err()))
+}
+
+fn decode(path: &Path) -> std::result::Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(|e| e.to_string())?;
+    for batch in reader {
+        batch.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -1,9 +1,24 @@
 use crate::config::StorageConfig;
+use crate::engine_map::EngineMap;
 use crate::errors::{Result, StorageError};
+use crate::fetch::Fetchable;
+use crate::foreign::{self, FOREIGN_PREFIX};
+use crate::identity::same_as_table;
+use crate::lake_store::LakeStore;
 use crate::models::{
-    ColumnSummary, HybridSearchHit, MultiEntitySearchHit, PathResult, TableSummary, TextSearchHit,
-    VectorSearchHit,
+    ColumnSummary, EdgeTypeSummary, GraphChange, GraphDiff, GraphExpansion, GraphStatistics,
+    HybridSearchHit, LogRepair, MaintenanceOptions, MultiEntitySearchHit, NodeDeletion, PathResult,
+    PersonalDataErasure, PersonalDataExport, PersonalDataTable, SyncRun, TableMaintenance,
+    TableQueryResult, TableSummary, TextSearchHit, VectorSearchHit,
 };
+use crate::schema_registry::{vector_index, vector_rules, SCHEMA_REGISTRY};
+use crate::schemas::generated_schemas::{GitIdentity, IssueDoc, PrDoc};
+#[cfg(feature = "duckdb")]
+use crate::sql_engine::DuckDbEngine;
+use crate::sql_engine::SqlEngine;
+#[cfg(feature = "iceberg")]
+use crate::table_format::IcebergTables;
+use crate::table_format::{delta, DeltaTables, OpenedTable, TableFormat, TableStore, WriteMode};
 use crate::utils;
 use anyhow::anyhow;
 use chrono::{DateTime, Utc};
@@ -11,24 +26,27 @@ use deltalake::arrow::array::{
     Array, ArrayRef, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
     TimestampMicrosecondArray, UInt32Array, UInt64Array,
 };
-use deltalake::arrow::datatypes::DataType;
+use deltalake::arrow::compute::cast;
+use deltalake::arrow::datatypes::{DataType, TimeUnit};
 use deltalake::arrow::record_batch::RecordBatch;
+use deltalake::datafusion::dataframe::DataFrame;
 use deltalake::datafusion::datasource::MemTable;
 use deltalake::datafusion::datasource::TableProvider;
-use deltalake::datafusion::execution::context::{SessionConfig, SessionContext};
-use deltalake::kernel::Action;
-use deltalake::operations::DeltaOps;
-use deltalake::protocol::SaveMode;
+use deltalake::datafusion::execution::context::{SQLOptions, SessionConfig, SessionContext};
+use deltalake::datafusion::physical_plan::SendableRecordBatchStream;
 use deltalake::DeltaTable;
-use deltalake::DeltaTableBuilder;
-use deltalake::ObjectStore;
-use deltalake::Path;
-use heed3::RoTxn;
-use helix_db::helix_engine::bm25::bm25::BM25;
+use futures::stream::{BoxStream, StreamExt};
+use heed3::{RoTxn, RwTxn};
+use helix_db::helix_engine::bm25::bm25::{BM25Flatten, BM25};
+use helix_db::helix_engine::storage_core::graph_visualization::GraphVisualization;
 use helix_db::helix_engine::storage_core::storage_methods::StorageMethods;
 use helix_db::helix_engine::storage_core::HelixGraphStorage;
 use helix_db::helix_engine::traversal_core::{
-    ops::{g::G, source::n_from_id::NFromIdAdapter, util::paths::ShortestPathAdapter},
+    ops::{
+        g::G,
+        source::n_from_id::NFromIdAdapter,
+        util::{paths::ShortestPathAdapter, update::UpdateAdapter},
+    },
     traversal_value::TraversalValue,
     HelixGraphEngine,
 };
@@ -38,33 +56,43 @@ use helix_db::helix_engine::vector_core::vector::HVector;
 use helix_db::protocol::value::Value as HelixValue;
 use helix_db::utils::items::{Edge, Node};
 use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::instrument;
 use url::Url;
 use uuid::Uuid;
 
-async fn read_parquet_batches(
-    object_store: Arc<dyn ObjectStore>,
-    path: &str,
-) -> Result<Vec<RecordBatch>> {
-    let object_path = Path::from(path);
-    let meta = object_store.get(&object_path).await?.bytes().await?;
-    let reader = deltalake::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
-        meta.clone(),
-    )
-    .map_err(|e| StorageError::Other(e.into()))?
-    .build()
-    .map_err(|e| StorageError::Other(e.into()))?;
-    let mut batches = Vec::new();
-    for batch in reader {
-        batches.push(batch.map_err(|e| StorageError::Other(e.into()))?);
-    }
-    Ok(batches)
-}
+/// How long [`Lake::tables_version`] trusts its last look at the tables.
+pub const TABLES_VERSION_TTL: Duration = Duration::from_secs(1);
 
 pub struct Lake {
     pub(crate) config: StorageConfig,
     engine: Arc<HelixGraphEngine>,
+    /// Held by every engine transaction so the map can grow between them.
+    engine_map: Arc<EngineMap>,
+    /// Identifies this handle, so write counts from another process or an
+    /// earlier run of this one are never mistaken for the current ones.
+    opened_at: u64,
+    /// Delta commits made through this handle.
+    writes: AtomicU64,
+    /// Last [`Lake::tables_version`]: when it was taken, the handle's write
+    /// count then, and the digest.
+    tables_version: Mutex<Option<(Instant, u64, String)>>,
+    /// Row count of each table as of the Delta version it was taken at.
+    row_counts: Mutex<HashMap<String, (i64, u64)>>,
+    /// URIs of the attached foreign tables, by name.
+    foreign_tables: RwLock<BTreeMap<String, Url>>,
+    /// The lake directory or object store the Delta tables are kept in.
+    store: Arc<LakeStore>,
+    /// Where the silver and gold tables are kept, per `StorageConfig::table_format`.
+    tables: Arc<dyn TableStore>,
+    /// One queue per table that writes wait in; see [`Lake::table_writer`].
+    table_writers: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// Runs `query_table_sql` when `StorageConfig::sql_engine` is DuckDB.
+    #[cfg(feature = "duckdb")]
+    duckdb: Option<Arc<DuckDbEngine>>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -94,6 +122,13 @@ pub struct Subgraph {
     pub edges: Vec<HashMap<String, JsonValue>>,
 }
 
+/// Result of [`Lake::stream_table_sql`]: the columns, then the rows one
+/// record batch at a time.
+pub struct TableRowStream {
+    pub columns: Vec<String>,
+    pub rows: BoxStream<'static, Result<Vec<HashMap<String, JsonValue>>>>,
+}
+
 impl Lake {
     fn extract_text_field(map: &HashMap<String, JsonValue>, keys: &[&str]) -> Option<String> {
         for key in keys {
@@ -120,62 +155,162 @@ impl Lake {
     }
 
     pub async fn new(config: StorageConfig, engine: Arc<HelixGraphEngine>) -> Result<Self> {
-        tokio::fs::create_dir_all(&config.lake_path).await?;
-        Ok(Self { config, engine })
+        let store = Arc::new(LakeStore::open(&config)?);
+        let opened_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        let tables: Arc<dyn TableStore> = match &config.table_format {
+            TableFormat::Delta => Arc::new(DeltaTables::new(Arc::clone(&store))),
+            TableFormat::Iceberg(_) if config.lake_store.is_some() => {
+                return Err(StorageError::Config(
+                    "`lake_store` holds Delta tables; Iceberg tables are stored where their \
+                     catalog's properties say"
+                        .into(),
+                ));
+            }
+            #[cfg(feature = "iceberg")]
+            TableFormat::Iceberg(iceberg) => Arc::new(IcebergTables::connect(iceberg).await?),
+            #[cfg(not(feature = "iceberg"))]
+            TableFormat::Iceberg(_) => {
+                return Err(StorageError::Config(
+                    "Iceberg tables need fstorage built with the `iceberg` feature".into(),
+                ));
+            }
+        };
+        #[cfg(feature = "duckdb")]
+        let duckdb = match config.sql_engine {
+            SqlEngine::DuckDb if config.lake_store.is_some() => {
+                return Err(StorageError::Config(
+                    "the DuckDB SQL engine reads table files from the lake directory and \
+                     cannot query a `lake_store`"
+                        .into(),
+                ));
+            }
+            SqlEngine::DuckDb => Some(Arc::new(DuckDbEngine::open(&config.lake_path)?)),
+            SqlEngine::DataFusion => None,
+        };
+        #[cfg(not(feature = "duckdb"))]
+        if config.sql_engine == SqlEngine::DuckDb {
+            return Err(StorageError::Config(
+                "the DuckDB SQL engine needs fstorage built with the `duckdb` feature".into(),
+            ));
+        }
+        let engine_map = Arc::new(EngineMap::new(&config.engine));
+        Ok(Self {
+            config,
+            engine,
+            engine_map,
+            opened_at,
+            writes: AtomicU64::new(0),
+            tables_version: Mutex::new(None),
+            row_counts: Mutex::new(HashMap::new()),
+            foreign_tables: RwLock::new(BTreeMap::new()),
+            store,
+            tables,
+            table_writers: Mutex::new(HashMap::new()),
+            #[cfg(feature = "duckdb")]
+            duckdb,
+        })
     }
 
-    #[inline]
-    fn single_partition_session() -> SessionContext {
-        SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1))
+    pub fn engine_map(&self) -> &Arc<EngineMap> {
+        &self.engine_map
     }
 
-    /// Convert a file path to a URL for Delta Lake operations
-    fn path_to_url(&self, path: &std::path::Path) -> Result<Url> {
-        // Use absolute path instead of canonicalize to avoid errors when path doesn't exist yet
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            std::env::current_dir()
-                .map_err(|e| StorageError::Io(e))?
-                .join(path)
-        };
-
-        // Ensure the path and its parent directories exist
-        std::fs::create_dir_all(&absolute_path).map_err(|e| StorageError::Io(e))?;
+    pub fn store(&self) -> &Arc<LakeStore> {
+        &self.store
+    }
 
-        Url::from_file_path(absolute_path)
-            .map_err(|_| StorageError::Config(format!("Invalid path: {:?}", path)))
+    /// Runs `op` in a read transaction on a blocking thread, so walking the
+    /// graph does not stall the runtime. At most `read_workers` of these run
+    /// at once; the rest wait here without holding a thread.
+    pub async fn read_graph<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Arc<HelixGraphStorage>, &RoTxn<'_>) -> Result<T> + Send + 'static,
+    {
+        let worker = self.engine_map.read_worker().await;
+        let storage = Arc::clone(&self.engine.storage);
+        let map = Arc::clone(&self.engine_map);
+        tokio::task::spawn_blocking(move || {
+            let _worker = worker;
+            let _map = map.hold();
+            let txn = storage.graph_env.read_txn()?;
+            op(&storage, &txn)
+        })
+        .await
+        .map_err(|e| StorageError::SyncError(format!("graph read task failed: {}", e)))?
     }
 
-    // create delta table
-    pub async fn get_or_create_table(&self, table_name: &str) -> Result<DeltaTable> {
-        let table_path = self.config.lake_path.join(table_name);
+    /// Changes whenever this handle commits to a table: an `(opened_at, writes)`
+    /// pair that only moves forward while the process runs.
+    pub fn write_version(&self) -> (u64, u64) {
+        (self.opened_at, self.writes.load(Ordering::Acquire))
+    }
 
-        // 确保父目录存在
-        if let Some(parent) = table_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+    /// Latest version of every table in the lake, vectors and bronze tables
+    /// included, by lake path.
+    async fn all_table_versions(&self) -> Result<BTreeMap<String, i64>> {
+        let mut versions = BTreeMap::new();
+        for table in self.tables.list("").await? {
+            if let Some(version) = self.tables.latest_version(&table).await? {
+                versions.insert(table, version);
+            }
         }
-        tokio::fs::create_dir_all(&table_path).await?;
+        Ok(versions)
+    }
 
-        if let Some(parent) = table_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+    /// Digest of the latest version of every table, which moves with a commit from any
+    /// process. A digest younger than [`TABLES_VERSION_TTL`] is reused unless
+    /// this handle committed since, so commits made elsewhere show up within
+    /// that time.
+    pub async fn tables_version(&self) -> Result<String> {
+        let writes = self.writes.load(Ordering::Acquire);
+        if let Some((taken, seen, digest)) = &*self.tables_version.lock().unwrap() {
+            if *seen == writes && taken.elapsed() < TABLES_VERSION_TTL {
+                return Ok(digest.clone());
+            }
         }
+        let versions = serde_json::to_vec(&self.all_table_versions().await?)?;
+        let digest = Uuid::new_v5(&Uuid::NAMESPACE_OID, &versions)
+            .simple()
+            .to_string();
+        *self.tables_version.lock().unwrap() = Some((Instant::now(), writes, digest.clone()));
+        Ok(digest)
+    }
 
-        let table_uri = self.path_to_url(&table_path)?;
+    fn record_write(&self) {
+        self.writes.fetch_add(1, Ordering::AcqRel);
+    }
 
-        match deltalake::open_table(table_uri.clone()).await {
-            Ok(table) => Ok(table),
-            Err(deltalake::DeltaTableError::NotATable(_)) => {
-                // 如果表尚未初始化，返回一个尚未加载的 DeltaTable 句柄，
-                // 后续写入操作会在第一次写入时创建表并注入 Schema。
-                let table = DeltaTableBuilder::from_uri(table_uri.clone())?.build()?;
-                Ok(table)
-            }
-            Err(e) => Err(StorageError::from(e)),
+    /// Lock that writes to `table_name` take in turn. A merge reads the table
+    /// and overwrites it with the result, so two running at once would each
+    /// drop the other's rows; the lock hands the table to waiting writers in
+    /// the order they arrived.
+    fn table_writer(&self, table_name: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut writers = self.table_writers.lock().unwrap();
+        Arc::clone(writers.entry(table_name.to_string()).or_default())
+    }
+
+    #[inline]
+    fn single_partition_session() -> SessionContext {
+        SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1))
+    }
+
+    // create delta table; the Delta handle is only meaningful for `TableFormat::Delta`
+    pub async fn get_or_create_table(&self, table_name: &str) -> Result<DeltaTable> {
+        Self::ensure_writable(table_name)?;
+        match self.store.open_table(table_name).await? {
+            Some(table) => Ok(table),
+            // 如果表尚未初始化，返回一个尚未加载的 DeltaTable 句柄，
+            // 后续写入操作会在第一次写入时创建表并注入 Schema。
+            None => Ok(self.store.table_builder(table_name)?.build()?),
         }
     }
 
-    /// 将RecordBatch写入指定的Delta Table，支持主键幂等写（基于 `merge_on`）。
+    /// 将RecordBatch写入指定的表，支持主键幂等写（基于 `merge_on`）。
+    #[instrument(name = "lake.write", skip_all, fields(table = table_name, batches = batches.len()))]
     pub async fn write_batches(
         &self,
         table_name: &str,
@@ -185,78 +320,64 @@ impl Lake {
         if batches.is_empty() {
             return Ok(());
         }
+        Self::ensure_writable(table_name)?;
+        let writer = self.table_writer(table_name);
+        let _writing = writer.lock().await;
 
-        let table_path = self.config.lake_path.join(table_name);
-        let table_uri = self.path_to_url(&table_path)?;
-        let delta_log_path = table_path.join("_delta_log");
-        let table_exists = tokio::fs::metadata(&delta_log_path).await.is_ok();
-
-        if !table_exists {
-            let table_display_name = table_name.replace('/', "_");
-            DeltaOps::try_from_uri(table_uri)
-                .await?
-                .write(batches.clone())
-                .with_save_mode(SaveMode::Overwrite)
-                .with_table_name(table_display_name)
+        let Some(existing_table) = self.tables.open(table_name).await? else {
+            self.tables
+                .write(table_name, batches, WriteMode::Overwrite)
                 .await?;
+            self.record_write();
             return Ok(());
-        }
+        };
 
-        if let Some(keys) = merge_on.clone() {
+        if let Some(keys) = merge_on {
             // If the table already exists, rewrite it with de-duplicated data using DataFusion.
-            match deltalake::open_table(table_uri).await {
-                Ok(existing_table) => {
-                    let schema = batches
-                        .get(0)
-                        .map(|b| b.schema())
-                        .ok_or_else(|| StorageError::InvalidArg("Missing batch schema".into()))?;
-
-                    let ctx = Self::single_partition_session();
-                    let mem_table = MemTable::try_new(schema.clone(), vec![batches.clone()])
-                        .map_err(|e| StorageError::Other(e.into()))?;
-                    ctx.register_table("new_data", Arc::new(mem_table))
-                        .map_err(|e| StorageError::Other(e.into()))?;
+            let schema = batches
+                .get(0)
+                .map(|b| b.schema())
+                .ok_or_else(|| StorageError::InvalidArg("Missing batch schema".into()))?;
 
-                    let provider_table = existing_table.clone();
-                    ctx.register_table("existing", Arc::new(provider_table))
-                        .map_err(|e| StorageError::Other(e.into()))?;
+            let ctx = Self::single_partition_session();
+            let mem_table = MemTable::try_new(schema.clone(), vec![batches])
+                .map_err(|e| StorageError::Other(e.into()))?;
+            ctx.register_table("new_data", Arc::new(mem_table))
+                .map_err(|e| StorageError::Other(e.into()))?;
+            ctx.register_table("existing", existing_table.provider)
+                .map_err(|e| StorageError::Other(e.into()))?;
 
-                    let key_list = keys
-                        .iter()
-                        .map(|k| format!("\"{}\"", k))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    let sql = format!(
-                        "SELECT * FROM new_data UNION ALL SELECT existing.* FROM existing LEFT ANTI JOIN new_data USING ({})",
-                        key_list
-                    );
+            let key_list = keys
+                .iter()
+                .map(|k| format!("\"{}\"", k))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!(
+                "SELECT * FROM new_data UNION ALL SELECT existing.* FROM existing LEFT ANTI JOIN new_data USING ({})",
+                key_list
+            );
 
-                    let final_df = ctx
-                        .sql(&sql)
-                        .await
-                        .map_err(|e| StorageError::Other(e.into()))?;
-                    let final_batches = final_df
-                        .collect()
-                        .await
-                        .map_err(|e| StorageError::Other(e.into()))?;
+            let final_df = ctx
+                .sql(&sql)
+                .await
+                .map_err(|e| StorageError::Other(e.into()))?;
+            let final_batches = final_df
+                .collect()
+                .await
+                .map_err(|e| StorageError::Other(e.into()))?;
 
-                    DeltaOps(existing_table)
-                        .write(final_batches)
-                        .with_save_mode(SaveMode::Overwrite)
-                        .await?;
+            self.tables
+                .write(table_name, final_batches, WriteMode::Overwrite)
+                .await?;
+            self.record_write();
 
-                    return Ok(());
-                }
-                Err(e) => {
-                    return Err(StorageError::from(e));
-                }
-            }
+            return Ok(());
         }
 
-        DeltaOps::try_from_uri(table_uri)
-            .await?
-            .write(batches)
+        self.tables
+            .write(table_name, batches, WriteMode::Append)
             .await?;
+        self.record_write();
 
         Ok(())
     }
@@ -313,44 +434,13 @@ impl Lake {
         table_name: &str,
         start_version: i64,
     ) -> Result<(Vec<(i64, Vec<RecordBatch>)>, i64)> {
-        let table_path = self.config.lake_path.join(table_name);
-        let table_uri = self.path_to_url(&table_path)?;
-
-        let mut table = DeltaTableBuilder::from_uri(table_uri)?.build()?;
-        table.load().await?;
-        let latest_version = table.version().unwrap_or(-1);
-
-        if latest_version <= start_version {
-            return Ok((Vec::new(), latest_version));
-        }
-
-        let log_store = table.log_store();
-
-        let mut changes = Vec::new();
-
-        for version in (start_version + 1)..=latest_version {
-            if let Some(bytes) = log_store.read_commit_entry(version).await? {
-                let mut version_batches = Vec::new();
-                for line in bytes.split(|b| *b == b'\n') {
-                    if line.is_empty() {
-                        continue;
-                    }
-                    let action: Action =
-                        serde_json::from_slice(line).map_err(|e| StorageError::Other(e.into()))?;
-                    if let Action::Add(add) = action {
-                        let file_path = add.path;
-                        let batches =
-                            read_parquet_batches(table.object_store(), &file_path).await?;
-                        version_batches.extend(batches);
-                    }
-                }
-                if !version_batches.is_empty() {
-                    changes.push((version, version_batches));
-                }
-            }
-        }
+        self.tables.changes_since(table_name, start_version).await
+    }
 
-        Ok((changes, latest_version))
+    /// Takes malformed entries out of the log of `table_name` and rebuilds its
+    /// checkpoint, see [`TableStore::repair_log`].
+    pub async fn repair_table_log(&self, table_name: &str) -> Result<LogRepair> {
+        self.tables.repair_log(table_name).await
     }
 }
 
@@ -360,6 +450,37 @@ enum Direction {
     In,
 }
 
+#[derive(Clone, Copy)]
+enum IndexOrder<'a> {
+    Recency,
+    RecencyAfter(Option<&'a IndexPosition>),
+}
+
+/// Where a page of [`Lake::search_index_nodes_after`] results stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexPosition {
+    /// `updated_at` of the last row, in microseconds; `None` for a row
+    /// without one.
+    pub updated_at_micros: Option<i64>,
+    pub id: String,
+}
+
+impl IndexPosition {
+    /// The position of `row`, one of the rows an index search returned.
+    pub fn of(row: &HashMap<String, JsonValue>) -> Option<Self> {
+        let id = row.get("id")?.as_str()?.to_string();
+        let updated_at_micros = row
+            .get("updated_at")
+            .and_then(JsonValue::as_str)
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|updated_at| updated_at.timestamp_micros());
+        Some(Self {
+            updated_at_micros,
+            id,
+        })
+    }
+}
+
 impl Lake {
     fn helix_value_to_json(value: &HelixValue) -> JsonValue {
         match value {
@@ -471,6 +592,7 @@ impl Lake {
             for (k, v) in props {
                 json_map.insert(k, Self::helix_value_to_json(&v));
             }
+            utils::source_url::insert_source_url(&mut json_map);
             result.insert("properties".to_string(), JsonValue::Object(json_map));
         } else {
             result.insert("properties".to_string(), JsonValue::Null);
@@ -496,6 +618,7 @@ impl Lake {
             for (key, value) in props {
                 json_map.insert(key, Self::helix_value_to_json(&value));
             }
+            utils::source_url::insert_source_url(&mut json_map);
             result.insert("properties".to_string(), JsonValue::Object(json_map));
         } else {
             result.insert("properties".to_string(), JsonValue::Null);
@@ -571,28 +694,16 @@ impl Lake {
         entity_type: &str,
         node_id: &str,
     ) -> Result<Option<HashMap<String, JsonValue>>> {
-        let index_path = self
-            .config
-            .lake_path
-            .join(format!("silver/index/{}", entity_type));
-        if tokio::fs::metadata(&index_path).await.is_err() {
+        let Some(index_table) = self
+            .open_table(&format!("silver/index/{}", entity_type))
+            .await?
+        else {
             return Ok(None);
-        }
-
-        let table_uri = match self.path_to_url(&index_path) {
-            Ok(uri) => uri,
-            Err(_) => return Ok(None),
-        };
-
-        let index_table = match deltalake::open_table(table_uri).await {
-            Ok(table) => table,
-            Err(deltalake::DeltaTableError::NotATable(_)) => return Ok(None),
-            Err(e) => return Err(StorageError::from(e)),
         };
 
         let ctx = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
         let alias = format!("index_{}", entity_type.replace('-', "_"));
-        ctx.register_table(&alias, Arc::new(index_table))
+        ctx.register_table(&alias, index_table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
         let escaped_id = node_id.replace('\'', "''");
@@ -631,29 +742,18 @@ impl Lake {
             }
         }
 
-        let entity_path = self
-            .config
-            .lake_path
-            .join(format!("silver/entities/{}", entity_type));
-        if tokio::fs::metadata(&entity_path).await.is_err() {
+        let Some(entity_table) = self
+            .open_table(&format!("silver/entities/{}", entity_type))
+            .await?
+        else {
             return Ok(None);
-        }
-
-        let entity_uri = match self.path_to_url(&entity_path) {
-            Ok(uri) => uri,
-            Err(_) => return Ok(None),
-        };
-        let entity_table = match deltalake::open_table(entity_uri).await {
-            Ok(table) => table,
-            Err(deltalake::DeltaTableError::NotATable(_)) => return Ok(None),
-            Err(e) => return Err(StorageError::from(e)),
         };
 
         let entity_ctx =
             SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
         let entity_alias = format!("entity_{}", entity_type.replace('-', "_"));
         entity_ctx
-            .register_table(&entity_alias, Arc::new(entity_table))
+            .register_table(&entity_alias, entity_table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
         let entity_batches = entity_ctx
             .sql(&format!("SELECT * FROM {}", entity_alias))
@@ -676,25 +776,138 @@ impl Lake {
         Ok(None)
     }
 
-    async fn get_available_index_entity_types(&self) -> Result<Vec<String>> {
-        let index_path = self.config.lake_path.join("silver/index");
-        let mut types = Vec::new();
-
-        if tokio::fs::metadata(&index_path).await.is_ok() {
-            let mut entries = tokio::fs::read_dir(&index_path).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(dir_name) = path.file_name().and_then(|name| name.to_str()) {
-                        types.push(dir_name.to_string());
-                    }
+    /// Batched [`Self::lookup_node_in_index`]: one `IN` query against the
+    /// index table and a single scan of the entity table for all `node_ids`.
+    async fn lookup_nodes_in_index(
+        &self,
+        entity_type: &str,
+        node_ids: &[String],
+    ) -> Result<HashMap<String, HashMap<String, JsonValue>>> {
+        let mut found = HashMap::new();
+        if node_ids.is_empty() {
+            return Ok(found);
+        }
+
+        let Some(index_table) = self
+            .open_table(&format!("silver/index/{}", entity_type))
+            .await?
+        else {
+            return Ok(found);
+        };
+        let ctx = Self::single_partition_session();
+        ctx.register_table("node_index", index_table.provider)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        let values: Vec<String> = node_ids
+            .iter()
+            .map(|id| format!("'{}'", id.replace('\'', "''")))
+            .collect();
+        let index_batches = ctx
+            .sql(&format!(
+                "SELECT * FROM node_index WHERE id IN ({})",
+                values.join(", ")
+            ))
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?
+            .collect()
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+
+        // Primary-key values of each requested node, in index column order.
+        let mut pk_columns: Vec<String> = Vec::new();
+        let mut ids_by_key: HashMap<Vec<Option<String>>, Vec<String>> = HashMap::new();
+        for batch in &index_batches {
+            let schema = batch.schema();
+            let Ok(id_idx) = schema.index_of("id") else {
+                continue;
+            };
+            let key_indices: Vec<usize> = (0..schema.fields().len())
+                .filter(|idx| !matches!(schema.field(*idx).name().as_str(), "id" | "updated_at"))
+                .collect();
+            pk_columns = key_indices
+                .iter()
+                .map(|idx| schema.field(*idx).name().clone())
+                .collect();
+            for row in 0..batch.num_rows() {
+                let Some(id) = Self::arrow_cell_to_json(batch.column(id_idx), row)
+                    .and_then(|value| Self::json_value_to_string(&value))
+                else {
+                    continue;
+                };
+                let key = key_indices
+                    .iter()
+                    .map(|idx| Self::cell_to_key_string(batch.column(*idx), row))
+                    .collect();
+                ids_by_key.entry(key).or_default().push(id);
+            }
+        }
+        if ids_by_key.is_empty() {
+            return Ok(found);
+        }
+
+        let Some(entity_table) = self
+            .open_table(&format!("silver/entities/{}", entity_type))
+            .await?
+        else {
+            return Ok(found);
+        };
+        let entity_ctx = Self::single_partition_session();
+        entity_ctx
+            .register_table("entity", entity_table.provider)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        let entity_batches = entity_ctx
+            .sql("SELECT * FROM entity")
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?
+            .collect()
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+
+        for batch in entity_batches {
+            let schema = batch.schema();
+            let Ok(key_indices) = pk_columns
+                .iter()
+                .map(|column| schema.index_of(column))
+                .collect::<std::result::Result<Vec<usize>, _>>()
+            else {
+                continue;
+            };
+            for row in 0..batch.num_rows() {
+                let key: Vec<Option<String>> = key_indices
+                    .iter()
+                    .map(|idx| Self::cell_to_key_string(batch.column(*idx), row))
+                    .collect();
+                let Some(ids) = ids_by_key.remove(&key) else {
+                    continue;
+                };
+                let map = Self::record_batch_row_to_map(&batch, row)?;
+                for id in ids {
+                    let mut node = map.clone();
+                    node.insert("id".to_string(), JsonValue::String(id.clone()));
+                    found.insert(id, node);
                 }
             }
         }
+        Ok(found)
+    }
+
+    fn cell_to_key_string(column: &ArrayRef, row: usize) -> Option<String> {
+        if column.is_null(row) {
+            return None;
+        }
+        Self::arrow_cell_to_json(column, row).and_then(|value| Self::json_value_to_string(&value))
+    }
 
-        Ok(types)
+    async fn get_available_index_entity_types(&self) -> Result<Vec<String>> {
+        Ok(self
+            .tables
+            .list("silver/index")
+            .await?
+            .iter()
+            .filter_map(|path| path.rsplit('/').next().map(str::to_string))
+            .collect())
     }
 
+    #[instrument(name = "lake.node", skip(self))]
     pub async fn get_node_by_id(
         &self,
         id: &str,
@@ -702,20 +915,21 @@ impl Lake {
     ) -> Result<Option<HashMap<String, JsonValue>>> {
         if let Ok(uuid) = Uuid::parse_str(id) {
             let node_key = uuid.as_u128();
-            let txn = self.engine.storage.graph_env.read_txn()?;
-            if let Ok(node) = self.engine.storage.get_node(&txn, &node_key) {
-                return Ok(Some(Self::node_to_map(node)));
-            }
-
-            match self
-                .engine
-                .storage
-                .vectors
-                .get_vector(&txn, node_key, 0, true)
-            {
-                Ok(vector) => return Ok(Some(Self::vector_to_node_map(&vector))),
-                Err(VectorError::VectorNotFound(_)) | Err(VectorError::EntryPointNotFound) => {}
-                Err(err) => return Err(StorageError::Graph(err.into())),
+            let found = self
+                .read_graph(move |storage, txn| {
+                    if let Ok(node) = storage.get_node(txn, &node_key) {
+                        return Ok(Some(Self::node_to_map(node)));
+                    }
+                    match storage.vectors.get_vector(txn, node_key, 0, true) {
+                        Ok(vector) => Ok(Some(Self::vector_to_node_map(&vector))),
+                        Err(VectorError::VectorNotFound(_))
+                        | Err(VectorError::EntryPointNotFound) => Ok(None),
+                        Err(err) => Err(StorageError::Graph(err.into())),
+                    }
+                })
+                .await?;
+            if found.is_some() {
+                return Ok(found);
             }
         }
 
@@ -734,6 +948,62 @@ impl Lake {
         Ok(None)
     }
 
+    /// Resolves many ids at once, like [`Self::get_node_by_id`] without a type
+    /// hint. Engine nodes and vectors are read in one transaction; remaining
+    /// ids are looked up per index table in a single query. Ids that match
+    /// nothing are left out of the result.
+    #[instrument(name = "lake.nodes", skip_all, fields(ids = ids.len()))]
+    pub async fn get_nodes_by_ids(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, HashMap<String, JsonValue>>> {
+        let ids = ids.to_vec();
+        let (mut found, mut pending) = self
+            .read_graph(move |storage, txn| {
+                let mut found = HashMap::new();
+                let mut pending: Vec<String> = Vec::new();
+                for id in ids {
+                    if found.contains_key(&id) || pending.contains(&id) {
+                        continue;
+                    }
+                    let Ok(uuid) = Uuid::parse_str(&id) else {
+                        pending.push(id);
+                        continue;
+                    };
+                    let node_key = uuid.as_u128();
+                    if let Ok(node) = storage.get_node(txn, &node_key) {
+                        found.insert(id, Self::node_to_map(node));
+                        continue;
+                    }
+                    match storage.vectors.get_vector(txn, node_key, 0, true) {
+                        Ok(vector) => {
+                            found.insert(id, Self::vector_to_node_map(&vector));
+                        }
+                        Err(VectorError::VectorNotFound(_))
+                        | Err(VectorError::EntryPointNotFound) => {
+                            pending.push(id);
+                        }
+                        Err(err) => return Err(StorageError::Graph(err.into())),
+                    }
+                }
+                Ok((found, pending))
+            })
+            .await?;
+
+        if pending.is_empty() {
+            return Ok(found);
+        }
+        for entity_type in self.get_available_index_entity_types().await? {
+            let resolved = self.lookup_nodes_in_index(&entity_type, &pending).await?;
+            pending.retain(|id| !resolved.contains_key(id));
+            found.extend(resolved);
+            if pending.is_empty() {
+                break;
+            }
+        }
+        Ok(found)
+    }
+
     pub async fn get_node_by_keys(
         &self,
         entity_type: &str,
@@ -770,20 +1040,12 @@ impl Lake {
             return Ok(HashMap::new());
         }
 
-        let table_path = self.config.lake_path.join(index_table);
-        if tokio::fs::metadata(&table_path).await.is_err() {
+        let Some(table) = self.open_table(index_table).await? else {
             return Ok(HashMap::new());
-        }
-
-        let table_uri = self.path_to_url(&table_path)?;
-        let table = match deltalake::open_table(table_uri).await {
-            Ok(table) => table,
-            Err(deltalake::DeltaTableError::NotATable(_)) => return Ok(HashMap::new()),
-            Err(err) => return Err(StorageError::from(err)),
         };
 
         let ctx = Self::single_partition_session();
-        ctx.register_table("vector_index", Arc::new(table))
+        ctx.register_table("vector_index", table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
         let escaped_ids: Vec<String> = ids
@@ -849,6 +1111,7 @@ impl Lake {
         Ok(map)
     }
 
+    #[instrument(name = "lake.neighbors", skip(self, edge_types))]
     pub async fn neighbors(
         &self,
         node_id: &str,
@@ -954,6 +1217,7 @@ impl Lake {
         Ok(results)
     }
 
+    #[instrument(name = "lake.subgraph", skip(self, edge_types))]
     pub async fn subgraph_bfs(
         &self,
         start_id: &str,
@@ -994,116 +1258,112 @@ impl Lake {
                 .collect::<HashSet<String>>()
         });
 
-        let txn = self.engine.storage.graph_env.read_txn()?;
-
-        while let Some((node_key, level)) = queue.pop_front() {
-            if visited_nodes.contains(&node_key) {
-                continue;
-            }
-
-            let node_map = match self.engine.storage.get_node(&txn, &node_key) {
-                Ok(node) => Self::node_to_map(node),
-                Err(GraphError::NodeNotFound) => {
-                    if missing_vector_nodes.contains(&node_key) {
-                        visited_nodes.insert(node_key);
-                        continue;
-                    }
+        self.read_graph(move |storage, txn| {
+            while let Some((node_key, level)) = queue.pop_front() {
+                if visited_nodes.contains(&node_key) {
+                    continue;
+                }
 
-                    match self
-                        .engine
-                        .storage
-                        .vectors
-                        .get_vector(&txn, node_key, 0, true)
-                    {
-                        Ok(vector) => {
-                            known_vector_nodes.insert(node_key);
-                            Self::vector_to_node_map(&vector)
-                        }
-                        Err(VectorError::VectorNotFound(_))
-                        | Err(VectorError::EntryPointNotFound) => {
-                            missing_vector_nodes.insert(node_key);
+                let node_map = match storage.get_node(txn, &node_key) {
+                    Ok(node) => Self::node_to_map(node),
+                    Err(GraphError::NodeNotFound) => {
+                        if missing_vector_nodes.contains(&node_key) {
                             visited_nodes.insert(node_key);
                             continue;
                         }
-                        Err(err) => return Err(StorageError::Graph(err.into())),
-                    }
-                }
-                Err(other) => return Err(StorageError::from(other)),
-            };
-
-            if included_nodes.insert(node_key) {
-                nodes.push(node_map.clone());
-            }
-            visited_nodes.insert(node_key);
-
-            if node_cap != usize::MAX && nodes.len() >= node_cap {
-                return Ok(Subgraph { nodes, edges });
-            }
 
-            if level >= depth {
-                continue;
-            }
+                        match storage.vectors.get_vector(txn, node_key, 0, true) {
+                            Ok(vector) => {
+                                known_vector_nodes.insert(node_key);
+                                Self::vector_to_node_map(&vector)
+                            }
+                            Err(VectorError::VectorNotFound(_))
+                            | Err(VectorError::EntryPointNotFound) => {
+                                missing_vector_nodes.insert(node_key);
+                                visited_nodes.insert(node_key);
+                                continue;
+                            }
+                            Err(err) => return Err(StorageError::Graph(err.into())),
+                        }
+                    }
+                    Err(other) => return Err(StorageError::from(other)),
+                };
 
-            let prefix = node_key.to_be_bytes();
-            let iter = self
-                .engine
-                .storage
-                .out_edges_db
-                .prefix_iter(&txn, &prefix)?;
+                if included_nodes.insert(node_key) {
+                    nodes.push(node_map.clone());
+                }
+                visited_nodes.insert(node_key);
 
-            for entry in iter {
-                if edge_cap != usize::MAX && edges.len() >= edge_cap {
-                    break;
+                if node_cap != usize::MAX && nodes.len() >= node_cap {
+                    return Ok(Subgraph { nodes, edges });
                 }
 
-                let (_raw_key, raw_value) = entry?;
-                let (edge_id, next_node_id) =
-                    HelixGraphStorage::unpack_adj_edge_data(raw_value.as_ref())?;
-                if seen_edges.contains(&edge_id) {
+                if level >= depth {
                     continue;
                 }
 
-                let edge = match self.engine.storage.get_edge(&txn, &edge_id) {
-                    Ok(edge) => edge,
-                    Err(GraphError::EdgeNotFound) => continue,
-                    Err(other) => return Err(StorageError::from(other)),
-                };
+                let prefix = node_key.to_be_bytes();
+                let iter = storage.out_edges_db.prefix_iter(txn, &prefix)?;
+
+                for entry in iter {
+                    if edge_cap != usize::MAX && edges.len() >= edge_cap {
+                        break;
+                    }
 
-                if let Some(ref allowed) = allowed_edge_types {
-                    if !allowed.contains(&edge.label) {
+                    let (_raw_key, raw_value) = entry?;
+                    let (edge_id, next_node_id) =
+                        HelixGraphStorage::unpack_adj_edge_data(raw_value.as_ref())?;
+                    if seen_edges.contains(&edge_id) {
                         continue;
                     }
-                }
 
-                let mut neighbor_map: Option<HashMap<String, JsonValue>> = None;
-                if !included_nodes.contains(&next_node_id) {
-                    neighbor_map = self.load_node_map_for_id(
-                        &txn,
-                        next_node_id,
-                        &mut known_vector_nodes,
-                        &mut missing_vector_nodes,
-                    )?;
-                    if neighbor_map.is_none() {
+                    let edge = match storage.get_edge(txn, &edge_id) {
+                        Ok(edge) => edge,
+                        Err(GraphError::EdgeNotFound) => continue,
+                        Err(other) => return Err(StorageError::from(other)),
+                    };
+
+                    if let Some(ref allowed) = allowed_edge_types {
+                        if !allowed.contains(&edge.label) {
+                            continue;
+                        }
+                    }
+
+                    let mut neighbor_map: Option<HashMap<String, JsonValue>> = None;
+                    if !included_nodes.contains(&next_node_id) {
+                        neighbor_map = Self::load_node_map_for_id(
+                            storage,
+                            txn,
+                            next_node_id,
+                            &mut known_vector_nodes,
+                            &mut missing_vector_nodes,
+                        )?;
+                        if neighbor_map.is_none() {
+                            continue;
+                        }
+                    } else if missing_vector_nodes.contains(&next_node_id) {
                         continue;
                     }
-                } else if missing_vector_nodes.contains(&next_node_id) {
-                    continue;
-                }
 
-                edges.push(Self::edge_to_map(edge));
-                seen_edges.insert(edge_id);
+                    edges.push(Self::edge_to_map(edge));
+                    seen_edges.insert(edge_id);
 
-                if let Some(map) = neighbor_map {
-                    if included_nodes.insert(next_node_id) {
-                        nodes.push(map);
+                    if let Some(map) = neighbor_map {
+                        if included_nodes.insert(next_node_id) {
+                            nodes.push(map);
+                        }
+                        if node_cap != usize::MAX && nodes.len() >= node_cap {
+                            return Ok(Subgraph { nodes, edges });
+                        }
                     }
-                    if node_cap != usize::MAX && nodes.len() >= node_cap {
-                        return Ok(Subgraph { nodes, edges });
+
+                    if !visited_nodes.contains(&next_node_id) && level + 1 <= depth {
+                        queue.push_back((next_node_id, level + 1));
                     }
-                }
 
-                if !visited_nodes.contains(&next_node_id) && level + 1 <= depth {
-                    queue.push_back((next_node_id, level + 1));
+                    if edge_cap != usize::MAX && edges.len() >= edge_cap {
+                        break;
+                    }
                 }
 
                 if edge_cap != usize::MAX && edges.len() >= edge_cap {
@@ -1111,14 +1371,12 @@ impl Lake {
                 }
             }
 
-            if edge_cap != usize::MAX && edges.len() >= edge_cap {
-                break;
-            }
-        }
-
-        Ok(Subgraph { nodes, edges })
+            Ok(Subgraph { nodes, edges })
+        })
+        .await
     }
 
+    #[instrument(name = "lake.shortest_path", skip(self))]
     pub async fn shortest_path(
         &self,
         from_id: &str,
@@ -1133,63 +1391,61 @@ impl Lake {
         let from_key = from_uuid.as_u128();
         let to_key = to_uuid.as_u128();
 
-        let txn = self.engine.storage.graph_env.read_txn()?;
+        let edge_label = edge_label.map(str::to_string);
 
-        if self.engine.storage.get_node(&txn, &from_key).is_err() {
-            return Ok(None);
-        }
+        self.read_graph(move |storage, txn| {
+            if storage.get_node(txn, &from_key).is_err() {
+                return Ok(None);
+            }
 
-        if self.engine.storage.get_node(&txn, &to_key).is_err() {
-            return Ok(None);
-        }
+            if storage.get_node(txn, &to_key).is_err() {
+                return Ok(None);
+            }
 
-        let mut iterator = G::new(self.engine.storage.clone(), &txn)
-            .n_from_id(&to_key)
-            .shortest_path(edge_label, Some(&from_key), None);
-
-        while let Some(item) = iterator.next() {
-            match item {
-                Ok(TraversalValue::Path((path_nodes, path_edges))) => {
-                    let nodes: Vec<HashMap<String, JsonValue>> =
-                        path_nodes.into_iter().map(Self::node_to_map).collect();
-                    let edges: Vec<HashMap<String, JsonValue>> =
-                        path_edges.into_iter().map(Self::edge_to_map).collect();
-                    return Ok(Some(PathResult {
-                        length: edges.len(),
-                        nodes,
-                        edges,
-                    }));
-                }
-                Ok(_) => continue,
-                Err(GraphError::ShortestPathNotFound) | Err(GraphError::NodeNotFound) => {
-                    return Ok(None)
+            let mut iterator = G::new(storage.clone(), txn)
+                .n_from_id(&to_key)
+                .shortest_path(edge_label.as_deref(), Some(&from_key), None);
+
+            while let Some(item) = iterator.next() {
+                match item {
+                    Ok(TraversalValue::Path((path_nodes, path_edges))) => {
+                        let nodes: Vec<HashMap<String, JsonValue>> =
+                            path_nodes.into_iter().map(Self::node_to_map).collect();
+                        let edges: Vec<HashMap<String, JsonValue>> =
+                            path_edges.into_iter().map(Self::edge_to_map).collect();
+                        return Ok(Some(PathResult {
+                            length: edges.len(),
+                            nodes,
+                            edges,
+                        }));
+                    }
+                    Ok(_) => continue,
+                    Err(GraphError::ShortestPathNotFound) | Err(GraphError::NodeNotFound) => {
+                        return Ok(None)
+                    }
+                    Err(err) => return Err(StorageError::Graph(err.into())),
                 }
-                Err(err) => return Err(StorageError::Graph(err.into())),
             }
-        }
 
-        Ok(None)
+            Ok(None)
+        })
+        .await
     }
 
     fn load_node_map_for_id(
-        &self,
+        storage: &HelixGraphStorage,
         txn: &RoTxn,
         node_id: u128,
         known_vector_nodes: &mut HashSet<u128>,
         missing_vector_nodes: &mut HashSet<u128>,
     ) -> Result<Option<HashMap<String, JsonValue>>> {
-        match self.engine.storage.get_node(txn, &node_id) {
+        match storage.get_node(txn, &node_id) {
             Ok(node) => Ok(Some(Self::node_to_map(node))),
             Err(GraphError::NodeNotFound) => {
                 if missing_vector_nodes.contains(&node_id) {
                     return Ok(None);
                 }
-                match self
-                    .engine
-                    .storage
-                    .vectors
-                    .get_vector(txn, node_id, 0, true)
-                {
+                match storage.vectors.get_vector(txn, node_id, 0, true) {
                     Ok(vector) => {
                         known_vector_nodes.insert(node_id);
                         Ok(Some(Self::vector_to_node_map(&vector)))
@@ -1205,19 +1461,20 @@ impl Lake {
         }
     }
 
+    #[instrument(name = "lake.query", skip(self, filters))]
     pub async fn query_table(
         &self,
         table_name: &str,
         filters: Option<&[(&str, &str)]>,
         limit: Option<usize>,
     ) -> Result<Vec<HashMap<String, JsonValue>>> {
-        let Some(table) = self.open_delta_table(table_name).await? else {
+        let Some(table) = self.open_table(table_name).await? else {
             return Ok(Vec::new());
         };
 
         let ctx = Self::single_partition_session();
         let alias = Self::sanitize_table_alias(table_name);
-        ctx.register_table(&alias, Arc::new(table))
+        ctx.register_table(&alias, table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
         let mut clauses = Vec::new();
@@ -1255,6 +1512,32 @@ impl Lake {
         entity_type: &str,
         query: &str,
         limit: usize,
+    ) -> Result<Vec<HashMap<String, JsonValue>>> {
+        self.search_index_rows(entity_type, query, IndexOrder::Recency, limit)
+            .await
+    }
+
+    /// Like [`Lake::search_index_nodes`], newest first with ties broken by id,
+    /// and starting strictly after `after`, so callers can page through
+    /// matches without rescanning. Rows without `updated_at` come last.
+    pub async fn search_index_nodes_after(
+        &self,
+        entity_type: &str,
+        query: &str,
+        after: Option<&IndexPosition>,
+        limit: usize,
+    ) -> Result<Vec<HashMap<String, JsonValue>>> {
+        self.search_index_rows(entity_type, query, IndexOrder::RecencyAfter(after), limit)
+            .await
+    }
+
+    #[instrument(name = "lake.search_index", skip(self, query, order))]
+    async fn search_index_rows(
+        &self,
+        entity_type: &str,
+        query: &str,
+        order: IndexOrder<'_>,
+        limit: usize,
     ) -> Result<Vec<HashMap<String, JsonValue>>> {
         if limit == 0 {
             return Ok(Vec::new());
@@ -1265,11 +1548,11 @@ impl Lake {
         }
 
         let table_name = format!("silver/index/{entity_type}");
-        let Some(table) = self.open_delta_table(&table_name).await? else {
+        let Some(table) = self.open_table(&table_name).await? else {
             return Ok(Vec::new());
         };
 
-        let schema = table.schema();
+        let schema = table.provider.schema();
         let mut clauses = Vec::new();
         let mut has_updated_at = false;
         let lowered_query = trimmed.to_lowercase();
@@ -1288,7 +1571,8 @@ impl Lake {
                         clauses.push(format!("LOWER({identifier}) LIKE '{escaped_text_pattern}'"));
                     }
                 }
-                DataType::Timestamp(_, _) => {
+                // Cursors compare `updated_at` as microseconds.
+                DataType::Timestamp(TimeUnit::Microsecond, _) => {
                     if field.name() == "updated_at" {
                         has_updated_at = true;
                     }
@@ -1303,14 +1587,33 @@ impl Lake {
 
         let alias = Self::sanitize_table_alias(&table_name);
         let ctx = Self::single_partition_session();
-        ctx.register_table(&alias, Arc::new(table))
+        ctx.register_table(&alias, table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
-        let where_clause = clauses.join(" OR ");
-        let order_clause = if has_updated_at {
-            " ORDER BY updated_at DESC"
-        } else {
-            ""
+        let mut where_clause = format!("({})", clauses.join(" OR "));
+        let order_clause = match order {
+            IndexOrder::Recency if has_updated_at => " ORDER BY updated_at DESC",
+            IndexOrder::Recency => "",
+            IndexOrder::RecencyAfter(after) => {
+                if let Some(after) = after {
+                    let id = Self::escape_sql_literal(&after.id);
+                    let keyset = match after.updated_at_micros {
+                        _ if !has_updated_at => format!("id > '{id}'"),
+                        Some(micros) => format!(
+                            "updated_at IS NULL \
+                             OR CAST(updated_at AS BIGINT) < {micros} \
+                             OR (CAST(updated_at AS BIGINT) = {micros} AND id > '{id}')"
+                        ),
+                        None => format!("updated_at IS NULL AND id > '{id}'"),
+                    };
+                    where_clause.push_str(&format!(" AND ({keyset})"));
+                }
+                if has_updated_at {
+                    " ORDER BY updated_at DESC NULLS LAST, id ASC"
+                } else {
+                    " ORDER BY id ASC"
+                }
+            }
         };
         let sql = format!(
             "SELECT * FROM {alias} WHERE {where_clause}{order_clause} LIMIT {limit}",
@@ -1331,18 +1634,25 @@ impl Lake {
         Self::record_batches_to_maps(&batches)
     }
 
+    #[instrument(name = "lake.sql", skip(self, sql))]
     pub async fn table_sql(
         &self,
         table_name: &str,
         sql: &str,
     ) -> Result<Vec<HashMap<String, JsonValue>>> {
-        let Some(table) = self.open_delta_table(table_name).await? else {
+        let batches = self.table_sql_batches(table_name, sql).await?;
+        Self::record_batches_to_maps(&batches)
+    }
+
+    /// [`Lake::table_sql`] returning the Arrow record batches as read.
+    pub async fn table_sql_batches(&self, table_name: &str, sql: &str) -> Result<Vec<RecordBatch>> {
+        let Some(table) = self.open_table(table_name).await? else {
             return Ok(Vec::new());
         };
 
         let ctx = Self::single_partition_session();
         let alias = Self::sanitize_table_alias(table_name);
-        ctx.register_table(&alias, Arc::new(table))
+        ctx.register_table(&alias, table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
         let final_sql = if sql.contains("{{table}}") {
@@ -1350,16 +1660,194 @@ impl Lake {
         } else {
             sql.to_string()
         };
+        self.register_foreign_tables(&ctx, &final_sql, &alias)
+            .await?;
 
-        let batches = ctx
-            .sql(&final_sql)
+        ctx.sql(&final_sql)
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?
+            .collect()
             .await
+            .map_err(|e| StorageError::Other(e.into()))
+    }
+
+    /// Runs a read-only statement against `table_name` and keeps at most `max_rows` rows.
+    ///
+    /// Meant for ad-hoc inspection: unlike [`Lake::table_sql`], DDL, DML and other
+    /// statements that could modify state are refused, and a missing table is an error.
+    #[instrument(name = "lake.sql", skip(self, sql))]
+    pub async fn query_table_sql(
+        &self,
+        table_name: &str,
+        sql: &str,
+        max_rows: usize,
+    ) -> Result<TableQueryResult> {
+        Self::validate_table_path(table_name)?;
+        #[cfg(feature = "duckdb")]
+        if let Some(result) = self
+            .query_table_sql_duckdb(table_name, sql, max_rows)
+            .await?
+        {
+            return Ok(result);
+        }
+        let frame = self.read_only_sql_frame(table_name, sql).await?;
+        Self::collect_capped(frame, max_rows).await
+    }
+
+    /// Runs a read-only DataFusion statement over every lake table at once,
+    /// keeping at most `max_rows` rows. Each table is registered under its
+    /// path with non-alphanumeric characters replaced by `_`, e.g.
+    /// `silver_entities_project`; attached foreign tables as `foreign_<name>`.
+    #[instrument(name = "lake.sql_all", skip(self, sql))]
+    pub async fn query_lake_sql(&self, sql: &str, max_rows: usize) -> Result<TableQueryResult> {
+        let ctx = Self::single_partition_session();
+        for table_path in self.tables.list("").await? {
+            let Some(table) = self.tables.open(&table_path).await? else {
+                continue;
+            };
+            ctx.register_table(&Self::sanitize_table_alias(&table_path), table.provider)
+                .map_err(|e| StorageError::Other(e.into()))?;
+        }
+        self.register_foreign_tables(&ctx, sql, "").await?;
+        let frame = Self::plan_read_only(&ctx, sql).await?;
+        Self::collect_capped(frame, max_rows).await
+    }
+
+    /// Collects at most `max_rows` rows of `frame`, noting whether there were more.
+    async fn collect_capped(frame: DataFrame, max_rows: usize) -> Result<TableQueryResult> {
+        let columns = Self::frame_columns(&frame);
+
+        // One extra row tells us whether the cap cut the result short.
+        let batches = frame
+            .limit(0, Some(max_rows.saturating_add(1)))
             .map_err(|e| StorageError::Other(e.into()))?
             .collect()
             .await
             .map_err(|e| StorageError::Other(e.into()))?;
+        let mut rows = Self::record_batches_to_maps(&batches)?;
+        let truncated = rows.len() > max_rows;
+        rows.truncate(max_rows);
+
+        Ok(TableQueryResult {
+            columns,
+            rows,
+            truncated,
+            engine: SqlEngine::DataFusion,
+        })
+    }
 
-        Self::record_batches_to_maps(&batches)
+    /// Like [`Lake::query_table_sql`], but hands the rows back as DataFusion
+    /// produces them instead of collecting them, for results too large to hold
+    /// at once. Always runs on DataFusion; `max_rows` of `None` streams them all.
+    #[instrument(name = "lake.sql_stream", skip(self, sql))]
+    pub async fn stream_table_sql(
+        &self,
+        table_name: &str,
+        sql: &str,
+        max_rows: Option<usize>,
+    ) -> Result<TableRowStream> {
+        Self::validate_table_path(table_name)?;
+        let frame = self.read_only_sql_frame(table_name, sql).await?;
+        let columns = Self::frame_columns(&frame);
+        let frame = match max_rows {
+            Some(max_rows) => frame
+                .limit(0, Some(max_rows))
+                .map_err(|e| StorageError::Other(e.into()))?,
+            None => frame,
+        };
+        let batches = frame
+            .execute_stream()
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+        let rows = batches
+            .map(|batch| {
+                let batch = batch.map_err(|e| StorageError::Other(e.into()))?;
+                Self::record_batches_to_maps(std::slice::from_ref(&batch))
+            })
+            .boxed();
+        Ok(TableRowStream { columns, rows })
+    }
+
+    /// Plans `sql` against `table_name`, refusing statements that could modify
+    /// state. `{{table}}` names the table and foreign tables it mentions are
+    /// registered alongside it.
+    async fn read_only_sql_frame(&self, table_name: &str, sql: &str) -> Result<DataFrame> {
+        let Some(table) = self.open_table(table_name).await? else {
+            return Err(StorageError::NotFound(format!(
+                "table '{table_name}' not found"
+            )));
+        };
+
+        let ctx = Self::single_partition_session();
+        let alias = Self::sanitize_table_alias(table_name);
+        ctx.register_table(&alias, table.provider)
+            .map_err(|e| StorageError::Other(e.into()))?;
+
+        let final_sql = sql.replace("{{table}}", &alias);
+        self.register_foreign_tables(&ctx, &final_sql, &alias)
+            .await?;
+        Self::plan_read_only(&ctx, &final_sql).await
+    }
+
+    /// Plans `sql` on `ctx`, refusing DDL, DML and other statements.
+    async fn plan_read_only(ctx: &SessionContext, sql: &str) -> Result<DataFrame> {
+        let options = SQLOptions::new()
+            .with_allow_ddl(false)
+            .with_allow_dml(false)
+            .with_allow_statements(false);
+        ctx.sql_with_options(sql, options)
+            .await
+            .map_err(|e| StorageError::InvalidArg(format!("invalid query: {e}")))
+    }
+
+    fn frame_columns(frame: &DataFrame) -> Vec<String> {
+        frame
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect()
+    }
+
+    /// Runs [`Lake::query_table_sql`] on DuckDB when it is configured and can
+    /// read the tables involved: Delta tables of the lake with at least one
+    /// data file. `None` leaves the query to DataFusion.
+    #[cfg(feature = "duckdb")]
+    async fn query_table_sql_duckdb(
+        &self,
+        table_name: &str,
+        sql: &str,
+        max_rows: usize,
+    ) -> Result<Option<TableQueryResult>> {
+        let Some(duckdb) = self.duckdb.clone() else {
+            return Ok(None);
+        };
+        let mentions_foreign = self
+            .foreign_tables
+            .read()
+            .unwrap()
+            .keys()
+            .any(|name| sql.contains(foreign::sql_name(name).as_str()));
+        if self.config.table_format != TableFormat::Delta
+            || table_name.starts_with(FOREIGN_PREFIX)
+            || mentions_foreign
+        {
+            return Ok(None);
+        }
+        let Some(files) = delta::data_files(&self.config.lake_path.join(table_name)).await? else {
+            return Err(StorageError::NotFound(format!(
+                "table '{table_name}' not found"
+            )));
+        };
+        if files.is_empty() {
+            return Ok(None);
+        }
+        let alias = Self::sanitize_table_alias(table_name);
+        let sql = sql.replace("{{table}}", &alias);
+        tokio::task::spawn_blocking(move || duckdb.query(&alias, &files, &sql, max_rows))
+            .await
+            .map_err(|err| StorageError::Other(err.into()))?
+            .map(Some)
     }
 
     async fn get_adjacent_edges(
@@ -1393,60 +1881,58 @@ impl Lake {
         direction: Direction,
     ) -> Result<Vec<HashMap<String, JsonValue>>> {
         let label_filter = edge_type.map(str::to_string);
-        let txn = self.engine.storage.graph_env.read_txn()?;
-
-        match self.engine.storage.get_node(&txn, &node_key) {
-            Ok(_) => {}
-            Err(GraphError::NodeNotFound) => {
-                match self
-                    .engine
-                    .storage
-                    .vectors
-                    .get_vector(&txn, node_key, 0, true)
-                {
-                    Ok(_) => {}
-                    Err(VectorError::VectorNotFound(_)) | Err(VectorError::EntryPointNotFound) => {
-                        return Err(StorageError::NotFound(format!(
-                            "Node '{}' was not found in Helix storage",
-                            Uuid::from_u128(node_key)
-                        )));
+        self.read_graph(move |storage, txn| {
+            match storage.get_node(txn, &node_key) {
+                Ok(_) => {}
+                Err(GraphError::NodeNotFound) => {
+                    match storage.vectors.get_vector(txn, node_key, 0, true) {
+                        Ok(_) => {}
+                        Err(VectorError::VectorNotFound(_))
+                        | Err(VectorError::EntryPointNotFound) => {
+                            return Err(StorageError::NotFound(format!(
+                                "Node '{}' was not found in Helix storage",
+                                Uuid::from_u128(node_key)
+                            )));
+                        }
+                        Err(err) => return Err(StorageError::Graph(err.into())),
                     }
-                    Err(err) => return Err(StorageError::Graph(err.into())),
                 }
+                Err(other) => return Err(StorageError::from(other)),
             }
-            Err(other) => return Err(StorageError::from(other)),
-        }
-
-        let prefix = &node_key.to_be_bytes();
-        let iter = match direction {
-            Direction::Out => self.engine.storage.out_edges_db.prefix_iter(&txn, prefix)?,
-            Direction::In => self.engine.storage.in_edges_db.prefix_iter(&txn, prefix)?,
-        };
 
-        let mut edges = Vec::new();
-        for entry in iter {
-            let (_key, value) = entry?;
-            let (edge_id, other_node_id) = HelixGraphStorage::unpack_adj_edge_data(value.as_ref())?;
-            let edge = self.engine.storage.get_edge(&txn, &edge_id)?;
-
-            let matches_direction = match direction {
-                Direction::Out => edge.from_node == node_key && edge.to_node == other_node_id,
-                Direction::In => edge.to_node == node_key && edge.from_node == other_node_id,
+            let prefix = &node_key.to_be_bytes();
+            let iter = match direction {
+                Direction::Out => storage.out_edges_db.prefix_iter(txn, prefix)?,
+                Direction::In => storage.in_edges_db.prefix_iter(txn, prefix)?,
             };
-            if !matches_direction {
-                continue;
-            }
 
-            if let Some(expected_label) = &label_filter {
-                if &edge.label != expected_label {
+            let mut edges = Vec::new();
+            for entry in iter {
+                let (_key, value) = entry?;
+                let (edge_id, other_node_id) =
+                    HelixGraphStorage::unpack_adj_edge_data(value.as_ref())?;
+                let edge = storage.get_edge(txn, &edge_id)?;
+
+                let matches_direction = match direction {
+                    Direction::Out => edge.from_node == node_key && edge.to_node == other_node_id,
+                    Direction::In => edge.to_node == node_key && edge.from_node == other_node_id,
+                };
+                if !matches_direction {
                     continue;
                 }
-            }
 
-            edges.push(Self::edge_to_map(edge));
-        }
+                if let Some(expected_label) = &label_filter {
+                    if &edge.label != expected_label {
+                        continue;
+                    }
+                }
 
-        Ok(edges)
+                edges.push(Self::edge_to_map(edge));
+            }
+
+            Ok(edges)
+        })
+        .await
     }
 
     async fn get_adjacent_edges_from_lake(
@@ -1463,25 +1949,13 @@ impl Lake {
 
         let mut results = Vec::new();
         for et in edge_types {
-            let table_path = self.config.lake_path.join(format!("silver/edges/{}", et));
-            if tokio::fs::metadata(&table_path).await.is_err() {
+            let Some(table) = self.open_table(&format!("silver/edges/{}", et)).await? else {
                 continue;
-            }
-
-            let table_uri = match self.path_to_url(&table_path) {
-                Ok(uri) => uri,
-                Err(_) => continue,
-            };
-
-            let table = match deltalake::open_table(table_uri).await {
-                Ok(table) => table,
-                Err(deltalake::DeltaTableError::NotATable(_)) => continue,
-                Err(e) => return Err(StorageError::from(e)),
             };
 
             let ctx = Self::single_partition_session();
             let alias = format!("edges_{}", et.replace('-', "_"));
-            ctx.register_table(&alias, Arc::new(table))
+            ctx.register_table(&alias, table.provider)
                 .map_err(|e| StorageError::Other(e.into()))?;
 
             let filter_column = match direction {
@@ -1554,20 +2028,124 @@ impl Lake {
         }
     }
 
-    async fn open_delta_table(&self, table_name: &str) -> Result<Option<DeltaTable>> {
-        let table_path = self.config.lake_path.join(table_name);
-        if tokio::fs::metadata(&table_path).await.is_err() {
-            return Ok(None);
+    /// Makes the Delta table at `uri` readable as `foreign/<name>`, after
+    /// checking that it opens. Replaces an earlier table of the same name.
+    pub async fn attach_foreign_table(&self, name: &str, uri: Url) -> Result<()> {
+        foreign::validate_name(name)?;
+        // Whatever keeps the table from opening is the caller's to fix.
+        deltalake::open_table(uri.clone()).await.map_err(|e| {
+            StorageError::InvalidArg(format!("cannot open a Delta table at '{uri}': {e}"))
+        })?;
+        self.register_foreign_table(name, uri);
+        Ok(())
+    }
+
+    /// Like [`Lake::attach_foreign_table`] without opening the table, for
+    /// restoring attachments at startup while their storage may be offline.
+    pub(crate) fn register_foreign_table(&self, name: &str, uri: Url) {
+        self.foreign_tables
+            .write()
+            .unwrap()
+            .insert(name.to_string(), uri);
+        // Reads of `foreign/<name>` may now return different data.
+        self.record_write();
+    }
+
+    /// Returns whether `name` was attached.
+    pub fn detach_foreign_table(&self, name: &str) -> bool {
+        let removed = self.foreign_tables.write().unwrap().remove(name).is_some();
+        if removed {
+            self.record_write();
         }
-        let table_uri = match self.path_to_url(&table_path) {
-            Ok(uri) => uri,
-            Err(_) => return Ok(None),
-        };
+        removed
+    }
+
+    fn foreign_table_uri(&self, name: &str) -> Option<Url> {
+        self.foreign_tables.read().unwrap().get(name).cloned()
+    }
+
+    /// Registers every attached foreign table `sql` mentions by its SQL name,
+    /// except the one already registered as `registered`.
+    async fn register_foreign_tables(
+        &self,
+        ctx: &SessionContext,
+        sql: &str,
+        registered: &str,
+    ) -> Result<()> {
+        let mentioned: Vec<(String, Url)> = self
+            .foreign_tables
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, uri)| (foreign::sql_name(name), uri.clone()))
+            .filter(|(alias, _)| alias != registered && sql.contains(alias.as_str()))
+            .collect();
+        for (alias, uri) in mentioned {
+            let Some(table) = delta::open_url(uri).await? else {
+                continue;
+            };
+            ctx.register_table(&alias, Arc::new(table))
+                .map_err(|e| StorageError::Other(e.into()))?;
+        }
+        Ok(())
+    }
+
+    fn ensure_writable(table_name: &str) -> Result<()> {
+        if table_name.starts_with(FOREIGN_PREFIX) {
+            return Err(StorageError::InvalidArg(format!(
+                "'{table_name}' is a read-only foreign table"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rejects empty paths and paths that could leave the lake root.
+    pub fn validate_table_path(table_name: &str) -> Result<()> {
+        let relative = std::path::Path::new(table_name);
+        if table_name.trim().is_empty()
+            || relative
+                .components()
+                .any(|component| !matches!(component, std::path::Component::Normal(_)))
+        {
+            return Err(StorageError::InvalidArg(format!(
+                "invalid table path '{table_name}'"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether `table_name` is a table of the lake.
+    pub async fn table_exists(&self, table_name: &str) -> Result<bool> {
+        Self::validate_table_path(table_name)?;
+        Ok(self.open_table(table_name).await?.is_some())
+    }
+
+    /// Opens a lake table, or the attached foreign table for a `foreign/` path.
+    async fn open_table(&self, table_name: &str) -> Result<Option<OpenedTable>> {
+        if let Some(name) = table_name.strip_prefix(FOREIGN_PREFIX) {
+            let Some(uri) = self.foreign_table_uri(name) else {
+                return Ok(None);
+            };
+            return Ok(delta::open_url(uri).await?.map(OpenedTable::from));
+        }
+        self.tables.open(table_name).await
+    }
 
-        match deltalake::open_table(table_uri).await {
-            Ok(table) => Ok(Some(table)),
-            Err(deltalake::DeltaTableError::NotATable(_)) => Ok(None),
-            Err(e) => Err(StorageError::from(e)),
+    async fn open_table_at(
+        &self,
+        table_name: &str,
+        version: i64,
+    ) -> Result<Arc<dyn TableProvider>> {
+        match table_name.strip_prefix(FOREIGN_PREFIX) {
+            Some(name) => {
+                let uri = self.foreign_table_uri(name).ok_or_else(|| {
+                    StorageError::NotFound(format!("foreign table '{name}' is not attached"))
+                })?;
+                Ok(Arc::new(
+                    deltalake::open_table_with_version(uri, version).await?,
+                ))
+            }
+            None => self.tables.open_at(table_name, version).await,
         }
     }
 
@@ -1578,13 +2156,13 @@ impl Lake {
         computed_id: &str,
     ) -> Result<Option<HashMap<String, JsonValue>>> {
         let table_name = format!("silver/entities/{}", entity_type);
-        let Some(table) = self.open_delta_table(&table_name).await? else {
+        let Some(table) = self.open_table(&table_name).await? else {
             return Ok(None);
         };
 
         let ctx = Self::single_partition_session();
         let alias = Self::sanitize_table_alias(&table_name);
-        ctx.register_table(&alias, Arc::new(table))
+        ctx.register_table(&alias, table.provider)
             .map_err(|e| StorageError::Other(e.into()))?;
 
         let mut predicates = Vec::new();
@@ -1667,7 +2245,9 @@ impl Lake {
         Ok(edges)
     }
 
-    fn record_batches_to_maps(batches: &[RecordBatch]) -> Result<Vec<HashMap<String, JsonValue>>> {
+    pub(crate) fn record_batches_to_maps(
+        batches: &[RecordBatch],
+    ) -> Result<Vec<HashMap<String, JsonValue>>> {
         let mut rows = Vec::new();
         for batch in batches {
             for row in 0..batch.num_rows() {
@@ -1677,7 +2257,8 @@ impl Lake {
         Ok(rows)
     }
 
-    fn sanitize_table_alias(table_name: &str) -> String {
+    /// Name [`Lake::query_lake_sql`] registers `table_name` under.
+    pub fn sanitize_table_alias(table_name: &str) -> String {
         let candidate: String = table_name
             .chars()
             .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
@@ -1757,7 +2338,7 @@ impl Lake {
     /// 获取边数据统计信息
     ///
     /// # 返回
-    /// * `Result<HashMap<String, i64>>` - 边类型到数量的映射
+    /// * `Result<HashMap<String, i64>>` - 边类型到行数的映射
     pub async fn get_edge_statistics(&self) -> Result<HashMap<String, i64>> {
         let mut stats = HashMap::new();
 
@@ -1766,149 +2347,457 @@ impl Lake {
 
         for et in edge_types {
             let table_path = format!("silver/edges/{}", et);
-            if let Ok(table_uri) = self.path_to_url(&self.config.lake_path.join(&table_path)) {
-                if let Ok(table) = deltalake::open_table(table_uri).await {
-                    // 使用表的版本数量估算记录数（简化实现）
-                    let file_count = table.version().map_or(0, |v| v as i64);
-                    stats.insert(et, file_count);
-                }
+            if let Some(rows) = self.count_rows(&table_path).await? {
+                stats.insert(et, rows as i64);
             }
         }
 
         Ok(stats)
     }
 
-    /// 获取所有可用的边类型
-    ///
-    /// # 返回
-    /// * `Result<Vec<String>>` - 边类型列表
-    async fn get_available_edge_types(&self) -> Result<Vec<String>> {
-        let edges_path = self.config.lake_path.join("silver/edges");
-        let mut edge_types = Vec::new();
-
-        if tokio::fs::metadata(&edges_path).await.is_ok() {
-            let mut entries = tokio::fs::read_dir(&edges_path).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(dir_name) = path.file_name().and_then(|name| name.to_str()) {
-                        edge_types.push(dir_name.to_string());
+    /// Edge labels declared in the schema or found under `silver/edges`, with
+    /// the node types each one connects. Hints for table-only labels come from
+    /// the distinct endpoint types stored in the table.
+    #[instrument(name = "lake.edge_types", skip(self))]
+    pub async fn list_edge_types(&self) -> Result<Vec<EdgeTypeSummary>> {
+        let mut labels: BTreeMap<String, EdgeTypeSummary> = BTreeMap::new();
+        for edge in SCHEMA_REGISTRY.edges() {
+            let summary =
+                labels
+                    .entry(edge.edge_type.to_string())
+                    .or_insert_with(|| EdgeTypeSummary {
+                        label: edge.edge_type.to_string(),
+                        table_path: None,
+                        from_types: Vec::new(),
+                        to_types: Vec::new(),
+                        in_schema: true,
+                    });
+            summary.from_types.push(edge.from_entity.to_string());
+            summary.to_types.push(edge.to_entity.to_string());
+        }
+
+        for table in self.get_available_edge_types().await? {
+            let table_path = format!("silver/edges/{table}");
+            let label = format!("edge_{table}");
+            let summary = labels
+                .entry(label.clone())
+                .or_insert_with(|| EdgeTypeSummary {
+                    label,
+                    table_path: None,
+                    from_types: Vec::new(),
+                    to_types: Vec::new(),
+                    in_schema: false,
+                });
+            if !summary.in_schema {
+                match self.edge_endpoint_types(&table_path).await {
+                    Ok(pairs) => {
+                        for (from, to) in pairs {
+                            summary.from_types.extend(from);
+                            summary.to_types.extend(to);
+                        }
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to read endpoint types from '{table_path}': {err}")
                     }
                 }
             }
+            summary.table_path = Some(table_path);
         }
 
-        Ok(edge_types)
+        Ok(labels
+            .into_values()
+            .map(|mut summary| {
+                for types in [&mut summary.from_types, &mut summary.to_types] {
+                    types.sort();
+                    types.dedup();
+                }
+                summary
+            })
+            .collect())
     }
 
-    pub async fn list_tables(&self, prefix: &str) -> Result<Vec<TableSummary>> {
-        let mut tables = Vec::new();
-        let base_path = if prefix.is_empty() {
-            self.config.lake_path.clone()
-        } else {
-            self.config.lake_path.join(prefix)
-        };
+    async fn edge_endpoint_types(
+        &self,
+        table_name: &str,
+    ) -> Result<Vec<(Option<String>, Option<String>)>> {
+        let rows = self
+            .table_sql(
+                table_name,
+                "SELECT DISTINCT lower(from_node_type) AS from_type, lower(to_node_type) AS to_type FROM {{table}} LIMIT 100",
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let text = |key: &str| row.get(key).and_then(JsonValue::as_str).map(str::to_string);
+                (text("from_type"), text("to_type"))
+            })
+            .collect())
+    }
 
-        if tokio::fs::metadata(&base_path).await.is_err() {
-            return Ok(tables);
-        }
-
-        let mut stack = vec![base_path];
-
-        while let Some(current) = stack.pop() {
-            let delta_log = current.join("_delta_log");
-            if tokio::fs::metadata(&delta_log).await.is_ok() {
-                if let Ok(uri) = self.path_to_url(&current) {
-                    match deltalake::open_table(uri.clone()).await {
-                        Ok(table) => {
-                            let schema = table.schema();
-                            let mut columns: Vec<ColumnSummary> = schema
-                                .fields()
-                                .iter()
-                                .map(|field| ColumnSummary {
-                                    name: field.name().to_string(),
-                                    data_type: field.data_type().to_string(),
-                                    nullable: field.is_nullable(),
-                                })
-                                .collect();
-                            columns.sort_by(|a, b| a.name.cmp(&b.name));
-                            let relative = current
-                                .strip_prefix(&self.config.lake_path)
-                                .unwrap_or(&current)
-                                .to_string_lossy()
-                                .to_string();
-                            tables.push(TableSummary {
-                                table_path: relative,
-                                columns,
-                            });
-                        }
-                        Err(err) => {
-                            log::warn!("Failed to open table at '{}': {}", uri, err);
-                        }
-                    }
-                }
+    /// Number of rows in `table_name`, or `None` when there is no such table.
+    ///
+    /// Counts are remembered per table version, so only tables committed to
+    /// since the last call are scanned again.
+    pub async fn count_rows(&self, table_name: &str) -> Result<Option<u64>> {
+        let Some(table) = self.open_table(table_name).await? else {
+            return Ok(None);
+        };
+        let version = table.version;
+        if let Some((counted_at, rows)) = self.row_counts.lock().unwrap().get(table_name) {
+            if *counted_at == version {
+                return Ok(Some(*rows));
             }
+        }
+        let ctx = Self::single_partition_session();
+        let alias = Self::sanitize_table_alias(table_name);
+        ctx.register_table(&alias, table.provider)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        let batches = ctx
+            .sql(&format!("SELECT COUNT(*) FROM {alias}"))
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?
+            .collect()
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+        let rows = batches
+            .first()
+            .filter(|batch| batch.num_rows() > 0)
+            .and_then(|batch| batch.column(0).as_any().downcast_ref::<Int64Array>())
+            .map(|column| column.value(0))
+            .unwrap_or_default();
+        let rows = rows.max(0) as u64;
+        self.row_counts
+            .lock()
+            .unwrap()
+            .insert(table_name.to_string(), (version, rows));
+        Ok(Some(rows))
+    }
 
-            let mut entries = match tokio::fs::read_dir(&current).await {
-                Ok(entries) => entries,
-                Err(_) => continue,
-            };
+    /// Counts engine nodes and edges per label, engine vectors, and the rows
+    /// of every lake table, with edge tables also listed by edge type.
+    #[instrument(name = "lake.graph_statistics", skip(self))]
+    pub async fn graph_statistics(&self) -> Result<GraphStatistics> {
+        let mut stats = self
+            .read_graph(|storage, txn| {
+                let mut stats = GraphStatistics::default();
+                for entry in storage.nodes_db.iter(txn)? {
+                    let (id, _) = entry?;
+                    let node = storage.get_node(txn, &id)?;
+                    *stats.nodes_by_label.entry(node.label).or_default() += 1;
+                }
+                for entry in storage.edges_db.iter(txn)? {
+                    let (id, _) = entry?;
+                    let edge = storage.get_edge(txn, &id)?;
+                    *stats.edges_by_label.entry(edge.label).or_default() += 1;
+                }
+                let engine_stats: JsonValue =
+                    serde_json::from_str(&storage.get_db_stats_json(txn)?)?;
+                stats.vector_count = engine_stats
+                    .get("num_vectors")
+                    .and_then(JsonValue::as_u64)
+                    .unwrap_or_default();
+                Ok(stats)
+            })
+            .await?;
 
-            while let Some(entry) = entries
-                .next_entry()
-                .await
-                .map_err(|e| StorageError::Other(e.into()))?
-            {
-                if entry
-                    .file_type()
-                    .await
-                    .map_err(|e| StorageError::Other(e.into()))?
-                    .is_dir()
+        for table in self.list_tables("").await? {
+            if let Some(rows) = self.count_rows(&table.table_path).await? {
+                if let Some(edge_type) = table
+                    .table_path
+                    .strip_prefix(EDGE_TABLE_PREFIX)
+                    .and_then(|rest| rest.strip_prefix('/'))
                 {
-                    stack.push(entry.path());
+                    stats.edge_rows.insert(edge_type.to_string(), rows);
                 }
+                stats.table_rows.insert(table.table_path, rows);
             }
         }
+        Ok(stats)
+    }
 
-        tables.sort_by(|a, b| a.table_path.cmp(&b.table_path));
-        Ok(tables)
+    /// Latest version of `table_name`, or `None` when the lake has no such
+    /// table.
+    pub async fn table_version(&self, table_name: &str) -> Result<Option<i64>> {
+        Ok(self
+            .tables
+            .open(table_name)
+            .await?
+            .map(|table| table.version))
     }
 
-    pub async fn search_bm25(
+    /// Current version of every entity and edge table.
+    pub async fn table_versions(&self) -> Result<BTreeMap<String, i64>> {
+        let mut versions = BTreeMap::new();
+        for prefix in [ENTITY_TABLE_PREFIX, EDGE_TABLE_PREFIX] {
+            for table_path in self.tables.list(prefix).await? {
+                let Some(table) = self.tables.open(&table_path).await? else {
+                    continue;
+                };
+                if table.version >= 0 {
+                    versions.insert(table_path, table.version);
+                }
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Nodes and edges added, removed or changed between two sync runs.
+    ///
+    /// Each table is read, through time travel, at the version each run
+    /// left it at; tables both runs left at the same version are skipped. Nodes
+    /// are matched on their primary keys and edges on their id. Each list holds
+    /// at most `limit` entries.
+    #[instrument(name = "lake.graph_diff", skip_all, fields(from = from.run_id, to = to.run_id))]
+    pub async fn graph_diff(
         &self,
-        entity_type: &str,
-        query: &str,
+        from: &SyncRun,
+        to: &SyncRun,
         limit: usize,
-    ) -> Result<Vec<TextSearchHit>> {
-        let trimmed = query.trim();
+    ) -> Result<GraphDiff> {
+        let mut diff = GraphDiff {
+            from_run: from.run_id,
+            to_run: to.run_id,
+            ..Default::default()
+        };
+        let tables: BTreeSet<&String> = from
+            .table_versions
+            .keys()
+            .chain(to.table_versions.keys())
+            .collect();
+        for table in tables {
+            let from_version = from.table_versions.get(table).copied();
+            let to_version = to.table_versions.get(table).copied();
+            if from_version == to_version {
+                continue;
+            }
+            let (edge_type, entity_type) = match (
+                table
+                    .strip_prefix(EDGE_TABLE_PREFIX)
+                    .and_then(|rest| rest.strip_prefix('/')),
+                table
+                    .strip_prefix(ENTITY_TABLE_PREFIX)
+                    .and_then(|rest| rest.strip_prefix('/')),
+            ) {
+                (Some(edge_type), _) => (Some(edge_type), None),
+                (None, Some(entity_type)) => (None, Some(entity_type)),
+                (None, None) => continue,
+            };
+            let keys: Vec<&str> = match entity_type {
+                None => vec!["id"],
+                Some(entity_type) => match SCHEMA_REGISTRY.entity(entity_type) {
+                    Some(metadata) if !metadata.primary_keys.is_empty() => {
+                        metadata.primary_keys.to_vec()
+                    }
+                    _ => {
+                        diff.notes
+                            .push(format!("{table}: no primary key to match rows on"));
+                        continue;
+                    }
+                },
+            };
+
+            let ctx = Self::single_partition_session();
+            for (alias, version) in [("run_from", from_version), ("run_to", to_version)] {
+                if let Some(version) = version {
+                    let provider = self.open_table_at(table, version).await?;
+                    ctx.register_table(alias, provider)
+                        .map_err(|e| StorageError::Other(e.into()))?;
+                }
+            }
+            let key_list = keys
+                .iter()
+                .map(|key| Self::escape_sql_identifier(key))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let cap = limit + 1;
+            let (added_sql, removed_sql, changed_sql) = match (from_version, to_version) {
+                (Some(_), Some(_)) => (
+                    Some(format!(
+                        "SELECT {key_list} FROM run_to EXCEPT SELECT {key_list} FROM run_from LIMIT {cap}"
+                    )),
+                    Some(format!(
+                        "SELECT {key_list} FROM run_from EXCEPT SELECT {key_list} FROM run_to LIMIT {cap}"
+                    )),
+                    Some(format!(
+                        "SELECT {key_list} FROM (SELECT * FROM run_to EXCEPT SELECT * FROM run_from) \
+                         INTERSECT SELECT {key_list} FROM run_from LIMIT {cap}"
+                    )),
+                ),
+                (None, _) => (
+                    Some(format!("SELECT {key_list} FROM run_to LIMIT {cap}")),
+                    None,
+                    None,
+                ),
+                (_, None) => (
+                    None,
+                    Some(format!("SELECT {key_list} FROM run_from LIMIT {cap}")),
+                    None,
+                ),
+            };
+
+            let to_changes = |rows: Vec<HashMap<String, JsonValue>>| -> Vec<GraphChange> {
+                rows.iter()
+                    .filter_map(|row| match (edge_type, entity_type) {
+                        (Some(edge_type), _) => {
+                            row.get("id")
+                                .and_then(JsonValue::as_str)
+                                .map(|id| GraphChange {
+                                    id: id.to_string(),
+                                    label: format!("edge_{edge_type}"),
+                                })
+                        }
+                        (None, Some(entity_type)) => Self::stable_id_from_row(entity_type, row)
+                            .map(|id| GraphChange {
+                                id,
+                                label: entity_type.to_string(),
+                            }),
+                        (None, None) => None,
+                    })
+                    .collect()
+            };
+            let set = if edge_type.is_some() {
+                &mut diff.edges
+            } else {
+                &mut diff.nodes
+            };
+            let mut truncated = false;
+            if let Some(sql) = added_sql {
+                let rows = Self::collect_rows(&ctx, &sql).await?;
+                truncated |= Self::extend_limited(&mut set.added, to_changes(rows), limit);
+            }
+            if let Some(sql) = removed_sql {
+                let rows = Self::collect_rows(&ctx, &sql).await?;
+                truncated |= Self::extend_limited(&mut set.removed, to_changes(rows), limit);
+            }
+            if let Some(sql) = changed_sql {
+                // Comparing whole rows fails when the table's schema evolved
+                // between the runs; additions and removals still stand.
+                match Self::collect_rows(&ctx, &sql).await {
+                    Ok(rows) => {
+                        truncated |=
+                            Self::extend_limited(&mut set.changed, to_changes(rows), limit);
+                    }
+                    Err(err) => diff
+                        .notes
+                        .push(format!("{table}: changed rows not compared: {err}")),
+                }
+            }
+            diff.truncated |= truncated;
+        }
+        Ok(diff)
+    }
+
+    async fn collect_rows(
+        ctx: &SessionContext,
+        sql: &str,
+    ) -> Result<Vec<HashMap<String, JsonValue>>> {
+        let batches = ctx
+            .sql(sql)
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?
+            .collect()
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+        Self::record_batches_to_maps(&batches)
+    }
+
+    /// Appends `items` to `list` up to `limit` entries; true when some were left out.
+    fn extend_limited(list: &mut Vec<GraphChange>, items: Vec<GraphChange>, limit: usize) -> bool {
+        let room = limit.saturating_sub(list.len());
+        let truncated = items.len() > room;
+        list.extend(items.into_iter().take(room));
+        truncated
+    }
+
+    /// 获取所有可用的边类型
+    ///
+    /// # 返回
+    /// * `Result<Vec<String>>` - 边类型列表
+    async fn get_available_edge_types(&self) -> Result<Vec<String>> {
+        Ok(self
+            .tables
+            .list(EDGE_TABLE_PREFIX)
+            .await?
+            .iter()
+            .filter_map(|path| path.rsplit('/').next().map(str::to_string))
+            .collect())
+    }
+
+    #[instrument(name = "lake.list_tables", skip(self))]
+    pub async fn list_tables(&self, prefix: &str) -> Result<Vec<TableSummary>> {
+        let mut tables = Vec::new();
+        for table_path in self.tables.list(prefix).await? {
+            match self.tables.open(&table_path).await {
+                Ok(Some(table)) => {
+                    let schema = table.provider.schema();
+                    let mut columns: Vec<ColumnSummary> = schema
+                        .fields()
+                        .iter()
+                        .map(|field| ColumnSummary {
+                            name: field.name().to_string(),
+                            data_type: field.data_type().to_string(),
+                            nullable: field.is_nullable(),
+                        })
+                        .collect();
+                    columns.sort_by(|a, b| a.name.cmp(&b.name));
+                    tables.push(TableSummary {
+                        table_path,
+                        columns,
+                    });
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    log::warn!("Failed to open table '{}': {}", table_path, err);
+                }
+            }
+        }
+
+        tables.sort_by(|a, b| a.table_path.cmp(&b.table_path));
+        Ok(tables)
+    }
+
+    #[instrument(name = "lake.search_bm25", skip(self, query))]
+    pub async fn search_bm25(
+        &self,
+        entity_type: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<TextSearchHit>> {
+        let trimmed = query.trim();
         if trimmed.is_empty() {
             return Ok(Vec::new());
         }
-        let bm25 = self.engine.storage.bm25.as_ref().ok_or_else(|| {
-            StorageError::SyncError("BM25 index is not enabled for this store".into())
-        })?;
-        let txn = self.engine.storage.graph_env.read_txn()?;
+        let trimmed = trimmed.to_string();
+        let entity_type = entity_type.to_string();
         let limit = limit.max(1);
-        let raw_results = bm25
-            .search(&txn, trimmed, limit)
-            .map_err(StorageError::Graph)?;
-        let mut hits = Vec::with_capacity(raw_results.len());
-        for (doc_id, score) in raw_results {
-            match self.engine.storage.get_node(&txn, &doc_id) {
-                Ok(node) if node.label == entity_type => {
-                    hits.push(TextSearchHit {
-                        score,
-                        node: Self::node_to_map(node),
-                    });
+        self.read_graph(move |storage, txn| {
+            let bm25 = storage.bm25.as_ref().ok_or_else(|| {
+                StorageError::SyncError("BM25 index is not enabled for this store".into())
+            })?;
+            let raw_results = bm25
+                .search(txn, &trimmed, limit)
+                .map_err(StorageError::Graph)?;
+            let mut hits = Vec::with_capacity(raw_results.len());
+            for (doc_id, score) in raw_results {
+                match storage.get_node(txn, &doc_id) {
+                    Ok(node) if node.label == entity_type => {
+                        hits.push(TextSearchHit {
+                            score,
+                            node: Self::node_to_map(node),
+                        });
+                    }
+                    Ok(_) => continue,
+                    Err(GraphError::NodeNotFound) => continue,
+                    Err(err) => return Err(StorageError::from(err)),
                 }
-                Ok(_) => continue,
-                Err(GraphError::NodeNotFound) => continue,
-                Err(err) => return Err(StorageError::from(err)),
             }
-        }
-        Ok(hits)
+            Ok(hits)
+        })
+        .await
     }
 
+    #[instrument(name = "lake.search_vectors", skip(self, query_vector))]
     pub async fn search_vectors(
         &self,
         entity_type: &str,
@@ -1918,24 +2807,25 @@ impl Lake {
         if query_vector.is_empty() {
             return Ok(Vec::new());
         }
-        let txn = self.engine.storage.graph_env.read_txn()?;
         let limit = limit.max(1);
-        let results = match self
-            .engine
-            .storage
-            .vectors
-            .search::<fn(&HVector, &RoTxn) -> bool>(
-                &txn,
-                query_vector,
-                limit,
-                entity_type,
-                None,
-                false,
-            ) {
-            Ok(results) => results,
-            Err(VectorError::EntryPointNotFound) => Vec::new(),
-            Err(err) => return Err(StorageError::Graph(err.into())),
-        };
+        let query_vector = query_vector.to_vec();
+        let label = entity_type.to_string();
+        let results = self
+            .read_graph(move |storage, txn| {
+                match storage.vectors.search::<fn(&HVector, &RoTxn) -> bool>(
+                    txn,
+                    &query_vector,
+                    limit,
+                    &label,
+                    None,
+                    false,
+                ) {
+                    Ok(results) => Ok(results),
+                    Err(VectorError::EntryPointNotFound) => Ok(Vec::new()),
+                    Err(err) => Err(StorageError::Graph(err.into())),
+                }
+            })
+            .await?;
         Ok(results
             .into_iter()
             .filter_map(|vector| {
@@ -1958,6 +2848,46 @@ impl Lake {
             .collect())
     }
 
+    /// Vectors of `entity_type` nearest to the stored vector `id`, which is
+    /// left out. Empty when `id` is not a vector of that type.
+    pub async fn search_vectors_near(
+        &self,
+        entity_type: &str,
+        id: &str,
+        limit: usize,
+    ) -> Result<Vec<VectorSearchHit>> {
+        let Ok(uuid) = Uuid::parse_str(id) else {
+            return Ok(Vec::new());
+        };
+        let label = entity_type.to_string();
+        let data = self
+            .read_graph(move |storage, txn| {
+                match storage.vectors.get_vector(txn, uuid.as_u128(), 0, true) {
+                    Ok(vector)
+                        if vector
+                            .get_label()
+                            .is_some_and(|value| value.inner_stringify() == label) =>
+                    {
+                        Ok(Some(vector.data.clone()))
+                    }
+                    Ok(_)
+                    | Err(VectorError::VectorNotFound(_))
+                    | Err(VectorError::EntryPointNotFound) => Ok(None),
+                    Err(err) => Err(StorageError::Graph(err.into())),
+                }
+            })
+            .await?;
+        let Some(data) = data else {
+            return Ok(Vec::new());
+        };
+        let own_id = uuid.to_string();
+        let mut hits = self.search_vectors(entity_type, &data, limit + 1).await?;
+        hits.retain(|hit| hit.vector.get("id").and_then(JsonValue::as_str) != Some(&own_id));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    #[instrument(name = "lake.search_hybrid", skip(self, query_text, query_vector))]
     pub async fn search_hybrid(
         &self,
         entity_type: &str,
@@ -1970,191 +2900,1452 @@ impl Lake {
             return Ok(Vec::new());
         }
 
-        let alpha = alpha.clamp(0.0, 1.0);
-        let limit = limit.max(1);
+        let alpha = alpha.clamp(0.0, 1.0);
+        let limit = limit.max(1);
+
+        let text = query_text.trim().to_string();
+        let bm25_search = async move {
+            if text.is_empty() {
+                return Ok(Vec::new());
+            }
+            self.read_graph(move |storage, txn| {
+                let bm25 = storage.bm25.as_ref().ok_or_else(|| {
+                    StorageError::SyncError("BM25 index is not enabled for this store".into())
+                })?;
+                bm25.search(txn, &text, limit * 2)
+                    .map_err(StorageError::Graph)
+            })
+            .await
+        };
+
+        let query_vec: Vec<f64> = query_vector.to_vec();
+        let label = entity_type.to_string();
+        let vector_search = async move {
+            if query_vec.is_empty() {
+                return Ok(Vec::new());
+            }
+            self.read_graph(move |storage, txn| {
+                match storage.vectors.search::<fn(&HVector, &RoTxn) -> bool>(
+                    txn,
+                    &query_vec,
+                    limit * 2,
+                    &label,
+                    None,
+                    false,
+                ) {
+                    Ok(results) => Ok(results),
+                    Err(VectorError::EntryPointNotFound) => Ok(Vec::new()),
+                    Err(err) => Err(StorageError::Graph(err.into())),
+                }
+            })
+            .await
+        };
+
+        let (bm25_results, vector_results) = tokio::try_join!(bm25_search, vector_search)?;
+
+        let mut combined_scores: HashMap<u128, f32> = HashMap::new();
+        for (doc_id, score) in bm25_results {
+            combined_scores
+                .entry(doc_id)
+                .and_modify(|existing| *existing = existing.max(alpha * score))
+                .or_insert(alpha * score);
+        }
+
+        for vector in &vector_results {
+            let label_matches = vector
+                .get_label()
+                .map(|value| value.inner_stringify() == entity_type)
+                .unwrap_or(true);
+            if !label_matches {
+                continue;
+            }
+            let similarity = (1.0 / (1.0 + vector.distance.unwrap_or(0.0))) as f32;
+            combined_scores
+                .entry(vector.id)
+                .and_modify(|existing| *existing += (1.0 - alpha) * similarity)
+                .or_insert((1.0 - alpha) * similarity);
+        }
+
+        let mut entries: Vec<(u128, f32)> = combined_scores.into_iter().collect();
+        entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        entries.truncate(limit);
+
+        let entity_type = entity_type.to_string();
+        self.read_graph(move |storage, txn| {
+            let mut hits = Vec::with_capacity(entries.len());
+            for (doc_id, score) in entries {
+                match storage.get_node(txn, &doc_id) {
+                    Ok(node) if node.label == entity_type => {
+                        hits.push(HybridSearchHit {
+                            score,
+                            node: Some(Self::node_to_map(node)),
+                            vector: None,
+                        });
+                    }
+                    Ok(_) => continue,
+                    Err(GraphError::NodeNotFound) => {
+                        match storage.vectors.get_vector(txn, doc_id, 0, true) {
+                            Ok(vector)
+                                if vector
+                                    .get_label()
+                                    .map(|value| value.inner_stringify() == entity_type)
+                                    .unwrap_or(true) =>
+                            {
+                                hits.push(HybridSearchHit {
+                                    score,
+                                    node: None,
+                                    vector: Some(Self::vector_to_map(vector)),
+                                });
+                            }
+                            Ok(_) => continue,
+                            Err(err) => return Err(StorageError::Graph(err.into())),
+                        }
+                    }
+                    Err(err) => return Err(StorageError::from(err)),
+                }
+            }
+            Ok(hits)
+        })
+        .await
+    }
+
+    #[instrument(name = "lake.search_hybrid", skip(self, query_text, query_vector))]
+    pub async fn search_hybrid_multi(
+        &self,
+        entity_types: &[String],
+        query_text: &str,
+        query_vector: &[f64],
+        alpha: f32,
+        limit: usize,
+    ) -> Result<Vec<MultiEntitySearchHit>> {
+        if entity_types.is_empty() || (query_text.trim().is_empty() && query_vector.is_empty()) {
+            return Ok(Vec::new());
+        }
+
+        let trimmed = query_text.trim();
+        let mut aggregate: Vec<MultiEntitySearchHit> = Vec::new();
+
+        for entity_type in entity_types {
+            let hits = self
+                .search_hybrid(entity_type, trimmed, query_vector, alpha, limit)
+                .await?;
+
+            aggregate.extend(hits.into_iter().map(|hit| MultiEntitySearchHit {
+                entity_type: entity_type.clone(),
+                score: hit.score,
+                rerank_score: None,
+                summary: hit.node.as_ref().and_then(Self::node_summary).or_else(|| {
+                    hit.vector.as_ref().and_then(|vector| {
+                        Self::extract_text_field(
+                            vector,
+                            &["text", "body", "summary", "content", "preview"],
+                        )
+                    })
+                }),
+                node: hit.node,
+                vector: hit.vector,
+                expansion: None,
+            }));
+        }
+
+        aggregate.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        aggregate.truncate(limit.max(1));
+
+        Ok(aggregate)
+    }
+
+    fn node_summary(node: &HashMap<String, JsonValue>) -> Option<String> {
+        Self::extract_text_field(node, &["title", "name", "label", "signature", "path"])
+    }
+
+    /// Graph context for search hits: the nodes each hit calls and is called
+    /// by, the file containing it, and the other nodes of its type in that
+    /// file. A vector hit stands for the node it embeds.
+    ///
+    /// Each context node scores the hit it was reached from times `decay` per
+    /// edge walked, keeping its best path. Nodes among `hits` are left out;
+    /// the best `limit` are returned, highest score first.
+    pub async fn expand_hits(
+        &self,
+        hits: &[MultiEntitySearchHit],
+        decay: f32,
+        limit: usize,
+    ) -> Result<Vec<MultiEntitySearchHit>> {
+        let mut anchors: Vec<(String, f32)> = Vec::new();
+        for hit in hits {
+            let id = match (&hit.node, &hit.vector) {
+                (Some(node), _) => node.get("id"),
+                (None, Some(vector)) => vector
+                    .get("properties")
+                    .and_then(|properties| properties.get("source_node_id")),
+                (None, None) => None,
+            };
+            if let Some(id) = id.and_then(JsonValue::as_str) {
+                anchors.push((id.to_string(), hit.score));
+            }
+        }
+        if anchors.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let anchor_ids: Vec<String> = anchors.iter().map(|(id, _)| id.clone()).collect();
+        let mut nodes = self.get_nodes_by_ids(&anchor_ids).await?;
+        let mut reached: HashMap<String, (f32, GraphExpansion)> = HashMap::new();
+        let mut reach = |id: String, score: f32, from: &str, via: &str, hops: u32| {
+            if reached.get(&id).is_none_or(|(best, _)| *best < score) {
+                let expansion = GraphExpansion {
+                    from_id: from.to_string(),
+                    via: via.to_string(),
+                    hops,
+                };
+                reached.insert(id, (score, expansion));
+            }
+        };
+        let ends = |edges: Vec<HashMap<String, JsonValue>>, end: &str| -> Vec<String> {
+            edges
+                .iter()
+                .filter_map(|edge| edge.get(end).and_then(JsonValue::as_str))
+                .take(GRAPH_EXPAND_FANOUT)
+                .map(str::to_string)
+                .collect()
+        };
+
+        for (anchor, score) in &anchors {
+            let callees = ends(
+                self.get_out_edges(anchor, Some(CALLS_EDGE)).await?,
+                "to_node_id",
+            );
+            let callers = ends(
+                self.get_in_edges(anchor, Some(CALLS_EDGE)).await?,
+                "from_node_id",
+            );
+            for id in callees.into_iter().chain(callers) {
+                reach(id, score * decay, anchor, CALLS_EDGE, 1);
+            }
+
+            let parents = ends(
+                self.get_in_edges(anchor, Some(CONTAINS_EDGE)).await?,
+                "from_node_id",
+            );
+            let unseen: Vec<String> = parents
+                .iter()
+                .filter(|id| !nodes.contains_key(*id))
+                .cloned()
+                .collect();
+            nodes.extend(self.get_nodes_by_ids(&unseen).await?);
+            for parent in parents {
+                if node_label(&nodes, &parent) != Some(FILE_ENTITY) {
+                    continue;
+                }
+                let siblings = ends(
+                    self.get_out_edges(&parent, Some(CONTAINS_EDGE)).await?,
+                    "to_node_id",
+                );
+                reach(parent, score * decay, anchor, CONTAINS_EDGE, 1);
+                for sibling in siblings.into_iter().filter(|id| id != anchor) {
+                    reach(sibling, score * decay * decay, anchor, CONTAINS_EDGE, 2);
+                }
+            }
+        }
+
+        for (id, _) in &anchors {
+            reached.remove(id);
+        }
+        let unseen: Vec<String> = reached
+            .keys()
+            .filter(|id| !nodes.contains_key(*id))
+            .cloned()
+            .collect();
+        nodes.extend(self.get_nodes_by_ids(&unseen).await?);
+
+        let mut expanded: Vec<(String, MultiEntitySearchHit)> = Vec::new();
+        for (id, (score, expansion)) in reached {
+            let Some(label) = node_label(&nodes, &id) else {
+                continue;
+            };
+            // Siblings are only context when they are the same kind of node,
+            // another function next to a function rather than its imports.
+            if expansion.hops > 1 && node_label(&nodes, &expansion.from_id) != Some(label) {
+                continue;
+            }
+            let entity_type = label.to_string();
+            let Some(node) = nodes.get(&id).cloned() else {
+                continue;
+            };
+            expanded.push((
+                id,
+                MultiEntitySearchHit {
+                    entity_type,
+                    score,
+                    rerank_score: None,
+                    summary: Self::node_summary(&node),
+                    node: Some(node),
+                    vector: None,
+                    expansion: Some(expansion),
+                },
+            ));
+        }
+        expanded.sort_by(|(a_id, a), (b_id, b)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a_id.cmp(b_id))
+        });
+        expanded.truncate(limit);
+        Ok(expanded.into_iter().map(|(_, hit)| hit).collect())
+    }
+}
+
+fn node_label<'a>(
+    nodes: &'a HashMap<String, HashMap<String, JsonValue>>,
+    id: &str,
+) -> Option<&'a str> {
+    nodes.get(id)?.get("label")?.as_str()
+}
+
+/// Edges followed from one node while expanding search hits, per edge type
+/// and direction; a file's functions beyond this many are not siblings.
+const GRAPH_EXPAND_FANOUT: usize = 16;
+const FILE_ENTITY: &str = "file";
+const CALLS_EDGE: &str = "edge_calls";
+const CONTAINS_EDGE: &str = "edge_contains";
+
+const DEVELOPER_ENTITY: &str = "developer";
+const PROJECT_ENTITY: &str = "project";
+const ENTITY_TABLE_PREFIX: &str = "silver/entities";
+const EDGE_TABLE_PREFIX: &str = "silver/edges";
+
+/// Columns outside the developer table that hold a login: the entity type, the
+/// column matched against the login, and the columns cleared on erasure.
+const LOGIN_REFERENCES: &[(&str, &str, &[&str])] = &[
+    ("issue", "author_login", &["author_login", "author_id"]),
+    (
+        "pullrequest",
+        "author_login",
+        &["author_login", "author_id"],
+    ),
+    ("pullrequest", "merged_by", &["merged_by"]),
+];
+
+/// Columns holding a JSON array of logins, by entity type; erasure takes the
+/// login out of the array.
+const LOGIN_LISTS: &[(&str, &str)] = &[("issue", "assignees")];
+
+/// Documents whose text names the author of the issue or pull request they
+/// were built from: the entity type, the vector type, and the vector column
+/// holding the entity's number.
+const AUTHORED_DOCS: &[(&str, &str, &str)] = &[
+    ("issue", IssueDoc::ENTITY_TYPE, "issue_number"),
+    ("pullrequest", PrDoc::ENTITY_TYPE, "pr_number"),
+];
+
+// Data-subject requests: everything the lake and engine hold about one developer.
+impl Lake {
+    /// Collects the `Developer` rows for `login`, the git identities its commits
+    /// were authored under, every edge touching those nodes, the issues and pull
+    /// requests that name `login` as author, merger or assignee, and the
+    /// documents built from the ones it authored.
+    ///
+    /// Commits keep their author only through an identity, and comments are
+    /// stored as digest text without theirs.
+    #[instrument(name = "lake.personal_data", skip_all)]
+    pub async fn personal_data(&self, login: &str) -> Result<PersonalDataExport> {
+        let login = login.trim();
+        if login.is_empty() {
+            return Err(StorageError::InvalidArg("login must not be empty".into()));
+        }
+
+        let developer_table = format!("silver/entities/{DEVELOPER_ENTITY}");
+        let index_table = format!("silver/index/{DEVELOPER_ENTITY}");
+        let developer_rows = self
+            .query_table(&developer_table, Some(&[("login", login)]), None)
+            .await?;
+        let index_rows = self
+            .query_table(&index_table, Some(&[("login", login)]), None)
+            .await?;
+
+        let mut node_ids = BTreeSet::new();
+        for row in &index_rows {
+            if let Some(id) = row.get("id").and_then(JsonValue::as_str) {
+                node_ids.insert(id.to_string());
+            }
+        }
+        for row in &developer_rows {
+            if let Some(id) = Self::stable_id_from_row(DEVELOPER_ENTITY, row) {
+                node_ids.insert(id);
+            }
+        }
+        let (identity_tables, identity_ids) =
+            self.git_identities(&node_ids, &developer_rows).await?;
+
+        let mut tables = vec![
+            PersonalDataTable {
+                table: developer_table,
+                rows: developer_rows,
+            },
+            PersonalDataTable {
+                table: index_table,
+                rows: index_rows,
+            },
+        ];
+        tables.extend(identity_tables);
+        node_ids.extend(identity_ids);
+        tables.extend(self.edges_touching(&node_ids).await?);
+        for (entity_type, column, _) in LOGIN_REFERENCES {
+            let table = format!("silver/entities/{entity_type}");
+            let rows = self
+                .query_table(&table, Some(&[(*column, login)]), None)
+                .await?;
+            tables.push(PersonalDataTable { table, rows });
+        }
+        for (entity_type, column) in LOGIN_LISTS {
+            let table = format!("silver/entities/{entity_type}");
+            let rows = self
+                .table_sql(
+                    &table,
+                    &format!(
+                        "SELECT * FROM {{{{table}}}} WHERE {}",
+                        Self::login_list_predicate(column, login)
+                    ),
+                )
+                .await?;
+            tables.push(PersonalDataTable { table, rows });
+        }
+        for (entity_type, vector_type, number_column) in AUTHORED_DOCS {
+            let entity_table = format!("silver/entities/{entity_type}");
+            let documented: Vec<String> = Self::rows_in(&tables, &entity_table)
+                .filter(|row| row.get("author_login").and_then(JsonValue::as_str) == Some(login))
+                .filter_map(|row| {
+                    let project_url = row.get("project_url")?.as_str()?;
+                    let number = row.get("number")?.as_i64()?;
+                    Some(format!(
+                        "(project_url = '{}' AND {} = {number})",
+                        Self::escape_sql_literal(project_url),
+                        Self::escape_sql_identifier(number_column)
+                    ))
+                })
+                .collect();
+            if documented.is_empty() {
+                continue;
+            }
+            let table = format!("silver/vectors/{vector_type}");
+            let rows = self
+                .table_sql(
+                    &table,
+                    &format!(
+                        "SELECT * FROM {{{{table}}}} WHERE {}",
+                        documented.join(" OR ")
+                    ),
+                )
+                .await?;
+            tables.push(PersonalDataTable { table, rows });
+        }
+        tables.retain(|table| !table.rows.is_empty());
+
+        let ids = node_ids.clone();
+        let engine_nodes = self
+            .read_graph(move |storage, txn| {
+                let mut nodes = Vec::new();
+                for id in &ids {
+                    let Ok(uuid) = Uuid::parse_str(id) else {
+                        continue;
+                    };
+                    if let Ok(node) = storage.get_node(txn, &uuid.as_u128()) {
+                        nodes.push(Self::node_to_map(node));
+                    }
+                }
+                Ok(nodes)
+            })
+            .await?;
+
+        Ok(PersonalDataExport {
+            login: login.to_string(),
+            node_ids: node_ids.into_iter().collect(),
+            tables,
+            engine_nodes,
+        })
+    }
+
+    /// The `GitIdentity` rows of a developer: those identity resolution linked
+    /// to one of `developer_ids`, and those recorded under the email of one of
+    /// `developer_rows`. Returns the entity and index rows and the node ids.
+    async fn git_identities(
+        &self,
+        developer_ids: &BTreeSet<String>,
+        developer_rows: &[HashMap<String, JsonValue>],
+    ) -> Result<(Vec<PersonalDataTable>, BTreeSet<String>)> {
+        let mut emails: BTreeSet<String> = developer_rows
+            .iter()
+            .filter_map(|row| row.get("email").and_then(JsonValue::as_str))
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty())
+            .collect();
+        let mut ids = BTreeSet::new();
+        if !developer_ids.is_empty() {
+            let linked = self
+                .table_sql(
+                    &same_as_table(),
+                    &format!(
+                        "SELECT to_node_id FROM {{{{table}}}} WHERE from_node_id IN ({})",
+                        Self::sql_in_list(developer_ids)
+                    ),
+                )
+                .await?;
+            ids.extend(
+                linked
+                    .iter()
+                    .filter_map(|row| row.get("to_node_id").and_then(JsonValue::as_str))
+                    .map(str::to_string),
+            );
+        }
+
+        let mut clauses = Vec::new();
+        if !ids.is_empty() {
+            clauses.push(format!("id IN ({})", Self::sql_in_list(&ids)));
+        }
+        if !emails.is_empty() {
+            clauses.push(format!("email IN ({})", Self::sql_in_list(&emails)));
+        }
+        if clauses.is_empty() {
+            return Ok((Vec::new(), ids));
+        }
+        let index_table = format!("silver/index/{}", GitIdentity::ENTITY_TYPE);
+        let index_rows = self
+            .table_sql(
+                &index_table,
+                &format!("SELECT * FROM {{{{table}}}} WHERE {}", clauses.join(" OR ")),
+            )
+            .await?;
+        for row in &index_rows {
+            if let Some(id) = row.get("id").and_then(JsonValue::as_str) {
+                ids.insert(id.to_string());
+            }
+            if let Some(email) = row.get("email").and_then(JsonValue::as_str) {
+                emails.insert(email.to_string());
+            }
+        }
+        let entity_table = GitIdentity::table_name();
+        let entity_rows = if emails.is_empty() {
+            Vec::new()
+        } else {
+            self.table_sql(
+                &entity_table,
+                &format!(
+                    "SELECT * FROM {{{{table}}}} WHERE email IN ({})",
+                    Self::sql_in_list(&emails)
+                ),
+            )
+            .await?
+        };
+        ids.extend(
+            entity_rows
+                .iter()
+                .filter_map(|row| Self::stable_id_from_row(GitIdentity::ENTITY_TYPE, row)),
+        );
+        Ok((
+            vec![
+                PersonalDataTable {
+                    table: entity_table,
+                    rows: entity_rows,
+                },
+                PersonalDataTable {
+                    table: index_table,
+                    rows: index_rows,
+                },
+            ],
+            ids,
+        ))
+    }
+
+    fn rows_in<'a>(
+        tables: &'a [PersonalDataTable],
+        table: &'a str,
+    ) -> impl Iterator<Item = &'a HashMap<String, JsonValue>> + 'a {
+        tables
+            .iter()
+            .filter(move |found| found.table == table)
+            .flat_map(|found| found.rows.iter())
+    }
+
+    /// Matches rows whose JSON array of logins in `column` lists `login`.
+    fn login_list_predicate(column: &str, login: &str) -> String {
+        let quoted = JsonValue::String(login.to_string()).to_string();
+        format!(
+            "{} LIKE '%{}%'",
+            Self::escape_sql_identifier(column),
+            Self::escape_sql_literal(&quoted)
+        )
+    }
+
+    /// `column`, a compact JSON array of logins, without `login`.
+    fn login_list_without(column: &str, login: &str) -> String {
+        let quoted = Self::escape_sql_literal(&JsonValue::String(login.to_string()).to_string());
+        let column = Self::escape_sql_identifier(column);
+        format!(
+            "replace(replace(replace({column}, '{quoted},', ''), ',{quoted}', ''), '{quoted}', '')"
+        )
+    }
+
+    /// Removes what [`Lake::personal_data`] finds for `login`.
+    ///
+    /// Developer and git identity rows, their index entries and edges are
+    /// deleted, and so are the documents built from the issues and pull
+    /// requests the developer authored, with their vectors. The issues and
+    /// pull requests themselves stay but lose their author and assignee
+    /// fields, and their search index entries are rebuilt without them. Older
+    /// versions of every touched table are vacuumed so the data cannot be
+    /// recovered through time travel.
+    #[instrument(name = "lake.erase_personal_data", skip_all)]
+    pub async fn erase_personal_data(&self, login: &str) -> Result<PersonalDataErasure> {
+        let export = self.personal_data(login).await?;
+        let login = export.login.as_str();
+        let node_ids: BTreeSet<String> = export.node_ids.iter().cloned().collect();
+        let mut erasure = PersonalDataErasure {
+            login: login.to_string(),
+            node_ids: export.node_ids.clone(),
+            ..Default::default()
+        };
+        let literal = Self::escape_sql_literal(login);
+        let rows_of = |table| Self::rows_in(&export.tables, table);
+
+        for table in [
+            format!("silver/entities/{DEVELOPER_ENTITY}"),
+            format!("silver/index/{DEVELOPER_ENTITY}"),
+        ] {
+            let deleted = self
+                .delete_where(&table, &format!("login = '{literal}'"))
+                .await?;
+            if deleted > 0 {
+                erasure.rows_deleted.insert(table, deleted);
+            }
+        }
+
+        let identity_table = GitIdentity::table_name();
+        let identity_index = format!("silver/index/{}", GitIdentity::ENTITY_TYPE);
+        let emails: BTreeSet<String> = rows_of(&identity_table)
+            .chain(rows_of(&identity_index))
+            .filter_map(|row| row.get("email").and_then(JsonValue::as_str))
+            .map(str::to_string)
+            .collect();
+        if !emails.is_empty() {
+            let emails = Self::sql_in_list(&emails);
+            for table in [&identity_table, &identity_index] {
+                let deleted = self
+                    .delete_where(table, &format!("email IN ({emails})"))
+                    .await?;
+                if deleted > 0 {
+                    erasure.rows_deleted.insert(table.clone(), deleted);
+                }
+            }
+        }
+
+        if !node_ids.is_empty() {
+            let ids = Self::sql_in_list(&node_ids);
+            for table in export
+                .tables
+                .iter()
+                .filter(|table| table.table.starts_with(EDGE_TABLE_PREFIX))
+            {
+                let deleted = self
+                    .delete_where(
+                        &table.table,
+                        &format!("from_node_id IN ({ids}) OR to_node_id IN ({ids})"),
+                    )
+                    .await?;
+                if deleted > 0 {
+                    erasure.rows_deleted.insert(table.table.clone(), deleted);
+                }
+            }
+        }
+
+        // Engine nodes of issues and pull requests are keyed by their primary keys,
+        // so their ids are taken from the rows before the author fields are cleared.
+        let mut updates: BTreeMap<u128, Vec<(String, HelixValue)>> = BTreeMap::new();
+        for (entity_type, column, cleared) in LOGIN_REFERENCES {
+            let table = format!("silver/entities/{entity_type}");
+            let authored = rows_of(&table)
+                .filter(|row| row.get(*column).and_then(JsonValue::as_str) == Some(login))
+                .filter_map(|row| Self::stable_id_from_row(entity_type, row))
+                .filter_map(|id| Uuid::parse_str(&id).ok());
+            for id in authored {
+                updates.entry(id.as_u128()).or_default().extend(
+                    cleared
+                        .iter()
+                        .map(|column| (column.to_string(), HelixValue::Empty)),
+                );
+            }
+            let updated = self
+                .clear_columns_where(
+                    &table,
+                    &format!("{} = '{literal}'", Self::escape_sql_identifier(column)),
+                    cleared,
+                )
+                .await?;
+            if updated > 0 {
+                *erasure.rows_updated.entry(table).or_default() += updated;
+            }
+        }
+        for (entity_type, column) in LOGIN_LISTS {
+            let table = format!("silver/entities/{entity_type}");
+            for row in rows_of(&table) {
+                let Some(listed) = row.get(*column).and_then(JsonValue::as_str) else {
+                    continue;
+                };
+                let Ok(logins) = serde_json::from_str::<Vec<String>>(listed) else {
+                    continue;
+                };
+                if !logins.iter().any(|listed| listed == login) {
+                    continue;
+                }
+                let Some(id) = Self::stable_id_from_row(entity_type, row)
+                    .and_then(|id| Uuid::parse_str(&id).ok())
+                else {
+                    continue;
+                };
+                let kept: Vec<String> = logins
+                    .into_iter()
+                    .filter(|listed| listed != login)
+                    .collect();
+                updates.entry(id.as_u128()).or_default().push((
+                    column.to_string(),
+                    HelixValue::String(serde_json::to_string(&kept)?),
+                ));
+            }
+            let updated = self
+                .update_where(
+                    &table,
+                    &Self::login_list_predicate(column, login),
+                    &[(*column, Self::login_list_without(column, login))],
+                )
+                .await?;
+            if updated > 0 {
+                *erasure.rows_updated.entry(table).or_default() += updated;
+            }
+        }
+
+        let mut doc_edges = Vec::new();
+        let mut doc_ids = BTreeSet::new();
+        for (_, vector_type, _) in AUTHORED_DOCS {
+            let ids: BTreeSet<String> = rows_of(&format!("silver/vectors/{vector_type}"))
+                .filter_map(|row| row.get("id").and_then(JsonValue::as_str))
+                .map(str::to_string)
+                .collect();
+            if ids.is_empty() {
+                continue;
+            }
+            let (edges, deleted) = self.delete_vector_rows(vector_type, &ids).await?;
+            doc_edges.extend(edges);
+            doc_ids.extend(ids);
+            for (table, count) in deleted {
+                *erasure.rows_deleted.entry(table).or_default() += count;
+            }
+        }
+
+        let touched: BTreeSet<&String> = erasure
+            .rows_deleted
+            .keys()
+            .chain(erasure.rows_updated.keys())
+            .collect();
+        for table in touched {
+            self.purge_history(table).await?;
+        }
+
+        let dropped: Vec<u128> = node_ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .map(|id| id.as_u128())
+            .collect();
+        let storage = Arc::clone(&self.engine.storage);
+        let engine_map = Arc::clone(&self.engine_map);
+        let (removed, updated, vectors_removed) = tokio::task::spawn_blocking(move || {
+            engine_map.write(&storage.graph_env, |txn| {
+                let mut removed = 0;
+                for node_id in &dropped {
+                    match storage.drop_node(txn, node_id) {
+                        Ok(()) => removed += 1,
+                        Err(GraphError::NodeNotFound) => continue,
+                        Err(err) => return Err(StorageError::Graph(err)),
+                    }
+                    if let Some(bm25) = &storage.bm25 {
+                        bm25.delete_doc(txn, *node_id)?;
+                    }
+                }
+                let mut updated = 0;
+                for (node_id, props) in &updates {
+                    if storage.get_node(txn, node_id).is_err() {
+                        continue;
+                    }
+                    let traversal = G::new(storage.clone(), txn)
+                        .n_from_id(node_id)
+                        .collect_to::<Vec<_>>();
+                    G::new_mut_from(storage.clone(), txn, traversal)
+                        .update(Some(props.clone()))
+                        .for_each(|_| {});
+                    Self::reindex_bm25(&storage, txn, node_id)?;
+                    updated += 1;
+                }
+                let vectors_removed =
+                    Self::drop_engine_vectors(&storage, txn, &doc_edges, &doc_ids)?;
+                Ok((removed, updated, vectors_removed))
+            })
+        })
+        .await
+        .map_err(|e| StorageError::SyncError(format!("erasure task failed: {}", e)))??;
+        erasure.engine_nodes_removed += removed;
+        erasure.engine_nodes_updated += updated;
+        erasure.engine_vectors_removed += vectors_removed as usize;
+
+        Ok(erasure)
+    }
+
+    /// Replaces the BM25 entry of `node_id` with one built from its current
+    /// properties, as the synchronizer builds it.
+    fn reindex_bm25(
+        storage: &HelixGraphStorage,
+        txn: &mut RwTxn<'_>,
+        node_id: &u128,
+    ) -> Result<()> {
+        let Some(bm25) = &storage.bm25 else {
+            return Ok(());
+        };
+        let node = storage.get_node(txn, node_id)?;
+        bm25.delete_doc(txn, *node_id)?;
+        let mut data = node
+            .properties
+            .as_ref()
+            .map(|props| props.flatten_bm25())
+            .unwrap_or_default();
+        data.push_str(&node.label);
+        bm25.insert_doc(txn, *node_id, &data)?;
+        Ok(())
+    }
+
+    /// Deletes the node `id` from the engine and the lake.
+    ///
+    /// Its entity and index rows and every lake edge row naming it are deleted,
+    /// the engine drops the node with its adjacent edges, and vectors linked to
+    /// it by an edge are removed from both the engine and their lake tables.
+    /// Unlike an erasure, older table versions are kept. Returns `None` when
+    /// nothing is stored under `id`.
+    #[instrument(name = "lake.delete_node", skip(self))]
+    pub async fn delete_node(&self, id: &str) -> Result<Option<NodeDeletion>> {
+        let uuid = Uuid::parse_str(id.trim())
+            .map_err(|_| StorageError::InvalidArg(format!("invalid node id '{id}'")))?;
+        let node_id = uuid.as_u128();
+        let id = uuid.to_string();
+        let literal = Self::escape_sql_literal(&id);
+
+        let engine_label = self
+            .read_graph(move |storage, txn| {
+                Ok(storage.get_node(txn, &node_id).ok().map(|node| node.label))
+            })
+            .await?;
+        let candidates = match &engine_label {
+            Some(label) => vec![label.clone()],
+            None => self.get_available_index_entity_types().await?,
+        };
+        let mut entity_type = None;
+        let mut index_rows = Vec::new();
+        for candidate in candidates {
+            let rows = self
+                .table_sql(
+                    &format!("silver/index/{candidate}"),
+                    &format!("SELECT * FROM {{{{table}}}} WHERE id = '{literal}'"),
+                )
+                .await?;
+            if !rows.is_empty() || engine_label.is_some() {
+                entity_type = Some(candidate);
+                index_rows = rows;
+                break;
+            }
+        }
+
+        let node_ids = BTreeSet::from([id.clone()]);
+        let edge_tables: Vec<PersonalDataTable> = self
+            .edges_touching(&node_ids)
+            .await?
+            .into_iter()
+            .filter(|table| !table.rows.is_empty())
+            .collect();
+        if entity_type.is_none() && edge_tables.is_empty() {
+            return Ok(None);
+        }
+
+        // Vectors hang off the node through edges; the other endpoint of each
+        // edge row is a vector when the engine resolves it as one.
+        let endpoints: BTreeSet<u128> = edge_tables
+            .iter()
+            .flat_map(|table| table.rows.iter())
+            .flat_map(|row| [row.get("from_node_id"), row.get("to_node_id")])
+            .flatten()
+            .filter_map(JsonValue::as_str)
+            .filter(|endpoint| *endpoint != id)
+            .filter_map(|endpoint| Uuid::parse_str(endpoint).ok())
+            .map(|endpoint| endpoint.as_u128())
+            .collect();
+        let vectors: BTreeMap<u128, String> = self
+            .read_graph(move |storage, txn| {
+                let mut vectors = BTreeMap::new();
+                for vector_id in endpoints {
+                    match storage.vectors.get_vector(txn, vector_id, 0, true) {
+                        Ok(vector) => {
+                            let label = vector
+                                .get_label()
+                                .map(|value| value.inner_stringify())
+                                .unwrap_or_default();
+                            vectors.insert(vector_id, label);
+                        }
+                        Err(VectorError::VectorNotFound(_))
+                        | Err(VectorError::EntryPointNotFound) => {}
+                        Err(err) => return Err(StorageError::Graph(err.into())),
+                    }
+                }
+                Ok(vectors)
+            })
+            .await?;
+
+        let mut deletion = NodeDeletion {
+            node_id: id.clone(),
+            entity_type: entity_type.clone(),
+            ..Default::default()
+        };
+
+        if let Some(entity_type) = &entity_type {
+            let entity_table = format!("silver/entities/{entity_type}");
+            for row in &index_rows {
+                let Some(predicate) = Self::primary_key_predicate(row) else {
+                    continue;
+                };
+                let deleted = self.delete_where(&entity_table, &predicate).await?;
+                if deleted > 0 {
+                    *deletion
+                        .rows_deleted
+                        .entry(entity_table.clone())
+                        .or_default() += deleted;
+                }
+            }
+            let index_table = format!("silver/index/{entity_type}");
+            let deleted = self
+                .delete_where(&index_table, &format!("id = '{literal}'"))
+                .await?;
+            if deleted > 0 {
+                deletion.rows_deleted.insert(index_table, deleted);
+            }
+        }
+
+        for table in &edge_tables {
+            let deleted = self
+                .delete_where(
+                    &table.table,
+                    &format!("from_node_id = '{literal}' OR to_node_id = '{literal}'"),
+                )
+                .await?;
+            if deleted > 0 {
+                deletion.rows_deleted.insert(table.table.clone(), deleted);
+            }
+        }
 
-        let bm25_handle = if !query_text.trim().is_empty() {
-            let storage = Arc::clone(&self.engine.storage);
-            let text = query_text.trim().to_string();
-            Some(tokio::task::spawn_blocking(
-                move || -> Result<Vec<(u128, f32)>> {
-                    let txn = storage.graph_env.read_txn()?;
-                    let bm25 = storage.bm25.as_ref().ok_or_else(|| {
-                        StorageError::SyncError("BM25 index is not enabled for this store".into())
-                    })?;
-                    bm25.search(&txn, &text, limit * 2)
-                        .map_err(StorageError::Graph)
-                },
-            ))
-        } else {
-            None
-        };
+        let mut vectors_by_type: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for (vector_id, label) in &vectors {
+            if !label.is_empty() {
+                vectors_by_type
+                    .entry(label.as_str())
+                    .or_default()
+                    .insert(Uuid::from_u128(*vector_id).to_string());
+            }
+        }
+        for (vector_type, ids) in &vectors_by_type {
+            let ids = Self::sql_in_list(ids);
+            let mut tables = vec![(format!("silver/vectors/{vector_type}"), "id")];
+            if let Some(meta) = vector_index(vector_type) {
+                tables.push((meta.index_table.to_string(), "vector_uuid"));
+            }
+            for (table, column) in tables {
+                let deleted = self
+                    .delete_where(&table, &format!("{column} IN ({ids})"))
+                    .await?;
+                if deleted > 0 {
+                    deletion.rows_deleted.insert(table, deleted);
+                }
+            }
+        }
 
-        let vector_handle = if !query_vector.is_empty() {
-            let storage = Arc::clone(&self.engine.storage);
-            let query_vec: Vec<f64> = query_vector.to_vec();
-            let label = entity_type.to_string();
-            Some(tokio::task::spawn_blocking(
-                move || -> Result<Vec<HVector>> {
-                    let txn = storage.graph_env.read_txn()?;
-                    match storage.vectors.search::<fn(&HVector, &RoTxn) -> bool>(
-                        &txn,
-                        &query_vec,
-                        limit * 2,
-                        &label,
-                        None,
-                        false,
-                    ) {
-                        Ok(results) => Ok(results),
-                        Err(VectorError::EntryPointNotFound) => Ok(Vec::new()),
-                        Err(err) => Err(StorageError::Graph(err.into())),
+        let (vectors_removed, engine_node_removed) =
+            self.engine_map
+                .write(&self.engine.storage.graph_env, |txn| {
+                    let mut vectors_removed = Vec::new();
+                    for vector_id in vectors.keys() {
+                        match self.engine.storage.vectors.delete(txn, *vector_id) {
+                            Ok(()) => vectors_removed.push(Uuid::from_u128(*vector_id).to_string()),
+                            Err(VectorError::VectorNotFound(_)) => {}
+                            Err(err) => return Err(StorageError::Graph(err.into())),
+                        }
                     }
-                },
-            ))
-        } else {
-            None
-        };
+                    let node_removed = match self.engine.storage.drop_node(txn, &node_id) {
+                        Ok(()) => true,
+                        Err(GraphError::NodeNotFound) => false,
+                        Err(err) => return Err(StorageError::Graph(err)),
+                    };
+                    if node_removed {
+                        if let Some(bm25) = &self.engine.storage.bm25 {
+                            bm25.delete_doc(txn, node_id)?;
+                        }
+                    }
+                    Ok((vectors_removed, node_removed))
+                })?;
+        deletion.vectors_removed.extend(vectors_removed);
+        deletion.engine_node_removed = engine_node_removed;
 
-        let bm25_results = if let Some(handle) = bm25_handle {
-            handle
-                .await
-                .map_err(|e| StorageError::SyncError(format!("BM25 search task failed: {}", e)))??
+        Ok(Some(deletion))
+    }
+
+    /// Deletes the edge `id` from the lake edge table `table_name` and from the
+    /// engine, leaving its endpoints alone. Returns whether either held it.
+    pub async fn delete_edge(&self, table_name: &str, id: &str) -> Result<bool> {
+        let uuid = Uuid::parse_str(id.trim())
+            .map_err(|_| StorageError::InvalidArg(format!("invalid edge id '{id}'")))?;
+        let edge_id = uuid.as_u128();
+        let deleted = if self.table_exists(table_name).await? {
+            let literal = Self::escape_sql_literal(&uuid.to_string());
+            self.delete_where(table_name, &format!("id = '{literal}'"))
+                .await?
         } else {
-            Vec::new()
+            0
         };
+        let dropped = self
+            .engine_map
+            .write(&self.engine.storage.graph_env, |txn| {
+                match self.engine.storage.get_edge(txn, &edge_id) {
+                    Ok(_) => {}
+                    Err(GraphError::EdgeNotFound) => return Ok(false),
+                    Err(err) => return Err(StorageError::Graph(err)),
+                }
+                self.engine
+                    .storage
+                    .drop_edge(txn, &edge_id)
+                    .map_err(StorageError::Graph)?;
+                Ok(true)
+            })?;
+        Ok(deleted > 0 || dropped)
+    }
 
-        let vector_results = if let Some(handle) = vector_handle {
-            handle.await.map_err(|e| {
-                StorageError::SyncError(format!("Vector search task failed: {}", e))
-            })??
+    /// Deletes the edges `ids` from the lake edge table `table_name`, in one
+    /// commit, and from the engine. Returns the lake rows removed.
+    pub async fn delete_edges(&self, table_name: &str, ids: &BTreeSet<String>) -> Result<u64> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let deleted = if self.table_exists(table_name).await? {
+            self.delete_where(table_name, &format!("id IN ({})", Self::sql_in_list(ids)))
+                .await?
         } else {
-            Vec::new()
+            0
         };
+        self.engine_map
+            .write(&self.engine.storage.graph_env, |txn| {
+                for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+                    let edge_id = id.as_u128();
+                    match self.engine.storage.get_edge(txn, &edge_id) {
+                        Ok(_) => self
+                            .engine
+                            .storage
+                            .drop_edge(txn, &edge_id)
+                            .map_err(StorageError::Graph)?,
+                        Err(GraphError::EdgeNotFound) => {}
+                        Err(err) => return Err(StorageError::Graph(err)),
+                    }
+                }
+                Ok(())
+            })?;
+        Ok(deleted as u64)
+    }
 
-        let mut combined_scores: HashMap<u128, f32> = HashMap::new();
-        for (doc_id, score) in bm25_results {
-            combined_scores
-                .entry(doc_id)
-                .and_modify(|existing| *existing = existing.max(alpha * score))
-                .or_insert(alpha * score);
+    /// Deletes the `vector_type` vectors `ids` from the lake, the vector index
+    /// and the engine, with the edges its vector rules drew to them. Returns
+    /// the number of engine vectors removed.
+    pub async fn delete_vectors(&self, vector_type: &str, ids: &BTreeSet<String>) -> Result<u64> {
+        if ids.is_empty() {
+            return Ok(0);
         }
+        let (edge_ids, _) = self.delete_vector_rows(vector_type, ids).await?;
+        let storage = &self.engine.storage;
+        self.engine_map.write(&storage.graph_env, |txn| {
+            Self::drop_engine_vectors(storage, txn, &edge_ids, ids)
+        })
+    }
 
-        for vector in &vector_results {
-            let label_matches = vector
-                .get_label()
-                .map(|value| value.inner_stringify() == entity_type)
-                .unwrap_or(true);
-            if !label_matches {
-                continue;
+    /// Deletes the lake rows of the `vector_type` vectors `ids`, their vector
+    /// index entries and the edges its vector rules drew to them. Returns the
+    /// engine ids of those edges and the rows deleted per table.
+    async fn delete_vector_rows(
+        &self,
+        vector_type: &str,
+        ids: &BTreeSet<String>,
+    ) -> Result<(Vec<u128>, BTreeMap<String, usize>)> {
+        let list = Self::sql_in_list(ids);
+        let edge_tables: BTreeSet<String> = vector_rules(vector_type)
+            .into_iter()
+            .flat_map(|rules| rules.rules.iter())
+            .map(|rule| {
+                let label = rule
+                    .edge_type
+                    .strip_prefix("edge_")
+                    .unwrap_or(rule.edge_type);
+                format!("silver/edges/{}", label.to_lowercase())
+            })
+            .collect();
+        let mut edge_ids = Vec::new();
+        let mut tables = Vec::new();
+        for table in edge_tables {
+            let rows = self
+                .table_sql(
+                    &table,
+                    &format!("SELECT id FROM {{{{table}}}} WHERE to_node_id IN ({list})"),
+                )
+                .await?;
+            edge_ids.extend(
+                rows.iter()
+                    .filter_map(|row| row.get("id").and_then(JsonValue::as_str))
+                    .filter_map(|id| Uuid::parse_str(id).ok())
+                    .map(|id| id.as_u128()),
+            );
+            tables.push((table, format!("to_node_id IN ({list})")));
+        }
+        tables.push((
+            format!("silver/vectors/{vector_type}"),
+            format!("id IN ({list})"),
+        ));
+        if let Some(meta) = vector_index(vector_type) {
+            tables.push((
+                meta.index_table.to_string(),
+                format!("vector_uuid IN ({list})"),
+            ));
+        }
+
+        let mut deleted = BTreeMap::new();
+        for (table, predicate) in tables {
+            let count = self.delete_where(&table, &predicate).await?;
+            if count > 0 {
+                deleted.insert(table, count);
             }
-            let similarity = (1.0 / (1.0 + vector.distance.unwrap_or(0.0))) as f32;
-            combined_scores
-                .entry(vector.id)
-                .and_modify(|existing| *existing += (1.0 - alpha) * similarity)
-                .or_insert((1.0 - alpha) * similarity);
         }
+        Ok((edge_ids, deleted))
+    }
 
-        let mut entries: Vec<(u128, f32)> = combined_scores.into_iter().collect();
-        entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        entries.truncate(limit);
+    /// Drops the edges `edge_ids` and the vectors `ids` from the engine;
+    /// returns the number of vectors removed.
+    fn drop_engine_vectors(
+        storage: &HelixGraphStorage,
+        txn: &mut RwTxn<'_>,
+        edge_ids: &[u128],
+        ids: &BTreeSet<String>,
+    ) -> Result<u64> {
+        for edge_id in edge_ids {
+            match storage.get_edge(txn, edge_id) {
+                Ok(_) => storage
+                    .drop_edge(txn, edge_id)
+                    .map_err(StorageError::Graph)?,
+                Err(GraphError::EdgeNotFound) => {}
+                Err(err) => return Err(StorageError::Graph(err)),
+            }
+        }
+        let mut removed = 0;
+        for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            match storage.vectors.delete(txn, id.as_u128()) {
+                Ok(()) => removed += 1,
+                Err(VectorError::VectorNotFound(_)) => {}
+                Err(err) => return Err(StorageError::Graph(err.into())),
+            }
+        }
+        Ok(removed)
+    }
 
-        let txn = self.engine.storage.graph_env.read_txn()?;
-        let mut hits = Vec::with_capacity(entries.len());
-        for (doc_id, score) in entries {
-            match self.engine.storage.get_node(&txn, &doc_id) {
-                Ok(node) if node.label == entity_type => {
-                    hits.push(HybridSearchHit {
-                        score,
-                        node: Some(Self::node_to_map(node)),
-                        vector: None,
-                    });
-                }
-                Ok(_) => continue,
-                Err(GraphError::NodeNotFound) => {
-                    match self
-                        .engine
-                        .storage
-                        .vectors
-                        .get_vector(&txn, doc_id, 0, true)
-                    {
-                        Ok(vector)
-                            if vector
-                                .get_label()
-                                .map(|value| value.inner_stringify() == entity_type)
-                                .unwrap_or(true) =>
-                        {
-                            hits.push(HybridSearchHit {
-                                score,
-                                node: None,
-                                vector: Some(Self::vector_to_map(vector)),
-                            });
-                        }
-                        Ok(_) => continue,
-                        Err(err) => return Err(StorageError::Graph(err.into())),
-                    }
+    /// `AND` of the primary-key columns of an index row, matched as text so the
+    /// predicate holds whatever type the entity table stores them as.
+    fn primary_key_predicate(index_row: &HashMap<String, JsonValue>) -> Option<String> {
+        let clauses: Vec<String> = index_row
+            .iter()
+            .filter(|(column, _)| !matches!(column.as_str(), "id" | "updated_at"))
+            .map(|(column, value)| {
+                let column = Self::escape_sql_identifier(column);
+                match value {
+                    JsonValue::Null => format!("{column} IS NULL"),
+                    JsonValue::String(value) => format!(
+                        "CAST({column} AS VARCHAR) = '{}'",
+                        Self::escape_sql_literal(value)
+                    ),
+                    other => format!(
+                        "CAST({column} AS VARCHAR) = '{}'",
+                        Self::escape_sql_literal(&other.to_string())
+                    ),
                 }
-                Err(err) => return Err(StorageError::from(err)),
+            })
+            .collect();
+        if clauses.is_empty() {
+            None
+        } else {
+            Some(clauses.join(" AND "))
+        }
+    }
+
+    async fn edges_touching(&self, node_ids: &BTreeSet<String>) -> Result<Vec<PersonalDataTable>> {
+        if node_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = Self::sql_in_list(node_ids);
+        let mut found = Vec::new();
+        for summary in self.list_tables(EDGE_TABLE_PREFIX).await? {
+            let has_endpoints = ["from_node_id", "to_node_id"]
+                .iter()
+                .all(|name| summary.columns.iter().any(|column| column.name == *name));
+            if !has_endpoints {
+                continue;
             }
+            let rows = self
+                .table_sql(
+                    &summary.table_path,
+                    &format!(
+                        "SELECT * FROM {{{{table}}}} WHERE from_node_id IN ({ids}) OR to_node_id IN ({ids})"
+                    ),
+                )
+                .await?;
+            found.push(PersonalDataTable {
+                table: summary.table_path,
+                rows,
+            });
         }
+        Ok(found)
+    }
 
-        Ok(hits)
+    async fn delete_where(&self, table_name: &str, predicate: &str) -> Result<usize> {
+        let writer = self.table_writer(table_name);
+        let _writing = writer.lock().await;
+        let deleted = self.tables.delete_where(table_name, predicate).await?;
+        self.record_write();
+        Ok(deleted)
     }
 
-    pub async fn search_hybrid_multi(
+    async fn update_where(
         &self,
-        entity_types: &[String],
-        query_text: &str,
-        query_vector: &[f64],
-        alpha: f32,
-        limit: usize,
-    ) -> Result<Vec<MultiEntitySearchHit>> {
-        if entity_types.is_empty() || (query_text.trim().is_empty() && query_vector.is_empty()) {
-            return Ok(Vec::new());
+        table_name: &str,
+        predicate: &str,
+        assignments: &[(&str, String)],
+    ) -> Result<usize> {
+        let writer = self.table_writer(table_name);
+        let _writing = writer.lock().await;
+        let updated = self
+            .tables
+            .update_where(table_name, predicate, assignments)
+            .await?;
+        self.record_write();
+        Ok(updated)
+    }
+
+    async fn clear_columns_where(
+        &self,
+        table_name: &str,
+        predicate: &str,
+        columns: &[&str],
+    ) -> Result<usize> {
+        let writer = self.table_writer(table_name);
+        let _writing = writer.lock().await;
+        let updated = self
+            .tables
+            .clear_columns_where(table_name, predicate, columns)
+            .await?;
+        self.record_write();
+        Ok(updated)
+    }
+
+    /// Drops files no longer referenced by the latest version of `table_name`.
+    async fn purge_history(&self, table_name: &str) -> Result<()> {
+        self.tables.purge_history(table_name).await
+    }
+
+    /// Compacts and vacuums `table_name`, see [`TableStore::maintain`].
+    pub async fn maintain_table(
+        &self,
+        table_name: &str,
+        options: &MaintenanceOptions,
+    ) -> Result<TableMaintenance> {
+        let writer = self.table_writer(table_name);
+        let _writing = writer.lock().await;
+        let report = self.tables.maintain(table_name, options).await?;
+        if report.compacted_files > 0 {
+            self.record_write();
         }
+        Ok(report)
+    }
 
-        let trimmed = query_text.trim();
-        let mut aggregate: Vec<MultiEntitySearchHit> = Vec::new();
+    /// Recomputes a node id from its primary key values, as the synchronizer does.
+    fn stable_id_from_row(entity_type: &str, row: &HashMap<String, JsonValue>) -> Option<String> {
+        let metadata = SCHEMA_REGISTRY.entity(entity_type)?;
+        if metadata.primary_keys.is_empty() {
+            return None;
+        }
+        let key_values = metadata
+            .primary_keys
+            .iter()
+            .map(|key| {
+                let value = match row.get(*key)? {
+                    JsonValue::String(value) => value.clone(),
+                    JsonValue::Null => return None,
+                    other => other.to_string(),
+                };
+                Some((*key, value))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let id = utils::id::stable_node_id_u128(entity_type, &key_values);
+        Some(Uuid::from_u128(id).to_string())
+    }
 
-        for entity_type in entity_types {
-            let hits = self
-                .search_hybrid(entity_type, trimmed, query_vector, alpha, limit)
-                .await?;
+    fn sql_in_list(values: &BTreeSet<String>) -> String {
+        values
+            .iter()
+            .map(|value| format!("'{}'", Self::escape_sql_literal(value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
 
-            aggregate.extend(hits.into_iter().map(|hit| {
-                MultiEntitySearchHit {
-                    entity_type: entity_type.clone(),
-                    score: hit.score,
-                    summary: hit
-                        .node
-                        .as_ref()
-                        .and_then(|node| {
-                            Self::extract_text_field(
-                                node,
-                                &["title", "name", "label", "signature", "path"],
-                            )
-                        })
-                        .or_else(|| {
-                            hit.vector.as_ref().and_then(|vector| {
-                                Self::extract_text_field(
-                                    vector,
-                                    &["text", "body", "summary", "content", "preview"],
-                                )
-                            })
-                        }),
-                    node: hit.node,
-                    vector: hit.vector,
+// Project datasets: the part of the lake that belongs to one project.
+impl Lake {
+    /// The id of the project at `project_url` and the ids of the nodes that
+    /// belong to it, by entity type: the project, the nodes its outgoing edges
+    /// reach transitively, and the sources of edges into it. The walk stops at
+    /// developers and other projects, which are shared with the rest of the graph.
+    #[instrument(name = "lake.project_members", skip(self))]
+    pub(crate) async fn project_members(
+        &self,
+        project_url: &str,
+    ) -> Result<(String, BTreeMap<String, HashSet<String>>)> {
+        let project_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+            PROJECT_ENTITY,
+            &[("url", project_url.to_string())],
+        ))
+        .to_string();
+        if self
+            .lookup_node_in_index(PROJECT_ENTITY, &project_id)
+            .await?
+            .is_none()
+        {
+            return Err(StorageError::NotFound(format!("project '{project_url}'")));
+        }
+
+        let mut members: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        members
+            .entry(PROJECT_ENTITY.to_string())
+            .or_default()
+            .insert(project_id.clone());
+        let mut outgoing: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for table in self.tables.list(EDGE_TABLE_PREFIX).await? {
+            let Some(mut batches) = self.scan_table(&table).await? else {
+                continue;
+            };
+            while let Some(batch) = batches.next().await {
+                let batch = batch.map_err(|e| StorageError::Other(e.into()))?;
+                let (Some(from), Some(from_type), Some(to), Some(to_type)) = (
+                    Self::string_column(&batch, "from_node_id")?,
+                    Self::string_column(&batch, "from_node_type")?,
+                    Self::string_column(&batch, "to_node_id")?,
+                    Self::string_column(&batch, "to_node_type")?,
+                ) else {
+                    break;
+                };
+                for row in 0..batch.num_rows() {
+                    if [&from, &from_type, &to, &to_type]
+                        .iter()
+                        .any(|column| column.is_null(row))
+                    {
+                        continue;
+                    }
+                    if to.value(row) == project_id {
+                        members
+                            .entry(from_type.value(row).to_string())
+                            .or_default()
+                            .insert(from.value(row).to_string());
+                    }
+                    outgoing
+                        .entry(from.value(row).to_string())
+                        .or_default()
+                        .push((to.value(row).to_string(), to_type.value(row).to_string()));
                 }
-            }));
+            }
         }
 
-        aggregate.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        aggregate.truncate(limit.max(1));
+        let mut visited: HashSet<String> = members.values().flatten().cloned().collect();
+        let mut queue = VecDeque::from([project_id.clone()]);
+        while let Some(node) = queue.pop_front() {
+            for (next, next_type) in outgoing.remove(&node).unwrap_or_default() {
+                if !visited.insert(next.clone()) {
+                    continue;
+                }
+                members
+                    .entry(next_type.clone())
+                    .or_default()
+                    .insert(next.clone());
+                if next_type != DEVELOPER_ENTITY && next_type != PROJECT_ENTITY {
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok((project_id, members))
+    }
 
-        Ok(aggregate)
+    /// Streams every row of `table_name`; `None` when there is no such table.
+    pub(crate) async fn scan_table(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<SendableRecordBatchStream>> {
+        let Some(table) = self.open_table(table_name).await? else {
+            return Ok(None);
+        };
+        let frame = Self::single_partition_session()
+            .read_table(table.provider)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        frame
+            .execute_stream()
+            .await
+            .map(Some)
+            .map_err(|e| StorageError::Other(e.into()))
+    }
+
+    /// Column `name` of `batch` as strings, `None` when the batch has none.
+    pub(crate) fn string_column(batch: &RecordBatch, name: &str) -> Result<Option<StringArray>> {
+        let Some(column) = batch.column_by_name(name) else {
+            return Ok(None);
+        };
+        let strings = cast(column, &DataType::Utf8)?;
+        Ok(strings.as_any().downcast_ref::<StringArray>().cloned())
+    }
+
+    /// Engine node id of each row of a batch from the `entity_type` table,
+    /// derived from its primary keys.
+    pub(crate) fn batch_node_ids(
+        entity_type: &str,
+        batch: &RecordBatch,
+    ) -> Result<Vec<Option<String>>> {
+        let schema = batch.schema();
+        let key_columns: Vec<usize> = SCHEMA_REGISTRY
+            .entity(entity_type)
+            .map(|meta| {
+                meta.primary_keys
+                    .iter()
+                    .filter_map(|key| schema.index_of(key).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let keys = batch.project(&key_columns)?;
+        (0..keys.num_rows())
+            .map(|row| {
+                let row = Self::record_batch_row_to_map(&keys, row)?;
+                Ok(Self::stable_id_from_row(entity_type, &row))
+            })
+            .collect()
     }
 }
 
@@ -2240,6 +4431,7 @@ mod tests {
         )
         .unwrap();
 
+        let (opened_at, writes_before) = lake.write_version();
         lake.write_batches(
             table_name,
             vec![initial_batch.clone()],
@@ -2265,6 +4457,7 @@ mod tests {
         )
         .await
         .unwrap();
+        assert_eq!(lake.write_version(), (opened_at, writes_before + 2));
 
         let table_path = config.lake_path.join(table_name);
         assert!(table_path.exists(), "Table path should exist after writes");
@@ -2272,7 +4465,7 @@ mod tests {
             table_path.join("_delta_log").exists(),
             "Delta log directory should be created"
         );
-        let table_uri = lake.path_to_url(&table_path).unwrap();
+        let table_uri = lake.store().table_url(table_name).unwrap();
         let final_table = deltalake::open_table(table_uri).await.unwrap();
 
         let ctx = Lake::single_partition_session();
@@ -2316,6 +4509,35 @@ mod tests {
         assert_eq!(count_value, 1);
     }
 
+    #[tokio::test]
+    async fn table_versions_see_commits_from_other_handles() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path());
+        let lake = create_lake(&config).await;
+        let table_name = "silver/entities/projects";
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)])),
+            vec![Arc::new(Int32Array::from(vec![1]))],
+        )
+        .unwrap();
+        lake.write_batches(table_name, vec![batch.clone()], None)
+            .await
+            .unwrap();
+        let before = lake.all_table_versions().await.unwrap();
+        let digest = lake.tables_version().await.unwrap();
+
+        // A second process writes to the same lake without going through `lake`.
+        let other = DeltaTables::local(&config.lake_path).unwrap();
+        other
+            .write(table_name, vec![batch], WriteMode::Append)
+            .await
+            .unwrap();
+        let after = lake.all_table_versions().await.unwrap();
+        assert_eq!(after[table_name], before[table_name] + 1);
+        tokio::time::sleep(TABLES_VERSION_TTL).await;
+        assert_ne!(lake.tables_version().await.unwrap(), digest);
+    }
+
     #[tokio::test]
     async fn test_read_changes_since() {
         let dir = tempdir().unwrap();
@@ -2372,4 +4594,80 @@ mod tests {
         assert_eq!(changes_v1[0].1.len(), 1);
         assert_eq!(changes_v1[0].1[0].num_rows(), 1);
     }
+
+    #[tokio::test]
+    async fn test_foreign_table_joins_without_accepting_writes() {
+        let lakehouse_dir = tempdir().unwrap();
+        let lakehouse = create_lake(&StorageConfig::new(lakehouse_dir.path())).await;
+        let accounts = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("owner", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["sales", "support"])),
+            ],
+        )
+        .unwrap();
+        lakehouse
+            .write_batches("crm/accounts", vec![accounts.clone()], None)
+            .await
+            .unwrap();
+
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path());
+        let lake = create_lake(&config).await;
+        let nodes = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("name", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 3])),
+                Arc::new(StringArray::from(vec!["alpha", "gamma"])),
+            ],
+        )
+        .unwrap();
+        lake.write_batches("silver/entities/nodes", vec![nodes], None)
+            .await
+            .unwrap();
+
+        let uri = crate::foreign::parse_uri(
+            lakehouse_dir
+                .path()
+                .join("lake/crm/accounts")
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+        lake.attach_foreign_table("crm", uri).await.unwrap();
+
+        let rows = lake
+            .table_sql(
+                "silver/entities/nodes",
+                "SELECT n.name, c.owner FROM {{table}} n JOIN foreign_crm c ON n.id = c.id",
+            )
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["name"], JsonValue::from("alpha"));
+        assert_eq!(rows[0]["owner"], JsonValue::from("sales"));
+        assert_eq!(
+            lake.query_table("foreign/crm", None, None)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let err = lake
+            .write_batches("foreign/crm", vec![accounts], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StorageError::InvalidArg(_)));
+
+        assert!(lake.detach_foreign_table("crm"));
+        assert!(!lake.table_exists("foreign/crm").await.unwrap());
+    }
 }
@@ -0,0 +1,318 @@
+//! Where the lake's Delta tables are stored: the lake directory, or a bucket
+//! of S3, Google Cloud Storage or Azure Blob Storage.
+//!
+//! Tables are opened and written through the `object_store` backend of the
+//! lake's URI, and so are the log and data files that
+//! [`DeltaTables`](crate::table_format::DeltaTables) reads and rewrites
+//! itself, so a table behaves the same wherever it lives. Snapshots, archives,
+//! file checksums and DuckDB queries work on the files in the lake directory
+//! and are not available for a lake in a bucket.
+
+use crate::config::StorageConfig;
+use crate::errors::{Result, StorageError};
+use crate::table_format::delta;
+use deltalake::{DeltaTable, DeltaTableBuilder, ObjectStore, ObjectStoreError, Path};
+use futures::TryStreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Once};
+use url::Url;
+
+/// An object store to keep the Delta tables in instead of
+/// `StorageConfig::lake_path`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LakeStoreConfig {
+    /// `s3://<bucket>/<prefix>`, `gs://<bucket>/<prefix>` or
+    /// `az://<container>/<prefix>`, which need fstorage built with the `s3`,
+    /// `gcs` or `azure` feature.
+    pub uri: String,
+    /// Credentials and client settings of the backend, e.g.
+    /// `aws_access_key_id`, `aws_region`, `google_service_account` or
+    /// `azure_storage_account_name`. Those left out are read from the
+    /// backend's usual environment variables.
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+impl LakeStoreConfig {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            options: HashMap::new(),
+        }
+    }
+}
+
+/// The root of the lake and the object store serving the paths under it,
+/// which are lake paths such as `silver/entities/project/_delta_log`.
+pub struct LakeStore {
+    /// Ends with `/`.
+    root: Url,
+    /// The root as a directory, for a lake that is one.
+    local: Option<PathBuf>,
+    options: HashMap<String, String>,
+    objects: Arc<dyn ObjectStore>,
+}
+
+impl LakeStore {
+    /// The object store `config` names, or its lake directory when it names
+    /// none.
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        match &config.lake_store {
+            Some(store) => Self::remote(store),
+            None => Self::local(&config.lake_path),
+        }
+    }
+
+    /// The directory `path`, created if missing.
+    pub fn local(path: &std::path::Path) -> Result<Self> {
+        let root = delta::dir_url(path)?;
+        let local = root.to_file_path().ok();
+        Self::with_root(root, local, HashMap::new())
+    }
+
+    fn remote(config: &LakeStoreConfig) -> Result<Self> {
+        let root = Url::parse(&config.uri).map_err(|err| {
+            StorageError::Config(format!("invalid lake store URI '{}': {err}", config.uri))
+        })?;
+        let feature = match root.scheme() {
+            "s3" | "s3a" => "s3",
+            "gs" => "gcs",
+            "az" | "abfs" | "abfss" | "azure" => "azure",
+            scheme => {
+                return Err(StorageError::Config(format!(
+                    "lake store URI '{}' has scheme '{scheme}'; expected s3://, gs:// or az://",
+                    config.uri
+                )));
+            }
+        };
+        if !register_backend(feature) {
+            return Err(StorageError::Config(format!(
+                "a {}:// lake store needs fstorage built with the `{feature}` feature",
+                root.scheme()
+            )));
+        }
+        Self::with_root(root, None, config.options.clone())
+    }
+
+    fn with_root(
+        mut root: Url,
+        local: Option<PathBuf>,
+        options: HashMap<String, String>,
+    ) -> Result<Self> {
+        if !root.path().ends_with('/') {
+            let path = format!("{}/", root.path());
+            root.set_path(&path);
+        }
+        let objects = DeltaTableBuilder::from_uri(root.clone())?
+            .with_storage_options(options.clone())
+            .build()?
+            .object_store();
+        Ok(Self {
+            root,
+            local,
+            options,
+            objects,
+        })
+    }
+
+    pub fn root(&self) -> &Url {
+        &self.root
+    }
+
+    /// Where `path` is, for messages: a local path or a URL.
+    pub fn describe(&self, path: &str) -> String {
+        match &self.local {
+            Some(dir) => dir.join(path).display().to_string(),
+            None => format!("{}{path}", self.root),
+        }
+    }
+
+    /// URL of the table directory `table`. A local one is created if missing,
+    /// as Delta expects.
+    pub fn table_url(&self, table: &str) -> Result<Url> {
+        match &self.local {
+            Some(dir) => delta::dir_url(&dir.join(table)),
+            None => self
+                .root
+                .join(&format!("{}/", table.trim_matches('/')))
+                .map_err(|err| {
+                    StorageError::InvalidArg(format!("invalid table path '{table}': {err}"))
+                }),
+        }
+    }
+
+    /// Builder of the Delta table `table`, carrying the store's options.
+    pub fn table_builder(&self, table: &str) -> Result<DeltaTableBuilder> {
+        Ok(DeltaTableBuilder::from_uri(self.table_url(table)?)?
+            .with_storage_options(self.options.clone()))
+    }
+
+    /// Loads the Delta table `table`; `None` when it has no log yet.
+    pub async fn open_table(&self, table: &str) -> Result<Option<DeltaTable>> {
+        if !self
+            .has_prefix(&format!("{table}/{}", delta::DELTA_LOG_DIR))
+            .await?
+        {
+            return Ok(None);
+        }
+        match self.table_builder(table)?.load().await {
+            Ok(table) => Ok(Some(table)),
+            Err(deltalake::DeltaTableError::NotATable(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Whether any object is under the directory `prefix`.
+    pub async fn has_prefix(&self, prefix: &str) -> Result<bool> {
+        let mut objects = self.objects.list(Some(&Path::from(prefix)));
+        Ok(objects.try_next().await?.is_some())
+    }
+
+    /// Names of the objects and of the directories directly under `dir`,
+    /// the root when it is empty.
+    pub async fn list_dir(&self, dir: &str) -> Result<(Vec<String>, Vec<String>)> {
+        let prefix = (!dir.is_empty()).then(|| Path::from(dir));
+        let listing = self.objects.list_with_delimiter(prefix.as_ref()).await?;
+        let name = |path: &Path| path.filename().unwrap_or_default().to_string();
+        Ok((
+            listing
+                .objects
+                .iter()
+                .map(|object| name(&object.location))
+                .collect(),
+            listing.common_prefixes.iter().map(name).collect(),
+        ))
+    }
+
+    /// Contents of `path`; `None` when there is no such object.
+    pub async fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.objects.get(&Path::from(path)).await {
+            Ok(object) => Ok(Some(object.bytes().await?.to_vec())),
+            Err(ObjectStoreError::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Size in bytes of `path`; `None` when there is no such object.
+    pub async fn size(&self, path: &str) -> Result<Option<u64>> {
+        match self.objects.head(&Path::from(path)).await {
+            Ok(meta) => Ok(Some(meta.size)),
+            Err(ObjectStoreError::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Replaces `path` with `contents`. Readers see the old object or the new
+    /// one, never a partial write.
+    pub async fn write(&self, path: &str, contents: Vec<u8>) -> Result<()> {
+        self.objects.put(&Path::from(path), contents.into()).await?;
+        Ok(())
+    }
+
+    pub async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.objects
+            .rename(&Path::from(from), &Path::from(to))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<()> {
+        self.objects.delete(&Path::from(path)).await?;
+        Ok(())
+    }
+
+    /// Lake path of a data file, given as Delta reports the files of a table.
+    pub(crate) fn lake_path_of(&self, uri: &str) -> Option<String> {
+        let Some(dir) = &self.local else {
+            return uri.strip_prefix(self.root.as_str()).map(str::to_string);
+        };
+        let file = Url::parse(uri)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .unwrap_or_else(|| PathBuf::from(uri));
+        Some(file.strip_prefix(dir).ok()?.to_string_lossy().into_owned())
+    }
+}
+
+/// Registers the Delta handlers of the cloud backends this build has, once.
+/// Returns whether it has `feature`.
+fn register_backend(feature: &str) -> bool {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        #[cfg(feature = "s3")]
+        deltalake::aws::register_handlers(None);
+        #[cfg(feature = "gcs")]
+        deltalake::gcp::register_handlers(None);
+        #[cfg(feature = "azure")]
+        deltalake::azure::register_handlers(None);
+    });
+    match feature {
+        "s3" => cfg!(feature = "s3"),
+        "gcs" => cfg!(feature = "gcs"),
+        "azure" => cfg!(feature = "azure"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_store_reads_and_lists_lake_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LakeStore::local(dir.path()).unwrap();
+        assert!(store.root().as_str().ends_with('/'));
+        assert!(!store.has_prefix("silver").await.unwrap());
+
+        store
+            .write("silver/entities/person/part-0.parquet", b"rows".to_vec())
+            .await
+            .unwrap();
+        assert!(dir
+            .path()
+            .join("silver/entities/person/part-0.parquet")
+            .exists());
+        assert!(store.has_prefix("silver").await.unwrap());
+        assert_eq!(
+            store.list_dir("silver/entities").await.unwrap(),
+            (Vec::new(), vec!["person".to_string()])
+        );
+        assert_eq!(
+            store
+                .size("silver/entities/person/part-0.parquet")
+                .await
+                .unwrap(),
+            Some(4)
+        );
+        assert_eq!(store.read("missing").await.unwrap(), None);
+        assert!(store
+            .open_table("silver/entities/person")
+            .await
+            .unwrap()
+            .is_none());
+
+        let file = dir.path().join("silver/entities/person/part-0.parquet");
+        let uri = Url::from_file_path(&file).unwrap();
+        assert_eq!(
+            store.lake_path_of(uri.as_str()).as_deref(),
+            Some("silver/entities/person/part-0.parquet")
+        );
+    }
+
+    #[test]
+    fn remote_stores_need_a_known_scheme_and_their_feature() {
+        let err = LakeStore::remote(&LakeStoreConfig::new("ftp://host/lake"))
+            .err()
+            .expect("unknown scheme");
+        assert!(err.to_string().contains("expected s3://"), "{err}");
+        if !cfg!(feature = "s3") {
+            let err = LakeStore::remote(&LakeStoreConfig::new("s3://bucket/lake"))
+                .err()
+                .expect("no s3 backend");
+            assert!(err.to_string().contains("`s3` feature"), "{err}");
+        }
+    }
+}
@@ -1,31 +1,73 @@
+pub mod activity;
+pub mod archive;
 pub mod auto_fetchable;
+pub mod budget;
 pub mod catalog;
+pub mod checkpoint;
 pub mod config;
+pub mod consistency;
+pub mod costs;
+pub mod dataset;
 pub mod embedding;
+pub mod engine_map;
 pub mod errors;
 pub mod fetch;
+pub mod foreign;
+pub mod identity;
+pub mod ids;
+pub mod import;
+pub mod integrity;
 pub mod lake;
+pub mod lake_store;
+pub mod lint;
+pub mod maintenance;
+pub mod migrate;
 pub mod models;
+pub mod progress;
+pub mod quality;
+pub mod redaction;
+pub mod rerank;
 pub mod schema_registry;
 pub mod schemas;
+pub mod snapshot;
+pub mod sql_engine;
+pub mod structure;
 pub mod sync;
+pub mod table_format;
+pub mod throttle;
+pub mod transform;
 pub mod utils;
 
 use crate::catalog::Catalog;
 use crate::config::StorageConfig;
-use crate::embedding::{
-    EmbeddingProvider, FastEmbedProvider, NullEmbeddingProvider, OpenAIProvider,
-};
+use crate::costs::{approximate_tokens, PricingTable, UsageMeter};
+#[cfg(feature = "openai")]
+use crate::embedding::OpenAIProvider;
+use crate::embedding::{EmbeddingProvider, NullEmbeddingProvider};
+#[cfg(feature = "fastembed")]
+use crate::embedding::{FastEmbedProvider, OnnxProvider};
 use crate::errors::Result;
-use crate::fetch::{Fetcher, FetcherCapability};
+use crate::fetch::{Fetcher, FetcherCapability, ProbeReport};
+use crate::import::EmbeddingImport;
 use crate::lake::Lake;
 use crate::models::{
-    EntityIdentifier, EntityMetadata, HybridSearchHit, MultiEntitySearchHit, PathResult,
-    ReadinessReport, TableSummary, TextSearchHit, VectorSearchHit,
+    ActivityDay, ArchiveManifest, ComponentHealth, ConsistencyReport, DatasetManifest,
+    EmbeddingImportReport, EntityIdentifier, EntityMetadata, ForeignTable, GraphDiff, HealthReport,
+    HybridSearchHit, IdAuditReport, IdentityMatch, IdentityMatchStatus, IdentityResolutionReport,
+    LakeVerification, LogRepair, MaintenanceOptions, MaintenanceReport, MultiEntitySearchHit,
+    NodeDeletion, PathResult, PersonalDataErasure, QualityReport, ReadinessReport, SnapshotInfo,
+    StructuralEmbeddingOptions, StructuralEmbeddingReport, StructuralNeighbor, SyncRun,
+    TableSummary, TextSearchHit, VectorSearchHit,
 };
+use crate::redaction::Redactor;
+use crate::rerank::Reranker;
 use crate::sync::{DataSynchronizer, FStorageSynchronizer};
+use crate::throttle::{ProviderLimits, ThrottledEmbeddingProvider};
+use crate::transform::BatchTransform;
 use helix_db::helix_engine::traversal_core::{HelixGraphEngine, HelixGraphEngineOpts};
-use std::collections::HashMap;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 
 /// The main entry point for the `fstorage` library.
@@ -38,11 +80,38 @@ pub struct FStorage {
     pub engine: Arc<HelixGraphEngine>,
     pub synchronizer: Arc<FStorageSynchronizer>,
     embedding_provider: Arc<dyn EmbeddingProvider>,
+    /// Providers from `StorageConfig::embedding_models`, by model name.
+    embedding_models: HashMap<String, Arc<dyn EmbeddingProvider>>,
+    reranker: Option<Arc<dyn Reranker>>,
+    pricing: Arc<PricingTable>,
 }
 
+/// Hybrid candidates a reranked search scores, at least; more when the
+/// requested limit is larger.
+const RERANK_CANDIDATES: usize = 50;
+
+/// Score kept per edge walked from a search hit to its graph context.
+const GRAPH_EXPAND_DECAY: f32 = 0.5;
+
 impl FStorage {
     /// Creates a new instance of FStorage and initializes it.
     pub async fn new(config: StorageConfig) -> Result<Self> {
+        Self::open(config, None).await
+    }
+
+    /// Opens the store embedding with `embedding_provider`, instead of the one
+    /// picked from `StorageConfig::onnx` and the environment.
+    pub async fn new_with_embedding_provider(
+        config: StorageConfig,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> Result<Self> {
+        Self::open(config, Some(embedding_provider)).await
+    }
+
+    async fn open(
+        config: StorageConfig,
+        embedding_provider: Option<Arc<dyn EmbeddingProvider>>,
+    ) -> Result<Self> {
         // Load environment variables
         dotenvy::dotenv().ok();
 
@@ -50,7 +119,7 @@ impl FStorage {
         tokio::fs::create_dir_all(&config.engine_path).await?;
 
         let catalog = Arc::new(Catalog::new(&config)?);
-        catalog.initialize_schema()?;
+        let created = catalog.is_empty()?;
 
         let engine_path = config
             .engine_path
@@ -62,41 +131,66 @@ impl FStorage {
             ..Default::default()
         };
         let engine = Arc::new(HelixGraphEngine::new(engine_opts)?);
+        engine_map::configure(&engine.storage.graph_env, &config.engine)?;
+
+        // Older layouts are upgraded before the current schema is laid over them.
+        if created {
+            catalog.initialize_schema()?;
+            catalog.set_layout_version(migrate::LAYOUT_VERSION)?;
+        } else {
+            migrate::run(&config, &catalog, &engine)?;
+            catalog.initialize_schema()?;
+        }
 
         let lake = Arc::new(Lake::new(config.clone(), Arc::clone(&engine)).await?);
+        lake.engine_map().on_commit({
+            let catalog = Arc::clone(&catalog);
+            move || catalog.bump_graph_version().map(drop)
+        });
+        for table in catalog.list_foreign_tables()? {
+            match foreign::parse_uri(&table.uri) {
+                Ok(uri) => lake.register_foreign_table(&table.name, uri),
+                Err(err) => log::warn!("skipping foreign table '{}': {}", table.name, err),
+            }
+        }
 
-        // Initialize the embedding provider
-        let embedding_model = engine
-            .storage
-            .storage_config
-            .embedding_model
-            .clone()
-            .unwrap_or_else(|| "text-embedding-ada-002".to_string());
-        let embedding_provider: Arc<dyn EmbeddingProvider> = match std::env::var("OPENAI_API_KEY") {
-            Ok(key) => Arc::new(OpenAIProvider::new(embedding_model, key)),
-            Err(_) => match FastEmbedProvider::new_default() {
-                Ok(provider) => {
-                    log::info!(
-                        "FASTEMBED backend initialized (no OPENAI_API_KEY present); vectors will be generated locally."
-                    );
-                    Arc::new(provider)
-                }
-                Err(err) => {
-                    log::warn!(
-                        "FASTEMBED initialization failed ({}); falling back to NullEmbeddingProvider. Vector embeddings will be empty.",
-                        err
-                    );
-                    Arc::new(NullEmbeddingProvider)
-                }
-            },
+        let embedding_provider = match (embedding_provider, &config.onnx) {
+            (Some(provider), _) => provider,
+            #[cfg(feature = "fastembed")]
+            (None, Some(onnx)) => Arc::new(OnnxProvider::new(onnx.clone())?),
+            #[cfg(not(feature = "fastembed"))]
+            (None, Some(_)) => {
+                return Err(crate::errors::StorageError::Config(
+                    "ONNX embedding models need fstorage built with the `fastembed` feature".into(),
+                ));
+            }
+            (None, None) => Self::default_embedding_provider(&engine),
         };
+        let embedding_provider = Self::throttled(&config, embedding_provider);
+        let mut embedding_models = HashMap::new();
+        for model in &config.embedding_models {
+            let provider = Self::throttled(&config, model.build()?);
+            embedding_models.insert(model.model().to_string(), provider);
+        }
 
-        let synchronizer = Arc::new(FStorageSynchronizer::new(
-            Arc::clone(&catalog),
-            Arc::clone(&lake),
-            Arc::clone(&engine),
-            embedding_provider.clone(),
-        ));
+        let reranker = config
+            .reranker
+            .as_ref()
+            .map(|reranker| reranker.build())
+            .transpose()?;
+
+        let pricing = Arc::new(PricingTable::with_overrides(&config.token_pricing));
+        let redactor = Arc::new(Redactor::new(&config.redaction)?);
+        let synchronizer = Arc::new(
+            FStorageSynchronizer::new(
+                Arc::clone(&catalog),
+                Arc::clone(&lake),
+                Arc::clone(&engine),
+                embedding_provider.clone(),
+            )
+            .with_pricing(Arc::clone(&pricing))
+            .with_redactor(redactor),
+        );
 
         Ok(Self {
             config,
@@ -105,9 +199,71 @@ impl FStorage {
             engine,
             synchronizer,
             embedding_provider,
+            embedding_models,
+            reranker,
+            pricing,
         })
     }
 
+    /// Wraps `provider` in the rate limits configured for it, if any.
+    fn throttled(
+        config: &StorageConfig,
+        provider: Arc<dyn EmbeddingProvider>,
+    ) -> Arc<dyn EmbeddingProvider> {
+        match ProviderLimits::lookup(
+            &config.embedding_limits,
+            provider.provider_name(),
+            provider.model_name(),
+        ) {
+            Some(limits) => Arc::new(ThrottledEmbeddingProvider::new(provider, limits)),
+            None => provider,
+        }
+    }
+
+    /// Replaces the reranker built from `StorageConfig::reranker`.
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    /// Used when no provider is passed and `StorageConfig::onnx` is unset: OpenAI
+    /// when `OPENAI_API_KEY` is set, local FastEmbed otherwise, and empty vectors
+    /// when neither is available or compiled in.
+    fn default_embedding_provider(
+        #[cfg_attr(not(feature = "openai"), allow(unused_variables))] engine: &HelixGraphEngine,
+    ) -> Arc<dyn EmbeddingProvider> {
+        #[cfg(feature = "openai")]
+        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+            let embedding_model = engine
+                .storage
+                .storage_config
+                .embedding_model
+                .clone()
+                .unwrap_or_else(|| "text-embedding-ada-002".to_string());
+            return Arc::new(OpenAIProvider::new(embedding_model, key));
+        }
+        #[cfg(feature = "fastembed")]
+        match FastEmbedProvider::new_default() {
+            Ok(provider) => {
+                log::info!(
+                    "FASTEMBED backend initialized (no OPENAI_API_KEY present); vectors will be generated locally."
+                );
+                return Arc::new(provider);
+            }
+            Err(err) => {
+                log::warn!(
+                    "FASTEMBED initialization failed ({}); falling back to NullEmbeddingProvider. Vector embeddings will be empty.",
+                    err
+                );
+            }
+        }
+        #[cfg(not(feature = "fastembed"))]
+        log::warn!(
+            "No embedding provider available (fstorage built without `fastembed`); falling back to NullEmbeddingProvider. Vector embeddings will be empty."
+        );
+        Arc::new(NullEmbeddingProvider)
+    }
+
     /// Registers a fetcher with the synchronizer.
     ///
     /// This method allows the application's entry point (e.g., `fagent`) to
@@ -116,6 +272,26 @@ impl FStorage {
         self.synchronizer.register_fetcher(fetcher);
     }
 
+    /// Adds a transform applied to fetched batches before they are stored, see
+    /// [`transform`](crate::transform).
+    pub fn register_transform(&self, transform: Arc<dyn BatchTransform>) {
+        self.synchronizer.register_transform(transform);
+    }
+
+    /// Checks a sync request against the policy of the fetcher it names.
+    pub fn admit_sync(&self, fetcher_name: &str, params: &serde_json::Value) -> Result<()> {
+        self.synchronizer.admit_sync(fetcher_name, params)
+    }
+
+    /// Probes the source of a sync request through the fetcher it names.
+    pub async fn probe_sync(
+        &self,
+        fetcher_name: &str,
+        params: serde_json::Value,
+    ) -> Result<ProbeReport> {
+        self.synchronizer.probe_sync(fetcher_name, params).await
+    }
+
     /// Lists the capabilities for all registered fetchers.
     pub fn list_fetchers_capability(&self) -> Vec<FetcherCapability> {
         self.synchronizer.list_fetcher_capabilities()
@@ -138,6 +314,12 @@ impl FStorage {
         Ok(entities)
     }
 
+    /// Number of known entities per category (`node`, `edge`, `vector`), read
+    /// from the catalog without listing them.
+    pub fn count_known_entities(&self) -> Result<BTreeMap<String, u64>> {
+        self.catalog.count_ingestion_offsets_by_category()
+    }
+
     /// Lists Delta tables under a given prefix, returning their schema summaries.
     pub async fn list_tables(&self, prefix: &str) -> Result<Vec<TableSummary>> {
         self.lake.list_tables(prefix).await
@@ -181,54 +363,226 @@ impl FStorage {
         if trimmed.is_empty() {
             return Ok(Vec::new());
         }
-        let embedding = self
-            .embedding_provider
-            .embed(vec![trimmed.to_string()])
-            .await?;
-        let vector = embedding.into_iter().next().unwrap_or_default();
+        let vector = self.embed_query(entity_type, trimmed, None).await?;
         self.search_vectors(entity_type, &vector, limit).await
     }
 
+    /// Hybrid search over one entity type. `embedding_model` picks the
+    /// provider the query is embedded with, see [`Self::search_hybrid_multi`].
     pub async fn search_hybrid(
         &self,
         entity_type: &str,
         query_text: &str,
         alpha: f32,
         limit: usize,
+        embedding_model: Option<&str>,
     ) -> Result<Vec<HybridSearchHit>> {
         let trimmed = query_text.trim();
         if trimmed.is_empty() {
             return Ok(Vec::new());
         }
-        let embedding = self
-            .embedding_provider
-            .embed(vec![trimmed.to_string()])
+        let vector = self
+            .embed_query(entity_type, trimmed, embedding_model)
             .await?;
-        let vector = embedding.into_iter().next().unwrap_or_default();
         self.lake
             .search_hybrid(entity_type, trimmed, &vector, alpha, limit)
             .await
     }
 
+    /// Hybrid search across `entity_types`. With `rerank`, the top
+    /// [`RERANK_CANDIDATES`] hits are rescored by the configured cross-encoder
+    /// and the best `limit` of them returned in its order.
+    ///
+    /// The query is embedded with the default provider unless
+    /// `embedding_model` names another one; the stored vectors must come from
+    /// the same model for the similarities to mean anything.
     pub async fn search_hybrid_multi(
         &self,
         entity_types: &[String],
         query_text: &str,
         alpha: f32,
         limit: usize,
+        rerank: bool,
+        embedding_model: Option<&str>,
     ) -> Result<Vec<MultiEntitySearchHit>> {
         let trimmed = query_text.trim();
         if entity_types.is_empty() || trimmed.is_empty() {
             return Ok(Vec::new());
         }
-        let embedding = self
-            .embedding_provider
-            .embed(vec![trimmed.to_string()])
+        let reranker = match (rerank, &self.reranker) {
+            (false, _) => None,
+            (true, Some(reranker)) => Some(Arc::clone(reranker)),
+            (true, None) => {
+                return Err(crate::errors::StorageError::InvalidArg(
+                    "reranking requested but no reranker is configured".into(),
+                ))
+            }
+        };
+        let candidates = match reranker {
+            Some(_) => limit.max(RERANK_CANDIDATES),
+            None => limit,
+        };
+        let vector = self
+            .embed_query(&entity_types.join(","), trimmed, embedding_model)
             .await?;
-        let vector = embedding.into_iter().next().unwrap_or_default();
-        self.lake
-            .search_hybrid_multi(entity_types, trimmed, &vector, alpha, limit)
-            .await
+        let mut hits = self
+            .lake
+            .search_hybrid_multi(entity_types, trimmed, &vector, alpha, candidates)
+            .await?;
+        let Some(reranker) = reranker else {
+            return Ok(hits);
+        };
+        if hits.is_empty() {
+            return Ok(hits);
+        }
+
+        let documents: Vec<String> = hits.iter().map(Self::rerank_document).collect();
+        // A cross-encoder reads the query once per document.
+        let estimate = approximate_tokens(&documents)
+            + approximate_tokens(&[trimmed.to_string()]) * documents.len() as u64;
+        let meter = UsageMeter::for_reranker(reranker.as_ref());
+        let reranked = reranker.rerank_with_usage(trimmed, documents).await;
+        meter.record_tokens(match &reranked {
+            Ok((_, Some(reported))) => *reported,
+            _ => estimate,
+        });
+        self.record_search_cost(&entity_types.join(","), &meter);
+        let scores = reranked?.0;
+        for (hit, score) in hits.iter_mut().zip(scores) {
+            hit.rerank_score = Some(score);
+        }
+        hits.sort_by(|a, b| {
+            b.rerank_score
+                .partial_cmp(&a.rerank_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        hits.truncate(limit.max(1));
+        Ok(hits)
+    }
+
+    /// `hits` followed by up to `limit` nodes of their graph context: callers
+    /// and callees, containing files and the functions or classes next to
+    /// them, scored lower the further they are from a hit.
+    pub async fn expand_search_hits(
+        &self,
+        mut hits: Vec<MultiEntitySearchHit>,
+        limit: usize,
+    ) -> Result<Vec<MultiEntitySearchHit>> {
+        let context = self
+            .lake
+            .expand_hits(&hits, GRAPH_EXPAND_DECAY, limit)
+            .await?;
+        hits.extend(context);
+        Ok(hits)
+    }
+
+    /// Text the reranker reads for a hit: a vector's stored text, or a node's
+    /// string properties one per line.
+    fn rerank_document(hit: &MultiEntitySearchHit) -> String {
+        let properties = |map: &HashMap<String, JsonValue>| {
+            map.get("properties")
+                .and_then(JsonValue::as_object)
+                .cloned()
+                .unwrap_or_default()
+        };
+        if let Some(text) = hit.vector.as_ref().and_then(|vector| {
+            properties(vector)
+                .get("text")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+        }) {
+            return text;
+        }
+        let lines: Vec<String> = hit
+            .node
+            .as_ref()
+            .map(properties)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(key, value)| {
+                value
+                    .as_str()
+                    .filter(|value| !value.trim().is_empty())
+                    .map(|value| format!("{key}: {value}"))
+            })
+            .collect();
+        if lines.is_empty() {
+            hit.summary.clone().unwrap_or_default()
+        } else {
+            lines.join("\n")
+        }
+    }
+
+    /// Embeds a search query and books the provider usage under the `search` scope.
+    async fn embed_query(
+        &self,
+        scope_ref: &str,
+        query_text: &str,
+        embedding_model: Option<&str>,
+    ) -> Result<Vec<f64>> {
+        let provider = self.query_embedding_provider(embedding_model)?;
+        let texts = vec![query_text.to_string()];
+        let meter = UsageMeter::for_provider(provider.as_ref());
+        let estimate = approximate_tokens(&texts);
+        let embedded = provider.embed_with_usage(texts).await;
+        meter.record_tokens(match &embedded {
+            Ok((_, Some(reported))) => *reported,
+            _ => estimate,
+        });
+        self.record_search_cost(scope_ref, &meter);
+        Ok(embedded?.0.into_iter().next().unwrap_or_default())
+    }
+
+    /// Books `meter`'s usage under the `search` scope.
+    fn record_search_cost(&self, scope_ref: &str, meter: &UsageMeter) {
+        let summary = meter.summary(&self.pricing);
+        if let Err(err) = self
+            .catalog
+            .insert_cost_record("search", Some(scope_ref), &summary)
+        {
+            log::warn!("Failed to record {} search cost: {}", summary.provider, err);
+        }
+    }
+
+    /// The provider named `embedding_model`: the default one when it matches
+    /// its model name or no name is given, otherwise one of
+    /// `StorageConfig::embedding_models`.
+    fn query_embedding_provider(
+        &self,
+        embedding_model: Option<&str>,
+    ) -> Result<Arc<dyn EmbeddingProvider>> {
+        let Some(name) = embedding_model
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        else {
+            return Ok(Arc::clone(&self.embedding_provider));
+        };
+        if self.embedding_provider.model_name() == Some(name) {
+            return Ok(Arc::clone(&self.embedding_provider));
+        }
+        self.embedding_models.get(name).cloned().ok_or_else(|| {
+            crate::errors::StorageError::InvalidArg(format!(
+                "unknown embedding model '{name}', expected one of: {}",
+                self.embedding_model_names().join(", ")
+            ))
+        })
+    }
+
+    /// Model names searches can pass as `embedding_model`, the default
+    /// provider's first when it has one.
+    pub fn embedding_model_names(&self) -> Vec<String> {
+        let mut extra: Vec<String> = self.embedding_models.keys().cloned().collect();
+        extra.sort();
+        self.embedding_provider
+            .model_name()
+            .map(str::to_string)
+            .into_iter()
+            .chain(extra)
+            .collect()
+    }
+
+    pub fn pricing(&self) -> Arc<PricingTable> {
+        Arc::clone(&self.pricing)
     }
 
     pub async fn embed_texts(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
@@ -239,6 +593,364 @@ impl FStorage {
         Arc::clone(&self.embedding_provider)
     }
 
+    /// Writes vectors embedded by an external pipeline, after checking them
+    /// against the schema registry and the vectors already stored for the type.
+    pub async fn import_embeddings(
+        &self,
+        mut import: EmbeddingImport,
+    ) -> Result<EmbeddingImportReport> {
+        let meta = import.validate()?;
+        import.check_stored(&self.lake, meta).await?;
+        let report = EmbeddingImportReport {
+            entity_type: meta.entity_type.to_string(),
+            model: import.model.clone(),
+            dimension: import.dimension,
+            rows: import.rows.len(),
+        };
+        if report.rows > 0 {
+            self.synchronizer
+                .process_graph_data(import.into_graph()?)
+                .await?;
+        }
+        Ok(report)
+    }
+
+    /// Writes the project at `project_url`, with what belongs to it, to `out` as
+    /// a Parquet dataset that [`FStorage::import_dataset`] reads back.
+    pub async fn export_project(&self, project_url: &str, out: &Path) -> Result<DatasetManifest> {
+        dataset::export_project(&self.lake, project_url, out).await
+    }
+
+    /// Writes the dataset in `dir` through the sync pipeline, file by file in
+    /// manifest order, and returns its manifest.
+    pub async fn import_dataset(&self, dir: &Path) -> Result<DatasetManifest> {
+        let manifest = dataset::read_manifest(dir)?;
+        for file in &manifest.files {
+            for graph in dataset::read_file(dir, file)? {
+                self.synchronizer.process_graph_data(graph?).await?;
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Checks that the catalog answers queries and the engine opens read
+    /// transactions. With `check_writes`, also proves the lake directory accepts
+    /// new files, which syncs depend on.
+    pub async fn health(&self, check_writes: bool) -> HealthReport {
+        let mut components = vec![
+            Self::probe("catalog", async { self.catalog.ping() }).await,
+            Self::probe("engine", self.probe_engine_read()).await,
+        ];
+        if check_writes {
+            components.push(Self::probe("lake", self.probe_lake_write()).await);
+        }
+        HealthReport {
+            ok: components.iter().all(|component| component.ok),
+            components,
+        }
+    }
+
+    /// Id of the engine's last committed write transaction. It only moves when
+    /// the engine changes, so equal values mean an unchanged engine.
+    pub async fn engine_version(&self) -> Result<u64> {
+        self.lake
+            .read_graph(|storage, _| Ok(storage.graph_env.info().last_txn_id as u64))
+            .await
+    }
+
+    /// Counter bumped by every engine commit that changes the graph, whether
+    /// from a sync, a deletion or an erasure. It is kept in the catalog, so it
+    /// keeps increasing across restarts.
+    pub fn graph_version(&self) -> Result<u64> {
+        self.catalog.get_graph_version()
+    }
+
+    /// Deletes a node with its edges, rows and vectors, see [`Lake::delete_node`].
+    /// A bookmark on the node is removed with it.
+    pub async fn delete_node(&self, id: &str) -> Result<Option<NodeDeletion>> {
+        let deletion = self.lake.delete_node(id).await?;
+        if let Some(deletion) = &deletion {
+            self.catalog.delete_bookmark(&deletion.node_id)?;
+        }
+        Ok(deletion)
+    }
+
+    /// Erases what is stored about the developer `login`, see
+    /// [`Lake::erase_personal_data`], together with the sync checkpoints that
+    /// hold it, and does the same in every snapshot. Syncs are held back
+    /// meanwhile so none writes the data back halfway through. Snapshots must
+    /// not be open elsewhere in the process.
+    pub async fn erase_personal_data(&self, login: &str) -> Result<PersonalDataErasure> {
+        let _paused = self.synchronizer.pause_writes().await;
+        let mut erasure = self.erase_stored_personal_data(login).await?;
+        for info in self.list_snapshots()? {
+            let store = self.open_snapshot(&info.name).await?;
+            let erased = store.erase_stored_personal_data(login).await?;
+            drop(store);
+            if !erased.is_empty() {
+                snapshot::refresh_versions(&self.config, &info.name)?;
+                erasure.snapshots.push(info.name);
+            }
+        }
+        Ok(erasure)
+    }
+
+    async fn erase_stored_personal_data(&self, login: &str) -> Result<PersonalDataErasure> {
+        let mut erasure = self.lake.erase_personal_data(login).await?;
+        // Checkpoints keep fetched pages as JSON, where the login is a string.
+        let needle = JsonValue::String(erasure.login.clone()).to_string();
+        erasure.checkpoint_entries_removed = self.catalog.clear_checkpoints_containing(&needle)?;
+        Ok(erasure)
+    }
+
+    /// Sync runs that persisted data, most recent first.
+    pub fn list_sync_runs(&self, limit: usize) -> Result<Vec<SyncRun>> {
+        self.catalog.list_sync_runs(limit)
+    }
+
+    /// What sync run `to_run` changed relative to `from_run`, see
+    /// [`Lake::graph_diff`].
+    pub async fn graph_diff(&self, from_run: i64, to_run: i64, limit: usize) -> Result<GraphDiff> {
+        let load = |run_id: i64| {
+            self.catalog.get_sync_run(run_id)?.ok_or_else(|| {
+                crate::errors::StorageError::NotFound(format!("sync run {run_id} not found"))
+            })
+        };
+        let from = load(from_run)?;
+        let to = load(to_run)?;
+        self.lake.graph_diff(&from, &to, limit).await
+    }
+
+    /// Measures every entity, edge and vector table and stores the result, see
+    /// [`quality::run`].
+    pub async fn run_quality_report(&self) -> Result<QualityReport> {
+        quality::run(&self.lake).await
+    }
+
+    /// Looks for node ids shared by distinct primary-key sets, see
+    /// [`ids::audit`].
+    pub async fn audit_ids(&self) -> Result<IdAuditReport> {
+        ids::audit(&self.lake).await
+    }
+
+    /// Links developers to the git identities that look like the same person,
+    /// see [`identity::resolve`].
+    pub async fn resolve_identities(&self) -> Result<IdentityResolutionReport> {
+        identity::resolve(&self.catalog, &self.lake, &self.synchronizer).await
+    }
+
+    /// The identity matches in the graph and the ones reviewers rejected.
+    pub async fn identity_matches(&self) -> Result<Vec<IdentityMatch>> {
+        identity::list(&self.catalog, &self.lake).await
+    }
+
+    /// Confirms or rejects the match between a developer and a git identity,
+    /// see [`identity::decide`].
+    pub async fn decide_identity_match(
+        &self,
+        developer_id: &str,
+        identity_id: &str,
+        status: IdentityMatchStatus,
+    ) -> Result<IdentityMatch> {
+        identity::decide(
+            &self.catalog,
+            &self.lake,
+            &self.synchronizer,
+            developer_id,
+            identity_id,
+            status,
+        )
+        .await
+    }
+
+    /// Learns node2vec embeddings over the graph and replaces the stored
+    /// ones, see [`structure::train`].
+    pub async fn train_structural_embeddings(
+        &self,
+        options: &StructuralEmbeddingOptions,
+    ) -> Result<StructuralEmbeddingReport> {
+        structure::train(&self.lake, &self.synchronizer, options).await
+    }
+
+    /// Nodes of the same type whose structural embeddings are nearest to
+    /// `node_id`'s, as of the last [`FStorage::train_structural_embeddings`].
+    pub async fn structurally_similar(
+        &self,
+        node_id: &str,
+        limit: usize,
+    ) -> Result<Vec<StructuralNeighbor>> {
+        structure::similar(&self.lake, node_id, limit).await
+    }
+
+    /// The last stored data-quality report, if the job ever ran.
+    pub async fn latest_quality_report(&self) -> Result<Option<QualityReport>> {
+        quality::latest(&self.lake).await
+    }
+
+    /// Checks every lake data file against its recorded checksum and decodes
+    /// it, see [`integrity::verify`].
+    pub async fn verify_lake(&self) -> Result<LakeVerification> {
+        integrity::verify(&self.config, &self.catalog).await
+    }
+
+    /// Cross-checks the catalog, the lake tables and the engine, and with
+    /// `repair` fixes what it can, see [`consistency::check`].
+    pub async fn check_consistency(&self, repair: bool) -> Result<ConsistencyReport> {
+        consistency::check(&self.catalog, &self.lake, &self.synchronizer, repair).await
+    }
+
+    /// Compacts and vacuums the lake tables and measures the engine, see
+    /// [`maintenance::run`].
+    pub async fn run_maintenance(&self, options: &MaintenanceOptions) -> Result<MaintenanceReport> {
+        maintenance::run(&self.catalog, &self.lake, options).await
+    }
+
+    /// Repairs the Delta log of `table` after a `CorruptLog` error, see
+    /// [`lake::Lake::repair_table_log`].
+    pub async fn repair_table(&self, table: &str) -> Result<LogRepair> {
+        self.lake.repair_table_log(table).await
+    }
+
+    /// Recounts daily commits, issues and pull requests per project, see
+    /// [`activity::materialize`].
+    pub async fn materialize_activity(&self) -> Result<usize> {
+        activity::materialize(&self.lake).await
+    }
+
+    /// Daily activity of `project_url` over the `days` UTC days ending today,
+    /// as of the last [`FStorage::materialize_activity`].
+    pub async fn project_activity(&self, project_url: &str, days: u32) -> Result<Vec<ActivityDay>> {
+        let until = chrono::Utc::now().date_naive();
+        let since = until - chrono::Duration::days(i64::from(days.max(1)) - 1);
+        activity::timeline(&self.lake, project_url, since, until).await
+    }
+
+    /// Attaches the Delta table at `uri` read-only as the foreign table `name`,
+    /// see [`foreign`]. The table must open now; the attachment is kept in the
+    /// catalog and restored when the storage is reopened.
+    pub async fn attach_foreign_table(&self, name: &str, uri: &str) -> Result<ForeignTable> {
+        foreign::validate_name(name)?;
+        let url = foreign::parse_uri(uri)?;
+        self.lake.attach_foreign_table(name, url.clone()).await?;
+        self.catalog.put_foreign_table(name, url.as_str())?;
+        self.catalog.get_foreign_table(name)?.ok_or_else(|| {
+            crate::errors::StorageError::NotFound(format!("foreign table '{name}' not found"))
+        })
+    }
+
+    /// Returns whether `name` was attached.
+    pub fn detach_foreign_table(&self, name: &str) -> Result<bool> {
+        self.lake.detach_foreign_table(name);
+        self.catalog.delete_foreign_table(name)
+    }
+
+    /// Opaque token naming the current state of both the graph and the lake
+    /// tables, taken from the engine's last transaction and the tables'
+    /// versions. It differs after a write from any process sharing the store
+    /// (within [`lake::TABLES_VERSION_TTL`] for lake commits made elsewhere),
+    /// so it can be used as a cache validator for read results.
+    pub async fn data_version(&self) -> Result<String> {
+        let tables = self.lake.tables_version().await?;
+        Ok(format!("{tables}.{}", self.engine_version().await?))
+    }
+
+    async fn probe_engine_read(&self) -> Result<()> {
+        self.lake.read_graph(|_, _| Ok(())).await
+    }
+
+    async fn probe_lake_write(&self) -> Result<()> {
+        let probe = format!(".health-{}", uuid::Uuid::new_v4());
+        let store = self.lake.store();
+        store.write(&probe, b"ok".to_vec()).await?;
+        store.delete(&probe).await
+    }
+
+    async fn probe(
+        component: &str,
+        check: impl std::future::Future<Output = Result<()>>,
+    ) -> ComponentHealth {
+        let started = std::time::Instant::now();
+        let outcome = check.await;
+        ComponentHealth {
+            component: component.to_string(),
+            ok: outcome.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: outcome.err().map(|err| err.to_string()),
+        }
+    }
+
+    /// Freezes the current catalog, lake and engine as the snapshot `name`.
+    ///
+    /// Syncs are held back until the copy is done, so the three parts agree.
+    pub async fn create_snapshot(
+        &self,
+        name: &str,
+        description: Option<String>,
+    ) -> Result<SnapshotInfo> {
+        snapshot::validate_name(name)?;
+        if !self.config.local_delta_lake() {
+            // A snapshot copies the lake directory, so the tables must be Delta
+            // tables kept there.
+            return Err(crate::errors::StorageError::InvalidArg(
+                "snapshots are only supported for Delta tables in the lake directory".into(),
+            ));
+        }
+        let _paused = self.synchronizer.pause_writes().await;
+        let config = self.config.clone();
+        let catalog = Arc::clone(&self.catalog);
+        let engine = Arc::clone(&self.engine);
+        let engine_map = Arc::clone(self.lake.engine_map());
+        let name = name.to_string();
+        tokio::task::spawn_blocking(move || {
+            let _map = engine_map.hold();
+            snapshot::create(&config, &catalog, &engine, &name, description)
+        })
+        .await
+        .map_err(|err| crate::errors::StorageError::Other(err.into()))?
+    }
+
+    /// Writes the catalog, lake and engine to the tar archive `out`, to be
+    /// restored elsewhere with [`archive::import`]. Syncs are held back until
+    /// the copy is done, as for a snapshot.
+    pub async fn export_archive(&self, out: &Path) -> Result<ArchiveManifest> {
+        if !self.config.local_delta_lake() {
+            return Err(crate::errors::StorageError::InvalidArg(
+                "archives are only supported for Delta tables in the lake directory".into(),
+            ));
+        }
+        let _paused = self.synchronizer.pause_writes().await;
+        let config = self.config.clone();
+        let catalog = Arc::clone(&self.catalog);
+        let engine = Arc::clone(&self.engine);
+        let engine_map = Arc::clone(self.lake.engine_map());
+        let out = out.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let _map = engine_map.hold();
+            archive::export(&config, &catalog, &engine, &out)
+        })
+        .await
+        .map_err(|err| crate::errors::StorageError::Other(err.into()))?
+    }
+
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        snapshot::list(&self.config)
+    }
+
+    /// Opens the snapshot `name` as a store of its own, sharing this store's
+    /// embedding providers and reranker. No fetchers are registered on it; callers are expected
+    /// to use it for reads only so the snapshot stays frozen.
+    pub async fn open_snapshot(&self, name: &str) -> Result<FStorage> {
+        let dir = snapshot::snapshot_dir(&self.config, name)?;
+        let mut config = StorageConfig::new(dir);
+        config.snapshot_path = None;
+        config.token_pricing = self.config.token_pricing.clone();
+        config.redaction = self.config.redaction.clone();
+        let mut storage = Self::open(config, Some(Arc::clone(&self.embedding_provider))).await?;
+        storage.embedding_models = self.embedding_models.clone();
+        storage.reranker = self.reranker.clone();
+        Ok(storage)
+    }
+
     pub async fn shortest_path(
         &self,
         from_id: &str,
@@ -0,0 +1,220 @@
+//! Validation of fetched batches against the schema registry.
+//!
+//! Rows the graph engine cannot key (a null primary key, an id or edge
+//! endpoint that is not a UUID) used to be written to the lake and then dropped
+//! one by one while ingesting into the engine, leaving only a log line. Every
+//! batch is now linted before it is written: such rows are removed from both
+//! writes, and each violation is counted per entity type in the sync's
+//! [`LintReport`]. Rows that are merely suspicious, like a timestamp at the Unix
+//! epoch or a vector without an embedding, are counted but kept.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::EntityCategory;
+use crate::models::LintReport;
+use crate::schema_registry::SCHEMA_REGISTRY;
+use deltalake::arrow::array::{Array, BooleanArray, StringArray, TimestampMicrosecondArray};
+use deltalake::arrow::compute::filter_record_batch;
+use deltalake::arrow::datatypes::{DataType, TimeUnit};
+use deltalake::arrow::record_batch::RecordBatch;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Timestamps further ahead than this are reported as out of range.
+const MAX_CLOCK_SKEW_MICROS: i64 = 86_400 * 1_000_000;
+
+const MISSING_FIELD: &str = "missing_field";
+const NULL_PRIMARY_KEY: &str = "null_primary_key";
+const INVALID_ID: &str = "invalid_id";
+const MISSING_ENDPOINT: &str = "missing_endpoint";
+const INVALID_ENDPOINT: &str = "invalid_endpoint";
+const MISSING_EMBEDDING: &str = "missing_embedding";
+const TIMESTAMP_OUT_OF_RANGE: &str = "timestamp_out_of_range";
+
+/// Checks `batch` and returns it without the rows that cannot be ingested.
+/// Violations are recorded in `report` as `<rule>:<column>`.
+///
+/// Only node primary keys must be present: edge and vector ids are derived
+/// during ingestion when a fetcher leaves them empty.
+pub fn lint_batch(
+    entity_type: &str,
+    category: EntityCategory,
+    primary_keys: &[&str],
+    batch: RecordBatch,
+    report: &mut LintReport,
+) -> Result<RecordBatch> {
+    let rows = batch.num_rows();
+    let mut violations: BTreeMap<String, usize> = BTreeMap::new();
+    let mut keep = vec![true; rows];
+    let mut check = |rule: &str, column: &str, failed: &[bool], rejects: bool| {
+        let count = failed.iter().filter(|failed| **failed).count();
+        if count == 0 {
+            return;
+        }
+        *violations.entry(format!("{rule}:{column}")).or_default() += count;
+        if rejects {
+            for (keep, failed) in keep.iter_mut().zip(failed) {
+                *keep &= !failed;
+            }
+        }
+    };
+
+    if let Some(meta) = SCHEMA_REGISTRY.entity(entity_type) {
+        for &field in meta.fields {
+            if batch.column_by_name(field).is_none() {
+                check(MISSING_FIELD, field, &vec![true; rows], false);
+            }
+        }
+    }
+    // Vector ids only name lake rows; the engine assigns its own.
+    let engine_ids =
+        string_column(&batch, "id").filter(|_| !matches!(category, EntityCategory::Vector));
+    if let Some(ids) = engine_ids {
+        let invalid: Vec<bool> = (0..rows)
+            .map(|row| !ids.is_null(row) && Uuid::parse_str(ids.value(row)).is_err())
+            .collect();
+        check(INVALID_ID, "id", &invalid, true);
+    }
+
+    match category {
+        EntityCategory::Edge => {
+            for endpoint in ["from_node_id", "to_node_id"] {
+                let Some(ids) = string_column(&batch, endpoint) else {
+                    check(MISSING_ENDPOINT, endpoint, &vec![true; rows], true);
+                    continue;
+                };
+                let missing: Vec<bool> = (0..rows).map(|row| ids.is_null(row)).collect();
+                let invalid: Vec<bool> = (0..rows)
+                    .map(|row| !ids.is_null(row) && Uuid::parse_str(ids.value(row)).is_err())
+                    .collect();
+                check(MISSING_ENDPOINT, endpoint, &missing, true);
+                check(INVALID_ENDPOINT, endpoint, &invalid, true);
+            }
+        }
+        EntityCategory::Vector => {
+            // Rows without an embedding may still match an already stored vector.
+            let missing: Vec<bool> = match batch.column_by_name("embedding") {
+                Some(column) => (0..rows).map(|row| column.is_null(row)).collect(),
+                None => vec![true; rows],
+            };
+            check(MISSING_EMBEDDING, "embedding", &missing, false);
+        }
+        EntityCategory::Node => {
+            for &key in primary_keys {
+                let nulls: Vec<bool> = match batch.column_by_name(key) {
+                    Some(column) => (0..rows).map(|row| column.is_null(row)).collect(),
+                    None => vec![true; rows],
+                };
+                check(NULL_PRIMARY_KEY, key, &nulls, true);
+            }
+        }
+    }
+
+    let latest = chrono::Utc::now().timestamp_micros() + MAX_CLOCK_SKEW_MICROS;
+    let schema = batch.schema();
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        if !matches!(
+            field.data_type(),
+            DataType::Timestamp(TimeUnit::Microsecond, _)
+        ) {
+            continue;
+        }
+        let Some(values) = column.as_any().downcast_ref::<TimestampMicrosecondArray>() else {
+            continue;
+        };
+        let out_of_range: Vec<bool> = (0..rows)
+            .map(|row| {
+                !values.is_null(row) && (values.value(row) <= 0 || values.value(row) > latest)
+            })
+            .collect();
+        check(TIMESTAMP_OUT_OF_RANGE, field.name(), &out_of_range, false);
+    }
+
+    let rejected = keep.iter().filter(|keep| !**keep).count();
+    report.record(entity_type, violations, rejected);
+    if rejected == 0 {
+        return Ok(batch);
+    }
+    log::warn!(
+        "Dropped {} of {} '{}' row(s) that failed schema checks",
+        rejected,
+        rows,
+        entity_type
+    );
+    filter_record_batch(&batch, &BooleanArray::from(keep))
+        .map_err(|err| StorageError::SyncError(format!("failed to filter linted rows: {err}")))
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Option<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deltalake::arrow::datatypes::{Field, Schema};
+    use std::sync::Arc;
+
+    fn edge_batch(from: Vec<Option<&str>>, created_at: Vec<Option<i64>>) -> RecordBatch {
+        let to = vec![Some("00000000-0000-0000-0000-000000000002"); from.len()];
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("from_node_id", DataType::Utf8, true),
+                Field::new("to_node_id", DataType::Utf8, true),
+                Field::new(
+                    "created_at",
+                    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                    true,
+                ),
+            ])),
+            vec![
+                Arc::new(StringArray::from(from)),
+                Arc::new(StringArray::from(to)),
+                Arc::new(TimestampMicrosecondArray::from(created_at).with_timezone("UTC")),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rows_without_usable_endpoints_are_dropped_and_counted() {
+        let batch = edge_batch(
+            vec![
+                Some("00000000-0000-0000-0000-000000000001"),
+                None,
+                Some("not-a-uuid"),
+                Some("00000000-0000-0000-0000-000000000003"),
+            ],
+            vec![Some(1_700_000_000_000_000), None, None, Some(0)],
+        );
+        let mut report = LintReport::default();
+        let linted =
+            lint_batch("edge_test", EntityCategory::Edge, &[], batch, &mut report).unwrap();
+
+        assert_eq!(linted.num_rows(), 2);
+        assert_eq!(report.rejected_rows, 2);
+        assert_eq!(
+            report.by_entity_type["edge_test"],
+            BTreeMap::from([
+                ("invalid_endpoint:from_node_id".to_string(), 1),
+                ("missing_endpoint:from_node_id".to_string(), 1),
+                ("timestamp_out_of_range:created_at".to_string(), 1),
+            ])
+        );
+        assert_eq!(report.total, 3);
+    }
+
+    #[test]
+    fn clean_batches_pass_through_unchanged() {
+        let batch = edge_batch(
+            vec![Some("00000000-0000-0000-0000-000000000001")],
+            vec![Some(1_700_000_000_000_000)],
+        );
+        let mut report = LintReport::default();
+        let linted =
+            lint_batch("edge_test", EntityCategory::Edge, &[], batch, &mut report).unwrap();
+        assert_eq!(linted.num_rows(), 1);
+        assert_eq!(report, LintReport::default());
+    }
+}
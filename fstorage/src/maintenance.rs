@@ -0,0 +1,68 @@
+//! Upkeep for stores that stay in use for a long time.
+//!
+//! Each sync adds at least one small data file to every table it writes, and
+//! merges leave the files they replace behind for time travel, so an untended
+//! lake only grows. A run compacts the small files of each table into larger
+//! ones, then vacuums the files that only versions past the retention period
+//! still refer to and forgets their checksums. The engine's data file cannot
+//! be shrunk while the store is open; the run reports how much of it is in use
+//! along with the engine's counters. A dry run changes nothing and reports
+//! what a real one would do.
+
+use crate::catalog::Catalog;
+use crate::errors::Result;
+use crate::lake::Lake;
+use crate::models::{EngineMaintenance, MaintenanceOptions, MaintenanceReport};
+
+/// Compacts and vacuums every lake table, then measures the engine.
+pub async fn run(
+    catalog: &Catalog,
+    lake: &Lake,
+    options: &MaintenanceOptions,
+) -> Result<MaintenanceReport> {
+    let started_at = chrono::Utc::now().timestamp();
+    let mut tables = Vec::new();
+    for table in lake.list_tables("").await? {
+        let maintained = lake.maintain_table(&table.table_path, options).await?;
+        if maintained.compacted_files > 0 || maintained.vacuumed_files > 0 {
+            log::info!(
+                "{}: compacted {} files into {}, vacuumed {} files ({} bytes)",
+                maintained.table_path,
+                maintained.compacted_files,
+                maintained.written_files,
+                maintained.vacuumed_files,
+                maintained.vacuumed_bytes
+            );
+        }
+        tables.push(maintained);
+    }
+
+    let vacuumed: Vec<String> = tables
+        .iter()
+        .flat_map(|table| table.vacuumed_paths.iter().cloned())
+        .collect();
+    let forgotten_checksums = if vacuumed.is_empty() {
+        0
+    } else {
+        catalog.delete_file_checksums(&vacuumed)? as u64
+    };
+
+    let engine = lake
+        .read_graph(|storage, txn| {
+            Ok(EngineMaintenance {
+                file_bytes: storage.graph_env.real_disk_size()?,
+                used_bytes: storage.graph_env.non_free_pages_size()?,
+                stats: serde_json::from_str(&storage.get_db_stats_json(txn)?)?,
+            })
+        })
+        .await?;
+
+    Ok(MaintenanceReport {
+        dry_run: options.dry_run,
+        started_at,
+        finished_at: chrono::Utc::now().timestamp(),
+        tables,
+        engine,
+        forgotten_checksums,
+    })
+}
@@ -0,0 +1,316 @@
+//! Upgrades of a store's on-disk layout, applied when it is opened.
+//!
+//! The layout version of a store is the catalog's `user_version`. A store this
+//! build creates starts at [`LAYOUT_VERSION`]; an older one is brought forward
+//! one migration at a time, each changing the catalog schema, moving lake
+//! tables or rewriting engine data. Before the first step that changes
+//! anything, the store is saved as a snapshot named `pre-layout-v<N>-<time>`,
+//! which can be opened or copied back by hand if an upgrade goes wrong. The
+//! version is recorded after every step, so an interrupted upgrade resumes
+//! where it stopped.
+//!
+//! With [`StorageConfig::auto_migrate`] off, a store that needs upgrading is
+//! not opened at all. A store written by a newer build never is.
+
+use crate::catalog::Catalog;
+use crate::config::StorageConfig;
+use crate::errors::{Result, StorageError};
+use crate::snapshot;
+use heed3::RwTxn;
+use helix_db::helix_engine::storage_core::HelixGraphStorage;
+use helix_db::helix_engine::traversal_core::HelixGraphEngine;
+use std::fs;
+
+/// Layout version of the stores this build creates and reads.
+pub const LAYOUT_VERSION: u32 = 1;
+
+/// What a migration changes to bring a store to its version.
+// Only `Record` has been needed so far; the others are here for the layout
+// changes to come.
+#[cfg_attr(not(test), allow(dead_code))]
+enum Step {
+    /// Nothing on disk changes; only the version is recorded.
+    Record,
+    /// SQL run on the catalog in the transaction that records the version.
+    Catalog(&'static str),
+    /// Moves a Delta table to another lake path, along with the catalog rows
+    /// naming it.
+    MoveTable {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// Rewrites engine data in one write transaction, e.g. to rebuild an index.
+    Engine(fn(&HelixGraphStorage, &mut RwTxn<'_>) -> Result<()>),
+}
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    step: Step,
+}
+
+/// Every layout change, oldest first; the last version is [`LAYOUT_VERSION`].
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "record the layout version of stores created before it was tracked",
+    step: Step::Record,
+}];
+
+/// Brings the store described by `config` up to [`LAYOUT_VERSION`]. Must run
+/// before anything else uses the store.
+pub(crate) fn run(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+) -> Result<()> {
+    run_migrations(config, catalog, engine, MIGRATIONS)
+}
+
+fn run_migrations(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    migrations: &[Migration],
+) -> Result<()> {
+    let current = catalog.layout_version()?;
+    let target = migrations.last().map_or(0, |migration| migration.version);
+    if current > target {
+        return Err(StorageError::Config(format!(
+            "store layout version {current} is newer than version {target} of this build; \
+             open it with a newer fstorage"
+        )));
+    }
+    let pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| migration.version > current)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    if !config.auto_migrate {
+        return Err(StorageError::Config(format!(
+            "store layout version {current} must be upgraded to {target}; \
+             open it with migrations enabled"
+        )));
+    }
+
+    if pending
+        .iter()
+        .any(|migration| !matches!(migration.step, Step::Record))
+    {
+        let name = format!(
+            "pre-layout-v{target}-{}",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S")
+        );
+        if !config.local_delta_lake() {
+            log::warn!(
+                "backup snapshot '{name}' holds the catalog and engine but not the lake tables, \
+                 which are kept outside the lake directory"
+            );
+        }
+        snapshot::create(
+            config,
+            catalog,
+            engine,
+            &name,
+            Some(format!(
+                "store before upgrading layout {current} to {target}"
+            )),
+        )?;
+        log::info!("saved the store as snapshot '{name}' before upgrading its layout");
+    }
+    for migration in pending {
+        log::info!(
+            "upgrading store layout to version {}: {}",
+            migration.version,
+            migration.description
+        );
+        apply(config, catalog, engine, migration)?;
+    }
+    Ok(())
+}
+
+fn apply(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    migration: &Migration,
+) -> Result<()> {
+    match &migration.step {
+        Step::Record => catalog.set_layout_version(migration.version),
+        Step::Catalog(sql) => catalog.migrate_schema(sql, migration.version),
+        Step::MoveTable { from, to } => {
+            move_table(config, from, to)?;
+            catalog.move_table_path(from, to, migration.version)
+        }
+        Step::Engine(rewrite) => {
+            let mut txn = engine.storage.graph_env.write_txn()?;
+            rewrite(&engine.storage, &mut txn)?;
+            txn.commit()?;
+            catalog.set_layout_version(migration.version)
+        }
+    }
+}
+
+/// Renames the directory of the Delta table `from` to `to`. A missing source
+/// is not an error: the store never had the table, or an earlier attempt
+/// moved it before it could record the version.
+fn move_table(config: &StorageConfig, from: &str, to: &str) -> Result<()> {
+    if !config.local_delta_lake() {
+        return Err(StorageError::Config(format!(
+            "this layout upgrade moves lake table {from} to {to}, which can only be done \
+             for Delta tables in the lake directory; move it in the Iceberg catalog or object \
+             store first"
+        )));
+    }
+    let source = config.lake_path.join(from);
+    let target = config.lake_path.join(to);
+    if !source.exists() {
+        return Ok(());
+    }
+    if target.exists() {
+        return Err(StorageError::InvalidArg(format!(
+            "cannot move lake table {from}: {to} already exists"
+        )));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Delta logs name data files relative to the table, so the move is enough.
+    fs::rename(source, target)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::EntityCategory;
+    use crate::models::FileChecksum;
+    use helix_db::helix_engine::traversal_core::HelixGraphEngineOpts;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    static ENGINE_REWRITTEN: AtomicBool = AtomicBool::new(false);
+
+    fn mark_rewritten(_: &HelixGraphStorage, _: &mut RwTxn<'_>) -> Result<()> {
+        ENGINE_REWRITTEN.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "baseline",
+            step: Step::Record,
+        },
+        Migration {
+            version: 2,
+            description: "add a column",
+            step: Step::Catalog("ALTER TABLE bookmarks ADD COLUMN color TEXT;"),
+        },
+        Migration {
+            version: 3,
+            description: "move a table",
+            step: Step::MoveTable {
+                from: "silver/entities/legacy",
+                to: "silver/entities/current",
+            },
+        },
+        Migration {
+            version: 4,
+            description: "rewrite the engine",
+            step: Step::Engine(mark_rewritten),
+        },
+    ];
+
+    fn open(config: &StorageConfig) -> (Catalog, HelixGraphEngine) {
+        fs::create_dir_all(&config.engine_path).unwrap();
+        let catalog = Catalog::new(config).unwrap();
+        catalog.initialize_schema().unwrap();
+        let engine = HelixGraphEngine::new(HelixGraphEngineOpts {
+            path: config.engine_path.to_str().unwrap().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        (catalog, engine)
+    }
+
+    #[test]
+    fn older_layouts_are_backed_up_and_upgraded_in_order() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path());
+        let (catalog, engine) = open(&config);
+        let legacy = config.lake_path.join("silver/entities/legacy/_delta_log");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("00000000000000000000.json"), "{}").unwrap();
+        catalog
+            .ensure_ingestion_offset(
+                "silver/entities/legacy",
+                "legacy",
+                EntityCategory::Node,
+                &["id".to_string()],
+            )
+            .unwrap();
+        catalog
+            .put_file_checksum(&FileChecksum {
+                path: "silver/entities/legacy/part-0.parquet".to_string(),
+                table_path: "silver/entities/legacy".to_string(),
+                size: 1,
+                sha256: "00".to_string(),
+                recorded_at: 0,
+                verified_at: 0,
+            })
+            .unwrap();
+
+        run_migrations(&config, &catalog, &engine, TEST_MIGRATIONS).unwrap();
+
+        assert_eq!(catalog.layout_version().unwrap(), 4);
+        assert!(ENGINE_REWRITTEN.load(Ordering::SeqCst));
+        assert!(config
+            .lake_path
+            .join("silver/entities/current/_delta_log")
+            .is_dir());
+        assert!(!config.lake_path.join("silver/entities/legacy").exists());
+        assert!(catalog
+            .get_ingestion_offset("silver/entities/current")
+            .unwrap()
+            .is_some());
+        let checksums = catalog.list_file_checksums().unwrap();
+        assert_eq!(checksums[0].path, "silver/entities/current/part-0.parquet");
+        let snapshots = snapshot::list(&config).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert!(snapshots[0].name.starts_with("pre-layout-v4-"));
+
+        // Nothing is pending any more, so no second backup is taken.
+        run_migrations(&config, &catalog, &engine, TEST_MIGRATIONS).unwrap();
+        assert_eq!(snapshot::list(&config).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn recording_the_baseline_takes_no_backup() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path());
+        let (catalog, engine) = open(&config);
+
+        run(&config, &catalog, &engine).unwrap();
+        assert_eq!(catalog.layout_version().unwrap(), LAYOUT_VERSION);
+        assert!(snapshot::list(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stores_are_not_opened_when_they_cannot_be_upgraded() {
+        let dir = tempdir().unwrap();
+        let mut config = StorageConfig::new(dir.path());
+        let (catalog, engine) = open(&config);
+
+        config.auto_migrate = false;
+        let refused = run_migrations(&config, &catalog, &engine, TEST_MIGRATIONS);
+        assert!(matches!(refused, Err(StorageError::Config(_))));
+        assert_eq!(catalog.layout_version().unwrap(), 0);
+
+        catalog.set_layout_version(LAYOUT_VERSION + 1).unwrap();
+        config.auto_migrate = true;
+        let refused = run(&config, &catalog, &engine);
+        assert!(matches!(refused, Err(StorageError::Config(message)) if message.contains("newer")));
+    }
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::fetch::ProbeReport;
+use crate::sql_engine::SqlEngine;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EntityIdentifier {
@@ -47,6 +48,45 @@ pub struct TableSummary {
     pub columns: Vec<ColumnSummary>,
 }
 
+/// Exact counts of what the store holds, from label scans of the engine and
+/// `COUNT(*)` over each lake table.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GraphStatistics {
+    pub nodes_by_label: BTreeMap<String, u64>,
+    pub edges_by_label: BTreeMap<String, u64>,
+    pub vector_count: u64,
+    /// Row counts keyed by table path relative to the lake root.
+    pub table_rows: BTreeMap<String, u64>,
+    /// Rows of each `silver/edges` table, keyed by edge type as in
+    /// [`crate::lake::Lake::get_edge_statistics`].
+    pub edge_rows: BTreeMap<String, u64>,
+}
+
+/// An edge label known to the store, with the node types it connects.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EdgeTypeSummary {
+    /// The label edges carry in the engine, as accepted by subgraph filters.
+    pub label: String,
+    /// Lake table holding these edges, when one has been written.
+    pub table_path: Option<String>,
+    pub from_types: Vec<String>,
+    pub to_types: Vec<String>,
+    /// Whether the label is declared in the schema.
+    pub in_schema: bool,
+}
+
+/// Rows returned by an ad-hoc SQL query over a lake table.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TableQueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<HashMap<String, JsonValue>>,
+    /// True when the query produced more rows than the cap allowed.
+    pub truncated: bool,
+    /// Engine that ran the query.
+    #[serde(default)]
+    pub engine: SqlEngine,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TextSearchHit {
     pub score: f32,
@@ -73,12 +113,30 @@ pub struct HybridSearchHit {
 pub struct MultiEntitySearchHit {
     pub entity_type: String,
     pub score: f32,
+    /// Cross-encoder score the hits were reordered by, when reranked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub summary: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub node: Option<HashMap<String, JsonValue>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub vector: Option<HashMap<String, JsonValue>>,
+    /// How a hit added for graph context was reached; absent on retrieved hits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expansion: Option<GraphExpansion>,
+}
+
+/// Path from a retrieved search hit to a node added as its graph context.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GraphExpansion {
+    /// Node of the retrieved hit; for a vector hit, the node it embeds.
+    pub from_id: String,
+    /// Edge type walked, e.g. `edge_calls`.
+    pub via: String,
+    /// Edges between the two nodes: 1 for a caller, callee or containing
+    /// file, 2 for a sibling in the same file.
+    pub hops: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,6 +150,75 @@ pub struct PathResult {
 pub enum SyncBudget {
     ByDuration(std::time::Duration),
     ByRequestCount(u32),
+    /// Every limit that is set must hold; the first one to run out stops the sync.
+    Composite(BudgetLimits),
+}
+
+impl SyncBudget {
+    /// Normalizes any budget flavour into a set of limits.
+    pub fn limits(&self) -> BudgetLimits {
+        match self {
+            SyncBudget::ByDuration(duration) => BudgetLimits {
+                max_duration_secs: Some(duration.as_secs_f64().ceil() as u64),
+                ..Default::default()
+            },
+            SyncBudget::ByRequestCount(count) => BudgetLimits {
+                max_requests: Some(*count),
+                ..Default::default()
+            },
+            SyncBudget::Composite(limits) => limits.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_requests: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_embedding_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+}
+
+/// The budget dimension that ran out first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetConstraint {
+    Requests,
+    Duration,
+    EmbeddingTokens,
+    Cost,
+}
+
+impl BudgetConstraint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetConstraint::Requests => "requests",
+            BudgetConstraint::Duration => "duration",
+            BudgetConstraint::EmbeddingTokens => "embedding_tokens",
+            BudgetConstraint::Cost => "cost",
+        }
+    }
+}
+
+impl std::fmt::Display for BudgetConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub limits: BudgetLimits,
+    pub requests: u64,
+    pub elapsed_secs: f64,
+    pub embedding_tokens: u64,
+    pub cost_usd: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binding_constraint: Option<BudgetConstraint>,
 }
 
 #[derive(Debug, Clone)]
@@ -100,6 +227,341 @@ pub struct SyncContext {
     pub target_entities: Vec<EntityIdentifier>,
 }
 
+/// Outcome of a completed sync run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub task_id: i64,
+    #[serde(default)]
+    pub costs: Vec<CostSummary>,
+    #[serde(default)]
+    pub budget: BudgetUsage,
+    /// Whether the run continued from a checkpoint left by an earlier attempt.
+    #[serde(default)]
+    pub resumed: bool,
+    /// Secrets removed from ingested text before it was stored.
+    #[serde(default)]
+    pub redactions: RedactionReport,
+    /// Fetched rows that failed schema checks.
+    #[serde(default)]
+    pub lint: LintReport,
+    /// Rows written to the lake, per entity type.
+    #[serde(default)]
+    pub rows: BTreeMap<String, u64>,
+}
+
+/// Rows of one lake table that hold a developer's personal data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalDataTable {
+    pub table: String,
+    pub rows: Vec<HashMap<String, JsonValue>>,
+}
+
+/// Everything stored about one developer login, for data-subject access requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalDataExport {
+    pub login: String,
+    /// Ids of the `Developer` nodes for this login and of the git identities
+    /// its commits were authored under.
+    pub node_ids: Vec<String>,
+    pub tables: Vec<PersonalDataTable>,
+    pub engine_nodes: Vec<HashMap<String, JsonValue>>,
+}
+
+/// What an erasure request removed or anonymized.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersonalDataErasure {
+    pub login: String,
+    pub node_ids: Vec<String>,
+    /// Deleted rows per lake table.
+    pub rows_deleted: BTreeMap<String, usize>,
+    /// Rows per lake table whose author or assignee fields were cleared.
+    pub rows_updated: BTreeMap<String, usize>,
+    pub engine_nodes_removed: usize,
+    pub engine_nodes_updated: usize,
+    /// Issue and pull request documents naming the developer as author.
+    pub engine_vectors_removed: usize,
+    /// Sync checkpoint entries dropped because they held the login.
+    pub checkpoint_entries_removed: usize,
+    /// Snapshots the same data was erased from.
+    pub snapshots: Vec<String>,
+}
+
+impl PersonalDataErasure {
+    /// Whether nothing was stored about the developer.
+    pub fn is_empty(&self) -> bool {
+        self.rows_deleted.is_empty()
+            && self.rows_updated.is_empty()
+            && self.engine_nodes_removed == 0
+            && self.engine_nodes_updated == 0
+            && self.engine_vectors_removed == 0
+            && self.checkpoint_entries_removed == 0
+    }
+}
+
+/// What was removed when a single node was deleted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeDeletion {
+    pub node_id: String,
+    /// Entity type the node was stored under, when it could be resolved.
+    pub entity_type: Option<String>,
+    /// Whether the node was still present in the graph engine.
+    pub engine_node_removed: bool,
+    /// Vectors attached to the node through an edge, removed along with it.
+    pub vectors_removed: Vec<String>,
+    /// Deleted rows per lake table.
+    pub rows_deleted: BTreeMap<String, usize>,
+}
+
+/// What a repair of a table's Delta log changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRepair {
+    pub table: String,
+    /// Versions whose commit files had malformed lines removed.
+    pub repaired_versions: Vec<i64>,
+    pub quarantined_lines: usize,
+    /// Directory the removed lines were saved to, when there were any.
+    pub quarantine_dir: Option<String>,
+    /// Version the rebuilt checkpoint was written at.
+    pub checkpoint_version: i64,
+}
+
+/// A named, frozen copy of the catalog, lake and engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created_at: i64,
+    /// Delta version captured for each lake table, keyed by table path.
+    pub table_versions: BTreeMap<String, i64>,
+}
+
+/// `archive.json` of a store archive, checked before an import restores it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    /// fstorage version that wrote the archive.
+    pub fstorage_version: String,
+    /// [`SchemaRegistry::fingerprint`](crate::schema_registry::SchemaRegistry::fingerprint)
+    /// of the writing build; an import refuses a different one.
+    pub schema_fingerprint: String,
+    pub created_at: i64,
+    pub graph_version: u64,
+    /// Delta version captured for each lake table, keyed by table path.
+    pub table_versions: BTreeMap<String, i64>,
+}
+
+/// `manifest.json` of a project dataset, listing its Parquet files in the
+/// order an import writes them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    pub format_version: u32,
+    pub project_url: String,
+    pub project_id: String,
+    pub exported_at: i64,
+    pub files: Vec<DatasetFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetFile {
+    /// Relative to the dataset directory, e.g. `entities/project.parquet`.
+    pub path: String,
+    /// Lake table the rows were read from and are imported into.
+    pub table: String,
+    pub entity_type: String,
+    /// `node`, `edge` or `vector`.
+    pub category: String,
+    pub rows: u64,
+}
+
+/// Outcome of an import of precomputed embeddings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingImportReport {
+    pub entity_type: String,
+    pub model: String,
+    pub dimension: usize,
+    pub rows: usize,
+}
+
+/// How one lake table differs between two snapshots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub version_a: Option<i64>,
+    pub version_b: Option<i64>,
+    pub rows_a: u64,
+    pub rows_b: u64,
+    /// Rows of `b` without an identical row in `a`: new or modified.
+    pub added_or_changed: Option<u64>,
+    /// Rows of `a` without an identical row in `b`: removed or modified.
+    pub removed_or_changed: Option<u64>,
+    /// A few of the new or modified rows, for node tables only.
+    #[serde(default)]
+    pub sample: Vec<HashMap<String, JsonValue>>,
+    /// Why rows could not be compared, e.g. after a schema change.
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Differences between snapshots `a` and `b`; unchanged tables are only counted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub a: String,
+    pub b: String,
+    pub entity_rows_a: u64,
+    pub entity_rows_b: u64,
+    pub edge_rows_a: u64,
+    pub edge_rows_b: u64,
+    pub changed_tables: Vec<TableDiff>,
+    pub unchanged_tables: usize,
+}
+
+/// Outcome of one storage component check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub component: String,
+    pub ok: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// True when every component check passed.
+    pub ok: bool,
+    pub components: Vec<ComponentHealth>,
+}
+
+/// Counts of redacted secrets, by rule and by entity type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionReport {
+    pub total: usize,
+    #[serde(default)]
+    pub by_rule: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub by_entity_type: BTreeMap<String, usize>,
+}
+
+impl RedactionReport {
+    pub fn record(&mut self, entity_type: &str, hits: impl IntoIterator<Item = (String, usize)>) {
+        let mut recorded = 0;
+        for (rule, count) in hits {
+            *self.by_rule.entry(rule).or_default() += count;
+            recorded += count;
+        }
+        if recorded > 0 {
+            self.total += recorded;
+            *self
+                .by_entity_type
+                .entry(entity_type.to_string())
+                .or_default() += recorded;
+        }
+    }
+}
+
+/// Schema violations found in fetched rows, see [`crate::lint`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintReport {
+    pub total: usize,
+    /// Rows dropped because they could not be ingested.
+    #[serde(default)]
+    pub rejected_rows: usize,
+    /// Violation counts per entity type, keyed by `<rule>:<column>`.
+    #[serde(default)]
+    pub by_entity_type: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl LintReport {
+    pub fn record(
+        &mut self,
+        entity_type: &str,
+        violations: BTreeMap<String, usize>,
+        rejected_rows: usize,
+    ) {
+        if violations.is_empty() {
+            return;
+        }
+        self.rejected_rows += rejected_rows;
+        let counts = self
+            .by_entity_type
+            .entry(entity_type.to_string())
+            .or_default();
+        for (violation, count) in violations {
+            self.total += count;
+            *counts.entry(violation).or_default() += count;
+        }
+    }
+}
+
+/// Stages of a sync run, in the order they normally start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Fetch,
+    Embedding,
+    LakeWrite,
+    EngineIngest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseStatus {
+    Started,
+    Running,
+    Finished,
+}
+
+/// One progress update emitted while a sync runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProgressEvent {
+    pub phase: SyncPhase,
+    pub status: PhaseStatus,
+    /// Units done so far: entity collections for lake/engine, texts for embedding.
+    pub completed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl SyncProgressEvent {
+    pub fn started(phase: SyncPhase, total: Option<u64>) -> Self {
+        Self {
+            phase,
+            status: PhaseStatus::Started,
+            completed: 0,
+            total,
+            detail: None,
+        }
+    }
+
+    pub fn running(
+        phase: SyncPhase,
+        completed: u64,
+        total: Option<u64>,
+        detail: Option<String>,
+    ) -> Self {
+        Self {
+            phase,
+            status: PhaseStatus::Running,
+            completed,
+            total,
+            detail,
+        }
+    }
+
+    pub fn finished(phase: SyncPhase, completed: u64) -> Self {
+        Self {
+            phase,
+            status: PhaseStatus::Finished,
+            completed,
+            total: Some(completed),
+            detail: None,
+        }
+    }
+}
+
 // --- Metadata Catalog (SQLite) Models ---
 
 #[derive(Debug)]
@@ -145,3 +607,638 @@ pub struct SourceAnchor {
     pub anchor_value: Option<String>,
     pub updated_at: i64,
 }
+
+/// Time bucket granularity used when aggregating API usage counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl UsageBucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageBucket::Day => "day",
+            UsageBucket::Week => "week",
+            UsageBucket::Month => "month",
+        }
+    }
+}
+
+impl std::str::FromStr for UsageBucket {
+    type Err = crate::errors::StorageError;
+
+    fn from_str(s: &str) -> crate::errors::Result<Self> {
+        match s {
+            "day" | "daily" => Ok(UsageBucket::Day),
+            "week" | "weekly" => Ok(UsageBucket::Week),
+            "month" | "monthly" => Ok(UsageBucket::Month),
+            other => Err(crate::errors::StorageError::InvalidArg(format!(
+                "Unknown usage bucket '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiUsageAggregate {
+    pub bucket_start: i64, // Unix timestamp (UTC)
+    pub endpoint: String,
+    pub api_key: String,
+    pub count: i64,
+}
+
+/// Token/request usage attributed to one provider, priced with the configured table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostSummary {
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub requests: u64,
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostAggregate {
+    pub bucket_start: i64, // Unix timestamp (UTC)
+    pub scope: String,
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub requests: i64,
+    pub tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// Lifecycle of a queued sync job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncJobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    /// The sync ran out of budget before finishing.
+    Stopped,
+}
+
+impl SyncJobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncJobState::Queued => "queued",
+            SyncJobState::Running => "running",
+            SyncJobState::Succeeded => "succeeded",
+            SyncJobState::Failed => "failed",
+            SyncJobState::Stopped => "stopped",
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            SyncJobState::Succeeded | SyncJobState::Failed | SyncJobState::Stopped
+        )
+    }
+}
+
+impl std::str::FromStr for SyncJobState {
+    type Err = crate::errors::StorageError;
+
+    fn from_str(s: &str) -> crate::errors::Result<Self> {
+        match s {
+            "queued" => Ok(SyncJobState::Queued),
+            "running" => Ok(SyncJobState::Running),
+            "succeeded" => Ok(SyncJobState::Succeeded),
+            "failed" => Ok(SyncJobState::Failed),
+            "stopped" => Ok(SyncJobState::Stopped),
+            other => Err(crate::errors::StorageError::InvalidArg(format!(
+                "Unknown sync job state '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncJob {
+    pub job_id: i64,
+    pub fetcher: String,
+    pub request: String, // JSON string
+    pub state: SyncJobState,
+    pub progress: Option<String>, // JSON string
+    pub task_id: Option<i64>,
+    pub result: Option<String>, // JSON string
+    pub error: Option<String>,
+    pub created_at: i64, // Unix timestamp
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+/// A sync submitted on a cron schedule, with the outcome of its last firing.
+#[derive(Debug, Clone)]
+pub struct SyncSchedule {
+    pub name: String,
+    pub cron: String,
+    pub request: String, // JSON string
+    /// Upper bound of the random delay added to each firing.
+    pub jitter_secs: u64,
+    pub enabled: bool,
+    pub next_run_at: i64,
+    pub last_run_at: Option<i64>,
+    /// Latest job the schedule submitted; a rejected firing clears it.
+    pub last_job_id: Option<i64>,
+    /// `queued`, `skipped` or why the sync was rejected.
+    pub last_outcome: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A node pinned to the analysts' working set, with an optional note.
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub node_id: String,
+    pub note: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// An external Delta table attached read-only, see [`crate::foreign`].
+#[derive(Debug, Clone)]
+pub struct ForeignTable {
+    pub name: String,
+    pub uri: String,
+    pub attached_at: i64,
+}
+
+/// A named search kept for the dashboard to re-run.
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub name: String,
+    /// `hybrid` or `graph`.
+    pub kind: String,
+    pub query: String, // JSON string
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// One persisted sync, with the Delta version every entity and edge table was
+/// left at, so later runs can be compared against it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub run_id: i64,
+    /// Graph version the run produced.
+    pub graph_version: u64,
+    pub finished_at: i64,
+    pub table_versions: BTreeMap<String, i64>,
+}
+
+/// One sync of a fetcher as recorded in the catalog, whatever its outcome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncHistoryEntry {
+    pub id: i64,
+    pub fetcher: String,
+    /// Digest of the sync parameters; syncs with the same parameters share it.
+    pub params_hash: String,
+    pub task_id: i64,
+    /// `running` until the sync returns.
+    pub state: SyncJobState,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    /// Rows written to the lake, per entity type.
+    pub rows: BTreeMap<String, u64>,
+    pub error: Option<String>,
+}
+
+/// A node or edge that differs between two sync runs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GraphChange {
+    pub id: String,
+    /// Entity type of a node, or label of an edge.
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphChangeSet {
+    pub added: Vec<GraphChange>,
+    pub removed: Vec<GraphChange>,
+    /// Present in both runs with different column values.
+    pub changed: Vec<GraphChange>,
+}
+
+/// What one sync run changed in the graph relative to an earlier one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphDiff {
+    pub from_run: i64,
+    pub to_run: i64,
+    pub nodes: GraphChangeSet,
+    pub edges: GraphChangeSet,
+    /// Set when a list was cut at the requested limit.
+    pub truncated: bool,
+    /// Tables whose changed rows could not be told apart, with the reason.
+    pub notes: Vec<String>,
+}
+
+/// Data-quality metrics of one lake table. Counts that do not apply to the
+/// table's category are `None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableQuality {
+    pub table_path: String,
+    pub entity_type: String,
+    /// `node`, `edge` or `vector`.
+    pub category: String,
+    pub rows: u64,
+    /// Vector rows stored without an embedding.
+    pub missing_embeddings: Option<u64>,
+    /// Engine nodes of this type that no edge touches.
+    pub orphan_nodes: Option<u64>,
+    /// Columns that are null in at least 90% of rows.
+    pub mostly_null_fields: Vec<String>,
+    /// Node or edge ids that do not parse as UUIDs.
+    pub id_parse_failures: Option<u64>,
+}
+
+/// One run of the data-quality job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub generated_at: i64,
+    pub tables: Vec<TableQuality>,
+}
+
+/// A node or edge id as the synchronizer derives it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StableId {
+    pub id: String,
+    /// The string hashed into `id`.
+    pub name: String,
+}
+
+/// Distinct primary-key sets of one entity type that hash to the same id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdCollision {
+    pub entity_type: String,
+    pub id: String,
+    /// Primary-key values of each colliding row, keyed by column name; `None`
+    /// for a null key.
+    pub key_sets: Vec<BTreeMap<String, Option<String>>>,
+}
+
+/// One run of the id collision audit over the `silver/index` tables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdAuditReport {
+    pub generated_at: i64,
+    pub tables: u64,
+    pub rows: u64,
+    pub collisions: Vec<IdCollision>,
+}
+
+/// Where a `same_as` link between a developer and a git identity stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityMatchStatus {
+    /// Proposed by identity resolution and not reviewed yet.
+    Suggested,
+    Confirmed,
+    /// Removed from the graph and never suggested again.
+    Rejected,
+}
+
+impl IdentityMatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IdentityMatchStatus::Suggested => "suggested",
+            IdentityMatchStatus::Confirmed => "confirmed",
+            IdentityMatchStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::str::FromStr for IdentityMatchStatus {
+    type Err = crate::errors::StorageError;
+
+    fn from_str(s: &str) -> crate::errors::Result<Self> {
+        match s {
+            "suggested" => Ok(IdentityMatchStatus::Suggested),
+            "confirmed" => Ok(IdentityMatchStatus::Confirmed),
+            "rejected" => Ok(IdentityMatchStatus::Rejected),
+            other => Err(crate::errors::StorageError::InvalidArg(format!(
+                "Unknown identity match status '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// A developer and a git identity believed to be the same person.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityMatch {
+    pub developer_id: String,
+    pub identity_id: String,
+    /// Login of the developer, when the node is still stored.
+    pub login: Option<String>,
+    /// Email of the git identity, when the node is still stored.
+    pub email: Option<String>,
+    /// How the match was found: `email`, `noreply`, `email_login`, `name`, or
+    /// `manual` for a link confirmed without a suggestion.
+    pub method: String,
+    /// 0 to 1; 1 for exact email matches and confirmed links.
+    pub confidence: f64,
+    pub status: IdentityMatchStatus,
+}
+
+/// A reviewer's verdict on a developer/git identity pair, kept in the catalog
+/// so identity resolution honours it on every later run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityDecision {
+    pub developer_id: String,
+    pub identity_id: String,
+    pub status: IdentityMatchStatus,
+    pub decided_at: i64,
+}
+
+/// One identity resolution run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityResolutionReport {
+    pub generated_at: i64,
+    pub developers: u64,
+    pub identities: u64,
+    /// The `same_as` links in the graph after the run, best first.
+    pub matches: Vec<IdentityMatch>,
+    /// Candidates left out because a reviewer rejected them.
+    pub rejected_skipped: u64,
+    /// Earlier suggestions the run no longer makes, removed from the graph.
+    pub stale_removed: u64,
+}
+
+/// Settings of a structural embedding run, see [`crate::structure`]. With
+/// both walk biases at 1 the walks are uniform, as in DeepWalk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StructuralEmbeddingOptions {
+    /// Node types that get an embedding; the walks cover every node.
+    pub entity_types: Vec<String>,
+    pub dimensions: usize,
+    pub walks_per_node: usize,
+    pub walk_length: usize,
+    /// Nodes on either side of a walk position that count as its context.
+    pub window: usize,
+    pub negative_samples: usize,
+    pub epochs: usize,
+    /// node2vec `p`: higher values make a walk less likely to step back.
+    pub return_param: f64,
+    /// node2vec `q`: below 1 walks move outward, above 1 they stay local.
+    pub in_out_param: f64,
+    pub seed: u64,
+}
+
+impl Default for StructuralEmbeddingOptions {
+    fn default() -> Self {
+        Self {
+            entity_types: vec![
+                "project".to_string(),
+                "class".to_string(),
+                "function".to_string(),
+            ],
+            dimensions: 64,
+            walks_per_node: 10,
+            walk_length: 40,
+            window: 5,
+            negative_samples: 5,
+            epochs: 1,
+            return_param: 1.0,
+            in_out_param: 1.0,
+            seed: 42,
+        }
+    }
+}
+
+/// One structural embedding run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuralEmbeddingReport {
+    pub generated_at: i64,
+    /// Stamped as `embedding_model` on the stored vectors.
+    pub model: String,
+    pub nodes: u64,
+    pub edges: u64,
+    /// Embeddings stored, by node type.
+    pub embedded: BTreeMap<String, u64>,
+    /// Vectors of earlier runs removed.
+    pub replaced: u64,
+}
+
+/// A node whose structural embedding is near another node's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuralNeighbor {
+    pub node_id: String,
+    pub entity_type: String,
+    pub similarity: f32,
+}
+
+/// What a maintenance run may do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceOptions {
+    /// Only report what would be compacted and reclaimed.
+    pub dry_run: bool,
+    /// Files only versions older than this many hours refer to are deleted;
+    /// time travel to those versions stops working.
+    pub retention_hours: u64,
+    /// Data files smaller than this are compacted into files of about this size.
+    pub target_file_size: u64,
+}
+
+impl Default for MaintenanceOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            retention_hours: 7 * 24,
+            target_file_size: 128 * 1024 * 1024,
+        }
+    }
+}
+
+/// Compaction and vacuum of one lake table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableMaintenance {
+    pub table_path: String,
+    /// Data files of the latest version below the target size.
+    pub small_files: u64,
+    /// Data files compaction replaced, and the files it wrote instead.
+    pub compacted_files: u64,
+    pub written_files: u64,
+    /// Files past the retention period, deleted unless this was a dry run.
+    pub vacuumed_files: u64,
+    pub vacuumed_bytes: u64,
+    /// Files vacuum deleted, relative to the lake directory.
+    #[serde(skip)]
+    pub vacuumed_paths: Vec<String>,
+}
+
+/// Space use of the graph engine's data file. LMDB reuses free pages but never
+/// shrinks the file; a snapshot or archive of the store holds a compacted copy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineMaintenance {
+    pub file_bytes: u64,
+    pub used_bytes: u64,
+    /// The engine's own counters, as in `/api/status`.
+    pub stats: JsonValue,
+}
+
+/// One run of lake and engine maintenance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub dry_run: bool,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub tables: Vec<TableMaintenance>,
+    pub engine: EngineMaintenance,
+    /// Checksums of vacuumed files the catalog no longer keeps.
+    pub forgotten_checksums: u64,
+}
+
+impl MaintenanceReport {
+    /// Bytes vacuum deleted, or would delete in a dry run.
+    pub fn vacuumed_bytes(&self) -> u64 {
+        self.tables.iter().map(|table| table.vacuumed_bytes).sum()
+    }
+}
+
+/// Size and SHA-256 of a lake data file, as first seen by a verification pass.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    /// Relative to the lake directory.
+    pub path: String,
+    pub table_path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub recorded_at: i64,
+    pub verified_at: i64,
+}
+
+/// What a verification pass found wrong with a lake data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileProblem {
+    /// The Delta log references the file but it is gone.
+    Missing,
+    /// Its size or SHA-256 differs from the recorded one.
+    ChecksumMismatch,
+    /// It does not decode as Parquet, e.g. after a partial write.
+    Unreadable,
+    /// The table's Delta log could not be read, so its files were not checked.
+    UnreadableLog,
+}
+
+impl FileProblem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileProblem::Missing => "missing",
+            FileProblem::ChecksumMismatch => "checksum_mismatch",
+            FileProblem::Unreadable => "unreadable",
+            FileProblem::UnreadableLog => "unreadable_log",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileIssue {
+    /// Relative to the lake directory; the table path for `unreadable_log`.
+    pub path: String,
+    pub table_path: String,
+    pub problem: FileProblem,
+    pub detail: String,
+}
+
+/// Outcome of a pass over every data file of the lake's Delta tables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LakeVerification {
+    pub verified_at: i64,
+    pub tables: u64,
+    pub files: u64,
+    pub bytes: u64,
+    /// Files seen for the first time, whose checksums were recorded.
+    pub recorded: u64,
+    /// Recorded checksums dropped because their files are no longer in use.
+    pub forgotten: u64,
+    pub issues: Vec<FileIssue>,
+}
+
+impl LakeVerification {
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// What a consistency check found between the catalog, the lake tables and
+/// the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyProblem {
+    /// The catalog tracks a table the lake does not have.
+    MissingTable,
+    /// The catalog's ingestion offset is past the table's latest version, so a
+    /// full ETL would skip changes the engine never saw.
+    OffsetAhead,
+    /// A `silver/index` row whose id the engine has no node for.
+    UnresolvedIndexId,
+    /// An edge row with an endpoint the engine has no node or vector for.
+    DanglingEdge,
+    /// A vector row no edge leads to.
+    OrphanVector,
+}
+
+impl ConsistencyProblem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsistencyProblem::MissingTable => "missing_table",
+            ConsistencyProblem::OffsetAhead => "offset_ahead",
+            ConsistencyProblem::UnresolvedIndexId => "unresolved_index_id",
+            ConsistencyProblem::DanglingEdge => "dangling_edge",
+            ConsistencyProblem::OrphanVector => "orphan_vector",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyIssue {
+    pub problem: ConsistencyProblem,
+    pub table_path: String,
+    /// Row the issue is about; absent for table-level issues.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub detail: String,
+    /// Whether the repair pass fixed it.
+    pub repaired: bool,
+}
+
+/// Outcome of cross-checking the catalog, the lake tables and the engine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    pub checked_at: i64,
+    /// Whether issues were repaired where possible.
+    pub repair: bool,
+    pub offsets: u64,
+    pub index_ids: u64,
+    pub edges: u64,
+    pub vectors: u64,
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+impl ConsistencyReport {
+    /// Issues still present after the run.
+    pub fn unrepaired(&self) -> usize {
+        self.issues.iter().filter(|issue| !issue.repaired).count()
+    }
+}
+
+/// Activity of one project on one UTC day.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityDay {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub commits: u64,
+    pub issues: u64,
+    pub pull_requests: u64,
+}
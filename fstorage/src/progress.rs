@@ -0,0 +1,70 @@
+//! Progress reporting hooks for sync runs.
+//!
+//! [`FStorageSynchronizer::sync_with_progress`](crate::sync::FStorageSynchronizer::sync_with_progress)
+//! reports every phase to a [`SyncProgress`] sink. Embedding happens inside the
+//! fetcher, so it is observed by wrapping the provider handed to it.
+
+use crate::embedding::EmbeddingProvider;
+use crate::errors::Result;
+use crate::models::{SyncPhase, SyncProgressEvent};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Receives progress events; implementations must be cheap and must not block.
+pub trait SyncProgress: Send + Sync {
+    fn report(&self, event: SyncProgressEvent);
+}
+
+pub struct NoopProgress;
+
+impl SyncProgress for NoopProgress {
+    fn report(&self, _event: SyncProgressEvent) {}
+}
+
+/// Reports a running total of embedded texts after every batch.
+pub struct ProgressEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    progress: Arc<dyn SyncProgress>,
+    embedded: AtomicU64,
+}
+
+impl ProgressEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, progress: Arc<dyn SyncProgress>) -> Self {
+        Self {
+            inner,
+            progress,
+            embedded: AtomicU64::new(0),
+        }
+    }
+
+    pub fn embedded(&self) -> u64 {
+        self.embedded.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for ProgressEmbeddingProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        let count = texts.len() as u64;
+        let vectors = self.inner.embed(texts).await?;
+        if count > 0 {
+            let embedded = self.embedded.fetch_add(count, Ordering::Relaxed) + count;
+            self.progress.report(SyncProgressEvent::running(
+                SyncPhase::Embedding,
+                embedded,
+                None,
+                None,
+            ));
+        }
+        Ok(vectors)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+}
@@ -0,0 +1,313 @@
+//! Data-quality metrics over the lake tables and the engine.
+//!
+//! A run measures every entity, edge and vector table: vector rows stored
+//! without an embedding, engine nodes no edge touches, columns that are almost
+//! always null, and ids that are not UUIDs, which the engine cannot resolve.
+//! Each run is appended to [`QUALITY_TABLE`], so earlier runs stay available for
+//! comparison.
+
+use crate::errors::{Result, StorageError};
+use crate::lake::Lake;
+use crate::models::{QualityReport, TableQuality};
+use deltalake::arrow::array::{ArrayRef, Int64Array, StringArray};
+use deltalake::arrow::datatypes::{DataType, Field, Schema};
+use deltalake::arrow::record_batch::RecordBatch;
+use heed3::RoTxn;
+use helix_db::helix_engine::storage_core::storage_methods::StorageMethods;
+use helix_db::helix_engine::storage_core::HelixGraphStorage;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Gold table holding one row per measured table and run.
+pub const QUALITY_TABLE: &str = "gold/quality";
+
+/// Share of null values from which a column is reported as mostly null.
+const MOSTLY_NULL_RATIO: f64 = 0.9;
+
+const UUID_PATTERN: &str =
+    "^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$";
+
+/// Table prefixes measured, with the category of what they hold.
+const MEASURED_PREFIXES: &[(&str, &str)] = &[
+    ("silver/entities", "node"),
+    ("silver/edges", "edge"),
+    ("silver/vectors", "vector"),
+];
+
+/// Measures every entity, edge and vector table and appends the result to
+/// [`QUALITY_TABLE`].
+pub async fn run(lake: &Lake) -> Result<QualityReport> {
+    let orphans = lake.read_graph(orphan_nodes_by_label).await?;
+    let mut report = QualityReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        tables: Vec::new(),
+    };
+    for (prefix, category) in MEASURED_PREFIXES {
+        for table in lake.list_tables(prefix).await? {
+            let Some(entity_type) = table
+                .table_path
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let columns: Vec<String> = table
+                .columns
+                .iter()
+                .map(|column| column.name.clone())
+                .collect();
+            let rows = lake
+                .count_rows(&table.table_path)
+                .await?
+                .unwrap_or_default();
+            let mut quality = TableQuality {
+                mostly_null_fields: mostly_null_fields(lake, &table.table_path, &columns, rows)
+                    .await?,
+                table_path: table.table_path,
+                entity_type,
+                category: category.to_string(),
+                rows,
+                missing_embeddings: None,
+                orphan_nodes: None,
+                id_parse_failures: None,
+            };
+            match *category {
+                "node" => {
+                    quality.orphan_nodes =
+                        Some(orphans.get(&quality.entity_type).copied().unwrap_or(0));
+                    let index_table = format!("silver/index/{}", quality.entity_type);
+                    quality.id_parse_failures =
+                        Some(count_where(lake, &index_table, &not_uuid("id")).await?);
+                }
+                "edge" => {
+                    let id_columns = ["id", "from_node_id", "to_node_id"]
+                        .into_iter()
+                        .filter(|name| columns.iter().any(|column| column == name))
+                        .map(not_uuid)
+                        .collect::<Vec<_>>();
+                    if !id_columns.is_empty() {
+                        quality.id_parse_failures = Some(
+                            count_where(lake, &quality.table_path, &id_columns.join(" OR "))
+                                .await?,
+                        );
+                    }
+                }
+                _ => {
+                    if columns.iter().any(|column| column == "embedding") {
+                        quality.missing_embeddings = Some(
+                            count_where(lake, &quality.table_path, "embedding IS NULL").await?,
+                        );
+                    }
+                }
+            }
+            report.tables.push(quality);
+        }
+    }
+
+    if !report.tables.is_empty() {
+        lake.write_batches(QUALITY_TABLE, vec![to_batch(&report)?], None)
+            .await?;
+    }
+    Ok(report)
+}
+
+/// The most recent run stored in [`QUALITY_TABLE`], if any.
+pub async fn latest(lake: &Lake) -> Result<Option<QualityReport>> {
+    let rows = lake
+        .table_sql(
+            QUALITY_TABLE,
+            "SELECT * FROM {{table}} WHERE generated_at = (SELECT MAX(generated_at) FROM {{table}}) \
+             ORDER BY table_path",
+        )
+        .await?;
+    let Some(generated_at) = rows
+        .first()
+        .and_then(|row| row.get("generated_at"))
+        .and_then(JsonValue::as_i64)
+    else {
+        return Ok(None);
+    };
+    let tables = rows
+        .iter()
+        .map(|row| {
+            let text = |name: &str| {
+                row.get(name)
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let count = |name: &str| row.get(name).and_then(JsonValue::as_u64);
+            TableQuality {
+                table_path: text("table_path"),
+                entity_type: text("entity_type"),
+                category: text("category"),
+                rows: count("rows").unwrap_or_default(),
+                missing_embeddings: count("missing_embeddings"),
+                orphan_nodes: count("orphan_nodes"),
+                mostly_null_fields: serde_json::from_str(&text("mostly_null_fields"))
+                    .unwrap_or_default(),
+                id_parse_failures: count("id_parse_failures"),
+            }
+        })
+        .collect();
+    Ok(Some(QualityReport {
+        generated_at,
+        tables,
+    }))
+}
+
+/// Engine nodes per label that are neither source nor target of any edge.
+fn orphan_nodes_by_label(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &RoTxn<'_>,
+) -> Result<BTreeMap<String, u64>> {
+    let mut connected = HashSet::new();
+    for entry in storage.edges_db.iter(txn)? {
+        let (id, _) = entry?;
+        let edge = storage.get_edge(txn, &id)?;
+        connected.insert(edge.from_node);
+        connected.insert(edge.to_node);
+    }
+    let mut orphans = BTreeMap::new();
+    for entry in storage.nodes_db.iter(txn)? {
+        let (id, _) = entry?;
+        if connected.contains(&id) {
+            continue;
+        }
+        let node = storage.get_node(txn, &id)?;
+        *orphans.entry(node.label).or_default() += 1;
+    }
+    Ok(orphans)
+}
+
+/// Columns of `table` whose share of nulls reaches [`MOSTLY_NULL_RATIO`].
+async fn mostly_null_fields(
+    lake: &Lake,
+    table: &str,
+    columns: &[String],
+    rows: u64,
+) -> Result<Vec<String>> {
+    if rows == 0 || columns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let counts = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| format!("COUNT(\"{}\") AS c{idx}", column.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let result = lake
+        .table_sql(table, &format!("SELECT {counts} FROM {{{{table}}}}"))
+        .await?;
+    let Some(row) = result.first() else {
+        return Ok(Vec::new());
+    };
+    Ok(columns
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| {
+            let present = row
+                .get(&format!("c{idx}"))
+                .and_then(JsonValue::as_u64)
+                .unwrap_or_default();
+            let nulls = rows.saturating_sub(present);
+            nulls as f64 / rows as f64 >= MOSTLY_NULL_RATIO
+        })
+        .map(|(_, column)| column.clone())
+        .collect())
+}
+
+/// Rows of `table` matching `predicate`; 0 when the table does not exist.
+async fn count_where(lake: &Lake, table: &str, predicate: &str) -> Result<u64> {
+    let rows = lake
+        .table_sql(
+            table,
+            &format!("SELECT COUNT(*) AS n FROM {{{{table}}}} WHERE {predicate}"),
+        )
+        .await?;
+    Ok(rows
+        .first()
+        .and_then(|row| row.get("n"))
+        .and_then(JsonValue::as_u64)
+        .unwrap_or_default())
+}
+
+fn not_uuid(column: &str) -> String {
+    format!("(\"{column}\" IS NULL OR NOT (\"{column}\" ~ '{UUID_PATTERN}'))")
+}
+
+fn to_batch(report: &QualityReport) -> Result<RecordBatch> {
+    let tables = &report.tables;
+    let text = |value: fn(&TableQuality) -> String| -> ArrayRef {
+        Arc::new(StringArray::from(
+            tables.iter().map(value).collect::<Vec<_>>(),
+        ))
+    };
+    let count = |value: fn(&TableQuality) -> Option<u64>| -> ArrayRef {
+        Arc::new(Int64Array::from(
+            tables
+                .iter()
+                .map(|table| value(table).map(|count| count as i64))
+                .collect::<Vec<_>>(),
+        ))
+    };
+    let schema = Schema::new(vec![
+        Field::new("generated_at", DataType::Int64, false),
+        Field::new("table_path", DataType::Utf8, false),
+        Field::new("entity_type", DataType::Utf8, false),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("rows", DataType::Int64, true),
+        Field::new("missing_embeddings", DataType::Int64, true),
+        Field::new("orphan_nodes", DataType::Int64, true),
+        Field::new("mostly_null_fields", DataType::Utf8, false),
+        Field::new("id_parse_failures", DataType::Int64, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from(vec![report.generated_at; tables.len()])),
+        text(|table| table.table_path.clone()),
+        text(|table| table.entity_type.clone()),
+        text(|table| table.category.clone()),
+        count(|table| Some(table.rows)),
+        count(|table| table.missing_embeddings),
+        count(|table| table.orphan_nodes),
+        text(|table| {
+            serde_json::to_string(&table.mostly_null_fields).unwrap_or_else(|_| "[]".to_string())
+        }),
+        count(|table| table.id_parse_failures),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).map_err(|e| StorageError::Other(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_rows_round_trip_through_a_batch() {
+        let report = QualityReport {
+            generated_at: 42,
+            tables: vec![TableQuality {
+                table_path: "silver/vectors/readme_chunk".to_string(),
+                entity_type: "readme_chunk".to_string(),
+                category: "vector".to_string(),
+                rows: 3,
+                missing_embeddings: Some(1),
+                orphan_nodes: None,
+                mostly_null_fields: vec!["source_file".to_string()],
+                id_parse_failures: None,
+            }],
+        };
+        let batch = to_batch(&report).unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        let rows: Vec<HashMap<String, JsonValue>> = Lake::record_batches_to_maps(&[batch]).unwrap();
+        assert_eq!(rows[0]["generated_at"], JsonValue::from(42));
+        assert_eq!(rows[0]["missing_embeddings"], JsonValue::from(1));
+        assert_eq!(rows[0]["orphan_nodes"], JsonValue::Null);
+        assert_eq!(
+            rows[0]["mostly_null_fields"],
+            JsonValue::from("[\"source_file\"]")
+        );
+    }
+}
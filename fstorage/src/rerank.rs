@@ -0,0 +1,321 @@
+//! Cross-encoder reranking of search candidates.
+//!
+//! Hybrid search scores BM25 and embedding similarity separately, so a query
+//! and a document are never read together. A [`Reranker`] scores each
+//! (query, document) pair jointly; [`crate::FStorage::search_hybrid_multi`]
+//! uses one to reorder its top candidates when asked to.
+
+use crate::errors::{Result, StorageError};
+use async_trait::async_trait;
+#[cfg(feature = "fastembed")]
+use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+#[cfg(feature = "fastembed")]
+use std::sync::Mutex;
+#[cfg(feature = "fastembed")]
+use tokio::task;
+
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Relevance of each document to `query`, in the order of `documents`.
+    /// Higher is more relevant; scores are only comparable within one call.
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>>;
+
+    /// Like [`rerank`](Self::rerank), along with the tokens the provider
+    /// billed for the call when its response reports them.
+    async fn rerank_with_usage(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+    ) -> Result<(Vec<f32>, Option<u64>)> {
+        Ok((self.rerank(query, documents).await?, None))
+    }
+
+    /// Short identifier used in logs (e.g. `fastembed`).
+    fn provider_name(&self) -> &str {
+        "unknown"
+    }
+
+    /// Model identifier, if the reranker exposes one.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Which reranker [`crate::FStorage`] builds on open.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum RerankerConfig {
+    /// A local cross-encoder run by FastEmbed; `model` is a FastEmbed model
+    /// code such as `BAAI/bge-reranker-base`, the default. Needs the
+    /// `fastembed` feature.
+    FastEmbed {
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// A rerank endpoint speaking the Cohere/Jina request format.
+    Http {
+        url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+impl RerankerConfig {
+    pub fn build(&self) -> Result<Arc<dyn Reranker>> {
+        Ok(match self {
+            #[cfg(feature = "fastembed")]
+            Self::FastEmbed { model: None } => Arc::new(FastEmbedReranker::new_default()?),
+            #[cfg(feature = "fastembed")]
+            Self::FastEmbed { model: Some(code) } => Arc::new(FastEmbedReranker::new_with_model(
+                Self::fastembed_model(code)?,
+            )?),
+            #[cfg(not(feature = "fastembed"))]
+            Self::FastEmbed { .. } => {
+                return Err(StorageError::Config(
+                    "the FastEmbed reranker needs fstorage built with the `fastembed` feature"
+                        .into(),
+                ));
+            }
+            Self::Http {
+                url,
+                api_key,
+                model,
+            } => Arc::new(HttpReranker::new(
+                url.clone(),
+                api_key.clone(),
+                model.clone(),
+            )),
+        })
+    }
+
+    #[cfg(feature = "fastembed")]
+    fn fastembed_model(code: &str) -> Result<RerankerModel> {
+        TextRerank::list_supported_models()
+            .into_iter()
+            .find(|info| {
+                info.model_code.eq_ignore_ascii_case(code)
+                    || format!("{:?}", info.model).eq_ignore_ascii_case(code)
+            })
+            .map(|info| info.model)
+            .ok_or_else(|| StorageError::Config(format!("unknown FastEmbed reranker '{code}'")))
+    }
+}
+
+impl std::str::FromStr for RerankerConfig {
+    type Err = String;
+
+    /// Parses `fastembed`, `fastembed:<model>` or an `http(s)://` endpoint.
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        let raw = raw.trim();
+        if raw.starts_with("http://") || raw.starts_with("https://") {
+            return Ok(Self::Http {
+                url: raw.to_string(),
+                api_key: None,
+                model: None,
+            });
+        }
+        let (kind, model) = match raw.split_once(':') {
+            Some((kind, model)) => (kind, Some(model.trim().to_string())),
+            None => (raw, None),
+        };
+        if kind.eq_ignore_ascii_case("fastembed") {
+            Ok(Self::FastEmbed {
+                model: model.filter(|model| !model.is_empty()),
+            })
+        } else {
+            Err(format!(
+                "unknown reranker '{raw}', expected fastembed[:model] or an http(s) URL"
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "fastembed")]
+pub struct FastEmbedReranker {
+    model: Arc<Mutex<TextRerank>>,
+    model_name: Option<String>,
+}
+
+#[cfg(feature = "fastembed")]
+impl FastEmbedReranker {
+    pub fn new_default() -> Result<Self> {
+        Self::new_with_options(Default::default())
+    }
+
+    pub fn new_with_model(model: RerankerModel) -> Result<Self> {
+        let model_name = format!("{:?}", model);
+        let mut reranker = Self::new_with_options(RerankInitOptions::new(model))?;
+        reranker.model_name = Some(model_name);
+        Ok(reranker)
+    }
+
+    pub fn new_with_options(options: RerankInitOptions) -> Result<Self> {
+        let model = TextRerank::try_new(options).map_err(|e| {
+            StorageError::SyncError(format!("Failed to initialize FastEmbed reranker: {}", e))
+        })?;
+        Ok(Self {
+            model: Arc::new(Mutex::new(model)),
+            model_name: None,
+        })
+    }
+}
+
+#[cfg(feature = "fastembed")]
+#[async_trait]
+impl Reranker for FastEmbedReranker {
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = Arc::clone(&self.model);
+        let query = query.to_string();
+        task::spawn_blocking(move || -> Result<Vec<f32>> {
+            let mut guard = model
+                .lock()
+                .map_err(|_| StorageError::SyncError("FastEmbed reranker mutex poisoned".into()))?;
+            let document_refs: Vec<&str> = documents.iter().map(|s| s.as_str()).collect();
+            let results = guard
+                .rerank(query.as_str(), document_refs, false, None)
+                .map_err(|e| StorageError::SyncError(format!("FastEmbed rerank failed: {}", e)))?;
+            let mut scores = vec![f32::MIN; documents.len()];
+            for result in results {
+                if let Some(score) = scores.get_mut(result.index) {
+                    *score = result.score;
+                }
+            }
+            Ok(scores)
+        })
+        .await
+        .map_err(|e| StorageError::SyncError(format!("FastEmbed task join error: {}", e)))?
+    }
+
+    fn provider_name(&self) -> &str {
+        "fastembed"
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model_name.as_deref()
+    }
+}
+
+#[derive(Serialize)]
+struct HttpRerankRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    query: &'a str,
+    documents: &'a [String],
+    top_n: usize,
+}
+
+#[derive(Deserialize)]
+struct HttpRerankResult {
+    index: usize,
+    #[serde(alias = "score")]
+    relevance_score: f32,
+}
+
+#[derive(Deserialize)]
+struct HttpRerankUsage {
+    total_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct HttpRerankResponse {
+    #[serde(alias = "data")]
+    results: Vec<HttpRerankResult>,
+    /// Reported by Jina; Cohere bills search units instead.
+    #[serde(default)]
+    usage: Option<HttpRerankUsage>,
+}
+
+/// Calls a hosted or self-served rerank endpoint: the request carries `query`,
+/// `documents` and `top_n`, the response lists `{index, relevance_score}` per
+/// document, as Cohere, Jina and Infinity servers do.
+pub struct HttpReranker {
+    url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpReranker {
+    pub fn new(url: String, api_key: Option<String>, model: Option<String>) -> Self {
+        Self {
+            url,
+            api_key,
+            model,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>> {
+        Ok(self.rerank_with_usage(query, documents).await?.0)
+    }
+
+    async fn rerank_with_usage(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+    ) -> Result<(Vec<f32>, Option<u64>)> {
+        if documents.is_empty() {
+            return Ok((Vec::new(), None));
+        }
+
+        let request_payload = HttpRerankRequest {
+            model: self.model.as_deref(),
+            query,
+            documents: &documents,
+            top_n: documents.len(),
+        };
+        let mut request = self.client.post(&self.url).json(&request_payload);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| StorageError::SyncError(format!("Rerank request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(StorageError::SyncError(format!(
+                "Rerank endpoint returned an error: {}",
+                error_body
+            )));
+        }
+
+        let rerank_response = response.json::<HttpRerankResponse>().await.map_err(|e| {
+            StorageError::SyncError(format!("Failed to parse rerank response: {}", e))
+        })?;
+
+        let mut scores = vec![f32::MIN; documents.len()];
+        for result in rerank_response.results {
+            if let Some(score) = scores.get_mut(result.index) {
+                *score = result.relevance_score;
+            }
+        }
+        Ok((
+            scores,
+            rerank_response.usage.map(|usage| usage.total_tokens),
+        ))
+    }
+
+    fn provider_name(&self) -> &str {
+        "http"
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
 
 use crate::fetch::EntityCategory;
 use crate::schemas::generated_schemas::{
@@ -64,6 +65,42 @@ impl SchemaRegistry {
     pub fn entities(&self) -> impl Iterator<Item = &EntityMetadata> {
         self.entities.values()
     }
+
+    pub fn edges(&self) -> impl Iterator<Item = &EdgeMetadata> {
+        self.edges.values().flatten()
+    }
+
+    /// Hex SHA-256 over every entity's table, keys and fields and every edge's
+    /// endpoints. Two builds with the same generated schemas agree on it, so a
+    /// store written by one can be read by the other.
+    pub fn fingerprint(&self) -> String {
+        let mut lines: Vec<String> = self
+            .entities()
+            .map(|meta| {
+                format!(
+                    "entity {} {} {} [{}] [{}]",
+                    meta.entity_type,
+                    meta.category.as_str(),
+                    meta.table_name,
+                    meta.primary_keys.join(","),
+                    meta.fields.join(",")
+                )
+            })
+            .chain(self.edges().map(|meta| {
+                format!(
+                    "edge {} {} {}",
+                    meta.edge_type, meta.from_entity, meta.to_entity
+                )
+            }))
+            .collect();
+        lines.sort();
+        let mut hasher = Sha256::new();
+        for line in lines {
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+        }
+        format!("{:x}", hasher.finalize())
+    }
 }
 
 impl From<&'static EntityMetaRecord> for EntityMetadata {
@@ -0,0 +1,473 @@
+//! Named snapshots: frozen copies of the catalog, lake and engine.
+//!
+//! A snapshot directory has the same layout as a storage root (`catalog.sqlite`,
+//! `lake/`, `engine/`) plus a `snapshot.json` manifest, so it can be opened like
+//! any other store. Lake tables are captured at their current Delta version: log
+//! entries up to that version are copied and data files are hard-linked, which is
+//! cheap because Delta never rewrites a Parquet file in place.
+//!
+//! Erasing a developer's personal data is the one change made to a snapshot
+//! after it was taken: its tables are rewritten and vacuumed like the live
+//! ones, so no hard link keeps the erased rows on disk, and [`refresh_versions`]
+//! records the versions that left in the manifest.
+
+use crate::catalog::Catalog;
+use crate::config::StorageConfig;
+use crate::errors::{Result, StorageError};
+use crate::lake::Lake;
+use crate::models::{SnapshotDiff, SnapshotInfo, TableDiff};
+use crate::table_format::delta::{log_version, DELTA_LOG_DIR};
+use deltalake::arrow::array::{Array, Int64Array};
+use deltalake::datafusion::execution::context::{SessionConfig, SessionContext};
+use heed3::CompactionOption;
+use helix_db::helix_engine::traversal_core::HelixGraphEngine;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+pub const MANIFEST_FILE: &str = "snapshot.json";
+const ENGINE_DATA_FILE: &str = "data.mdb";
+const ENGINE_LOCK_FILE: &str = "lock.mdb";
+const MAX_NAME_LEN: usize = 64;
+
+/// Snapshot names become directory names: ASCII letters, digits, `-`, `_` and `.`.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidArg(format!(
+            "invalid snapshot name '{name}': use up to {MAX_NAME_LEN} letters, digits, '-', '_' or '.'"
+        )))
+    }
+}
+
+/// Directory of the snapshot `name`, failing when it does not exist.
+pub fn snapshot_dir(config: &StorageConfig, name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    let dir = config.snapshot_root().join(name);
+    if !dir.join(MANIFEST_FILE).is_file() {
+        return Err(StorageError::NotFound(format!("snapshot '{name}'")));
+    }
+    Ok(dir)
+}
+
+pub fn load(config: &StorageConfig, name: &str) -> Result<SnapshotInfo> {
+    let dir = snapshot_dir(config, name)?;
+    let raw = fs::read(dir.join(MANIFEST_FILE))?;
+    Ok(serde_json::from_slice(&raw)?)
+}
+
+/// Lists every complete snapshot, oldest first.
+pub fn list(config: &StorageConfig) -> Result<Vec<SnapshotInfo>> {
+    let root = config.snapshot_root();
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        // Staging directories start with a dot and have no manifest yet.
+        if validate_name(name).is_err() {
+            continue;
+        }
+        match load(config, name) {
+            Ok(info) => snapshots.push(info),
+            Err(StorageError::NotFound(_)) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    snapshots.sort_by(|a, b| (a.created_at, &a.name).cmp(&(b.created_at, &b.name)));
+    Ok(snapshots)
+}
+
+/// Copies the store described by `config` into a new snapshot called `name`.
+///
+/// The caller must keep writers out for the duration (see
+/// `FStorageSynchronizer::pause_writes`); the copy is staged under a hidden
+/// directory and only renamed into place once complete.
+pub(crate) fn create(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    name: &str,
+    description: Option<String>,
+) -> Result<SnapshotInfo> {
+    validate_name(name)?;
+    let root = config.snapshot_root();
+    let target = root.join(name);
+    if target.exists() {
+        return Err(StorageError::InvalidArg(format!(
+            "snapshot '{name}' already exists"
+        )));
+    }
+    let staging = root.join(format!(".{name}.partial"));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    let result = capture(config, catalog, engine, &staging, name, description);
+    match result {
+        Ok(info) => {
+            fs::rename(&staging, &target)?;
+            Ok(info)
+        }
+        Err(err) => {
+            let _ = fs::remove_dir_all(&staging);
+            Err(err)
+        }
+    }
+}
+
+fn capture(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    dir: &Path,
+    name: &str,
+    description: Option<String>,
+) -> Result<SnapshotInfo> {
+    let table_versions = copy_store(config, catalog, engine, dir)?;
+    let info = SnapshotInfo {
+        name: name.to_string(),
+        description,
+        created_at: chrono::Utc::now().timestamp(),
+        table_versions,
+    };
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&info)?)?;
+    Ok(info)
+}
+
+/// Copies the catalog, engine and lake tables of the store described by
+/// `config` into `dir` in the storage root layout. Returns the Delta version
+/// captured for each table. Writers must be kept out meanwhile.
+pub(crate) fn copy_store(
+    config: &StorageConfig,
+    catalog: &Catalog,
+    engine: &HelixGraphEngine,
+    dir: &Path,
+) -> Result<BTreeMap<String, i64>> {
+    let layout = StorageConfig::new(dir);
+    catalog.backup_to(&layout.catalog_path)?;
+
+    fs::create_dir_all(&layout.engine_path)?;
+    engine.storage.graph_env.copy_to_path(
+        layout.engine_path.join(ENGINE_DATA_FILE),
+        CompactionOption::Enabled,
+    )?;
+    for entry in fs::read_dir(&config.engine_path)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if entry.file_type()?.is_file()
+            && file_name != ENGINE_DATA_FILE
+            && file_name != ENGINE_LOCK_FILE
+        {
+            fs::copy(entry.path(), layout.engine_path.join(&file_name))?;
+        }
+    }
+
+    let mut table_versions = BTreeMap::new();
+    fs::create_dir_all(&layout.lake_path)?;
+    copy_tables(
+        &config.lake_path,
+        &config.lake_path,
+        &layout.lake_path,
+        &mut table_versions,
+    )?;
+    Ok(table_versions)
+}
+
+/// Walks `dir` for Delta tables and captures each one under `dest_root`.
+fn copy_tables(
+    lake_root: &Path,
+    dir: &Path,
+    dest_root: &Path,
+    versions: &mut BTreeMap<String, i64>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(lake_root) else {
+            continue;
+        };
+        if path.join(DELTA_LOG_DIR).is_dir() {
+            if let Some(version) = copy_table(&path, &dest_root.join(relative))? {
+                let table = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                versions.insert(table, version);
+            }
+        } else {
+            copy_tables(lake_root, &path, dest_root, versions)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the Delta log of one table up to its latest commit and links its data
+/// files. Returns the captured version, or `None` for a table without commits.
+fn copy_table(src: &Path, dest: &Path) -> Result<Option<i64>> {
+    let log_dir = src.join(DELTA_LOG_DIR);
+    let mut log_files = Vec::new();
+    for entry in fs::read_dir(&log_dir)? {
+        let file_name = entry?.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if let Some(version) = log_version(file_name) {
+            log_files.push((version, file_name.to_string()));
+        }
+    }
+    let Some(latest) = log_files
+        .iter()
+        .filter(|(_, file_name)| file_name.ends_with(".json"))
+        .map(|(version, _)| *version)
+        .max()
+    else {
+        return Ok(None);
+    };
+
+    // `_last_checkpoint` is left out on purpose: it may point past `latest`, and
+    // readers fall back to listing the log when it is missing.
+    let dest_log = dest.join(DELTA_LOG_DIR);
+    fs::create_dir_all(&dest_log)?;
+    for (version, file_name) in &log_files {
+        if *version <= latest {
+            fs::copy(log_dir.join(file_name), dest_log.join(file_name))?;
+        }
+    }
+    link_data_files(src, dest)?;
+    Ok(Some(latest))
+}
+
+/// Records the latest Delta version of every table of the snapshot `name` in
+/// its manifest, after the snapshot was changed in place.
+pub(crate) fn refresh_versions(config: &StorageConfig, name: &str) -> Result<()> {
+    let dir = snapshot_dir(config, name)?;
+    let mut info = load(config, name)?;
+    let lake = StorageConfig::new(&dir).lake_path;
+    for (table, version) in info.table_versions.iter_mut() {
+        if let Some(latest) = latest_commit(&lake.join(table).join(DELTA_LOG_DIR))? {
+            *version = latest;
+        }
+    }
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec_pretty(&info)?)?;
+    Ok(())
+}
+
+fn latest_commit(log_dir: &Path) -> Result<Option<i64>> {
+    let mut latest = None;
+    for entry in fs::read_dir(log_dir)? {
+        let file_name = entry?.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if file_name.ends_with(".json") {
+            latest = latest.max(log_version(file_name));
+        }
+    }
+    Ok(latest)
+}
+
+fn link_data_files(src: &Path, dest: &Path) -> Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if file_name == DELTA_LOG_DIR {
+            continue;
+        }
+        let target = dest.join(&file_name);
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            link_data_files(&entry.path(), &target)?;
+        } else if fs::hard_link(entry.path(), &target).is_err() {
+            // Snapshots on another filesystem cannot share inodes with the lake.
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compares the lake tables of snapshots `a` and `b`.
+///
+/// Both snapshots come from the same store, so a table captured at the same
+/// Delta version in both is identical and skipped. Changed tables are compared
+/// row by row; up to `sample` new or modified rows are returned for node tables.
+pub async fn diff(config: &StorageConfig, a: &str, b: &str, sample: usize) -> Result<SnapshotDiff> {
+    let info_a = load(config, a)?;
+    let info_b = load(config, b)?;
+    let lake_a = StorageConfig::new(snapshot_dir(config, a)?).lake_path;
+    let lake_b = StorageConfig::new(snapshot_dir(config, b)?).lake_path;
+
+    let mut diff = SnapshotDiff {
+        a: a.to_string(),
+        b: b.to_string(),
+        entity_rows_a: 0,
+        entity_rows_b: 0,
+        edge_rows_a: 0,
+        edge_rows_b: 0,
+        changed_tables: Vec::new(),
+        unchanged_tables: 0,
+    };
+    let tables: BTreeSet<&String> = info_a
+        .table_versions
+        .keys()
+        .chain(info_b.table_versions.keys())
+        .collect();
+    for table in tables {
+        let version_a = info_a.table_versions.get(table).copied();
+        let version_b = info_b.table_versions.get(table).copied();
+        let ctx = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
+        if version_a.is_some() {
+            register(&ctx, "snapshot_a", &lake_a, table).await?;
+        }
+        if version_b.is_some() {
+            register(&ctx, "snapshot_b", &lake_b, table).await?;
+        }
+        let rows_a = match version_a {
+            Some(_) => count(&ctx, "SELECT count(*) FROM snapshot_a").await?,
+            None => 0,
+        };
+        let rows_b = match version_b {
+            Some(_) => count(&ctx, "SELECT count(*) FROM snapshot_b").await?,
+            None => 0,
+        };
+        if table.starts_with("silver/entities/") {
+            diff.entity_rows_a += rows_a;
+            diff.entity_rows_b += rows_b;
+        } else if table.starts_with("silver/edges/") {
+            diff.edge_rows_a += rows_a;
+            diff.edge_rows_b += rows_b;
+        }
+        if version_a.is_some() && version_a == version_b {
+            diff.unchanged_tables += 1;
+            continue;
+        }
+
+        let mut table_diff = TableDiff {
+            table: table.clone(),
+            version_a,
+            version_b,
+            rows_a,
+            rows_b,
+            added_or_changed: None,
+            removed_or_changed: None,
+            sample: Vec::new(),
+            note: None,
+        };
+        let changed_sql = match (version_a, version_b) {
+            (Some(_), Some(_)) => "SELECT * FROM snapshot_b EXCEPT SELECT * FROM snapshot_a",
+            (None, _) => "SELECT * FROM snapshot_b",
+            (_, None) => "SELECT * FROM snapshot_a WHERE false",
+        };
+        let removed_sql = match (version_a, version_b) {
+            (Some(_), Some(_)) => "SELECT * FROM snapshot_a EXCEPT SELECT * FROM snapshot_b",
+            (Some(_), None) => "SELECT * FROM snapshot_a",
+            (None, _) => "SELECT * FROM snapshot_b WHERE false",
+        };
+        let compared = async {
+            let added = count(&ctx, &format!("SELECT count(*) FROM ({changed_sql})")).await?;
+            let removed = count(&ctx, &format!("SELECT count(*) FROM ({removed_sql})")).await?;
+            let rows = if sample > 0 && table.starts_with("silver/entities/") {
+                query(&ctx, &format!("{changed_sql} LIMIT {sample}")).await?
+            } else {
+                Vec::new()
+            };
+            Ok::<_, StorageError>((added, removed, rows))
+        };
+        match compared.await {
+            Ok((added, removed, rows)) => {
+                table_diff.added_or_changed = Some(added);
+                table_diff.removed_or_changed = Some(removed);
+                table_diff.sample = rows;
+            }
+            Err(err) => table_diff.note = Some(format!("rows not compared: {err}")),
+        }
+        diff.changed_tables.push(table_diff);
+    }
+    Ok(diff)
+}
+
+async fn register(ctx: &SessionContext, alias: &str, lake: &Path, table: &str) -> Result<()> {
+    let path = std::path::absolute(lake.join(table))?;
+    let url = Url::from_directory_path(&path)
+        .map_err(|_| StorageError::Config(format!("Invalid path: {path:?}")))?;
+    let delta = deltalake::open_table(url).await?;
+    ctx.register_table(alias, Arc::new(delta))
+        .map_err(|e| StorageError::Other(e.into()))?;
+    Ok(())
+}
+
+async fn query(
+    ctx: &SessionContext,
+    sql: &str,
+) -> Result<Vec<std::collections::HashMap<String, serde_json::Value>>> {
+    let batches = ctx
+        .sql(sql)
+        .await
+        .map_err(|e| StorageError::Other(e.into()))?
+        .collect()
+        .await
+        .map_err(|e| StorageError::Other(e.into()))?;
+    Lake::record_batches_to_maps(&batches)
+}
+
+async fn count(ctx: &SessionContext, sql: &str) -> Result<u64> {
+    let batches = ctx
+        .sql(sql)
+        .await
+        .map_err(|e| StorageError::Other(e.into()))?
+        .collect()
+        .await
+        .map_err(|e| StorageError::Other(e.into()))?;
+    let value = batches
+        .first()
+        .and_then(|batch| batch.column(0).as_any().downcast_ref::<Int64Array>())
+        .filter(|column| !column.is_empty())
+        .map(|column| column.value(0))
+        .unwrap_or_default();
+    Ok(value.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_must_be_plain_directory_names() {
+        validate_name("before-refactor_2024.1").unwrap();
+        assert!(validate_name("").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("../escape").is_err());
+        assert!(validate_name("with space").is_err());
+    }
+
+    #[test]
+    fn log_versions_come_from_the_file_name_prefix() {
+        assert_eq!(log_version("00000000000000000007.json"), Some(7));
+        assert_eq!(
+            log_version("00000000000000000010.checkpoint.parquet"),
+            Some(10)
+        );
+        assert_eq!(log_version("_last_checkpoint"), None);
+        assert_eq!(log_version("7.json"), None);
+    }
+}
@@ -0,0 +1,47 @@
+//! Engines that run the ad-hoc SQL of [`Lake::query_table_sql`](crate::lake::Lake::query_table_sql).
+//!
+//! DataFusion is the default and reads every kind of table the lake has. With
+//! the `duckdb` feature, small local deployments can run these queries on an
+//! embedded DuckDB reading the Parquet files of Delta tables instead, which
+//! skips building a DataFusion context per request. Queries DuckDB cannot
+//! serve, over Iceberg or foreign tables, still run on DataFusion.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "duckdb")]
+mod duckdb;
+
+#[cfg(feature = "duckdb")]
+pub(crate) use duckdb::DuckDbEngine;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlEngine {
+    #[default]
+    DataFusion,
+    /// DuckDB SQL over the table's Parquet files; needs the `duckdb` feature.
+    DuckDb,
+}
+
+impl SqlEngine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SqlEngine::DataFusion => "datafusion",
+            SqlEngine::DuckDb => "duckdb",
+        }
+    }
+}
+
+impl std::str::FromStr for SqlEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "datafusion" => Ok(SqlEngine::DataFusion),
+            "duckdb" => Ok(SqlEngine::DuckDb),
+            other => Err(format!(
+                "unknown SQL engine '{other}', expected datafusion or duckdb"
+            )),
+        }
+    }
+}
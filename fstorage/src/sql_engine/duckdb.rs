@@ -0,0 +1,179 @@
+//! Read-only DuckDB queries over the Parquet files of Delta tables.
+//!
+//! Every query gets its own connection to one in-memory database, with the
+//! table registered as a temporary view over the files of its current Delta
+//! version. The database may only read files under the lake directory and its
+//! configuration is locked, so a query cannot reach anything else; the query is
+//! also wrapped in a `SELECT`, which keeps other statements out.
+
+use crate::errors::{Result, StorageError};
+use crate::models::TableQueryResult;
+use crate::sql_engine::SqlEngine;
+use chrono::{DateTime, NaiveDate, Utc};
+use duckdb::types::Value;
+use duckdb::Connection;
+use serde_json::{Number as JsonNumber, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub(crate) struct DuckDbEngine {
+    /// Connections for queries are cloned from this one.
+    base: Mutex<Connection>,
+}
+
+fn duckdb_error(err: duckdb::Error) -> StorageError {
+    StorageError::Other(err.into())
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl DuckDbEngine {
+    pub(crate) fn open(lake_path: &Path) -> Result<Self> {
+        let lake_path = std::path::absolute(lake_path)?;
+        let base = Connection::open_in_memory().map_err(duckdb_error)?;
+        base.execute_batch(&format!(
+            "SET allowed_directories = [{}];
+             SET enable_external_access = false;
+             SET autoinstall_known_extensions = false;
+             SET lock_configuration = true;",
+            quote_literal(&lake_path.to_string_lossy())
+        ))
+        .map_err(duckdb_error)?;
+        Ok(Self {
+            base: Mutex::new(base),
+        })
+    }
+
+    /// Runs `sql` with `alias` naming a view over `files`, keeping at most
+    /// `max_rows` rows. Blocks; call it off the async runtime. A caller that
+    /// stops waiting does not stop the query, which runs to completion.
+    pub(crate) fn query(
+        &self,
+        alias: &str,
+        files: &[PathBuf],
+        sql: &str,
+        max_rows: usize,
+    ) -> Result<TableQueryResult> {
+        let conn = self
+            .base
+            .lock()
+            .unwrap()
+            .try_clone()
+            .map_err(duckdb_error)?;
+        let file_list = files
+            .iter()
+            .map(|file| quote_literal(&file.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute_batch(&format!(
+            "CREATE TEMP VIEW \"{alias}\" AS SELECT * FROM read_parquet([{file_list}], union_by_name = true)"
+        ))
+        .map_err(duckdb_error)?;
+
+        // One extra row tells us whether the cap cut the result short.
+        let wrapped = format!(
+            "SELECT * FROM ({}) AS query LIMIT {}",
+            sql.trim().trim_end_matches(';'),
+            max_rows.saturating_add(1)
+        );
+        let mut stmt = conn
+            .prepare(&wrapped)
+            .map_err(|e| StorageError::InvalidArg(format!("invalid query: {e}")))?;
+        let mut result_rows = stmt
+            .query([])
+            .map_err(|e| StorageError::InvalidArg(format!("invalid query: {e}")))?;
+        let columns = result_rows
+            .as_ref()
+            .map(|stmt| stmt.column_names())
+            .unwrap_or_default();
+
+        let mut rows = Vec::new();
+        while let Some(row) = result_rows.next().map_err(duckdb_error)? {
+            let mut map = HashMap::with_capacity(columns.len());
+            for (idx, column) in columns.iter().enumerate() {
+                let value: Value = row.get(idx).map_err(duckdb_error)?;
+                map.insert(column.clone(), value_to_json(value));
+            }
+            rows.push(map);
+        }
+        let truncated = rows.len() > max_rows;
+        rows.truncate(max_rows);
+
+        Ok(TableQueryResult {
+            columns,
+            rows,
+            truncated,
+            engine: SqlEngine::DuckDb,
+        })
+    }
+}
+
+/// Renders values the way the DataFusion path does: numbers, strings, booleans
+/// and RFC 3339 timestamps, with dates as `YYYY-MM-DD`. Anything else is null.
+fn value_to_json(value: Value) -> JsonValue {
+    match value {
+        Value::Boolean(value) => JsonValue::Bool(value),
+        Value::TinyInt(value) => value.into(),
+        Value::SmallInt(value) => value.into(),
+        Value::Int(value) => value.into(),
+        Value::BigInt(value) => value.into(),
+        Value::UTinyInt(value) => value.into(),
+        Value::USmallInt(value) => value.into(),
+        Value::UInt(value) => value.into(),
+        Value::UBigInt(value) => value.into(),
+        Value::HugeInt(value) => match i64::try_from(value) {
+            Ok(value) => value.into(),
+            Err(_) => JsonValue::String(value.to_string()),
+        },
+        Value::Float(value) => float_to_json(f64::from(value)),
+        Value::Double(value) => float_to_json(value),
+        // DuckDB types decimal literals such as `1.5` as DECIMAL where
+        // DataFusion would use a float.
+        Value::Decimal(value) => value
+            .to_string()
+            .parse()
+            .map(float_to_json)
+            .unwrap_or(JsonValue::Null),
+        Value::Text(value) => JsonValue::String(value),
+        Value::Timestamp(unit, value) => {
+            DateTime::<Utc>::from_timestamp_micros(unit.to_micros(value))
+                .map(|dt| JsonValue::String(dt.to_rfc3339()))
+                .unwrap_or(JsonValue::Null)
+        }
+        Value::Date32(days) => NaiveDate::from_num_days_from_ce_opt(days + 719_163)
+            .map(|date| JsonValue::String(date.to_string()))
+            .unwrap_or(JsonValue::Null),
+        _ => JsonValue::Null,
+    }
+}
+
+fn float_to_json(value: f64) -> JsonValue {
+    JsonNumber::from_f64(value)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_render_like_the_datafusion_path() {
+        assert_eq!(value_to_json(Value::BigInt(7)), JsonValue::from(7));
+        assert_eq!(value_to_json(Value::HugeInt(12)), JsonValue::from(12));
+        assert_eq!(value_to_json(Value::Text("a".into())), JsonValue::from("a"));
+        assert_eq!(value_to_json(Value::Double(f64::NAN)), JsonValue::Null);
+        assert_eq!(
+            value_to_json(Value::Date32(0)),
+            JsonValue::from("1970-01-01")
+        );
+        assert_eq!(
+            value_to_json(Value::Timestamp(duckdb::types::TimeUnit::Second, 0)),
+            JsonValue::from("1970-01-01T00:00:00+00:00")
+        );
+        assert_eq!(value_to_json(Value::Null), JsonValue::Null);
+    }
+}
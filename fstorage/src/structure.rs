@@ -0,0 +1,510 @@
+//! Structural node embeddings learned with node2vec.
+//!
+//! [`train`] reads every edge table into one undirected graph, leaving out the
+//! edges that lead to vectors, and runs biased random walks from each node. A
+//! step back to the node the walk came from is weighted `1/p`, a step to a
+//! neighbour of that node 1 and a step further away `1/q`. A skip-gram model
+//! with negative sampling is then fitted to the walks, so nodes that appear in
+//! similar surroundings, like functions called from the same places, end up
+//! close together whatever their text says.
+//!
+//! The unit-length vectors of the requested node types are stored as
+//! `nodeembedding` vectors, linked to their node by a `has_structure` edge.
+//! An `embedding_id` that is already stored keeps its first vector, so every
+//! run writes under ids of its own and then deletes the vectors of the runs
+//! before it.
+
+use crate::errors::{Result, StorageError};
+use crate::fetch::{EntityCategory, Fetchable, GraphData};
+use crate::lake::Lake;
+use crate::models::{StructuralEmbeddingOptions, StructuralEmbeddingReport, StructuralNeighbor};
+use crate::schema_registry::SCHEMA_REGISTRY;
+use crate::schemas::generated_schemas::NodeEmbedding;
+use crate::sync::{DataSynchronizer, FStorageSynchronizer};
+use chrono::Utc;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Vectors handed to the synchronizer at a time.
+const WRITE_BATCH: usize = 5_000;
+/// Nearest vectors fetched per neighbour asked for; vectors of other node
+/// types are dropped from the hits afterwards.
+const CANDIDATES_PER_NEIGHBOR: usize = 4;
+const START_LEARNING_RATE: f32 = 0.025;
+const MIN_LEARNING_RATE: f32 = 0.0001;
+
+/// The undirected graph the walks run on. Neighbour lists are sorted, so the
+/// second-order bias can look up whether two nodes are adjacent.
+struct Graph {
+    ids: Vec<String>,
+    types: Vec<String>,
+    adjacency: Vec<Vec<u32>>,
+}
+
+impl Graph {
+    fn from_edges<'a>(edges: impl IntoIterator<Item = [(&'a str, &'a str); 2]>) -> Self {
+        let mut graph = Graph {
+            ids: Vec::new(),
+            types: Vec::new(),
+            adjacency: Vec::new(),
+        };
+        let mut index: HashMap<&str, u32> = HashMap::new();
+        for [(from, from_type), (to, to_type)] in edges {
+            if from == to {
+                continue;
+            }
+            let mut node = |id: &'a str, entity_type: &str| {
+                *index.entry(id).or_insert_with(|| {
+                    graph.ids.push(id.to_string());
+                    graph.types.push(entity_type.to_ascii_lowercase());
+                    graph.adjacency.push(Vec::new());
+                    (graph.ids.len() - 1) as u32
+                })
+            };
+            let (from, to) = (node(from, from_type), node(to, to_type));
+            graph.adjacency[from as usize].push(to);
+            graph.adjacency[to as usize].push(from);
+        }
+        for neighbors in &mut graph.adjacency {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+        graph
+    }
+
+    fn edge_count(&self) -> u64 {
+        self.adjacency.iter().map(Vec::len).sum::<usize>() as u64 / 2
+    }
+}
+
+/// SplitMix64, enough for walks and sampling and reproducible from a seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn validate(options: &StructuralEmbeddingOptions) -> Result<()> {
+    let invalid = |reason: &str| Err(StorageError::InvalidArg(reason.to_string()));
+    if options.entity_types.is_empty() {
+        return invalid("at least one entity type must be embedded");
+    }
+    if options.dimensions == 0
+        || options.walks_per_node == 0
+        || options.window == 0
+        || options.epochs == 0
+    {
+        return invalid("dimensions, walks_per_node, window and epochs must be positive");
+    }
+    if options.walk_length < 2 {
+        return invalid("walk_length must be at least 2");
+    }
+    for param in [options.return_param, options.in_out_param] {
+        if !(param.is_finite() && param > 0.0) {
+            return invalid("return_param and in_out_param must be positive");
+        }
+    }
+    Ok(())
+}
+
+/// The node after `current` on a walk that arrived from `previous`, drawn by
+/// rejection sampling against the node2vec weights.
+fn step(
+    graph: &Graph,
+    previous: Option<u32>,
+    current: u32,
+    options: &StructuralEmbeddingOptions,
+    rng: &mut Rng,
+) -> u32 {
+    let neighbors = &graph.adjacency[current as usize];
+    let uniform = options.return_param == 1.0 && options.in_out_param == 1.0;
+    let Some(previous) = previous.filter(|_| !uniform) else {
+        return neighbors[rng.below(neighbors.len())];
+    };
+    let back = 1.0 / options.return_param;
+    let out = 1.0 / options.in_out_param;
+    let max = back.max(out).max(1.0);
+    loop {
+        let next = neighbors[rng.below(neighbors.len())];
+        let weight = if next == previous {
+            back
+        } else if graph.adjacency[previous as usize]
+            .binary_search(&next)
+            .is_ok()
+        {
+            1.0
+        } else {
+            out
+        };
+        if rng.unit() * max < weight {
+            return next;
+        }
+    }
+}
+
+fn walk(
+    graph: &Graph,
+    start: u32,
+    options: &StructuralEmbeddingOptions,
+    rng: &mut Rng,
+) -> Vec<u32> {
+    let mut path = vec![start];
+    while path.len() < options.walk_length {
+        let previous = path.len().checked_sub(2).map(|idx| path[idx]);
+        let current = path[path.len() - 1];
+        path.push(step(graph, previous, current, options, rng));
+    }
+    path
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x.clamp(-8.0, 8.0)).exp())
+}
+
+/// Fits skip-gram with negative sampling to walks from every node and
+/// returns the unit-length embeddings, `dimensions` values per node.
+/// Negatives are drawn by degree to the power 0.75, the walk frequency they
+/// approximate.
+fn fit(graph: &Graph, options: &StructuralEmbeddingOptions) -> Vec<Vec<f32>> {
+    let nodes = graph.ids.len();
+    let dims = options.dimensions;
+    let mut rng = Rng(options.seed);
+    let mut input: Vec<f32> = (0..nodes * dims)
+        .map(|_| (rng.unit() as f32 - 0.5) / dims as f32)
+        .collect();
+    let mut output = vec![0f32; nodes * dims];
+
+    let mut cumulative = Vec::with_capacity(nodes);
+    let mut total = 0.0;
+    for neighbors in &graph.adjacency {
+        total += (neighbors.len() as f64).powf(0.75);
+        cumulative.push(total);
+    }
+    let negative = |rng: &mut Rng| {
+        let target = rng.unit() * total;
+        cumulative
+            .partition_point(|&bound| bound <= target)
+            .min(nodes - 1)
+    };
+
+    let total_walks = (options.epochs * options.walks_per_node * nodes) as f32;
+    let mut done = 0f32;
+    let mut order: Vec<u32> = (0..nodes as u32).collect();
+    let mut gradient = vec![0f32; dims];
+    for _ in 0..options.epochs * options.walks_per_node {
+        for idx in (1..order.len()).rev() {
+            order.swap(idx, rng.below(idx + 1));
+        }
+        for &start in &order {
+            let rate = (START_LEARNING_RATE * (1.0 - done / total_walks)).max(MIN_LEARNING_RATE);
+            done += 1.0;
+            let path = walk(graph, start, options, &mut rng);
+            for (pos, &center) in path.iter().enumerate() {
+                let center = center as usize * dims;
+                let from = pos.saturating_sub(options.window);
+                let to = (pos + options.window).min(path.len() - 1);
+                for (other, &context) in path.iter().enumerate().take(to + 1).skip(from) {
+                    if other == pos {
+                        continue;
+                    }
+                    gradient.fill(0.0);
+                    for sample in 0..=options.negative_samples {
+                        let (target, label) = if sample == 0 {
+                            (context as usize, 1.0)
+                        } else {
+                            let target = negative(&mut rng);
+                            if target == context as usize {
+                                continue;
+                            }
+                            (target, 0.0)
+                        };
+                        let center = &input[center..center + dims];
+                        let target = &mut output[target * dims..(target + 1) * dims];
+                        let dot: f32 = center.iter().zip(target.iter()).map(|(a, b)| a * b).sum();
+                        let g = (label - sigmoid(dot)) * rate;
+                        for ((grad, out), value) in
+                            gradient.iter_mut().zip(target.iter_mut()).zip(center)
+                        {
+                            *grad += g * *out;
+                            *out += g * value;
+                        }
+                    }
+                    for (value, grad) in input[center..center + dims].iter_mut().zip(&gradient) {
+                        *value += grad;
+                    }
+                }
+            }
+        }
+    }
+
+    input
+        .chunks(dims)
+        .map(|row| {
+            let norm = row.iter().map(|value| value * value).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                row.iter().map(|value| value / norm).collect()
+            } else {
+                row.to_vec()
+            }
+        })
+        .collect()
+}
+
+fn is_vector_type(entity_type: &str) -> bool {
+    SCHEMA_REGISTRY
+        .entity(entity_type)
+        .is_some_and(|meta| meta.category == EntityCategory::Vector)
+}
+
+async fn load_graph(lake: &Lake) -> Result<Graph> {
+    let mut rows = Vec::new();
+    for edge_type in lake.list_edge_types().await? {
+        let Some(table) = edge_type.table_path else {
+            continue;
+        };
+        rows.extend(
+            lake.table_sql(
+                &table,
+                "SELECT from_node_id, to_node_id, from_node_type, to_node_type FROM {{table}}",
+            )
+            .await?,
+        );
+    }
+    let text = |row: &HashMap<String, JsonValue>, key: &str| {
+        row.get(key).and_then(JsonValue::as_str).unwrap_or_default()
+    };
+    Ok(Graph::from_edges(rows.iter().filter_map(|row| {
+        let from = (text(row, "from_node_id"), text(row, "from_node_type"));
+        let to = (text(row, "to_node_id"), text(row, "to_node_type"));
+        let skip = from.0.is_empty()
+            || to.0.is_empty()
+            || is_vector_type(&from.1.to_ascii_lowercase())
+            || is_vector_type(&to.1.to_ascii_lowercase());
+        (!skip).then_some([from, to])
+    })))
+}
+
+/// Trains embeddings over the whole graph and replaces the stored ones with
+/// those of `options.entity_types`.
+pub async fn train(
+    lake: &Lake,
+    synchronizer: &FStorageSynchronizer,
+    options: &StructuralEmbeddingOptions,
+) -> Result<StructuralEmbeddingReport> {
+    validate(options)?;
+    let graph = load_graph(lake).await?;
+    let now = Utc::now();
+    let mut report = StructuralEmbeddingReport {
+        generated_at: now.timestamp(),
+        model: format!(
+            "node2vec:d{}:p{}:q{}",
+            options.dimensions, options.return_param, options.in_out_param
+        ),
+        nodes: graph.ids.len() as u64,
+        edges: graph.edge_count(),
+        embedded: BTreeMap::new(),
+        replaced: 0,
+    };
+
+    let previous: BTreeSet<String> = lake
+        .table_sql(&NodeEmbedding::table_name(), "SELECT id FROM {{table}}")
+        .await?
+        .iter()
+        .filter_map(|row| row.get("id").and_then(JsonValue::as_str))
+        .map(str::to_string)
+        .collect();
+
+    let embeddings = if graph.ids.is_empty() {
+        Vec::new()
+    } else {
+        let fit_options = options.clone();
+        tokio::task::spawn_blocking(move || {
+            let embeddings = fit(&graph, &fit_options);
+            (graph, embeddings)
+        })
+        .await
+        .map(|(trained, embeddings)| {
+            let wanted: HashSet<&str> = options.entity_types.iter().map(String::as_str).collect();
+            trained
+                .ids
+                .into_iter()
+                .zip(trained.types)
+                .zip(embeddings)
+                .filter(|((_, entity_type), _)| wanted.contains(entity_type.as_str()))
+                .collect::<Vec<_>>()
+        })
+        .map_err(|err| StorageError::Other(err.into()))?
+    };
+
+    let run = Uuid::new_v4().simple().to_string();
+    for chunk in embeddings.chunks(WRITE_BATCH) {
+        let mut rows = Vec::with_capacity(chunk.len());
+        for ((node_id, entity_type), embedding) in chunk {
+            *report.embedded.entry(entity_type.clone()).or_default() += 1;
+            rows.push(NodeEmbedding {
+                id: None,
+                source_node_id: Some(node_id.clone()),
+                source_node_key: Some(format!("{entity_type}::{node_id}")),
+                text: None,
+                embedding: Some(embedding.clone()),
+                embedding_model: Some(report.model.clone()),
+                embedding_id: Some(format!("{node_id}:{run}")),
+                token_count: None,
+                chunk_order: None,
+                created_at: Some(now),
+                updated_at: Some(now),
+            });
+        }
+        let mut graph = GraphData::new();
+        graph.add_entities(rows);
+        synchronizer.process_graph_data(graph).await?;
+    }
+
+    report.replaced = lake
+        .delete_vectors(NodeEmbedding::ENTITY_TYPE, &previous)
+        .await?;
+    Ok(report)
+}
+
+/// Up to `limit` nodes of the same type as `node_id` whose structural
+/// embeddings are nearest to its own, most similar first.
+pub async fn similar(lake: &Lake, node_id: &str, limit: usize) -> Result<Vec<StructuralNeighbor>> {
+    let node_id = Uuid::parse_str(node_id.trim())
+        .map_err(|_| StorageError::InvalidArg(format!("invalid node id '{node_id}'")))?
+        .to_string();
+    let rows = lake
+        .table_sql(
+            &NodeEmbedding::table_name(),
+            &format!(
+                "SELECT id, source_node_key FROM {{{{table}}}} WHERE source_node_id = '{node_id}'"
+            ),
+        )
+        .await?;
+    let source_type = |key: &str| {
+        key.split_once("::")
+            .map(|(entity_type, _)| entity_type.to_string())
+    };
+    let Some((vector_id, entity_type)) = rows.iter().find_map(|row| {
+        let id = row.get("id").and_then(JsonValue::as_str)?;
+        let key = row.get("source_node_key").and_then(JsonValue::as_str)?;
+        Some((id.to_string(), source_type(key)?))
+    }) else {
+        return Err(StorageError::NotFound(format!(
+            "node '{node_id}' has no structural embedding"
+        )));
+    };
+
+    let hits = lake
+        .search_vectors_near(
+            NodeEmbedding::ENTITY_TYPE,
+            &vector_id,
+            limit.saturating_mul(CANDIDATES_PER_NEIGHBOR),
+        )
+        .await?;
+    let mut seen = HashSet::from([node_id]);
+    let mut neighbors = Vec::new();
+    for hit in hits {
+        let properties = hit.vector.get("properties");
+        let field = |name: &str| {
+            properties
+                .and_then(|properties| properties.get(name))
+                .and_then(JsonValue::as_str)
+        };
+        let (Some(id), Some(key)) = (field("source_node_id"), field("source_node_key")) else {
+            continue;
+        };
+        if source_type(key).as_deref() != Some(entity_type.as_str()) || !seen.insert(id.to_string())
+        {
+            continue;
+        }
+        neighbors.push(StructuralNeighbor {
+            node_id: id.to_string(),
+            entity_type: entity_type.clone(),
+            similarity: hit.similarity,
+        });
+        if neighbors.len() == limit {
+            break;
+        }
+    }
+    Ok(neighbors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> StructuralEmbeddingOptions {
+        StructuralEmbeddingOptions {
+            dimensions: 16,
+            walks_per_node: 20,
+            walk_length: 10,
+            window: 3,
+            epochs: 2,
+            ..StructuralEmbeddingOptions::default()
+        }
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn low_return_param_keeps_walks_bouncing_back() {
+        // A path a - b - c: from b, having come from a, a walk either returns
+        // to a or moves on to c.
+        let graph = Graph::from_edges([[("a", "x"), ("b", "x")], [("b", "x"), ("c", "x")]]);
+        let options = StructuralEmbeddingOptions {
+            return_param: 0.01,
+            ..StructuralEmbeddingOptions::default()
+        };
+        let mut rng = Rng(7);
+        let returns = (0..1_000)
+            .filter(|_| step(&graph, Some(0), 1, &options, &mut rng) == 0)
+            .count();
+        assert!(returns > 950, "{returns} of 1000 steps went back");
+    }
+
+    #[test]
+    fn nodes_of_one_cluster_embed_closer_than_across_clusters() {
+        // Two 5-cliques joined by a single edge between a4 and b0.
+        let names: Vec<String> = ["a", "b"]
+            .iter()
+            .flat_map(|side| (0..5).map(move |idx| format!("{side}{idx}")))
+            .collect();
+        let mut edges = Vec::new();
+        for side in names.chunks(5) {
+            for (idx, from) in side.iter().enumerate() {
+                for to in &side[idx + 1..] {
+                    edges.push([(from.as_str(), "function"), (to.as_str(), "function")]);
+                }
+            }
+        }
+        edges.push([("a4", "function"), ("b0", "function")]);
+        let graph = Graph::from_edges(edges);
+        assert_eq!((graph.ids.len(), graph.edge_count()), (10, 21));
+
+        let embeddings = fit(&graph, &options());
+        let index = |name: &str| graph.ids.iter().position(|id| id == name).unwrap();
+        let same = cosine(&embeddings[index("a1")], &embeddings[index("a2")]);
+        let across = cosine(&embeddings[index("a1")], &embeddings[index("b2")]);
+        assert!(same > across, "same cluster {same}, across {across}");
+        assert!(embeddings
+            .iter()
+            .all(|row| (cosine(row, row) - 1.0).abs() < 1e-4));
+    }
+}
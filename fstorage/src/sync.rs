@@ -1,14 +1,24 @@
 use crate::auto_fetchable;
+use crate::budget::BudgetTracker;
 use crate::catalog::Catalog;
+use crate::checkpoint::{params_digest, CheckpointedEmbeddingProvider, SyncCheckpoint};
+use crate::costs::{MeteredEmbeddingProvider, PricingTable, UsageMeter};
 use crate::errors::{Result, StorageError};
 use crate::fetch::{
     EntityCategory, FetchResponse, Fetcher, FetcherCapability, GraphData, ProbeReport,
 };
 use crate::lake::Lake;
-use crate::models::{EntityIdentifier, ReadinessReport, SyncBudget, SyncContext};
+use crate::lint;
+use crate::models::{
+    BudgetConstraint, CostSummary, EntityIdentifier, LintReport, ReadinessReport, RedactionReport,
+    SyncBudget, SyncContext, SyncJobState, SyncPhase, SyncProgressEvent, SyncReport,
+};
+use crate::progress::{NoopProgress, ProgressEmbeddingProvider, SyncProgress};
+use crate::redaction::{RedactingEmbeddingProvider, Redactor};
 use crate::schema_registry::{
     vector_index, vector_rules, SourceNodeId, SourceNodeType, SCHEMA_REGISTRY,
 };
+use crate::transform::BatchTransform;
 use crate::utils;
 use async_trait::async_trait;
 use bincode;
@@ -18,6 +28,7 @@ use deltalake::arrow::array::{
 };
 use deltalake::arrow::datatypes::{DataType, Field, Schema};
 use deltalake::arrow::record_batch::RecordBatch;
+use futures::FutureExt;
 use heed3::{RoTxn, RwTxn};
 use helix_db::{
     helix_engine::{
@@ -41,8 +52,9 @@ use helix_db::{
         label_hash::hash_label,
     },
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use tracing::{field::Empty, info_span, instrument, Instrument, Span};
 use uuid::Uuid;
 
 /// Defines the core interface for dynamically synchronizing data.
@@ -67,7 +79,7 @@ pub trait DataSynchronizer {
         params: serde_json::Value,
         context: SyncContext,
         budget: SyncBudget,
-    ) -> Result<()>;
+    ) -> Result<SyncReport>;
 
     /// Runs a full ETL process from the data lake to the graph engine.
     async fn run_full_etl_from_lake(&self, target_repo_uri: &str) -> Result<()>;
@@ -83,7 +95,13 @@ pub struct FStorageSynchronizer {
     lake: Arc<Lake>,
     engine: Arc<HelixGraphEngine>,
     fetchers: RwLock<HashMap<String, Arc<dyn Fetcher>>>,
+    transforms: RwLock<Vec<Arc<dyn BatchTransform>>>,
     embedding_provider: Arc<dyn EmbeddingProvider>,
+    pricing: Arc<PricingTable>,
+    redactor: Arc<Redactor>,
+    /// Held shared by every write into the lake and engine, exclusively by
+    /// [`FStorageSynchronizer::pause_writes`].
+    write_gate: tokio::sync::RwLock<()>,
 }
 
 #[derive(Debug, Clone)]
@@ -104,6 +122,67 @@ struct VectorIndexWrite {
     updated_at: Option<DateTime<Utc>>,
 }
 
+/// Counts the entity collections of one persist written to the lake and
+/// ingested into the engine, each reported as its own phase.
+struct PersistProgress<'a> {
+    progress: &'a dyn SyncProgress,
+    total: u64,
+    written: u64,
+    ingested: u64,
+}
+
+impl<'a> PersistProgress<'a> {
+    fn new(progress: &'a dyn SyncProgress, total: u64) -> Self {
+        progress.report(SyncProgressEvent::started(
+            SyncPhase::LakeWrite,
+            Some(total),
+        ));
+        Self {
+            progress,
+            total,
+            written: 0,
+            ingested: 0,
+        }
+    }
+
+    fn written(&mut self, entity_type: &str) {
+        self.written += 1;
+        self.progress.report(SyncProgressEvent::running(
+            SyncPhase::LakeWrite,
+            self.written,
+            Some(self.total),
+            Some(entity_type.to_string()),
+        ));
+    }
+
+    fn ingested(&mut self, entity_type: &str) {
+        if self.ingested == 0 {
+            self.progress.report(SyncProgressEvent::started(
+                SyncPhase::EngineIngest,
+                Some(self.total),
+            ));
+        }
+        self.ingested += 1;
+        self.progress.report(SyncProgressEvent::running(
+            SyncPhase::EngineIngest,
+            self.ingested,
+            Some(self.total),
+            Some(entity_type.to_string()),
+        ));
+    }
+
+    fn finish(self) {
+        self.progress.report(SyncProgressEvent::finished(
+            SyncPhase::LakeWrite,
+            self.written,
+        ));
+        self.progress.report(SyncProgressEvent::finished(
+            SyncPhase::EngineIngest,
+            self.ingested,
+        ));
+    }
+}
+
 impl FStorageSynchronizer {
     pub fn new(
         catalog: Arc<Catalog>,
@@ -116,8 +195,77 @@ impl FStorageSynchronizer {
             lake,
             engine,
             fetchers: RwLock::new(HashMap::new()),
+            transforms: RwLock::new(Vec::new()),
             embedding_provider,
+            pricing: Arc::new(PricingTable::default()),
+            redactor: Arc::new(Redactor::default()),
+            write_gate: tokio::sync::RwLock::new(()),
+        }
+    }
+
+    /// Overrides the pricing table used to cost embedding usage during syncs.
+    pub fn with_pricing(mut self, pricing: Arc<PricingTable>) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Overrides the secret redaction applied to ingested text.
+    pub fn with_redactor(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// The redaction applied to ingested text, for callers that persist
+    /// sync parameters themselves.
+    pub fn redactor(&self) -> &Arc<Redactor> {
+        &self.redactor
+    }
+
+    /// Adds `transform` after those already registered.
+    pub fn register_transform(&self, transform: Arc<dyn BatchTransform>) {
+        self.transforms.write().unwrap().push(transform);
+    }
+
+    fn apply_transforms(&self, entity_type: &str, mut batch: RecordBatch) -> Result<RecordBatch> {
+        let transforms = self.transforms.read().unwrap().clone();
+        for transform in transforms {
+            batch = transform.transform(entity_type, batch)?;
         }
+        Ok(batch)
+    }
+
+    /// Waits for in-flight writes to finish and blocks new ones until the guard
+    /// is dropped, so the lake, engine and catalog can be copied as one state.
+    pub async fn pause_writes(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.write_gate.write().await
+    }
+
+    /// Upserts every current row of the lake table `table_path` into the
+    /// engine again, e.g. after the engine lost nodes the lake still holds.
+    /// Returns the rows loaded.
+    pub async fn reload_table_into_engine(&self, table_path: &str) -> Result<usize> {
+        let _writing = self.write_gate.read().await;
+        let offset = self
+            .catalog
+            .get_ingestion_offset(table_path)?
+            .ok_or_else(|| {
+                StorageError::NotFound(format!("no catalog entry for '{table_path}'"))
+            })?;
+        let batches = self
+            .lake
+            .table_sql_batches(table_path, "SELECT * FROM {{table}}")
+            .await?;
+        let mut rows = 0;
+        for batch in &batches {
+            self.update_engine_from_batch_with_meta(
+                &offset.entity_type,
+                offset.category,
+                &offset.primary_keys,
+                batch,
+            )?;
+            rows += batch.num_rows();
+        }
+        Ok(rows)
     }
 
     fn string_from_columns(
@@ -430,8 +578,23 @@ impl FStorageSynchronizer {
             batch.num_rows()
         );
 
+        self.lake
+            .engine_map()
+            .write(&self.engine.storage.graph_env, |txn| {
+                self.write_batch_to_engine(txn, entity_type, category, primary_keys, batch)
+            })
+    }
+
+    /// Upserts the rows of `batch` into the engine within `txn`.
+    fn write_batch_to_engine(
+        &self,
+        txn: &mut RwTxn<'_>,
+        entity_type: &str,
+        category: crate::fetch::EntityCategory,
+        primary_keys: &[String],
+        batch: &RecordBatch,
+    ) -> Result<()> {
         let schema = batch.schema();
-        let mut txn = self.engine.storage.graph_env.write_txn()?;
 
         match category {
             EntityCategory::Node => {
@@ -485,12 +648,12 @@ impl FStorageSynchronizer {
                         utils::id::stable_node_id_u128(entity_type, &key_values)
                     };
 
-                    if self.engine.storage.get_node(&txn, &id_u128).is_ok() {
+                    if self.engine.storage.get_node(txn, &id_u128).is_ok() {
                         let props_vec: Vec<(String, Value)> = properties.into_iter().collect();
-                        let traversal = G::new(self.engine.storage.clone(), &txn)
+                        let traversal = G::new(self.engine.storage.clone(), txn)
                             .n_from_id(&id_u128)
                             .collect_to::<Vec<_>>();
-                        G::new_mut_from(self.engine.storage.clone(), &mut txn, traversal)
+                        G::new_mut_from(self.engine.storage.clone(), txn, traversal)
                             .update(Some(props_vec))
                             .for_each(|_| {});
                         log::debug!(
@@ -507,23 +670,20 @@ impl FStorageSynchronizer {
                         };
 
                         let bytes = node.encode_node()?;
-                        self.engine
-                            .storage
-                            .nodes_db
-                            .put(&mut txn, &id_u128, &bytes)?;
+                        self.engine.storage.nodes_db.put(txn, &id_u128, &bytes)?;
 
                         if let Some(props) = &node.properties {
                             for (key, value) in props {
                                 if let Some(db) = self.engine.storage.secondary_indices.get(key) {
                                     let value_bytes = bincode::serialize(value)
                                         .map_err(|e| StorageError::SyncError(e.to_string()))?;
-                                    db.put(&mut txn, &value_bytes, &node.id)?;
+                                    db.put(txn, &value_bytes, &node.id)?;
                                 }
                             }
                             if let Some(bm25) = &self.engine.storage.bm25 {
                                 let mut data = props.flatten_bm25();
                                 data.push_str(&node.label);
-                                bm25.insert_doc(&mut txn, node.id, &data)?;
+                                bm25.insert_doc(txn, node.id, &data)?;
                             }
                         }
                         log::debug!(
@@ -586,7 +746,7 @@ impl FStorageSynchronizer {
                         Some(props_vec)
                     };
 
-                    let _ = G::new_mut(self.engine.storage.clone(), &mut txn)
+                    let _ = G::new_mut(self.engine.storage.clone(), txn)
                         .insert_v::<fn(&HVector, &RoTxn) -> bool>(
                             &embedding_vec,
                             entity_type,
@@ -655,17 +815,17 @@ impl FStorageSynchronizer {
                         }
                     };
 
-                    match self.engine.storage.get_edge(&txn, &id_u128) {
+                    match self.engine.storage.get_edge(txn, &id_u128) {
                         Ok(existing_edge) => {
                             if existing_edge.from_node != from_u128
                                 || existing_edge.to_node != to_u128
                             {
                                 self.engine
                                     .storage
-                                    .drop_edge(&mut txn, &id_u128)
+                                    .drop_edge(txn, &id_u128)
                                     .map_err(|e| StorageError::SyncError(e.to_string()))?;
                                 self.insert_edge_into_engine(
-                                    &mut txn,
+                                    txn,
                                     id_u128,
                                     entity_type,
                                     properties,
@@ -681,10 +841,10 @@ impl FStorageSynchronizer {
                             } else {
                                 let props_vec: Vec<(String, Value)> =
                                     properties.into_iter().map(|(k, v)| (k, v)).collect();
-                                let traversal = G::new(self.engine.storage.clone(), &txn)
+                                let traversal = G::new(self.engine.storage.clone(), txn)
                                     .e_from_id(&id_u128)
                                     .collect_to::<Vec<_>>();
-                                G::new_mut_from(self.engine.storage.clone(), &mut txn, traversal)
+                                G::new_mut_from(self.engine.storage.clone(), txn, traversal)
                                     .update(Some(props_vec))
                                     .for_each(|_| {});
                                 log::debug!(
@@ -697,7 +857,7 @@ impl FStorageSynchronizer {
                         }
                         Err(_) => {
                             self.insert_edge_into_engine(
-                                &mut txn,
+                                txn,
                                 id_u128,
                                 entity_type,
                                 properties,
@@ -715,7 +875,6 @@ impl FStorageSynchronizer {
             }
         }
 
-        txn.commit()?;
         Ok(())
     }
 
@@ -842,6 +1001,7 @@ impl FStorageSynchronizer {
         Ok(Some(batch))
     }
 
+    #[instrument(name = "sync.vectors", skip_all, fields(entity_type = entity_type))]
     async fn process_vector_collection(
         &self,
         _fetchable_collection: Box<dyn crate::fetch::AnyFetchable>,
@@ -849,6 +1009,7 @@ impl FStorageSynchronizer {
         entity_type: &str,
         table_name: String,
         merge_keys: Vec<String>,
+        persisted: &mut PersistProgress<'_>,
     ) -> Result<()> {
         let schema = record_batch.schema();
         let num_rows = record_batch.num_rows();
@@ -868,6 +1029,7 @@ impl FStorageSynchronizer {
                 crate::fetch::EntityCategory::Vector,
                 &merge_keys,
             )?;
+            persisted.written(entity_type);
             return Ok(());
         }
 
@@ -926,215 +1088,231 @@ impl FStorageSynchronizer {
             }
         }
 
-        let mut vector_ids: Vec<Option<String>> = Vec::with_capacity(num_rows);
-        let mut edges_by_type: HashMap<String, Vec<EdgeWrite>> = HashMap::new();
-        let mut index_updates: HashMap<String, VectorIndexWrite> = HashMap::new();
-
-        let mut txn = self.engine.storage.graph_env.write_txn()?;
-
-        for row in 0..num_rows {
-            let mut properties = HashMap::new();
-            let mut embedding: Option<Vec<f64>> = None;
-            let mut row_created_at: Option<DateTime<Utc>> = None;
-            let mut id_value: Option<String> = None;
-
-            let existing_id = id_idx.and_then(|idx| {
-                columns[idx]
-                    .as_any()
-                    .downcast_ref::<StringArray>()
-                    .and_then(|arr| {
-                        if arr.is_null(row) {
-                            None
-                        } else {
-                            Some(arr.value(row).to_string())
-                        }
-                    })
-            });
+        let (vector_ids, edges_by_type, index_updates) =
+            self.lake
+                .engine_map()
+                .write(&self.engine.storage.graph_env, |txn| {
+                    // Ids assigned by an aborted attempt must not be reused.
+                    let mut existing_index = existing_index.clone();
+                    let mut vector_ids: Vec<Option<String>> = Vec::with_capacity(num_rows);
+                    let mut edges_by_type: HashMap<String, Vec<EdgeWrite>> = HashMap::new();
+                    let mut index_updates: HashMap<String, VectorIndexWrite> = HashMap::new();
 
-            if let Some(idx) = created_at_idx {
-                if let Some(arr) = columns[idx]
-                    .as_any()
-                    .downcast_ref::<TimestampMicrosecondArray>()
-                {
-                    if !arr.is_null(row) {
-                        let value = arr.value(row);
-                        let seconds = value.div_euclid(1_000_000);
-                        let micros = (value.rem_euclid(1_000_000)) as u32;
-                        if let Some(datetime) =
-                            DateTime::<Utc>::from_timestamp(seconds, micros * 1000)
-                        {
-                            row_created_at = Some(datetime);
-                        }
-                    }
-                }
-            }
+                    for row in 0..num_rows {
+                        let mut properties = HashMap::new();
+                        let mut embedding: Option<Vec<f64>> = None;
+                        let mut row_created_at: Option<DateTime<Utc>> = None;
+                        let mut id_value: Option<String> = None;
+
+                        let existing_id = id_idx.and_then(|idx| {
+                            columns[idx]
+                                .as_any()
+                                .downcast_ref::<StringArray>()
+                                .and_then(|arr| {
+                                    if arr.is_null(row) {
+                                        None
+                                    } else {
+                                        Some(arr.value(row).to_string())
+                                    }
+                                })
+                        });
 
-            for (field, column) in schema.fields().iter().zip(columns.iter()) {
-                match field.name().as_str() {
-                    "embedding" => {
-                        let list_array = column.as_any().downcast_ref::<ListArray>();
-                        if let Some(list_array) = list_array {
-                            if !list_array.is_null(row) {
-                                let values = list_array.value(row);
-                                let float_array = values
-                                    .as_any()
-                                    .downcast_ref::<Float32Array>()
-                                    .expect("embedding list should contain f32 values");
-                                let mut vec = Vec::with_capacity(float_array.len());
-                                for idx in 0..float_array.len() {
-                                    vec.push(float_array.value(idx) as f64);
+                        if let Some(idx) = created_at_idx {
+                            if let Some(arr) = columns[idx]
+                                .as_any()
+                                .downcast_ref::<TimestampMicrosecondArray>()
+                            {
+                                if !arr.is_null(row) {
+                                    let value = arr.value(row);
+                                    let seconds = value.div_euclid(1_000_000);
+                                    let micros = (value.rem_euclid(1_000_000)) as u32;
+                                    if let Some(datetime) =
+                                        DateTime::<Utc>::from_timestamp(seconds, micros * 1000)
+                                    {
+                                        row_created_at = Some(datetime);
+                                    }
                                 }
-                                embedding = Some(vec);
                             }
                         }
-                    }
-                    "id" => {}
-                    name if vector_index_meta
-                        .as_ref()
-                        .map(|meta| meta.id_column == name)
-                        .unwrap_or(false) =>
-                    {
-                        if let Some(arr) = column.as_any().downcast_ref::<StringArray>() {
-                            if !arr.is_null(row) {
-                                let value = arr.value(row).to_string();
-                                id_value = Some(value.clone());
-                                properties.insert(field.name().clone(), Value::String(value));
+
+                        for (field, column) in schema.fields().iter().zip(columns.iter()) {
+                            match field.name().as_str() {
+                                "embedding" => {
+                                    let list_array = column.as_any().downcast_ref::<ListArray>();
+                                    if let Some(list_array) = list_array {
+                                        if !list_array.is_null(row) {
+                                            let values = list_array.value(row);
+                                            let float_array = values
+                                                .as_any()
+                                                .downcast_ref::<Float32Array>()
+                                                .expect("embedding list should contain f32 values");
+                                            let mut vec = Vec::with_capacity(float_array.len());
+                                            for idx in 0..float_array.len() {
+                                                vec.push(float_array.value(idx) as f64);
+                                            }
+                                            embedding = Some(vec);
+                                        }
+                                    }
+                                }
+                                "id" => {}
+                                name if vector_index_meta
+                                    .as_ref()
+                                    .map(|meta| meta.id_column == name)
+                                    .unwrap_or(false) =>
+                                {
+                                    if let Some(arr) = column.as_any().downcast_ref::<StringArray>()
+                                    {
+                                        if !arr.is_null(row) {
+                                            let value = arr.value(row).to_string();
+                                            id_value = Some(value.clone());
+                                            properties
+                                                .insert(field.name().clone(), Value::String(value));
+                                        }
+                                    }
+                                }
+                                _ => {
+                                    if let Some(value) =
+                                        Self::arrow_value_to_helix_value(column, row)
+                                    {
+                                        properties.insert(field.name().clone(), value);
+                                    }
+                                }
                             }
                         }
-                    }
-                    _ => {
-                        if let Some(value) = Self::arrow_value_to_helix_value(column, row) {
-                            properties.insert(field.name().clone(), value);
-                        }
-                    }
-                }
-            }
 
-            let existing_uuid = id_value
-                .as_ref()
-                .and_then(|id| existing_index.get(id).cloned());
+                        let existing_uuid = id_value
+                            .as_ref()
+                            .and_then(|id| existing_index.get(id).cloned());
 
-            let vector_uuid_final: String;
-            let vector_record_identity_final: String;
+                        let vector_uuid_final: String;
+                        let vector_record_identity_final: String;
 
-            if let Some(existing_uuid) = existing_uuid {
-                let record_identity = existing_id.clone().unwrap_or(existing_uuid.clone());
-                vector_ids.push(Some(record_identity.clone()));
-                vector_uuid_final = existing_uuid;
-                vector_record_identity_final = record_identity;
-            } else {
-                let Some(embedding_vec) = embedding else {
-                    log::warn!(
+                        if let Some(existing_uuid) = existing_uuid {
+                            let record_identity =
+                                existing_id.clone().unwrap_or(existing_uuid.clone());
+                            vector_ids.push(Some(record_identity.clone()));
+                            vector_uuid_final = existing_uuid;
+                            vector_record_identity_final = record_identity;
+                        } else {
+                            let Some(embedding_vec) = embedding else {
+                                log::warn!(
                         "Skipping vector of type '{}' at row {} due to missing embedding values",
                         entity_type,
                         row
                     );
-                    vector_ids.push(existing_id);
-                    continue;
-                };
+                                vector_ids.push(existing_id);
+                                continue;
+                            };
 
-                if embedding_vec.is_empty() {
-                    log::warn!(
+                            if embedding_vec.is_empty() {
+                                log::warn!(
                         "Skipping vector of type '{}' at row {} because embedding is empty",
                         entity_type,
                         row
                     );
-                    vector_ids.push(existing_id);
-                    continue;
-                }
-
-                let props_vec: Vec<(String, Value)> = properties.clone().into_iter().collect();
-                let fields_opt = if props_vec.is_empty() {
-                    None
-                } else {
-                    Some(props_vec)
-                };
+                                vector_ids.push(existing_id);
+                                continue;
+                            }
 
-                let traversal = G::new_mut(self.engine.storage.clone(), &mut txn)
-                    .insert_v::<fn(&HVector, &RoTxn) -> bool>(
-                        &embedding_vec,
-                        entity_type,
-                        fields_opt,
-                    )
-                    .collect_to_obj();
-                let new_uuid = traversal.uuid();
-                let record_identity = existing_id.clone().unwrap_or_else(|| new_uuid.clone());
-                vector_ids.push(Some(record_identity.clone()));
-
-                if let (Some(_), Some(id)) = (vector_index_meta.as_ref(), id_value.as_ref()) {
-                    let timestamp = row_created_at.clone().unwrap_or_else(|| {
-                        let now = Utc::now();
-                        row_created_at = Some(now);
-                        now
-                    });
-                    existing_index.insert(id.clone(), new_uuid.clone());
-                    index_updates.insert(
-                        id.clone(),
-                        VectorIndexWrite {
-                            id_value: id.clone(),
-                            vector_uuid: new_uuid.clone(),
-                            updated_at: Some(timestamp),
-                        },
-                    );
-                }
+                            let props_vec: Vec<(String, Value)> =
+                                properties.clone().into_iter().collect();
+                            let fields_opt = if props_vec.is_empty() {
+                                None
+                            } else {
+                                Some(props_vec)
+                            };
 
-                vector_uuid_final = new_uuid;
-                vector_record_identity_final = record_identity;
-            }
+                            let traversal = G::new_mut(self.engine.storage.clone(), txn)
+                                .insert_v::<fn(&HVector, &RoTxn) -> bool>(
+                                    &embedding_vec,
+                                    entity_type,
+                                    fields_opt,
+                                )
+                                .collect_to_obj();
+                            let new_uuid = traversal.uuid();
+                            let record_identity =
+                                existing_id.clone().unwrap_or_else(|| new_uuid.clone());
+                            vector_ids.push(Some(record_identity.clone()));
+
+                            if let (Some(_), Some(id)) =
+                                (vector_index_meta.as_ref(), id_value.as_ref())
+                            {
+                                let timestamp = row_created_at.clone().unwrap_or_else(|| {
+                                    let now = Utc::now();
+                                    row_created_at = Some(now);
+                                    now
+                                });
+                                existing_index.insert(id.clone(), new_uuid.clone());
+                                index_updates.insert(
+                                    id.clone(),
+                                    VectorIndexWrite {
+                                        id_value: id.clone(),
+                                        vector_uuid: new_uuid.clone(),
+                                        updated_at: Some(timestamp),
+                                    },
+                                );
+                            }
 
-            if let (Some(_), Some(id)) = (vector_index_meta.as_ref(), id_value.as_ref()) {
-                let timestamp = row_created_at.clone().unwrap_or_else(|| {
-                    let now = Utc::now();
-                    row_created_at = Some(now);
-                    now
-                });
-                existing_index.insert(id.clone(), vector_uuid_final.clone());
-                index_updates
-                    .entry(id.clone())
-                    .and_modify(|entry| {
-                        entry.vector_uuid = vector_uuid_final.clone();
-                        entry.updated_at = Some(timestamp);
-                    })
-                    .or_insert(VectorIndexWrite {
-                        id_value: id.clone(),
-                        vector_uuid: vector_uuid_final.clone(),
-                        updated_at: Some(timestamp),
-                    });
-            }
+                            vector_uuid_final = new_uuid;
+                            vector_record_identity_final = record_identity;
+                        }
 
-            if let Some(rule_set) = rules {
-                for rule in &rule_set.rules {
-                    match Self::resolve_vector_rule(
-                        rule,
-                        &columns,
-                        &column_index,
-                        row,
-                        row_created_at,
-                        &vector_uuid_final,
-                        &vector_record_identity_final,
-                    ) {
-                        Ok(Some(edge)) => {
-                            edges_by_type
-                                .entry(rule.edge_type.to_string())
-                                .or_default()
-                                .push(edge);
+                        if let (Some(_), Some(id)) = (vector_index_meta.as_ref(), id_value.as_ref())
+                        {
+                            let timestamp = row_created_at.clone().unwrap_or_else(|| {
+                                let now = Utc::now();
+                                row_created_at = Some(now);
+                                now
+                            });
+                            existing_index.insert(id.clone(), vector_uuid_final.clone());
+                            index_updates
+                                .entry(id.clone())
+                                .and_modify(|entry| {
+                                    entry.vector_uuid = vector_uuid_final.clone();
+                                    entry.updated_at = Some(timestamp);
+                                })
+                                .or_insert(VectorIndexWrite {
+                                    id_value: id.clone(),
+                                    vector_uuid: vector_uuid_final.clone(),
+                                    updated_at: Some(timestamp),
+                                });
                         }
-                        Ok(None) => {}
-                        Err(err) => {
-                            log::warn!(
+
+                        if let Some(rule_set) = rules {
+                            for rule in &rule_set.rules {
+                                match Self::resolve_vector_rule(
+                                    rule,
+                                    &columns,
+                                    &column_index,
+                                    row,
+                                    row_created_at,
+                                    &vector_uuid_final,
+                                    &vector_record_identity_final,
+                                ) {
+                                    Ok(Some(edge)) => {
+                                        edges_by_type
+                                            .entry(rule.edge_type.to_string())
+                                            .or_default()
+                                            .push(edge);
+                                    }
+                                    Ok(None) => {}
+                                    Err(err) => {
+                                        log::warn!(
                                 "Failed to materialize edge '{}' for vector '{}' row {}: {}",
                                 rule.edge_type,
                                 entity_type,
                                 row,
                                 err
                             );
+                                    }
+                                }
+                            }
                         }
                     }
-                }
-            }
-        }
 
-        txn.commit()?;
+                    Ok((vector_ids, edges_by_type, index_updates))
+                })?;
+        persisted.ingested(entity_type);
 
         if let Some(idx) = id_idx {
             let id_array = auto_fetchable::to_arrow_array(vector_ids)?;
@@ -1210,6 +1388,7 @@ impl FStorageSynchronizer {
                 &vec![meta.id_column.to_string()],
             )?;
         }
+        persisted.written(entity_type);
 
         Ok(())
     }
@@ -1262,14 +1441,372 @@ impl FStorageSynchronizer {
     }
 }
 
-#[async_trait]
-impl DataSynchronizer for FStorageSynchronizer {
-    async fn process_graph_data(&self, graph_data: GraphData) -> Result<()> {
+impl FStorageSynchronizer {
+    /// Asks the named fetcher whether it would accept a sync with `params`.
+    pub fn admit_sync(&self, fetcher_name: &str, params: &serde_json::Value) -> Result<()> {
+        let fetcher = {
+            let guard = self.fetchers.read().unwrap();
+            guard.get(fetcher_name).cloned()
+        }
+        .ok_or_else(|| StorageError::UnknownFetcher(fetcher_name.to_string()))?;
+        fetcher.admit(params)
+    }
+
+    /// Asks the named fetcher what a sync with `params` would find upstream,
+    /// without fetching it.
+    pub async fn probe_sync(
+        &self,
+        fetcher_name: &str,
+        params: serde_json::Value,
+    ) -> Result<ProbeReport> {
+        let fetcher = {
+            let guard = self.fetchers.read().unwrap();
+            guard.get(fetcher_name).cloned()
+        }
+        .ok_or_else(|| StorageError::UnknownFetcher(fetcher_name.to_string()))?;
+        fetcher.probe(params).await
+    }
+
+    /// Runs [`DataSynchronizer::sync`] while reporting each phase (fetch, embedding,
+    /// lake write, engine ingest) to `progress`. The run and its outcome are kept
+    /// in the catalog's sync history.
+    #[instrument(name = "sync", skip_all, fields(fetcher = fetcher_name, task_id = Empty))]
+    pub async fn sync_with_progress(
+        &self,
+        fetcher_name: &str,
+        params: serde_json::Value,
+        context: SyncContext,
+        budget: SyncBudget,
+        progress: Arc<dyn SyncProgress>,
+    ) -> Result<SyncReport> {
+        let task_name = format!("sync_with_{}", fetcher_name);
+        let task_id = self.catalog.create_task_log(&task_name)?;
+        Span::current().record("task_id", task_id);
+
+        let params_hash = params_digest(&params).to_string();
+        let history_id = self
+            .catalog
+            .start_sync_history(fetcher_name, &params_hash, task_id)?;
+        // A panicking fetcher fails this sync like any other error rather than
+        // leaving its history row, and the job behind it, running forever.
+        let outcome = std::panic::AssertUnwindSafe(self.run_sync(
+            fetcher_name,
+            params,
+            context,
+            budget,
+            progress,
+            task_id,
+        ))
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown cause".to_string());
+            Err(StorageError::SyncError(format!("sync panicked: {message}")))
+        });
+        let (state, rows, error) = match &outcome {
+            Ok(report) => (SyncJobState::Succeeded, report.rows.clone(), None),
+            Err(err @ StorageError::BudgetExhausted(_)) => (
+                SyncJobState::Stopped,
+                BTreeMap::new(),
+                Some(err.to_string()),
+            ),
+            Err(err) => (SyncJobState::Failed, BTreeMap::new(), Some(err.to_string())),
+        };
+        if let Err(err) =
+            self.catalog
+                .finish_sync_history(history_id, state, &rows, error.as_deref())
+        {
+            log::warn!(
+                "Failed to record the outcome of sync {}: {}",
+                history_id,
+                err
+            );
+        }
+        outcome
+    }
+
+    async fn run_sync(
+        &self,
+        fetcher_name: &str,
+        params: serde_json::Value,
+        context: SyncContext,
+        budget: SyncBudget,
+        progress: Arc<dyn SyncProgress>,
+        task_id: i64,
+    ) -> Result<SyncReport> {
+        let fetcher = {
+            let guard = self.fetchers.read().unwrap();
+            guard.get(fetcher_name).cloned()
+        }
+        .ok_or_else(|| StorageError::UnknownFetcher(fetcher_name.to_string()))?;
+        let capability = fetcher.capability();
+        let ttl_default = capability.default_ttl_secs.unwrap_or(3600);
+
+        // Targets meant for another fetcher only matter for the readiness bookkeeping below.
+        let targets: Vec<EntityIdentifier> = context
+            .target_entities
+            .iter()
+            .filter(|entity| {
+                entity
+                    .fetcher_name
+                    .as_deref()
+                    .is_none_or(|name| name == fetcher_name)
+            })
+            .cloned()
+            .collect();
+        let params = fetcher.scope_params(params, &targets)?;
+
+        // Every embedding call made on behalf of this sync is booked against its task
+        // and checked against the shared budget before it is sent.
+        let meter = Arc::new(UsageMeter::for_provider(self.embedding_provider.as_ref()));
+        let tracker = Arc::new(BudgetTracker::new(
+            budget.limits(),
+            Arc::clone(&meter),
+            Arc::clone(&self.pricing),
+        ));
+        let metered_provider: Arc<dyn EmbeddingProvider> = Arc::new(
+            MeteredEmbeddingProvider::new(self.embedding_provider.clone(), Arc::clone(&meter))
+                .with_budget(Arc::clone(&tracker)),
+        );
+        // Progress survives a stopped or crashed run; vectors restored from the
+        // checkpoint are served ahead of the meter and cost nothing.
+        if let Err(err) = SyncCheckpoint::expire_stale(&self.catalog) {
+            log::warn!("Failed to expire stale sync checkpoints: {}", err);
+        }
+        let checkpoint = Arc::new(
+            SyncCheckpoint::for_sync(Arc::clone(&self.catalog), fetcher_name, &params)
+                .with_redactor(Arc::clone(&self.redactor)),
+        );
+        let resumed = checkpoint.has_progress()?;
+        if resumed {
+            log::info!(
+                "Resuming sync with '{}' from checkpoint {}",
+                fetcher_name,
+                checkpoint.scope()
+            );
+        }
+        let checkpointed: Arc<dyn EmbeddingProvider> = Arc::new(
+            CheckpointedEmbeddingProvider::new(metered_provider, Arc::clone(&checkpoint)),
+        );
+        let embedding_progress = Arc::new(ProgressEmbeddingProvider::new(
+            checkpointed,
+            Arc::clone(&progress),
+        ));
+        // Secrets are scrubbed before text reaches the provider or the checkpoint.
+        let provider: Arc<dyn EmbeddingProvider> = Arc::new(RedactingEmbeddingProvider::new(
+            embedding_progress.clone(),
+            Arc::clone(&self.redactor),
+        ));
+
+        // The fetcher is now responsible for all transformation, including vectorization.
+        progress.report(SyncProgressEvent::started(SyncPhase::Fetch, None));
+        let fetched = match tracker.check_request() {
+            Ok(()) => {
+                tracker.charge_requests(1);
+                let fetch = fetcher
+                    .fetch_resumable(params.clone(), provider, Arc::clone(&checkpoint))
+                    .instrument(info_span!("sync.fetch"));
+                match tracker.remaining_duration() {
+                    Some(remaining) => match tokio::time::timeout(remaining, fetch).await {
+                        Ok(result) => result,
+                        Err(_) => Err(tracker.exhaust(BudgetConstraint::Duration)),
+                    },
+                    None => fetch.await,
+                }
+            }
+            Err(err) => Err(err),
+        };
+        // Everything is embedded during the fetch, so its spend is booked now,
+        // also for a fetch the budget stopped or an error ended.
+        let costs = self.record_sync_cost(task_id, &meter);
+        let response = match fetched {
+            Ok(response) => {
+                progress.report(SyncProgressEvent::finished(SyncPhase::Fetch, 1));
+                if embedding_progress.embedded() > 0 {
+                    progress.report(SyncProgressEvent::finished(
+                        SyncPhase::Embedding,
+                        embedding_progress.embedded(),
+                    ));
+                }
+                response
+            }
+            Err(err @ StorageError::BudgetExhausted(_)) => {
+                let usage = tracker.usage();
+                log::warn!(
+                    "Sync with '{}' stopped early: {} (usage: {:?})",
+                    fetcher_name,
+                    err,
+                    usage
+                );
+                self.catalog.update_task_log_status(
+                    task_id,
+                    "STOPPED",
+                    &serde_json::to_string(&usage)?,
+                )?;
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut redactions = RedactionReport::default();
+        let mut lint = LintReport::default();
+        let mut rows = BTreeMap::new();
+        match response {
+            FetchResponse::GraphData(graph_data) => {
+                (redactions, lint, rows) = self
+                    .process_graph_data_with_progress(graph_data, progress.as_ref())
+                    .await?;
+                if lint.total > 0 {
+                    log::warn!(
+                        "Data fetched by '{}' failed {} schema check(s), {} row(s) dropped: {:?}",
+                        fetcher_name,
+                        lint.total,
+                        lint.rejected_rows,
+                        lint.by_entity_type
+                    );
+                }
+                if redactions.total > 0 {
+                    log::info!(
+                        "Redacted {} secret(s) from data fetched by '{}': {:?}",
+                        redactions.total,
+                        fetcher_name,
+                        redactions.by_rule
+                    );
+                }
+            }
+            FetchResponse::PanelData { table_name, batch } => {
+                log::info!("Cold Path: Writing panel data to table '{}'", &table_name);
+                progress.report(SyncProgressEvent::started(SyncPhase::LakeWrite, Some(1)));
+                rows.insert(table_name.clone(), batch.num_rows() as u64);
+                self.lake
+                    .write_batches(&table_name, vec![batch], None)
+                    .await?;
+                progress.report(SyncProgressEvent::finished(SyncPhase::LakeWrite, 1));
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        for entity in &context.target_entities {
+            let readiness = crate::models::EntityReadiness {
+                entity_uri: entity.uri.clone(),
+                entity_type: entity.entity_type.clone(),
+                last_synced_at: Some(now),
+                ttl_seconds: Some(ttl_default),
+                coverage_metrics: "{}".to_string(),
+            };
+            self.catalog.upsert_readiness(&readiness)?;
+
+            if entity
+                .fetcher_name
+                .as_deref()
+                .map(|name| name == fetcher_name)
+                .unwrap_or(false)
+            {
+                let anchor_key = entity.anchor_key.as_deref().unwrap_or("default");
+                let probe_params = entity
+                    .params
+                    .clone()
+                    .unwrap_or_else(|| serde_json::Value::Null);
+                match fetcher.probe(probe_params).await {
+                    Ok(report) => {
+                        let anchor_value_ref = report.remote_anchor.as_deref();
+                        self.catalog.upsert_source_anchor(
+                            &entity.uri,
+                            fetcher_name,
+                            anchor_key,
+                            anchor_value_ref,
+                            now,
+                        )?;
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "Post-sync probe for entity '{}' via fetcher '{}' failed: {}",
+                            entity.uri,
+                            fetcher_name,
+                            err
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Err(err) = checkpoint.clear() {
+            log::warn!(
+                "Failed to clear checkpoint {} after sync: {}",
+                checkpoint.scope(),
+                err
+            );
+        }
+
+        self.catalog
+            .update_task_log_status(task_id, "SUCCESS", "Sync completed successfully.")?;
+
+        Ok(SyncReport {
+            task_id,
+            costs,
+            budget: tracker.usage(),
+            resumed,
+            redactions,
+            lint,
+            rows,
+        })
+    }
+
+    /// Books the provider usage of sync task `task_id` and returns it; empty
+    /// when nothing was embedded.
+    fn record_sync_cost(&self, task_id: i64, meter: &UsageMeter) -> Vec<CostSummary> {
+        if meter.requests() == 0 {
+            return Vec::new();
+        }
+        let summary = meter.summary(&self.pricing);
+        if let Err(err) =
+            self.catalog
+                .insert_cost_record("sync", Some(&task_id.to_string()), &summary)
+        {
+            log::warn!(
+                "Failed to record the cost of sync task {}: {}",
+                task_id,
+                err
+            );
+        }
+        vec![summary]
+    }
+
+    /// Persists `graph_data` like [`DataSynchronizer::process_graph_data`], reporting
+    /// lake writes and engine ingestion per entity collection. Registered
+    /// transforms run first, then rows failing [`lint::lint_batch`] are dropped
+    /// and configured text columns redacted; the returned reports count both,
+    /// followed by the rows written per entity type.
+    #[instrument(name = "sync.persist", skip_all, fields(collections = graph_data.entities.len()))]
+    pub async fn process_graph_data_with_progress(
+        &self,
+        graph_data: GraphData,
+        progress: &dyn SyncProgress,
+    ) -> Result<(RedactionReport, LintReport, BTreeMap<String, u64>)> {
+        let _writing = self.write_gate.read().await;
+        let mut persisted = PersistProgress::new(progress, graph_data.entities.len() as u64);
+        let mut redactions = RedactionReport::default();
+        let mut lint = LintReport::default();
+        let mut rows: BTreeMap<String, u64> = BTreeMap::new();
         // --- STAGE 2: Persistence - Process all entities (original and newly created) ---
         for fetchable_collection in graph_data.entities {
-            let record_batch = fetchable_collection.to_record_batch_any()?;
             let entity_type = fetchable_collection.entity_type_any();
             let category = fetchable_collection.category_any();
+            let record_batch = lint::lint_batch(
+                entity_type,
+                category,
+                &fetchable_collection.primary_keys_any(),
+                self.apply_transforms(entity_type, fetchable_collection.to_record_batch_any()?)?,
+                &mut lint,
+            )?;
+            let record_batch =
+                self.redactor
+                    .redact_batch(entity_type, record_batch, &mut redactions)?;
+            *rows.entry(entity_type.to_string()).or_default() += record_batch.num_rows() as u64;
             let table_name = match category {
                 EntityCategory::Edge => {
                     let edge_suffix = entity_type
@@ -1293,6 +1830,7 @@ impl DataSynchronizer for FStorageSynchronizer {
                     entity_type,
                     table_name,
                     merge_keys,
+                    &mut persisted,
                 )
                 .await?;
                 continue;
@@ -1342,12 +1880,38 @@ impl DataSynchronizer for FStorageSynchronizer {
                 }
             }
 
+            persisted.written(entity_type);
+
             // Hot Path: Write to Graph Engine
-            self.update_engine_from_batch(fetchable_collection, &record_batch)?;
+            info_span!(
+                "sync.engine_ingest",
+                entity_type,
+                rows = record_batch.num_rows()
+            )
+            .in_scope(|| self.update_engine_from_batch(fetchable_collection, &record_batch))?;
+            persisted.ingested(entity_type);
         }
 
-        Ok(())
+        if persisted.total > 0 {
+            // The engine writes above bumped it already.
+            let graph_version = self.catalog.get_graph_version()?;
+            let table_versions = self.lake.table_versions().await?;
+            self.catalog
+                .record_sync_run(graph_version, &table_versions)?;
+        }
+        persisted.finish();
+        Ok((redactions, lint, rows))
     }
+}
+
+#[async_trait]
+impl DataSynchronizer for FStorageSynchronizer {
+    async fn process_graph_data(&self, graph_data: GraphData) -> Result<()> {
+        self.process_graph_data_with_progress(graph_data, &NoopProgress)
+            .await
+            .map(|_| ())
+    }
+
     fn register_fetcher(&self, fetcher: Arc<dyn Fetcher>) {
         let name = fetcher.name().to_string();
         let mut guard = self.fetchers.write().unwrap();
@@ -1459,87 +2023,16 @@ impl DataSynchronizer for FStorageSynchronizer {
         fetcher_name: &str,
         params: serde_json::Value,
         context: SyncContext,
-        _budget: SyncBudget,
-    ) -> Result<()> {
-        let task_name = format!("sync_with_{}", fetcher_name);
-        let task_id = self.catalog.create_task_log(&task_name)?;
-
-        let fetcher = {
-            let guard = self.fetchers.read().unwrap();
-            guard.get(fetcher_name).cloned()
-        }
-        .ok_or_else(|| {
-            StorageError::Config(format!("Fetcher '{}' not registered.", fetcher_name))
-        })?;
-        let capability = fetcher.capability();
-        let ttl_default = capability.default_ttl_secs.unwrap_or(3600);
-
-        // The fetcher is now responsible for all transformation, including vectorization.
-        let response = fetcher
-            .fetch(params.clone(), self.embedding_provider.clone())
-            .await?;
-
-        match response {
-            FetchResponse::GraphData(graph_data) => {
-                self.process_graph_data(graph_data).await?;
-            }
-            FetchResponse::PanelData { table_name, batch } => {
-                log::info!("Cold Path: Writing panel data to table '{}'", &table_name);
-                self.lake
-                    .write_batches(&table_name, vec![batch], None)
-                    .await?;
-            }
-        }
-
-        let now = chrono::Utc::now().timestamp();
-        for entity in &context.target_entities {
-            let readiness = crate::models::EntityReadiness {
-                entity_uri: entity.uri.clone(),
-                entity_type: entity.entity_type.clone(),
-                last_synced_at: Some(now),
-                ttl_seconds: Some(ttl_default),
-                coverage_metrics: "{}".to_string(),
-            };
-            self.catalog.upsert_readiness(&readiness)?;
-
-            if entity
-                .fetcher_name
-                .as_deref()
-                .map(|name| name == fetcher_name)
-                .unwrap_or(false)
-            {
-                let anchor_key = entity.anchor_key.as_deref().unwrap_or("default");
-                let probe_params = entity
-                    .params
-                    .clone()
-                    .unwrap_or_else(|| serde_json::Value::Null);
-                match fetcher.probe(probe_params).await {
-                    Ok(report) => {
-                        let anchor_value_ref = report.remote_anchor.as_deref();
-                        self.catalog.upsert_source_anchor(
-                            &entity.uri,
-                            fetcher_name,
-                            anchor_key,
-                            anchor_value_ref,
-                            now,
-                        )?;
-                    }
-                    Err(err) => {
-                        log::warn!(
-                            "Post-sync probe for entity '{}' via fetcher '{}' failed: {}",
-                            entity.uri,
-                            fetcher_name,
-                            err
-                        );
-                    }
-                }
-            }
-        }
-
-        self.catalog
-            .update_task_log_status(task_id, "SUCCESS", "Sync completed successfully.")?;
-
-        Ok(())
+        budget: SyncBudget,
+    ) -> Result<SyncReport> {
+        self.sync_with_progress(
+            fetcher_name,
+            params,
+            context,
+            budget,
+            Arc::new(NoopProgress),
+        )
+        .await
     }
 
     async fn run_full_etl_from_lake(&self, target_repo_uri: &str) -> Result<()> {
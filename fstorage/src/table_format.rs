@@ -0,0 +1,305 @@
+//! Table formats the lake keeps its silver and gold tables in.
+//!
+//! [`Lake`](crate::lake::Lake) queries tables as DataFusion sources and leaves
+//! storing them, their history and their maintenance to a [`TableStore`].
+//! Delta tables under the lake directory, or in an object store configured as
+//! `StorageConfig::lake_store`, are the default. Deployments
+//! standardized on Apache Iceberg can keep the tables in an Iceberg REST
+//! catalog instead, with the `iceberg` feature. Snapshots copy the lake
+//! directory and foreign tables are Delta tables, so both stay Delta-only.
+
+use crate::errors::{Result, StorageError};
+use crate::models::{LogRepair, MaintenanceOptions, TableMaintenance};
+use async_trait::async_trait;
+use deltalake::arrow::record_batch::RecordBatch;
+use deltalake::datafusion::datasource::TableProvider;
+use deltalake::datafusion::execution::context::{SessionConfig, SessionContext};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) mod delta;
+#[cfg(feature = "iceberg")]
+mod iceberg;
+
+pub use delta::DeltaTables;
+#[cfg(feature = "iceberg")]
+pub use iceberg::IcebergTables;
+
+/// Where the lake's tables live.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum TableFormat {
+    /// Delta tables under `StorageConfig::lake_path`, or in
+    /// `StorageConfig::lake_store` when one is set.
+    #[default]
+    Delta,
+    /// Iceberg tables in a REST catalog; needs the `iceberg` feature.
+    Iceberg(IcebergConfig),
+}
+
+/// An Iceberg REST catalog and the namespace fagent keeps its tables in.
+///
+/// A lake path becomes a table name by replacing `/` with `__`, so
+/// `silver/entities/project` is `<namespace>.silver__entities__project`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct IcebergConfig {
+    /// Base URI of the REST catalog, e.g. `http://localhost:8181`.
+    pub catalog_uri: String,
+    /// Warehouse to ask the catalog for, when it serves several.
+    #[serde(default)]
+    pub warehouse: Option<String>,
+    #[serde(default = "IcebergConfig::default_namespace")]
+    pub namespace: String,
+    /// Further catalog and file IO properties, e.g. S3 credentials.
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+impl IcebergConfig {
+    pub fn new(catalog_uri: impl Into<String>) -> Self {
+        Self {
+            catalog_uri: catalog_uri.into(),
+            warehouse: None,
+            namespace: Self::default_namespace(),
+            properties: HashMap::new(),
+        }
+    }
+
+    fn default_namespace() -> String {
+        "fagent".to_string()
+    }
+}
+
+/// A table's current state, readable through DataFusion.
+pub struct OpenedTable {
+    pub provider: Arc<dyn TableProvider>,
+    /// Increases with every write; -1 before the first one.
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    Append,
+    /// Replaces every row; creates the table when missing.
+    Overwrite,
+}
+
+/// Storage of the lake's tables, addressed by lake path such as
+/// `silver/entities/project`.
+#[async_trait]
+pub trait TableStore: Send + Sync {
+    /// `None` when there is no such table.
+    async fn open(&self, table: &str) -> Result<Option<OpenedTable>>;
+
+    /// Latest version of `table`, `None` when there is no such table.
+    async fn latest_version(&self, table: &str) -> Result<Option<i64>> {
+        Ok(self.open(table).await?.map(|opened| opened.version))
+    }
+
+    /// The table as it was at `version`.
+    async fn open_at(&self, table: &str, version: i64) -> Result<Arc<dyn TableProvider>>;
+
+    /// Writes `batches`, creating the table with their schema when missing.
+    async fn write(&self, table: &str, batches: Vec<RecordBatch>, mode: WriteMode) -> Result<()>;
+
+    /// Rows added by each version after `since`, oldest first, and the latest
+    /// version. A rewrite of the table counts all its rows as added.
+    async fn changes_since(
+        &self,
+        table: &str,
+        since: i64,
+    ) -> Result<(Vec<(i64, Vec<RecordBatch>)>, i64)>;
+
+    /// Paths of the tables under `prefix`, or of all of them when it is empty.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Removes the rows matching the SQL `predicate`; returns how many.
+    async fn delete_where(&self, table: &str, predicate: &str) -> Result<usize>;
+
+    /// Sets each column of `assignments` to its SQL expression, evaluated on
+    /// the old row, in the rows matching `predicate`; returns how many rows
+    /// matched.
+    async fn update_where(
+        &self,
+        table: &str,
+        predicate: &str,
+        assignments: &[(&str, String)],
+    ) -> Result<usize>;
+
+    /// Sets the string `columns` to null in the rows matching `predicate`;
+    /// returns how many rows matched.
+    async fn clear_columns_where(
+        &self,
+        table: &str,
+        predicate: &str,
+        columns: &[&str],
+    ) -> Result<usize> {
+        let assignments: Vec<(&str, String)> = columns
+            .iter()
+            .map(|column| (*column, "CAST(NULL AS VARCHAR)".to_string()))
+            .collect();
+        self.update_where(table, predicate, &assignments).await
+    }
+
+    /// Drops data files that only earlier versions refer to, so deleted rows
+    /// are gone from storage and not just from the latest version.
+    async fn purge_history(&self, table: &str) -> Result<()>;
+
+    /// Moves malformed entries out of the table's log and writes a fresh
+    /// checkpoint, so a table that stopped opening opens again.
+    async fn repair_log(&self, table: &str) -> Result<LogRepair>;
+
+    /// Compacts small data files of `table` and deletes files that only
+    /// versions past the retention period refer to. A dry run changes nothing
+    /// and reports what would be done.
+    async fn maintain(&self, table: &str, options: &MaintenanceOptions)
+        -> Result<TableMaintenance>;
+}
+
+/// The rows of `table` a rewrite keeps, for formats without row-level deletes
+/// and updates: `select` over `table`, which is registered as `t`.
+pub(crate) async fn select_from(
+    table: Arc<dyn TableProvider>,
+    select: &str,
+) -> Result<Vec<RecordBatch>> {
+    let ctx = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
+    ctx.register_table("t", table)
+        .map_err(|e| StorageError::Other(e.into()))?;
+    ctx.sql(select)
+        .await
+        .map_err(|e| StorageError::Other(e.into()))?
+        .collect()
+        .await
+        .map_err(|e| StorageError::Other(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deltalake::arrow::array::{Int64Array, StringArray};
+    use deltalake::arrow::datatypes::{DataType, Field, Schema};
+
+    fn rows(ids: &[i64], names: &[&str]) -> Vec<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        vec![RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(names.to_vec())),
+            ],
+        )
+        .unwrap()]
+    }
+
+    #[test]
+    fn delta_is_the_default_format() {
+        let format: TableFormat = serde_json::from_str(r#"{"kind": "delta"}"#).unwrap();
+        assert_eq!(format, TableFormat::default());
+        let format: TableFormat =
+            serde_json::from_str(r#"{"kind": "iceberg", "catalog_uri": "http://localhost:8181"}"#)
+                .unwrap();
+        assert_eq!(
+            format,
+            TableFormat::Iceberg(IcebergConfig::new("http://localhost:8181"))
+        );
+    }
+
+    #[tokio::test]
+    async fn delta_tables_track_versions_and_rewrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let tables = DeltaTables::local(dir.path()).unwrap();
+        let table = "silver/entities/person";
+        assert!(tables.open(table).await.unwrap().is_none());
+
+        tables
+            .write(table, rows(&[1, 2], &["ada", "bob"]), WriteMode::Overwrite)
+            .await
+            .unwrap();
+        tables
+            .write(table, rows(&[3], &["cy"]), WriteMode::Append)
+            .await
+            .unwrap();
+        assert_eq!(tables.open(table).await.unwrap().unwrap().version, 1);
+        assert_eq!(tables.list("silver").await.unwrap(), vec![table]);
+
+        let (changes, latest) = tables.changes_since(table, 0).await.unwrap();
+        assert_eq!(latest, 1);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].1[0].num_rows(), 1);
+
+        assert_eq!(tables.delete_where(table, "id = 2").await.unwrap(), 1);
+        assert_eq!(
+            tables
+                .clear_columns_where(table, "id = 1", &["name"])
+                .await
+                .unwrap(),
+            1
+        );
+        let current = tables.open(table).await.unwrap().unwrap();
+        let left = select_from(current.provider, "SELECT id FROM t WHERE name IS NOT NULL")
+            .await
+            .unwrap();
+        let ids: Vec<i64> = left
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap();
+                ids.values().to_vec()
+            })
+            .collect();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn malformed_log_lines_are_skipped_then_repaired() {
+        let dir = tempfile::tempdir().unwrap();
+        let tables = DeltaTables::local(dir.path()).unwrap();
+        let table = "silver/entities/person";
+        tables
+            .write(table, rows(&[1], &["ada"]), WriteMode::Overwrite)
+            .await
+            .unwrap();
+        tables
+            .write(table, rows(&[2], &["bob"]), WriteMode::Append)
+            .await
+            .unwrap();
+        let commit = dir
+            .path()
+            .join(table)
+            .join("_delta_log/00000000000000000001.json");
+        let mut log = std::fs::read(&commit).unwrap();
+        log.extend_from_slice(b"\n{\"add\": {\"path\": \"part-trunc\n");
+        std::fs::write(&commit, log).unwrap();
+
+        let err = tables.open(table).await.err().expect("damaged log");
+        assert!(
+            matches!(&err, StorageError::CorruptLog { table: t, .. } if t == table),
+            "{err}"
+        );
+        assert!(err.to_string().contains("fagent repair-table"));
+
+        let (changes, latest) = tables.changes_since(table, -1).await.unwrap();
+        assert_eq!(latest, 1);
+        assert_eq!(changes.len(), 2);
+        let quarantined = dir
+            .path()
+            .join("_quarantine")
+            .join(table)
+            .join("00000000000000000001.jsonl");
+        assert!(quarantined.exists());
+
+        let repair = tables.repair_log(table).await.unwrap();
+        assert_eq!(repair.repaired_versions, vec![1]);
+        assert_eq!(repair.quarantined_lines, 1);
+        assert_eq!(repair.checkpoint_version, 1);
+        assert_eq!(tables.open(table).await.unwrap().unwrap().version, 1);
+        assert_eq!(tables.list("").await.unwrap(), vec![table]);
+    }
+}
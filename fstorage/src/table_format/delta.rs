@@ -0,0 +1,585 @@
+//! Delta tables in directories under the lake root, which is a local
+//! directory or an object store prefix; see [`crate::lake_store`].
+//!
+//! A malformed line in a table's log, say from a crash halfway through a
+//! commit, makes the table fail to open. Changes are read from the commit files
+//! directly, so sync skips such a line instead of stopping at it, and saves it
+//! under `_quarantine/<table>/` in the lake root. [`TableStore::repair_log`]
+//! then removes the lines from the log for good and rebuilds the checkpoint.
+
+use super::{OpenedTable, TableStore, WriteMode};
+use crate::errors::{Result, StorageError};
+use crate::lake_store::LakeStore;
+use crate::models::{LogRepair, MaintenanceOptions, TableMaintenance};
+use async_trait::async_trait;
+use deltalake::arrow::record_batch::RecordBatch;
+use deltalake::datafusion::datasource::TableProvider;
+use deltalake::kernel::Action;
+use deltalake::operations::DeltaOps;
+use deltalake::protocol::SaveMode;
+use deltalake::{DeltaTable, ObjectStore, Path};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use std::sync::Arc;
+use url::Url;
+
+pub(crate) const DELTA_LOG_DIR: &str = "_delta_log";
+/// Directory under the lake root that malformed log lines are saved to.
+const QUARANTINE_DIR: &str = "_quarantine";
+/// Keys of the log actions the Delta protocol defines. A line that is a JSON
+/// object with none of them is an action from a newer protocol and kept.
+const KNOWN_ACTIONS: &[&str] = &[
+    "add",
+    "remove",
+    "metaData",
+    "protocol",
+    "commitInfo",
+    "txn",
+    "cdc",
+    "domainMetadata",
+];
+
+pub struct DeltaTables {
+    store: Arc<LakeStore>,
+}
+
+impl DeltaTables {
+    pub fn new(store: Arc<LakeStore>) -> Self {
+        Self { store }
+    }
+
+    /// Tables in the directory `root`, created if missing.
+    pub fn local(root: impl AsRef<std::path::Path>) -> Result<Self> {
+        Ok(Self::new(Arc::new(LakeStore::local(root.as_ref())?)))
+    }
+
+    /// `None` when `table` has no Delta log yet.
+    async fn open_delta(&self, table: &str) -> Result<Option<DeltaTable>> {
+        match self.store.open_table(table).await {
+            Err(err) => Err(self.explain_open_error(table, err).await),
+            opened => opened,
+        }
+    }
+
+    /// `err` as [`StorageError::CorruptLog`] when the log of `table` has a
+    /// malformed line, the usual reason a table stops opening.
+    async fn explain_open_error(&self, table: &str, err: StorageError) -> StorageError {
+        let Ok(versions) = self.commit_versions(table).await else {
+            return err;
+        };
+        for version in versions {
+            let Ok(Some(bytes)) = self.store.read(&commit_path(table, version)).await else {
+                continue;
+            };
+            if let Some(line) = split_commit(&bytes).1.first() {
+                return StorageError::CorruptLog {
+                    table: table.to_string(),
+                    detail: format!("version {version}, line {}: {}", line.line, line.error),
+                };
+            }
+        }
+        err
+    }
+
+    /// Versions of the commit files in the log of `table`, ascending; empty
+    /// when there is no log.
+    async fn commit_versions(&self, table: &str) -> Result<Vec<i64>> {
+        let (files, _) = self.store.list_dir(&log_dir(table)).await?;
+        let mut versions: Vec<i64> = files
+            .iter()
+            .filter_map(|file_name| {
+                log_version(file_name).filter(|version| *file_name == commit_file_name(*version))
+            })
+            .collect();
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    /// Saves the malformed `lines` of `version` of `table` to its quarantine
+    /// directory, replacing an earlier save of the same version. Returns where
+    /// they went.
+    async fn quarantine(
+        &self,
+        table: &str,
+        version: i64,
+        lines: &[MalformedLine],
+    ) -> Result<String> {
+        let path = format!("{}/{version:020}.jsonl", quarantine_dir(table));
+        let mut contents = Vec::new();
+        for line in lines {
+            serde_json::to_writer(
+                &mut contents,
+                &json!({"line": line.line, "error": line.error, "text": line.text}),
+            )?;
+            contents.push(b'\n');
+        }
+        self.store.write(&path, contents).await?;
+        Ok(self.store.describe(&path))
+    }
+}
+
+/// A line of a commit file that is not a well-formed action.
+struct MalformedLine {
+    /// 1-based.
+    line: usize,
+    text: String,
+    error: String,
+}
+
+/// The lines of a commit file that hold actions, parsed when this version of
+/// Delta knows them, and the lines that are malformed.
+fn split_commit(bytes: &[u8]) -> (Vec<(&[u8], Option<Action>)>, Vec<MalformedLine>) {
+    let mut actions = Vec::new();
+    let mut malformed = Vec::new();
+    for (index, line) in bytes.split(|b| *b == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let error = match serde_json::from_slice::<Action>(line) {
+            Ok(action) => {
+                actions.push((line, Some(action)));
+                continue;
+            }
+            Err(err) => err,
+        };
+        let unknown_action = serde_json::from_slice::<JsonValue>(line)
+            .ok()
+            .and_then(|value| value.as_object().cloned())
+            .is_some_and(|object| {
+                !object
+                    .keys()
+                    .any(|key| KNOWN_ACTIONS.contains(&key.as_str()))
+            });
+        if unknown_action {
+            actions.push((line, None));
+        } else {
+            malformed.push(MalformedLine {
+                line: index + 1,
+                text: String::from_utf8_lossy(line).into_owned(),
+                error: error.to_string(),
+            });
+        }
+    }
+    (actions, malformed)
+}
+
+fn commit_file_name(version: i64) -> String {
+    format!("{version:020}.json")
+}
+
+fn log_dir(table: &str) -> String {
+    format!("{table}/{DELTA_LOG_DIR}")
+}
+
+fn commit_path(table: &str, version: i64) -> String {
+    format!("{}/{}", log_dir(table), commit_file_name(version))
+}
+
+fn quarantine_dir(table: &str) -> String {
+    format!("{QUARANTINE_DIR}/{table}")
+}
+
+/// Version encoded in a Delta log file name (`00000000000000000007.json`,
+/// `00000000000000000010.checkpoint.parquet`, ...).
+pub(crate) fn log_version(file_name: &str) -> Option<i64> {
+    let (prefix, _) = file_name.split_once('.')?;
+    if prefix.len() != 20 {
+        return None;
+    }
+    prefix.parse().ok()
+}
+
+/// File URL of the directory `path`, created if missing.
+pub(crate) fn dir_url(path: &std::path::Path) -> Result<Url> {
+    // Use absolute path instead of canonicalize to avoid errors when path doesn't exist yet
+    let absolute_path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| StorageError::Io(e))?
+            .join(path)
+    };
+
+    // Ensure the path and its parent directories exist
+    std::fs::create_dir_all(&absolute_path).map_err(|e| StorageError::Io(e))?;
+
+    Url::from_file_path(absolute_path)
+        .map_err(|_| StorageError::Config(format!("Invalid path: {:?}", path)))
+}
+
+/// Local paths of the data files in the current version of the Delta table
+/// at `path`; `None` when there is no table there.
+pub(crate) async fn data_files(path: &std::path::Path) -> Result<Option<Vec<PathBuf>>> {
+    if tokio::fs::metadata(path).await.is_err() {
+        return Ok(None);
+    }
+    let Some(table) = open_url(dir_url(path)?).await? else {
+        return Ok(None);
+    };
+    let files = table
+        .get_file_uris()?
+        .map(|uri| {
+            Url::parse(&uri)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .unwrap_or_else(|| PathBuf::from(uri))
+        })
+        .collect();
+    Ok(Some(files))
+}
+
+/// Opens the Delta table at `uri`, `None` when there is none.
+pub(crate) async fn open_url(uri: Url) -> Result<Option<DeltaTable>> {
+    match deltalake::open_table(uri).await {
+        Ok(table) => Ok(Some(table)),
+        Err(deltalake::DeltaTableError::NotATable(_)) => Ok(None),
+        Err(e) => Err(StorageError::from(e)),
+    }
+}
+
+impl From<DeltaTable> for OpenedTable {
+    fn from(table: DeltaTable) -> Self {
+        Self {
+            version: table.version().unwrap_or(-1),
+            provider: Arc::new(table),
+        }
+    }
+}
+
+#[async_trait]
+impl TableStore for DeltaTables {
+    async fn open(&self, table: &str) -> Result<Option<OpenedTable>> {
+        Ok(self.open_delta(table).await?.map(OpenedTable::from))
+    }
+
+    async fn latest_version(&self, table: &str) -> Result<Option<i64>> {
+        // Listing the log is enough; opening the table would replay it.
+        Ok(self.commit_versions(table).await?.last().copied())
+    }
+
+    async fn open_at(&self, table: &str, version: i64) -> Result<Arc<dyn TableProvider>> {
+        Ok(Arc::new(
+            self.store
+                .table_builder(table)?
+                .with_version(version)
+                .load()
+                .await?,
+        ))
+    }
+
+    async fn write(&self, table: &str, batches: Vec<RecordBatch>, mode: WriteMode) -> Result<()> {
+        let (delta, exists) = match self.open_delta(table).await? {
+            Some(delta) => (delta, true),
+            None => (self.store.table_builder(table)?.build()?, false),
+        };
+        let write = DeltaOps(delta).write(batches);
+        match (mode, exists) {
+            (WriteMode::Append, true) => write.await?,
+            (WriteMode::Overwrite, true) => write.with_save_mode(SaveMode::Overwrite).await?,
+            (_, false) => {
+                write
+                    .with_save_mode(SaveMode::Overwrite)
+                    .with_table_name(table.replace('/', "_"))
+                    .await?
+            }
+        };
+        Ok(())
+    }
+
+    async fn changes_since(
+        &self,
+        table: &str,
+        since: i64,
+    ) -> Result<(Vec<(i64, Vec<RecordBatch>)>, i64)> {
+        let versions = self.commit_versions(table).await?;
+        let latest_version = versions.last().copied().unwrap_or(-1);
+        if latest_version <= since {
+            return Ok((Vec::new(), latest_version));
+        }
+
+        // The commit files are read one by one instead of through a loaded
+        // table, so a malformed line loses only itself.
+        let object_store = self.store.table_builder(table)?.build()?.object_store();
+        let mut changes = Vec::new();
+        for version in versions.into_iter().filter(|version| *version > since) {
+            let bytes = self
+                .store
+                .read(&commit_path(table, version))
+                .await?
+                .ok_or_else(|| {
+                    StorageError::NotFound(format!(
+                        "version {version} of table '{table}' left its log"
+                    ))
+                })?;
+            let (actions, malformed) = split_commit(&bytes);
+            if !malformed.is_empty() {
+                let saved = self.quarantine(table, version, &malformed).await?;
+                log::warn!(
+                    "Skipped {} malformed line(s) in version {} of the Delta log of '{}' and \
+                     saved them to {}; run `fagent repair-table {}` before the table is read again",
+                    malformed.len(),
+                    version,
+                    table,
+                    saved,
+                    table
+                );
+            }
+            let mut version_batches = Vec::new();
+            for (_, action) in actions {
+                if let Some(Action::Add(add)) = action {
+                    let batches = read_parquet_batches(object_store.clone(), &add.path).await?;
+                    version_batches.extend(batches);
+                }
+            }
+            if !version_batches.is_empty() {
+                changes.push((version, version_batches));
+            }
+        }
+
+        Ok((changes, latest_version))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut tables = Vec::new();
+        let mut stack = vec![prefix.trim_matches('/').to_string()];
+        while let Some(current) = stack.pop() {
+            let (_, dirs) = self.store.list_dir(&current).await?;
+            for dir in dirs {
+                if dir == DELTA_LOG_DIR {
+                    tables.push(current.clone());
+                } else if current.is_empty() {
+                    stack.push(dir);
+                } else {
+                    stack.push(format!("{current}/{dir}"));
+                }
+            }
+        }
+        tables.sort();
+        Ok(tables)
+    }
+
+    async fn delete_where(&self, table: &str, predicate: &str) -> Result<usize> {
+        let Some(table) = self.open_delta(table).await? else {
+            return Ok(0);
+        };
+        let (_, metrics) = DeltaOps(table)
+            .delete()
+            .with_predicate(predicate.to_string())
+            .await?;
+        Ok(metrics.num_deleted_rows)
+    }
+
+    async fn update_where(
+        &self,
+        table: &str,
+        predicate: &str,
+        assignments: &[(&str, String)],
+    ) -> Result<usize> {
+        let Some(table) = self.open_delta(table).await? else {
+            return Ok(0);
+        };
+        let mut update = DeltaOps(table)
+            .update()
+            .with_predicate(predicate.to_string());
+        for (column, value) in assignments {
+            update = update.with_update(*column, value.as_str());
+        }
+        let (_, metrics) = update.await?;
+        Ok(metrics.num_updated_rows)
+    }
+
+    async fn purge_history(&self, table: &str) -> Result<()> {
+        let Some(table) = self.open_delta(table).await? else {
+            return Ok(());
+        };
+        DeltaOps(table)
+            .vacuum()
+            .with_retention_period(chrono::Duration::zero())
+            .with_enforce_retention_duration(false)
+            .await?;
+        Ok(())
+    }
+
+    async fn repair_log(&self, table: &str) -> Result<LogRepair> {
+        let versions = self.commit_versions(table).await?;
+        if versions.is_empty() {
+            return Err(StorageError::NotFound(format!(
+                "table '{table}' has no Delta log"
+            )));
+        }
+        let quarantine_dir = quarantine_dir(table);
+        let mut repair = LogRepair {
+            table: table.to_string(),
+            repaired_versions: Vec::new(),
+            quarantined_lines: 0,
+            quarantine_dir: None,
+            checkpoint_version: -1,
+        };
+
+        for &version in &versions {
+            let commit = commit_path(table, version);
+            let Some(bytes) = self.store.read(&commit).await? else {
+                continue;
+            };
+            let (actions, malformed) = split_commit(&bytes);
+            if malformed.is_empty() {
+                continue;
+            }
+            self.quarantine(table, version, &malformed).await?;
+            let mut kept = Vec::new();
+            for (line, _) in &actions {
+                kept.extend_from_slice(line);
+                kept.push(b'\n');
+            }
+            if actions.is_empty() {
+                // Versions have to stay contiguous, so an emptied commit
+                // records the repair instead.
+                serde_json::to_writer(
+                    &mut kept,
+                    &json!({"commitInfo": {
+                        "timestamp": chrono::Utc::now().timestamp_millis(),
+                        "operation": "REPAIR",
+                    }}),
+                )?;
+                kept.push(b'\n');
+            }
+            // The store swaps the whole commit in at once, so a crash leaves
+            // the old version or the repaired one.
+            self.store.write(&commit, kept).await?;
+            repair.repaired_versions.push(version);
+            repair.quarantined_lines += malformed.len();
+            repair.quarantine_dir = Some(self.store.describe(&quarantine_dir));
+        }
+
+        // With every commit still in the log, the checkpoints are redundant and
+        // may be what is damaged, so they are set aside and rebuilt from the
+        // commits. Otherwise the commits before the latest checkpoint are gone
+        // and it has to be trusted.
+        let complete =
+            versions.first() == Some(&0) && versions.windows(2).all(|pair| pair[1] == pair[0] + 1);
+        if complete {
+            let (files, _) = self.store.list_dir(&log_dir(table)).await?;
+            for file_name in files {
+                if file_name == "_last_checkpoint" || file_name.contains(".checkpoint.") {
+                    self.store
+                        .rename(
+                            &format!("{}/{file_name}", log_dir(table)),
+                            &format!("{quarantine_dir}/{file_name}"),
+                        )
+                        .await?;
+                }
+            }
+        }
+
+        let repaired = self
+            .open_delta(table)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("table '{table}' not found")))?;
+        deltalake::checkpoints::create_checkpoint(&repaired, None)
+            .await
+            .map_err(|e| StorageError::Other(e.into()))?;
+        repair.checkpoint_version = repaired.version().unwrap_or(-1);
+        Ok(repair)
+    }
+
+    async fn maintain(
+        &self,
+        table: &str,
+        options: &MaintenanceOptions,
+    ) -> Result<TableMaintenance> {
+        let mut report = TableMaintenance {
+            table_path: table.to_string(),
+            ..TableMaintenance::default()
+        };
+        let Some(delta) = self.open_delta(table).await? else {
+            return Ok(report);
+        };
+        for uri in delta.get_file_uris()? {
+            let Some(file) = self.store.lake_path_of(&uri) else {
+                continue;
+            };
+            let small = self
+                .store
+                .size(&file)
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|len| len < options.target_file_size);
+            if small {
+                report.small_files += 1;
+            }
+        }
+
+        // A single small file has nothing to be merged with.
+        let delta = if options.dry_run || report.small_files < 2 {
+            delta
+        } else {
+            let (delta, metrics) = DeltaOps(delta)
+                .optimize()
+                .with_target_size(options.target_file_size)
+                .await?;
+            report.compacted_files = metrics.num_files_removed;
+            report.written_files = metrics.num_files_added;
+            delta
+        };
+
+        // Sizes are taken from a dry run first, since the files are gone after
+        // the real one.
+        let retention = chrono::Duration::hours(options.retention_hours as i64);
+        let (delta, candidates) = DeltaOps(delta)
+            .vacuum()
+            .with_retention_period(retention)
+            .with_enforce_retention_duration(false)
+            .with_dry_run(true)
+            .await?;
+        let mut sizes = std::collections::HashMap::new();
+        for file in candidates.files_deleted {
+            let len = self
+                .store
+                .size(&format!("{table}/{file}"))
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or(0);
+            sizes.insert(file, len);
+        }
+        let deleted: Vec<String> = if options.dry_run || sizes.is_empty() {
+            sizes.keys().cloned().collect()
+        } else {
+            let (_, metrics) = DeltaOps(delta)
+                .vacuum()
+                .with_retention_period(retention)
+                .with_enforce_retention_duration(false)
+                .await?;
+            metrics.files_deleted
+        };
+        report.vacuumed_files = deleted.len() as u64;
+        report.vacuumed_bytes = deleted.iter().filter_map(|file| sizes.get(file)).sum();
+        if !options.dry_run {
+            report.vacuumed_paths = deleted
+                .iter()
+                .map(|file| format!("{table}/{file}"))
+                .collect();
+        }
+        Ok(report)
+    }
+}
+
+async fn read_parquet_batches(
+    object_store: Arc<dyn ObjectStore>,
+    path: &str,
+) -> Result<Vec<RecordBatch>> {
+    let object_path = Path::from(path);
+    let meta = object_store.get(&object_path).await?.bytes().await?;
+    let reader = deltalake::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
+        meta.clone(),
+    )
+    .map_err(|e| StorageError::Other(e.into()))?
+    .build()
+    .map_err(|e| StorageError::Other(e.into()))?;
+    let mut batches = Vec::new();
+    for batch in reader {
+        batches.push(batch.map_err(|e| StorageError::Other(e.into()))?);
+    }
+    Ok(batches)
+}
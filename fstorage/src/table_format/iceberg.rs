@@ -0,0 +1,1079 @@
+//! Iceberg tables in a REST catalog.
+//!
+//! Data files are Parquet written and read with the same arrow stack as the
+//! Delta tables, and appends are registered with the catalog through
+//! `iceberg`'s fast append. Files are written without Parquet field ids, so
+//! tables carry a `schema.name-mapping.default` for other engines to resolve
+//! columns by name.
+//!
+//! iceberg-rust has no overwrite action yet, so a rewrite (an upsert merge, a
+//! privacy erasure) writes the manifest and manifest list of its snapshot
+//! itself and commits them in one catalog transaction: the new snapshot
+//! refers only to the rewritten data file, and the commit fails if another
+//! writer moved the main branch since the rows were read. Earlier snapshots
+//! stay readable until [`purge_history`](TableStore::purge_history) or
+//! maintenance expires them and deletes the files only they refer to. Each
+//! snapshot records its version in the `fagent.version` summary property.
+//!
+//! Reads plan the data files of a snapshot when a query scans it and stream
+//! them one file at a time, decoding only the projected columns; there is no
+//! predicate pushdown into the files.
+
+use super::{select_from, IcebergConfig, OpenedTable, TableStore, WriteMode};
+use crate::errors::{Result, StorageError};
+use crate::models::{LogRepair, MaintenanceOptions, TableMaintenance};
+use async_trait::async_trait;
+use bytes::Bytes;
+use deltalake::arrow::array::new_null_array;
+use deltalake::arrow::compute::cast;
+use deltalake::arrow::datatypes::{DataType, Field, Schema as ArrowSchema, SchemaRef, TimeUnit};
+use deltalake::arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use deltalake::datafusion::catalog::Session;
+use deltalake::datafusion::datasource::TableProvider;
+use deltalake::datafusion::error::{DataFusionError, Result as DataFusionResult};
+use deltalake::datafusion::execution::TaskContext;
+use deltalake::datafusion::logical_expr::{Expr, TableType};
+use deltalake::datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use deltalake::datafusion::physical_plan::streaming::{PartitionStream, StreamingTableExec};
+use deltalake::datafusion::physical_plan::{ExecutionPlan, SendableRecordBatchStream};
+use deltalake::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use deltalake::parquet::arrow::{ArrowWriter, ProjectionMask};
+use futures::{StreamExt, TryStreamExt};
+use iceberg::spec::{
+    DataContentType, DataFile, DataFileBuilder, DataFileFormat, ListType, ManifestListWriter,
+    ManifestStatus, ManifestWriterBuilder, NestedField, Operation, PrimitiveType, Schema, Snapshot,
+    SnapshotRef, SnapshotReference, SnapshotRetention, Struct, Summary, TableMetadata, Type,
+    MAIN_BRANCH,
+};
+use iceberg::table::Table;
+use iceberg::transaction::{ActionCommit, ApplyTransactionAction, Transaction, TransactionAction};
+use iceberg::{Catalog, NamespaceIdent, TableCreation, TableIdent, TableRequirement, TableUpdate};
+use iceberg_catalog_rest::{RestCatalog, RestCatalogConfig};
+use serde_json::json;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const VERSION_PROPERTY: &str = "fagent.version";
+const NAME_MAPPING_PROPERTY: &str = "schema.name-mapping.default";
+
+pub struct IcebergTables {
+    catalog: RestCatalog,
+    namespace: NamespaceIdent,
+}
+
+fn iceberg_error(err: iceberg::Error) -> StorageError {
+    StorageError::Other(err.into())
+}
+
+impl IcebergTables {
+    /// Connects to the catalog and creates the namespace if it is missing.
+    pub async fn connect(config: &IcebergConfig) -> Result<Self> {
+        let catalog = RestCatalog::new(
+            RestCatalogConfig::builder()
+                .uri(config.catalog_uri.clone())
+                .warehouse_opt(config.warehouse.clone())
+                .props(config.properties.clone())
+                .build(),
+        );
+        let namespace = NamespaceIdent::new(config.namespace.clone());
+        if !catalog
+            .namespace_exists(&namespace)
+            .await
+            .map_err(iceberg_error)?
+        {
+            catalog
+                .create_namespace(&namespace, HashMap::new())
+                .await
+                .map_err(iceberg_error)?;
+        }
+        Ok(Self { catalog, namespace })
+    }
+
+    fn ident(&self, table: &str) -> TableIdent {
+        TableIdent::new(self.namespace.clone(), table.replace('/', "__"))
+    }
+
+    async fn load(&self, table: &str) -> Result<Option<Table>> {
+        let ident = self.ident(table);
+        if !self
+            .catalog
+            .table_exists(&ident)
+            .await
+            .map_err(iceberg_error)?
+        {
+            return Ok(None);
+        }
+        self.catalog
+            .load_table(&ident)
+            .await
+            .map(Some)
+            .map_err(iceberg_error)
+    }
+
+    async fn create(&self, table: &str, schema: &ArrowSchema) -> Result<Table> {
+        let (schema, name_mapping) = iceberg_schema(schema, None)?;
+        let creation = TableCreation::builder()
+            .name(self.ident(table).name().to_string())
+            .schema(schema)
+            .properties(HashMap::from([(
+                NAME_MAPPING_PROPERTY.to_string(),
+                name_mapping.to_string(),
+            )]))
+            .build();
+        self.catalog
+            .create_table(&self.namespace, creation)
+            .await
+            .map_err(iceberg_error)
+    }
+
+    /// Writes `batches` as one Parquet data file of `table`; `None` when they
+    /// hold no rows.
+    async fn write_data_file(
+        &self,
+        table: &Table,
+        batches: &[RecordBatch],
+    ) -> Result<Option<DataFile>> {
+        let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        let Some(schema) = batches
+            .first()
+            .map(RecordBatch::schema)
+            .filter(|_| rows > 0)
+        else {
+            return Ok(None);
+        };
+        let mut buffer = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buffer, schema, None)
+            .map_err(|e| StorageError::Other(e.into()))?;
+        for batch in batches {
+            writer
+                .write(batch)
+                .map_err(|e| StorageError::Other(e.into()))?;
+        }
+        writer.close().map_err(|e| StorageError::Other(e.into()))?;
+
+        let path = format!(
+            "{}/data/{}.parquet",
+            table.metadata().location().trim_end_matches('/'),
+            Uuid::new_v4()
+        );
+        let size = buffer.len() as u64;
+        table
+            .file_io()
+            .new_output(&path)
+            .map_err(iceberg_error)?
+            .write(Bytes::from(buffer))
+            .await
+            .map_err(iceberg_error)?;
+        DataFileBuilder::default()
+            .content(DataContentType::Data)
+            .file_path(path)
+            .file_format(DataFileFormat::Parquet)
+            .partition(Struct::empty())
+            .partition_spec_id(table.metadata().default_partition_spec_id())
+            .record_count(rows as u64)
+            .file_size_in_bytes(size)
+            .build()
+            .map(Some)
+            .map_err(|e| StorageError::Other(anyhow::anyhow!(e.to_string())))
+    }
+
+    /// Writes `batches` as one Parquet data file and commits it as the
+    /// snapshot of `version`.
+    async fn append(&self, table: &Table, batches: Vec<RecordBatch>, version: i64) -> Result<()> {
+        let Some(data_file) = self.write_data_file(table, &batches).await? else {
+            return Ok(());
+        };
+        let transaction = Transaction::new(table);
+        let transaction = transaction
+            .fast_append()
+            .set_snapshot_properties(HashMap::from([(
+                VERSION_PROPERTY.to_string(),
+                version.to_string(),
+            )]))
+            .add_data_files(vec![data_file])
+            .apply(transaction)
+            .map_err(iceberg_error)?;
+        transaction
+            .commit(&self.catalog)
+            .await
+            .map_err(iceberg_error)?;
+        Ok(())
+    }
+
+    /// Rows of `snapshot`, or of the current snapshot, read when queried.
+    fn scan(&self, table: &Table, snapshot: Option<i64>) -> Result<Arc<dyn TableProvider>> {
+        Ok(Arc::new(SnapshotScan {
+            schema: arrow_schema(table.metadata().current_schema())?,
+            table: table.clone(),
+            snapshot,
+        }))
+    }
+
+    /// Replaces the rows of `existing`, the table as they were read from, with
+    /// `batches`, one version after its current one. The table is created
+    /// when it is missing.
+    async fn rewrite(
+        &self,
+        table: &str,
+        existing: Option<Table>,
+        batches: Vec<RecordBatch>,
+        schema: SchemaRef,
+    ) -> Result<()> {
+        let Some(existing) = existing else {
+            let created = self.create(table, &schema).await?;
+            return self.append(&created, batches, 0).await;
+        };
+        let metadata = existing.metadata();
+        let (evolved, name_mapping) = iceberg_schema(&schema, Some(metadata))?;
+        let evolved = (evolved.as_struct() != metadata.current_schema().as_struct())
+            .then_some((evolved, name_mapping));
+        let data_file = self.write_data_file(&existing, &batches).await?;
+        let transaction = Transaction::new(&existing);
+        let transaction = ReplaceFiles {
+            base_snapshot: metadata.current_snapshot_id(),
+            base_schema: metadata.current_schema_id(),
+            schema: evolved,
+            data_file,
+            version: current_version(&existing) + 1,
+        }
+        .apply(transaction)
+        .map_err(iceberg_error)?;
+        transaction
+            .commit(&self.catalog)
+            .await
+            .map_err(iceberg_error)?;
+        Ok(())
+    }
+
+    /// Removes the snapshots of `table` other than the current one that were
+    /// committed before `before`, in milliseconds, or all of them, and
+    /// deletes the files only they refer to. A dry run only lists those files.
+    /// Returns the files and their size in bytes.
+    async fn expire(
+        &self,
+        table: &str,
+        before: Option<i64>,
+        dry_run: bool,
+    ) -> Result<(Vec<String>, u64)> {
+        let Some(table) = self.load(table).await? else {
+            return Ok((Vec::new(), 0));
+        };
+        let metadata = table.metadata();
+        let current = metadata.current_snapshot_id();
+        let referenced: HashSet<i64> = metadata
+            .refs()
+            .values()
+            .map(|reference| reference.snapshot_id)
+            .collect();
+        let (expired, kept): (Vec<&SnapshotRef>, Vec<&SnapshotRef>) =
+            metadata.snapshots().partition(|snapshot| {
+                Some(snapshot.snapshot_id()) != current
+                    && !referenced.contains(&snapshot.snapshot_id())
+                    && before.is_none_or(|before| snapshot.timestamp_ms() < before)
+            });
+        if expired.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
+
+        let mut live = HashMap::new();
+        for snapshot in kept {
+            snapshot_files(&table, snapshot, &mut live).await?;
+        }
+        let mut candidates = HashMap::new();
+        for snapshot in &expired {
+            snapshot_files(&table, snapshot, &mut candidates).await?;
+        }
+        candidates.retain(|path, _| !live.contains_key(path));
+        let bytes = candidates.values().sum();
+        let mut files: Vec<String> = candidates.into_keys().collect();
+        files.sort();
+        if dry_run {
+            return Ok((files, bytes));
+        }
+
+        let transaction = Transaction::new(&table);
+        let transaction = RemoveSnapshots {
+            base_snapshot: current,
+            snapshot_ids: expired
+                .iter()
+                .map(|snapshot| snapshot.snapshot_id())
+                .collect(),
+        }
+        .apply(transaction)
+        .map_err(iceberg_error)?;
+        transaction
+            .commit(&self.catalog)
+            .await
+            .map_err(iceberg_error)?;
+        for file in &files {
+            table.file_io().delete(file).await.map_err(iceberg_error)?;
+        }
+        Ok((files, bytes))
+    }
+}
+
+#[async_trait]
+impl TableStore for IcebergTables {
+    async fn open(&self, table: &str) -> Result<Option<OpenedTable>> {
+        let Some(table) = self.load(table).await? else {
+            return Ok(None);
+        };
+        Ok(Some(OpenedTable {
+            version: current_version(&table),
+            provider: self.scan(&table, None)?,
+        }))
+    }
+
+    async fn open_at(&self, table: &str, version: i64) -> Result<Arc<dyn TableProvider>> {
+        let missing = || {
+            StorageError::NotFound(format!(
+                "version {version} of '{table}' is no longer in the Iceberg catalog"
+            ))
+        };
+        let loaded = self.load(table).await?.ok_or_else(missing)?;
+        let snapshot = loaded
+            .metadata()
+            .snapshots()
+            .find(|snapshot| snapshot_version(snapshot) == Some(version))
+            .map(|snapshot| snapshot.snapshot_id())
+            .ok_or_else(missing)?;
+        self.scan(&loaded, Some(snapshot))
+    }
+
+    async fn write(&self, table: &str, batches: Vec<RecordBatch>, mode: WriteMode) -> Result<()> {
+        let Some(schema) = batches.first().map(RecordBatch::schema) else {
+            return Ok(());
+        };
+        match (mode, self.load(table).await?) {
+            (WriteMode::Append, Some(existing)) => {
+                let version = current_version(&existing) + 1;
+                self.append(&existing, batches, version).await
+            }
+            (_, existing) => self.rewrite(table, existing, batches, schema).await,
+        }
+    }
+
+    async fn changes_since(
+        &self,
+        table: &str,
+        since: i64,
+    ) -> Result<(Vec<(i64, Vec<RecordBatch>)>, i64)> {
+        let Some(table) = self.load(table).await? else {
+            return Ok((Vec::new(), -1));
+        };
+        let latest = current_version(&table);
+        let mut snapshots: Vec<(i64, &SnapshotRef)> = table
+            .metadata()
+            .snapshots()
+            .filter_map(|snapshot| Some((snapshot_version(snapshot)?, snapshot)))
+            .filter(|(version, _)| *version > since)
+            .collect();
+        snapshots.sort_by_key(|(version, _)| *version);
+
+        let mut changes = Vec::new();
+        for (version, snapshot) in snapshots {
+            let manifests = snapshot
+                .load_manifest_list(table.file_io(), table.metadata())
+                .await
+                .map_err(iceberg_error)?;
+            let mut batches = Vec::new();
+            for manifest in manifests.entries() {
+                if manifest.added_snapshot_id != snapshot.snapshot_id() {
+                    continue;
+                }
+                let manifest = manifest
+                    .load_manifest(table.file_io())
+                    .await
+                    .map_err(iceberg_error)?;
+                for entry in manifest.entries() {
+                    if entry.status() == ManifestStatus::Added {
+                        batches
+                            .extend(read_data_file(&table, entry.data_file().file_path()).await?);
+                    }
+                }
+            }
+            if !batches.is_empty() {
+                changes.push((version, batches));
+            }
+        }
+        Ok((changes, latest))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let prefix = prefix.trim_end_matches('/');
+        let mut tables: Vec<String> = self
+            .catalog
+            .list_tables(&self.namespace)
+            .await
+            .map_err(iceberg_error)?
+            .into_iter()
+            .map(|ident| ident.name().replace("__", "/"))
+            .filter(|path| {
+                prefix.is_empty()
+                    || path == prefix
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .collect();
+        tables.sort();
+        Ok(tables)
+    }
+
+    async fn delete_where(&self, table: &str, predicate: &str) -> Result<usize> {
+        let Some(loaded) = self.load(table).await? else {
+            return Ok(0);
+        };
+        let provider = self.scan(&loaded, None)?;
+        let schema = provider.schema();
+        let before = count(provider.clone()).await?;
+        let kept = select_from(
+            provider,
+            &format!("SELECT * FROM t WHERE NOT COALESCE(({predicate}), false)"),
+        )
+        .await?;
+        let after: usize = kept.iter().map(RecordBatch::num_rows).sum();
+        if after < before {
+            self.rewrite(table, Some(loaded), kept, schema).await?;
+        }
+        Ok(before - after)
+    }
+
+    async fn update_where(
+        &self,
+        table: &str,
+        predicate: &str,
+        assignments: &[(&str, String)],
+    ) -> Result<usize> {
+        let Some(loaded) = self.load(table).await? else {
+            return Ok(0);
+        };
+        let provider = self.scan(&loaded, None)?;
+        let schema = provider.schema();
+        let matched = select_from(
+            provider.clone(),
+            &format!("SELECT COUNT(*) FROM t WHERE COALESCE(({predicate}), false)"),
+        )
+        .await?;
+        let matched = matched
+            .first()
+            .and_then(|batch| {
+                batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<deltalake::arrow::array::Int64Array>()
+            })
+            .map(|column| column.value(0).max(0) as usize)
+            .unwrap_or_default();
+        if matched == 0 {
+            return Ok(0);
+        }
+        let projection = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let name = format!("\"{}\"", field.name().replace('"', "\"\""));
+                match assignments
+                    .iter()
+                    .find(|(column, _)| *column == field.name().as_str())
+                {
+                    Some((_, value)) => format!(
+                        "CASE WHEN COALESCE(({predicate}), false) THEN {value} \
+                         ELSE {name} END AS {name}"
+                    ),
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rows = select_from(provider, &format!("SELECT {projection} FROM t")).await?;
+        self.rewrite(table, Some(loaded), rows, schema).await?;
+        Ok(matched)
+    }
+
+    async fn purge_history(&self, table: &str) -> Result<()> {
+        let (files, _) = self.expire(table, None, false).await?;
+        log::debug!(
+            "Purged {} files of earlier snapshots of Iceberg table '{table}'",
+            files.len()
+        );
+        Ok(())
+    }
+
+    async fn repair_log(&self, table: &str) -> Result<LogRepair> {
+        Err(StorageError::InvalidArg(format!(
+            "Iceberg table '{table}' has no Delta log; its REST catalog owns the metadata"
+        )))
+    }
+
+    async fn maintain(
+        &self,
+        table: &str,
+        options: &MaintenanceOptions,
+    ) -> Result<TableMaintenance> {
+        // Compaction and orphan files are left to the catalog; snapshots past
+        // the retention period are expired here like Delta versions are
+        // vacuumed. The files are not in the lake directory, so they are not
+        // reported as vacuumed paths.
+        let retention = chrono::Duration::hours(options.retention_hours as i64);
+        let before = (chrono::Utc::now() - retention).timestamp_millis();
+        let (files, bytes) = self.expire(table, Some(before), options.dry_run).await?;
+        Ok(TableMaintenance {
+            table_path: table.to_string(),
+            vacuumed_files: files.len() as u64,
+            vacuumed_bytes: bytes,
+            ..TableMaintenance::default()
+        })
+    }
+}
+
+async fn count(table: Arc<dyn TableProvider>) -> Result<usize> {
+    let batches = select_from(table, "SELECT * FROM t").await?;
+    Ok(batches.iter().map(RecordBatch::num_rows).sum())
+}
+
+/// Commits a snapshot of the main branch that refers to `data_file` alone,
+/// or to no file when every row is gone. The commit fails when the branch or
+/// the schema moved away from what the rows were read at.
+struct ReplaceFiles {
+    base_snapshot: Option<i64>,
+    base_schema: i32,
+    /// The table's schema after the rewrite and its name mapping, when the
+    /// rewritten rows changed the columns.
+    schema: Option<(Schema, serde_json::Value)>,
+    data_file: Option<DataFile>,
+    version: i64,
+}
+
+#[async_trait]
+impl TransactionAction for ReplaceFiles {
+    async fn commit(self: Arc<Self>, table: &Table) -> iceberg::Result<ActionCommit> {
+        let metadata = table.metadata();
+        let location = metadata.location().trim_end_matches('/');
+        let snapshot_id = new_snapshot_id(metadata);
+        let sequence_number = metadata.next_sequence_number();
+        let mut updates = Vec::new();
+        let mut requirements = vec![
+            TableRequirement::UuidMatch {
+                uuid: metadata.uuid(),
+            },
+            TableRequirement::RefSnapshotIdMatch {
+                r#ref: MAIN_BRANCH.to_string(),
+                snapshot_id: self.base_snapshot,
+            },
+            TableRequirement::CurrentSchemaIdMatch {
+                current_schema_id: self.base_schema,
+            },
+        ];
+        let schema = match &self.schema {
+            Some((schema, name_mapping)) => {
+                requirements.push(TableRequirement::LastAssignedFieldIdMatch {
+                    last_assigned_field_id: metadata.last_column_id(),
+                });
+                updates.push(TableUpdate::AddSchema {
+                    schema: schema.clone(),
+                });
+                updates.push(TableUpdate::SetCurrentSchema { schema_id: -1 });
+                updates.push(TableUpdate::SetProperties {
+                    updates: HashMap::from([(
+                        NAME_MAPPING_PROPERTY.to_string(),
+                        name_mapping.to_string(),
+                    )]),
+                });
+                Arc::new(schema.clone())
+            }
+            None => metadata.current_schema().clone(),
+        };
+
+        let mut manifests = Vec::new();
+        if let Some(data_file) = &self.data_file {
+            let output = table
+                .file_io()
+                .new_output(format!("{location}/metadata/{}-m0.avro", Uuid::new_v4()))?;
+            let mut writer = ManifestWriterBuilder::new(
+                output,
+                Some(snapshot_id),
+                None,
+                schema.clone(),
+                metadata.default_partition_spec().as_ref().clone(),
+            )
+            .build_v2_data();
+            writer.add_file(data_file.clone(), sequence_number)?;
+            manifests.push(writer.write_manifest_file().await?);
+        }
+        let manifest_list = format!(
+            "{location}/metadata/snap-{snapshot_id}-0-{}.avro",
+            Uuid::new_v4()
+        );
+        let mut writer = ManifestListWriter::v2(
+            table.file_io().new_output(&manifest_list)?,
+            snapshot_id,
+            self.base_snapshot,
+            sequence_number,
+        );
+        writer.add_manifests(manifests.into_iter())?;
+        writer.close().await?;
+
+        let operation = if self.data_file.is_some() {
+            Operation::Overwrite
+        } else {
+            Operation::Delete
+        };
+        let snapshot = Snapshot::builder()
+            .with_snapshot_id(snapshot_id)
+            .with_parent_snapshot_id(self.base_snapshot)
+            .with_sequence_number(sequence_number)
+            .with_timestamp_ms(chrono::Utc::now().timestamp_millis())
+            .with_manifest_list(manifest_list)
+            .with_summary(Summary {
+                operation,
+                additional_properties: HashMap::from([(
+                    VERSION_PROPERTY.to_string(),
+                    self.version.to_string(),
+                )]),
+            })
+            .with_schema_id(schema.schema_id())
+            .build();
+        updates.push(TableUpdate::AddSnapshot { snapshot });
+        updates.push(TableUpdate::SetSnapshotRef {
+            ref_name: MAIN_BRANCH.to_string(),
+            reference: SnapshotReference::new(
+                snapshot_id,
+                SnapshotRetention::branch(None, None, None),
+            ),
+        });
+        Ok(ActionCommit::new(updates, requirements))
+    }
+}
+
+/// Removes `snapshot_ids` from the table's metadata, as long as the main
+/// branch is still where the files to delete were worked out at.
+struct RemoveSnapshots {
+    base_snapshot: Option<i64>,
+    snapshot_ids: Vec<i64>,
+}
+
+#[async_trait]
+impl TransactionAction for RemoveSnapshots {
+    async fn commit(self: Arc<Self>, _table: &Table) -> iceberg::Result<ActionCommit> {
+        Ok(ActionCommit::new(
+            vec![TableUpdate::RemoveSnapshots {
+                snapshot_ids: self.snapshot_ids.clone(),
+            }],
+            vec![TableRequirement::RefSnapshotIdMatch {
+                r#ref: MAIN_BRANCH.to_string(),
+                snapshot_id: self.base_snapshot,
+            }],
+        ))
+    }
+}
+
+/// A positive snapshot id the table does not use yet.
+fn new_snapshot_id(metadata: &TableMetadata) -> i64 {
+    loop {
+        let (high, low) = Uuid::new_v4().as_u64_pair();
+        let id = ((high ^ low) as i64).wrapping_abs();
+        if id > 0 && metadata.snapshot_by_id(id).is_none() {
+            return id;
+        }
+    }
+}
+
+/// Adds the manifest list, manifests and data files of `snapshot` to `files`,
+/// with their size in bytes where the metadata records it.
+async fn snapshot_files(
+    table: &Table,
+    snapshot: &SnapshotRef,
+    files: &mut HashMap<String, u64>,
+) -> Result<()> {
+    files
+        .entry(snapshot.manifest_list().to_string())
+        .or_insert(0);
+    let manifests = snapshot
+        .load_manifest_list(table.file_io(), table.metadata())
+        .await
+        .map_err(iceberg_error)?;
+    for manifest in manifests.entries() {
+        if files
+            .insert(
+                manifest.manifest_path.clone(),
+                manifest.manifest_length as u64,
+            )
+            .is_some()
+        {
+            // Manifests carried over from an earlier snapshot were listed
+            // with it.
+            continue;
+        }
+        let manifest = manifest
+            .load_manifest(table.file_io())
+            .await
+            .map_err(iceberg_error)?;
+        for entry in manifest.entries() {
+            let data_file = entry.data_file();
+            files.insert(
+                data_file.file_path().to_string(),
+                data_file.file_size_in_bytes(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The rows of one snapshot of a table, planned and read when a query scans
+/// them.
+struct SnapshotScan {
+    table: Table,
+    /// The current snapshot when `None`.
+    snapshot: Option<i64>,
+    schema: SchemaRef,
+}
+
+impl fmt::Debug for SnapshotScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotScan")
+            .field("table", self.table.identifier())
+            .field("snapshot", &self.snapshot)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TableProvider for SnapshotScan {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let schema = match projection {
+            Some(projection) => Arc::new(self.schema.project(projection)?),
+            None => self.schema.clone(),
+        };
+        let files = SnapshotFiles {
+            table: self.table.clone(),
+            snapshot: self.snapshot,
+            schema: schema.clone(),
+        };
+        let plan = StreamingTableExec::try_new(
+            schema,
+            vec![Arc::new(files)],
+            None,
+            Vec::new(),
+            false,
+            limit,
+        )?;
+        Ok(Arc::new(plan))
+    }
+}
+
+/// The data files of a [`SnapshotScan`] as one stream, with the columns of
+/// `schema`.
+struct SnapshotFiles {
+    table: Table,
+    snapshot: Option<i64>,
+    schema: SchemaRef,
+}
+
+impl fmt::Debug for SnapshotFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotFiles")
+            .field("table", self.table.identifier())
+            .field("snapshot", &self.snapshot)
+            .finish()
+    }
+}
+
+impl PartitionStream for SnapshotFiles {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let table = self.table.clone();
+        let snapshot = self.snapshot;
+        let schema = self.schema.clone();
+        let batches = futures::stream::once(async move {
+            let paths = data_files(&table, snapshot).await?;
+            Ok::<_, StorageError>(
+                futures::stream::iter(paths)
+                    .then(move |path| {
+                        let table = table.clone();
+                        let schema = schema.clone();
+                        async move { read_columns(&table, &path, &schema).await }
+                    })
+                    .map_ok(|batches| futures::stream::iter(batches.into_iter().map(Ok)))
+                    .try_flatten(),
+            )
+        })
+        .try_flatten()
+        .map_err(|e| DataFusionError::External(Box::new(e)));
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches))
+    }
+}
+
+/// Paths of the data files of `snapshot`, or of the current snapshot.
+async fn data_files(table: &Table, snapshot: Option<i64>) -> Result<Vec<String>> {
+    if snapshot.is_none() && table.metadata().current_snapshot().is_none() {
+        return Ok(Vec::new());
+    }
+    let mut scan = table.scan();
+    if let Some(snapshot) = snapshot {
+        scan = scan.snapshot_id(snapshot);
+    }
+    let tasks: Vec<_> = scan
+        .build()
+        .map_err(iceberg_error)?
+        .plan_files()
+        .await
+        .map_err(iceberg_error)?
+        .try_collect()
+        .await
+        .map_err(iceberg_error)?;
+    Ok(tasks.into_iter().map(|task| task.data_file_path).collect())
+}
+
+fn snapshot_version(snapshot: &SnapshotRef) -> Option<i64> {
+    snapshot
+        .summary()
+        .additional_properties
+        .get(VERSION_PROPERTY)?
+        .parse()
+        .ok()
+}
+
+fn current_version(table: &Table) -> i64 {
+    table
+        .metadata()
+        .current_snapshot()
+        .and_then(snapshot_version)
+        .unwrap_or(-1)
+}
+
+async fn read_data_file(table: &Table, path: &str) -> Result<Vec<RecordBatch>> {
+    let bytes = table
+        .file_io()
+        .new_input(path)
+        .map_err(iceberg_error)?
+        .read()
+        .await
+        .map_err(iceberg_error)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)
+        .map_err(|e| StorageError::Other(e.into()))?
+        .build()
+        .map_err(|e| StorageError::Other(e.into()))?;
+    reader
+        .map(|batch| batch.map_err(|e| StorageError::Other(e.into())))
+        .collect()
+}
+
+/// The columns of data file `path` named in `schema`, with its types. Columns
+/// the file predates are null.
+async fn read_columns(table: &Table, path: &str, schema: &SchemaRef) -> Result<Vec<RecordBatch>> {
+    let bytes = table
+        .file_io()
+        .new_input(path)
+        .map_err(iceberg_error)?
+        .read()
+        .await
+        .map_err(iceberg_error)?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(bytes)
+        .map_err(|e| StorageError::Other(e.into()))?;
+    let file_schema = builder.schema().clone();
+    let roots: Vec<usize> = schema
+        .fields()
+        .iter()
+        .filter_map(|field| file_schema.index_of(field.name()).ok())
+        .collect();
+    let mask = ProjectionMask::roots(builder.parquet_schema(), roots);
+    let reader = builder
+        .with_projection(mask)
+        .build()
+        .map_err(|e| StorageError::Other(e.into()))?;
+    reader
+        .map(|batch| {
+            let batch = batch.map_err(|e| StorageError::Other(e.into()))?;
+            let columns = schema
+                .fields()
+                .iter()
+                .map(|field| match batch.column_by_name(field.name()) {
+                    Some(column) => cast(column, field.data_type()),
+                    None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let options = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
+            Ok(RecordBatch::try_new_with_options(
+                schema.clone(),
+                columns,
+                &options,
+            )?)
+        })
+        .collect()
+}
+
+/// The Iceberg schema for `schema`, and the name mapping from column names to
+/// its field ids. Columns the table at `current` already has keep their ids;
+/// the others are numbered after the last id it assigned, or from 1 for a new
+/// table.
+fn iceberg_schema(
+    schema: &ArrowSchema,
+    current: Option<&TableMetadata>,
+) -> Result<(Schema, serde_json::Value)> {
+    let mut next_id = current.map_or(0, TableMetadata::last_column_id);
+    let mut fields = Vec::new();
+    let mut mapping = Vec::new();
+    for field in schema.fields() {
+        let existing =
+            current.and_then(|metadata| metadata.current_schema().field_by_name(field.name()));
+        let (id, field_type, nested) = match existing {
+            Some(existing) => {
+                let (incoming, _) = iceberg_type(field, &mut 0)?;
+                if arrow_type(&incoming)? != arrow_type(&existing.field_type)? {
+                    return Err(StorageError::InvalidArg(format!(
+                        "column '{}' is {} in the Iceberg table and cannot become {}",
+                        field.name(),
+                        existing.field_type,
+                        field.data_type()
+                    )));
+                }
+                let nested = match existing.field_type.as_ref() {
+                    Type::List(list) => {
+                        Some(json!([{"field-id": list.element_field.id, "names": ["element"]}]))
+                    }
+                    _ => None,
+                };
+                (existing.id, existing.field_type.as_ref().clone(), nested)
+            }
+            None => {
+                next_id += 1;
+                let id = next_id;
+                let (field_type, nested) = iceberg_type(field, &mut next_id)?;
+                (id, field_type, nested)
+            }
+        };
+        fields.push(Arc::new(if field.is_nullable() {
+            NestedField::optional(id, field.name(), field_type)
+        } else {
+            NestedField::required(id, field.name(), field_type)
+        }));
+        let mut entry = json!({"field-id": id, "names": [field.name()]});
+        if let Some(nested) = nested {
+            entry["fields"] = nested;
+        }
+        mapping.push(entry);
+    }
+    let mut builder = Schema::builder().with_fields(fields);
+    if let Some(metadata) = current {
+        let schema_id = metadata
+            .schemas_iter()
+            .map(|schema| schema.schema_id())
+            .max()
+            .unwrap_or_default();
+        builder = builder.with_schema_id(schema_id + 1);
+    }
+    let schema = builder.build().map_err(iceberg_error)?;
+    Ok((schema, json!(mapping)))
+}
+
+/// The Iceberg type of `field`, and the name mapping of its element for lists.
+fn iceberg_type(field: &Field, next_id: &mut i32) -> Result<(Type, Option<serde_json::Value>)> {
+    let primitive = match field.data_type() {
+        DataType::Boolean => PrimitiveType::Boolean,
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 => {
+            PrimitiveType::Int
+        }
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => PrimitiveType::Long,
+        DataType::Float32 => PrimitiveType::Float,
+        DataType::Float64 => PrimitiveType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 => PrimitiveType::String,
+        DataType::Binary | DataType::LargeBinary => PrimitiveType::Binary,
+        DataType::Date32 => PrimitiveType::Date,
+        DataType::Timestamp(TimeUnit::Microsecond, None) => PrimitiveType::Timestamp,
+        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => PrimitiveType::Timestamptz,
+        DataType::List(element) | DataType::LargeList(element) => {
+            *next_id += 1;
+            let element_id = *next_id;
+            let (element_type, _) = iceberg_type(element, next_id)?;
+            let list = ListType {
+                element_field: NestedField::list_element(
+                    element_id,
+                    element_type,
+                    !element.is_nullable(),
+                )
+                .into(),
+            };
+            let mapping = json!([{"field-id": element_id, "names": ["element"]}]);
+            return Ok((Type::List(list), Some(mapping)));
+        }
+        other => {
+            return Err(StorageError::InvalidArg(format!(
+                "column '{}' has type {other}, which the Iceberg tables do not support",
+                field.name()
+            )))
+        }
+    };
+    Ok((Type::Primitive(primitive), None))
+}
+
+/// The arrow schema data files are read with: the columns of `schema`, with
+/// the types the lake writes them as.
+fn arrow_schema(schema: &Schema) -> Result<SchemaRef> {
+    let fields = schema
+        .as_struct()
+        .fields()
+        .iter()
+        .map(|field| {
+            Ok(Field::new(
+                &field.name,
+                arrow_type(&field.field_type)?,
+                !field.required,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Arc::new(ArrowSchema::new(fields)))
+}
+
+fn arrow_type(field_type: &Type) -> Result<DataType> {
+    Ok(match field_type {
+        Type::Primitive(PrimitiveType::Boolean) => DataType::Boolean,
+        Type::Primitive(PrimitiveType::Int) => DataType::Int32,
+        Type::Primitive(PrimitiveType::Long) => DataType::Int64,
+        Type::Primitive(PrimitiveType::Float) => DataType::Float32,
+        Type::Primitive(PrimitiveType::Double) => DataType::Float64,
+        Type::Primitive(PrimitiveType::String) => DataType::Utf8,
+        Type::Primitive(PrimitiveType::Binary) => DataType::Binary,
+        Type::Primitive(PrimitiveType::Date) => DataType::Date32,
+        Type::Primitive(PrimitiveType::Timestamp) => {
+            DataType::Timestamp(TimeUnit::Microsecond, None)
+        }
+        Type::Primitive(PrimitiveType::Timestamptz) => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        }
+        Type::List(list) => DataType::List(Arc::new(Field::new(
+            "element",
+            arrow_type(&list.element_field.field_type)?,
+            !list.element_field.required,
+        ))),
+        other => {
+            return Err(StorageError::InvalidArg(format!(
+                "Iceberg type {other} is not supported"
+            )))
+        }
+    })
+}
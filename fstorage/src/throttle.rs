@@ -0,0 +1,271 @@
+//! Request and token rate limits for embedding providers.
+//!
+//! Hosted providers enforce per-account RPM/TPM quotas and answer bursts above
+//! them with 429s. [`ThrottledEmbeddingProvider`] sits in front of the shared
+//! provider, splits batches that exceed the token quota and waits for both
+//! buckets to refill before sending, so every sync and search running in this
+//! process stays under the configured limits together.
+
+use crate::costs::approximate_tokens;
+use crate::embedding::EmbeddingProvider;
+use crate::errors::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Per-minute quotas for one provider; an unset limit is not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct ProviderLimits {
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl ProviderLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none() && self.tokens_per_minute.is_none()
+    }
+
+    /// Limits configured for `provider:model`, falling back to bare `provider`.
+    pub fn lookup(
+        limits: &HashMap<String, ProviderLimits>,
+        provider: &str,
+        model: Option<&str>,
+    ) -> Option<Self> {
+        model
+            .and_then(|model| limits.get(&format!("{provider}:{model}")))
+            .or_else(|| limits.get(provider))
+            .copied()
+            .filter(|limits| !limits.is_unlimited())
+    }
+}
+
+/// A bucket holding up to one minute's allowance, refilled continuously.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        let capacity = f64::from(per_minute.max(1));
+        Self {
+            capacity,
+            available: capacity,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        let refilled = self.capacity * elapsed.as_secs_f64() / 60.0;
+        self.available = (self.available + refilled).min(self.capacity);
+    }
+
+    /// Time until `amount` is available; zero when it already is.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing * 60.0 / self.capacity)
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+struct Quota {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    updated: Instant,
+}
+
+impl Quota {
+    fn new(limits: ProviderLimits, now: Instant) -> Self {
+        Self {
+            requests: limits.requests_per_minute.map(Bucket::new),
+            tokens: limits.tokens_per_minute.map(Bucket::new),
+            updated: now,
+        }
+    }
+
+    /// Takes one request and `tokens` tokens if both are available, otherwise
+    /// returns how long to wait before trying again.
+    fn try_reserve(&mut self, tokens: u64, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = now;
+        let tokens = tokens as f64;
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = &mut self.requests {
+            bucket.refill(elapsed);
+            wait = wait.max(bucket.wait_for(1.0));
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.refill(elapsed);
+            wait = wait.max(bucket.wait_for(tokens));
+        }
+        if !wait.is_zero() {
+            return Some(wait);
+        }
+        if let Some(bucket) = &mut self.requests {
+            bucket.take(1.0);
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.take(tokens);
+        }
+        None
+    }
+}
+
+/// Holds embedding calls back until the provider's quotas allow them.
+pub struct ThrottledEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    limits: ProviderLimits,
+    quota: Mutex<Quota>,
+}
+
+impl ThrottledEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, limits: ProviderLimits) -> Self {
+        Self {
+            inner,
+            limits,
+            quota: Mutex::new(Quota::new(limits, Instant::now())),
+        }
+    }
+
+    async fn reserve(&self, tokens: u64) {
+        loop {
+            let wait = self.quota.lock().await.try_reserve(tokens, Instant::now());
+            match wait {
+                None => return,
+                Some(wait) => {
+                    log::debug!(
+                        "{} embedding quota exhausted; waiting {:?}",
+                        self.inner.provider_name(),
+                        wait
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+    }
+}
+
+/// Splits `texts` into consecutive batches of at most `max_tokens` estimated
+/// tokens. A single text above the limit still gets a batch of its own.
+fn split_by_tokens(texts: Vec<String>, max_tokens: Option<u32>) -> Vec<Vec<String>> {
+    let Some(max_tokens) = max_tokens.map(u64::from) else {
+        return vec![texts];
+    };
+    let mut batches: Vec<Vec<String>> = Vec::new();
+    let mut current = Vec::new();
+    let mut current_tokens = 0;
+    for text in texts {
+        let tokens = approximate_tokens(std::slice::from_ref(&text));
+        if !current.is_empty() && current_tokens + tokens > max_tokens {
+            batches.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current_tokens += tokens;
+        current.push(text);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+#[async_trait]
+impl EmbeddingProvider for ThrottledEmbeddingProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
+        Ok(self.embed_with_usage(texts).await?.0)
+    }
+
+    /// The tokens reported are the sum over the batches, as long as the
+    /// provider reported them for every batch.
+    async fn embed_with_usage(&self, texts: Vec<String>) -> Result<(Vec<Vec<f64>>, Option<u64>)> {
+        if texts.is_empty() {
+            return self.inner.embed_with_usage(texts).await;
+        }
+        let mut vectors = Vec::with_capacity(texts.len());
+        let mut reported = Some(0);
+        for batch in split_by_tokens(texts, self.limits.tokens_per_minute) {
+            self.reserve(approximate_tokens(&batch)).await;
+            let (batch_vectors, tokens) = self.inner.embed_with_usage(batch).await?;
+            vectors.extend(batch_vectors);
+            reported = reported.zip(tokens).map(|(sum, tokens)| sum + tokens);
+        }
+        Ok((vectors, reported))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quota_waits_for_the_scarcer_bucket() {
+        let start = Instant::now();
+        let mut quota = Quota::new(
+            ProviderLimits {
+                requests_per_minute: Some(2),
+                tokens_per_minute: Some(600),
+            },
+            start,
+        );
+
+        assert_eq!(quota.try_reserve(500, start), None);
+        // One request left but only 100 tokens: wait 20s for 200 more.
+        let wait = quota.try_reserve(300, start).unwrap();
+        assert!((wait.as_secs_f64() - 20.0).abs() < 1e-6);
+        let later = start + Duration::from_secs(20);
+        assert_eq!(quota.try_reserve(300, later), None);
+        // A third of a request refilled meanwhile; the rest takes another 10s.
+        let wait = quota.try_reserve(1, later).unwrap();
+        assert!((wait.as_secs_f64() - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn batches_are_split_to_fit_the_token_quota() {
+        let texts = vec![
+            "a".repeat(40),
+            "b".repeat(40),
+            "c".repeat(40),
+            "d".repeat(400),
+        ];
+        let sizes: Vec<usize> = split_by_tokens(texts.clone(), Some(25))
+            .iter()
+            .map(Vec::len)
+            .collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+        assert_eq!(split_by_tokens(texts, None).len(), 1);
+
+        let mut limits = HashMap::new();
+        limits.insert(
+            "openai".to_string(),
+            ProviderLimits {
+                requests_per_minute: Some(3000),
+                tokens_per_minute: None,
+            },
+        );
+        assert!(
+            ProviderLimits::lookup(&limits, "openai", Some("text-embedding-3-small")).is_some()
+        );
+        assert!(ProviderLimits::lookup(&limits, "fastembed", None).is_none());
+    }
+}
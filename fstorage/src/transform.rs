@@ -0,0 +1,26 @@
+//! Deployment-specific rewriting of fetched data before it is stored.
+//!
+//! Transforms are registered on the synchronizer at startup and run, in
+//! registration order, on every fetched batch ahead of linting, redaction and
+//! the lake and engine writes. They can drop rows (e.g. forks a deployment does
+//! not track) or add columns (e.g. a team tag) without changing the fetchers.
+//! A column added by a transform must be added from the first sync of the
+//! entity type on, since lake tables keep the schema they were created with.
+
+use crate::errors::Result;
+use deltalake::arrow::record_batch::RecordBatch;
+
+/// Rewrites fetched batches of one entity type.
+pub trait BatchTransform: Send + Sync {
+    /// Returns the batch to store in place of `batch`. An error fails the sync.
+    fn transform(&self, entity_type: &str, batch: RecordBatch) -> Result<RecordBatch>;
+}
+
+impl<F> BatchTransform for F
+where
+    F: Fn(&str, RecordBatch) -> Result<RecordBatch> + Send + Sync,
+{
+    fn transform(&self, entity_type: &str, batch: RecordBatch) -> Result<RecordBatch> {
+        self(entity_type, batch)
+    }
+}
@@ -5,6 +5,14 @@ pub fn uuid_v5_u128(ns: Uuid, name: &str) -> u128 {
 }
 
 pub fn stable_node_id_u128(entity_type: &str, key_values: &[(&str, String)]) -> u128 {
+    uuid_v5_u128(
+        Uuid::NAMESPACE_OID,
+        &stable_node_id_name(entity_type, key_values),
+    )
+}
+
+/// The name [`stable_node_id_u128`] hashes.
+pub fn stable_node_id_name(entity_type: &str, key_values: &[(&str, String)]) -> String {
     // name 形如 "Project|url=https://...|name=repo"
     let mut name = String::from(entity_type);
     for (k, v) in key_values {
@@ -13,7 +21,7 @@ pub fn stable_node_id_u128(entity_type: &str, key_values: &[(&str, String)]) ->
         name.push('=');
         name.push_str(v);
     }
-    uuid_v5_u128(Uuid::NAMESPACE_OID, &name)
+    name
 }
 
 pub fn stable_edge_id_u128(edge_label: &str, from: &str, to: &str) -> u128 {
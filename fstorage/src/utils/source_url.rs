@@ -0,0 +1,134 @@
+//! Links from stored chunks back to the file they were cut from.
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+/// GitHub blob URL of `path` at `revision` in the repository at `project_url`,
+/// anchored to the line range when one is known. `None` for repositories not
+/// hosted on GitHub, whose URL layout is not known.
+pub fn source_url(
+    project_url: &str,
+    revision: &str,
+    path: &str,
+    start_line: Option<i64>,
+    end_line: Option<i64>,
+) -> Option<String> {
+    let repo = project_url.trim().trim_end_matches('/');
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let rest = repo
+        .strip_prefix("https://github.com/")
+        .or_else(|| repo.strip_prefix("http://github.com/"))?;
+    if rest.split('/').filter(|part| !part.is_empty()).count() != 2 {
+        return None;
+    }
+    let revision = revision.trim();
+    let path = path.trim().trim_start_matches("./").trim_start_matches('/');
+    if revision.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    let mut url = format!(
+        "https://github.com/{rest}/blob/{}/{}",
+        encode_path(revision),
+        encode_path(path)
+    );
+    match (start_line.filter(|line| *line > 0), end_line) {
+        (Some(start), Some(end)) if end > start => url.push_str(&format!("#L{start}-L{end}")),
+        (Some(start), _) => url.push_str(&format!("#L{start}")),
+        (None, _) => {}
+    }
+    Some(url)
+}
+
+/// Adds `source_url` to the properties of a README or code chunk, read from
+/// its `project_url`, `revision_sha`, `source_file` and line range.
+pub fn insert_source_url(properties: &mut JsonMap<String, JsonValue>) {
+    let text = |key: &str| properties.get(key).and_then(JsonValue::as_str);
+    let line = |key: &str| properties.get(key).and_then(JsonValue::as_i64);
+    let (Some(project_url), Some(revision), Some(path)) = (
+        text("project_url"),
+        text("revision_sha"),
+        text("source_file"),
+    ) else {
+        return;
+    };
+    if let Some(url) = source_url(
+        project_url,
+        revision,
+        path,
+        line("start_line"),
+        line("end_line"),
+    ) {
+        properties.insert("source_url".to_string(), JsonValue::String(url));
+    }
+}
+
+/// Percent-encodes the characters that would end or corrupt a URL path,
+/// keeping `/` so nested paths stay readable.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn github_chunks_link_to_their_lines() {
+        assert_eq!(
+            source_url(
+                "https://github.com/owner/repo.git",
+                "abc123",
+                "src/my file.rs",
+                Some(10),
+                Some(24),
+            )
+            .as_deref(),
+            Some("https://github.com/owner/repo/blob/abc123/src/my%20file.rs#L10-L24")
+        );
+        assert_eq!(
+            source_url(
+                "https://github.com/owner/repo/",
+                "abc123",
+                "README.md",
+                Some(3),
+                Some(3)
+            )
+            .as_deref(),
+            Some("https://github.com/owner/repo/blob/abc123/README.md#L3")
+        );
+        assert_eq!(
+            source_url(
+                "https://gitlab.com/owner/repo",
+                "abc123",
+                "README.md",
+                None,
+                None
+            ),
+            None
+        );
+
+        let mut properties = json!({
+            "project_url": "https://github.com/owner/repo",
+            "revision_sha": "abc123",
+            "source_file": "README.md",
+        })
+        .as_object()
+        .cloned()
+        .expect("object");
+        insert_source_url(&mut properties);
+        assert_eq!(
+            properties["source_url"],
+            "https://github.com/owner/repo/blob/abc123/README.md"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use fstorage::{
+    archive,
+    config::StorageConfig,
+    errors::StorageError,
+    fetch::{Fetchable, GraphData},
+    schemas::generated_schemas::Project,
+    sync::DataSynchronizer,
+    FStorage,
+};
+use tempfile::tempdir;
+
+fn project(url: &str) -> Project {
+    Project {
+        url: Some(url.to_string()),
+        name: Some("archived".to_string()),
+        description: None,
+        language: Some("Rust".to_string()),
+        stars: Some(3),
+        forks: None,
+    }
+}
+
+#[tokio::test]
+async fn exported_archive_restores_into_an_empty_directory() -> anyhow::Result<()> {
+    let source_dir = tempdir()?;
+    let source = FStorage::new(StorageConfig::new(source_dir.path())).await?;
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        project("https://example.com/a"),
+        project("https://example.com/b"),
+    ]);
+    source.synchronizer.process_graph_data(graph).await?;
+
+    let out_dir = tempdir()?;
+    let archive_path = out_dir.path().join("store.tar");
+    let manifest = source.export_archive(&archive_path).await?;
+    assert!(manifest.table_versions.contains_key(&Project::table_name()));
+    assert_eq!(
+        archive::read_manifest(&archive_path)?.schema_fingerprint,
+        manifest.schema_fingerprint
+    );
+    let again = source.export_archive(&archive_path).await;
+    assert!(matches!(again, Err(StorageError::InvalidArg(_))));
+
+    let target_root = tempdir()?;
+    let target_path = target_root.path().join("restored");
+    archive::import(&archive_path, &target_path)?;
+    let restored = FStorage::new(StorageConfig::new(&target_path)).await?;
+    let stats = restored.lake.graph_statistics().await?;
+    assert_eq!(
+        stats.nodes_by_label.get(Project::ENTITY_TYPE).copied(),
+        Some(2)
+    );
+    assert_eq!(
+        stats.table_rows.get(&Project::table_name()).copied(),
+        Some(2)
+    );
+    assert_eq!(restored.graph_version()?, source.graph_version()?);
+
+    // The target must be empty; the restored store is left alone.
+    let refused = archive::import(&archive_path, &target_path);
+    assert!(matches!(refused, Err(StorageError::InvalidArg(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn archives_from_another_schema_are_refused() -> anyhow::Result<()> {
+    let source_dir = tempdir()?;
+    let source = FStorage::new(StorageConfig::new(source_dir.path())).await?;
+    let out_dir = tempdir()?;
+    let archive_path = out_dir.path().join("store.tar");
+    let mut manifest = source.export_archive(&archive_path).await?;
+
+    manifest.schema_fingerprint = "0".repeat(64);
+    let raw = serde_json::to_vec(&manifest)?;
+    let forged_path = out_dir.path().join("forged.tar");
+    let mut builder = tar::Builder::new(std::fs::File::create(&forged_path)?);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(raw.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, archive::MANIFEST_FILE, raw.as_slice())?;
+    builder.finish()?;
+
+    let target_root = tempdir()?;
+    let target_path = target_root.path().join("restored");
+    let refused = archive::import(&forged_path, &target_path);
+    assert!(
+        matches!(refused, Err(StorageError::InvalidArg(message)) if message.contains("schema"))
+    );
+    assert!(!target_path.exists());
+    Ok(())
+}
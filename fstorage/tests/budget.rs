@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use fstorage::{
+    embedding::EmbeddingProvider,
+    errors::StorageError,
+    fetch::{FetchResponse, Fetcher, FetcherCapability, GraphData, ProbeReport},
+    models::{BudgetConstraint, SyncBudget, SyncContext, UsageBucket},
+    sync::DataSynchronizer,
+};
+use serde_json::json;
+
+mod common;
+
+/// Embeds three batches of one text each.
+struct EmbeddingFetcher;
+
+impl EmbeddingFetcher {
+    const NAME: &'static str = "embedding_fetcher";
+}
+
+#[async_trait::async_trait]
+impl Fetcher for EmbeddingFetcher {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: Self::NAME,
+            description: "Embeds a few texts and produces nothing",
+            param_schema: json!({"type": "object"}),
+            produces: Vec::new(),
+            default_ttl_secs: None,
+            examples: Vec::new(),
+        }
+    }
+
+    async fn probe(&self, _params: serde_json::Value) -> fstorage::errors::Result<ProbeReport> {
+        Ok(ProbeReport {
+            fresh: None,
+            remote_anchor: None,
+            local_anchor: None,
+            anchor_key: None,
+            estimated_missing: None,
+            rate_limit_left: None,
+            reason: None,
+        })
+    }
+
+    async fn fetch(
+        &self,
+        _params: serde_json::Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        for text in ["first", "second", "third"] {
+            embedding_provider.embed(vec![text.to_string()]).await?;
+        }
+        Ok(FetchResponse::GraphData(GraphData::new()))
+    }
+}
+
+#[tokio::test]
+async fn stopped_syncs_still_book_their_spend() -> anyhow::Result<()> {
+    let ctx = common::init_test_context().await?;
+    ctx.synchronizer
+        .register_fetcher(Arc::new(EmbeddingFetcher) as Arc<dyn Fetcher>);
+
+    // The fetch itself and one batch fit; the second batch does not.
+    let err = ctx
+        .synchronizer
+        .sync(
+            EmbeddingFetcher::NAME,
+            json!({}),
+            SyncContext {
+                triggering_query: None,
+                target_entities: Vec::new(),
+            },
+            SyncBudget::ByRequestCount(2),
+        )
+        .await
+        .err()
+        .expect("the budget stops the sync");
+    assert!(matches!(
+        err,
+        StorageError::BudgetExhausted(BudgetConstraint::Requests)
+    ));
+
+    let costs = ctx
+        .catalog
+        .aggregate_costs(UsageBucket::Day, None, None, Some("sync"))?;
+    assert_eq!(costs.len(), 1, "{costs:?}");
+    assert_eq!(costs[0].requests, 1);
+    Ok(())
+}
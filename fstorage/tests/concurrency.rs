@@ -0,0 +1,336 @@
+//! Syncs, searches and graph walks running at once against one store.
+//!
+//! Every test runs on a multi-threaded runtime under a deadline, so a deadlock
+//! fails the test instead of hanging it, and counts rows afterwards so a write
+//! that was silently overwritten by another shows up as a missing row.
+
+use std::{sync::Arc, time::Duration};
+
+use fstorage::{
+    config::StorageConfig,
+    embedding::EmbeddingProvider,
+    fetch::{FetchResponse, Fetchable, Fetcher, FetcherCapability, GraphData, ProbeReport},
+    models::{SyncBudget, SyncContext},
+    schemas::generated_schemas::{Function, HasVersion, Project, Version},
+    sync::DataSynchronizer,
+    utils, FStorage,
+};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+const DEADLINE: Duration = Duration::from_secs(120);
+const WRITERS: usize = 8;
+const ROWS_PER_WRITER: usize = 5;
+
+fn functions(writer: usize, count: usize) -> Vec<Function> {
+    (0..count)
+        .map(|row| Function {
+            version_sha: Some("sha-stress".to_string()),
+            file_path: Some(format!("src/writer_{writer}.rs")),
+            name: Some(format!("function::search_{writer}_{row}")),
+            signature: Some(format!("fn search_{writer}_{row}()")),
+            start_line: Some(row as i64),
+            end_line: Some(row as i64 + 1),
+            is_component: Some(false),
+        })
+        .collect()
+}
+
+async fn function_rows(storage: &FStorage) -> anyhow::Result<u64> {
+    let result = storage
+        .lake
+        .query_table_sql(
+            &Function::table_name(),
+            "SELECT COUNT(*) AS n FROM {{table}}",
+            1,
+        )
+        .await?;
+    Ok(result
+        .rows
+        .first()
+        .and_then(|row| row.get("n"))
+        .and_then(Value::as_u64)
+        .unwrap_or_default())
+}
+
+/// Waits for every task in `tasks`, failing on the first error or when the
+/// deadline passes first.
+async fn join_all(mut tasks: JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
+    tokio::time::timeout(DEADLINE, async {
+        while let Some(joined) = tasks.join_next().await {
+            joined??;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("tasks still running after {DEADLINE:?}; deadlock?"))?
+}
+
+/// Writes `ROWS_PER_WRITER` functions for the `writer` param on every fetch.
+struct StressFetcher {
+    name: &'static str,
+}
+
+#[async_trait::async_trait]
+impl Fetcher for StressFetcher {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn capability(&self) -> FetcherCapability {
+        FetcherCapability {
+            name: self.name,
+            description: "Fetcher writing a few functions per call",
+            param_schema: json!({"type": "object"}),
+            produces: Vec::new(),
+            default_ttl_secs: None,
+            examples: Vec::new(),
+        }
+    }
+
+    async fn probe(&self, _params: Value) -> fstorage::errors::Result<ProbeReport> {
+        Ok(ProbeReport {
+            fresh: None,
+            remote_anchor: None,
+            local_anchor: None,
+            anchor_key: None,
+            estimated_missing: None,
+            rate_limit_left: None,
+            reason: None,
+        })
+    }
+
+    async fn fetch(
+        &self,
+        params: Value,
+        _embedding_provider: Arc<dyn EmbeddingProvider>,
+    ) -> fstorage::errors::Result<FetchResponse> {
+        let writer = params.get("writer").and_then(Value::as_u64).unwrap_or(0) as usize;
+        let mut graph = GraphData::new();
+        graph.add_entities(functions(writer, ROWS_PER_WRITER));
+        Ok(FetchResponse::GraphData(graph))
+    }
+}
+
+const FETCHER_NAMES: [&str; 4] = ["stress_0", "stress_1", "stress_2", "stress_3"];
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_writes_to_one_table_keep_every_row() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let mut tasks = JoinSet::new();
+    for writer in 0..WRITERS {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            let mut graph = GraphData::new();
+            graph.add_entities(functions(writer, ROWS_PER_WRITER));
+            storage.synchronizer.process_graph_data(graph).await?;
+            Ok(())
+        });
+    }
+    join_all(tasks).await?;
+
+    let expected = (WRITERS * ROWS_PER_WRITER) as u64;
+    assert_eq!(function_rows(&storage).await?, expected);
+    let stats = storage.lake.graph_statistics().await?;
+    assert_eq!(
+        stats.nodes_by_label.get(Function::ENTITY_TYPE).copied(),
+        Some(expected)
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn syncs_searches_and_subgraphs_run_side_by_side() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    for name in FETCHER_NAMES {
+        storage.register_fetcher(Arc::new(StressFetcher { name }));
+    }
+
+    let project_url = "https://example.com/stress";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", "sha-stress".to_string())],
+    ))
+    .to_string();
+    let mut seed = GraphData::new();
+    seed.add_entities(vec![Project {
+        url: Some(project_url.to_string()),
+        name: Some("stress".to_string()),
+        description: None,
+        language: None,
+        stars: None,
+        forks: None,
+    }]);
+    seed.add_entities(vec![Version {
+        sha: Some("sha-stress".to_string()),
+        tag: None,
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    let edge_id =
+        utils::id::stable_edge_id_u128(HasVersion::ENTITY_TYPE, &project_uuid, &version_uuid);
+    seed.add_entities(vec![HasVersion {
+        id: Some(Uuid::from_u128(edge_id).to_string()),
+        from_node_id: Some(project_uuid.clone()),
+        to_node_id: Some(version_uuid.clone()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(seed).await?;
+
+    let mut tasks = JoinSet::new();
+    for writer in 0..WRITERS {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            let fetcher = FETCHER_NAMES[writer % FETCHER_NAMES.len()];
+            storage
+                .synchronizer
+                .sync(
+                    fetcher,
+                    json!({ "writer": writer }),
+                    SyncContext {
+                        triggering_query: None,
+                        target_entities: Vec::new(),
+                    },
+                    SyncBudget::ByRequestCount(10),
+                )
+                .await?;
+            Ok(())
+        });
+    }
+    for _ in 0..WRITERS {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            let types = vec![Function::ENTITY_TYPE.to_string()];
+            storage
+                .search_hybrid_multi(&types, "search", 0.5, 10, false, None)
+                .await?;
+            Ok(())
+        });
+    }
+    for _ in 0..WRITERS {
+        let storage = Arc::clone(&storage);
+        let project_uuid = project_uuid.clone();
+        tasks.spawn(async move {
+            let subgraph = storage
+                .lake
+                .subgraph_bfs(&project_uuid, None, 1, 0, 0)
+                .await?;
+            assert_eq!(subgraph.nodes.len(), 2);
+            assert_eq!(subgraph.edges.len(), 1);
+            Ok(())
+        });
+    }
+    join_all(tasks).await?;
+
+    assert_eq!(
+        function_rows(&storage).await?,
+        (WRITERS * ROWS_PER_WRITER) as u64
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn fetcher_registry_serves_syncs_while_fetchers_are_registered() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+    storage.register_fetcher(Arc::new(StressFetcher {
+        name: FETCHER_NAMES[0],
+    }));
+
+    let mut tasks = JoinSet::new();
+    {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            for round in 0..50 {
+                let name = FETCHER_NAMES[round % FETCHER_NAMES.len()];
+                storage.register_fetcher(Arc::new(StressFetcher { name }));
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        });
+    }
+    for _ in 0..4 {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            for _ in 0..50 {
+                let listed = storage.list_fetchers_capability();
+                assert!(listed.iter().any(|cap| cap.name == FETCHER_NAMES[0]));
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        });
+    }
+    for writer in 0..4 {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            storage
+                .synchronizer
+                .sync(
+                    FETCHER_NAMES[0],
+                    json!({ "writer": writer }),
+                    SyncContext {
+                        triggering_query: None,
+                        target_entities: Vec::new(),
+                    },
+                    SyncBudget::ByRequestCount(10),
+                )
+                .await?;
+            Ok(())
+        });
+    }
+    join_all(tasks).await?;
+
+    let names: Vec<&str> = storage
+        .list_fetchers_capability()
+        .iter()
+        .map(|cap| cap.name)
+        .collect();
+    assert_eq!(names, FETCHER_NAMES);
+    assert_eq!(function_rows(&storage).await?, (4 * ROWS_PER_WRITER) as u64);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn paused_writes_wait_and_then_all_land() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = Arc::new(FStorage::new(StorageConfig::new(dir.path())).await?);
+
+    let paused = storage.synchronizer.pause_writes().await;
+    let mut tasks = JoinSet::new();
+    for writer in 0..WRITERS {
+        let storage = Arc::clone(&storage);
+        tasks.spawn(async move {
+            let mut graph = GraphData::new();
+            graph.add_entities(functions(writer, ROWS_PER_WRITER));
+            storage.synchronizer.process_graph_data(graph).await?;
+            Ok(())
+        });
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        function_rows(&storage).await.unwrap_or_default(),
+        0,
+        "writes landed while paused"
+    );
+    drop(paused);
+    join_all(tasks).await?;
+
+    assert_eq!(
+        function_rows(&storage).await?,
+        (WRITERS * ROWS_PER_WRITER) as u64
+    );
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use chrono::Utc;
+use fstorage::{
+    config::StorageConfig,
+    errors::StorageError,
+    fetch::{Fetchable, GraphData},
+    models::ConsistencyProblem,
+    schemas::generated_schemas::{Calls, Function, Project, ReadmeChunk},
+    sync::DataSynchronizer,
+    utils::id::{stable_edge_id_u128, stable_node_id_u128},
+    FStorage,
+};
+use helix_db::helix_engine::storage_core::storage_methods::StorageMethods;
+use std::collections::BTreeSet;
+use tempfile::tempdir;
+use uuid::Uuid;
+
+fn function(name: &str) -> Function {
+    Function {
+        version_sha: Some("sha".to_string()),
+        file_path: Some("src/lib.rs".to_string()),
+        name: Some(name.to_string()),
+        signature: Some(format!("fn {name}()")),
+        start_line: Some(1),
+        end_line: Some(2),
+        is_component: Some(false),
+    }
+}
+
+fn function_id(name: &str) -> String {
+    Uuid::from_u128(stable_node_id_u128(
+        Function::ENTITY_TYPE,
+        &[
+            ("version_sha", "sha".to_string()),
+            ("file_path", "src/lib.rs".to_string()),
+            ("name", name.to_string()),
+        ],
+    ))
+    .to_string()
+}
+
+#[tokio::test]
+async fn validation_finds_and_repairs_drift() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    let (caller, callee) = (function_id("caller"), function_id("callee"));
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![function("caller"), function("callee")]);
+    graph.add_entities(vec![Calls {
+        id: Some(
+            Uuid::from_u128(stable_edge_id_u128(Calls::ENTITY_TYPE, &caller, &callee)).to_string(),
+        ),
+        from_node_id: Some(caller.clone()),
+        to_node_id: Some(callee.clone()),
+        from_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        to_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        created_at: Some(Utc::now()),
+        updated_at: None,
+    }]);
+    graph.add_entities(vec![Project {
+        url: Some("https://github.com/example/repo".to_string()),
+        name: Some("repo".to_string()),
+        description: None,
+        language: Some("Rust".to_string()),
+        stars: None,
+        forks: None,
+    }]);
+    graph.add_entities(vec![ReadmeChunk {
+        id: None,
+        project_url: Some("https://github.com/example/repo".to_string()),
+        revision_sha: Some("rev".to_string()),
+        source_file: Some("README.md".to_string()),
+        start_line: Some(1),
+        end_line: Some(3),
+        text: Some("An example".to_string()),
+        embedding: Some(vec![0.1, 0.2, 0.3]),
+        embedding_model: Some("fixture-model".to_string()),
+        embedding_id: Some("chunk-1".to_string()),
+        token_count: Some(2),
+        chunk_order: Some(0),
+        created_at: Some(Utc::now()),
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let clean = storage.check_consistency(false).await?;
+    assert!(clean.issues.is_empty(), "{:?}", clean.issues);
+    assert_eq!(clean.vectors, 1);
+
+    // The engine loses a node, the rule edge to the chunk goes missing and
+    // the catalog claims versions the function table does not have.
+    let callee_key = Uuid::parse_str(&callee)?.as_u128();
+    storage
+        .lake
+        .engine_map()
+        .write(&storage.engine.storage.graph_env, |txn| {
+            storage
+                .engine
+                .storage
+                .drop_node(txn, &callee_key)
+                .map_err(StorageError::Graph)
+        })?;
+    let rule_edges: BTreeSet<String> = storage
+        .lake
+        .table_sql("silver/edges/containscontent", "SELECT id FROM {{table}}")
+        .await?
+        .iter()
+        .filter_map(|row| row.get("id")?.as_str().map(str::to_string))
+        .collect();
+    assert_eq!(rule_edges.len(), 1);
+    storage
+        .lake
+        .delete_edges("silver/edges/containscontent", &rule_edges)
+        .await?;
+    storage
+        .catalog
+        .update_ingestion_offset("silver/entities/function", 99)?;
+
+    let found = storage.check_consistency(false).await?;
+    let problems: Vec<(ConsistencyProblem, &str)> = found
+        .issues
+        .iter()
+        .map(|issue| (issue.problem, issue.table_path.as_str()))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (ConsistencyProblem::OffsetAhead, "silver/entities/function"),
+            (
+                ConsistencyProblem::UnresolvedIndexId,
+                "silver/index/function"
+            ),
+            (ConsistencyProblem::DanglingEdge, "silver/edges/calls"),
+            (
+                ConsistencyProblem::OrphanVector,
+                "silver/vectors/readmechunk"
+            ),
+        ]
+    );
+    assert_eq!(found.issues[1].id.as_deref(), Some(callee.as_str()));
+    assert_eq!(found.unrepaired(), 4);
+
+    // The node is reloaded before edges are checked, so the call edge stays.
+    let repaired = storage.check_consistency(true).await?;
+    assert_eq!(repaired.issues.len(), 3);
+    assert_eq!(repaired.unrepaired(), 0);
+    assert_eq!(
+        storage.lake.count_rows("silver/edges/calls").await?,
+        Some(1)
+    );
+    assert_eq!(
+        storage
+            .lake
+            .count_rows("silver/vectors/readmechunk")
+            .await?,
+        Some(0)
+    );
+
+    let after = storage.check_consistency(false).await?;
+    assert!(after.issues.is_empty(), "{:?}", after.issues);
+    Ok(())
+}
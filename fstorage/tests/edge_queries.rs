@@ -77,6 +77,18 @@ async fn helix_and_delta_edge_queries_both_succeed() -> anyhow::Result<()> {
     }]);
     ctx.synchronizer.process_graph_data(helix_edges).await?;
 
+    let graph_stats = ctx.lake.graph_statistics().await?;
+    assert_eq!(
+        graph_stats.nodes_by_label.get(Function::ENTITY_TYPE),
+        Some(&2)
+    );
+    assert_eq!(graph_stats.edges_by_label.get(Calls::ENTITY_TYPE), Some(&1));
+    assert_eq!(
+        graph_stats.table_rows.get(&Function::table_name()),
+        Some(&2)
+    );
+    assert_eq!(graph_stats.edge_rows.get("calls"), Some(&1));
+
     // Helix path: the node ID is a UUID, so Helix should answer directly.
     let to_uuid_string = Uuid::from_u128(to_node_id).to_string();
 
@@ -135,11 +147,21 @@ async fn helix_and_delta_edge_queries_both_succeed() -> anyhow::Result<()> {
         Some(to_name)
     );
 
+    let edge_types = ctx.lake.list_edge_types().await?;
+    let calls = edge_types
+        .iter()
+        .find(|summary| summary.label == Calls::ENTITY_TYPE)
+        .expect("calls edges should be catalogued");
+    assert!(calls.in_schema);
+    assert_eq!(calls.table_path.as_deref(), Some("silver/edges/calls"));
+    assert_eq!(calls.from_types, vec![Function::ENTITY_TYPE.to_string()]);
+
+    // The synced edge plus the legacy one written above; the earlier count
+    // must not be served once the table has moved on.
     let stats = ctx.lake.get_edge_statistics().await?;
-    assert!(
-        stats.contains_key("calls"),
-        "expected statistics to contain entry for 'calls'"
-    );
+    assert_eq!(stats.get("calls"), Some(&2));
+    let graph_stats = ctx.lake.graph_statistics().await?;
+    assert_eq!(graph_stats.edge_rows.get("calls"), Some(&2));
 
     Ok(())
 }
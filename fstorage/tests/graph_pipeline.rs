@@ -1,8 +1,20 @@
 use deltalake::{
-    arrow::array::StringArray, datafusion::execution::context::SessionContext, open_table,
+    arrow::{
+        array::{Array, ArrayRef, BooleanArray, StringArray},
+        compute::filter_record_batch,
+        datatypes::{DataType, Field, Schema},
+        record_batch::RecordBatch,
+    },
+    datafusion::execution::context::SessionContext,
+    open_table,
 };
 use fstorage::{
-    fetch::Fetchable, schemas::generated_schemas::Project, sync::DataSynchronizer, utils,
+    fetch::Fetchable,
+    progress::NoopProgress,
+    schemas::generated_schemas::{HasVersion, Project},
+    sync::DataSynchronizer,
+    transform::BatchTransform,
+    utils,
 };
 use helix_db::{
     helix_engine::storage_core::storage_methods::StorageMethods, protocol::value::Value,
@@ -93,3 +105,135 @@ async fn graph_pipeline_updates_delta_and_helix() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn rows_failing_schema_checks_are_reported_not_written() -> anyhow::Result<()> {
+    let ctx = common::init_test_context().await?;
+    let project_url = "https://github.com/example/linted";
+    let project_id = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+
+    let mut graph_data = fstorage::fetch::GraphData::new();
+    graph_data.add_entities(vec![
+        Project {
+            url: Some(project_url.to_string()),
+            name: Some("linted".to_string()),
+            description: None,
+            language: None,
+            stars: None,
+            forks: None,
+        },
+        Project {
+            url: None,
+            name: Some("no url".to_string()),
+            description: None,
+            language: None,
+            stars: None,
+            forks: None,
+        },
+    ]);
+    graph_data.add_entities(vec![HasVersion {
+        id: None,
+        from_node_id: Some(project_id),
+        to_node_id: Some("v1.0.0".to_string()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+
+    let (_, lint, _) = ctx
+        .synchronizer
+        .process_graph_data_with_progress(graph_data, &NoopProgress)
+        .await?;
+    assert_eq!(lint.rejected_rows, 2);
+    assert_eq!(
+        lint.by_entity_type[Project::ENTITY_TYPE]["null_primary_key:url"],
+        1
+    );
+    assert_eq!(
+        lint.by_entity_type[HasVersion::ENTITY_TYPE]["invalid_endpoint:to_node_id"],
+        1
+    );
+
+    assert_eq!(ctx.lake.count_rows(&Project::table_name()).await?, Some(1));
+    let edges = ctx.lake.count_rows("silver/edges/has_version").await?;
+    assert_eq!(edges.unwrap_or_default(), 0);
+    Ok(())
+}
+
+/// Keeps Rust projects only and tags them with the owning team.
+fn rust_team_transform(
+    entity_type: &str,
+    batch: RecordBatch,
+) -> fstorage::errors::Result<RecordBatch> {
+    if entity_type != Project::ENTITY_TYPE {
+        return Ok(batch);
+    }
+    let languages = batch
+        .column_by_name("language")
+        .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+        .expect("language column should be utf8");
+    let keep: BooleanArray = (0..languages.len())
+        .map(|row| Some(!languages.is_null(row) && languages.value(row) == "Rust"))
+        .collect();
+    let kept = filter_record_batch(&batch, &keep).map_err(|err| anyhow::anyhow!(err))?;
+
+    let mut fields: Vec<Field> = kept
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.as_ref().clone())
+        .collect();
+    fields.push(Field::new("team", DataType::Utf8, true));
+    let mut columns: Vec<ArrayRef> = kept.columns().to_vec();
+    columns.push(Arc::new(StringArray::from(vec![
+        "platform";
+        kept.num_rows()
+    ])));
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|err| anyhow::anyhow!(err))?)
+}
+
+#[tokio::test]
+async fn registered_transforms_filter_and_enrich_batches() -> anyhow::Result<()> {
+    let ctx = common::init_test_context().await?;
+    let transform: Arc<dyn BatchTransform> = Arc::new(rust_team_transform);
+    ctx.synchronizer.register_transform(transform);
+
+    let project = |url: &str, language: &str| Project {
+        url: Some(url.to_string()),
+        name: Some(url.rsplit('/').next().unwrap_or_default().to_string()),
+        description: None,
+        language: Some(language.to_string()),
+        stars: None,
+        forks: None,
+    };
+    let mut graph_data = fstorage::fetch::GraphData::new();
+    graph_data.add_entities(vec![
+        project("https://github.com/example/kept", "Rust"),
+        project("https://github.com/example/dropped", "Go"),
+    ]);
+    ctx.synchronizer.process_graph_data(graph_data).await?;
+
+    assert_eq!(ctx.lake.count_rows(&Project::table_name()).await?, Some(1));
+    let kept_id = utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", "https://github.com/example/kept".to_string())],
+    );
+    let dropped_id = utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", "https://github.com/example/dropped".to_string())],
+    );
+    let txn = ctx.engine.storage.graph_env.read_txn()?;
+    let node = ctx.engine.storage.get_node(&txn, &kept_id)?;
+    assert!(matches!(
+        node.properties.as_ref().and_then(|props| props.get("team")),
+        Some(Value::String(value)) if value == "platform"
+    ));
+    assert!(ctx.engine.storage.get_node(&txn, &dropped_id).is_err());
+    Ok(())
+}
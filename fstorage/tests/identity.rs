@@ -0,0 +1,141 @@
+use fstorage::{
+    config::StorageConfig,
+    fetch::{Fetchable, GraphData},
+    models::IdentityMatchStatus,
+    schemas::generated_schemas::{Developer, GitIdentity},
+    sync::DataSynchronizer,
+    utils::id::stable_node_id_u128,
+    FStorage,
+};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+fn developer(account_id: &str, login: &str, name: &str, email: Option<&str>) -> Developer {
+    Developer {
+        platform: Some("github".to_string()),
+        account_id: Some(account_id.to_string()),
+        login: Some(login.to_string()),
+        name: Some(name.to_string()),
+        company: None,
+        followers: None,
+        following: None,
+        location: None,
+        email: email.map(str::to_string),
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+fn identity(email: &str, name: &str) -> GitIdentity {
+    GitIdentity {
+        email: Some(email.to_string()),
+        name: Some(name.to_string()),
+    }
+}
+
+fn developer_id(account_id: &str, login: &str) -> String {
+    Uuid::from_u128(stable_node_id_u128(
+        Developer::ENTITY_TYPE,
+        &[
+            ("platform", "github".to_string()),
+            ("account_id", account_id.to_string()),
+            ("login", login.to_string()),
+        ],
+    ))
+    .to_string()
+}
+
+fn identity_id(email: &str) -> String {
+    Uuid::from_u128(stable_node_id_u128(
+        GitIdentity::ENTITY_TYPE,
+        &[("email", email.to_string())],
+    ))
+    .to_string()
+}
+
+#[tokio::test]
+async fn resolution_links_identities_and_honours_decisions() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        developer("1", "mona", "Mona Lisa", Some("mona@example.com")),
+        developer("2", "hubot", "Hu Bot", None),
+    ]);
+    graph.add_entities(vec![
+        identity("mona@example.com", "Mona"),
+        identity("2+hubot@users.noreply.github.com", "hubot"),
+        identity("hb@example.com", "Bot Hu"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let mona = developer_id("1", "mona");
+    let hubot = developer_id("2", "hubot");
+    let report = storage.resolve_identities().await?;
+    assert_eq!((report.developers, report.identities), (2, 3));
+    let links: Vec<_> = report
+        .matches
+        .iter()
+        .map(|link| {
+            (
+                link.developer_id.as_str(),
+                link.email.as_deref().unwrap_or_default(),
+                link.method.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            (mona.as_str(), "mona@example.com", "email"),
+            (
+                hubot.as_str(),
+                "2+hubot@users.noreply.github.com",
+                "noreply"
+            ),
+            (hubot.as_str(), "hb@example.com", "name"),
+        ]
+    );
+    assert!(report
+        .matches
+        .iter()
+        .all(|link| link.status == IdentityMatchStatus::Suggested));
+    assert_eq!(
+        storage.lake.count_rows("silver/edges/sameas").await?,
+        Some(3)
+    );
+
+    let rejected = storage
+        .decide_identity_match(
+            &hubot,
+            &identity_id("hb@example.com"),
+            IdentityMatchStatus::Rejected,
+        )
+        .await?;
+    assert_eq!(rejected.method, "name");
+    let confirmed = storage
+        .decide_identity_match(
+            &mona,
+            &identity_id("mona@example.com"),
+            IdentityMatchStatus::Confirmed,
+        )
+        .await?;
+    assert_eq!(confirmed.confidence, 1.0);
+
+    let rerun = storage.resolve_identities().await?;
+    assert_eq!(rerun.rejected_skipped, 1);
+    assert_eq!(rerun.matches.len(), 2);
+    assert_eq!(rerun.matches[0].status, IdentityMatchStatus::Confirmed);
+
+    let listed = storage.identity_matches().await?;
+    assert_eq!(listed.len(), 3);
+    let last = listed.last().expect("rejected pair listed");
+    assert_eq!(last.status, IdentityMatchStatus::Rejected);
+    assert_eq!(last.login.as_deref(), Some("hubot"));
+
+    let invalid = storage
+        .decide_identity_match(&mona, &hubot, IdentityMatchStatus::Confirmed)
+        .await;
+    assert!(invalid.is_err());
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use fstorage::{
+    config::StorageConfig,
+    errors::StorageError,
+    fetch::{Fetchable, GraphData},
+    ids,
+    schemas::generated_schemas::{Label, Project},
+    sync::DataSynchronizer,
+    FStorage,
+};
+use tempfile::tempdir;
+
+fn label(project_url: &str, name: &str) -> Label {
+    Label {
+        project_url: Some(project_url.to_string()),
+        name: Some(name.to_string()),
+        color: None,
+        description: None,
+    }
+}
+
+fn keys(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn node_ids_match_the_ones_sync_assigns() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    let url = "https://example.com/ids";
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![Project {
+        url: Some(url.to_string()),
+        name: Some("ids".to_string()),
+        description: None,
+        language: None,
+        stars: None,
+        forks: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let id = ids::node_id(Project::ENTITY_TYPE, &keys(&[("url", url)]))?;
+    assert_eq!(id.name, format!("{}|url={url}", Project::ENTITY_TYPE));
+    let node = storage
+        .lake
+        .get_node_by_id(&id.id, None)
+        .await?
+        .expect("project node under its stable id");
+    assert_eq!(node["url"], url);
+
+    let missing = ids::node_id(Project::ENTITY_TYPE, &BTreeMap::new());
+    assert!(matches!(missing, Err(StorageError::InvalidArg(message)) if message.contains("url")));
+    let unknown = ids::node_id(Project::ENTITY_TYPE, &keys(&[("url", url), ("name", "x")]));
+    assert!(matches!(unknown, Err(StorageError::InvalidArg(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn audit_reports_key_sets_sharing_an_id() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    // Both hash `label|project_url=p|name=a|name=b`.
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![
+        label("p|name=a", "b"),
+        label("p", "a|name=b"),
+        label("p", "c"),
+    ]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let report = storage.audit_ids().await?;
+    assert_eq!(report.tables, 1);
+    assert_eq!(report.rows, 3);
+    assert_eq!(report.collisions.len(), 1);
+    let collision = &report.collisions[0];
+    assert_eq!(collision.entity_type, Label::ENTITY_TYPE);
+    assert_eq!(
+        collision.id,
+        ids::node_id(
+            Label::ENTITY_TYPE,
+            &keys(&[("project_url", "p"), ("name", "a|name=b")])
+        )?
+        .id
+    );
+    assert_eq!(collision.key_sets.len(), 2);
+    Ok(())
+}
@@ -7,7 +7,10 @@ use fstorage::{
         FetchResponse, Fetchable, Fetcher, FetcherCapability, GraphData, ProbeReport,
         ProducedDataset,
     },
-    models::{EntityIdentifier, SyncBudget, SyncContext},
+    models::{
+        EntityIdentifier, PhaseStatus, SyncBudget, SyncContext, SyncPhase, SyncProgressEvent,
+    },
+    progress::SyncProgress,
     schemas::generated_schemas::Function,
     sync::DataSynchronizer,
 };
@@ -15,6 +18,17 @@ use serde_json::json;
 use tempfile::tempdir;
 use tokio::sync::Mutex;
 
+#[derive(Default)]
+struct RecordingProgress {
+    events: std::sync::Mutex<Vec<SyncProgressEvent>>,
+}
+
+impl SyncProgress for RecordingProgress {
+    fn report(&self, event: SyncProgressEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
 struct MockFetcher {
     remote_anchor: Mutex<String>,
 }
@@ -210,3 +224,45 @@ async fn storage_introspection_reports_capabilities_and_tables() -> anyhow::Resu
 
     Ok(())
 }
+
+#[tokio::test]
+async fn sync_with_progress_reports_each_phase() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    storage.register_fetcher(Arc::new(MockFetcher::new("sha-alpha")));
+
+    let progress = Arc::new(RecordingProgress::default());
+    storage
+        .synchronizer
+        .sync_with_progress(
+            MockFetcher::NAME,
+            json!({"repo": "example"}),
+            SyncContext {
+                triggering_query: None,
+                target_entities: Vec::new(),
+            },
+            SyncBudget::ByRequestCount(1),
+            progress.clone(),
+        )
+        .await?;
+
+    let events = progress.events.lock().unwrap().clone();
+    let position = |phase: SyncPhase, status: PhaseStatus| {
+        events
+            .iter()
+            .position(|event| event.phase == phase && event.status == status)
+            .unwrap_or_else(|| panic!("missing {phase:?} {status:?} event"))
+    };
+    assert!(
+        position(SyncPhase::Fetch, PhaseStatus::Started)
+            < position(SyncPhase::Fetch, PhaseStatus::Finished)
+    );
+    assert!(
+        position(SyncPhase::Fetch, PhaseStatus::Finished)
+            < position(SyncPhase::LakeWrite, PhaseStatus::Running)
+    );
+    let ingested = &events[position(SyncPhase::EngineIngest, PhaseStatus::Finished)];
+    assert_eq!(ingested.completed, 1);
+    assert_eq!(ingested.total, Some(1));
+    Ok(())
+}
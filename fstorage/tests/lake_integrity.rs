@@ -0,0 +1,109 @@
+use fstorage::{
+    config::StorageConfig,
+    fetch::{Fetchable, GraphData},
+    models::FileProblem,
+    schemas::generated_schemas::{Project, Version},
+    sync::DataSynchronizer,
+    FStorage,
+};
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn parquet_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "parquet") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+async fn seeded_storage(base: &Path) -> anyhow::Result<FStorage> {
+    let storage = FStorage::new(StorageConfig::new(base)).await?;
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![Project {
+        url: Some("https://github.com/example/repo".to_string()),
+        name: Some("repo".to_string()),
+        description: None,
+        language: Some("Rust".to_string()),
+        stars: Some(1),
+        forks: None,
+    }]);
+    graph.add_entities(vec![Version {
+        sha: Some("sha-1".to_string()),
+        tag: Some("v1.0.0".to_string()),
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+    Ok(storage)
+}
+
+#[tokio::test]
+async fn verification_records_checksums_then_detects_damage() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = seeded_storage(dir.path()).await?;
+
+    let first = storage.verify_lake().await?;
+    assert!(first.is_healthy(), "{:?}", first.issues);
+    assert!(first.files >= 2);
+    assert_eq!(first.recorded, first.files);
+
+    let second = storage.verify_lake().await?;
+    assert!(second.is_healthy(), "{:?}", second.issues);
+    assert_eq!(second.recorded, 0);
+    assert_eq!(second.files, first.files);
+
+    // Flip a byte in the project file and remove the version file.
+    let lake = &storage.config.lake_path;
+    let project_file = parquet_files(&lake.join(Project::table_name()))?.remove(0);
+    let mut bytes = std::fs::read(&project_file)?;
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0xff;
+    std::fs::write(&project_file, bytes)?;
+    for file in parquet_files(&lake.join(Version::table_name()))? {
+        std::fs::remove_file(file)?;
+    }
+
+    let damaged = storage.verify_lake().await?;
+    assert!(!damaged.is_healthy());
+    let problem_of = |table: String| {
+        damaged
+            .issues
+            .iter()
+            .find(|issue| issue.table_path == table)
+            .map(|issue| issue.problem)
+    };
+    assert_eq!(
+        problem_of(Project::table_name()),
+        Some(FileProblem::ChecksumMismatch)
+    );
+    assert_eq!(
+        problem_of(Version::table_name()),
+        Some(FileProblem::Missing)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn truncated_files_are_reported_on_their_first_pass() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = seeded_storage(dir.path()).await?;
+
+    let project_file =
+        parquet_files(&storage.config.lake_path.join(Project::table_name()))?.remove(0);
+    let bytes = std::fs::read(&project_file)?;
+    std::fs::write(&project_file, &bytes[..bytes.len() / 2])?;
+
+    let report = storage.verify_lake().await?;
+    let issue = report
+        .issues
+        .iter()
+        .find(|issue| issue.table_path == Project::table_name())
+        .expect("the truncated file should be reported");
+    assert_eq!(issue.problem, FileProblem::Unreadable);
+    assert_eq!(report.recorded, report.files - 1);
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use fstorage::{
+    config::StorageConfig,
+    fetch::{Fetchable, GraphData},
+    models::MaintenanceOptions,
+    schemas::generated_schemas::Project,
+    sync::DataSynchronizer,
+    FStorage,
+};
+use tempfile::tempdir;
+
+fn project(url: &str) -> Project {
+    Project {
+        url: Some(url.to_string()),
+        name: Some("maintained".to_string()),
+        description: None,
+        language: None,
+        stars: None,
+        forks: None,
+    }
+}
+
+#[tokio::test]
+async fn vacuum_reclaims_replaced_files_only_outside_dry_runs() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+    // Every merge rewrites the table and leaves the previous files behind.
+    for n in 0..3 {
+        let mut graph = GraphData::new();
+        graph.add_entities(vec![project(&format!("https://example.com/{n}"))]);
+        storage.synchronizer.process_graph_data(graph).await?;
+    }
+    let options = MaintenanceOptions {
+        dry_run: true,
+        retention_hours: 0,
+        ..MaintenanceOptions::default()
+    };
+    let projects = |report: &fstorage::models::MaintenanceReport| {
+        report
+            .tables
+            .iter()
+            .find(|table| table.table_path == Project::table_name())
+            .cloned()
+            .expect("project table maintained")
+    };
+
+    let planned = storage.run_maintenance(&options).await?;
+    assert!(planned.dry_run);
+    assert!(projects(&planned).vacuumed_files > 0);
+    assert!(planned.vacuumed_bytes() > 0);
+    assert!(planned.engine.file_bytes >= planned.engine.used_bytes);
+    let again = storage.run_maintenance(&options).await?;
+    assert_eq!(
+        projects(&again).vacuumed_files,
+        projects(&planned).vacuumed_files
+    );
+
+    let done = storage
+        .run_maintenance(&MaintenanceOptions {
+            dry_run: false,
+            ..options.clone()
+        })
+        .await?;
+    assert_eq!(
+        projects(&done).vacuumed_files,
+        projects(&planned).vacuumed_files
+    );
+    assert_eq!(
+        storage.lake.count_rows(&Project::table_name()).await?,
+        Some(3)
+    );
+    assert!(storage.verify_lake().await?.is_healthy());
+    let after = storage.run_maintenance(&options).await?;
+    assert_eq!(projects(&after).vacuumed_files, 0);
+    Ok(())
+}
@@ -109,3 +109,59 @@ async fn shortest_path_traverses_linked_nodes() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn concurrent_subgraph_reads_agree() -> anyhow::Result<()> {
+    let ctx = common::init_test_context().await?;
+
+    let project_url = "https://example.com/busy";
+    let version_sha = "v2.0.0";
+    let project_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Project::ENTITY_TYPE,
+        &[("url", project_url.to_string())],
+    ))
+    .to_string();
+    let version_uuid = Uuid::from_u128(utils::id::stable_node_id_u128(
+        Version::ENTITY_TYPE,
+        &[("sha", version_sha.to_string())],
+    ))
+    .to_string();
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![Project {
+        url: Some(project_url.to_string()),
+        name: Some("busy-repo".to_string()),
+        description: None,
+        language: None,
+        stars: None,
+        forks: None,
+    }]);
+    graph.add_entities(vec![Version {
+        sha: Some(version_sha.to_string()),
+        tag: Some("v2.0.0".to_string()),
+        is_head: Some(true),
+        created_at: None,
+    }]);
+    let edge_id =
+        utils::id::stable_edge_id_u128(HasVersion::ENTITY_TYPE, &project_uuid, &version_uuid);
+    graph.add_entities(vec![HasVersion {
+        id: Some(Uuid::from_u128(edge_id).to_string()),
+        from_node_id: Some(project_uuid.clone()),
+        to_node_id: Some(version_uuid.clone()),
+        from_node_type: Some("project".to_string()),
+        to_node_type: Some("version".to_string()),
+        created_at: None,
+        updated_at: None,
+    }]);
+    ctx.synchronizer.process_graph_data(graph).await?;
+
+    // More reads than there are read workers, so some of them queue.
+    let reads = (0..32).map(|_| ctx.lake.subgraph_bfs(&project_uuid, None, 1, 0, 0));
+    for subgraph in futures::future::join_all(reads).await {
+        let subgraph = subgraph?;
+        assert_eq!(subgraph.nodes.len(), 2);
+        assert_eq!(subgraph.edges.len(), 1);
+    }
+
+    Ok(())
+}
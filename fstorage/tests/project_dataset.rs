@@ -0,0 +1,170 @@
+use fstorage::{
+    config::StorageConfig,
+    dataset::MANIFEST_FILE,
+    errors::StorageError,
+    fetch::{Fetchable, GraphData},
+    schemas::generated_schemas::{ContributesTo, Developer, HasVersion, Project, Version},
+    sync::DataSynchronizer,
+    utils, FStorage,
+};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+fn node_id<T: Fetchable>(keys: &[(&'static str, &str)]) -> String {
+    let values: Vec<(&str, String)> = T::primary_keys()
+        .into_iter()
+        .filter_map(|key| {
+            keys.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (key, value.to_string()))
+        })
+        .collect();
+    Uuid::from_u128(utils::id::stable_node_id_u128(T::ENTITY_TYPE, &values)).to_string()
+}
+
+fn project(url: &str) -> Project {
+    Project {
+        url: Some(url.to_string()),
+        name: Some(url.rsplit('/').next().unwrap_or(url).to_string()),
+        description: None,
+        language: Some("Rust".to_string()),
+        stars: None,
+        forks: None,
+    }
+}
+
+fn version(sha: &str) -> Version {
+    Version {
+        sha: Some(sha.to_string()),
+        tag: None,
+        is_head: Some(true),
+        created_at: None,
+    }
+}
+
+/// Two projects with a version each and a developer contributing to both.
+async fn seed(storage: &FStorage, urls: [&str; 2]) -> anyhow::Result<()> {
+    let developer_keys = [
+        ("platform", "github"),
+        ("account_id", "1"),
+        ("login", "octocat"),
+    ];
+    let developer_id = node_id::<Developer>(&developer_keys);
+
+    let mut graph = GraphData::new();
+    graph.add_entities(urls.iter().map(|url| project(url)).collect());
+    graph.add_entities(vec![version("sha-a"), version("sha-b")]);
+    graph.add_entities(vec![Developer {
+        platform: Some("github".to_string()),
+        account_id: Some("1".to_string()),
+        login: Some("octocat".to_string()),
+        name: None,
+        company: None,
+        followers: None,
+        following: None,
+        location: None,
+        email: None,
+        created_at: None,
+        updated_at: None,
+    }]);
+    let mut has_version = Vec::new();
+    let mut contributes_to = Vec::new();
+    for (url, sha) in urls.iter().zip(["sha-a", "sha-b"]) {
+        let project_id = node_id::<Project>(&[("url", url)]);
+        has_version.push(HasVersion {
+            id: None,
+            from_node_id: Some(project_id.clone()),
+            to_node_id: Some(node_id::<Version>(&[("sha", sha)])),
+            from_node_type: Some(Project::ENTITY_TYPE.to_string()),
+            to_node_type: Some(Version::ENTITY_TYPE.to_string()),
+            created_at: None,
+            updated_at: None,
+        });
+        contributes_to.push(ContributesTo {
+            id: None,
+            from_node_id: Some(developer_id.clone()),
+            to_node_id: Some(project_id),
+            from_node_type: Some(Developer::ENTITY_TYPE.to_string()),
+            to_node_type: Some(Project::ENTITY_TYPE.to_string()),
+            created_at: None,
+            updated_at: None,
+        });
+    }
+    graph.add_entities(has_version);
+    graph.add_entities(contributes_to);
+    storage.synchronizer.process_graph_data(graph).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn exported_project_imports_into_a_fresh_store() -> anyhow::Result<()> {
+    let (source_dir, target_dir, out_dir) = (tempdir()?, tempdir()?, tempdir()?);
+    let source = FStorage::new(StorageConfig::new(source_dir.path())).await?;
+    let urls = [
+        "https://github.com/example/a",
+        "https://github.com/example/b",
+    ];
+    seed(&source, urls).await?;
+
+    let out = out_dir.path().join("a");
+    let manifest = source.export_project(urls[0], &out).await?;
+    assert_eq!(manifest.project_url, urls[0]);
+    assert!(out.join(MANIFEST_FILE).exists());
+    let rows = |path: &str| {
+        manifest
+            .files
+            .iter()
+            .find(|file| file.path == path)
+            .map(|file| file.rows)
+    };
+    assert_eq!(rows("entities/project.parquet"), Some(1));
+    assert_eq!(rows("entities/version.parquet"), Some(1));
+    assert_eq!(rows("entities/developer.parquet"), Some(1));
+    assert_eq!(rows("edges/hasversion.parquet"), Some(1));
+    assert_eq!(rows("edges/contributesto.parquet"), Some(1));
+
+    let target = FStorage::new(StorageConfig::new(target_dir.path())).await?;
+    let imported = target.import_dataset(&out).await?;
+    assert_eq!(imported.project_id, manifest.project_id);
+    for (table, expected) in [
+        (Project::table_name(), 1),
+        (Version::table_name(), 1),
+        (Developer::table_name(), 1),
+        ("silver/edges/hasversion".to_string(), 1),
+        ("silver/edges/contributesto".to_string(), 1),
+    ] {
+        assert_eq!(
+            target.lake.count_rows(&table).await?,
+            Some(expected),
+            "{table}"
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn export_rejects_unknown_projects_and_used_directories() -> anyhow::Result<()> {
+    let (source_dir, out_dir) = (tempdir()?, tempdir()?);
+    let source = FStorage::new(StorageConfig::new(source_dir.path())).await?;
+    let urls = [
+        "https://github.com/example/a",
+        "https://github.com/example/b",
+    ];
+    seed(&source, urls).await?;
+
+    let missing = source
+        .export_project(
+            "https://github.com/example/missing",
+            &out_dir.path().join("x"),
+        )
+        .await;
+    assert!(matches!(missing, Err(StorageError::NotFound(_))));
+
+    std::fs::write(out_dir.path().join("stale.txt"), "left over")?;
+    let used = source.export_project(urls[0], out_dir.path()).await;
+    assert!(matches!(used, Err(StorageError::InvalidArg(_))));
+
+    let no_manifest = source.import_dataset(&out_dir.path().join("x")).await;
+    assert!(matches!(no_manifest, Err(StorageError::NotFound(_))));
+    Ok(())
+}
@@ -1,11 +1,13 @@
 use chrono::Utc;
 use fstorage::{
+    errors::StorageError,
     fetch::{Fetchable, GraphData},
     lake::{NeighborDirection, NeighborEdgeOrientation},
     schemas::generated_schemas::{Calls, Function, Project, ReadmeChunk},
     sync::DataSynchronizer,
     utils,
 };
+use futures::TryStreamExt;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -308,6 +310,54 @@ async fn query_api_covers_hot_and_cold_paths() -> anyhow::Result<()> {
         .await?;
     assert_eq!(sql_rows.len(), 3);
 
+    let capped = ctx
+        .lake
+        .query_table_sql(&table_name, "SELECT name FROM {{table}} ORDER BY name", 2)
+        .await?;
+    assert_eq!(capped.columns, vec!["name".to_string()]);
+    assert_eq!(capped.rows.len(), 2);
+    assert!(capped.truncated);
+    assert!(matches!(
+        ctx.lake
+            .query_table_sql(&table_name, "DROP TABLE {{table}}", 10)
+            .await,
+        Err(StorageError::InvalidArg(_))
+    ));
+    assert!(matches!(
+        ctx.lake.query_table_sql("../outside", "SELECT 1", 10).await,
+        Err(StorageError::InvalidArg(_))
+    ));
+
+    let streamed = ctx
+        .lake
+        .stream_table_sql(
+            &table_name,
+            "SELECT name FROM {{table}} ORDER BY name",
+            None,
+        )
+        .await?;
+    assert_eq!(streamed.columns, vec!["name".to_string()]);
+    let streamed_rows: Vec<_> = streamed.rows.try_concat().await?;
+    assert_eq!(streamed_rows.len(), 3);
+    assert_eq!(get_scalar(&streamed_rows[0], "name"), Some(function_b));
+
+    let across = ctx
+        .lake
+        .query_lake_sql(
+            &format!(
+                "SELECT name FROM {} ORDER BY name",
+                table_name.replace('/', "_")
+            ),
+            10,
+        )
+        .await?;
+    assert_eq!(across.rows.len(), 3);
+    assert!(!across.truncated);
+    assert!(matches!(
+        ctx.lake.query_lake_sql("DROP TABLE anything", 10).await,
+        Err(StorageError::InvalidArg(_))
+    ));
+
     {
         let mut txn = ctx.engine.storage.graph_env.write_txn()?;
         ctx.engine.storage.nodes_db.delete(&mut txn, &node_b_id)?;
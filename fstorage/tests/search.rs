@@ -1,16 +1,24 @@
 use chrono::Utc;
 use fstorage::{
+    errors::StorageError,
     fetch::{Fetchable, GraphData},
-    schemas::generated_schemas::{Function, Project, ReadmeChunk},
+    lake::IndexPosition,
+    models::MultiEntitySearchHit,
+    rerank::Reranker,
+    schemas::generated_schemas::{Calls, Class, Contains, File, Function, Project, ReadmeChunk},
     sync::DataSynchronizer,
+    utils::id::{stable_edge_id_u128, stable_node_id_u128},
     FStorage,
 };
 use heed3::RoTxn;
 use helix_db::helix_engine::traversal_core::ops::{g::G, vectors::insert::InsertVAdapter};
 use helix_db::helix_engine::vector_core::hnsw::HNSW;
 use helix_db::helix_engine::vector_core::vector::HVector;
-use std::collections::HashSet;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tempfile::tempdir;
+use uuid::Uuid;
 
 #[tokio::test]
 async fn bm25_search_returns_expected_nodes() -> anyhow::Result<()> {
@@ -139,7 +147,7 @@ async fn hybrid_search_falls_back_to_bm25() -> anyhow::Result<()> {
     storage.synchronizer.process_graph_data(graph).await?;
 
     let hits = storage
-        .search_hybrid(Function::ENTITY_TYPE, "hybrid search", 0.5, 5)
+        .search_hybrid(Function::ENTITY_TYPE, "hybrid search", 0.5, 5, None)
         .await?;
     assert!(
         !hits.is_empty(),
@@ -265,7 +273,7 @@ async fn hybrid_multi_search_aggregates_across_entities() -> anyhow::Result<()>
         ReadmeChunk::ENTITY_TYPE.to_string(),
     ];
     let hits = storage
-        .search_hybrid_multi(&entity_types, "hybrid search example", 0.5, 10)
+        .search_hybrid_multi(&entity_types, "hybrid search example", 0.5, 10, false, None)
         .await?;
 
     assert!(
@@ -286,5 +294,298 @@ async fn hybrid_multi_search_aggregates_across_entities() -> anyhow::Result<()>
         seen_types.contains(ReadmeChunk::ENTITY_TYPE),
         "expected readme chunk hits"
     );
+
+    let unknown_model = storage
+        .search_hybrid_multi(
+            &entity_types,
+            "hybrid search example",
+            0.5,
+            10,
+            false,
+            Some("no-such-model"),
+        )
+        .await;
+    assert!(matches!(unknown_model, Err(StorageError::InvalidArg(_))));
+    Ok(())
+}
+
+/// Prefers documents mentioning `beta`.
+struct KeywordReranker;
+
+#[async_trait::async_trait]
+impl Reranker for KeywordReranker {
+    async fn rerank(
+        &self,
+        _query: &str,
+        documents: Vec<String>,
+    ) -> fstorage::errors::Result<Vec<f32>> {
+        Ok(documents
+            .iter()
+            .map(|document| if document.contains("beta") { 1.0 } else { 0.0 })
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn hybrid_multi_search_reranks_candidates() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = fstorage::config::StorageConfig::new(dir.path());
+    let storage = FStorage::new(config).await?;
+
+    let mut graph = GraphData::new();
+    graph.add_entities(
+        ["alpha", "beta"]
+            .into_iter()
+            .map(|name| Function {
+                version_sha: Some("sha-rerank".to_string()),
+                file_path: Some("src/search.rs".to_string()),
+                name: Some(format!("function::search_{name}")),
+                signature: Some(format!("fn search({name}: u8)")),
+                start_line: Some(1),
+                end_line: Some(10),
+                is_component: Some(false),
+            })
+            .collect(),
+    );
+    storage.synchronizer.process_graph_data(graph).await?;
+    let entity_types = vec![Function::ENTITY_TYPE.to_string()];
+
+    let unconfigured = storage
+        .search_hybrid_multi(&entity_types, "search", 1.0, 1, true, None)
+        .await;
+    assert!(matches!(unconfigured, Err(StorageError::InvalidArg(_))));
+
+    let storage = storage.with_reranker(Arc::new(KeywordReranker));
+    let hits = storage
+        .search_hybrid_multi(&entity_types, "search", 1.0, 1, true, None)
+        .await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].rerank_score, Some(1.0));
+    let signature = hits[0]
+        .node
+        .as_ref()
+        .and_then(|node| node.get("properties"))
+        .and_then(|properties| properties.get("signature"))
+        .and_then(|value| value.as_str());
+    assert_eq!(signature, Some("fn search(beta: u8)"));
+    Ok(())
+}
+
+fn code_id(entity_type: &str, file_path: &str, name: &str) -> String {
+    Uuid::from_u128(stable_node_id_u128(
+        entity_type,
+        &[
+            ("version_sha", "sha-expand".to_string()),
+            ("file_path", file_path.to_string()),
+            ("name", name.to_string()),
+        ],
+    ))
+    .to_string()
+}
+
+fn search_hit(score: f32, fields: serde_json::Value, is_node: bool) -> MultiEntitySearchHit {
+    let fields: HashMap<String, serde_json::Value> = serde_json::from_value(fields).unwrap();
+    MultiEntitySearchHit {
+        entity_type: String::new(),
+        score,
+        rerank_score: None,
+        summary: None,
+        node: is_node.then(|| fields.clone()),
+        vector: (!is_node).then_some(fields),
+        expansion: None,
+    }
+}
+
+#[tokio::test]
+async fn graph_expansion_adds_callees_files_and_sibling_functions() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let config = fstorage::config::StorageConfig::new(dir.path());
+    let storage = FStorage::new(config).await?;
+
+    let function = |file_path: &str, name: &str| Function {
+        version_sha: Some("sha-expand".to_string()),
+        file_path: Some(file_path.to_string()),
+        name: Some(name.to_string()),
+        signature: Some(format!("fn {name}()")),
+        start_line: Some(1),
+        end_line: Some(2),
+        is_component: Some(false),
+    };
+    let file_id = Uuid::from_u128(stable_node_id_u128(
+        File::ENTITY_TYPE,
+        &[
+            ("version_sha", "sha-expand".to_string()),
+            ("path", "src/parse.rs".to_string()),
+        ],
+    ))
+    .to_string();
+    let parse = code_id(Function::ENTITY_TYPE, "src/parse.rs", "parse");
+    let lex = code_id(Function::ENTITY_TYPE, "src/parse.rs", "lex");
+    let render = code_id(Function::ENTITY_TYPE, "src/parse.rs", "render");
+    let parser = code_id(Class::ENTITY_TYPE, "src/parse.rs", "Parser");
+    let helper = code_id(Function::ENTITY_TYPE, "src/util.rs", "helper");
+
+    let mut graph = GraphData::new();
+    graph.add_entities(vec![File {
+        version_sha: Some("sha-expand".to_string()),
+        path: Some("src/parse.rs".to_string()),
+        language: Some("rust".to_string()),
+    }]);
+    graph.add_entities(vec![
+        function("src/parse.rs", "parse"),
+        function("src/parse.rs", "lex"),
+        function("src/parse.rs", "render"),
+        function("src/util.rs", "helper"),
+    ]);
+    graph.add_entities(vec![Class {
+        version_sha: Some("sha-expand".to_string()),
+        file_path: Some("src/parse.rs".to_string()),
+        name: Some("Parser".to_string()),
+        start_line: Some(1),
+        end_line: Some(9),
+    }]);
+    graph.add_entities(
+        [
+            (&parse, Function::ENTITY_TYPE),
+            (&lex, Function::ENTITY_TYPE),
+            (&render, Function::ENTITY_TYPE),
+            (&parser, Class::ENTITY_TYPE),
+        ]
+        .into_iter()
+        .map(|(to, to_type)| Contains {
+            id: Some(
+                Uuid::from_u128(stable_edge_id_u128(Contains::ENTITY_TYPE, &file_id, to))
+                    .to_string(),
+            ),
+            from_node_id: Some(file_id.clone()),
+            to_node_id: Some(to.clone()),
+            from_node_type: Some(File::ENTITY_TYPE.to_string()),
+            to_node_type: Some(to_type.to_string()),
+            created_at: Some(Utc::now()),
+            updated_at: None,
+        })
+        .collect(),
+    );
+    graph.add_entities(vec![Calls {
+        id: Some(
+            Uuid::from_u128(stable_edge_id_u128(Calls::ENTITY_TYPE, &parse, &helper)).to_string(),
+        ),
+        from_node_id: Some(parse.clone()),
+        to_node_id: Some(helper.clone()),
+        from_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        to_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        created_at: Some(Utc::now()),
+        updated_at: None,
+    }]);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    // A function hit, and a code chunk hit standing for `lex`.
+    let hits = vec![
+        search_hit(1.0, json!({ "id": parse }), true),
+        search_hit(
+            0.8,
+            json!({ "id": "chunk-lex", "properties": { "source_node_id": lex } }),
+            false,
+        ),
+    ];
+    let expanded = storage.expand_search_hits(hits, 10).await?;
+    assert_eq!(expanded.len(), 5);
+    assert!(expanded[..2].iter().all(|hit| hit.expansion.is_none()));
+
+    let context: Vec<(String, f32, &str, String, u32)> = expanded[2..]
+        .iter()
+        .map(|hit| {
+            let expansion = hit.expansion.as_ref().expect("context hits say how");
+            let id = hit.node.as_ref().and_then(|node| node.get("id"));
+            (
+                id.and_then(|id| id.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                hit.score,
+                hit.entity_type.as_str(),
+                expansion.via.clone(),
+                expansion.hops,
+            )
+        })
+        .collect();
+    // Ties come out by node id.
+    let mut expected = vec![
+        (helper.clone(), 0.5, "function", "edge_calls".to_string(), 1),
+        (file_id.clone(), 0.5, "file", "edge_contains".to_string(), 1),
+    ];
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    expected.push((
+        render.clone(),
+        0.25,
+        "function",
+        "edge_contains".to_string(),
+        2,
+    ));
+    assert_eq!(context, expected);
+    assert!(expanded[2..]
+        .iter()
+        .all(|hit| hit.expansion.as_ref().unwrap().from_id == parse));
+
+    let capped = storage
+        .expand_search_hits(vec![search_hit(1.0, json!({ "id": parse }), true)], 1)
+        .await?;
+    assert_eq!(capped.len(), 2);
+    assert_eq!(capped[1].score, 0.5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn index_search_pages_newest_first_without_repeats() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(fstorage::config::StorageConfig::new(dir.path())).await?;
+
+    for (batch, names) in [["paged::a", "paged::b"], ["paged::c", "paged::d"]]
+        .into_iter()
+        .enumerate()
+    {
+        let mut graph = GraphData::new();
+        graph.add_entities(
+            names
+                .iter()
+                .map(|name| Function {
+                    version_sha: Some(format!("sha-paged-{batch}")),
+                    file_path: Some("src/lib.rs".to_string()),
+                    name: Some(name.to_string()),
+                    signature: None,
+                    start_line: Some(1),
+                    end_line: Some(2),
+                    is_component: Some(false),
+                })
+                .collect(),
+        );
+        storage.synchronizer.process_graph_data(graph).await?;
+    }
+
+    let mut positions = Vec::new();
+    let mut after: Option<IndexPosition> = None;
+    loop {
+        let rows = storage
+            .lake
+            .search_index_nodes_after(Function::ENTITY_TYPE, "paged::", after.as_ref(), 1)
+            .await?;
+        let Some(row) = rows.first() else {
+            break;
+        };
+        let position = IndexPosition::of(row).expect("index rows carry an id");
+        positions.push(position.clone());
+        after = Some(position);
+    }
+
+    let ids: HashSet<&str> = positions.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(positions.len(), 4, "{positions:?}");
+    assert_eq!(ids.len(), 4);
+    for pair in positions.windows(2) {
+        let (newer, older) = (&pair[0], &pair[1]);
+        assert!(
+            newer.updated_at_micros > older.updated_at_micros
+                || (newer.updated_at_micros == older.updated_at_micros && newer.id < older.id),
+            "{newer:?} should come before {older:?}"
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,122 @@
+use chrono::Utc;
+use fstorage::{
+    config::StorageConfig,
+    errors::StorageError,
+    fetch::{Fetchable, GraphData},
+    models::StructuralEmbeddingOptions,
+    schemas::generated_schemas::{Calls, Function},
+    sync::DataSynchronizer,
+    utils::id::{stable_edge_id_u128, stable_node_id_u128},
+    FStorage,
+};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+fn function(name: &str) -> Function {
+    Function {
+        version_sha: Some("sha".to_string()),
+        file_path: Some("src/lib.rs".to_string()),
+        name: Some(name.to_string()),
+        signature: Some(format!("fn {name}()")),
+        start_line: Some(1),
+        end_line: Some(2),
+        is_component: Some(false),
+    }
+}
+
+fn function_id(name: &str) -> String {
+    Uuid::from_u128(stable_node_id_u128(
+        Function::ENTITY_TYPE,
+        &[
+            ("version_sha", "sha".to_string()),
+            ("file_path", "src/lib.rs".to_string()),
+            ("name", name.to_string()),
+        ],
+    ))
+    .to_string()
+}
+
+fn calls(from: &str, to: &str) -> Calls {
+    let (from, to) = (function_id(from), function_id(to));
+    Calls {
+        id: Some(Uuid::from_u128(stable_edge_id_u128(Calls::ENTITY_TYPE, &from, &to)).to_string()),
+        from_node_id: Some(from),
+        to_node_id: Some(to),
+        from_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        to_node_type: Some(Function::ENTITY_TYPE.to_string()),
+        created_at: Some(Utc::now()),
+        updated_at: None,
+    }
+}
+
+#[tokio::test]
+async fn node2vec_groups_call_clusters_and_replaces_earlier_runs() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let storage = FStorage::new(StorageConfig::new(dir.path())).await?;
+
+    // Two groups of functions calling each other, joined by a single call.
+    let names = ["a0", "a1", "a2", "a3", "b0", "b1", "b2", "b3"];
+    let mut edges = Vec::new();
+    for group in names.chunks(4) {
+        for (idx, from) in group.iter().enumerate() {
+            for to in &group[idx + 1..] {
+                edges.push(calls(from, to));
+            }
+        }
+    }
+    edges.push(calls("a3", "b0"));
+    let mut graph = GraphData::new();
+    graph.add_entities(names.iter().map(|name| function(name)).collect());
+    graph.add_entities(edges);
+    storage.synchronizer.process_graph_data(graph).await?;
+
+    let options = StructuralEmbeddingOptions {
+        dimensions: 16,
+        walks_per_node: 20,
+        walk_length: 10,
+        window: 3,
+        epochs: 2,
+        ..StructuralEmbeddingOptions::default()
+    };
+    let report = storage.train_structural_embeddings(&options).await?;
+    assert_eq!((report.nodes, report.edges), (8, 13));
+    assert_eq!(report.embedded.get("function"), Some(&8));
+    assert_eq!(report.replaced, 0);
+    assert_eq!(
+        storage
+            .lake
+            .count_rows("silver/vectors/nodeembedding")
+            .await?,
+        Some(8)
+    );
+
+    let similar = storage.structurally_similar(&function_id("a1"), 2).await?;
+    let ids: Vec<&str> = similar.iter().map(|hit| hit.node_id.as_str()).collect();
+    assert_eq!(ids.len(), 2);
+    let group_a: Vec<String> = ["a0", "a2", "a3"].into_iter().map(function_id).collect();
+    assert!(
+        ids.iter().all(|id| group_a.iter().any(|a| a == id)),
+        "{ids:?}"
+    );
+    assert!(similar.iter().all(|hit| hit.entity_type == "function"));
+
+    let rerun = storage.train_structural_embeddings(&options).await?;
+    assert_eq!(rerun.replaced, 8);
+    assert_eq!(
+        storage
+            .lake
+            .count_rows("silver/vectors/nodeembedding")
+            .await?,
+        Some(8)
+    );
+    assert_eq!(
+        storage.lake.count_rows("silver/edges/hasstructure").await?,
+        Some(8)
+    );
+
+    let missing = storage
+        .structurally_similar(&Uuid::new_v4().to_string(), 2)
+        .await;
+    assert!(matches!(missing, Err(StorageError::NotFound(_))));
+    Ok(())
+}
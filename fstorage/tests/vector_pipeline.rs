@@ -328,6 +328,8 @@ fn build_code_chunk(
         source_node_key: Some(source_node_key.to_string()),
         source_node_id: Some(function_id.to_string()),
         language: Some("rust".to_string()),
+        start_line: None,
+        end_line: None,
         text: Some(text),
         embedding: Some(embedding),
         embedding_model: Some("fixture-code".to_string()),
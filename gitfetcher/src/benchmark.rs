@@ -0,0 +1,237 @@
+//! Benchmark results measured at a repository snapshot's revision.
+//!
+//! A snapshot may name benchmark output files; each benchmark becomes a
+//! BenchmarkResult node of the snapshot's Version, linked to the function it
+//! measures, so results of one benchmark can be compared across versions.
+//!
+//! Criterion results are read from `cargo criterion --message-format=json`,
+//! whose `benchmark-complete` lines carry the estimates and the change against
+//! the previous run.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    code_index::check_relative,
+    error::{GitFetcherError, Result},
+};
+
+/// A benchmark output file, relative to the fetcher's benchmark directory.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BenchmarkSource {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: BenchmarkFormat,
+    /// Function names by benchmark id or group, for benchmarks whose id does
+    /// not name the function they measure.
+    #[serde(default)]
+    pub functions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkFormat {
+    #[default]
+    Criterion,
+}
+
+/// How a benchmark compares with the run it was measured against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchmarkVerdict {
+    Improved,
+    Regressed,
+    NoChange,
+}
+
+impl BenchmarkVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Improved => "improved",
+            Self::Regressed => "regressed",
+            Self::NoChange => "no_change",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkRecord {
+    /// Benchmark id, e.g. `parse/small_input`.
+    pub name: String,
+    pub unit: String,
+    pub mean: f64,
+    pub median: f64,
+    /// Confidence interval of the typical time.
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    /// Change of the mean against the previous run, in percent.
+    pub change_pct: Option<f64>,
+    pub verdict: Option<BenchmarkVerdict>,
+    /// Name of the function measured, when the source maps the benchmark to one.
+    pub function: Option<String>,
+}
+
+impl BenchmarkRecord {
+    /// Candidate function names, most specific first: the source's mapping,
+    /// then the segments of the id from the group down.
+    pub fn function_names(&self) -> Vec<&str> {
+        match &self.function {
+            Some(function) => vec![function.as_str()],
+            None => self
+                .name
+                .split('/')
+                .filter(|part| !part.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl BenchmarkSource {
+    /// Reads and parses the results under `root`, refusing paths that leave it.
+    pub fn load(&self, root: &Path) -> Result<Vec<BenchmarkRecord>> {
+        check_relative(&self.path, "benchmark results")?;
+        let text = std::fs::read_to_string(root.join(&self.path))?;
+        let mut records = match self.format {
+            BenchmarkFormat::Criterion => parse_criterion(&text)?,
+        };
+        for record in &mut records {
+            let group = record.name.split('/').next().unwrap_or_default();
+            record.function = self
+                .functions
+                .get(&record.name)
+                .or_else(|| self.functions.get(group))
+                .cloned();
+        }
+        Ok(records)
+    }
+}
+
+// --- Criterion ---
+
+#[derive(Deserialize)]
+struct CriterionMessage {
+    reason: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    typical: Option<CriterionEstimate>,
+    #[serde(default)]
+    mean: Option<CriterionEstimate>,
+    #[serde(default)]
+    median: Option<CriterionEstimate>,
+    #[serde(default)]
+    change: Option<CriterionChange>,
+}
+
+#[derive(Deserialize)]
+struct CriterionEstimate {
+    estimate: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+#[derive(Deserialize)]
+struct CriterionChange {
+    #[serde(default)]
+    mean: Option<CriterionEstimate>,
+    #[serde(default)]
+    change: Option<String>,
+}
+
+/// Parses `cargo criterion --message-format=json` output, keeping the
+/// `benchmark-complete` messages.
+pub fn parse_criterion(text: &str) -> Result<Vec<BenchmarkRecord>> {
+    let mut records = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let message: CriterionMessage = serde_json::from_str(line).map_err(|err| {
+            GitFetcherError::ParseFailure(format!("criterion line {}: {err}", number + 1))
+        })?;
+        if message.reason != "benchmark-complete" {
+            continue;
+        }
+        let missing = |field: &str| {
+            GitFetcherError::ParseFailure(format!(
+                "criterion line {}: benchmark-complete without {field}",
+                number + 1
+            ))
+        };
+        let name = message.id.ok_or_else(|| missing("id"))?;
+        let typical = message.typical.ok_or_else(|| missing("typical"))?;
+        let mean = message.mean.map_or(typical.estimate, |mean| mean.estimate);
+        let median = message
+            .median
+            .map_or(typical.estimate, |median| median.estimate);
+        let (change_pct, verdict) = match message.change {
+            // Criterion reports relative changes as fractions.
+            Some(change) => (
+                change.mean.map(|mean| mean.estimate * 100.0),
+                change.change.as_deref().and_then(|verdict| match verdict {
+                    "Improved" => Some(BenchmarkVerdict::Improved),
+                    "Regressed" => Some(BenchmarkVerdict::Regressed),
+                    "NoChange" => Some(BenchmarkVerdict::NoChange),
+                    _ => None,
+                }),
+            ),
+            None => (None, None),
+        };
+        records.push(BenchmarkRecord {
+            name,
+            unit: message.unit.unwrap_or_else(|| "ns".to_string()),
+            mean,
+            median,
+            lower_bound: typical.lower_bound,
+            upper_bound: typical.upper_bound,
+            change_pct,
+            verdict,
+            function: None,
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn criterion_messages_become_records() {
+        let estimate = |value: f64| json!({"estimate": value, "lower_bound": value - 1.0, "upper_bound": value + 1.0, "unit": "ns"});
+        let lines = [
+            json!({"reason": "benchmark-complete", "id": "parse/small_input", "unit": "ns",
+                   "typical": estimate(120.0), "mean": estimate(121.0), "median": estimate(119.5),
+                   "change": {"mean": {"estimate": 0.25, "lower_bound": 0.2, "upper_bound": 0.3, "unit": "%"},
+                              "change": "Regressed"}}),
+            json!({"reason": "group-complete", "group_name": "parse", "benchmarks": ["parse/small_input"]}),
+            json!({"reason": "benchmark-complete", "id": "lex", "unit": "ns", "typical": estimate(40.0)}),
+        ];
+        let output: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let records = parse_criterion(&output.join("\n")).expect("criterion output");
+        assert_eq!(records.len(), 2);
+
+        let parse = &records[0];
+        assert_eq!(parse.name, "parse/small_input");
+        assert_eq!((parse.mean, parse.median), (121.0, 119.5));
+        assert_eq!((parse.lower_bound, parse.upper_bound), (119.0, 121.0));
+        assert_eq!(parse.change_pct, Some(25.0));
+        assert_eq!(parse.verdict, Some(BenchmarkVerdict::Regressed));
+        assert_eq!(parse.function_names(), vec!["parse", "small_input"]);
+
+        let lex = &records[1];
+        assert_eq!((lex.mean, lex.change_pct, lex.verdict), (40.0, None, None));
+
+        assert!(matches!(
+            parse_criterion(r#"{"reason": "benchmark-complete", "id": "x"}"#),
+            Err(GitFetcherError::ParseFailure(_))
+        ));
+    }
+}
@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 use chrono::Utc;
+use fstorage::checkpoint::SyncCheckpoint;
 use octocrab::{
     models::{
+        issues::Issue,
+        pulls::PullRequest,
         repos::{Object, RepoCommit},
         Repository,
     },
@@ -9,6 +12,7 @@ use octocrab::{
     params::{self, Direction},
     Octocrab,
 };
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::{
@@ -21,6 +25,10 @@ use crate::{
     params::{RepoSnapshotParams, SearchRepoParams},
 };
 
+/// Checkpoint entries of issues and pull requests listed so far.
+const ISSUES_CURSOR_KEY: &str = "gitfetcher:issues";
+const PULLS_CURSOR_KEY: &str = "gitfetcher:pull_requests";
+
 #[derive(Debug, Clone)]
 pub struct ProbeMetadata {
     pub remote_anchor: String,
@@ -38,6 +46,20 @@ pub trait GitHubService: Send + Sync {
         params: &RepoSnapshotParams,
     ) -> Result<RepoSnapshot>;
 
+    /// Like [`GitHubService::fetch_repo_snapshot`], but records the list pages
+    /// done so far in `checkpoint` and continues after them when a stopped sync
+    /// is retried. The default ignores the checkpoint.
+    async fn fetch_repo_snapshot_resumable(
+        &self,
+        owner: &str,
+        repo: &str,
+        params: &RepoSnapshotParams,
+        checkpoint: &SyncCheckpoint,
+    ) -> Result<RepoSnapshot> {
+        let _ = checkpoint;
+        self.fetch_repo_snapshot(owner, repo, params).await
+    }
+
     async fn probe_repo_snapshot(
         &self,
         owner: &str,
@@ -47,6 +69,72 @@ pub trait GitHubService: Send + Sync {
 
     async fn search_repositories(&self, params: &SearchRepoParams)
         -> Result<Vec<SearchRepository>>;
+
+    /// Repository metadata on its own, used to enforce a [`RepoPolicy`] before the
+    /// snapshot is fetched. Services that cannot load it cheaply return `None` and
+    /// the policy is checked against the snapshot instead.
+    ///
+    /// [`RepoPolicy`]: crate::policy::RepoPolicy
+    async fn repository_info(&self, owner: &str, repo: &str) -> Result<Option<RepositoryInfo>> {
+        let _ = (owner, repo);
+        Ok(None)
+    }
+}
+
+/// Items of a paged listing collected so far, and the page to continue from.
+#[derive(Serialize, Deserialize)]
+struct PageCursor<T> {
+    next_page: Option<u32>,
+    items: Vec<T>,
+    /// Developers met on the pages done, as checkpointed.
+    developers: Vec<DeveloperProfile>,
+}
+
+impl<T: Serialize + DeserializeOwned> PageCursor<T> {
+    /// The progress an earlier attempt recorded under `key`, with its
+    /// developers merged into `developers`; the first page when there is none.
+    fn resume(
+        checkpoint: Option<&SyncCheckpoint>,
+        key: &str,
+        developers: &mut HashMap<String, DeveloperProfile>,
+    ) -> Result<Self> {
+        let saved = match checkpoint {
+            Some(checkpoint) => checkpoint.get::<Self>(key)?,
+            None => None,
+        };
+        let Some(mut cursor) = saved else {
+            return Ok(Self {
+                next_page: Some(1),
+                items: Vec::new(),
+                developers: Vec::new(),
+            });
+        };
+        log::info!(
+            "Resuming {key} with {} item(s) from a checkpoint",
+            cursor.items.len()
+        );
+        for developer in cursor.developers.drain(..) {
+            developers
+                .entry(developer.account_id.clone())
+                .or_insert(developer);
+        }
+        Ok(cursor)
+    }
+
+    fn save(
+        &mut self,
+        checkpoint: Option<&SyncCheckpoint>,
+        key: &str,
+        developers: &HashMap<String, DeveloperProfile>,
+    ) -> Result<()> {
+        let Some(checkpoint) = checkpoint else {
+            return Ok(());
+        };
+        self.developers = developers.values().cloned().collect();
+        checkpoint.put(key, self)?;
+        self.developers.clear();
+        Ok(())
+    }
 }
 
 pub struct OctocrabService {
@@ -83,6 +171,8 @@ impl OctocrabService {
             stargazers_count,
             forks_count,
             default_branch,
+            size,
+            license,
             ..
         } = repo;
 
@@ -109,6 +199,10 @@ impl OctocrabService {
             stargazers: stargazers_count.unwrap_or(0) as u64,
             forks: forks_count.unwrap_or(0) as u64,
             default_branch,
+            size_kb: size.map(u64::from),
+            license: license
+                .map(|license| license.spdx_id)
+                .filter(|spdx| !spdx.is_empty() && spdx != "NOASSERTION"),
         }
     }
 
@@ -219,6 +313,13 @@ impl OctocrabService {
         let commit = self.fetch_commit_object(owner, repo, sha).await?;
         let message = commit.commit.message.clone();
         let author_login = commit.author.as_ref().map(|author| author.login.clone());
+        let git_author = commit.commit.author.as_ref();
+        let author_name = git_author
+            .map(|author| author.name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        let author_email = git_author
+            .map(|author| author.email.trim().to_lowercase())
+            .filter(|email| !email.is_empty());
         let authored_at = commit
             .commit
             .author
@@ -230,6 +331,8 @@ impl OctocrabService {
             sha: commit.sha,
             message,
             author: author_login,
+            author_name,
+            author_email,
             authored_at,
         })
     }
@@ -271,238 +374,340 @@ impl OctocrabService {
         project_url: &str,
         params: &RepoSnapshotParams,
         developers: &mut HashMap<String, DeveloperProfile>,
+        checkpoint: Option<&SyncCheckpoint>,
     ) -> Result<Vec<IssueInfo>> {
         log::info!("Loading issues for {owner}/{repo}");
-        let mut collected = Vec::new();
-        let mut page = self
-            .client
-            .issues(owner, repo)
-            .list()
-            .state(params::State::All)
-            .sort(params::issues::Sort::Updated)
-            .direction(Direction::Descending)
-            .per_page(100)
-            .send()
-            .await?;
-        let mut issues = page.take_items();
-        while let Some(next) = self.client.get_page(&page.next).await? {
-            page = next;
-            issues.extend(page.take_items());
+        let mut cursor = PageCursor::resume(checkpoint, ISSUES_CURSOR_KEY, developers)?;
+
+        while let Some(page_number) = cursor.next_page {
+            let mut page = self
+                .client
+                .issues(owner, repo)
+                .list()
+                .state(params::State::All)
+                .sort(params::issues::Sort::Updated)
+                .direction(Direction::Descending)
+                .per_page(100)
+                .page(page_number)
+                .send()
+                .await?;
+            let has_next = page.next.is_some();
+            for issue in page.take_items() {
+                if let Some(issue) = self
+                    .map_issue(owner, repo, project_url, params, developers, issue)
+                    .await?
+                {
+                    cursor.items.push(issue);
+                }
+            }
+            cursor.next_page = has_next.then_some(page_number + 1);
+            cursor.save(checkpoint, ISSUES_CURSOR_KEY, developers)?;
         }
 
-        let comment_limit = params.representative_comment_limit.unwrap_or(16) * 4;
+        Ok(cursor.items)
+    }
 
-        for issue in issues.into_iter() {
-            if issue.pull_request.is_some() {
-                continue;
+    async fn load_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        project_url: &str,
+        params: &RepoSnapshotParams,
+        developers: &mut HashMap<String, DeveloperProfile>,
+        checkpoint: Option<&SyncCheckpoint>,
+    ) -> Result<Vec<PullRequestInfo>> {
+        log::info!("Loading pull requests for {owner}/{repo}");
+        let mut cursor = PageCursor::resume(checkpoint, PULLS_CURSOR_KEY, developers)?;
+
+        while let Some(page_number) = cursor.next_page {
+            let mut page = self
+                .client
+                .pulls(owner, repo)
+                .list()
+                .state(params::State::All)
+                .sort(params::pulls::Sort::Updated)
+                .direction(Direction::Descending)
+                .per_page(100)
+                .page(page_number)
+                .send()
+                .await?;
+            let has_next = page.next.is_some();
+            for pr in page.take_items() {
+                let pr = self
+                    .map_pull_request(owner, repo, project_url, params, developers, pr)
+                    .await?;
+                cursor.items.push(pr);
             }
+            cursor.next_page = has_next.then_some(page_number + 1);
+            cursor.save(checkpoint, PULLS_CURSOR_KEY, developers)?;
+        }
 
-            Self::ensure_developer(developers, &issue.user);
-            let author_login = Some(issue.user.login.clone());
-            let author_id = issue.user.id.0.to_string();
-
-            let assignees = issue
-                .assignees
-                .iter()
-                .map(|author| {
-                    Self::ensure_developer(developers, author);
-                    author.login.clone()
-                })
-                .collect::<Vec<_>>();
-
-            let labels = issue.labels.iter().map(Self::map_label).collect::<Vec<_>>();
-
-            let milestone = issue
-                .milestone
-                .as_ref()
-                .map(|milestone| milestone.title.clone());
-
-            let comments = self
-                .load_issue_comments(owner, repo, issue.number, developers, comment_limit)
-                .await?;
-            let (representative_ids, digest_text) = Self::select_representative_comments(
-                &comments,
-                params.representative_comment_limit.unwrap_or(8),
-            );
+        Ok(cursor.items)
+    }
 
-            collected.push(IssueInfo {
-                project_url: project_url.to_string(),
-                number: issue.number as i64,
-                title: issue.title.clone(),
-                body: issue.body.clone(),
-                state: format!("{:?}", issue.state),
-                author_login,
-                author_id: Some(author_id),
-                created_at: issue.created_at,
-                updated_at: Some(issue.updated_at),
-                closed_at: issue.closed_at,
-                comments_count: issue.comments as u64,
-                is_locked: issue.locked,
-                milestone,
-                assignees,
-                labels,
-                reactions: ReactionSummary::default(),
-                comments,
-                representative_comment_ids: representative_ids,
-                representative_digest_text: digest_text,
-            });
+    /// Only the `numbers` asked for, each loaded on its own instead of paging
+    /// through every issue. Numbers that are gone, or are pull requests, are skipped.
+    async fn load_target_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        project_url: &str,
+        params: &RepoSnapshotParams,
+        numbers: &[i64],
+        developers: &mut HashMap<String, DeveloperProfile>,
+    ) -> Result<Vec<IssueInfo>> {
+        log::info!(
+            "Loading {} targeted issue(s) for {owner}/{repo}",
+            numbers.len()
+        );
+        let mut collected = Vec::with_capacity(numbers.len());
+        for &number in numbers {
+            let issue = match self.client.issues(owner, repo).get(number as u64).await {
+                Ok(issue) => issue,
+                Err(octocrab::Error::GitHub { .. }) => {
+                    log::warn!("Targeted issue #{number} of {owner}/{repo} not found");
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if let Some(issue) = self
+                .map_issue(owner, repo, project_url, params, developers, issue)
+                .await?
+            {
+                collected.push(issue);
+            }
         }
-
         Ok(collected)
     }
 
-    async fn load_pull_requests(
+    /// Like [`Self::load_target_issues`], for pull requests.
+    async fn load_target_pull_requests(
         &self,
         owner: &str,
         repo: &str,
         project_url: &str,
         params: &RepoSnapshotParams,
+        numbers: &[i64],
         developers: &mut HashMap<String, DeveloperProfile>,
     ) -> Result<Vec<PullRequestInfo>> {
-        log::info!("Loading pull requests for {owner}/{repo}");
-        let mut collected = Vec::new();
-        let mut page = self
-            .client
-            .pulls(owner, repo)
-            .list()
-            .state(params::State::All)
-            .sort(params::pulls::Sort::Updated)
-            .direction(Direction::Descending)
-            .per_page(100)
-            .send()
-            .await?;
-        let mut pulls = page.take_items();
-        while let Some(next) = self.client.get_page(&page.next).await? {
-            page = next;
-            pulls.extend(page.take_items());
+        log::info!(
+            "Loading {} targeted pull request(s) for {owner}/{repo}",
+            numbers.len()
+        );
+        let mut collected = Vec::with_capacity(numbers.len());
+        for &number in numbers {
+            let pr = match self.client.pulls(owner, repo).get(number as u64).await {
+                Ok(pr) => pr,
+                Err(octocrab::Error::GitHub { .. }) => {
+                    log::warn!("Targeted pull request #{number} of {owner}/{repo} not found");
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            collected.push(
+                self.map_pull_request(owner, repo, project_url, params, developers, pr)
+                    .await?,
+            );
         }
+        Ok(collected)
+    }
 
+    async fn map_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        project_url: &str,
+        params: &RepoSnapshotParams,
+        developers: &mut HashMap<String, DeveloperProfile>,
+        issue: Issue,
+    ) -> Result<Option<IssueInfo>> {
+        // The issues API lists pull requests too; they are loaded on their own.
+        if issue.pull_request.is_some() {
+            return Ok(None);
+        }
         let comment_limit = params.representative_comment_limit.unwrap_or(16) * 4;
 
-        for pr in pulls.into_iter() {
-            if let Some(user) = pr.user.as_deref() {
-                Self::ensure_developer(developers, user);
-            }
-            if let Some(merged_by) = pr.merged_by.as_deref() {
-                Self::ensure_developer(developers, merged_by);
-            }
+        Self::ensure_developer(developers, &issue.user);
+        let author_login = Some(issue.user.login.clone());
+        let author_id = issue.user.id.0.to_string();
 
-            let author_login = pr.user.as_ref().map(|u| u.login.clone());
-            let author_id = pr.user.as_ref().map(|u| u.id.0.to_string());
-
-            let assignees = pr
-                .assignees
-                .as_ref()
-                .map(|list| {
-                    list.iter()
-                        .map(|author| {
-                            Self::ensure_developer(developers, author);
-                            author.login.clone()
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
+        let assignees = issue
+            .assignees
+            .iter()
+            .map(|author| {
+                Self::ensure_developer(developers, author);
+                author.login.clone()
+            })
+            .collect::<Vec<_>>();
 
-            let labels = pr
-                .labels
-                .as_ref()
-                .map(|list| list.iter().map(Self::map_label).collect::<Vec<_>>())
-                .unwrap_or_default();
+        let labels = issue.labels.iter().map(Self::map_label).collect::<Vec<_>>();
 
-            let issue_comments = self
-                .load_issue_comments(owner, repo, pr.number, developers, comment_limit)
-                .await?;
-            let review_comments = self
-                .load_review_comments(owner, repo, pr.number, developers, comment_limit)
-                .await?;
-            let mut all_comments = issue_comments.clone();
-            all_comments.extend(review_comments.clone());
-            let (representative_ids, digest_text) = Self::select_representative_comments(
-                &all_comments,
-                params.representative_comment_limit.unwrap_or(8),
-            );
+        let milestone = issue
+            .milestone
+            .as_ref()
+            .map(|milestone| milestone.title.clone());
 
-            let mut relations = Vec::new();
-            if let Some(title) = pr.title.as_ref() {
-                relations.extend(Self::extract_issue_links(title, owner, repo, "pr_title"));
-            }
-            if let Some(body) = pr.body.as_ref() {
-                relations.extend(Self::extract_issue_links(body, owner, repo, "pr_body"));
-            }
-            let mut relation_map: HashMap<(String, String, i64), IssueRelation> = HashMap::new();
-            for relation in relations.into_iter() {
-                let key = (
-                    relation.owner.clone(),
-                    relation.repo.clone(),
-                    relation.number,
-                );
-                relation_map
-                    .entry(key)
-                    .and_modify(|existing| {
-                        if relation.strength > existing.strength {
-                            *existing = relation.clone();
-                        }
+        let comments = self
+            .load_issue_comments(owner, repo, issue.number, developers, comment_limit)
+            .await?;
+        let (representative_ids, digest_text) = Self::select_representative_comments(
+            &comments,
+            params.representative_comment_limit.unwrap_or(8),
+        );
+
+        Ok(Some(IssueInfo {
+            project_url: project_url.to_string(),
+            number: issue.number as i64,
+            title: issue.title.clone(),
+            body: issue.body.clone(),
+            state: format!("{:?}", issue.state),
+            author_login,
+            author_id: Some(author_id),
+            created_at: issue.created_at,
+            updated_at: Some(issue.updated_at),
+            closed_at: issue.closed_at,
+            comments_count: issue.comments as u64,
+            is_locked: issue.locked,
+            milestone,
+            assignees,
+            labels,
+            reactions: ReactionSummary::default(),
+            comments,
+            representative_comment_ids: representative_ids,
+            representative_digest_text: digest_text,
+        }))
+    }
+
+    async fn map_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        project_url: &str,
+        params: &RepoSnapshotParams,
+        developers: &mut HashMap<String, DeveloperProfile>,
+        pr: PullRequest,
+    ) -> Result<PullRequestInfo> {
+        let comment_limit = params.representative_comment_limit.unwrap_or(16) * 4;
+        if let Some(user) = pr.user.as_deref() {
+            Self::ensure_developer(developers, user);
+        }
+        if let Some(merged_by) = pr.merged_by.as_deref() {
+            Self::ensure_developer(developers, merged_by);
+        }
+
+        let author_login = pr.user.as_ref().map(|u| u.login.clone());
+        let author_id = pr.user.as_ref().map(|u| u.id.0.to_string());
+
+        let assignees = pr
+            .assignees
+            .as_ref()
+            .map(|list| {
+                list.iter()
+                    .map(|author| {
+                        Self::ensure_developer(developers, author);
+                        author.login.clone()
                     })
-                    .or_insert(relation);
-            }
-            let mut related_issues: Vec<IssueRelation> = relation_map.into_values().collect();
-            related_issues.sort_by(|a, b| b.strength.cmp(&a.strength));
-            if related_issues.len() > 50 {
-                related_issues.truncate(50);
-            }
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
 
-            let base_sha = pr.base.sha.clone();
-            let head_sha = pr.head.sha.clone();
-
-            let is_cross_repo = pr
-                .head
-                .repo
-                .as_ref()
-                .and_then(|repo_info| repo_info.full_name.clone())
-                .map(|full_name| !full_name.eq_ignore_ascii_case(&format!("{owner}/{repo}")))
-                .unwrap_or(false);
-
-            collected.push(PullRequestInfo {
-                project_url: project_url.to_string(),
-                number: pr.number as i64,
-                title: pr.title.clone().unwrap_or_default(),
-                body: pr.body.clone(),
-                state: pr
-                    .state
-                    .map(|state| format!("{state:?}"))
-                    .unwrap_or_else(|| "UNKNOWN".to_string()),
-                draft: pr.draft.unwrap_or(false),
-                author_login,
-                author_id,
-                created_at: pr.created_at.unwrap_or_else(Utc::now),
-                updated_at: pr.updated_at,
-                closed_at: pr.closed_at,
-                merged: pr.merged.unwrap_or(false),
-                merged_at: pr.merged_at,
-                merged_by: pr.merged_by.as_ref().map(|user| user.login.clone()),
-                additions: pr.additions,
-                deletions: pr.deletions,
-                changed_files: pr.changed_files,
-                commits: pr.commits,
-                base_ref: Some(pr.base.ref_field.clone()),
-                head_ref: Some(pr.head.ref_field.clone()),
-                base_sha: Some(base_sha),
-                head_sha: Some(head_sha),
-                is_cross_repo,
-                comments_count: pr.comments.unwrap_or(0),
-                review_comments_count: pr.review_comments.unwrap_or(0),
-                labels,
-                assignees,
-                reactions: ReactionSummary::default(),
-                issue_comments,
-                review_comments,
-                representative_comment_ids: representative_ids,
-                representative_digest_text: digest_text,
-                related_issues,
-            });
+        let labels = pr
+            .labels
+            .as_ref()
+            .map(|list| list.iter().map(Self::map_label).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let issue_comments = self
+            .load_issue_comments(owner, repo, pr.number, developers, comment_limit)
+            .await?;
+        let review_comments = self
+            .load_review_comments(owner, repo, pr.number, developers, comment_limit)
+            .await?;
+        let mut all_comments = issue_comments.clone();
+        all_comments.extend(review_comments.clone());
+        let (representative_ids, digest_text) = Self::select_representative_comments(
+            &all_comments,
+            params.representative_comment_limit.unwrap_or(8),
+        );
+
+        let mut relations = Vec::new();
+        if let Some(title) = pr.title.as_ref() {
+            relations.extend(Self::extract_issue_links(title, owner, repo, "pr_title"));
+        }
+        if let Some(body) = pr.body.as_ref() {
+            relations.extend(Self::extract_issue_links(body, owner, repo, "pr_body"));
+        }
+        let mut relation_map: HashMap<(String, String, i64), IssueRelation> = HashMap::new();
+        for relation in relations.into_iter() {
+            let key = (
+                relation.owner.clone(),
+                relation.repo.clone(),
+                relation.number,
+            );
+            relation_map
+                .entry(key)
+                .and_modify(|existing| {
+                    if relation.strength > existing.strength {
+                        *existing = relation.clone();
+                    }
+                })
+                .or_insert(relation);
+        }
+        let mut related_issues: Vec<IssueRelation> = relation_map.into_values().collect();
+        related_issues.sort_by(|a, b| b.strength.cmp(&a.strength));
+        if related_issues.len() > 50 {
+            related_issues.truncate(50);
         }
 
-        Ok(collected)
+        let base_sha = pr.base.sha.clone();
+        let head_sha = pr.head.sha.clone();
+
+        let is_cross_repo = pr
+            .head
+            .repo
+            .as_ref()
+            .and_then(|repo_info| repo_info.full_name.clone())
+            .map(|full_name| !full_name.eq_ignore_ascii_case(&format!("{owner}/{repo}")))
+            .unwrap_or(false);
+
+        Ok(PullRequestInfo {
+            project_url: project_url.to_string(),
+            number: pr.number as i64,
+            title: pr.title.clone().unwrap_or_default(),
+            body: pr.body.clone(),
+            state: pr
+                .state
+                .map(|state| format!("{state:?}"))
+                .unwrap_or_else(|| "UNKNOWN".to_string()),
+            draft: pr.draft.unwrap_or(false),
+            author_login,
+            author_id,
+            created_at: pr.created_at.unwrap_or_else(Utc::now),
+            updated_at: pr.updated_at,
+            closed_at: pr.closed_at,
+            merged: pr.merged.unwrap_or(false),
+            merged_at: pr.merged_at,
+            merged_by: pr.merged_by.as_ref().map(|user| user.login.clone()),
+            additions: pr.additions,
+            deletions: pr.deletions,
+            changed_files: pr.changed_files,
+            commits: pr.commits,
+            base_ref: Some(pr.base.ref_field.clone()),
+            head_ref: Some(pr.head.ref_field.clone()),
+            base_sha: Some(base_sha),
+            head_sha: Some(head_sha),
+            is_cross_repo,
+            comments_count: pr.comments.unwrap_or(0),
+            review_comments_count: pr.review_comments.unwrap_or(0),
+            labels,
+            assignees,
+            reactions: ReactionSummary::default(),
+            issue_comments,
+            review_comments,
+            representative_comment_ids: representative_ids,
+            representative_digest_text: digest_text,
+            related_issues,
+        })
     }
 
     async fn load_issue_comments(
@@ -977,6 +1182,108 @@ impl OctocrabService {
         ("mentions", 1)
     }
 
+    async fn load_snapshot(
+        &self,
+        owner: &str,
+        repo: &str,
+        params: &RepoSnapshotParams,
+        checkpoint: Option<&SyncCheckpoint>,
+    ) -> Result<RepoSnapshot> {
+        let repository = self.load_repository(owner, repo).await?;
+        let revision = self
+            .resolve_revision(owner, repo, &repository, params.rev.as_deref())
+            .await?;
+        let commit = self.load_commit(owner, repo, &revision.sha).await?;
+
+        let readme = if params.include_readme {
+            self.load_readme(
+                owner,
+                repo,
+                params
+                    .rev
+                    .as_deref()
+                    .or(revision.reference.as_deref())
+                    .or(Some(&revision.sha)),
+            )
+            .await?
+        } else {
+            None
+        };
+
+        let project_url = repository.html_url.clone();
+        let mut developers_map = HashMap::new();
+
+        // A targeted sync loads its issues and pull requests one by one.
+        let targets = params
+            .targets
+            .as_ref()
+            .filter(|targets| !targets.is_empty());
+
+        let issues = match targets {
+            _ if !params.include_issues => Vec::new(),
+            Some(targets) => {
+                self.load_target_issues(
+                    owner,
+                    repo,
+                    &project_url,
+                    params,
+                    &targets.issues,
+                    &mut developers_map,
+                )
+                .await?
+            }
+            None => {
+                self.load_issues(
+                    owner,
+                    repo,
+                    &project_url,
+                    params,
+                    &mut developers_map,
+                    checkpoint,
+                )
+                .await?
+            }
+        };
+
+        let pull_requests = match targets {
+            _ if !params.include_pulls => Vec::new(),
+            Some(targets) => {
+                self.load_target_pull_requests(
+                    owner,
+                    repo,
+                    &project_url,
+                    params,
+                    &targets.pull_requests,
+                    &mut developers_map,
+                )
+                .await?
+            }
+            None => {
+                self.load_pull_requests(
+                    owner,
+                    repo,
+                    &project_url,
+                    params,
+                    &mut developers_map,
+                    checkpoint,
+                )
+                .await?
+            }
+        };
+
+        let developers = developers_map.into_values().collect();
+
+        Ok(RepoSnapshot {
+            repository,
+            revision,
+            commit,
+            readme,
+            developers,
+            issues,
+            pull_requests,
+        })
+    }
+
     fn ensure_developer(
         developers: &mut HashMap<String, DeveloperProfile>,
         author: &octocrab::models::Author,
@@ -1023,55 +1330,18 @@ impl GitHubService for OctocrabService {
         repo: &str,
         params: &RepoSnapshotParams,
     ) -> Result<RepoSnapshot> {
-        let repository = self.load_repository(owner, repo).await?;
-        let revision = self
-            .resolve_revision(owner, repo, &repository, params.rev.as_deref())
-            .await?;
-        let commit = self.load_commit(owner, repo, &revision.sha).await?;
-
-        let readme = if params.include_readme {
-            self.load_readme(
-                owner,
-                repo,
-                params
-                    .rev
-                    .as_deref()
-                    .or(revision.reference.as_deref())
-                    .or(Some(&revision.sha)),
-            )
-            .await?
-        } else {
-            None
-        };
-
-        let project_url = repository.html_url.clone();
-        let mut developers_map = HashMap::new();
-
-        let issues = if params.include_issues {
-            self.load_issues(owner, repo, &project_url, params, &mut developers_map)
-                .await?
-        } else {
-            Vec::new()
-        };
-
-        let pull_requests = if params.include_pulls {
-            self.load_pull_requests(owner, repo, &project_url, params, &mut developers_map)
-                .await?
-        } else {
-            Vec::new()
-        };
-
-        let developers = developers_map.into_values().collect();
+        self.load_snapshot(owner, repo, params, None).await
+    }
 
-        Ok(RepoSnapshot {
-            repository,
-            revision,
-            commit,
-            readme,
-            developers,
-            issues,
-            pull_requests,
-        })
+    async fn fetch_repo_snapshot_resumable(
+        &self,
+        owner: &str,
+        repo: &str,
+        params: &RepoSnapshotParams,
+        checkpoint: &SyncCheckpoint,
+    ) -> Result<RepoSnapshot> {
+        self.load_snapshot(owner, repo, params, Some(checkpoint))
+            .await
     }
 
     async fn probe_repo_snapshot(
@@ -1096,6 +1366,10 @@ impl GitHubService for OctocrabService {
         })
     }
 
+    async fn repository_info(&self, owner: &str, repo: &str) -> Result<Option<RepositoryInfo>> {
+        self.load_repository(owner, repo).await.map(Some)
+    }
+
     async fn search_repositories(
         &self,
         params: &SearchRepoParams,
@@ -0,0 +1,672 @@
+//! Precise code indexes as an alternative to tree-sitter parsing.
+//!
+//! Compiler-backed indexers resolve references that tree-sitter can only guess
+//! at, so a repository snapshot may name a SCIP or LSIF index instead of having
+//! the checkout parsed. Both formats are read into a [`CodeIndex`] of
+//! definitions and references per document, which the mapper turns into the
+//! usual File/Function/Class/Trait/DataModel/Variable nodes and `Calls` edges.
+//!
+//! SCIP indexes are read in their JSON form (`scip print --json index.scip`);
+//! LSIF dumps are JSON lines as emitted. LSIF definitions are only recognised
+//! when the indexer tags their ranges with a name and symbol kind.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{GitFetcherError, Result};
+
+/// An index file to read the code graph from, relative to the fetcher's index directory.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CodeIndexSource {
+    pub path: PathBuf,
+    /// Detected from the file extension when unset: `.lsif` and `.jsonl` are
+    /// LSIF, anything else SCIP JSON.
+    #[serde(default)]
+    pub format: Option<CodeIndexFormat>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeIndexFormat {
+    Scip,
+    Lsif,
+}
+
+impl CodeIndexSource {
+    pub fn format(&self) -> CodeIndexFormat {
+        if let Some(format) = self.format {
+            return format;
+        }
+        match self.path.extension().and_then(|ext| ext.to_str()) {
+            Some("lsif" | "jsonl") => CodeIndexFormat::Lsif,
+            _ => CodeIndexFormat::Scip,
+        }
+    }
+
+    /// Reads and parses the index under `root`, refusing paths that leave it.
+    pub fn load(&self, root: &Path) -> Result<CodeIndex> {
+        check_relative(&self.path, "code index")?;
+        let text = std::fs::read_to_string(root.join(&self.path))?;
+        match self.format() {
+            CodeIndexFormat::Scip => parse_scip_json(&text),
+            CodeIndexFormat::Lsif => parse_lsif(&text),
+        }
+    }
+}
+
+/// Rejects artifact paths that are absolute or climb out of their directory.
+pub(crate) fn check_relative(path: &Path, what: &str) -> Result<()> {
+    let escapes = path.is_absolute()
+        || path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(GitFetcherError::InvalidParam(format!(
+            "{what} path '{}' must be relative to its directory",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Node types a definition can become.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Trait,
+    DataModel,
+    Variable,
+}
+
+/// Definitions and references of a whole index, by repository-relative path.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CodeIndex {
+    pub documents: BTreeMap<String, IndexedDocument>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IndexedDocument {
+    pub language: Option<String>,
+    pub definitions: Vec<IndexedDefinition>,
+    pub references: Vec<IndexedReference>,
+}
+
+/// Lines are 0-based, as in both index formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedDefinition {
+    pub symbol: String,
+    pub kind: SymbolKind,
+    pub name: String,
+    pub signature: Option<String>,
+    pub start_line: u32,
+    /// Last line of the definition's body when the index records it.
+    pub end_line: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedReference {
+    pub symbol: String,
+    pub line: u32,
+}
+
+impl IndexedDocument {
+    /// The innermost function whose body spans `line`. Definitions without a
+    /// recorded end are taken to run until the next definition in the file.
+    pub fn enclosing_function(&self, line: u32) -> Option<&IndexedDefinition> {
+        let mut starts: Vec<u32> = self.definitions.iter().map(|def| def.start_line).collect();
+        starts.sort_unstable();
+        self.definitions
+            .iter()
+            .filter(|def| def.kind == SymbolKind::Function && def.start_line <= line)
+            .filter(|def| {
+                let end = def.end_line.unwrap_or_else(|| {
+                    starts
+                        .iter()
+                        .find(|start| **start > def.start_line)
+                        .map_or(u32::MAX, |next| next - 1)
+                });
+                line <= end
+            })
+            .max_by_key(|def| def.start_line)
+    }
+}
+
+// --- SCIP ---
+
+/// `SymbolRole.Definition` in the SCIP schema.
+const SCIP_DEFINITION_ROLE: i64 = 0x1;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScipIndex {
+    #[serde(default)]
+    documents: Vec<ScipDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScipDocument {
+    #[serde(alias = "relative_path")]
+    relative_path: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    occurrences: Vec<ScipOccurrence>,
+    #[serde(default)]
+    symbols: Vec<ScipSymbolInformation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScipOccurrence {
+    #[serde(default)]
+    range: Vec<i64>,
+    #[serde(default)]
+    symbol: String,
+    #[serde(default, alias = "symbol_roles")]
+    symbol_roles: i64,
+    #[serde(default, alias = "enclosing_range")]
+    enclosing_range: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScipSymbolInformation {
+    symbol: String,
+    /// The enum's name, or its number from indexers that write them raw.
+    #[serde(default)]
+    kind: Option<Value>,
+    #[serde(default, alias = "display_name")]
+    display_name: Option<String>,
+    #[serde(default, alias = "signature_documentation")]
+    signature_documentation: Option<ScipSignature>,
+}
+
+#[derive(Deserialize)]
+struct ScipSignature {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Parses the JSON form of a SCIP index.
+pub fn parse_scip_json(text: &str) -> Result<CodeIndex> {
+    let index: ScipIndex = serde_json::from_str(text)?;
+    let mut code_index = CodeIndex::default();
+    for document in index.documents {
+        let info: HashMap<&str, &ScipSymbolInformation> = document
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.symbol.as_str(), symbol))
+            .collect();
+        let mut indexed = IndexedDocument {
+            language: document.language.clone().filter(|lang| !lang.is_empty()),
+            ..Default::default()
+        };
+        for occurrence in &document.occurrences {
+            let Some(&line) = occurrence.range.first() else {
+                continue;
+            };
+            let Ok(line) = u32::try_from(line) else {
+                continue;
+            };
+            if occurrence.symbol.is_empty() || occurrence.symbol.starts_with("local ") {
+                continue;
+            }
+            if occurrence.symbol_roles & SCIP_DEFINITION_ROLE == 0 {
+                indexed.references.push(IndexedReference {
+                    symbol: occurrence.symbol.clone(),
+                    line,
+                });
+                continue;
+            }
+            let info = info.get(occurrence.symbol.as_str());
+            let kind = info
+                .and_then(|info| info.kind.as_ref())
+                .and_then(scip_kind)
+                .or_else(|| scip_descriptor_kind(&occurrence.symbol));
+            let Some(kind) = kind else {
+                continue;
+            };
+            let Some(name) = info
+                .and_then(|info| info.display_name.clone())
+                .filter(|name| !name.is_empty())
+                .or_else(|| scip_descriptor_name(&occurrence.symbol))
+            else {
+                continue;
+            };
+            // Enclosing ranges are [start, startChar, endChar] on one line or
+            // [start, startChar, end, endChar].
+            let end_line = match occurrence.enclosing_range.as_slice() {
+                [_, _, end, _] => u32::try_from(*end).ok(),
+                [start, _, _] => u32::try_from(*start).ok(),
+                _ => None,
+            };
+            indexed.definitions.push(IndexedDefinition {
+                symbol: occurrence.symbol.clone(),
+                kind,
+                name,
+                signature: info
+                    .and_then(|info| info.signature_documentation.as_ref())
+                    .and_then(|signature| signature.text.clone()),
+                start_line: line,
+                end_line,
+            });
+        }
+        code_index
+            .documents
+            .insert(normalize_path(&document.relative_path), indexed);
+    }
+    Ok(code_index)
+}
+
+fn scip_kind(kind: &Value) -> Option<SymbolKind> {
+    let name = match kind {
+        Value::String(name) => name.as_str(),
+        // Numbers of the `SymbolInformation.Kind` values mapped below.
+        Value::Number(number) => match number.as_i64()? {
+            7 => "Class",
+            8 => "Constant",
+            11 => "Enum",
+            17 => "Function",
+            21 => "Interface",
+            26 => "Method",
+            49 => "Struct",
+            53 => "Trait",
+            55 => "TypeAlias",
+            61 => "Variable",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match name {
+        "Function" | "Method" | "StaticMethod" | "AbstractMethod" | "Constructor" | "Macro"
+        | "Getter" | "Setter" | "ProtocolMethod" | "TraitMethod" | "PureVirtualMethod" => {
+            Some(SymbolKind::Function)
+        }
+        "Class" | "Object" | "SingletonClass" => Some(SymbolKind::Class),
+        "Trait" | "Interface" | "Protocol" | "TypeClass" => Some(SymbolKind::Trait),
+        "Struct" | "Enum" | "Union" | "TypeAlias" | "Type" | "Message" | "Record" => {
+            Some(SymbolKind::DataModel)
+        }
+        "Variable" | "Constant" | "StaticVariable" => Some(SymbolKind::Variable),
+        _ => None,
+    }
+}
+
+/// Kind implied by the last descriptor of a SCIP symbol: `name().` is a
+/// method and `Name#` a type.
+fn scip_descriptor_kind(symbol: &str) -> Option<SymbolKind> {
+    if symbol.ends_with(").") {
+        Some(SymbolKind::Function)
+    } else if symbol.ends_with('#') {
+        Some(SymbolKind::Class)
+    } else {
+        None
+    }
+}
+
+fn scip_descriptor_name(symbol: &str) -> Option<String> {
+    let descriptor = symbol.trim_end_matches(['.', '#']);
+    let descriptor = match descriptor.strip_suffix(')') {
+        Some(method) => &method[..method.rfind('(')?],
+        None => descriptor,
+    };
+    let name = descriptor
+        .rsplit([' ', '/', '#', '.'])
+        .next()?
+        .trim_matches('`');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// --- LSIF ---
+
+#[derive(Deserialize)]
+struct LsifPosition {
+    line: u32,
+}
+
+#[derive(Deserialize)]
+struct LsifFullRange {
+    end: LsifPosition,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LsifTag {
+    #[serde(rename = "type")]
+    tag_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    kind: Option<u32>,
+    #[serde(default)]
+    full_range: Option<LsifFullRange>,
+}
+
+struct LsifRange {
+    start_line: u32,
+    tag: Option<LsifTag>,
+}
+
+/// Parses an LSIF dump in JSON-lines form.
+pub fn parse_lsif(text: &str) -> Result<CodeIndex> {
+    let mut project_root = String::new();
+    let mut documents: HashMap<u64, (String, Option<String>)> = HashMap::new();
+    let mut ranges: HashMap<u64, LsifRange> = HashMap::new();
+    let mut range_document: HashMap<u64, u64> = HashMap::new();
+    // `next` edges from ranges and result sets to result sets.
+    let mut next: HashMap<u64, u64> = HashMap::new();
+    // Result set -> its definition result.
+    let mut definition_results: HashMap<u64, u64> = HashMap::new();
+    // Definition result -> the definition ranges it lists.
+    let mut definition_items: HashMap<u64, Vec<u64>> = HashMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let element: Value = serde_json::from_str(line).map_err(|err| {
+            GitFetcherError::ParseFailure(format!("LSIF line {}: {err}", number + 1))
+        })?;
+        let Some(id) = lsif_id(&element["id"]) else {
+            continue;
+        };
+        let label = element["label"].as_str().unwrap_or_default();
+        match (element["type"].as_str(), label) {
+            (Some("vertex"), "metaData") => {
+                project_root = element["projectRoot"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            (Some("vertex"), "document") => {
+                let uri = element["uri"].as_str().unwrap_or_default().to_string();
+                let language = element["languageId"].as_str().map(str::to_string);
+                documents.insert(id, (uri, language));
+            }
+            (Some("vertex"), "range") => {
+                let Some(start_line) = element["start"]["line"]
+                    .as_u64()
+                    .and_then(|line| u32::try_from(line).ok())
+                else {
+                    continue;
+                };
+                let tag = serde_json::from_value(element["tag"].clone()).ok();
+                ranges.insert(id, LsifRange { start_line, tag });
+            }
+            (Some("edge"), _) => {
+                let out_v = lsif_id(&element["outV"]);
+                let in_vs: Vec<u64> = match element["inVs"].as_array() {
+                    Some(ids) => ids.iter().filter_map(lsif_id).collect(),
+                    None => lsif_id(&element["inV"]).into_iter().collect(),
+                };
+                let Some(out_v) = out_v else {
+                    continue;
+                };
+                match label {
+                    "contains" => {
+                        for range in in_vs {
+                            range_document.insert(range, out_v);
+                        }
+                    }
+                    "next" => {
+                        if let Some(&target) = in_vs.first() {
+                            next.insert(out_v, target);
+                        }
+                    }
+                    "textDocument/definition" => {
+                        if let Some(&target) = in_vs.first() {
+                            definition_results.insert(out_v, target);
+                        }
+                    }
+                    "item" => {
+                        let is_reference = element["property"].as_str() == Some("references");
+                        if !is_reference {
+                            definition_items.entry(out_v).or_default().extend(in_vs);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The definition result a range resolves to, following `next` edges.
+    let resolve = |range: u64| -> Option<u64> {
+        let mut current = range;
+        let mut seen = HashSet::new();
+        loop {
+            if let Some(result) = definition_results.get(&current) {
+                return Some(*result);
+            }
+            if !seen.insert(current) {
+                return None;
+            }
+            current = *next.get(&current)?;
+        }
+    };
+
+    let mut code_index = CodeIndex::default();
+    let mut document_paths: HashMap<u64, String> = HashMap::new();
+    for (id, (uri, language)) in &documents {
+        let path = lsif_relative_path(uri, &project_root);
+        document_paths.insert(*id, path.clone());
+        code_index.documents.insert(
+            path,
+            IndexedDocument {
+                language: language.clone().filter(|lang| !lang.is_empty()),
+                ..Default::default()
+            },
+        );
+    }
+
+    let listed_in: HashMap<u64, u64> = definition_items
+        .iter()
+        .flat_map(|(result, items)| items.iter().map(move |range| (*range, *result)))
+        .collect();
+    for (range_id, range) in &ranges {
+        let Some(document) = range_document
+            .get(range_id)
+            .and_then(|doc| document_paths.get(doc))
+            .and_then(|path| code_index.documents.get_mut(path))
+        else {
+            continue;
+        };
+        let tag = range
+            .tag
+            .as_ref()
+            .filter(|tag| tag.tag_type == "definition");
+        let listed = listed_in.get(range_id).copied();
+        if tag.is_none() && listed.is_none() {
+            if let Some(result) = resolve(*range_id) {
+                document.references.push(IndexedReference {
+                    symbol: result.to_string(),
+                    line: range.start_line,
+                });
+            }
+            continue;
+        }
+        let Some((name, kind)) = tag.and_then(|tag| {
+            let name = tag.text.clone().filter(|name| !name.is_empty())?;
+            Some((name, lsp_kind(tag.kind?)?))
+        }) else {
+            continue;
+        };
+        let symbol = listed.or_else(|| resolve(*range_id)).unwrap_or(*range_id);
+        document.definitions.push(IndexedDefinition {
+            symbol: symbol.to_string(),
+            kind,
+            name,
+            signature: None,
+            start_line: range.start_line,
+            end_line: tag
+                .and_then(|tag| tag.full_range.as_ref())
+                .map(|full| full.end.line),
+        });
+    }
+    for document in code_index.documents.values_mut() {
+        document
+            .definitions
+            .sort_by_key(|def| (def.start_line, def.name.clone()));
+        document.references.sort_by_key(|reference| reference.line);
+    }
+    Ok(code_index)
+}
+
+/// LSIF ids may be numbers or strings.
+fn lsif_id(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// LSP `SymbolKind` values.
+fn lsp_kind(kind: u32) -> Option<SymbolKind> {
+    match kind {
+        6 | 9 | 12 => Some(SymbolKind::Function),
+        5 => Some(SymbolKind::Class),
+        11 => Some(SymbolKind::Trait),
+        10 | 23 => Some(SymbolKind::DataModel),
+        13 | 14 => Some(SymbolKind::Variable),
+        _ => None,
+    }
+}
+
+fn lsif_relative_path(uri: &str, project_root: &str) -> String {
+    let root = project_root.trim_end_matches('/');
+    let path = match uri.strip_prefix(root) {
+        Some(rest) if !root.is_empty() => rest,
+        _ => uri.strip_prefix("file://").unwrap_or(uri),
+    };
+    normalize_path(path)
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scip_occurrences_become_definitions_and_references() {
+        let index = json!({
+            "documents": [{
+                "relativePath": "src/lib.rs",
+                "language": "rust",
+                "occurrences": [
+                    {"range": [2, 7, 12], "symbol": "rust-analyzer cargo demo 0.1.0 parse().",
+                     "symbolRoles": 1, "enclosingRange": [2, 0, 6, 1]},
+                    {"range": [8, 11, 15], "symbol": "rust-analyzer cargo demo 0.1.0 Token#",
+                     "symbolRoles": 1},
+                    {"range": [4, 4, 9], "symbol": "rust-analyzer cargo demo 0.1.0 lex()."},
+                    {"range": [5, 8, 9], "symbol": "local 3"}
+                ],
+                "symbols": [{
+                    "symbol": "rust-analyzer cargo demo 0.1.0 parse().",
+                    "kind": "Function",
+                    "signatureDocumentation": {"text": "fn parse()"}
+                }]
+            }]
+        });
+        let index = parse_scip_json(&index.to_string()).expect("scip index");
+        let document = &index.documents["src/lib.rs"];
+        assert_eq!(document.language.as_deref(), Some("rust"));
+        let definitions: Vec<_> = document
+            .definitions
+            .iter()
+            .map(|def| (def.name.as_str(), def.kind, def.start_line, def.end_line))
+            .collect();
+        assert_eq!(
+            definitions,
+            vec![
+                ("parse", SymbolKind::Function, 2, Some(6)),
+                ("Token", SymbolKind::Class, 8, None),
+            ]
+        );
+        assert_eq!(
+            document.definitions[0].signature.as_deref(),
+            Some("fn parse()")
+        );
+        assert_eq!(
+            document.references,
+            vec![IndexedReference {
+                symbol: "rust-analyzer cargo demo 0.1.0 lex().".to_string(),
+                line: 4,
+            }]
+        );
+        assert_eq!(
+            document.enclosing_function(4).map(|def| def.name.as_str()),
+            Some("parse")
+        );
+        assert!(document.enclosing_function(7).is_none());
+    }
+
+    #[test]
+    fn lsif_ranges_resolve_through_result_sets() {
+        let lines = [
+            json!({"id": 1, "type": "vertex", "label": "metaData", "projectRoot": "file:///work/demo"}),
+            json!({"id": 2, "type": "vertex", "label": "document", "uri": "file:///work/demo/src/main.ts", "languageId": "typescript"}),
+            json!({"id": 3, "type": "vertex", "label": "range", "start": {"line": 0, "character": 9}, "end": {"line": 0, "character": 14},
+                   "tag": {"type": "definition", "text": "parse", "kind": 12, "fullRange": {"start": {"line": 0, "character": 0}, "end": {"line": 2, "character": 1}}}}),
+            json!({"id": 4, "type": "vertex", "label": "range", "start": {"line": 4, "character": 9}, "end": {"line": 4, "character": 13},
+                   "tag": {"type": "definition", "text": "main", "kind": 12, "fullRange": {"start": {"line": 4, "character": 0}, "end": {"line": 6, "character": 1}}}}),
+            json!({"id": 5, "type": "vertex", "label": "range", "start": {"line": 5, "character": 2}, "end": {"line": 5, "character": 7}}),
+            json!({"id": 6, "type": "vertex", "label": "resultSet"}),
+            json!({"id": 7, "type": "vertex", "label": "definitionResult"}),
+            json!({"id": 8, "type": "edge", "label": "contains", "outV": 2, "inVs": [3, 4, 5]}),
+            json!({"id": 9, "type": "edge", "label": "next", "outV": 3, "inV": 6}),
+            json!({"id": 10, "type": "edge", "label": "next", "outV": 5, "inV": 6}),
+            json!({"id": 11, "type": "edge", "label": "textDocument/definition", "outV": 6, "inV": 7}),
+            json!({"id": 12, "type": "edge", "label": "item", "outV": 7, "inVs": [3], "document": 2}),
+        ];
+        let dump: Vec<String> = lines.iter().map(Value::to_string).collect();
+        let index = parse_lsif(&dump.join("\n")).expect("lsif dump");
+        let document = &index.documents["src/main.ts"];
+        assert_eq!(document.language.as_deref(), Some("typescript"));
+        let names: Vec<_> = document
+            .definitions
+            .iter()
+            .map(|def| (def.name.as_str(), def.end_line))
+            .collect();
+        assert_eq!(names, vec![("parse", Some(2)), ("main", Some(6))]);
+        let parse = &document.definitions[0];
+        assert_eq!(
+            document.references,
+            vec![IndexedReference {
+                symbol: parse.symbol.clone(),
+                line: 5,
+            }]
+        );
+        assert_eq!(
+            document.enclosing_function(5).map(|def| def.name.as_str()),
+            Some("main")
+        );
+    }
+
+    #[test]
+    fn index_paths_must_stay_in_the_index_directory() {
+        let source = CodeIndexSource {
+            path: PathBuf::from("../secrets.lsif"),
+            format: None,
+        };
+        assert_eq!(source.format(), CodeIndexFormat::Lsif);
+        assert!(matches!(
+            source.load(Path::new("/srv/indexes")),
+            Err(GitFetcherError::InvalidParam(_))
+        ));
+    }
+}
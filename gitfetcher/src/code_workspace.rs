@@ -1,6 +1,12 @@
 use std::{
     env,
     path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use ast::{
@@ -10,12 +16,74 @@ use ast::{
 use fstorage::errors::{Result as StorageResult, StorageError};
 use git2::{
     build::{CheckoutBuilder, RepoBuilder},
-    FetchOptions, ProxyOptions, Repository,
+    FetchOptions, ProxyOptions, RemoteCallbacks, Repository,
 };
+use serde::Deserialize;
 use tempfile::TempDir;
-use tokio::task;
+use tokio::{process::Command, task};
 
 const DEFAULT_REPO_DIR: &str = "repo";
+const DEFAULT_MAX_WORKSPACE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+const DEFAULT_CLONE_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_PARSE_TIMEOUT_SECS: u64 = 1_800;
+
+/// Resource limits for the throwaway checkout that code analysis runs in.
+///
+/// Repositories are untrusted input: these bound how much disk and wall-clock
+/// time a single snapshot may consume before the sync is failed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceLimits {
+    /// Directory workspaces are created under; the system temp dir when unset.
+    pub root_dir: Option<PathBuf>,
+    /// Largest workspace accepted, counting transferred pack data and the checked-out tree.
+    pub max_bytes: Option<u64>,
+    /// Limit for cloning and checking out the requested revision.
+    pub clone_timeout_secs: Option<u64>,
+    /// Limit for language detection and AST graph construction.
+    pub parse_timeout_secs: Option<u64>,
+    /// Command prefix the clone runs under, e.g.
+    /// `["systemd-run", "--user", "--scope", "-p", "MemoryMax=1G"]` or
+    /// `["unshare", "--user", "--map-root-user"]`. When set, `git clone` is run as
+    /// a child process of that command instead of in-process.
+    pub sandbox_command: Vec<String>,
+}
+
+impl Default for WorkspaceLimits {
+    fn default() -> Self {
+        Self {
+            root_dir: None,
+            max_bytes: Some(DEFAULT_MAX_WORKSPACE_BYTES),
+            clone_timeout_secs: Some(DEFAULT_CLONE_TIMEOUT_SECS),
+            parse_timeout_secs: Some(DEFAULT_PARSE_TIMEOUT_SECS),
+            sandbox_command: Vec::new(),
+        }
+    }
+}
+
+impl WorkspaceLimits {
+    fn clone_timeout(&self) -> Option<Duration> {
+        self.clone_timeout_secs.map(Duration::from_secs)
+    }
+
+    fn parse_timeout(&self) -> Option<Duration> {
+        self.parse_timeout_secs.map(Duration::from_secs)
+    }
+
+    fn create_temp_dir(&self) -> StorageResult<TempDir> {
+        let created = match &self.root_dir {
+            Some(root) => std::fs::create_dir_all(root).and_then(|_| {
+                tempfile::Builder::new()
+                    .prefix("workspace-")
+                    .tempdir_in(root)
+            }),
+            None => TempDir::new(),
+        };
+        created.map_err(|err| {
+            StorageError::SyncError(format!("failed to create temporary directory: {err}"))
+        })
+    }
+}
 
 /// Configuration for preparing a local checkout that AST can consume.
 pub struct WorkspaceConfig<'a> {
@@ -27,6 +95,10 @@ pub struct WorkspaceConfig<'a> {
     pub revision: &'a str,
     /// Whether to pass revision history to AST for incremental filtering.
     pub enable_incremental_filter: bool,
+    /// Repository-relative paths AST is limited to; every file when empty.
+    pub files: &'a [String],
+    /// Disk, time and sandbox limits applied while preparing and parsing.
+    pub limits: &'a WorkspaceLimits,
 }
 
 pub struct CodeWorkspace {
@@ -35,6 +107,7 @@ pub struct CodeWorkspace {
     repos: Repos,
     revision: String,
     display_name: String,
+    parse_timeout: Option<Duration>,
 }
 
 impl CodeWorkspace {
@@ -59,34 +132,54 @@ impl CodeWorkspace {
     }
 
     pub async fn build_graph(&self) -> StorageResult<BTreeMapGraph> {
-        self.repos
-            .build_graphs()
-            .await
-            .map_err(|err| StorageError::SyncError(format!("AST graph build failed: {err}")))
+        let build = self.repos.build_graphs();
+        let result = match self.parse_timeout {
+            Some(limit) => tokio::time::timeout(limit, build).await.map_err(|_| {
+                StorageError::SyncError(format!(
+                    "AST graph build for {} exceeded {}s",
+                    self.display_name,
+                    limit.as_secs()
+                ))
+            })?,
+            None => build.await,
+        };
+        result.map_err(|err| StorageError::SyncError(format!("AST graph build failed: {err}")))
     }
 }
 
 pub async fn prepare_workspace(config: WorkspaceConfig<'_>) -> StorageResult<CodeWorkspace> {
-    let temp_dir = TempDir::new().map_err(|err| {
-        StorageError::SyncError(format!("failed to create temporary directory: {err}"))
-    })?;
+    let limits = config.limits;
+    let temp_dir = limits.create_temp_dir()?;
     let checkout_path = temp_dir.path().join(DEFAULT_REPO_DIR);
 
     let repo_url = config.repo_url.to_string();
     let revision = config.revision.to_string();
-    let repo_url_for_clone = repo_url.clone();
-    let revision_for_clone = revision.clone();
-    let checkout_path_clone = checkout_path.clone();
-
-    task::spawn_blocking(move || {
-        clone_and_checkout(
-            &repo_url_for_clone,
-            &checkout_path_clone,
-            &revision_for_clone,
-        )
-    })
-    .await
-    .map_err(|err| StorageError::SyncError(format!("checkout task failed: {err}")))??;
+    let guard = TransferGuard::new(limits.max_bytes);
+    // A targeted checkout only reads its files at `revision`, so the history
+    // is left on the remote. Local transports cannot fetch shallowly.
+    let shallow =
+        !config.files.is_empty() && !config.enable_incremental_filter && is_remote_url(&repo_url);
+
+    let clone = clone_workspace(
+        repo_url.clone(),
+        checkout_path.clone(),
+        revision.clone(),
+        shallow,
+        limits.sandbox_command.clone(),
+        guard.clone(),
+    );
+    match limits.clone_timeout() {
+        Some(limit) => tokio::time::timeout(limit, clone).await.map_err(|_| {
+            // Stops an in-process transfer at its next progress callback.
+            guard.cancel();
+            StorageError::SyncError(format!(
+                "cloning {} exceeded {}s",
+                config.display_name,
+                limit.as_secs()
+            ))
+        })??,
+        None => clone.await?,
+    }
 
     let checkout_str = checkout_path
         .to_str()
@@ -110,15 +203,25 @@ pub async fn prepare_workspace(config: WorkspaceConfig<'_>) -> StorageResult<Cod
         Vec::new()
     };
 
-    let repos = ast::repo::Repo::new_multi_detect(
+    let detect = ast::repo::Repo::new_multi_detect(
         &checkout_str,
         Some(repo_origin),
-        Vec::new(),
+        config.files.to_vec(),
         revs,
         Some(false),
-    )
-    .await
-    .map_err(|err| StorageError::SyncError(format!("AST language detection failed: {err}")))?;
+    );
+    let detected = match limits.parse_timeout() {
+        Some(limit) => tokio::time::timeout(limit, detect).await.map_err(|_| {
+            StorageError::SyncError(format!(
+                "AST language detection for {} exceeded {}s",
+                config.display_name,
+                limit.as_secs()
+            ))
+        })?,
+        None => detect.await,
+    };
+    let repos = detected
+        .map_err(|err| StorageError::SyncError(format!("AST language detection failed: {err}")))?;
 
     Ok(CodeWorkspace {
         _temp_dir: temp_dir,
@@ -126,10 +229,172 @@ pub async fn prepare_workspace(config: WorkspaceConfig<'_>) -> StorageResult<Cod
         repos,
         revision,
         display_name: config.display_name.to_string(),
+        parse_timeout: limits.parse_timeout(),
     })
 }
 
-fn clone_and_checkout(repo_url: &str, dest: &Path, revision: &str) -> Result<(), StorageError> {
+/// Clones `repo_url` into `dest`, checks out `revision` and enforces the size cap.
+/// A `shallow` clone fetches the checked out commit without its history.
+async fn clone_workspace(
+    repo_url: String,
+    dest: PathBuf,
+    revision: String,
+    shallow: bool,
+    sandbox_command: Vec<String>,
+    guard: TransferGuard,
+) -> StorageResult<()> {
+    if !sandbox_command.is_empty() {
+        reset_checkout_dest(&dest)?;
+        clone_with_command(&sandbox_command, &repo_url, &dest, shallow).await?;
+    }
+
+    task::spawn_blocking(move || {
+        if sandbox_command.is_empty() {
+            clone_and_checkout(&repo_url, &dest, &revision, shallow, &guard)?;
+        } else if !revision.is_empty() {
+            let repo = Repository::open(&dest).map_err(|err| {
+                StorageError::SyncError(format!("failed to open cloned repository: {err}"))
+            })?;
+            checkout_revision(&repo, &revision, shallow, &guard)?;
+        }
+        guard.check_directory(&dest)
+    })
+    .await
+    .map_err(|err| StorageError::SyncError(format!("checkout task failed: {err}")))?
+}
+
+/// Runs `git clone` under the configured sandbox prefix. The child is killed if
+/// the surrounding clone timeout drops this future.
+async fn clone_with_command(
+    prefix: &[String],
+    repo_url: &str,
+    dest: &Path,
+    shallow: bool,
+) -> StorageResult<()> {
+    let (program, args) = prefix
+        .split_first()
+        .ok_or_else(|| StorageError::SyncError("empty sandbox command".into()))?;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .args(["git", "clone", "--quiet"])
+        .args(shallow.then_some(["--depth", "1"]).into_iter().flatten())
+        .arg("--")
+        .arg(repo_url)
+        .arg(dest)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = command.output().await.map_err(|err| {
+        StorageError::SyncError(format!(
+            "failed to spawn sandboxed clone '{program}': {err}"
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(StorageError::SyncError(format!(
+            "sandboxed git clone failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Shared between the clone task and its caller so transfers can be cut short.
+#[derive(Clone)]
+struct TransferGuard {
+    max_bytes: Option<u64>,
+    cancelled: Arc<AtomicBool>,
+    oversized: Arc<AtomicBool>,
+}
+
+impl TransferGuard {
+    fn new(max_bytes: Option<u64>) -> Self {
+        Self {
+            max_bytes,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            oversized: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn callbacks(&self) -> RemoteCallbacks<'static> {
+        let guard = self.clone();
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(move |progress| {
+            if guard
+                .max_bytes
+                .is_some_and(|max| progress.received_bytes() as u64 > max)
+            {
+                guard.oversized.store(true, Ordering::Relaxed);
+                return false;
+            }
+            !guard.cancelled.load(Ordering::Relaxed)
+        });
+        callbacks
+    }
+
+    /// Replaces libgit2's generic "callback aborted" error with the limit that tripped.
+    fn transfer_error(&self, action: &str, err: git2::Error) -> StorageError {
+        if self.oversized.load(Ordering::Relaxed) {
+            StorageError::SyncError(format!(
+                "{action} aborted: transfer exceeded the {} byte workspace limit",
+                self.max_bytes.unwrap_or_default()
+            ))
+        } else if self.cancelled.load(Ordering::Relaxed) {
+            StorageError::SyncError(format!("{action} cancelled"))
+        } else {
+            StorageError::SyncError(format!("{action} failed: {err}"))
+        }
+    }
+
+    fn check_directory(&self, dir: &Path) -> StorageResult<()> {
+        let Some(max) = self.max_bytes else {
+            return Ok(());
+        };
+        let size = directory_size(dir, max).map_err(|err| {
+            StorageError::SyncError(format!(
+                "failed to measure checkout at {}: {err}",
+                dir.display()
+            ))
+        })?;
+        if size > max {
+            return Err(StorageError::SyncError(format!(
+                "checkout exceeds the {max} byte workspace limit"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Sums file sizes under `dir` without following symlinks, stopping once `limit` is passed.
+fn directory_size(dir: &Path, limit: u64) -> std::io::Result<u64> {
+    let mut total = 0u64;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        for entry in std::fs::read_dir(&path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total = total.saturating_add(metadata.len());
+                if total > limit {
+                    return Ok(total);
+                }
+            }
+        }
+    }
+    Ok(total)
+}
+
+fn reset_checkout_dest(dest: &Path) -> Result<(), StorageError> {
     let parent = dest
         .parent()
         .ok_or_else(|| StorageError::SyncError("invalid checkout destination".into()))?;
@@ -145,28 +410,48 @@ fn clone_and_checkout(repo_url: &str, dest: &Path, revision: &str) -> Result<(),
             ))
         })?;
     }
+    Ok(())
+}
+
+fn clone_and_checkout(
+    repo_url: &str,
+    dest: &Path,
+    revision: &str,
+    shallow: bool,
+    guard: &TransferGuard,
+) -> Result<(), StorageError> {
+    reset_checkout_dest(dest)?;
 
     let mut builder = RepoBuilder::new();
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(guard.callbacks());
+    if shallow {
+        fetch_options.depth(1);
+    }
     if let Some(proxy_options) = proxy_options_from_env(repo_url) {
-        let mut fetch_options = FetchOptions::new();
         fetch_options.proxy_options(proxy_options);
-        builder.fetch_options(fetch_options);
     }
+    builder.fetch_options(fetch_options);
 
     let repo = builder
         .clone(repo_url, dest)
-        .map_err(|err| StorageError::SyncError(format!("git clone failed: {err}")))?;
+        .map_err(|err| guard.transfer_error("git clone", err))?;
 
     if revision.is_empty() {
         return Ok(());
     }
 
-    checkout_revision(&repo, revision)?;
+    checkout_revision(&repo, revision, shallow, guard)?;
 
     Ok(())
 }
 
-fn checkout_revision(repo: &Repository, revision: &str) -> Result<(), StorageError> {
+fn checkout_revision(
+    repo: &Repository,
+    revision: &str,
+    shallow: bool,
+    guard: &TransferGuard,
+) -> Result<(), StorageError> {
     let commit_obj = match repo.revparse_single(&format!("{revision}^{{commit}}")) {
         Ok(obj) => obj,
         Err(_) => {
@@ -175,12 +460,16 @@ fn checkout_revision(repo: &Repository, revision: &str) -> Result<(), StorageErr
             })?;
             let mut fetch_options = FetchOptions::new();
             fetch_options.download_tags(git2::AutotagOption::All);
+            fetch_options.remote_callbacks(guard.callbacks());
+            if shallow {
+                fetch_options.depth(1);
+            }
             if let Some(proxy_options) = proxy_options_from_env(remote.url().unwrap_or_default()) {
                 fetch_options.proxy_options(proxy_options);
             }
             remote
                 .fetch(&[revision], Some(&mut fetch_options), None)
-                .map_err(|err| StorageError::SyncError(format!("git fetch failed: {err}")))?;
+                .map_err(|err| guard.transfer_error("git fetch", err))?;
             repo.revparse_single(&format!("{revision}^{{commit}}"))
                 .map_err(|err| {
                     StorageError::SyncError(format!("unknown revision {revision}: {err}"))
@@ -201,6 +490,12 @@ fn checkout_revision(repo: &Repository, revision: &str) -> Result<(), StorageErr
     Ok(())
 }
 
+/// Whether `url` is fetched over the network rather than from a local path.
+fn is_remote_url(url: &str) -> bool {
+    let url = url.trim();
+    (url.contains("://") && !url.starts_with("file://")) || url.starts_with("git@")
+}
+
 fn make_origin_url(display_name: &str, fallback_url: &str) -> String {
     let mut candidate = if display_name.contains("://") || display_name.starts_with('/') {
         display_name.to_string()
@@ -301,55 +596,66 @@ impl Drop for EnvVarGuard {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use git2::Signature;
+    use git2::{Oid, Signature};
     use tokio::runtime::Runtime;
 
+    fn init_source_repo(source_path: &Path) -> Oid {
+        std::fs::create_dir_all(source_path).expect("create source");
+        let repo = Repository::init(source_path).expect("init repo");
+
+        std::fs::create_dir_all(source_path.join("src")).expect("create src");
+        std::fs::write(
+            source_path.join("src").join("lib.rs"),
+            "pub fn hello() -> u32 { 42 }",
+        )
+        .expect("write lib.rs");
+        std::fs::write(
+            source_path.join("Cargo.toml"),
+            "[package]\nname=\"demo\"\nversion=\"0.1.0\"\nedition=\"2021\"",
+        )
+        .expect("write cargo");
+
+        let mut index = repo.index().expect("index");
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .expect("add all");
+        index.write().expect("write index");
+        let tree_id = index.write_tree().expect("tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        let sig = Signature::now("Tester", "tester@example.com").expect("sig");
+        repo.commit(Some("HEAD"), &sig, &sig, "initial commit", &tree, &[])
+            .expect("commit")
+    }
+
     #[test]
     fn prepare_workspace_clones_and_detects() {
         let rt = Runtime::new().expect("runtime");
         rt.block_on(async {
             let source_dir = TempDir::new().expect("source dir");
             let source_path = source_dir.path().join("source");
-            std::fs::create_dir_all(&source_path).expect("create source");
-            let repo = Repository::init(&source_path).expect("init repo");
-
-            std::fs::create_dir_all(source_path.join("src")).expect("create src");
-            std::fs::write(
-                source_path.join("src").join("lib.rs"),
-                "pub fn hello() -> u32 { 42 }",
-            )
-            .expect("write lib.rs");
-            std::fs::write(
-                source_path.join("Cargo.toml"),
-                "[package]\nname=\"demo\"\nversion=\"0.1.0\"\nedition=\"2021\"",
-            )
-            .expect("write cargo");
-
-            let mut index = repo.index().expect("index");
-            index
-                .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
-                .expect("add all");
-            index.write().expect("write index");
-            let tree_id = index.write_tree().expect("tree");
-            let tree = repo.find_tree(tree_id).expect("find tree");
-            let sig = Signature::now("Tester", "tester@example.com").expect("sig");
-            let oid = repo
-                .commit(Some("HEAD"), &sig, &sig, "initial commit", &tree, &[])
-                .expect("commit");
+            let oid = init_source_repo(&source_path);
 
             let repo_url = source_path.to_str().expect("unicode path").to_string();
             let revision = oid.to_string();
+            let workspace_root = TempDir::new().expect("workspace root");
+            let limits = WorkspaceLimits {
+                root_dir: Some(workspace_root.path().to_path_buf()),
+                ..WorkspaceLimits::default()
+            };
 
             let workspace = prepare_workspace(WorkspaceConfig {
                 repo_url: &repo_url,
                 display_name: "local/test",
                 revision: &revision,
                 enable_incremental_filter: false,
+                files: &[],
+                limits: &limits,
             })
             .await
             .expect("workspace");
 
             assert!(workspace.repo_root().join("Cargo.toml").exists());
+            assert!(workspace.repo_root().starts_with(workspace_root.path()));
             assert!(
                 !workspace.repositories().is_empty(),
                 "expected at least one detected language"
@@ -357,4 +663,35 @@ mod tests {
             assert_eq!(workspace.revision(), revision);
         });
     }
+
+    #[test]
+    fn prepare_workspace_rejects_checkouts_over_the_size_cap() {
+        let rt = Runtime::new().expect("runtime");
+        rt.block_on(async {
+            let source_dir = TempDir::new().expect("source dir");
+            let source_path = source_dir.path().join("source");
+            let oid = init_source_repo(&source_path);
+
+            let repo_url = source_path.to_str().expect("unicode path").to_string();
+            let revision = oid.to_string();
+            let limits = WorkspaceLimits {
+                max_bytes: Some(16),
+                ..WorkspaceLimits::default()
+            };
+
+            let Err(err) = prepare_workspace(WorkspaceConfig {
+                repo_url: &repo_url,
+                display_name: "local/test",
+                revision: &revision,
+                enable_incremental_filter: false,
+                files: &[],
+                limits: &limits,
+            })
+            .await
+            else {
+                panic!("oversized checkout should be rejected");
+            };
+            assert!(err.to_string().contains("byte workspace limit"), "{err}");
+        });
+    }
 }
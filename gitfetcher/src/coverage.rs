@@ -0,0 +1,412 @@
+//! Test-coverage reports linking tests to the code they exercise.
+//!
+//! A repository snapshot may name lcov or Cobertura reports produced by a test
+//! run at the snapshot's revision. Both are read into a [`CoverageReport`] of
+//! line hits per test and file, from which the mapper draws `Covers` edges
+//! from Test nodes to the Function and File nodes whose lines were executed.
+//!
+//! lcov sections are attributed to the test named by their `TN:` record;
+//! Cobertura reports carry no test names, so the whole report is attributed to
+//! the source's `test`, or to the report's file stem when that is unset.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    code_index::check_relative,
+    error::{GitFetcherError, Result},
+};
+
+/// A coverage report, relative to the fetcher's coverage directory.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CoverageSource {
+    pub path: PathBuf,
+    /// Detected from the file extension when unset: `.xml` is Cobertura,
+    /// anything else lcov.
+    #[serde(default)]
+    pub format: Option<CoverageFormat>,
+    /// Test the report is attributed to when it does not name one.
+    #[serde(default)]
+    pub test: Option<String>,
+    /// Removed from the report's file paths, typically the directory the
+    /// tests ran in, so that they match the repository's paths.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageFormat {
+    Lcov,
+    Cobertura,
+}
+
+impl CoverageSource {
+    pub fn format(&self) -> CoverageFormat {
+        if let Some(format) = self.format {
+            return format;
+        }
+        match self.path.extension().and_then(|ext| ext.to_str()) {
+            Some("xml") => CoverageFormat::Cobertura,
+            _ => CoverageFormat::Lcov,
+        }
+    }
+
+    fn default_test(&self) -> String {
+        self.test.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "coverage".to_string())
+        })
+    }
+
+    /// Reads and parses the report under `root`, refusing paths that leave it.
+    pub fn load(&self, root: &Path) -> Result<CoverageReport> {
+        check_relative(&self.path, "coverage report")?;
+        let text = std::fs::read_to_string(root.join(&self.path))?;
+        let test = self.default_test();
+        let report = match self.format() {
+            CoverageFormat::Lcov => parse_lcov(&text, &test)?,
+            CoverageFormat::Cobertura => parse_cobertura(&text, &test)?,
+        };
+        let source = self.path.to_string_lossy().into_owned();
+        Ok(report.relocate(&source, self.path_prefix.as_deref()))
+    }
+}
+
+/// Line hits per test and repository-relative file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CoverageReport {
+    pub tests: BTreeMap<String, TestCoverage>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TestCoverage {
+    /// Report the test was first read from.
+    pub source: String,
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+/// Hit counts of the instrumented lines of one file; lines are 1-based.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileCoverage {
+    pub lines: BTreeMap<u32, u64>,
+}
+
+/// Covered and instrumented line counts over a file or part of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCoverage {
+    pub covered: u32,
+    pub total: u32,
+}
+
+impl LineCoverage {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            f64::from(self.covered) * 100.0 / f64::from(self.total)
+        }
+    }
+}
+
+impl FileCoverage {
+    fn record(&mut self, line: u32, hits: u64) {
+        let entry = self.lines.entry(line).or_default();
+        *entry = entry.saturating_add(hits);
+    }
+
+    /// Counts the instrumented lines within `start..=end`, or the whole file.
+    pub fn summary(&self, span: Option<(u32, u32)>) -> LineCoverage {
+        let (start, end) = span.unwrap_or((0, u32::MAX));
+        let mut coverage = LineCoverage {
+            covered: 0,
+            total: 0,
+        };
+        for hits in self
+            .lines
+            .range(start..=end.max(start))
+            .map(|(_, hits)| hits)
+        {
+            coverage.total += 1;
+            if *hits > 0 {
+                coverage.covered += 1;
+            }
+        }
+        coverage
+    }
+}
+
+impl CoverageReport {
+    fn file(&mut self, test: &str, path: &str) -> &mut FileCoverage {
+        self.tests
+            .entry(test.to_string())
+            .or_default()
+            .files
+            .entry(path.to_string())
+            .or_default()
+    }
+
+    /// Adds the hits of `other`, so that several reports of one test add up.
+    pub fn merge(&mut self, other: CoverageReport) {
+        for (name, test) in other.tests {
+            let merged = self.tests.entry(name).or_insert_with(|| TestCoverage {
+                source: test.source.clone(),
+                ..TestCoverage::default()
+            });
+            for (path, file) in test.files {
+                let target = merged.files.entry(path).or_default();
+                for (line, hits) in file.lines {
+                    target.record(line, hits);
+                }
+            }
+        }
+    }
+
+    /// Records `source` on every test and rewrites the file paths relative to
+    /// the repository.
+    fn relocate(self, source: &str, prefix: Option<&str>) -> Self {
+        let mut relocated = CoverageReport::default();
+        for (name, test) in self.tests {
+            let entry = relocated.tests.entry(name).or_default();
+            entry.source = source.to_string();
+            for (path, file) in test.files {
+                let path = repository_path(&path, prefix);
+                let target = entry.files.entry(path).or_default();
+                for (line, hits) in file.lines {
+                    target.record(line, hits);
+                }
+            }
+        }
+        relocated
+    }
+}
+
+fn repository_path(path: &str, prefix: Option<&str>) -> String {
+    let path = Path::new(path);
+    let path = prefix
+        .and_then(|prefix| path.strip_prefix(prefix).ok())
+        .unwrap_or(path);
+    let path = path.strip_prefix("./").unwrap_or(path);
+    path.to_string_lossy().into_owned()
+}
+
+// --- lcov ---
+
+/// Parses an lcov tracefile. Sections without a `TN:` name go to `default_test`.
+pub fn parse_lcov(text: &str, default_test: &str) -> Result<CoverageReport> {
+    let mut report = CoverageReport::default();
+    let mut test = default_test.to_string();
+    let mut file: Option<String> = None;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let invalid = || GitFetcherError::ParseFailure(format!("lcov line {}: {line}", number + 1));
+        if let Some(name) = line.strip_prefix("TN:") {
+            test = match name.trim() {
+                "" => default_test.to_string(),
+                name => name.to_string(),
+            };
+        } else if let Some(path) = line.strip_prefix("SF:") {
+            file = Some(path.trim().to_string());
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let path = file.as_deref().ok_or_else(invalid)?;
+            let mut fields = data.split(',');
+            let line_number = fields
+                .next()
+                .and_then(|value| value.trim().parse::<u32>().ok())
+                .ok_or_else(invalid)?;
+            let hits = fields
+                .next()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .ok_or_else(invalid)?;
+            report.file(&test, path).record(line_number, hits);
+        } else if line == "end_of_record" {
+            file = None;
+        }
+    }
+    Ok(report)
+}
+
+// --- Cobertura ---
+
+#[derive(Deserialize)]
+struct CoberturaReport {
+    #[serde(default)]
+    sources: Option<CoberturaSources>,
+    #[serde(default)]
+    packages: Option<CoberturaPackages>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaSources {
+    #[serde(default)]
+    source: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaPackages {
+    #[serde(default)]
+    package: Vec<CoberturaPackage>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaPackage {
+    #[serde(default)]
+    classes: Option<CoberturaClasses>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaClasses {
+    #[serde(default, rename = "class")]
+    classes: Vec<CoberturaClass>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaClass {
+    #[serde(rename = "@filename")]
+    filename: String,
+    #[serde(default)]
+    lines: Option<CoberturaLines>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaLines {
+    #[serde(default)]
+    line: Vec<CoberturaLine>,
+}
+
+#[derive(Deserialize)]
+struct CoberturaLine {
+    #[serde(rename = "@number")]
+    number: u32,
+    #[serde(rename = "@hits")]
+    hits: u64,
+}
+
+/// Parses a Cobertura XML report, attributing all of it to `test`. Relative
+/// file names are resolved against the report's first `<source>`.
+pub fn parse_cobertura(text: &str, test: &str) -> Result<CoverageReport> {
+    let parsed: CoberturaReport = quick_xml::de::from_str(text)
+        .map_err(|err| GitFetcherError::ParseFailure(format!("Cobertura report: {err}")))?;
+    let source = parsed
+        .sources
+        .and_then(|sources| sources.source.into_iter().next())
+        .map(|source| source.trim().trim_end_matches('/').to_string())
+        .filter(|source| !source.is_empty() && source != ".");
+    let mut report = CoverageReport::default();
+    let classes = parsed
+        .packages
+        .into_iter()
+        .flat_map(|packages| packages.package)
+        .filter_map(|package| package.classes)
+        .flat_map(|classes| classes.classes);
+    for class in classes {
+        let path = match &source {
+            Some(source) if !class.filename.starts_with('/') => {
+                format!("{source}/{}", class.filename)
+            }
+            _ => class.filename,
+        };
+        // Classes of one file (e.g. nested types) repeat its lines.
+        let file = report.file(test, &path);
+        for line in class.lines.into_iter().flat_map(|lines| lines.line) {
+            let hits = file.lines.entry(line.number).or_default();
+            *hits = (*hits).max(line.hits);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lcov_sections_are_attributed_to_their_tests() {
+        let tracefile = "\
+TN:parses_tokens
+SF:/work/demo/src/lib.rs
+DA:3,1
+DA:4,0
+DA:9,2
+end_of_record
+TN:
+SF:/work/demo/src/lib.rs
+DA:4,1
+end_of_record
+";
+        let report = parse_lcov(tracefile, "unit")
+            .expect("lcov")
+            .relocate("lcov.info", Some("/work/demo/"));
+        assert_eq!(
+            report.tests.keys().collect::<Vec<_>>(),
+            vec!["parses_tokens", "unit"]
+        );
+        let file = &report.tests["parses_tokens"].files["src/lib.rs"];
+        assert_eq!(
+            file.summary(Some((3, 5))),
+            LineCoverage {
+                covered: 1,
+                total: 2
+            }
+        );
+        assert_eq!(file.summary(None).percent(), 200.0 / 3.0);
+        assert_eq!(report.tests["unit"].source, "lcov.info");
+
+        assert!(matches!(
+            parse_lcov("DA:1,1\n", "unit"),
+            Err(GitFetcherError::ParseFailure(_))
+        ));
+    }
+
+    #[test]
+    fn cobertura_classes_resolve_against_the_source() {
+        let xml = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5" version="7.4">
+  <sources><source>/ci/demo</source></sources>
+  <packages>
+    <package name="pkg" line-rate="0.5">
+      <classes>
+        <class name="mod.py" filename="pkg/mod.py" line-rate="0.5">
+          <methods/>
+          <lines>
+            <line number="1" hits="1"/>
+            <line number="2" hits="0"/>
+          </lines>
+        </class>
+      </classes>
+    </package>
+  </packages>
+</coverage>"#;
+        let source = CoverageSource {
+            path: PathBuf::from("reports/pytest.xml"),
+            format: None,
+            test: None,
+            path_prefix: Some("/ci/demo".to_string()),
+        };
+        assert_eq!(source.format(), CoverageFormat::Cobertura);
+        let report = parse_cobertura(xml, &source.default_test())
+            .expect("cobertura")
+            .relocate("reports/pytest.xml", source.path_prefix.as_deref());
+        let file = &report.tests["pytest"].files["pkg/mod.py"];
+        assert_eq!(file.summary(None).percent(), 50.0);
+    }
+
+    #[test]
+    fn merged_reports_add_up_hits() {
+        let mut report = parse_lcov("SF:a.rs\nDA:1,0\nDA:2,1\n", "suite").expect("lcov");
+        report.merge(parse_lcov("SF:a.rs\nDA:1,3\n", "suite").expect("lcov"));
+        assert_eq!(
+            report.tests["suite"].files["a.rs"].summary(None),
+            LineCoverage {
+                covered: 2,
+                total: 2
+            }
+        );
+    }
+}
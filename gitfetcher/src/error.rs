@@ -26,6 +26,9 @@ pub enum GitFetcherError {
     #[error("Base64 error: {0}")]
     Base64(#[from] base64::DecodeError),
 
+    #[error("storage error: {0}")]
+    Storage(#[from] fstorage::errors::StorageError),
+
     #[error("Parser error: {0}")]
     ParseFailure(String),
 
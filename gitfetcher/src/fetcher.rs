@@ -1,21 +1,32 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
 use fstorage::schemas::generated_schemas as schemas;
 use fstorage::{
+    checkpoint::SyncCheckpoint,
     embedding::EmbeddingProvider,
     errors::{Result as StorageResult, StorageError},
     fetch::{FetchResponse, Fetchable, Fetcher, FetcherCapability, ProbeReport, ProducedDataset},
+    models::EntityIdentifier,
 };
 use serde_json::json;
 
 use crate::{
+    benchmark::BenchmarkRecord,
     client::{GitHubService, OctocrabService},
+    code_index::CodeIndex,
+    code_workspace::WorkspaceLimits,
+    coverage::CoverageReport,
     mapper,
     models::RepoSnapshot,
-    params::{FetcherParams, RepoSnapshotParams, SearchRepoParams},
+    params::{FetcherParams, RepoSnapshotParams, SearchRepoParams, SnapshotTargets},
+    policy::RepoPolicy,
+    privacy::DeveloperPrivacy,
 };
 
+/// Checkpoint entry holding the GitHub snapshot of an unfinished sync.
+const SNAPSHOT_CHECKPOINT_KEY: &str = "gitfetcher:repo_snapshot";
+
 fn edge_table_path(entity_type: &str) -> String {
     let suffix = entity_type
         .strip_prefix("edge_")
@@ -62,20 +73,129 @@ fn vector_dataset<T: Fetchable>() -> ProducedDataset {
 
 pub struct GitFetcher {
     client: Arc<dyn GitHubService>,
+    policy: RepoPolicy,
+    workspace_limits: WorkspaceLimits,
+    privacy: DeveloperPrivacy,
+    code_index_dir: Option<PathBuf>,
+    coverage_dir: Option<PathBuf>,
+    benchmark_dir: Option<PathBuf>,
 }
 
 impl GitFetcher {
     pub fn new(client: Arc<dyn GitHubService>) -> Self {
-        Self { client }
+        Self {
+            client,
+            policy: RepoPolicy::default(),
+            workspace_limits: WorkspaceLimits::default(),
+            privacy: DeveloperPrivacy::default(),
+            code_index_dir: None,
+            coverage_dir: None,
+            benchmark_dir: None,
+        }
     }
 
     pub fn with_default_client(token: Option<String>) -> StorageResult<Self> {
         let client = OctocrabService::new(token).map_err(|err| {
             StorageError::Initialization(format!("failed to create Octocrab client: {err}"))
         })?;
-        Ok(Self {
-            client: Arc::new(client),
-        })
+        Ok(Self::new(Arc::new(client)))
+    }
+
+    /// Refuses repository snapshots that `policy` does not allow.
+    pub fn with_policy(mut self, policy: RepoPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Bounds the disk and time each code checkout may use.
+    pub fn with_workspace_limits(mut self, limits: WorkspaceLimits) -> Self {
+        self.workspace_limits = limits;
+        self
+    }
+
+    /// Hashes or drops developer names and emails before they are mapped or checkpointed.
+    pub fn with_developer_privacy(mut self, privacy: DeveloperPrivacy) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Lets repository snapshots read SCIP and LSIF indexes from `dir`; without
+    /// it, snapshots naming a `code_index` are refused.
+    pub fn with_code_index_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.code_index_dir = Some(dir.into());
+        self
+    }
+
+    fn load_code_index(&self, params: &RepoSnapshotParams) -> StorageResult<Option<CodeIndex>> {
+        let Some(source) = params.code_index.as_ref().filter(|_| params.include_code) else {
+            return Ok(None);
+        };
+        let dir = self.code_index_dir.as_ref().ok_or_else(|| {
+            StorageError::InvalidArg("code indexes are not enabled on this fetcher".to_string())
+        })?;
+        let index = source.load(dir).map_err(|err| {
+            StorageError::InvalidArg(format!(
+                "failed to read code index '{}': {err}",
+                source.path.display()
+            ))
+        })?;
+        Ok(Some(index))
+    }
+
+    /// Lets repository snapshots read lcov and Cobertura reports from `dir`;
+    /// without it, snapshots naming `coverage` reports are refused.
+    pub fn with_coverage_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.coverage_dir = Some(dir.into());
+        self
+    }
+
+    fn load_coverage(&self, params: &RepoSnapshotParams) -> StorageResult<Option<CoverageReport>> {
+        if params.coverage.is_empty() || !params.include_code {
+            return Ok(None);
+        }
+        let dir = self.coverage_dir.as_ref().ok_or_else(|| {
+            StorageError::InvalidArg("coverage reports are not enabled on this fetcher".to_string())
+        })?;
+        let mut report = CoverageReport::default();
+        for source in &params.coverage {
+            let loaded = source.load(dir).map_err(|err| {
+                StorageError::InvalidArg(format!(
+                    "failed to read coverage report '{}': {err}",
+                    source.path.display()
+                ))
+            })?;
+            report.merge(loaded);
+        }
+        Ok(Some(report))
+    }
+
+    /// Lets repository snapshots read benchmark results from `dir`; without
+    /// it, snapshots naming `benchmarks` are refused.
+    pub fn with_benchmark_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.benchmark_dir = Some(dir.into());
+        self
+    }
+
+    fn load_benchmarks(&self, params: &RepoSnapshotParams) -> StorageResult<Vec<BenchmarkRecord>> {
+        if params.benchmarks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dir = self.benchmark_dir.as_ref().ok_or_else(|| {
+            StorageError::InvalidArg(
+                "benchmark results are not enabled on this fetcher".to_string(),
+            )
+        })?;
+        let mut records = Vec::new();
+        for source in &params.benchmarks {
+            let loaded = source.load(dir).map_err(|err| {
+                StorageError::InvalidArg(format!(
+                    "failed to read benchmark results '{}': {err}",
+                    source.path.display()
+                ))
+            })?;
+            records.extend(loaded);
+        }
+        Ok(records)
     }
 
     fn capability_descriptor() -> FetcherCapability {
@@ -117,6 +237,7 @@ impl GitFetcher {
             node_dataset::<schemas::Test>(),
             node_dataset::<schemas::Endpoint>(),
             node_dataset::<schemas::Library>(),
+            node_dataset::<schemas::BenchmarkResult>(),
             edge_dataset::<schemas::Contains>(),
             edge_dataset::<schemas::Calls>(),
             edge_dataset::<schemas::Uses>(),
@@ -126,6 +247,9 @@ impl GitFetcher {
             edge_dataset::<schemas::Implements>(),
             edge_dataset::<schemas::NestedIn>(),
             edge_dataset::<schemas::Imports>(),
+            edge_dataset::<schemas::Covers>(),
+            edge_dataset::<schemas::HasBenchmark>(),
+            edge_dataset::<schemas::Measures>(),
             vector_dataset::<schemas::ReadmeChunk>(),
             vector_dataset::<schemas::CodeChunk>(),
             vector_dataset::<schemas::IssueDoc>(),
@@ -147,9 +271,63 @@ impl GitFetcher {
                     "include_issues": { "type": "boolean" },
                     "include_pulls": { "type": "boolean" },
                     "include_developers": { "type": "boolean" },
+                    "targets": {
+                        "type": "object",
+                        "description": "Restrict a snapshot to these files, issues and pull requests; filled from the sync's target entities",
+                        "properties": {
+                            "files": { "type": "array", "items": { "type": "string" } },
+                            "issues": { "type": "array", "items": { "type": "integer" } },
+                            "pull_requests": { "type": "array", "items": { "type": "integer" } }
+                        }
+                    },
+                    "entity_types": {
+                        "type": "array",
+                        "items": { "enum": ["code", "readme", "issue", "pull_request", "developer"] },
+                        "description": "Sync only these entity groups; overrides the include_* flags"
+                    },
                     "doc_level_only": { "type": "boolean", "description": "When true, only issue/pr doc vectors are produced (no comment-level chunks)" },
                     "touches_mode": { "type": "string", "enum": ["none", "dir_topk", "hot_topk"] },
                     "representative_comment_limit": { "type": "integer", "minimum": 1, "maximum": 16 },
+                    "code_index": {
+                        "type": "object",
+                        "description": "SCIP (JSON) or LSIF index, relative to the fetcher's index directory, to build the code graph from instead of tree-sitter",
+                        "required": ["path"],
+                        "properties": {
+                            "path": { "type": "string" },
+                            "format": { "enum": ["scip", "lsif"] }
+                        }
+                    },
+                    "coverage": {
+                        "type": "array",
+                        "description": "lcov or Cobertura reports, relative to the fetcher's coverage directory, whose tests get COVERS edges to the functions and files they execute",
+                        "items": {
+                            "type": "object",
+                            "required": ["path"],
+                            "properties": {
+                                "path": { "type": "string" },
+                                "format": { "enum": ["lcov", "cobertura"] },
+                                "test": { "type": "string" },
+                                "path_prefix": { "type": "string" }
+                            }
+                        }
+                    },
+                    "benchmarks": {
+                        "type": "array",
+                        "description": "Benchmark results measured at the snapshot's revision, relative to the fetcher's benchmark directory",
+                        "items": {
+                            "type": "object",
+                            "required": ["path"],
+                            "properties": {
+                                "path": { "type": "string" },
+                                "format": { "enum": ["criterion"] },
+                                "functions": {
+                                    "type": "object",
+                                    "description": "Function names by benchmark id or group",
+                                    "additionalProperties": { "type": "string" }
+                                }
+                            }
+                        }
+                    },
                     "query": { "type": "string" },
                     "language": { "type": "string" },
                     "min_stars": { "type": "integer" },
@@ -164,33 +342,101 @@ impl GitFetcher {
             default_ttl_secs: Some(6 * 3600),
             examples: vec![
                 json!({"mode": "repo_snapshot", "repo": "rust-lang/rust", "include_code": false, "include_issues": true, "include_pulls": true, "doc_level_only": true}),
+                json!({"mode": "repo_snapshot", "repo": "rust-lang/rust", "entity_types": ["issue", "pull_request"]}),
                 json!({"mode": "search_repo", "query": "language:rust compiler", "min_stars": 5000}),
             ],
         }
     }
 
     fn parse_params(value: serde_json::Value) -> StorageResult<FetcherParams> {
-        serde_json::from_value::<FetcherParams>(value)
-            .map_err(|err| StorageError::InvalidArg(format!("invalid fetch params: {err}")))
+        let mut params = serde_json::from_value::<FetcherParams>(value)
+            .map_err(|err| StorageError::InvalidArg(format!("invalid fetch params: {err}")))?;
+        if let FetcherParams::RepoSnapshot(snapshot) = &mut params {
+            snapshot.apply_entity_types();
+            snapshot.apply_targets();
+        }
+        Ok(params)
     }
 
     async fn fetch_repo_snapshot(
         &self,
         params: RepoSnapshotParams,
         embedding_provider: Arc<dyn EmbeddingProvider>,
+        checkpoint: Option<&SyncCheckpoint>,
     ) -> StorageResult<FetchResponse> {
         let (owner, repo) = params
             .coordinates()
             .map_err(|err| StorageError::InvalidArg(format!("invalid repo coordinates: {err}")))?;
+        self.policy.check_name(&format!("{owner}/{repo}"))?;
+        // Read before any GitHub calls so bad indexes or reports fail the sync early.
+        let code_index = self.load_code_index(&params)?;
+        let coverage = self.load_coverage(&params)?;
+        let benchmarks = self.load_benchmarks(&params)?;
+        if self.policy.needs_metadata() {
+            let info = self
+                .client
+                .repository_info(&owner, &repo)
+                .await
+                .map_err(|err| StorageError::SyncError(err.to_string()))?;
+            if let Some(info) = info {
+                self.policy.check_repository(&info)?;
+            }
+        }
 
-        let snapshot: RepoSnapshot = self
-            .client
-            .fetch_repo_snapshot(&owner, &repo, &params)
-            .await
-            .map_err(|err| StorageError::SyncError(err.to_string()))?;
+        // Paging through issues, pull requests and their comments is the slow part of
+        // a snapshot, so a retried sync reuses the pages fetched by the previous
+        // attempt: the whole snapshot once it was complete, the list pages done so
+        // far otherwise.
+        let cached = match checkpoint {
+            Some(checkpoint) => checkpoint.get::<RepoSnapshot>(SNAPSHOT_CHECKPOINT_KEY)?,
+            None => None,
+        };
+        let mut snapshot: RepoSnapshot = match cached {
+            Some(mut snapshot) => {
+                log::info!("Reusing checkpointed GitHub snapshot of {owner}/{repo}");
+                self.privacy.scrub_snapshot(&mut snapshot);
+                snapshot
+            }
+            None => {
+                let fetched = match checkpoint {
+                    Some(checkpoint) => {
+                        self.client
+                            .fetch_repo_snapshot_resumable(&owner, &repo, &params, checkpoint)
+                            .await
+                    }
+                    None => {
+                        self.client
+                            .fetch_repo_snapshot(&owner, &repo, &params)
+                            .await
+                    }
+                };
+                let mut snapshot =
+                    fetched.map_err(|err| StorageError::SyncError(err.to_string()))?;
+                self.privacy.scrub_snapshot(&mut snapshot);
+                if let Some(checkpoint) = checkpoint {
+                    checkpoint.put(SNAPSHOT_CHECKPOINT_KEY, &snapshot)?;
+                }
+                snapshot
+            }
+        };
+        // Services without a cheap metadata lookup are checked against the snapshot.
+        self.policy.check_repository(&snapshot.repository)?;
+        // GitHub is only asked for the targets; this catches services that
+        // return the whole repository anyway.
+        if let Some(targets) = &params.targets {
+            targets.retain_in(&mut snapshot);
+        }
 
-        let graph =
-            mapper::build_repo_snapshot_graph(&snapshot, &params, embedding_provider).await?;
+        let graph = mapper::build_repo_snapshot_graph(
+            &snapshot,
+            &params,
+            code_index.as_ref(),
+            coverage.as_ref(),
+            &benchmarks,
+            &self.workspace_limits,
+            embedding_provider,
+        )
+        .await?;
 
         Ok(FetchResponse::GraphData(graph))
     }
@@ -264,6 +510,48 @@ impl Fetcher for GitFetcher {
         }
     }
 
+    fn admit(&self, params: &serde_json::Value) -> StorageResult<()> {
+        if self.policy.is_unrestricted() {
+            return Ok(());
+        }
+        match Self::parse_params(params.clone())? {
+            FetcherParams::RepoSnapshot(params) => {
+                let (owner, repo) = params.coordinates().map_err(|err| {
+                    StorageError::InvalidArg(format!("invalid repo coordinates: {err}"))
+                })?;
+                self.policy.check_name(&format!("{owner}/{repo}"))
+            }
+            FetcherParams::SearchRepo(_) => Ok(()),
+        }
+    }
+
+    /// File, issue and pull request targets become the `targets` of a repo snapshot.
+    fn scope_params(
+        &self,
+        mut params: serde_json::Value,
+        targets: &[EntityIdentifier],
+    ) -> StorageResult<serde_json::Value> {
+        let scoped = SnapshotTargets::from_identifiers(targets)
+            .map_err(|err| StorageError::InvalidArg(format!("invalid sync target: {err}")))?;
+        let is_snapshot =
+            params.get("mode").and_then(|mode| mode.as_str()) == Some("repo_snapshot");
+        let Some(object) = params
+            .as_object_mut()
+            .filter(|_| is_snapshot && !scoped.is_empty())
+        else {
+            return Ok(params);
+        };
+
+        let mut merged: SnapshotTargets = match object.get("targets") {
+            Some(existing) => serde_json::from_value(existing.clone())
+                .map_err(|err| StorageError::InvalidArg(format!("invalid fetch params: {err}")))?,
+            None => SnapshotTargets::default(),
+        };
+        merged.merge(scoped);
+        object.insert("targets".to_string(), serde_json::to_value(&merged)?);
+        Ok(params)
+    }
+
     async fn fetch(
         &self,
         params: serde_json::Value,
@@ -271,7 +559,23 @@ impl Fetcher for GitFetcher {
     ) -> StorageResult<FetchResponse> {
         match Self::parse_params(params)? {
             FetcherParams::RepoSnapshot(params) => {
-                self.fetch_repo_snapshot(params, embedding_provider).await
+                self.fetch_repo_snapshot(params, embedding_provider, None)
+                    .await
+            }
+            FetcherParams::SearchRepo(params) => self.fetch_search_repo(params).await,
+        }
+    }
+
+    async fn fetch_resumable(
+        &self,
+        params: serde_json::Value,
+        embedding_provider: Arc<dyn EmbeddingProvider>,
+        checkpoint: Arc<SyncCheckpoint>,
+    ) -> StorageResult<FetchResponse> {
+        match Self::parse_params(params)? {
+            FetcherParams::RepoSnapshot(params) => {
+                self.fetch_repo_snapshot(params, embedding_provider, Some(&checkpoint))
+                    .await
             }
             FetcherParams::SearchRepo(params) => self.fetch_search_repo(params).await,
         }
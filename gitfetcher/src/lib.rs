@@ -1,11 +1,22 @@
+pub mod benchmark;
 pub mod client;
+pub mod code_index;
 pub mod code_workspace;
+pub mod coverage;
 pub mod error;
 pub mod fetcher;
 pub mod mapper;
 pub mod models;
 pub mod params;
+pub mod policy;
+pub mod privacy;
 pub mod readme;
 
+pub use crate::benchmark::{BenchmarkFormat, BenchmarkSource};
+pub use crate::code_index::{CodeIndexFormat, CodeIndexSource};
+pub use crate::code_workspace::WorkspaceLimits;
+pub use crate::coverage::{CoverageFormat, CoverageSource};
 pub use crate::fetcher::GitFetcher;
 pub use crate::params::{FetchMode, FetcherParams, RepoSnapshotParams, SearchRepoParams};
+pub use crate::policy::RepoPolicy;
+pub use crate::privacy::DeveloperPrivacy;
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, convert::TryFrom, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+    path::Path,
+    sync::Arc,
+};
 
 use crate::readme::{chunk_readme, ReadmeChunkPiece};
 use ast::lang::asg::NodeData;
@@ -15,17 +20,21 @@ use fstorage::{
     fetch::Fetchable,
     fetch::GraphData,
     schemas::generated_schemas::{
-        Calls, Class, CodeChunk, Commit, Contains, DataModel, DependsOn, Developer, Endpoint, File,
-        Function, Handler, HasIssue, HasLabel, HasPr, HasVersion, Implements, Imports, IsCommit,
-        Issue, IssueDoc, Label, Library, NestedIn, OpenedIssue, OpenedPr, Operand, ParentOf, PrDoc,
-        Project, PullRequest, ReadmeChunk, RelatesTo, Test, Trait, Uses, Variable, Version,
+        AuthoredBy, BenchmarkResult, Calls, Class, CodeChunk, Commit, Contains, Covers, DataModel,
+        DependsOn, Developer, Endpoint, File, Function, Handler, HasBenchmark, HasIssue, HasLabel,
+        HasPr, HasVersion, Implements, Imports, IsCommit, Issue, IssueDoc, Label, Library,
+        Measures, NestedIn, OpenedIssue, OpenedPr, Operand, ParentOf, PrDoc, Project, PullRequest,
+        ReadmeChunk, RelatesTo, Test, Trait, Uses, Variable, Version,
     },
     utils::id::{stable_edge_id_u128, stable_node_id_u128},
 };
 use uuid::Uuid;
 
 use crate::{
-    code_workspace::{prepare_workspace, WorkspaceConfig},
+    benchmark::BenchmarkRecord,
+    code_index::{CodeIndex, SymbolKind},
+    code_workspace::{prepare_workspace, WorkspaceConfig, WorkspaceLimits},
+    coverage::{CoverageReport, LineCoverage},
     models::{
         DeveloperProfile, IssueInfo, LabelInfo, PullRequestInfo, RepoSnapshot, RepositoryInfo,
         SearchRepository,
@@ -35,9 +44,17 @@ use crate::{
 
 const README_MAX_LINES_PER_CHUNK: usize = 120;
 
+/// Maps a repository snapshot. The code graph comes from `code_index` when one
+/// was loaded for the snapshot, and from parsing a checkout otherwise;
+/// `coverage` links its tests to the functions and files they execute.
+/// `benchmarks` are results measured at the snapshot's revision.
 pub async fn build_repo_snapshot_graph(
     snapshot: &RepoSnapshot,
     params: &RepoSnapshotParams,
+    code_index: Option<&CodeIndex>,
+    coverage: Option<&CoverageReport>,
+    benchmarks: &[BenchmarkRecord],
+    workspace_limits: &WorkspaceLimits,
     embedding_provider: Arc<dyn EmbeddingProvider>,
 ) -> StorageResult<GraphData> {
     let repo = &snapshot.repository;
@@ -95,13 +112,36 @@ pub async fn build_repo_snapshot_graph(
             &commit_node_id,
         )),
         from_node_id: Some(version_node_id.clone()),
-        to_node_id: Some(commit_node_id),
+        to_node_id: Some(commit_node_id.clone()),
         from_node_type: Some(Version::ENTITY_TYPE.to_string()),
         to_node_type: Some(Commit::ENTITY_TYPE.to_string()),
         created_at: Some(commit.authored_at),
         updated_at: Some(commit.authored_at),
     }]);
 
+    // Identity resolution links these to the developers they belong to.
+    if let Some(email) = &commit.author_email {
+        let identity_node_id =
+            uuid_from_node(GitIdentity::ENTITY_TYPE, &[("email", email.clone())]);
+        graph.add_entities(vec![GitIdentity {
+            email: Some(email.clone()),
+            name: commit.author_name.clone(),
+        }]);
+        graph.add_entities(vec![AuthoredBy {
+            id: Some(uuid_from_edge(
+                AuthoredBy::ENTITY_TYPE,
+                &commit_node_id,
+                &identity_node_id,
+            )),
+            from_node_id: Some(commit_node_id),
+            to_node_id: Some(identity_node_id),
+            from_node_type: Some(Commit::ENTITY_TYPE.to_string()),
+            to_node_type: Some(GitIdentity::ENTITY_TYPE.to_string()),
+            created_at: Some(commit.authored_at),
+            updated_at: Some(commit.authored_at),
+        }]);
+    }
+
     let mut developer_node_ids = HashMap::new();
     if params.include_developers {
         add_developer_nodes(&mut graph, &snapshot.developers, &mut developer_node_ids);
@@ -203,16 +243,56 @@ pub async fn build_repo_snapshot_graph(
         }
     }
 
+    let mut outline = CodeOutline::default();
     if params.include_code {
-        append_code_graph(
+        let file_filter = params
+            .targets
+            .as_ref()
+            .filter(|targets| !targets.files.is_empty())
+            .map(|targets| targets.files.iter().cloned().collect::<HashSet<_>>());
+        outline = match code_index {
+            Some(index) => translate_code_index(
+                &mut graph,
+                index,
+                commit.authored_at,
+                &revision.sha,
+                &NodeDescriptor::new(Version::ENTITY_TYPE, version_node_id.clone()),
+                file_filter.as_ref(),
+            ),
+            None => {
+                append_code_graph(
+                    &mut graph,
+                    snapshot,
+                    &version_node_id,
+                    &project_url,
+                    &repo.full_name,
+                    file_filter.as_ref(),
+                    workspace_limits,
+                    embedding_provider.clone(),
+                )
+                .await?
+            }
+        };
+        if let Some(report) = coverage {
+            translate_coverage(
+                &mut graph,
+                report,
+                &outline,
+                commit.authored_at,
+                &revision.sha,
+            );
+        }
+    }
+
+    if !benchmarks.is_empty() {
+        translate_benchmarks(
             &mut graph,
-            snapshot,
-            &version_node_id,
-            &project_url,
-            &repo.full_name,
-            embedding_provider.clone(),
-        )
-        .await?;
+            benchmarks,
+            &outline,
+            &NodeDescriptor::new(Version::ENTITY_TYPE, version_node_id.clone()),
+            commit.authored_at,
+            &revision.sha,
+        );
     }
 
     Ok(graph)
@@ -224,15 +304,24 @@ async fn append_code_graph(
     version_node_id: &str,
     project_url: &str,
     repo_full_name: &str,
+    file_filter: Option<&HashSet<String>>,
+    workspace_limits: &WorkspaceLimits,
     embedding_provider: Arc<dyn EmbeddingProvider>,
-) -> StorageResult<()> {
+) -> StorageResult<CodeOutline> {
     let repo = &snapshot.repository;
     let clone_source = repo_clone_source(repo);
+    // Targeted files are the only ones parsed; the translation below still
+    // filters, as AST may pull in files it resolves calls through.
+    let files: Vec<String> = file_filter
+        .map(|files| files.iter().cloned().collect())
+        .unwrap_or_default();
     let workspace = prepare_workspace(WorkspaceConfig {
         repo_url: &clone_source,
         display_name: &repo.full_name,
         revision: &snapshot.revision.sha,
         enable_incremental_filter: false,
+        files: &files,
+        limits: workspace_limits,
     })
     .await?;
 
@@ -240,13 +329,14 @@ async fn append_code_graph(
     let version_descriptor = NodeDescriptor::new(Version::ENTITY_TYPE, version_node_id.to_string());
     let repo_root = workspace.repo_root();
     let mut code_chunk_sources = Vec::new();
-    translate_ast_graph(
+    let outline = translate_ast_graph(
         graph,
         &code_graph,
         snapshot.commit.authored_at,
         &snapshot.revision.sha,
         &version_descriptor,
         repo_root,
+        file_filter,
         &mut code_chunk_sources,
     )?;
     emit_code_chunks(
@@ -259,7 +349,7 @@ async fn append_code_graph(
         embedding_provider,
     )
     .await?;
-    Ok(())
+    Ok(outline)
 }
 
 fn repo_clone_source(repo: &RepositoryInfo) -> String {
@@ -308,6 +398,24 @@ struct CodeChunkSource {
     chunk_order: usize,
 }
 
+/// Where the functions and tests of a code graph sit, for linking coverage
+/// reports and benchmarks to them after the nodes are flushed.
+#[derive(Default)]
+struct CodeOutline {
+    files: HashMap<String, NodeDescriptor>,
+    /// Functions by file.
+    functions: HashMap<String, Vec<OutlinedFunction>>,
+    tests: HashMap<String, Vec<NodeDescriptor>>,
+}
+
+struct OutlinedFunction {
+    descriptor: NodeDescriptor,
+    name: String,
+    /// 1-based line span.
+    start_line: u32,
+    end_line: u32,
+}
+
 #[derive(Default)]
 struct NodeBuckets {
     files: Vec<File>,
@@ -322,6 +430,72 @@ struct NodeBuckets {
 }
 
 impl NodeBuckets {
+    fn outline(&self) -> CodeOutline {
+        let mut outline = CodeOutline::default();
+        for file in &self.files {
+            let (Some(version_sha), Some(path)) = (&file.version_sha, &file.path) else {
+                continue;
+            };
+            let node_id = uuid_from_node(
+                File::ENTITY_TYPE,
+                &[("version_sha", version_sha.clone()), ("path", path.clone())],
+            );
+            outline.files.insert(
+                path.clone(),
+                NodeDescriptor::new(File::ENTITY_TYPE, node_id),
+            );
+        }
+        for function in &self.functions {
+            let (Some(version_sha), Some(file_path), Some(name)) =
+                (&function.version_sha, &function.file_path, &function.name)
+            else {
+                continue;
+            };
+            let (Some(start), Some(end)) = (function.start_line, function.end_line) else {
+                continue;
+            };
+            let node_id = uuid_from_node(
+                Function::ENTITY_TYPE,
+                &[
+                    ("version_sha", version_sha.clone()),
+                    ("file_path", file_path.clone()),
+                    ("name", name.clone()),
+                ],
+            );
+            outline
+                .functions
+                .entry(file_path.clone())
+                .or_default()
+                .push(OutlinedFunction {
+                    descriptor: NodeDescriptor::new(Function::ENTITY_TYPE, node_id),
+                    name: name.clone(),
+                    start_line: u32::try_from(start).unwrap_or(0),
+                    end_line: u32::try_from(end).unwrap_or(0),
+                });
+        }
+        for test in &self.tests {
+            let (Some(version_sha), Some(file_path), Some(name)) =
+                (&test.version_sha, &test.file_path, &test.name)
+            else {
+                continue;
+            };
+            let node_id = uuid_from_node(
+                Test::ENTITY_TYPE,
+                &[
+                    ("version_sha", version_sha.clone()),
+                    ("file_path", file_path.clone()),
+                    ("name", name.clone()),
+                ],
+            );
+            outline
+                .tests
+                .entry(name.clone())
+                .or_default()
+                .push(NodeDescriptor::new(Test::ENTITY_TYPE, node_id));
+        }
+        outline
+    }
+
     fn flush(self, graph: &mut GraphData) {
         if !self.files.is_empty() {
             graph.add_entities(self.files);
@@ -421,12 +595,21 @@ fn translate_ast_graph(
     version_sha: &str,
     version_descriptor: &NodeDescriptor,
     repo_root: &Path,
+    file_filter: Option<&HashSet<String>>,
     code_chunk_sources: &mut Vec<CodeChunkSource>,
-) -> StorageResult<()> {
+) -> StorageResult<CodeOutline> {
     let mut descriptors: HashMap<String, NodeDescriptor> = HashMap::new();
     let mut nodes = NodeBuckets::default();
 
     for (key, node) in &code_graph.nodes {
+        // Nodes outside the targeted files are dropped, and with them every edge
+        // that touches them.
+        if let Some(files) = file_filter {
+            let in_scope = ast_node_file(node, repo_root).is_some_and(|file| files.contains(&file));
+            if !in_scope {
+                continue;
+            }
+        }
         if let Some(mapped) = map_ast_node(node, version_sha, repo_root) {
             match mapped {
                 MappedNode::File(value, descriptor) => {
@@ -489,6 +672,7 @@ fn translate_ast_graph(
         }
     }
 
+    let outline = nodes.outline();
     nodes.flush(graph);
 
     let mut edges = EdgeBuckets::default();
@@ -557,7 +741,305 @@ fn translate_ast_graph(
     }
 
     edges.flush(graph);
-    Ok(())
+    Ok(outline)
+}
+
+/// Maps a precise code index onto the nodes `translate_ast_graph` produces.
+/// A reference becomes a `Calls` edge when it points at a function defined in
+/// the index and lies inside another one. No code chunks are emitted, as the
+/// index carries no source text.
+fn translate_code_index(
+    graph: &mut GraphData,
+    index: &CodeIndex,
+    commit_ts: DateTime<Utc>,
+    version_sha: &str,
+    version_descriptor: &NodeDescriptor,
+    file_filter: Option<&HashSet<String>>,
+) -> CodeOutline {
+    let in_scope = |path: &str| file_filter.map_or(true, |files| files.contains(path));
+    let mut nodes = NodeBuckets::default();
+    let mut edges = EdgeBuckets::default();
+    let mut symbols: HashMap<&str, NodeDescriptor> = HashMap::new();
+
+    for (path, document) in &index.documents {
+        if !in_scope(path) {
+            continue;
+        }
+        let file_descriptor = NodeDescriptor::new(
+            File::ENTITY_TYPE,
+            uuid_from_node(
+                File::ENTITY_TYPE,
+                &[
+                    ("version_sha", version_sha.to_string()),
+                    ("path", path.clone()),
+                ],
+            ),
+        );
+        nodes.files.push(File {
+            version_sha: Some(version_sha.to_string()),
+            path: Some(path.clone()),
+            language: document.language.clone(),
+        });
+        edges.contains.push(make_contains(
+            version_descriptor,
+            &file_descriptor,
+            commit_ts,
+        ));
+
+        for definition in &document.definitions {
+            let entity_type = match definition.kind {
+                SymbolKind::Function => Function::ENTITY_TYPE,
+                SymbolKind::Class => Class::ENTITY_TYPE,
+                SymbolKind::Trait => Trait::ENTITY_TYPE,
+                SymbolKind::DataModel => DataModel::ENTITY_TYPE,
+                SymbolKind::Variable => Variable::ENTITY_TYPE,
+            };
+            let descriptor = NodeDescriptor::new(
+                entity_type,
+                uuid_from_node(
+                    entity_type,
+                    &[
+                        ("version_sha", version_sha.to_string()),
+                        ("file_path", path.clone()),
+                        ("name", definition.name.clone()),
+                    ],
+                ),
+            );
+            let start_line = line_number(definition.start_line as usize);
+            let end_line = line_number(
+                definition
+                    .end_line
+                    .unwrap_or(definition.start_line)
+                    .max(definition.start_line) as usize,
+            );
+            let version_sha = Some(version_sha.to_string());
+            let file_path = Some(path.clone());
+            let name = Some(definition.name.clone());
+            match definition.kind {
+                SymbolKind::Function => nodes.functions.push(Function {
+                    version_sha,
+                    file_path,
+                    name,
+                    signature: definition.signature.clone(),
+                    start_line,
+                    end_line,
+                    is_component: None,
+                }),
+                SymbolKind::Class => nodes.classes.push(Class {
+                    version_sha,
+                    file_path,
+                    name,
+                    start_line,
+                    end_line,
+                }),
+                SymbolKind::Trait => nodes.traits.push(Trait {
+                    version_sha,
+                    file_path,
+                    name,
+                    start_line,
+                    end_line,
+                }),
+                SymbolKind::DataModel => nodes.data_models.push(DataModel {
+                    version_sha,
+                    file_path,
+                    name,
+                    construct: None,
+                    start_line,
+                    end_line,
+                }),
+                SymbolKind::Variable => nodes.variables.push(Variable {
+                    version_sha,
+                    file_path,
+                    name,
+                    data_type: None,
+                }),
+            }
+            push_contains_edge(&mut edges, &file_descriptor, &descriptor, commit_ts);
+            symbols
+                .entry(definition.symbol.as_str())
+                .or_insert(descriptor);
+        }
+    }
+
+    let mut seen_calls = HashSet::new();
+    for (path, document) in &index.documents {
+        if !in_scope(path) {
+            continue;
+        }
+        for reference in &document.references {
+            let Some(callee) = symbols
+                .get(reference.symbol.as_str())
+                .filter(|callee| callee.entity_type() == Function::ENTITY_TYPE)
+            else {
+                continue;
+            };
+            let Some(caller) = document
+                .enclosing_function(reference.line)
+                .and_then(|caller| symbols.get(caller.symbol.as_str()))
+            else {
+                continue;
+            };
+            if seen_calls.insert((caller.node_id().to_string(), callee.node_id().to_string())) {
+                edges.calls.push(make_calls(caller, callee, commit_ts));
+            }
+        }
+    }
+
+    let outline = nodes.outline();
+    nodes.flush(graph);
+    edges.flush(graph);
+    outline
+}
+
+/// Draws `Covers` edges from each test of `report` to the functions and files
+/// of the code graph it executed. Tests are matched to parsed Test nodes by
+/// name; a test the code graph does not know, such as a whole suite named
+/// after its report, gets a Test node of kind "coverage" filed under the
+/// report's path.
+fn translate_coverage(
+    graph: &mut GraphData,
+    report: &CoverageReport,
+    outline: &CodeOutline,
+    commit_ts: DateTime<Utc>,
+    version_sha: &str,
+) {
+    let mut suites = Vec::new();
+    let mut covers = Vec::new();
+    for (name, test) in &report.tests {
+        let targets: Vec<(&NodeDescriptor, LineCoverage)> = test
+            .files
+            .iter()
+            .flat_map(|(path, file)| {
+                let file_target = outline
+                    .files
+                    .get(path)
+                    .map(|descriptor| (descriptor, file.summary(None)));
+                let function_targets =
+                    outline
+                        .functions
+                        .get(path)
+                        .into_iter()
+                        .flatten()
+                        .map(|function| {
+                            let span = (function.start_line, function.end_line);
+                            (&function.descriptor, file.summary(Some(span)))
+                        });
+                file_target.into_iter().chain(function_targets)
+            })
+            .filter(|(_, lines)| lines.covered > 0)
+            .collect();
+        if targets.is_empty() {
+            continue;
+        }
+
+        let sources = match outline.tests.get(name) {
+            Some(tests) => tests.clone(),
+            None => {
+                let node_id = uuid_from_node(
+                    Test::ENTITY_TYPE,
+                    &[
+                        ("version_sha", version_sha.to_string()),
+                        ("file_path", test.source.clone()),
+                        ("name", name.clone()),
+                    ],
+                );
+                suites.push(Test {
+                    version_sha: Some(version_sha.to_string()),
+                    file_path: Some(test.source.clone()),
+                    name: Some(name.clone()),
+                    test_kind: Some("coverage".to_string()),
+                    start_line: None,
+                    end_line: None,
+                });
+                vec![NodeDescriptor::new(Test::ENTITY_TYPE, node_id)]
+            }
+        };
+        for source in &sources {
+            for (target, lines) in &targets {
+                covers.push(make_covers(source, target, *lines, commit_ts));
+            }
+        }
+    }
+
+    if !suites.is_empty() {
+        graph.add_entities(suites);
+    }
+    if !covers.is_empty() {
+        graph.add_entities(covers);
+    }
+}
+
+/// Adds a BenchmarkResult node per benchmark, linked from the version and to
+/// the function it measures. A benchmark is linked to the first of its
+/// [`BenchmarkRecord::function_names`] that names exactly one function of the
+/// code graph, and to none when every candidate is missing or ambiguous.
+fn translate_benchmarks(
+    graph: &mut GraphData,
+    benchmarks: &[BenchmarkRecord],
+    outline: &CodeOutline,
+    version_descriptor: &NodeDescriptor,
+    commit_ts: DateTime<Utc>,
+    version_sha: &str,
+) {
+    let mut functions_by_name: HashMap<&str, Vec<&NodeDescriptor>> = HashMap::new();
+    for function in outline.functions.values().flatten() {
+        functions_by_name
+            .entry(function.name.as_str())
+            .or_default()
+            .push(&function.descriptor);
+    }
+    // A benchmark reported by several files keeps its last result.
+    let latest: BTreeMap<&str, &BenchmarkRecord> = benchmarks
+        .iter()
+        .map(|benchmark| (benchmark.name.as_str(), benchmark))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut has_benchmark = Vec::new();
+    let mut measures = Vec::new();
+    for (name, benchmark) in latest {
+        let descriptor = NodeDescriptor::new(
+            BenchmarkResult::ENTITY_TYPE,
+            uuid_from_node(
+                BenchmarkResult::ENTITY_TYPE,
+                &[
+                    ("version_sha", version_sha.to_string()),
+                    ("name", name.to_string()),
+                ],
+            ),
+        );
+        results.push(BenchmarkResult {
+            version_sha: Some(version_sha.to_string()),
+            name: Some(name.to_string()),
+            unit: Some(benchmark.unit.clone()),
+            mean: Some(benchmark.mean),
+            median: Some(benchmark.median),
+            lower_bound: Some(benchmark.lower_bound),
+            upper_bound: Some(benchmark.upper_bound),
+            change_pct: benchmark.change_pct,
+            verdict: benchmark
+                .verdict
+                .map(|verdict| verdict.as_str().to_string()),
+        });
+        has_benchmark.push(make_has_benchmark(
+            version_descriptor,
+            &descriptor,
+            commit_ts,
+        ));
+        let function = benchmark.function_names().into_iter().find_map(|name| {
+            match functions_by_name.get(name).map(Vec::as_slice) {
+                Some([function]) => Some(*function),
+                _ => None,
+            }
+        });
+        if let Some(function) = function {
+            measures.push(make_measures(&descriptor, function, commit_ts));
+        }
+    }
+
+    graph.add_entities(results);
+    graph.add_entities(has_benchmark);
+    graph.add_entities(measures);
 }
 
 async fn emit_code_chunks(
@@ -629,6 +1111,7 @@ async fn emit_code_chunks(
 
         let token_count = approximate_token_count(&text);
         let embedding_model_value = embedding.as_ref().and_then(|_| embedding_model.clone());
+        let (start_line, end_line) = line_bounds(&source.node_data);
         let chunk = CodeChunk {
             id: Some(chunk_id),
             project_url: Some(project_url.clone()),
@@ -637,6 +1120,8 @@ async fn emit_code_chunks(
             source_node_key: Some(source_node_key),
             source_node_id: Some(source.descriptor.node_id().to_string()),
             language: source.language.clone(),
+            start_line,
+            end_line,
             text: Some(text),
             embedding,
             embedding_model: embedding_model_value,
@@ -725,6 +1210,7 @@ mod tests {
             "deadbeef",
             &version_descriptor,
             Path::new("/dummy/repo"),
+            None,
             &mut Vec::new(),
         )
         .expect("translate");
@@ -814,6 +1300,71 @@ mod tests {
             "expected file->library depends_on edge"
         );
     }
+
+    #[test]
+    fn translate_ast_graph_keeps_only_targeted_files() {
+        let mut graph = GraphData::new();
+        let mut code_graph = BTreeMapGraph::default();
+
+        let mut chunks = Vec::new();
+        for path in ["src/lib.rs", "src/other.rs"] {
+            let mut file_node = NodeData::default();
+            file_node.name = path.into();
+            file_node.file = path.into();
+            let mut function_node = NodeData::default();
+            function_node.name = format!("greet_{}", path.len());
+            function_node.file = path.into();
+            function_node.start = 0;
+            function_node.end = 1;
+            code_graph.add_node(NodeType::File, file_node.clone());
+            code_graph.add_node(NodeType::Function, function_node.clone());
+            code_graph.add_edge(Edge::contains(
+                NodeType::File,
+                &file_node,
+                NodeType::Function,
+                &function_node,
+            ));
+        }
+
+        let version_descriptor = NodeDescriptor::new(
+            Version::ENTITY_TYPE,
+            uuid_from_node(Version::ENTITY_TYPE, &[("sha", "deadbeef".to_string())]),
+        );
+        let targeted: HashSet<String> = ["src/lib.rs".to_string()].into_iter().collect();
+        translate_ast_graph(
+            &mut graph,
+            &code_graph,
+            Utc::now(),
+            "deadbeef",
+            &version_descriptor,
+            Path::new("/dummy/repo"),
+            Some(&targeted),
+            &mut chunks,
+        )
+        .expect("translate");
+
+        let rows = |entity_type: &str| -> usize {
+            graph
+                .entities
+                .iter()
+                .filter(|entity| entity.entity_type_any() == entity_type)
+                .map(|entity| entity.to_record_batch_any().expect("batch").num_rows())
+                .sum()
+        };
+        assert_eq!(rows(File::ENTITY_TYPE), 1);
+        assert_eq!(rows(Function::ENTITY_TYPE), 1);
+        assert!(chunks.iter().all(|chunk| chunk.file_path == "src/lib.rs"));
+    }
+}
+
+/// Repository-relative path of the file `node` belongs to.
+fn ast_node_file(node: &AstNode, repo_root: &Path) -> Option<String> {
+    let raw_path = match node.node_type {
+        NodeType::File => optional_string(&node.node_data.file)
+            .or_else(|| optional_string(&node.node_data.name))?,
+        _ => node_file_path(&node.node_data)?,
+    };
+    Some(normalize_file_path(&raw_path, repo_root))
 }
 
 fn map_ast_node(node: &AstNode, version_sha: &str, repo_root: &Path) -> Option<MappedNode> {
@@ -1206,6 +1757,61 @@ fn make_depends_on(
     }
 }
 
+fn make_covers(
+    from: &NodeDescriptor,
+    to: &NodeDescriptor,
+    lines: LineCoverage,
+    created_at: DateTime<Utc>,
+) -> Covers {
+    let base = edge_base(Covers::ENTITY_TYPE, from, to, created_at);
+    Covers {
+        id: Some(base.id),
+        from_node_id: Some(base.from_node_id),
+        to_node_id: Some(base.to_node_id),
+        from_node_type: Some(base.from_node_type),
+        to_node_type: Some(base.to_node_type),
+        created_at: base.created_at,
+        updated_at: None,
+        covered_lines: Some(i64::from(lines.covered)),
+        total_lines: Some(i64::from(lines.total)),
+        coverage_pct: Some(lines.percent()),
+    }
+}
+
+fn make_has_benchmark(
+    from: &NodeDescriptor,
+    to: &NodeDescriptor,
+    created_at: DateTime<Utc>,
+) -> HasBenchmark {
+    let base = edge_base(HasBenchmark::ENTITY_TYPE, from, to, created_at);
+    HasBenchmark {
+        id: Some(base.id),
+        from_node_id: Some(base.from_node_id),
+        to_node_id: Some(base.to_node_id),
+        from_node_type: Some(base.from_node_type),
+        to_node_type: Some(base.to_node_type),
+        created_at: base.created_at,
+        updated_at: None,
+    }
+}
+
+fn make_measures(
+    from: &NodeDescriptor,
+    to: &NodeDescriptor,
+    created_at: DateTime<Utc>,
+) -> Measures {
+    let base = edge_base(Measures::ENTITY_TYPE, from, to, created_at);
+    Measures {
+        id: Some(base.id),
+        from_node_id: Some(base.from_node_id),
+        to_node_id: Some(base.to_node_id),
+        from_node_type: Some(base.from_node_type),
+        to_node_type: Some(base.to_node_type),
+        created_at: base.created_at,
+        updated_at: None,
+    }
+}
+
 fn make_calls(from: &NodeDescriptor, to: &NodeDescriptor, created_at: DateTime<Utc>) -> Calls {
     let base = edge_base(Calls::ENTITY_TYPE, from, to, created_at);
     Calls {
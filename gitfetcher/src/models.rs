@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryInfo {
     pub owner: String,
     pub name: String,
@@ -12,30 +12,42 @@ pub struct RepositoryInfo {
     pub stargazers: u64,
     pub forks: u64,
     pub default_branch: Option<String>,
+    /// Repository size in KiB as reported by GitHub.
+    #[serde(default)]
+    pub size_kb: Option<u64>,
+    /// SPDX id of the detected license.
+    #[serde(default)]
+    pub license: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitInfo {
     pub sha: String,
     pub message: String,
     pub author: Option<String>,
+    /// Name and email git recorded for the author, which need not match any
+    /// GitHub account.
+    #[serde(default)]
+    pub author_name: Option<String>,
+    #[serde(default)]
+    pub author_email: Option<String>,
     pub authored_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolvedRevision {
     pub reference: Option<String>,
     pub sha: String,
     pub is_head: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadmeContent {
     pub text: String,
     pub source_file: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeveloperProfile {
     pub platform: String,
     pub account_id: String,
@@ -50,14 +62,14 @@ pub struct DeveloperProfile {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LabelInfo {
     pub name: String,
     pub color: Option<String>,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ReactionSummary {
     pub plus_one: u64,
     pub heart: u64,
@@ -67,13 +79,13 @@ pub struct ReactionSummary {
     pub confused: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CommentKind {
     Issue,
     Review,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommentInfo {
     pub id: i64,
     pub body: String,
@@ -92,7 +104,7 @@ pub struct CommentInfo {
     pub position: Option<i64>,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssueRelation {
     pub owner: String,
     pub repo: String,
@@ -103,7 +115,7 @@ pub struct IssueRelation {
     pub cross_repo: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssueInfo {
     pub project_url: String,
     pub number: i64,
@@ -126,7 +138,7 @@ pub struct IssueInfo {
     pub representative_digest_text: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequestInfo {
     pub project_url: String,
     pub number: i64,
@@ -163,7 +175,7 @@ pub struct PullRequestInfo {
     pub related_issues: Vec<IssueRelation>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoSnapshot {
     pub repository: RepositoryInfo,
     pub revision: ResolvedRevision,
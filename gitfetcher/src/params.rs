@@ -1,6 +1,13 @@
-use serde::Deserialize;
+use fstorage::fetch::Fetchable;
+use fstorage::models::EntityIdentifier;
+use fstorage::schemas::generated_schemas::{File, Issue, PullRequest};
+use serde::{Deserialize, Serialize};
 
+use crate::benchmark::BenchmarkSource;
+use crate::code_index::CodeIndexSource;
+use crate::coverage::CoverageSource;
 use crate::error::{GitFetcherError, Result};
+use crate::models::RepoSnapshot;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RepoSnapshotParams {
@@ -23,6 +30,36 @@ pub struct RepoSnapshotParams {
     pub touches_mode: TouchesMode,
     #[serde(default)]
     pub representative_comment_limit: Option<usize>,
+    /// When set, only these entity groups are synced and the `include_*` flags are ignored.
+    #[serde(default)]
+    pub entity_types: Option<Vec<SnapshotEntityType>>,
+    /// When set, only these files, issues and pull requests are synced.
+    #[serde(default)]
+    pub targets: Option<SnapshotTargets>,
+    /// SCIP or LSIF index to take the code graph from instead of parsing a checkout.
+    #[serde(default)]
+    pub code_index: Option<CodeIndexSource>,
+    /// lcov or Cobertura reports whose tests are linked to the code they cover.
+    #[serde(default)]
+    pub coverage: Vec<CoverageSource>,
+    /// Benchmark results measured at `rev`, recorded against the version.
+    #[serde(default)]
+    pub benchmarks: Vec<BenchmarkSource>,
+}
+
+/// Entity groups of a repository snapshot that can be synced on their own.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotEntityType {
+    Code,
+    #[serde(alias = "readme_chunk")]
+    Readme,
+    #[serde(alias = "issues")]
+    Issue,
+    #[serde(alias = "pulls", alias = "pull_requests")]
+    PullRequest,
+    #[serde(alias = "developers")]
+    Developer,
 }
 
 fn default_include_readme() -> bool {
@@ -56,6 +93,117 @@ impl RepoSnapshotParams {
             ))),
         }
     }
+
+    /// Turns the flags off for every group `targets` does not name. Readme and
+    /// developers are never targets, so a targeted sync skips them.
+    pub fn apply_targets(&mut self) {
+        let Some(targets) = self.targets.as_ref().filter(|targets| !targets.is_empty()) else {
+            return;
+        };
+        self.include_code &= !targets.files.is_empty();
+        self.include_issues &= !targets.issues.is_empty();
+        self.include_pulls &= !targets.pull_requests.is_empty();
+        self.include_readme = false;
+        self.include_developers = false;
+    }
+
+    /// Narrows the `include_*` flags to the selected `entity_types`, if any.
+    pub fn apply_entity_types(&mut self) {
+        let Some(selected) = &self.entity_types else {
+            return;
+        };
+        let wants = |kind: SnapshotEntityType| selected.contains(&kind);
+        self.include_code = wants(SnapshotEntityType::Code);
+        self.include_readme = wants(SnapshotEntityType::Readme);
+        self.include_issues = wants(SnapshotEntityType::Issue);
+        self.include_pulls = wants(SnapshotEntityType::PullRequest);
+        self.include_developers = wants(SnapshotEntityType::Developer);
+    }
+}
+
+/// Individual entities a repository snapshot is restricted to.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct SnapshotTargets {
+    /// Repository-relative file paths whose code graph is rebuilt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pull_requests: Vec<i64>,
+}
+
+impl SnapshotTargets {
+    /// Collects the file, issue and pull request identifiers among `identifiers`.
+    ///
+    /// A file is identified by its repository-relative path; issues and pull
+    /// requests by a URI ending in their number (`42`, `owner/repo#42` or the
+    /// GitHub URL). Identifiers of other entity types are ignored.
+    pub fn from_identifiers(identifiers: &[EntityIdentifier]) -> Result<Self> {
+        let mut targets = Self::default();
+        for identifier in identifiers {
+            let uri = identifier.uri.trim();
+            match identifier.entity_type.as_str() {
+                t if t == File::ENTITY_TYPE => {
+                    let path = uri.trim_start_matches("./").trim_start_matches('/');
+                    if path.is_empty() {
+                        return Err(GitFetcherError::InvalidParam(
+                            "file target needs a path".to_string(),
+                        ));
+                    }
+                    targets.files.push(path.to_string());
+                }
+                t if t == Issue::ENTITY_TYPE => targets.issues.push(trailing_number(uri)?),
+                t if t == PullRequest::ENTITY_TYPE => {
+                    targets.pull_requests.push(trailing_number(uri)?)
+                }
+                _ => {}
+            }
+        }
+        targets.normalize();
+        Ok(targets)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.issues.is_empty() && self.pull_requests.is_empty()
+    }
+
+    pub fn merge(&mut self, other: SnapshotTargets) {
+        self.files.extend(other.files);
+        self.issues.extend(other.issues);
+        self.pull_requests.extend(other.pull_requests);
+        self.normalize();
+    }
+
+    /// Drops the issues and pull requests of `snapshot` that are not targeted.
+    pub fn retain_in(&self, snapshot: &mut RepoSnapshot) {
+        snapshot
+            .issues
+            .retain(|issue| self.issues.contains(&issue.number));
+        snapshot
+            .pull_requests
+            .retain(|pull| self.pull_requests.contains(&pull.number));
+    }
+
+    // Sorted, deduplicated targets keep the params (and the checkpoint scope
+    // derived from them) identical for the same set of entities.
+    fn normalize(&mut self) {
+        self.files.sort();
+        self.files.dedup();
+        self.issues.sort_unstable();
+        self.issues.dedup();
+        self.pull_requests.sort_unstable();
+        self.pull_requests.dedup();
+    }
+}
+
+fn trailing_number(uri: &str) -> Result<i64> {
+    uri.rsplit(['/', '#'])
+        .next()
+        .and_then(|segment| segment.parse().ok())
+        .ok_or_else(|| {
+            GitFetcherError::InvalidParam(format!("target '{uri}' does not end in a number"))
+        })
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -0,0 +1,176 @@
+use fstorage::errors::{Result as StorageResult, StorageError};
+use serde::Deserialize;
+
+use crate::models::RepositoryInfo;
+
+/// Which repositories a shared deployment is willing to ingest.
+///
+/// Name patterns are checked before anything is fetched; the size and license
+/// limits need repository metadata and are checked as soon as it is available.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RepoPolicy {
+    /// `owner/repo` glob patterns (`*` and `?`); when non-empty, a repository must match one.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// `owner/repo` glob patterns that are always refused, even if allowed.
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Largest repository accepted, in KiB as reported by GitHub.
+    #[serde(default)]
+    pub max_size_kb: Option<u64>,
+    /// SPDX license ids accepted; when non-empty, unlicensed repositories are refused.
+    #[serde(default)]
+    pub licenses: Vec<String>,
+}
+
+impl RepoPolicy {
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_empty()
+            && self.deny.is_empty()
+            && self.max_size_kb.is_none()
+            && self.licenses.is_empty()
+    }
+
+    /// True when [`RepoPolicy::check_repository`] needs metadata beyond the name.
+    pub fn needs_metadata(&self) -> bool {
+        self.max_size_kb.is_some() || !self.licenses.is_empty()
+    }
+
+    pub fn check_name(&self, full_name: &str) -> StorageResult<()> {
+        if let Some(pattern) = self
+            .deny
+            .iter()
+            .find(|pattern| glob_match(pattern, full_name))
+        {
+            return Err(StorageError::PolicyDenied(format!(
+                "repository '{full_name}' matches deny pattern '{pattern}'"
+            )));
+        }
+        if !self.allow.is_empty()
+            && !self
+                .allow
+                .iter()
+                .any(|pattern| glob_match(pattern, full_name))
+        {
+            return Err(StorageError::PolicyDenied(format!(
+                "repository '{full_name}' is not on the allowlist"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_repository(&self, repo: &RepositoryInfo) -> StorageResult<()> {
+        self.check_name(&repo.full_name)?;
+        if let (Some(max), Some(size)) = (self.max_size_kb, repo.size_kb) {
+            if size > max {
+                return Err(StorageError::PolicyDenied(format!(
+                    "repository '{}' is {size} KiB, above the {max} KiB limit",
+                    repo.full_name
+                )));
+            }
+        }
+        if !self.licenses.is_empty() {
+            let accepted = repo.license.as_deref().is_some_and(|license| {
+                self.licenses
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(license))
+            });
+            if !accepted {
+                return Err(StorageError::PolicyDenied(format!(
+                    "repository '{}' has license {}, which is not allowed",
+                    repo.full_name,
+                    repo.license.as_deref().unwrap_or("<none>")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Case-insensitive glob match supporting `*` (any run of characters) and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(full_name: &str, size_kb: Option<u64>, license: Option<&str>) -> RepositoryInfo {
+        let (owner, name) = full_name.split_once('/').unwrap();
+        RepositoryInfo {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            full_name: full_name.to_string(),
+            html_url: format!("https://github.com/{full_name}"),
+            description: None,
+            language: None,
+            stargazers: 0,
+            forks: 0,
+            default_branch: None,
+            size_kb,
+            license: license.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn globs_match_owner_and_repo_segments() {
+        assert!(glob_match("rust-lang/*", "rust-lang/rust"));
+        assert!(glob_match("*/hello-?orld", "Octocat/Hello-World"));
+        assert!(!glob_match("rust-lang/*", "tokio-rs/tokio"));
+        assert!(glob_match("*", "any/repo"));
+        assert!(!glob_match("a*b", "acbd"));
+    }
+
+    #[test]
+    fn policy_checks_names_size_and_license() {
+        let policy = RepoPolicy {
+            allow: vec!["acme/*".into()],
+            deny: vec!["acme/secret-*".into()],
+            max_size_kb: Some(1_000),
+            licenses: vec!["MIT".into(), "Apache-2.0".into()],
+        };
+        assert!(policy.needs_metadata());
+        policy.check_name("acme/widgets").unwrap();
+        assert!(policy.check_name("acme/secret-sauce").is_err());
+        assert!(policy.check_name("other/widgets").is_err());
+
+        policy
+            .check_repository(&repo("acme/widgets", Some(10), Some("mit")))
+            .unwrap();
+        assert!(policy
+            .check_repository(&repo("acme/widgets", Some(5_000), Some("MIT")))
+            .is_err());
+        assert!(policy
+            .check_repository(&repo("acme/widgets", Some(10), Some("GPL-3.0")))
+            .is_err());
+        assert!(policy
+            .check_repository(&repo("acme/widgets", Some(10), None))
+            .is_err());
+        assert!(RepoPolicy::default().is_unrestricted());
+    }
+}
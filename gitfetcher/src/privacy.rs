@@ -0,0 +1,133 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::{DeveloperProfile, RepoSnapshot};
+
+const HASH_PREFIX: &str = "sha256:";
+
+/// How developer names and emails are kept when a snapshot is mapped.
+///
+/// Logins and account ids are never touched: they identify `Developer` nodes and
+/// the authorship edges around them, so the graph keeps its shape in every mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum DeveloperPrivacy {
+    /// Store names and emails as GitHub reports them.
+    #[default]
+    Keep,
+    /// Replace them with a salted SHA-256 digest; equal values still compare equal.
+    Hash {
+        #[serde(default)]
+        salt: String,
+    },
+    /// Drop them.
+    Omit,
+}
+
+impl DeveloperPrivacy {
+    pub fn is_keep(&self) -> bool {
+        matches!(self, Self::Keep)
+    }
+
+    /// Scrubs every personal field in `snapshot`, including the commit author.
+    pub fn scrub_snapshot(&self, snapshot: &mut RepoSnapshot) {
+        if self.is_keep() {
+            return;
+        }
+        snapshot.commit.author = self.scrub_value(snapshot.commit.author.take());
+        snapshot.commit.author_name = self.scrub_value(snapshot.commit.author_name.take());
+        snapshot.commit.author_email = self.scrub_value(snapshot.commit.author_email.take());
+        for developer in &mut snapshot.developers {
+            self.scrub_developer(developer);
+        }
+    }
+
+    pub fn scrub_developer(&self, developer: &mut DeveloperProfile) {
+        developer.name = self.scrub_value(developer.name.take());
+        developer.email = self.scrub_value(developer.email.take());
+    }
+
+    fn scrub_value(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Self::Keep => value,
+            Self::Omit => None,
+            // Already-hashed values come from a checkpoint written by this same mode.
+            Self::Hash { salt } => value.map(|value| {
+                if value.starts_with(HASH_PREFIX) {
+                    value
+                } else {
+                    hash_value(salt, &value)
+                }
+            }),
+        }
+    }
+}
+
+fn hash_value(salt: &str, value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update([0]);
+    hasher.update(value.trim().to_lowercase().as_bytes());
+    let digest = hasher.finalize();
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{HASH_PREFIX}{hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn developer() -> DeveloperProfile {
+        DeveloperProfile {
+            platform: "github".into(),
+            account_id: "42".into(),
+            login: "octocat".into(),
+            name: Some("The Octocat".into()),
+            company: Some("GitHub".into()),
+            followers: Some(10),
+            following: Some(1),
+            location: None,
+            email: Some("Octocat@Example.com".into()),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn hash_mode_is_stable_and_keeps_identity() {
+        let privacy = DeveloperPrivacy::Hash {
+            salt: "deployment".into(),
+        };
+        let mut first = developer();
+        privacy.scrub_developer(&mut first);
+        let email = first.email.clone().unwrap();
+        assert!(email.starts_with(HASH_PREFIX));
+        assert_eq!(first.login, "octocat");
+        assert_eq!(first.account_id, "42");
+
+        let mut second = developer();
+        second.email = Some("octocat@example.com ".into());
+        privacy.scrub_developer(&mut second);
+        assert_eq!(second.email.as_deref(), Some(email.as_str()));
+
+        // Scrubbing again (e.g. a checkpointed snapshot) leaves digests alone.
+        privacy.scrub_developer(&mut first);
+        assert_eq!(first.email.as_deref(), Some(email.as_str()));
+
+        let mut salted = developer();
+        DeveloperPrivacy::Hash {
+            salt: "other".into(),
+        }
+        .scrub_developer(&mut salted);
+        assert_ne!(salted.email.as_deref(), Some(email.as_str()));
+    }
+
+    #[test]
+    fn omit_mode_drops_names_and_emails() {
+        let mut profile = developer();
+        DeveloperPrivacy::Omit.scrub_developer(&mut profile);
+        assert!(profile.name.is_none());
+        assert!(profile.email.is_none());
+        assert_eq!(profile.company.as_deref(), Some("GitHub"));
+    }
+}
@@ -1,13 +1,26 @@
-use std::{fs, sync::Arc};
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 use chrono::{TimeZone, Utc};
+use deltalake::arrow::array::{Float64Array, StringArray};
 use fstorage::{
+    catalog::Catalog,
+    checkpoint::SyncCheckpoint,
+    config::StorageConfig,
     embedding::NullEmbeddingProvider,
+    errors::StorageError,
     fetch::{FetchResponse, Fetchable, Fetcher},
+    models::EntityIdentifier,
     schemas::generated_schemas::{
-        Commit, HasIssue, HasPr, HasVersion, IsCommit, Issue, IssueDoc, Label, OpenedIssue,
-        OpenedPr, PrDoc, Project, PullRequest, ReadmeChunk, RelatesTo, Version,
+        AuthoredBy, BenchmarkResult, Calls, Commit, Covers, File, Function, GitIdentity,
+        HasBenchmark, HasIssue, HasPr, HasVersion, IsCommit, Issue, IssueDoc, Label, Measures,
+        OpenedIssue, OpenedPr, PrDoc, Project, PullRequest, ReadmeChunk, RelatesTo, Test, Version,
     },
 };
 use git2::{Repository, Signature};
@@ -19,7 +32,7 @@ use gitfetcher::{
         ResolvedRevision, SearchRepository,
     },
     params::{RepoSnapshotParams, SearchRepoParams},
-    GitFetcher,
+    GitFetcher, RepoPolicy,
 };
 use serde_json::json;
 use tempfile::TempDir;
@@ -58,6 +71,41 @@ impl GitHubService for MockGitHubService {
     }
 }
 
+/// Counts snapshot fetches so tests can tell whether GitHub was paged again.
+struct CountingGitHubService {
+    inner: MockGitHubService,
+    snapshot_calls: AtomicUsize,
+}
+
+#[async_trait]
+impl GitHubService for CountingGitHubService {
+    async fn fetch_repo_snapshot(
+        &self,
+        owner: &str,
+        repo: &str,
+        params: &RepoSnapshotParams,
+    ) -> gitfetcher::error::Result<RepoSnapshot> {
+        self.snapshot_calls.fetch_add(1, Ordering::SeqCst);
+        self.inner.fetch_repo_snapshot(owner, repo, params).await
+    }
+
+    async fn probe_repo_snapshot(
+        &self,
+        owner: &str,
+        repo: &str,
+        rev: Option<&str>,
+    ) -> gitfetcher::error::Result<ProbeMetadata> {
+        self.inner.probe_repo_snapshot(owner, repo, rev).await
+    }
+
+    async fn search_repositories(
+        &self,
+        params: &SearchRepoParams,
+    ) -> gitfetcher::error::Result<Vec<SearchRepository>> {
+        self.inner.search_repositories(params).await
+    }
+}
+
 fn sample_snapshot() -> RepoSnapshot {
     let repository = RepositoryInfo {
         owner: "octocat".into(),
@@ -69,6 +117,8 @@ fn sample_snapshot() -> RepoSnapshot {
         stargazers: 42,
         forks: 7,
         default_branch: Some("main".into()),
+        size_kb: Some(2_048),
+        license: Some("MIT".into()),
     };
 
     let revision = ResolvedRevision {
@@ -81,6 +131,8 @@ fn sample_snapshot() -> RepoSnapshot {
         sha: "abc123".into(),
         message: "Initial commit".into(),
         author: Some("octocat".into()),
+        author_name: Some("The Octocat".into()),
+        author_email: Some("octocat@example.com".into()),
         authored_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
     };
 
@@ -292,6 +344,8 @@ fn snapshot_with_local_repo() -> (TempDir, RepoSnapshot) {
         stargazers: 0,
         forks: 0,
         default_branch: Some("master".into()),
+        size_kb: None,
+        license: None,
     };
 
     let revision = ResolvedRevision {
@@ -304,6 +358,8 @@ fn snapshot_with_local_repo() -> (TempDir, RepoSnapshot) {
         sha: oid.to_string(),
         message: "Initial revision".into(),
         author: Some("Tester".into()),
+        author_name: None,
+        author_email: None,
         authored_at: Utc::now(),
     };
 
@@ -355,6 +411,8 @@ async fn repo_snapshot_fetch_builds_graph() {
             assert!(entity_types.contains(Label::ENTITY_TYPE));
             assert!(entity_types.contains(HasVersion::ENTITY_TYPE));
             assert!(entity_types.contains(IsCommit::ENTITY_TYPE));
+            assert!(entity_types.contains(GitIdentity::ENTITY_TYPE));
+            assert!(entity_types.contains(AuthoredBy::ENTITY_TYPE));
             assert!(entity_types.contains(HasIssue::ENTITY_TYPE));
             assert!(entity_types.contains(HasPr::ENTITY_TYPE));
             assert!(entity_types.contains(OpenedIssue::ENTITY_TYPE));
@@ -390,6 +448,186 @@ async fn repo_snapshot_fetch_builds_graph() {
     }
 }
 
+#[tokio::test]
+async fn entity_types_limit_snapshot_to_selected_groups() {
+    let service = Arc::new(MockGitHubService {
+        snapshot: sample_snapshot(),
+        search_results: sample_search_results(),
+        probe: sample_probe(),
+    });
+    let fetcher = GitFetcher::new(service);
+
+    let response = fetcher
+        .fetch(
+            json!({
+                "mode": "repo_snapshot",
+                "repo": "octocat/hello-world",
+                "include_readme": true,
+                "entity_types": ["issues", "pull_request"]
+            }),
+            Arc::new(NullEmbeddingProvider),
+        )
+        .await
+        .expect("fetch should succeed");
+
+    let FetchResponse::GraphData(graph) = response else {
+        panic!("unexpected response");
+    };
+    let entity_types: std::collections::HashSet<_> = graph
+        .entities
+        .iter()
+        .map(|entity| entity.entity_type_any())
+        .collect();
+    assert!(entity_types.contains(Issue::ENTITY_TYPE));
+    assert!(entity_types.contains(PullRequest::ENTITY_TYPE));
+    assert!(entity_types.contains(Project::ENTITY_TYPE));
+    assert!(!entity_types.contains(ReadmeChunk::ENTITY_TYPE));
+
+    let Err(err) = fetcher
+        .fetch(
+            json!({
+                "mode": "repo_snapshot",
+                "repo": "octocat/hello-world",
+                "entity_types": ["wiki"]
+            }),
+            Arc::new(NullEmbeddingProvider),
+        )
+        .await
+    else {
+        panic!("unknown entity types are rejected");
+    };
+    assert!(err.to_string().contains("invalid fetch params"));
+}
+
+fn target(entity_type: &str, uri: &str) -> EntityIdentifier {
+    EntityIdentifier {
+        uri: uri.to_string(),
+        entity_type: entity_type.to_string(),
+        fetcher_name: None,
+        params: None,
+        anchor_key: None,
+    }
+}
+
+#[tokio::test]
+async fn target_entities_scope_snapshot_to_listed_issues_and_pulls() {
+    let service = Arc::new(MockGitHubService {
+        snapshot: sample_snapshot(),
+        search_results: sample_search_results(),
+        probe: sample_probe(),
+    });
+    let fetcher = GitFetcher::new(service);
+
+    let params = fetcher
+        .scope_params(
+            json!({ "mode": "repo_snapshot", "repo": "octocat/hello-world" }),
+            &[
+                target("issue", "https://github.com/octocat/hello-world/issues/1"),
+                target("pull_request", "octocat/hello-world#7"),
+                target("project", "repo://octocat/hello-world"),
+            ],
+        )
+        .expect("targets are understood");
+    assert_eq!(
+        params["targets"],
+        json!({ "issues": [1], "pull_requests": [7] })
+    );
+
+    let response = fetcher
+        .fetch(params, Arc::new(NullEmbeddingProvider))
+        .await
+        .expect("fetch should succeed");
+    let FetchResponse::GraphData(graph) = response else {
+        panic!("unexpected response");
+    };
+    let entity_types: std::collections::HashSet<_> = graph
+        .entities
+        .iter()
+        .map(|entity| entity.entity_type_any())
+        .collect();
+    assert!(entity_types.contains(Issue::ENTITY_TYPE));
+    assert!(!entity_types.contains(PullRequest::ENTITY_TYPE));
+    assert!(!entity_types.contains(ReadmeChunk::ENTITY_TYPE));
+
+    let untouched = fetcher
+        .scope_params(
+            json!({ "mode": "search_repo", "query": "rust" }),
+            &[target("issue", "1")],
+        )
+        .expect("search params pass through");
+    assert!(untouched.get("targets").is_none());
+    assert!(fetcher
+        .scope_params(
+            json!({ "mode": "repo_snapshot", "repo": "octocat/hello-world" }),
+            &[target("issue", "latest")],
+        )
+        .is_err());
+}
+
+#[tokio::test]
+async fn repo_policy_refuses_disallowed_repositories() {
+    let fetcher = |policy: RepoPolicy| {
+        GitFetcher::new(Arc::new(MockGitHubService {
+            snapshot: sample_snapshot(),
+            search_results: sample_search_results(),
+            probe: sample_probe(),
+        }))
+        .with_policy(policy)
+    };
+    let snapshot_params =
+        |repo: &str| json!({ "mode": "repo_snapshot", "repo": repo, "include_code": false });
+
+    let allowlisted = fetcher(RepoPolicy {
+        allow: vec!["octocat/*".into()],
+        ..Default::default()
+    });
+    allowlisted
+        .admit(&snapshot_params("octocat/hello-world"))
+        .expect("allowlisted repository is admitted");
+    assert!(matches!(
+        allowlisted.admit(&snapshot_params("someone/else")),
+        Err(StorageError::PolicyDenied(_))
+    ));
+    allowlisted
+        .admit(&json!({ "mode": "search_repo", "query": "rust" }))
+        .expect("searches are not restricted");
+
+    // Size and license limits are enforced once the repository metadata is known.
+    for policy in [
+        RepoPolicy {
+            max_size_kb: Some(1_024),
+            ..Default::default()
+        },
+        RepoPolicy {
+            licenses: vec!["Apache-2.0".into()],
+            ..Default::default()
+        },
+    ] {
+        let result = fetcher(policy)
+            .fetch(
+                snapshot_params("octocat/hello-world"),
+                Arc::new(NullEmbeddingProvider),
+            )
+            .await;
+        assert!(
+            matches!(result, Err(StorageError::PolicyDenied(_))),
+            "policy should refuse the snapshot"
+        );
+    }
+
+    fetcher(RepoPolicy {
+        max_size_kb: Some(4_096),
+        licenses: vec!["mit".into()],
+        ..Default::default()
+    })
+    .fetch(
+        snapshot_params("octocat/hello-world"),
+        Arc::new(NullEmbeddingProvider),
+    )
+    .await
+    .expect("repository within the policy is fetched");
+}
+
 #[tokio::test]
 async fn search_repo_fetch_returns_panel() {
     let service = Arc::new(MockGitHubService {
@@ -472,3 +710,261 @@ async fn repo_snapshot_fetch_with_code_succeeds() {
 
     drop(temp_dir);
 }
+
+#[tokio::test]
+async fn resumable_fetch_reuses_checkpointed_snapshot() {
+    let service = Arc::new(CountingGitHubService {
+        inner: MockGitHubService {
+            snapshot: sample_snapshot(),
+            search_results: sample_search_results(),
+            probe: sample_probe(),
+        },
+        snapshot_calls: AtomicUsize::new(0),
+    });
+    let fetcher = GitFetcher::new(service.clone());
+
+    let dir = TempDir::new().expect("temp dir");
+    let catalog = Arc::new(Catalog::new(&StorageConfig::new(dir.path())).expect("catalog"));
+    catalog.initialize_schema().expect("schema");
+    let params = json!({ "mode": "repo_snapshot", "repo": "octocat/hello-world" });
+    let checkpoint = Arc::new(SyncCheckpoint::for_sync(catalog, "gitfetcher", &params));
+
+    for _ in 0..2 {
+        let response = fetcher
+            .fetch_resumable(
+                params.clone(),
+                Arc::new(NullEmbeddingProvider),
+                Arc::clone(&checkpoint),
+            )
+            .await
+            .expect("fetch should succeed");
+        assert!(matches!(response, FetchResponse::GraphData(_)));
+    }
+    assert_eq!(service.snapshot_calls.load(Ordering::SeqCst), 1);
+
+    checkpoint.clear().expect("clear checkpoint");
+    fetcher
+        .fetch_resumable(params, Arc::new(NullEmbeddingProvider), checkpoint)
+        .await
+        .expect("fetch should succeed");
+    assert_eq!(service.snapshot_calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn code_index_replaces_tree_sitter_parsing() {
+    let index_dir = TempDir::new().expect("index dir");
+    let index = json!({
+        "documents": [{
+            "relativePath": "src/lib.rs",
+            "language": "rust",
+            "occurrences": [
+                {"range": [0, 7, 12], "symbol": "rust-analyzer cargo hello 0.1.0 parse().",
+                 "symbolRoles": 1, "enclosingRange": [0, 0, 2, 1]},
+                {"range": [4, 7, 11], "symbol": "rust-analyzer cargo hello 0.1.0 main().",
+                 "symbolRoles": 1, "enclosingRange": [4, 0, 6, 1]},
+                {"range": [5, 4, 9], "symbol": "rust-analyzer cargo hello 0.1.0 parse()."}
+            ],
+            "symbols": [
+                {"symbol": "rust-analyzer cargo hello 0.1.0 parse().", "kind": "Function"},
+                {"symbol": "rust-analyzer cargo hello 0.1.0 main().", "kind": "Function"}
+            ]
+        }]
+    });
+    fs::write(index_dir.path().join("index.scip.json"), index.to_string()).expect("write index");
+
+    let service = Arc::new(MockGitHubService {
+        snapshot: sample_snapshot(),
+        search_results: sample_search_results(),
+        probe: sample_probe(),
+    });
+    let params = json!({
+        "mode": "repo_snapshot",
+        "repo": "octocat/hello-world",
+        "include_code": true,
+        "code_index": {"path": "index.scip.json"}
+    });
+
+    // Without an index directory the path is never read.
+    let refused = GitFetcher::new(service.clone())
+        .fetch(params.clone(), Arc::new(NullEmbeddingProvider))
+        .await;
+    assert!(matches!(refused, Err(StorageError::InvalidArg(_))));
+
+    // No checkout is made: every code node comes from the index.
+    let response = GitFetcher::new(service)
+        .with_code_index_dir(index_dir.path())
+        .fetch(params, Arc::new(NullEmbeddingProvider))
+        .await
+        .expect("fetch should succeed");
+    let FetchResponse::GraphData(graph) = response else {
+        panic!("unexpected response");
+    };
+    let rows = |entity_type: &str| -> usize {
+        graph
+            .entities
+            .iter()
+            .filter(|entity| entity.entity_type_any() == entity_type)
+            .map(|entity| entity.to_record_batch_any().expect("batch").num_rows())
+            .sum()
+    };
+    assert_eq!(rows(File::ENTITY_TYPE), 1);
+    assert_eq!(rows(Function::ENTITY_TYPE), 2);
+    assert_eq!(rows(Calls::ENTITY_TYPE), 1);
+}
+
+#[tokio::test]
+async fn coverage_reports_link_tests_to_covered_code() {
+    let artifacts = TempDir::new().expect("artifact dir");
+    let index = json!({
+        "documents": [{
+            "relativePath": "src/lib.rs",
+            "occurrences": [
+                {"range": [0, 7, 12], "symbol": "rust-analyzer cargo hello 0.1.0 parse().",
+                 "symbolRoles": 1, "enclosingRange": [0, 0, 2, 1]},
+                {"range": [4, 7, 11], "symbol": "rust-analyzer cargo hello 0.1.0 main().",
+                 "symbolRoles": 1, "enclosingRange": [4, 0, 6, 1]}
+            ],
+            "symbols": [
+                {"symbol": "rust-analyzer cargo hello 0.1.0 parse().", "kind": "Function"},
+                {"symbol": "rust-analyzer cargo hello 0.1.0 main().", "kind": "Function"}
+            ]
+        }]
+    });
+    fs::write(artifacts.path().join("index.scip.json"), index.to_string()).expect("write index");
+    // parse() spans lines 1-3 and main() lines 5-7; only parse() runs.
+    let tracefile = "TN:parse_roundtrip\nSF:/ci/hello/src/lib.rs\nDA:1,1\nDA:2,4\nDA:3,0\nDA:5,0\nDA:6,0\nend_of_record\n";
+    fs::write(artifacts.path().join("lcov.info"), tracefile).expect("write report");
+
+    let service = Arc::new(MockGitHubService {
+        snapshot: sample_snapshot(),
+        search_results: sample_search_results(),
+        probe: sample_probe(),
+    });
+    let params = json!({
+        "mode": "repo_snapshot",
+        "repo": "octocat/hello-world",
+        "include_code": true,
+        "code_index": {"path": "index.scip.json"},
+        "coverage": [{"path": "lcov.info", "path_prefix": "/ci/hello"}]
+    });
+
+    let refused = GitFetcher::new(service.clone())
+        .with_code_index_dir(artifacts.path())
+        .fetch(params.clone(), Arc::new(NullEmbeddingProvider))
+        .await;
+    assert!(matches!(refused, Err(StorageError::InvalidArg(_))));
+
+    let response = GitFetcher::new(service)
+        .with_code_index_dir(artifacts.path())
+        .with_coverage_dir(artifacts.path())
+        .fetch(params, Arc::new(NullEmbeddingProvider))
+        .await
+        .expect("fetch should succeed");
+    let FetchResponse::GraphData(graph) = response else {
+        panic!("unexpected response");
+    };
+    let batches = |entity_type: &str| {
+        graph
+            .entities
+            .iter()
+            .filter(|entity| entity.entity_type_any() == entity_type)
+            .map(|entity| entity.to_record_batch_any().expect("batch"))
+            .collect::<Vec<_>>()
+    };
+
+    // The index has no tests, so the lcov test gets a node of its own.
+    let tests = batches(Test::ENTITY_TYPE);
+    assert_eq!(tests.iter().map(|batch| batch.num_rows()).sum::<usize>(), 1);
+
+    let mut covers: Vec<(String, f64)> = batches(Covers::ENTITY_TYPE)
+        .iter()
+        .flat_map(|batch| {
+            let targets = batch
+                .column_by_name("to_node_type")
+                .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+                .expect("to_node_type")
+                .clone();
+            let percents = batch
+                .column_by_name("coverage_pct")
+                .and_then(|column| column.as_any().downcast_ref::<Float64Array>())
+                .expect("coverage_pct")
+                .clone();
+            (0..batch.num_rows())
+                .map(move |row| (targets.value(row).to_string(), percents.value(row)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    covers.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        covers,
+        vec![
+            (File::ENTITY_TYPE.to_string(), 40.0),
+            (Function::ENTITY_TYPE.to_string(), 200.0 / 3.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn benchmark_results_attach_to_the_version_and_function() {
+    let artifacts = TempDir::new().expect("artifact dir");
+    let index = json!({
+        "documents": [{
+            "relativePath": "src/lib.rs",
+            "occurrences": [
+                {"range": [0, 7, 12], "symbol": "rust-analyzer cargo hello 0.1.0 parse().",
+                 "symbolRoles": 1, "enclosingRange": [0, 0, 2, 1]},
+                {"range": [4, 7, 11], "symbol": "rust-analyzer cargo hello 0.1.0 main().",
+                 "symbolRoles": 1, "enclosingRange": [4, 0, 6, 1]}
+            ],
+            "symbols": [
+                {"symbol": "rust-analyzer cargo hello 0.1.0 parse().", "kind": "Function"},
+                {"symbol": "rust-analyzer cargo hello 0.1.0 main().", "kind": "Function"}
+            ]
+        }]
+    });
+    fs::write(artifacts.path().join("index.scip.json"), index.to_string()).expect("write index");
+    let estimate =
+        json!({"estimate": 100.0, "lower_bound": 95.0, "upper_bound": 105.0, "unit": "ns"});
+    let messages: Vec<String> = ["parse/small_input", "startup", "serialize/large"]
+        .iter()
+        .map(|id| {
+            json!({"reason": "benchmark-complete", "id": id, "unit": "ns", "typical": estimate})
+                .to_string()
+        })
+        .collect();
+    fs::write(artifacts.path().join("criterion.json"), messages.join("\n")).expect("write results");
+
+    let service = Arc::new(MockGitHubService {
+        snapshot: sample_snapshot(),
+        search_results: sample_search_results(),
+        probe: sample_probe(),
+    });
+    let params = json!({
+        "mode": "repo_snapshot",
+        "repo": "octocat/hello-world",
+        "include_code": true,
+        "code_index": {"path": "index.scip.json"},
+        "benchmarks": [{"path": "criterion.json", "functions": {"startup": "main"}}]
+    });
+    let response = GitFetcher::new(service)
+        .with_code_index_dir(artifacts.path())
+        .with_benchmark_dir(artifacts.path())
+        .fetch(params, Arc::new(NullEmbeddingProvider))
+        .await
+        .expect("fetch should succeed");
+    let FetchResponse::GraphData(graph) = response else {
+        panic!("unexpected response");
+    };
+    let rows = |entity_type: &str| -> usize {
+        graph
+            .entities
+            .iter()
+            .filter(|entity| entity.entity_type_any() == entity_type)
+            .map(|entity| entity.to_record_batch_any().expect("batch").num_rows())
+            .sum()
+    };
+    assert_eq!(rows(BenchmarkResult::ENTITY_TYPE), 3);
+    assert_eq!(rows(HasBenchmark::ENTITY_TYPE), 3);
+    // "serialize/large" names no function of the index.
+    assert_eq!(rows(Measures::ENTITY_TYPE), 2);
+}
@@ -30,6 +30,12 @@ schema::5 {
         updated_at: Date,
     }
 
+    // Represents a name/email pair commits are authored under, as git records it
+    N::GIT_IDENTITY {
+        INDEX email: String,        // Author email, trimmed and lowercased
+        name: String,               // Author name on the latest commit seen
+    }
+
     // Represents a specific commit, the ground truth for a code state
     N::COMMIT {
         INDEX sha: String,          // The unique SHA hash of the commit
@@ -194,6 +200,19 @@ schema::5 {
         http_method: String,        // "GET", "POST", etc.
     }
 
+    // Represents one benchmark's result measured at a version (e.g. from criterion)
+    N::BENCHMARK_RESULT {
+        INDEX version_sha: String,
+        INDEX name: String,         // Benchmark id, e.g., "parse/small_input"
+        unit: String,               // Unit of the estimates, e.g., "ns"
+        mean: F64,
+        median: F64,
+        lower_bound: F64,           // Confidence interval of the typical value
+        upper_bound: F64,
+        change_pct: F64,            // Change of the mean against the previous run
+        verdict: String,            // "improved", "regressed", "no_change"
+    }
+
 
     // =====================================================================
     // Section 3: Vector Nodes (V::)
@@ -218,6 +237,8 @@ schema::5 {
         source_node_key: String,    // Unique key of the source N::FUNCTION or N::CLASS
         source_node_id: String,
         language: String,
+        start_line: I32,            // Lines of the source node, for deep links
+        end_line: I32,
     }
 
     // Represents a synthesized document chunk for an issue thread
@@ -236,6 +257,13 @@ schema::5 {
         source_updated_at: Date,
     }
 
+    // Represents a structural (node2vec) embedding of a graph node
+    V::NODE_EMBEDDING {
+        id: String,
+        source_node_id: String,
+        source_node_key: String,    // "<entity_type>::<id>" of the embedded node
+    }
+
 
     // =====================================================================
     // Section 4: Edge Definitions (E::)
@@ -246,6 +274,17 @@ schema::5 {
     E::IS_COMMIT { From: VERSION, To: COMMIT }
     E::CONTRIBUTES_TO { From: DEVELOPER, To: PROJECT }
     E::AUTHORED { From: DEVELOPER, To: COMMIT }
+    E::AUTHORED_BY { From: COMMIT, To: GIT_IDENTITY }
+    // Written by identity resolution; rejected matches are removed and not suggested again.
+    E::SAME_AS {
+        From: DEVELOPER,
+        To: GIT_IDENTITY,
+        Properties: {
+            method: String,         // "email", "noreply", "email_login" or "name"
+            confidence: F64,        // 0-1; 1 for exact email matches and confirmed links
+            status: String,         // "suggested" or "confirmed"
+        }
+    }
     E::HAS_ISSUE { From: PROJECT, To: ISSUE }
     E::HAS_PR { From: PROJECT, To: PULL_REQUEST }
     E::OPENED_ISSUE { From: DEVELOPER, To: ISSUE }
@@ -268,6 +307,7 @@ schema::5 {
     E::CONTAINS { From: FILE, To: VARIABLE }
     E::CONTAINS { From: FILE, To: TEST }
     E::CONTAINS { From: FILE, To: ENDPOINT }
+    E::HAS_BENCHMARK { From: VERSION, To: BENCHMARK_RESULT }
     E::DEPENDS_ON { From: FILE, To: LIBRARY }
 
     // --- Code-Level Relationship Edges (from stackgraph-ast) ---
@@ -279,6 +319,28 @@ schema::5 {
     E::IMPLEMENTS { From: CLASS, To: TRAIT } // Class implements an interface
     E::NESTED_IN { From: FUNCTION, To: FUNCTION }
     E::IMPORTS { From: FILE, To: FILE }
+    E::MEASURES { From: BENCHMARK_RESULT, To: FUNCTION }
+
+    // --- Test Coverage Edges (from lcov/Cobertura reports) ---
+    // Only drawn when the test executed at least one of the target's lines.
+    E::COVERS {
+        From: TEST,
+        To: FUNCTION,
+        Properties: {
+            covered_lines: I64,     // Instrumented lines the test executed
+            total_lines: I64,       // Instrumented lines of the target
+            coverage_pct: F64,      // covered_lines / total_lines, 0-100
+        }
+    }
+    E::COVERS {
+        From: TEST,
+        To: FILE,
+        Properties: {
+            covered_lines: I64,
+            total_lines: I64,
+            coverage_pct: F64,
+        }
+    }
 
     // --- Documentation and Content Edges ---
     E::CONTAINS_CONTENT { From: PROJECT, To: README_CHUNK }
@@ -289,4 +351,7 @@ schema::5 {
     E::DOCUMENTS { From: PULL_REQUEST, To: PR_DOC }
     E::EMBEDS { From: FUNCTION, To: CODE_CHUNK }
     E::EMBEDS { From: CLASS, To: CODE_CHUNK }
+    E::HAS_STRUCTURE { From: PROJECT, To: NODE_EMBEDDING }
+    E::HAS_STRUCTURE { From: CLASS, To: NODE_EMBEDDING }
+    E::HAS_STRUCTURE { From: FUNCTION, To: NODE_EMBEDDING }
 }